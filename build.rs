@@ -0,0 +1,45 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Embeds the commit the crate is built from as FLOW2D_GIT_HASH, see
+// metadata::GIT_HASH. Builds outside a git checkout of this crate, like a published
+// crate, have none unless the variable is set in the environment.
+fn main() {
+    println!("cargo:rerun-if-env-changed=FLOW2D_GIT_HASH");
+    if std::env::var_os("FLOW2D_GIT_HASH").is_some() {
+        return;
+    }
+
+    let git = |arguments: &[&str]| {
+        let output = Command::new("git").args(arguments).output().ok()?;
+        let text = String::from_utf8(output.stdout).ok()?;
+        output.status.success().then(|| text.trim().to_string())
+    };
+    let (Some(toplevel), Some(git_dir), Some(hash)) = (
+        git(&["rev-parse", "--show-toplevel"]),
+        git(&["rev-parse", "--git-dir"]),
+        git(&["rev-parse", "HEAD"]),
+    ) else {
+        return;
+    };
+
+    // A crate unpacked inside some other repository, e.g. vendored or in the cargo
+    // registry under a git home directory, would report that repository's commit
+    let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from);
+    let is_own_checkout = manifest_dir.is_some_and(|manifest_dir| {
+        Path::new(&toplevel).canonicalize().ok() == manifest_dir.canonicalize().ok()
+    });
+    if !is_own_checkout {
+        return;
+    }
+
+    // A missing path would rerun the script on every build
+    let git_dir = Path::new(&git_dir);
+    for path in ["HEAD", "refs", "packed-refs"] {
+        let path = git_dir.join(path);
+        if path.exists() {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+    }
+    println!("cargo:rustc-env=FLOW2D_GIT_HASH={hash}");
+}
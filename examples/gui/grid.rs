@@ -1,35 +1,143 @@
+use flow2d_rs::cell::BoundaryConditionCell;
 use flow2d_rs::cell::Cell;
 use flow2d_rs::cell::CellType;
+use flow2d_rs::colormap::{Colormap, RangeMode};
+use flow2d_rs::field::Field;
+use flow2d_rs::history::History;
+use flow2d_rs::lattice_boltzmann::LatticeBoltzmann;
 use flow2d_rs::presets;
+use flow2d_rs::quiver::quiver;
+use flow2d_rs::shallow_water::ShallowWater;
 use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+use flow2d_rs::streamfunction_vorticity::StreamfunctionVorticity;
 
-use iced::widget::canvas::{Cache, Canvas, Frame, Geometry, Path, Program, Stroke};
+use iced::widget::canvas::event::{self, Event};
+use iced::widget::canvas::{Cache, Canvas, Frame, Geometry, Path, Program, Stroke, Text};
 use iced::{mouse, Color, Element, Length, Point, Renderer, Size, Theme};
 
 use plotters::prelude::*;
 
+const VORTICITY_CONFINEMENT: f32 = 0.3;
+
+// Mouse stirring, radius in cells and velocity per meter dragged
+const STIR_RADIUS: f32 = 2.0;
+const STIR_STRENGTH: f32 = 20.0;
+
+// Velocity arrows, every VELOCITY_STRIDE cells with length velocity * VELOCITY_SCALE
+const VELOCITY_STRIDE: usize = 1;
+const VELOCITY_SCALE: f32 = 0.1;
+
+// Rewind history, a keyframe every HISTORY_KEYFRAME_INTERVAL steps
+const HISTORY_KEYFRAMES: usize = 100;
+const HISTORY_KEYFRAME_INTERVAL: usize = 10;
+const REWIND_STEPS: usize = 50;
+
 #[derive(Default)]
 pub struct Grid {
-    simulation: Simulation,
+    backend: Backend,
+    preset: Preset,
+    solver_type: SolverType,
     next_cache: Cache,
     vector_cache: Cache,
     color_type: ColorType,
+    palette: Palette,
+    scale_mode: ScaleMode,
     zoom: f32,
     show_velocity: bool,
+    vorticity_confinement: bool,
+    // Started by the first tick after a reset
+    history: Option<History>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Message {
+    Stir {
+        position: [f32; 2],
+        velocity: [f32; 2],
+    },
+    PaintObstacle {
+        position: [f32; 2],
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Interaction {
+    #[default]
+    None,
+    // Left mouse button, holds the last cursor position
+    Stirring(Point),
+    // Right mouse button
+    Painting,
+}
+
+pub enum Backend {
+    // Boxed, the Navier-Stokes solver carries far more state than the others
+    NavierStokes(Box<Simulation>),
+    LatticeBoltzmann(LatticeBoltzmann),
+    StreamfunctionVorticity(StreamfunctionVorticity),
+    ShallowWater(ShallowWater),
+}
+
+impl Backend {
+    // Drawing and export only rely on the solver interface
+    fn solver(&self) -> &dyn FluidSolver {
+        match self {
+            Backend::NavierStokes(simulation) => simulation.as_ref(),
+            Backend::LatticeBoltzmann(lattice_boltzmann) => lattice_boltzmann,
+            Backend::StreamfunctionVorticity(streamfunction_vorticity) => streamfunction_vorticity,
+            Backend::ShallowWater(shallow_water) => shallow_water,
+        }
+    }
+
+    fn solver_mut(&mut self) -> &mut dyn FluidSolver {
+        match self {
+            Backend::NavierStokes(simulation) => simulation.as_mut(),
+            Backend::LatticeBoltzmann(lattice_boltzmann) => lattice_boltzmann,
+            Backend::StreamfunctionVorticity(streamfunction_vorticity) => streamfunction_vorticity,
+            Backend::ShallowWater(shallow_water) => shallow_water,
+        }
+    }
 }
 
+impl Default for Backend {
+    fn default() -> Self {
+        Backend::NavierStokes(Box::default())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SolverType {
+    #[default]
+    NavierStokes,
+    LatticeBoltzmann,
+    StreamfunctionVorticity,
+    ShallowWater,
+}
+
+pub static ALLSOLVERTYPE: &[SolverType] = &[
+    SolverType::NavierStokes,
+    SolverType::LatticeBoltzmann,
+    SolverType::StreamfunctionVorticity,
+    SolverType::ShallowWater,
+];
+
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum Preset {
     #[default]
     CylinderCrossFlow,
     BackwardFacingStep,
     LidDrivenCavity,
+    KelvinHelmholtz,
+    BuoyantPlume,
 }
 
 pub static ALLPRESET: &[Preset] = &[
     Preset::CylinderCrossFlow,
     Preset::BackwardFacingStep,
     Preset::LidDrivenCavity,
+    Preset::KelvinHelmholtz,
+    Preset::BuoyantPlume,
 ];
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
@@ -38,26 +146,129 @@ pub enum ColorType {
     Pressure,
     Speed,
     Streamline,
+    Dye,
 }
 
-pub static ALLCOLORTYPE: &[ColorType] =
-    &[ColorType::Pressure, ColorType::Speed, ColorType::Streamline];
+pub static ALLCOLORTYPE: &[ColorType] = &[
+    ColorType::Pressure,
+    ColorType::Speed,
+    ColorType::Streamline,
+    ColorType::Dye,
+];
+
+// Colormap for pressure and speed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Palette {
+    #[default]
+    Rainbow,
+    Viridis,
+    Plasma,
+    Coolwarm,
+}
+
+pub static ALLPALETTE: &[Palette] = &[
+    Palette::Rainbow,
+    Palette::Viridis,
+    Palette::Plasma,
+    Palette::Coolwarm,
+];
+
+impl Palette {
+    fn colormap(&self) -> Colormap {
+        match self {
+            Palette::Rainbow => Colormap::rainbow(),
+            Palette::Viridis => Colormap::viridis(),
+            Palette::Plasma => Colormap::plasma(),
+            Palette::Coolwarm => Colormap::coolwarm(),
+        }
+    }
+}
+
+// How the color scale follows pressure and speed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScaleMode {
+    #[default]
+    Autoscale,
+    RunningMinMax,
+    Smoothed,
+    // Keep the range at the moment it was picked
+    Frozen,
+}
+
+pub static ALLSCALEMODE: &[ScaleMode] = &[
+    ScaleMode::Autoscale,
+    ScaleMode::RunningMinMax,
+    ScaleMode::Smoothed,
+    ScaleMode::Frozen,
+];
+
+const SCALE_SMOOTHING: f32 = 0.05;
 
 impl Grid {
-    pub fn set_preset(&mut self, preset: Preset) {
-        self.next_cache.clear();
-        self.vector_cache.clear();
-        match preset {
-            Preset::CylinderCrossFlow => {
-                self.simulation = Simulation::from_preset(presets::cylinder_cross_flow());
+    // The current simulation stays when the new one doesn't fit in memory
+    pub fn set_preset(&mut self, preset: Preset) -> Result<(), String> {
+        self.reset(preset, self.solver_type)
+    }
+
+    pub fn set_solver_type(&mut self, solver_type: SolverType) -> Result<(), String> {
+        self.reset(self.preset, solver_type)
+    }
+
+    fn reset(&mut self, preset: Preset, solver_type: SolverType) -> Result<(), String> {
+        let name = match preset {
+            Preset::CylinderCrossFlow => "cylinder_cross_flow",
+            Preset::BackwardFacingStep => "backward_facing_step",
+            Preset::LidDrivenCavity => "lid_driven_cavity",
+            Preset::KelvinHelmholtz => "kelvin_helmholtz",
+            Preset::BuoyantPlume => "buoyant_plume",
+        };
+        let simulation_preset = presets::try_by_name(name)
+            .expect("every preset has a name")
+            .map_err(|error| error.to_string())?;
+
+        // Lattice Boltzmann needs square cells and the stream function no periodic
+        // edges, fall back to Navier-Stokes otherwise
+        let delta_space = simulation_preset.space_domain.delta_space();
+        let is_periodic = simulation_preset
+            .space_domain
+            .fields()
+            .cell_type
+            .iter()
+            .any(|cell_type| {
+                matches!(
+                    cell_type,
+                    CellType::BoundaryConditionCell(BoundaryConditionCell::PeriodicCell)
+                )
+            });
+        let backend = match solver_type {
+            SolverType::LatticeBoltzmann if delta_space[0] == delta_space[1] => {
+                LatticeBoltzmann::try_from_preset(simulation_preset)
+                    .map(Backend::LatticeBoltzmann)
+                    .map_err(|error| error.to_string())?
             }
-            Preset::BackwardFacingStep => {
-                self.simulation = Simulation::from_preset(presets::backward_facing_step());
+            SolverType::StreamfunctionVorticity if !is_periodic => {
+                StreamfunctionVorticity::try_from_preset(simulation_preset)
+                    .map(Backend::StreamfunctionVorticity)
+                    .map_err(|error| error.to_string())?
             }
-            Preset::LidDrivenCavity => {
-                self.simulation = Simulation::from_preset(presets::lid_driven_cavity());
+            // Flat bed a meter deep, pressure shows the free surface
+            SolverType::ShallowWater => {
+                ShallowWater::try_from_preset(simulation_preset, |_, _| 0.0, |_, _| 1.0)
+                    .map(Backend::ShallowWater)
+                    .map_err(|error| error.to_string())?
             }
-        }
+            _ => Backend::NavierStokes(Box::new(Simulation::from_preset(simulation_preset))),
+        };
+
+        self.preset = preset;
+        self.solver_type = solver_type;
+        self.backend = backend;
+        self.next_cache.clear();
+        self.vector_cache.clear();
+        self.history = None;
+        self.set_vorticity_confinement(self.vorticity_confinement);
+        self.set_scale_mode(self.scale_mode);
+        Ok(())
     }
 
     pub fn set_zoom(&mut self, zoom: f32) {
@@ -72,17 +283,76 @@ impl Grid {
         self.color_type = color_type
     }
 
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.next_cache.clear();
+        self.vector_cache.clear();
+        self.palette = palette
+    }
+
+    pub fn set_scale_mode(&mut self, scale_mode: ScaleMode) {
+        self.next_cache.clear();
+        self.scale_mode = scale_mode;
+
+        let (pressure, speed) = match scale_mode {
+            ScaleMode::Autoscale => (RangeMode::Autoscale, RangeMode::Autoscale),
+            ScaleMode::RunningMinMax => (RangeMode::RunningMinMax, RangeMode::RunningMinMax),
+            ScaleMode::Smoothed => (
+                RangeMode::Smoothed(SCALE_SMOOTHING),
+                RangeMode::Smoothed(SCALE_SMOOTHING),
+            ),
+            ScaleMode::Frozen => (
+                RangeMode::Fixed(self.solver().pressure_range()),
+                RangeMode::Fixed(self.solver().speed_range()),
+            ),
+        };
+        self.solver_mut().set_range_modes(pressure, speed);
+    }
+
     pub fn set_show_velocity(&mut self, show_velocity: bool) {
         self.next_cache.clear();
         self.vector_cache.clear();
         self.show_velocity = show_velocity;
     }
 
+    pub fn set_vorticity_confinement(&mut self, vorticity_confinement: bool) {
+        self.vorticity_confinement = vorticity_confinement;
+        if let Backend::NavierStokes(simulation) = &mut self.backend {
+            simulation
+                .set_vorticity_confinement(vorticity_confinement.then_some(VORTICITY_CONFINEMENT));
+        }
+    }
+
     pub fn get_time(&self) -> f32 {
-        self.simulation.time()
+        self.solver().time()
     }
 
-    pub fn view(&self) -> Element<()> {
+    // Mouse interaction is only supported by the Navier-Stokes solver
+    pub fn update(&mut self, message: Message) {
+        let Backend::NavierStokes(simulation) = &mut self.backend else {
+            return;
+        };
+
+        match message {
+            Message::Stir { position, velocity } => {
+                let delta_space = simulation.delta_space();
+                let radius = STIR_RADIUS * delta_space[0].max(delta_space[1]);
+                simulation.apply_velocity_brush(position, radius, velocity, Some(1.0));
+                self.next_cache.clear();
+                self.vector_cache.clear();
+            }
+            Message::PaintObstacle { position } => {
+                let delta_space = simulation.delta_space();
+                let x = (position[0] / delta_space[0]).max(0.0) as usize;
+                let y = (position[1] / delta_space[1]).max(0.0) as usize;
+                if simulation.set_obstacle(x, y, true) {
+                    self.next_cache.clear();
+                    self.vector_cache.clear();
+                }
+            }
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
         Canvas::new(self)
             .width(Length::Fill)
             .height(Length::Fill)
@@ -90,19 +360,43 @@ impl Grid {
     }
 
     pub fn tick(&mut self) {
-        self.simulation.iterate_one_timestep();
+        let history = self
+            .history
+            .get_or_insert_with(|| History::new(HISTORY_KEYFRAMES, HISTORY_KEYFRAME_INTERVAL));
+        if history.current_step().is_none() {
+            history.record(self.backend.solver());
+        }
+        self.backend.solver_mut().iterate_one_timestep();
+        history.record(self.backend.solver());
+
         self.next_cache.clear();
         self.vector_cache.clear();
     }
 
+    // Step back REWIND_STEPS steps, or to the oldest state still in the history
+    pub fn rewind(&mut self) {
+        let Some(history) = self.history.as_mut() else {
+            return;
+        };
+        let (Some(step), Some(steps)) = (history.current_step(), history.steps()) else {
+            return;
+        };
+        let target = step.saturating_sub(REWIND_STEPS).max(*steps.start());
+        if history.rewind(self.backend.solver_mut(), target) {
+            self.next_cache.clear();
+            self.vector_cache.clear();
+        }
+    }
+
     pub fn export_image(&self) {
         let scale = 20.0;
-        let space_size = self.simulation.space_size();
-        let delta_space = self.simulation.delta_space();
+        let space_size = self.solver().space_size();
+        let delta_space = self.solver().delta_space();
 
-        // let pressure_range = self.simulation.get_pressure_range();
-        let speed_range = self.simulation.speed_range();
-        // let psi_range = self.simulation.get_psi_range();
+        // let pressure_range = self.solver().get_pressure_range();
+        let speed_range = self.solver().speed_range();
+        // let psi_range = self.solver().get_psi_range();
+        let colormap = self.palette.colormap();
 
         let pixel_scale = [scale * delta_space[0], scale * delta_space[1]];
         let drawing_area = BitMapBackend::new(
@@ -115,12 +409,12 @@ impl Grid {
         .into_drawing_area();
         drawing_area.fill(&WHITE).unwrap();
 
-        for x in 0..self.simulation.space_size()[0] {
-            for y in 0..self.simulation.space_size()[1] {
+        for x in 0..self.solver().space_size()[0] {
+            for y in 0..self.solver().space_size()[1] {
                 let pos_x = x as i32;
-                let reversed_y = self.simulation.space_size()[1] - 1 - y;
+                let reversed_y = self.solver().space_size()[1] - 1 - y;
                 let pos_y = reversed_y as i32;
-                let color = color_speed(self.simulation.get_cell(x, y), speed_range);
+                let color = color_speed(&self.solver().get_cell(x, y), speed_range, &colormap);
                 drawing_area
                     .draw(&Rectangle::new(
                         [
@@ -147,72 +441,150 @@ impl Grid {
     }
 }
 
-impl Program<()> for Grid {
-    type State = ();
+impl Program<Message> for Grid {
+    type State = Interaction;
+
+    fn update(
+        &self,
+        state: &mut Interaction,
+        event: Event,
+        bounds: iced::Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<Message>) {
+        let Some(cursor_position) = cursor.position_in(bounds) else {
+            *state = Interaction::None;
+            return (event::Status::Ignored, None);
+        };
+
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                *state = Interaction::Stirring(cursor_position);
+                (event::Status::Captured, None)
+            }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Right)) => {
+                *state = Interaction::Painting;
+                let message = Message::PaintObstacle {
+                    position: self.to_physical(cursor_position),
+                };
+                (event::Status::Captured, Some(message))
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(_)) => {
+                *state = Interaction::None;
+                (event::Status::Captured, None)
+            }
+            Event::Mouse(mouse::Event::CursorMoved { .. }) => match *state {
+                Interaction::Stirring(last_position) => {
+                    *state = Interaction::Stirring(cursor_position);
+                    let from = self.to_physical(last_position);
+                    let to = self.to_physical(cursor_position);
+                    let message = Message::Stir {
+                        position: to,
+                        velocity: [
+                            (to[0] - from[0]) * STIR_STRENGTH,
+                            (to[1] - from[1]) * STIR_STRENGTH,
+                        ],
+                    };
+                    (event::Status::Captured, Some(message))
+                }
+                Interaction::Painting => {
+                    let message = Message::PaintObstacle {
+                        position: self.to_physical(cursor_position),
+                    };
+                    (event::Status::Captured, Some(message))
+                }
+                Interaction::None => (event::Status::Ignored, None),
+            },
+            _ => (event::Status::Ignored, None),
+        }
+    }
 
     fn draw(
         &self,
-        _state: &(),
+        _state: &Interaction,
         renderer: &Renderer,
         _theme: &Theme,
         bounds: iced::Rectangle,
-        _cursor: mouse::Cursor,
+        cursor: mouse::Cursor,
     ) -> Vec<Geometry> {
         let cells = self.next_cache.draw(renderer, bounds.size(), |frame| {
-            if self.zoom == 0.0 {
-                frame.scale(100.0);
-            } else {
-                frame.scale(self.zoom);
-            }
+            frame.scale(self.scale());
 
             self.draw_cells(frame);
         });
 
         let vectors = self.vector_cache.draw(renderer, bounds.size(), |frame| {
-            if self.zoom == 0.0 {
-                frame.scale(100.0);
-            } else {
-                frame.scale(self.zoom);
-            }
+            frame.scale(self.scale());
             self.draw_velocity_vector(frame);
         });
 
-        if self.show_velocity {
+        let mut geometries = if self.show_velocity {
             vec![cells, vectors]
         } else {
             vec![cells]
+        };
+        if let Some(cursor_position) = cursor.position_in(bounds) {
+            geometries.extend(self.draw_inspector(renderer, bounds, cursor_position));
         }
+        geometries
     }
 }
 
 impl Grid {
+    fn solver(&self) -> &dyn FluidSolver {
+        self.backend.solver()
+    }
+
+    fn solver_mut(&mut self) -> &mut dyn FluidSolver {
+        self.backend.solver_mut()
+    }
+
+    fn scale(&self) -> f32 {
+        if self.zoom == 0.0 {
+            100.0
+        } else {
+            self.zoom
+        }
+    }
+
+    // Canvas coordinates to simulation coordinates, y axis pointing up
+    fn to_physical(&self, point: Point) -> [f32; 2] {
+        let height = self.solver().space_size()[1] as f32 * self.solver().delta_space()[1];
+        [point.x / self.scale(), height - point.y / self.scale()]
+    }
+
     fn draw_cells(&self, frame: &mut Frame) {
         let background = Path::rectangle(Point::ORIGIN, frame.size());
         frame.fill(&background, Color::from_rgb8(0x40, 0x44, 0x4B));
 
         frame.with_save(|frame| {
-            let delta_x = self.simulation.delta_space()[0];
-            let delta_y = self.simulation.delta_space()[1];
-            let pressure_range = self.simulation.pressure_range();
-            let speed_range = self.simulation.speed_range();
-            let psi_range = self.simulation.psi_range();
-
-            for x in 0..self.simulation.space_size()[0] {
-                for y in 0..self.simulation.space_size()[1] {
+            let delta_x = self.solver().delta_space()[0];
+            let delta_y = self.solver().delta_space()[1];
+            let pressure_range = self.solver().pressure_range();
+            let speed_range = self.solver().speed_range();
+            let psi_range = self
+                .solver()
+                .field(Field::Psi)
+                .range()
+                .unwrap_or([0.0, 0.0]);
+            let colormap = self.palette.colormap();
+
+            for x in 0..self.solver().space_size()[0] {
+                for y in 0..self.solver().space_size()[1] {
                     let pos_x = delta_x * (x as f32);
-                    let reversed_y = self.simulation.space_size()[1] - 1 - y;
+                    let reversed_y = self.solver().space_size()[1] - 1 - y;
                     let pos_y = delta_y * (reversed_y as f32);
 
                     let color: Color = match self.color_type {
                         ColorType::Pressure => {
-                            color_presure(self.simulation.get_cell(x, y), pressure_range)
+                            color_presure(&self.solver().get_cell(x, y), pressure_range, &colormap)
                         }
                         ColorType::Speed => {
-                            color_speed(self.simulation.get_cell(x, y), speed_range)
+                            color_speed(&self.solver().get_cell(x, y), speed_range, &colormap)
                         }
                         ColorType::Streamline => {
-                            color_psi(self.simulation.get_cell(x, y), psi_range)
+                            color_psi(&self.solver().get_cell(x, y), psi_range)
                         }
+                        ColorType::Dye => color_dye(&self.solver().get_cell(x, y)),
                     };
 
                     frame.fill_rectangle(
@@ -225,54 +597,63 @@ impl Grid {
         });
     }
 
-    fn draw_velocity_vector(&self, frame: &mut Frame) {
-        let delta_x = self.simulation.delta_space()[0];
-        let delta_y = self.simulation.delta_space()[1];
-        for x in 0..self.simulation.space_size()[0] {
-            for y in 0..self.simulation.space_size()[1] {
-                // if x % 2 != 0 || y % 2 != 0 {
-                //     continue;
-                // }
-                if let CellType::FluidCell = self.simulation.get_cell(x, y).cell_type {
-                    let pos_x = delta_x * (x as f32);
-                    let reversed_y = self.simulation.space_size()[1] - 1 - y;
-                    let pos_y = delta_y * (reversed_y as f32);
-
-                    let velocity_scale = 0.1;
-                    let velocity = self.simulation.get_centered_velocity(x, y);
-
-                    let vector_start = Point::new(pos_x + delta_x / 2.0, pos_y + delta_y / 2.0);
-                    let vector_end = Point::new(
-                        vector_start.x + velocity[0] * velocity_scale,
-                        vector_start.y - velocity[1] * velocity_scale,
-                    );
+    // Field values under the cursor, only the Navier-Stokes solver can be sampled
+    fn draw_inspector(
+        &self,
+        renderer: &Renderer,
+        bounds: iced::Rectangle,
+        cursor_position: Point,
+    ) -> Option<Geometry> {
+        let Backend::NavierStokes(simulation) = &self.backend else {
+            return None;
+        };
+        let [px, py] = self.to_physical(cursor_position);
+        let sample = simulation.sample_at_point(px, py)?;
+
+        let mut frame = Frame::new(renderer, bounds.size());
+        frame.fill_text(Text {
+            content: format!(
+                "u {:.3}\nv {:.3}\np {:.3}\nvorticity {:.3}\npsi {:.3}",
+                sample.velocity[0],
+                sample.velocity[1],
+                sample.pressure,
+                sample.vorticity,
+                sample.psi
+            ),
+            position: Point::new(cursor_position.x + 12.0, cursor_position.y + 12.0),
+            color: Color::WHITE,
+            size: 14.0,
+            ..Default::default()
+        });
+        Some(frame.into_geometry())
+    }
 
-                    let vector = Path::line(vector_start, vector_end);
-                    frame.stroke(
-                        &vector,
-                        Stroke {
-                            width: 1.0,
-                            ..Default::default()
-                        },
-                    );
-                }
-            }
+    fn draw_velocity_vector(&self, frame: &mut Frame) {
+        let height = self.solver().space_size()[1] as f32 * self.solver().delta_space()[1];
+
+        for arrow in quiver(self.solver(), VELOCITY_STRIDE, VELOCITY_SCALE) {
+            let vector_start = Point::new(arrow.position[0], height - arrow.position[1]);
+            let vector_end = Point::new(
+                vector_start.x + arrow.vector[0],
+                vector_start.y - arrow.vector[1],
+            );
+
+            let vector = Path::line(vector_start, vector_end);
+            frame.stroke(
+                &vector,
+                Stroke {
+                    width: 1.0,
+                    ..Default::default()
+                },
+            );
         }
     }
 }
 
-pub fn color_presure(cell: &Cell, pressure_range: [f32; 2]) -> Color {
+pub fn color_presure(cell: &Cell, pressure_range: [f32; 2], colormap: &Colormap) -> Color {
     match cell.cell_type {
         CellType::FluidCell => {
-            // 240 offset to map from blue to red instead of the whole range of hue
-            let hue: f32 = 240.0
-                - (cell.pressure - pressure_range[0]) * 240.0
-                    / (pressure_range[1] - pressure_range[0]);
-            let saturation = 1.0;
-            let lightness = 0.5;
-
-            let (r, g, b) = hsl_to_rgb(hue, saturation, lightness);
-
+            let [r, g, b] = colormap.map(cell.pressure, pressure_range);
             Color::from_rgb(r, g, b)
         }
         CellType::BoundaryConditionCell(_) => Color::from_rgb(0.5, 0.5, 0.5),
@@ -280,19 +661,11 @@ pub fn color_presure(cell: &Cell, pressure_range: [f32; 2]) -> Color {
     }
 }
 
-pub fn color_speed(cell: &Cell, speed_range: [f32; 2]) -> Color {
+pub fn color_speed(cell: &Cell, speed_range: [f32; 2], colormap: &Colormap) -> Color {
     match cell.cell_type {
         CellType::FluidCell => {
             let speed = (cell.velocity[0].powi(2) + cell.velocity[1].powi(2)).sqrt();
-
-            // 240 offset to map from blue to red instead of the whole range of hue
-            let hue: f32 =
-                240.0 - (speed - speed_range[0]) * 240.0 / (speed_range[1] - speed_range[0]);
-            let saturation = 1.0;
-            let lightness = 0.5;
-
-            let (r, g, b) = hsl_to_rgb(hue, saturation, lightness);
-
+            let [r, g, b] = colormap.map(speed, speed_range);
             Color::from_rgb(r, g, b)
         }
         CellType::BoundaryConditionCell(_) => Color::from_rgb(0.5, 0.5, 0.5),
@@ -318,6 +691,17 @@ pub fn color_psi(cell: &Cell, psi_range: [f32; 2]) -> Color {
     }
 }
 
+pub fn color_dye(cell: &Cell) -> Color {
+    match cell.cell_type {
+        CellType::FluidCell => {
+            let dye = cell.dye.clamp(0.0, 1.0);
+            Color::from_rgb(dye, dye, dye)
+        }
+        CellType::BoundaryConditionCell(_) => Color::from_rgb(0.5, 0.5, 0.5),
+        CellType::VoidCell => Color::BLACK,
+    }
+}
+
 fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> (f32, f32, f32) {
     let c = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
     let x = c * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
@@ -1,8 +1,11 @@
 mod grid;
 
-use grid::{ColorType, Grid, Preset, ALLCOLORTYPE, ALLPRESET};
+use grid::{
+    ColorType, Grid, Palette, Preset, ScaleMode, SolverType, ALLCOLORTYPE, ALLPALETTE, ALLPRESET,
+    ALLSCALEMODE, ALLSOLVERTYPE,
+};
 
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use iced::executor;
 use iced::theme::{self, Theme};
@@ -28,24 +31,35 @@ struct Flow2dGUI {
     grid: Grid,
     is_playing: bool,
     is_velocity_enabled: bool,
+    is_vorticity_confinement_enabled: bool,
     speed: usize,
     preset: Preset,
+    solver_type: SolverType,
     color_type: ColorType,
+    palette: Palette,
+    scale_mode: ScaleMode,
     zoom: f32,
+    // Why the last preset or solver couldn't be set up
+    error: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 enum Message {
-    Tick(Instant),
+    Tick,
     TogglePlayback,
     Next,
+    Rewind,
     SpeedChanged(f32),
     Export,
     PresetPicked(Preset),
+    SolverTypePicked(SolverType),
     ToggleVelocity(bool),
+    ToggleVorticityConfinement(bool),
     ColorTypePicked(ColorType),
+    PalettePicked(Palette),
+    ScaleModePicked(ScaleMode),
     ZoomChanged(f32),
-    None,
+    Grid(grid::Message),
 }
 
 impl std::fmt::Display for Preset {
@@ -57,6 +71,23 @@ impl std::fmt::Display for Preset {
                 Preset::CylinderCrossFlow => "Cylinder Cross Flow",
                 Preset::BackwardFacingStep => "Backward Facing Step",
                 Preset::LidDrivenCavity => "Lid Driven Cavity",
+                Preset::KelvinHelmholtz => "Kelvin-Helmholtz",
+                Preset::BuoyantPlume => "Buoyant Plume",
+            }
+        )
+    }
+}
+
+impl std::fmt::Display for SolverType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                SolverType::NavierStokes => "Navier-Stokes",
+                SolverType::LatticeBoltzmann => "Lattice Boltzmann",
+                SolverType::StreamfunctionVorticity => "Streamfunction-Vorticity",
+                SolverType::ShallowWater => "Shallow Water",
             }
         )
     }
@@ -71,6 +102,37 @@ impl std::fmt::Display for ColorType {
                 ColorType::Pressure => "Pressure",
                 ColorType::Speed => "Speed",
                 ColorType::Streamline => "Streamline",
+                ColorType::Dye => "Dye",
+            }
+        )
+    }
+}
+
+impl std::fmt::Display for Palette {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Palette::Rainbow => "Rainbow",
+                Palette::Viridis => "Viridis",
+                Palette::Plasma => "Plasma",
+                Palette::Coolwarm => "Coolwarm",
+            }
+        )
+    }
+}
+
+impl std::fmt::Display for ScaleMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ScaleMode::Autoscale => "Autoscale",
+                ScaleMode::RunningMinMax => "Running Min/Max",
+                ScaleMode::Smoothed => "Smoothed",
+                ScaleMode::Frozen => "Frozen",
             }
         )
     }
@@ -99,9 +161,12 @@ impl Application for Flow2dGUI {
 
     fn update(&mut self, message: Message) -> Command<Message> {
         match message {
-            Message::Tick(_) | Message::Next => {
+            Message::Tick | Message::Next => {
                 self.grid.tick();
             }
+            Message::Rewind => {
+                self.grid.rewind();
+            }
             Message::TogglePlayback => {
                 self.is_playing = !self.is_playing;
             }
@@ -112,8 +177,16 @@ impl Application for Flow2dGUI {
                 self.grid.export_image();
             }
             Message::PresetPicked(preset) => {
-                self.preset = preset;
-                self.grid.set_preset(preset);
+                self.error = self.grid.set_preset(preset).err();
+                if self.error.is_none() {
+                    self.preset = preset;
+                }
+            }
+            Message::SolverTypePicked(solver_type) => {
+                self.error = self.grid.set_solver_type(solver_type).err();
+                if self.error.is_none() {
+                    self.solver_type = solver_type;
+                }
             }
             Message::ZoomChanged(zoom) => {
                 self.grid.set_zoom(zoom);
@@ -123,36 +196,55 @@ impl Application for Flow2dGUI {
                 self.color_type = color_type;
                 self.grid.set_color_type(color_type);
             }
+            Message::PalettePicked(palette) => {
+                self.palette = palette;
+                self.grid.set_palette(palette);
+            }
+            Message::ScaleModePicked(scale_mode) => {
+                self.scale_mode = scale_mode;
+                self.grid.set_scale_mode(scale_mode);
+            }
             Message::ToggleVelocity(is_velocity_enabled) => {
                 self.is_velocity_enabled = is_velocity_enabled;
                 self.grid.set_show_velocity(is_velocity_enabled);
             }
-            Message::None => {}
+            Message::ToggleVorticityConfinement(is_enabled) => {
+                self.is_vorticity_confinement_enabled = is_enabled;
+                self.grid.set_vorticity_confinement(is_enabled);
+            }
+            Message::Grid(message) => {
+                self.grid.update(message);
+            }
         }
         Command::none()
     }
 
     fn subscription(&self) -> Subscription<Message> {
         if self.is_playing {
-            time::every(Duration::from_millis(1000 / self.speed as u64)).map(Message::Tick)
+            time::every(Duration::from_millis(1000 / self.speed as u64)).map(|_| Message::Tick)
         } else {
             Subscription::none()
         }
     }
 
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         let controls = view_controls(
             self.is_playing,
             self.is_velocity_enabled,
+            self.is_vorticity_confinement_enabled,
             self.speed,
             self.preset,
+            self.solver_type,
             self.color_type,
+            self.palette,
+            self.scale_mode,
             self.zoom,
         );
 
         let content = column![
-            self.grid.view().map(move |_| { Message::None }),
+            self.grid.view().map(Message::Grid),
             text(format!("time: {:.4}s", self.grid.get_time())).size(16),
+            text(self.error.as_deref().unwrap_or_default()).size(16),
             controls,
         ];
 
@@ -167,16 +259,24 @@ impl Application for Flow2dGUI {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn view_controls<'a>(
     is_playing: bool,
     is_velocity_enabled: bool,
+    is_vorticity_confinement_enabled: bool,
     speed: usize,
     preset: Preset,
+    solver_type: SolverType,
     color_type: ColorType,
+    palette: Palette,
+    scale_mode: ScaleMode,
     zoom: f32,
 ) -> Element<'a, Message> {
     let playback_controls = row![
         button(if is_playing { "Pause" } else { "Play" }).on_press(Message::TogglePlayback),
+        button("Rewind")
+            .on_press(Message::Rewind)
+            .style(theme::Button::Secondary),
         button("Next")
             .on_press(Message::Next)
             .style(theme::Button::Secondary),
@@ -187,12 +287,29 @@ fn view_controls<'a>(
             .size(16)
             .spacing(5)
             .text_size(16),
+        checkbox(
+            "Vorticity confinement",
+            is_vorticity_confinement_enabled,
+            Message::ToggleVorticityConfinement
+        )
+        .size(16)
+        .spacing(5)
+        .text_size(16),
         pick_list(ALLPRESET, Some(preset), Message::PresetPicked)
             .padding(8)
             .text_size(16),
+        pick_list(ALLSOLVERTYPE, Some(solver_type), Message::SolverTypePicked)
+            .padding(8)
+            .text_size(16),
         pick_list(ALLCOLORTYPE, Some(color_type), Message::ColorTypePicked)
             .padding(8)
             .text_size(16),
+        pick_list(ALLPALETTE, Some(palette), Message::PalettePicked)
+            .padding(8)
+            .text_size(16),
+        pick_list(ALLSCALEMODE, Some(scale_mode), Message::ScaleModePicked)
+            .padding(8)
+            .text_size(16),
     ]
     .spacing(10);
 
@@ -205,7 +322,7 @@ fn view_controls<'a>(
     .spacing(10);
 
     let zoom_controls = row![
-        slider(1.0..=1000.0, zoom as f32, Message::ZoomChanged),
+        slider(1.0..=1000.0, zoom, Message::ZoomChanged),
         text(format!("Zoom x{zoom}")).size(16),
     ]
     .width(Length::Fill)
@@ -0,0 +1,38 @@
+// Newton iteration on the inflow velocity of the cylinder preset, with the drag
+// sensitivity from finite differences, until the drag after a short run hits a target.
+// Progress on stdout.
+// cargo run --release --example optimize_inflow
+use flow2d_rs::presets;
+use flow2d_rs::sensitivity::{self, Control, Sensitivity};
+use flow2d_rs::simulation::Simulation;
+
+const TARGET_DRAG: f32 = 2.0;
+const HORIZON: f32 = 1.0; // seconds
+const ITERATIONS: usize = 10;
+
+fn main() {
+    let simulation = Simulation::from_preset(presets::cylinder_cross_flow_sized([55, 21]));
+    let sensitivity = Sensitivity::new(
+        &simulation,
+        &[Control::InflowVelocity(0)],
+        HORIZON,
+        sensitivity::drag,
+    )
+    .parallel(true);
+
+    // The drag grows monotonically with the inflow velocity
+    let mut values = sensitivity.values();
+    println!("iteration,inflow_velocity,drag,gradient");
+    for iteration in 0..ITERATIONS {
+        let gradient = sensitivity.gradient(&values);
+        println!(
+            "{},{},{},{}",
+            iteration, values[0], gradient.objective, gradient.gradient[0]
+        );
+        let error = gradient.objective - TARGET_DRAG;
+        if error.abs() < 1e-3 * TARGET_DRAG || gradient.gradient[0] == 0.0 {
+            break;
+        }
+        values[0] = (values[0] - error / gradient.gradient[0]).max(0.0);
+    }
+}
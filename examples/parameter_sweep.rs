@@ -0,0 +1,14 @@
+// Reynolds number sweep of the cylinder preset, results table on stdout.
+// cargo run --release --example parameter_sweep
+use flow2d_rs::experiments::{self, Experiment};
+use flow2d_rs::presets;
+
+const STEPS: usize = 8000;
+
+fn main() -> std::io::Result<()> {
+    let results = Experiment::new(presets::cylinder_cross_flow_sized, [110, 41], STEPS)
+        .reynolds(&[50.0, 100.0, 200.0])
+        .parallel(true)
+        .run();
+    experiments::write_csv(&mut std::io::stdout(), &results)
+}
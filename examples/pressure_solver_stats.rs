@@ -0,0 +1,60 @@
+// Pressure iterations per timestep of every pressure solver with and without warm
+// starting, averaged over consecutive windows of the run. Results table on stdout.
+// cargo run --release --example pressure_solver_stats
+use flow2d_rs::observer::StepEvent;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::{PressureSolver, Simulation};
+
+use std::sync::{Arc, Mutex};
+
+const WINDOWS: usize = 3;
+const WINDOW_STEPS: usize = 200;
+
+// The cavity coarser than its preset, SOR hardly converges on 128 x 128 cells
+fn preset(name: &str) -> presets::SimulationPreset {
+    match name {
+        "lid_driven_cavity" => presets::lid_driven_cavity_sized([64, 64]),
+        _ => presets::by_name(name).unwrap(),
+    }
+}
+
+fn main() {
+    let solvers = [
+        PressureSolver::Sor,
+        PressureSolver::ConjugateGradient,
+        PressureSolver::BiCgStab,
+    ];
+
+    print!("preset,solver,warm_start");
+    for window in 0..WINDOWS {
+        print!(
+            ",steps_{}_{}",
+            window * WINDOW_STEPS,
+            (window + 1) * WINDOW_STEPS
+        );
+    }
+    println!();
+    for name in ["cylinder_cross_flow", "lid_driven_cavity"] {
+        for pressure_solver in solvers {
+            for warm_start in [false, true] {
+                let mut simulation = Simulation::from_preset(preset(name));
+                simulation.set_pressure_solver(pressure_solver);
+                simulation.set_warm_start(warm_start);
+                let iterations = Arc::new(Mutex::new([0; WINDOWS]));
+                let counter = iterations.clone();
+                simulation.add_observer(move |event: &StepEvent| {
+                    counter.lock().unwrap()[event.step / WINDOW_STEPS] += event.pressure_iterations;
+                });
+                for _ in 0..WINDOWS * WINDOW_STEPS {
+                    simulation.iterate_one_timestep();
+                }
+
+                print!("{name},{pressure_solver:?},{warm_start}");
+                for iterations in *iterations.lock().unwrap() {
+                    print!(",{}", iterations as f32 / WINDOW_STEPS as f32);
+                }
+                println!();
+            }
+        }
+    }
+}
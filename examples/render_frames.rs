@@ -0,0 +1,44 @@
+// Headless run of the cylinder preset rendering speed frames.
+// cargo run --example render_frames -- [output directory | movie.mp4]
+use flow2d_rs::colormap::Colormap;
+use flow2d_rs::frame_renderer::{FrameOutput, FrameRecorder, FrameRenderer, RenderField};
+use flow2d_rs::observer::StepEvent;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+
+const STEPS: usize = 2000;
+const FRAME_EVERY: usize = 20;
+const PROGRESS_EVERY: usize = 200;
+
+fn main() -> std::io::Result<()> {
+    let target = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "img/frames".to_string());
+    let output = if target.ends_with(".mp4") {
+        FrameOutput::Ffmpeg {
+            path: target.into(),
+            frames_per_second: 30,
+        }
+    } else {
+        FrameOutput::PngFiles(target.into())
+    };
+
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow());
+    simulation.add_observer(|event: &StepEvent| {
+        if event.step.is_multiple_of(PROGRESS_EVERY) {
+            println!(
+                "step {} t = {:.3} s, {} pressure iterations, residual {:.2e}",
+                event.step, event.time, event.pressure_iterations, event.pressure_residual
+            );
+        }
+    });
+    let renderer = FrameRenderer::new(RenderField::Vorticity, Colormap::coolwarm(), 4);
+    let mut recorder = FrameRecorder::new(renderer, output, FRAME_EVERY)?;
+
+    for _ in 0..STEPS {
+        simulation.iterate_one_timestep();
+        recorder.record(&simulation)?;
+    }
+    println!("wrote {} frames", recorder.frames());
+    recorder.finish()
+}
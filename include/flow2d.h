@@ -0,0 +1,105 @@
+#ifndef FLOW2D_H
+#define FLOW2D_H
+
+/* Declarations of src/ffi.rs, tests/ffi.rs checks that they match */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// Fields for flow2d_simulation_copy_field
+#define FLOW2D_FIELD_PRESSURE 0
+
+#define FLOW2D_FIELD_U 1
+
+#define FLOW2D_FIELD_V 2
+
+#define FLOW2D_FIELD_PSI 3
+
+typedef enum Flow2dStatus {
+  Flow2dStatus_Ok = 0,
+  // Null pointer, unknown name or field, or a parameter out of range
+  Flow2dStatus_InvalidArgument = 1,
+  // The buffer holds fewer values than the field
+  Flow2dStatus_BufferTooSmall = 2,
+} Flow2dStatus;
+
+// Opaque simulation handle
+typedef struct Flow2dSimulation Flow2dSimulation;
+
+// Parameters that may change between timesteps, see Parameters
+typedef struct Flow2dParameters {
+  float reynolds;
+  // meters/seconds^2
+  float acceleration[2];
+  // SOR relaxation of the pressure solve, 0 < omega < 2
+  float omega;
+  // seconds
+  float delta_time;
+} Flow2dParameters;
+
+// Creates a simulation from one of the presets by name, such as
+// "lid_driven_cavity", null for an unknown name.
+//
+// # Safety
+// preset is null or a NUL terminated string.
+struct Flow2dSimulation *flow2d_simulation_create(const char *preset);
+
+// # Safety
+// simulation is null or a handle from flow2d_simulation_create that is not used
+// afterwards.
+void flow2d_simulation_destroy(struct Flow2dSimulation *simulation);
+
+// Replaces the state of the handle with a preset, the handle stays valid.
+//
+// # Safety
+// simulation is null or a live handle, preset is null or a NUL terminated string.
+enum Flow2dStatus flow2d_simulation_load_preset(struct Flow2dSimulation *simulation,
+                                                const char *preset);
+
+// # Safety
+// simulation is null or a live handle.
+void flow2d_simulation_step(struct Flow2dSimulation *simulation, uint32_t steps);
+
+// Simulated time in seconds, 0 for null.
+//
+// # Safety
+// simulation is null or a live handle.
+float flow2d_simulation_time(const struct Flow2dSimulation *simulation);
+
+// Cells in x and y, fields hold width * height values.
+//
+// # Safety
+// simulation is null or a live handle, width and height are null or writable.
+enum Flow2dStatus flow2d_simulation_size(const struct Flow2dSimulation *simulation,
+                                         size_t *width,
+                                         size_t *height);
+
+// Copies a FLOW2D_FIELD_* into buffer, value of cell (x, y) at x * height + y.
+//
+// # Safety
+// simulation is null or a live handle, buffer is null or holds length floats.
+enum Flow2dStatus flow2d_simulation_copy_field(const struct Flow2dSimulation *simulation,
+                                               uint32_t field,
+                                               float *buffer,
+                                               size_t length);
+
+// # Safety
+// simulation is null or a live handle, parameters is null or writable.
+enum Flow2dStatus flow2d_simulation_get_parameters(const struct Flow2dSimulation *simulation,
+                                                   struct Flow2dParameters *parameters);
+
+// Applied from the next timestep on, out of range values leave the simulation
+// unchanged.
+//
+// # Safety
+// simulation is null or a live handle, parameters is null or readable.
+enum Flow2dStatus flow2d_simulation_set_parameters(struct Flow2dSimulation *simulation,
+                                                   const struct Flow2dParameters *parameters);
+
+// Velocity of every inflow cell in meters/seconds.
+//
+// # Safety
+// simulation is null or a live handle.
+void flow2d_simulation_set_inflow_velocity(struct Flow2dSimulation *simulation, float u, float v);
+
+#endif /* FLOW2D_H */
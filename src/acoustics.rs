@@ -0,0 +1,140 @@
+use crate::simulation::Simulation;
+
+use std::f32::consts::{PI, TAU};
+
+// Root mean square pressure of 0 decibels, pascals
+pub const REFERENCE_PRESSURE: f32 = 2e-5;
+// Fewer samples than this make no spectrum
+const MIN_SAMPLES: usize = 8;
+
+// Listening position relative to the body, far from it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Microphone {
+    pub distance: f32, // meters
+    // Counterclockwise from +x, the direction of the drag
+    pub angle: f32, // radians
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoundSpectrum {
+    pub frequencies: Vec<f32>, // hertz
+    // Pressure amplitude at the microphone per frequency, pascals
+    pub amplitudes: Vec<f32>,
+    // Sound pressure levels of the amplitudes, decibels
+    pub levels: Vec<f32>,
+}
+
+impl SoundSpectrum {
+    // (frequency, level) of the loudest frequency
+    pub fn peak(&self) -> Option<(f32, f32)> {
+        self.levels
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, &level)| (self.frequencies[index], level))
+    }
+}
+
+// Far field sound of a body from its (time in seconds, [drag, lift]) history by
+// Curle's analogy. A body small against the wavelength radiates as a dipole of the
+// fluctuating force on it. In two dimensions the dipole of force amplitude F at
+// wavenumber k is heard at distance r and angle theta with the pressure amplitude
+// sqrt(k / (8 pi r)) |F . (cos theta, sin theta)|, the far field of the Hankel
+// function Green's function, valid for k r >> 1. Density is 1 like in the momentum
+// equations. The samples are interpolated onto their mean spacing, the mean force,
+// which makes no sound, is removed and a Hann window limits the leakage between
+// frequencies.
+pub fn far_field_spectrum(
+    samples: &[(f32, [f32; 2])],
+    microphone: Microphone,
+    speed_of_sound: f32,
+) -> Option<SoundSpectrum> {
+    assert!(
+        microphone.distance > 0.0 && speed_of_sound > 0.0,
+        "distance and speed of sound must be positive"
+    );
+    if samples.len() < MIN_SAMPLES {
+        return None;
+    }
+    let count = samples.len();
+    let start = samples[0].0;
+    let duration = samples[count - 1].0 - start;
+    if duration <= 0.0 {
+        return None;
+    }
+    let step = duration / (count - 1) as f32;
+
+    // Force along the microphone direction on an even time grid
+    let direction = [microphone.angle.cos(), microphone.angle.sin()];
+    let mut later = 1;
+    let mut projected: Vec<f32> = (0..count)
+        .map(|i| {
+            let time = start + i as f32 * step;
+            while later < count - 1 && samples[later].0 < time {
+                later += 1;
+            }
+            let (time_a, force_a) = samples[later - 1];
+            let (time_b, force_b) = samples[later];
+            let t = if time_b > time_a {
+                ((time - time_a) / (time_b - time_a)).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            (0..2)
+                .map(|axis| direction[axis] * (force_a[axis] + t * (force_b[axis] - force_a[axis])))
+                .sum()
+        })
+        .collect();
+    let mean = projected.iter().sum::<f32>() / count as f32;
+    let window: Vec<f32> = (0..count)
+        .map(|i| 0.5 - 0.5 * (TAU * i as f32 / (count - 1) as f32).cos())
+        .collect();
+    let window_sum: f32 = window.iter().sum();
+    for (value, weight) in projected.iter_mut().zip(&window) {
+        *value = (*value - mean) * weight;
+    }
+
+    let (frequencies, amplitudes): (Vec<f32>, Vec<f32>) = (1..=count / 2)
+        .map(|bin| {
+            let frequency = bin as f32 / (count as f32 * step);
+            let [real, imaginary] =
+                projected
+                    .iter()
+                    .enumerate()
+                    .fold([0.0, 0.0], |[real, imaginary], (i, value)| {
+                        let phase = TAU * (bin * i % count) as f32 / count as f32;
+                        [real + value * phase.cos(), imaginary - value * phase.sin()]
+                    });
+            let force = 2.0 * (real.powi(2) + imaginary.powi(2)).sqrt() / window_sum;
+            let wavenumber = TAU * frequency / speed_of_sound;
+            let pressure = (wavenumber / (8.0 * PI * microphone.distance)).sqrt() * force;
+            (frequency, pressure)
+        })
+        .unzip();
+    let levels = amplitudes
+        .iter()
+        .map(|amplitude| 20.0 * (amplitude / 2f32.sqrt() / REFERENCE_PRESSURE).log10())
+        .collect();
+
+    Some(SoundSpectrum {
+        frequencies,
+        amplitudes,
+        levels,
+    })
+}
+
+// Sound of the cylinder of presets::cylinder_cross_flow from the second half of the
+// force samples of its "cylinder" obstacle, after the start has settled. Force
+// monitoring is off in the preset, switch it on with Simulation::obstacle_mut before
+// stepping.
+pub fn cylinder_far_field(
+    simulation: &Simulation,
+    microphone: Microphone,
+    speed_of_sound: f32,
+) -> Option<SoundSpectrum> {
+    let cylinder = simulation
+        .obstacle("cylinder")
+        .expect("not a cylinder cross flow simulation");
+    let samples = &cylinder.force_samples;
+    far_field_spectrum(&samples[samples.len() / 2..], microphone, speed_of_sound)
+}
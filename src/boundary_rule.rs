@@ -0,0 +1,104 @@
+use crate::cell::BoundaryConditionCell;
+
+// What a boundary condition does to one face it shares with a fluid cell, seen from
+// that fluid cell. SpaceDomain applies the rules of each face on its own, so a new
+// condition only needs its rules here.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundaryRule {
+    // The velocity through the shared face
+    pub normal: NormalRule,
+    // The ghost velocity along the wall, on the boundary cell's own face
+    pub tangential: TangentialRule,
+    // The pressure of the boundary cell
+    pub pressure: PressureRule,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NormalRule {
+    // The face moves with this velocity, meters per second
+    Fixed(f32),
+    // The velocity of the fluid cell's far face, no gradient through the wall
+    ZeroGradient,
+    // The velocity the boundary cell holds itself, as set by an inflow profile
+    Prescribed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TangentialRule {
+    // Mirrored about this velocity so the mean with the fluid holds on the wall face
+    Mirrored(f32),
+    // The fluid velocity, no shear at the wall
+    ZeroGradient,
+    // The ghost is left as it is
+    Unchanged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PressureRule {
+    // The pressure of the fluid cell, no pressure gradient through the wall
+    #[default]
+    ZeroGradient,
+}
+
+impl BoundaryRule {
+    // Velocity of the shared face from the velocity the boundary cell holds on it and
+    // the velocity of the fluid cell's far face, both along the normal. The far face
+    // is only read by ZeroGradient.
+    pub fn normal_velocity(&self, own: f32, far: impl FnOnce() -> f32) -> f32 {
+        match self.normal {
+            NormalRule::Fixed(velocity) => velocity,
+            NormalRule::ZeroGradient => far(),
+            NormalRule::Prescribed => own,
+        }
+    }
+
+    // Ghost velocity across the wall from the fluid velocity along it, None to keep
+    // the ghost
+    pub fn tangential_velocity(&self, fluid: f32) -> Option<f32> {
+        match self.tangential {
+            TangentialRule::Mirrored(velocity) => Some(2.0 * velocity - fluid),
+            TangentialRule::ZeroGradient => Some(fluid),
+            TangentialRule::Unchanged => None,
+        }
+    }
+
+    // Pressure the boundary cell takes from the fluid cell, the boundary cell
+    // averages this over its fluid neighbours
+    pub fn pressure(&self, fluid: f32) -> f32 {
+        match self.pressure {
+            PressureRule::ZeroGradient => fluid,
+        }
+    }
+}
+
+impl BoundaryConditionCell {
+    // Rule for the faces whose normal lies along axis, 0 for the left and right faces
+    // and 1 for the bottom and top ones. The tangential rule applies to the velocity
+    // across that axis. None for periodic cells, which copy a whole cell instead, see
+    // SpaceDomain::update_periodic_cells.
+    pub fn rule(&self, axis: usize) -> Option<BoundaryRule> {
+        let (normal, tangential) = match *self {
+            BoundaryConditionCell::NoSlipCell {
+                boundary_condition_velocity,
+            } => (
+                NormalRule::Fixed(boundary_condition_velocity[axis]),
+                TangentialRule::Mirrored(boundary_condition_velocity[1 - axis]),
+            ),
+            BoundaryConditionCell::FreeSlipCell => {
+                (NormalRule::Fixed(0.0), TangentialRule::ZeroGradient)
+            }
+            BoundaryConditionCell::OutFlowCell => {
+                (NormalRule::ZeroGradient, TangentialRule::ZeroGradient)
+            }
+            BoundaryConditionCell::InflowCell => {
+                (NormalRule::Prescribed, TangentialRule::Unchanged)
+            }
+            BoundaryConditionCell::PeriodicCell => return None,
+        };
+        Some(BoundaryRule {
+            normal,
+            tangential,
+            pressure: PressureRule::ZeroGradient,
+        })
+    }
+}
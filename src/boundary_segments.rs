@@ -0,0 +1,147 @@
+use crate::cell::{CellFlags, CellType};
+use crate::space_domain::{SpaceDomain, FACE_NEIGHBORS};
+use crate::wall_shear;
+
+// Named group of boundary cells, like the inlet of a channel or the wall of a
+// cylinder, for referring to a part of the boundary without its cell coordinates.
+// Segments are fixed cells of one grid, obstacles moving later don't take them along.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoundarySegment {
+    pub name: String,
+    pub cells: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+// Face a segment shares with a fluid cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentFace {
+    pub cell: (usize, usize),
+    pub fluid: (usize, usize),
+    // Unit offset into the fluid
+    pub normal: [isize; 2],
+}
+
+// Over the faces a segment shares with fluid cells
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SegmentStatistics {
+    pub face_count: usize,
+    pub length: f32, // meters
+    // Volume flux per unit depth in meters^2/seconds, positive for flow from the
+    // segment into the fluid
+    pub flow_rate: f32,
+    pub mean_normal_velocity: f32,
+    // Of the fluid cells next to the faces, None without faces
+    pub mean_pressure: Option<f32>,
+    // Per unit depth the fluid exerts on the segment, pressure on every face and wall
+    // shear on the faces of no-slip cells, see wall_shear::wall_faces
+    pub force: [f32; 2],
+}
+
+impl BoundarySegment {
+    pub fn new(name: &str, cells: Vec<(usize, usize)>) -> Self {
+        Self {
+            name: name.to_string(),
+            cells,
+        }
+    }
+
+    // Boundary cells along an edge of the domain that share a face with the fluid,
+    // which leaves out the corners
+    pub fn on_edge(name: &str, space_domain: &SpaceDomain, edge: Edge) -> Self {
+        let [nx, ny] = space_domain.space_size();
+        let cells: Vec<(usize, usize)> = match edge {
+            Edge::Left => (0..ny).map(|y| (0, y)).collect(),
+            Edge::Right => (0..ny).map(|y| (nx - 1, y)).collect(),
+            Edge::Bottom => (0..nx).map(|x| (x, 0)).collect(),
+            Edge::Top => (0..nx).map(|x| (x, ny - 1)).collect(),
+        };
+        let cells = cells
+            .into_iter()
+            .filter(|&(x, y)| space_domain.flags(x, y).contains(CellFlags::SURFACE))
+            .collect();
+        Self::new(name, cells)
+    }
+
+    // In the order of the cells and then of FACE_NEIGHBORS
+    pub fn fluid_faces(&self, space_domain: &SpaceDomain) -> Vec<SegmentFace> {
+        let mut faces = Vec::new();
+        for &(x, y) in &self.cells {
+            if matches!(space_domain.cell_type(x, y), CellType::FluidCell) {
+                continue;
+            }
+            let flags = space_domain.flags(x, y);
+            for (offset, neighbor_flag) in FACE_NEIGHBORS
+                .into_iter()
+                .zip(CellFlags::FACE_NEIGHBOR_FLAGS)
+            {
+                if flags.contains(neighbor_flag) {
+                    let fluid = space_domain.neighbor_position(x, y, offset).unwrap();
+                    faces.push(SegmentFace {
+                        cell: (x, y),
+                        fluid,
+                        normal: offset,
+                    });
+                }
+            }
+        }
+        faces
+    }
+
+    pub fn statistics(&self, space_domain: &SpaceDomain, reynolds: f32) -> SegmentStatistics {
+        let [dx, dy] = space_domain.delta_space();
+        let faces = self.fluid_faces(space_domain);
+
+        let mut length = 0.0;
+        let mut flow_rate = 0.0;
+        let mut pressure_sum = 0.0;
+        let mut force = [0.0, 0.0];
+        for face in &faces {
+            let ((x, y), (fx, fy), normal) = (face.cell, face.fluid, face.normal);
+            let axis = if normal[0] != 0 { 0 } else { 1 };
+            let face_length = if axis == 0 { dy } else { dx };
+            // The face belongs to the cell on its left or bottom
+            let (owner_x, owner_y) = if normal[axis] < 0 { (fx, fy) } else { (x, y) };
+            let velocity = space_domain.velocity(owner_x, owner_y)[axis];
+            let pressure = space_domain.pressure(fx, fy);
+
+            length += face_length;
+            flow_rate += velocity * normal[axis] as f32 * face_length;
+            pressure_sum += pressure;
+            force[axis] -= pressure * normal[axis] as f32 * face_length;
+        }
+        for face in wall_shear::wall_faces(
+            space_domain.fields(),
+            space_domain.space_size(),
+            [dx, dy],
+            reynolds,
+            self.cells.iter().copied(),
+        ) {
+            let face_length = if face.normal[0] != 0.0 { dy } else { dx };
+            let tangent = face.tangent();
+            for axis in [0, 1] {
+                force[axis] += face.shear_stress * tangent[axis] * face_length;
+            }
+        }
+
+        let face_count = faces.len();
+        SegmentStatistics {
+            face_count,
+            length,
+            flow_rate,
+            mean_normal_velocity: if face_count == 0 {
+                0.0
+            } else {
+                flow_rate / length
+            },
+            mean_pressure: (face_count > 0).then(|| pressure_sum / face_count as f32),
+            force,
+        }
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Cell {
     pub cell_type: CellType,
     pub velocity: [f32; 2],
@@ -7,9 +7,10 @@ pub struct Cell {
     pub f: f32,
     pub g: f32,
     pub psi: f32,
+    pub dye: f32,
 }
 
-#[derive(Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub enum CellType {
     #[default]
     FluidCell,
@@ -17,7 +18,24 @@ pub enum CellType {
     BoundaryConditionCell(BoundaryConditionCell),
 }
 
-#[derive(Clone, Copy)]
+impl CellType {
+    // 0 fluid, 1 boundary condition, 2 void, as written by the exporters
+    pub fn id(&self) -> u8 {
+        match self {
+            CellType::FluidCell => 0,
+            CellType::BoundaryConditionCell(_) => 1,
+            CellType::VoidCell => 2,
+        }
+    }
+
+    // Boundary condition cells other than the open ones of CellFlags::OPEN
+    pub fn blocks_flow(&self) -> bool {
+        let flags = CellFlags::of_type(*self);
+        flags.intersects(CellFlags::BOUNDARY) && !flags.intersects(CellFlags::OPEN)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BoundaryConditionCell {
     NoSlipCell {
         boundary_condition_velocity: [f32; 2],
@@ -25,4 +43,92 @@ pub enum BoundaryConditionCell {
     FreeSlipCell,
     OutFlowCell,
     InflowCell,
+    // Ghost cell on the domain edge mirroring the cell one in from the opposite edge,
+    // supported by Simulation and LatticeBoltzmann
+    PeriodicCell,
+}
+
+// Precomputed summary of a cell and its face neighbors, kept by SpaceDomain whenever
+// the geometry changes so hot loops test bits instead of matching cell types. Void
+// cells and cells outside the domain set none of the type bits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CellFlags(u16);
+
+impl CellFlags {
+    pub const EMPTY: Self = Self(0);
+    pub const FLUID: Self = Self(1 << 0);
+    // No slip and free slip walls
+    pub const OBSTACLE: Self = Self(1 << 1);
+    pub const INFLOW: Self = Self(1 << 2);
+    pub const OUTFLOW: Self = Self(1 << 3);
+    pub const PERIODIC: Self = Self(1 << 4);
+    // Not fluid but sharing a face with the fluid, where boundary conditions act
+    pub const SURFACE: Self = Self(1 << 5);
+    // The face neighbor on the left, right, bottom or top is fluid
+    pub const FLUID_WEST: Self = Self(1 << 6);
+    pub const FLUID_EAST: Self = Self(1 << 7);
+    pub const FLUID_SOUTH: Self = Self(1 << 8);
+    pub const FLUID_NORTH: Self = Self(1 << 9);
+
+    pub const BOUNDARY: Self =
+        Self(Self::OBSTACLE.0 | Self::INFLOW.0 | Self::OUTFLOW.0 | Self::PERIODIC.0);
+    // Periodic cells continue the fluid from the other side
+    pub const OPEN: Self = Self(Self::FLUID.0 | Self::PERIODIC.0);
+    pub const FLUID_NEIGHBORS: Self =
+        Self(Self::FLUID_WEST.0 | Self::FLUID_EAST.0 | Self::FLUID_SOUTH.0 | Self::FLUID_NORTH.0);
+    // Neighbor bits in the order of space_domain::FACE_NEIGHBORS
+    pub const FACE_NEIGHBOR_FLAGS: [Self; 4] = [
+        Self::FLUID_WEST,
+        Self::FLUID_EAST,
+        Self::FLUID_SOUTH,
+        Self::FLUID_NORTH,
+    ];
+
+    // Type bits of a cell, without its neighbors
+    pub fn of_type(cell_type: CellType) -> Self {
+        match cell_type {
+            CellType::FluidCell => Self::FLUID,
+            CellType::VoidCell => Self::EMPTY,
+            CellType::BoundaryConditionCell(boundary) => match boundary {
+                BoundaryConditionCell::NoSlipCell { .. } | BoundaryConditionCell::FreeSlipCell => {
+                    Self::OBSTACLE
+                }
+                BoundaryConditionCell::InflowCell => Self::INFLOW,
+                BoundaryConditionCell::OutFlowCell => Self::OUTFLOW,
+                BoundaryConditionCell::PeriodicCell => Self::PERIODIC,
+            },
+        }
+    }
+
+    pub fn bits(self) -> u16 {
+        self.0
+    }
+
+    // All of the bits of other are set
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    // Any of the bits of other is set
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub fn is_fluid(self) -> bool {
+        self.contains(Self::FLUID)
+    }
+}
+
+impl std::ops::BitOr for CellFlags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl std::ops::BitOrAssign for CellFlags {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
 }
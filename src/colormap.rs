@@ -0,0 +1,222 @@
+// Piecewise linear colormaps from scalar fields to RGB(A)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Colormap {
+    // (position in [0, 1], rgb in [0, 1]), sorted by position
+    stops: Vec<(f32, [f32; 3])>,
+    // Zero should sit in the middle of the range
+    is_diverging: bool,
+}
+
+impl Colormap {
+    // Stops must start at 0, end at 1 and increase in between
+    pub fn from_stops(stops: Vec<(f32, [f32; 3])>) -> Self {
+        assert!(stops.len() >= 2, "a colormap needs at least two stops");
+        assert!(
+            stops.first().unwrap().0 == 0.0 && stops.last().unwrap().0 == 1.0,
+            "colormap stops must span [0, 1]"
+        );
+        assert!(
+            stops.windows(2).all(|pair| pair[0].0 < pair[1].0),
+            "colormap stops must be increasing"
+        );
+        Self {
+            stops,
+            is_diverging: false,
+        }
+    }
+
+    pub fn diverging(mut self) -> Self {
+        self.is_diverging = true;
+        self
+    }
+
+    pub fn is_diverging(&self) -> bool {
+        self.is_diverging
+    }
+
+    // Sampled from matplotlib's viridis
+    pub fn viridis() -> Self {
+        Self::from_stops(vec![
+            (0.0, [0.267, 0.005, 0.329]),
+            (0.125, [0.282, 0.141, 0.458]),
+            (0.25, [0.254, 0.265, 0.530]),
+            (0.375, [0.207, 0.372, 0.553]),
+            (0.5, [0.164, 0.471, 0.558]),
+            (0.625, [0.128, 0.567, 0.551]),
+            (0.75, [0.135, 0.659, 0.518]),
+            (0.875, [0.267, 0.749, 0.441]),
+            (0.94, [0.478, 0.821, 0.3182]),
+            (1.0, [0.993, 0.906, 0.144]),
+        ])
+    }
+
+    // Sampled from matplotlib's plasma
+    pub fn plasma() -> Self {
+        Self::from_stops(vec![
+            (0.0, [0.050, 0.030, 0.528]),
+            (0.125, [0.255, 0.014, 0.615]),
+            (0.25, [0.418, 0.001, 0.658]),
+            (0.375, [0.563, 0.052, 0.642]),
+            (0.5, [0.693, 0.165, 0.565]),
+            (0.625, [0.798, 0.280, 0.470]),
+            (0.75, [0.881, 0.393, 0.383]),
+            (0.875, [0.949, 0.518, 0.296]),
+            (0.94, [0.974, 0.622, 0.208]),
+            (1.0, [0.940, 0.975, 0.131]),
+        ])
+    }
+
+    // Moreland's blue to red diverging map
+    pub fn coolwarm() -> Self {
+        Self::from_stops(vec![
+            (0.0, [0.230, 0.299, 0.754]),
+            (0.25, [0.552, 0.690, 0.996]),
+            (0.5, [0.865, 0.865, 0.865]),
+            (0.75, [0.958, 0.604, 0.482]),
+            (1.0, [0.706, 0.016, 0.150]),
+        ])
+        .diverging()
+    }
+
+    // Hue from blue to red at full saturation, the original viewer coloring
+    pub fn rainbow() -> Self {
+        Self::from_stops(vec![
+            (0.0, [0.0, 0.0, 1.0]),
+            (0.25, [0.0, 1.0, 1.0]),
+            (0.5, [0.0, 1.0, 0.0]),
+            (0.75, [1.0, 1.0, 0.0]),
+            (1.0, [1.0, 0.0, 0.0]),
+        ])
+    }
+
+    pub fn grayscale() -> Self {
+        Self::from_stops(vec![(0.0, [0.0, 0.0, 0.0]), (1.0, [1.0, 1.0, 1.0])])
+    }
+
+    // t is clamped to [0, 1], NaN maps to the lowest color
+    pub fn color(&self, t: f32) -> [f32; 3] {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+
+        let upper = self
+            .stops
+            .iter()
+            .position(|&(position, _)| position >= t)
+            .unwrap()
+            .max(1);
+        let (start, start_color) = self.stops[upper - 1];
+        let (end, end_color) = self.stops[upper];
+        let weight = (t - start) / (end - start);
+
+        [0, 1, 2].map(|i| start_color[i] + (end_color[i] - start_color[i]) * weight)
+    }
+
+    // Color of value with range mapped onto the colormap, a diverging map centres
+    // the range on zero
+    pub fn map(&self, value: f32, range: [f32; 2]) -> [f32; 3] {
+        let range = if self.is_diverging {
+            symmetric_range(range)
+        } else {
+            range
+        };
+        let width = range[1] - range[0];
+        let t = if width > 0.0 {
+            (value - range[0]) / width
+        } else {
+            0.5
+        };
+        self.color(t)
+    }
+
+    // Refill buffer with 8 bit RGBA, None is fully transparent
+    pub fn fill_rgba(
+        &self,
+        values: impl IntoIterator<Item = Option<f32>>,
+        range: [f32; 2],
+        buffer: &mut Vec<u8>,
+    ) {
+        buffer.clear();
+        for value in values {
+            match value {
+                Some(value) => {
+                    let [r, g, b] = self.map(value, range).map(|c| (c * 255.0).round() as u8);
+                    buffer.extend_from_slice(&[r, g, b, 255]);
+                }
+                None => buffer.extend_from_slice(&[0, 0, 0, 0]),
+            }
+        }
+    }
+}
+
+// Widen range so it is centred on zero, for signed fields like pressure or vorticity
+pub fn symmetric_range(range: [f32; 2]) -> [f32; 2] {
+    let extent = range[0].abs().max(range[1].abs());
+    [-extent, extent]
+}
+
+// How the displayed range follows the field from frame to frame
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum RangeMode {
+    // Instantaneous min/max, colors flicker when the extremes jump
+    #[default]
+    Autoscale,
+    // Widest range seen so far
+    RunningMinMax,
+    // Exponential smoothing, the weight of the newest frame in (0, 1]
+    Smoothed(f32),
+    Fixed([f32; 2]),
+}
+
+// Display range of one field, fed with the instantaneous range every frame
+#[derive(Debug, Clone, Default)]
+pub struct ColorRange {
+    mode: RangeMode,
+    range: Option<[f32; 2]>,
+}
+
+impl ColorRange {
+    pub fn new(mode: RangeMode) -> Self {
+        if let RangeMode::Smoothed(weight) = mode {
+            assert!(
+                weight > 0.0 && weight <= 1.0,
+                "smoothing weight must be in (0, 1]"
+            );
+        }
+        Self { mode, range: None }
+    }
+
+    pub fn mode(&self) -> RangeMode {
+        self.mode
+    }
+
+    // Changing the mode forgets the history
+    pub fn set_mode(&mut self, mode: RangeMode) {
+        *self = Self::new(mode);
+    }
+
+    pub fn reset(&mut self) {
+        self.range = None;
+    }
+
+    pub fn update(&mut self, instantaneous: [f32; 2]) -> [f32; 2] {
+        // No fluid cells gives an infinite range, keep the previous one
+        let is_valid = instantaneous.iter().all(|value| value.is_finite());
+
+        let range = match (self.mode, self.range) {
+            (RangeMode::Fixed(range), _) => range,
+            (_, Some(previous)) if !is_valid => previous,
+            (RangeMode::Autoscale, _) | (_, None) => instantaneous,
+            (RangeMode::RunningMinMax, Some(previous)) => [
+                previous[0].min(instantaneous[0]),
+                previous[1].max(instantaneous[1]),
+            ],
+            (RangeMode::Smoothed(weight), Some(previous)) => [
+                previous[0] + weight * (instantaneous[0] - previous[0]),
+                previous[1] + weight * (instantaneous[1] - previous[1]),
+            ],
+        };
+        if is_valid || matches!(self.mode, RangeMode::Fixed(_)) {
+            self.range = Some(range);
+        }
+        range
+    }
+}
@@ -0,0 +1,271 @@
+use crate::boundary_segments::SegmentStatistics;
+use crate::cell::{BoundaryConditionCell, CellType};
+use crate::simulation::Simulation;
+use crate::solver::FluidSolver;
+
+use std::ops::Range;
+
+// Body force per unit mass on the u and v faces of the cells in a rectangle, in cells
+// of the current grid. Simulation::resample drops them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionForce {
+    pub name: String,
+    pub x: Range<usize>,
+    pub y: Range<usize>,
+    pub force: [f32; 2], // meters/seconds^2
+}
+
+// Where a control loop measures the flow
+#[derive(Debug, Clone, PartialEq)]
+pub enum Probe {
+    // Velocity component at the center of cell (x, y)
+    Velocity {
+        x: usize,
+        y: usize,
+        component: usize,
+    },
+    // Velocity component interpolated at a point of the fluid in meters, see
+    // FluidSolver::velocity_at
+    VelocityAt {
+        position: [f32; 2],
+        component: usize,
+    },
+    Pressure {
+        x: usize,
+        y: usize,
+    },
+    // Component of the [drag, lift] on a named obstacle
+    ObstacleForce {
+        name: String,
+        component: usize,
+    },
+    // Latest flux through a named flux monitor, zero before the first step
+    Flux {
+        name: String,
+    },
+    // Flow rate from a named boundary segment into the fluid
+    SegmentFlowRate {
+        name: String,
+    },
+    // Component of the force on a named boundary segment
+    SegmentForce {
+        name: String,
+        component: usize,
+    },
+}
+
+impl Probe {
+    pub fn read(&self, simulation: &Simulation) -> f32 {
+        match self {
+            Probe::Velocity { x, y, component } => {
+                simulation.get_centered_velocity(*x, *y)[*component]
+            }
+            Probe::VelocityAt {
+                position,
+                component,
+            } => simulation
+                .velocity_at(*position)
+                .unwrap_or_else(|error| panic!("{error}"))[*component],
+            Probe::Pressure { x, y } => simulation.get_cell(*x, *y).pressure,
+            Probe::ObstacleForce { name, component } => simulation
+                .named_obstacle_force(name)
+                .expect("no obstacle of that name")[*component],
+            Probe::Flux { name } => simulation
+                .flux_monitors()
+                .get(name)
+                .expect("no flux monitor of that name")
+                .latest()
+                .unwrap_or(0.0),
+            Probe::SegmentFlowRate { name } => segment_statistics(simulation, name).flow_rate,
+            Probe::SegmentForce { name, component } => {
+                segment_statistics(simulation, name).force[*component]
+            }
+        }
+    }
+}
+
+fn segment_statistics(simulation: &Simulation, name: &str) -> SegmentStatistics {
+    simulation
+        .segment_statistics(name)
+        .expect("no boundary segment of that name")
+}
+
+// What a control loop adjusts, to its actuation times the direction
+#[derive(Debug, Clone, PartialEq)]
+pub enum Actuator {
+    // Velocity of every inflow cell
+    Inflow {
+        direction: [f32; 2],
+    },
+    // Wall velocity of the no-slip cells in a rectangle, blowing or suction where it
+    // points into the fluid and a moving wall where it runs along it
+    WallJet {
+        x: Range<usize>,
+        y: Range<usize>,
+        direction: [f32; 2],
+    },
+    // Force of the named region force of the simulation
+    BodyForce {
+        name: String,
+        direction: [f32; 2],
+    },
+}
+
+impl Actuator {
+    pub fn apply(&self, simulation: &mut Simulation, actuation: f32) {
+        match self {
+            Actuator::Inflow { direction } => {
+                simulation.set_inflow_velocity(direction.map(|component| actuation * component))
+            }
+            Actuator::WallJet { x, y, direction } => {
+                let velocity = direction.map(|component| actuation * component);
+                for x in x.clone() {
+                    for y in y.clone() {
+                        if matches!(
+                            simulation.get_cell(x, y).cell_type,
+                            CellType::BoundaryConditionCell(
+                                BoundaryConditionCell::NoSlipCell { .. }
+                            )
+                        ) {
+                            simulation.set_wall_velocity(x, y, velocity);
+                        }
+                    }
+                }
+            }
+            Actuator::BodyForce { name, direction } => {
+                simulation
+                    .region_force_mut(name)
+                    .expect("no region force of that name")
+                    .force = direction.map(|component| actuation * component);
+            }
+        }
+    }
+}
+
+// Turns a measurement into an actuation, once per timestep
+pub trait Controller {
+    fn update(&mut self, measurement: f32, delta_time: f32) -> f32;
+}
+
+// Closures are controllers too
+impl<F: FnMut(f32, f32) -> f32> Controller for F {
+    fn update(&mut self, measurement: f32, delta_time: f32) -> f32 {
+        self(measurement, delta_time)
+    }
+}
+
+// Proportional, integral and derivative feedback on setpoint - measurement, added
+// to the bias. While the output is clamped the integral holds still, so it doesn't
+// wind up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pid {
+    pub proportional: f32,
+    pub integral: f32,
+    pub derivative: f32,
+    pub setpoint: f32,
+    // Actuation without error, like the uncontrolled inflow speed
+    pub bias: f32,
+    pub output_range: Option<[f32; 2]>,
+    error_integral: f32,
+    previous_error: Option<f32>,
+}
+
+impl Pid {
+    pub fn new(proportional: f32, integral: f32, derivative: f32, setpoint: f32) -> Self {
+        Self {
+            proportional,
+            integral,
+            derivative,
+            setpoint,
+            bias: 0.0,
+            output_range: None,
+            error_integral: 0.0,
+            previous_error: None,
+        }
+    }
+
+    pub fn with_bias(mut self, bias: f32) -> Self {
+        self.bias = bias;
+        self
+    }
+
+    pub fn with_output_range(mut self, output_range: Option<[f32; 2]>) -> Self {
+        if let Some([min, max]) = output_range {
+            assert!(min <= max, "output range must not be empty");
+        }
+        self.output_range = output_range;
+        self
+    }
+
+    // Forget the integral and the previous error
+    pub fn reset(&mut self) {
+        self.error_integral = 0.0;
+        self.previous_error = None;
+    }
+}
+
+impl Controller for Pid {
+    fn update(&mut self, measurement: f32, delta_time: f32) -> f32 {
+        let error = self.setpoint - measurement;
+        let error_rate = match self.previous_error {
+            Some(previous) if delta_time > 0.0 => (error - previous) / delta_time,
+            _ => 0.0,
+        };
+        self.previous_error = Some(error);
+
+        let integral = self.error_integral + error * delta_time;
+        let output = self.bias
+            + self.proportional * error
+            + self.integral * integral
+            + self.derivative * error_rate;
+        match self.output_range {
+            Some([min, max]) if !(min..=max).contains(&output) => output.clamp(min, max),
+            _ => {
+                self.error_integral = integral;
+                output
+            }
+        }
+    }
+}
+
+// Closes the loop from a probe through a controller to an actuator. Call step in
+// place of Simulation::iterate_one_timestep, or actuate before it to drive several
+// loops in one simulation.
+pub struct ControlLoop {
+    pub probe: Probe,
+    pub actuator: Actuator,
+    controller: Box<dyn Controller + Send>,
+    // (time in seconds, measurement, actuation) at every actuation
+    pub history: Vec<(f32, f32, f32)>,
+}
+
+impl ControlLoop {
+    pub fn new(
+        probe: Probe,
+        controller: impl Controller + Send + 'static,
+        actuator: Actuator,
+    ) -> Self {
+        Self {
+            probe,
+            actuator,
+            controller: Box::new(controller),
+            history: Vec::new(),
+        }
+    }
+
+    // Measures, updates the controller and applies its actuation for the next
+    // timestep, returns the actuation
+    pub fn actuate(&mut self, simulation: &mut Simulation) -> f32 {
+        let measurement = self.probe.read(simulation);
+        let actuation = self.controller.update(measurement, simulation.delta_time());
+        self.actuator.apply(simulation, actuation);
+        self.history
+            .push((simulation.time(), measurement, actuation));
+        actuation
+    }
+
+    pub fn step(&mut self, simulation: &mut Simulation) {
+        self.actuate(simulation);
+        simulation.iterate_one_timestep();
+    }
+}
@@ -0,0 +1,107 @@
+use crate::space_domain::Fields;
+
+use std::ops::Range;
+
+// Rectangle of cells, x and y are cell indices
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirtyRegion {
+    pub x: Range<usize>,
+    pub y: Range<usize>,
+}
+
+// Splits the domain into square tiles and reports the tiles where some cell moved
+// further than threshold from the values at the previous report. Cells that change
+// slowly are still reported once their drift adds up.
+#[derive(Clone)]
+pub struct DirtyTracker {
+    tile_size: usize,
+    threshold: f32,
+    reported: Fields,
+    dirty_regions: Vec<DirtyRegion>,
+}
+
+impl DirtyTracker {
+    pub fn new(tile_size: usize, threshold: f32) -> Self {
+        assert!(tile_size > 0, "tile size must be positive");
+        Self {
+            tile_size,
+            threshold,
+            reported: Fields::default(),
+            dirty_regions: Vec::new(),
+        }
+    }
+
+    pub fn tile_size(&self) -> usize {
+        self.tile_size
+    }
+
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    // Regions found by the last update
+    pub fn dirty_regions(&self) -> &[DirtyRegion] {
+        &self.dirty_regions
+    }
+
+    // Report every tile on the next update, e.g. after a resize or restore
+    pub fn mark_all_dirty(&mut self) {
+        self.reported = Fields::default();
+    }
+
+    pub fn update(&mut self, fields: &Fields, space_size: [usize; 2]) -> &[DirtyRegion] {
+        assert_eq!(
+            fields.len(),
+            space_size[0] * space_size[1],
+            "cell count mismatch"
+        );
+        self.dirty_regions.clear();
+
+        if self.reported.len() != fields.len() {
+            self.reported = fields.clone();
+            self.dirty_regions.push(DirtyRegion {
+                x: 0..space_size[0],
+                y: 0..space_size[1],
+            });
+            return &self.dirty_regions;
+        }
+
+        for tile_x in (0..space_size[0]).step_by(self.tile_size) {
+            for tile_y in (0..space_size[1]).step_by(self.tile_size) {
+                let region = DirtyRegion {
+                    x: tile_x..(tile_x + self.tile_size).min(space_size[0]),
+                    y: tile_y..(tile_y + self.tile_size).min(space_size[1]),
+                };
+                let indices = || {
+                    region
+                        .x
+                        .clone()
+                        .flat_map(|x| region.y.clone().map(move |y| x * space_size[1] + y))
+                };
+
+                if indices().any(|index| self.has_changed(fields, index)) {
+                    for index in indices() {
+                        self.reported.set_cell(index, &fields.cell(index));
+                    }
+                    self.dirty_regions.push(region);
+                }
+            }
+        }
+        &self.dirty_regions
+    }
+
+    fn has_changed(&self, fields: &Fields, index: usize) -> bool {
+        let reported = &self.reported;
+        fields.cell_type[index] != reported.cell_type[index]
+            || [
+                fields.u[index] - reported.u[index],
+                fields.v[index] - reported.v[index],
+                fields.pressure[index] - reported.pressure[index],
+                fields.psi[index] - reported.psi[index],
+                fields.dye[index] - reported.dye[index],
+            ]
+            .iter()
+            // NaN counts as changed
+            .any(|difference| difference.abs() > self.threshold || difference.is_nan())
+    }
+}
@@ -0,0 +1,348 @@
+use crate::cell::Cell;
+use crate::cell::CellType;
+use crate::events::EventSchedule;
+use crate::presets::SimulationPreset;
+use crate::simulation::{Halo, Simulation};
+use crate::solver::Checkpoint;
+use crate::space_domain::SpaceDomain;
+
+use std::ops::Range;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+// u, v, pressure, rhs, f, g, psi, dye
+const VALUES_PER_CELL: usize = 8;
+
+// Point to point messaging between the processes of a distributed run. Every process
+// issues the same sequence of collective calls, messages between two processes arrive
+// in the order they were sent.
+pub trait Communicator {
+    fn rank(&self) -> usize;
+    fn size(&self) -> usize;
+    fn send(&self, to: usize, data: Vec<f32>);
+    // Blocks until a message from `from` arrives
+    fn receive(&self, from: usize) -> Vec<f32>;
+
+    // Sum in rank order, so every process gets the same bits
+    fn all_reduce_sum(&self, value: f32) -> f32 {
+        for to in (0..self.size()).filter(|&to| to != self.rank()) {
+            self.send(to, vec![value]);
+        }
+        (0..self.size())
+            .map(|from| {
+                if from == self.rank() {
+                    value
+                } else {
+                    self.receive(from)[0]
+                }
+            })
+            .sum()
+    }
+}
+
+// In process communicator, one per thread
+pub struct ChannelCommunicator {
+    rank: usize,
+    senders: Vec<Sender<Vec<f32>>>,     // indexed by destination
+    receivers: Vec<Receiver<Vec<f32>>>, // indexed by source
+}
+
+impl ChannelCommunicator {
+    pub fn group(size: usize) -> Vec<ChannelCommunicator> {
+        let mut senders: Vec<Vec<Sender<Vec<f32>>>> = vec![Vec::new(); size];
+        let mut receivers: Vec<Vec<Receiver<Vec<f32>>>> = (0..size).map(|_| Vec::new()).collect();
+
+        // senders[from][to], receivers[to][from]
+        for from_senders in senders.iter_mut() {
+            for to_receivers in receivers.iter_mut() {
+                let (sender, receiver) = channel();
+                from_senders.push(sender);
+                to_receivers.push(receiver);
+            }
+        }
+
+        senders
+            .into_iter()
+            .zip(receivers)
+            .enumerate()
+            .map(|(rank, (senders, receivers))| ChannelCommunicator {
+                rank,
+                senders,
+                receivers,
+            })
+            .collect()
+    }
+}
+
+impl Communicator for ChannelCommunicator {
+    fn rank(&self) -> usize {
+        self.rank
+    }
+
+    fn size(&self) -> usize {
+        self.senders.len()
+    }
+
+    fn send(&self, to: usize, data: Vec<f32>) {
+        self.senders[to]
+            .send(data)
+            .expect("receiving process hung up");
+    }
+
+    fn receive(&self, from: usize) -> Vec<f32> {
+        self.receivers[from]
+            .recv()
+            .expect("sending process hung up")
+    }
+}
+
+// Global columns of one tile. Interior tiles carry one ghost column on each side that is
+// refreshed from the neighbour, and beyond it a void cap so stencils of the ghost
+// column stay inside the tile.
+#[derive(Debug, Clone)]
+struct TileLayout {
+    owned: Range<usize>,
+    local: Range<usize>,
+    has_left: bool,
+    has_right: bool,
+}
+
+impl TileLayout {
+    fn new(rank: usize, size: usize, columns: usize) -> Self {
+        let owned = rank * columns / size..(rank + 1) * columns / size;
+        assert!(owned.len() >= 2, "too many tiles for {columns} columns");
+
+        let has_left = rank > 0;
+        let has_right = rank + 1 < size;
+        let local_start = if has_left {
+            owned.start - 2
+        } else {
+            owned.start
+        };
+        let local_end = if has_right { owned.end + 2 } else { owned.end };
+
+        Self {
+            owned,
+            local: local_start..local_end,
+            has_left,
+            has_right,
+        }
+    }
+
+    fn to_local(&self, x: usize) -> usize {
+        x - self.local.start
+    }
+}
+
+struct TileHalo<'a, C: Communicator> {
+    communicator: &'a C,
+    layout: &'a TileLayout,
+}
+
+impl<C: Communicator> Halo for TileHalo<'_, C> {
+    fn owned_columns(&self, _space_size: [usize; 2]) -> Range<usize> {
+        self.layout.to_local(self.layout.owned.start)..self.layout.to_local(self.layout.owned.end)
+    }
+
+    fn exchange(&mut self, space_domain: &mut SpaceDomain) {
+        let rank = self.communicator.rank();
+        let owned = self.owned_columns(space_domain.space_size());
+
+        // Channels are unbounded, so sending first cannot deadlock
+        if self.layout.has_left {
+            let column = pack_column(space_domain, owned.start);
+            self.communicator.send(rank - 1, column);
+        }
+        if self.layout.has_right {
+            let column = pack_column(space_domain, owned.end - 1);
+            self.communicator.send(rank + 1, column);
+        }
+
+        if self.layout.has_left {
+            let column = self.communicator.receive(rank - 1);
+            unpack_column(space_domain, owned.start - 1, &column);
+        }
+        if self.layout.has_right {
+            let column = self.communicator.receive(rank + 1);
+            unpack_column(space_domain, owned.end, &column);
+        }
+    }
+
+    fn sum(&mut self, value: f32) -> f32 {
+        self.communicator.all_reduce_sum(value)
+    }
+}
+
+// One tile of a domain split into vertical strips, one per process. Geometry is fixed,
+// scheduled events are not forwarded to the tiles and semi-Lagrangian backtraces longer
+// than one column stop at the tile edge.
+pub struct DistributedSimulation<C: Communicator> {
+    communicator: C,
+    layout: TileLayout,
+    simulation: Simulation,
+    space_size: [usize; 2],
+    // Full domain cells kept by rank 0 to assemble gathered fields
+    gathered_cells: Option<Vec<Cell>>,
+}
+
+impl<C: Communicator> DistributedSimulation<C> {
+    // Every process builds the same preset and keeps its own strip
+    pub fn from_preset(preset: SimulationPreset, communicator: C) -> Self {
+        let space_domain = &preset.space_domain;
+        let space_size = space_domain.space_size();
+        let layout = TileLayout::new(communicator.rank(), communicator.size(), space_size[0]);
+
+        let mut columns: Vec<Vec<Cell>> = layout
+            .local
+            .clone()
+            .map(|x| {
+                (0..space_size[1])
+                    .map(|y| space_domain.get_cell(x, y))
+                    .collect()
+            })
+            .collect();
+        if layout.has_left {
+            columns[0] = vec![void_cell(); space_size[1]];
+        }
+        if layout.has_right {
+            *columns.last_mut().unwrap() = vec![void_cell(); space_size[1]];
+        }
+
+        let tile = SpaceDomain::new(columns, space_domain.delta_space(), space_domain.gamma());
+        let gathered_cells = (communicator.rank() == 0).then(|| space_domain.cells());
+
+        let simulation = Simulation::from_preset(SimulationPreset {
+            space_domain: tile,
+            delta_time: preset.delta_time,
+            acceleration: preset.acceleration,
+            reynolds: preset.reynolds,
+            events: EventSchedule::new(),
+            obstacles: Vec::new(),
+            dye_buoyancy: preset.dye_buoyancy,
+        });
+
+        Self {
+            communicator,
+            layout,
+            simulation,
+            space_size,
+            gathered_cells,
+        }
+    }
+
+    pub fn rank(&self) -> usize {
+        self.communicator.rank()
+    }
+
+    pub fn time(&self) -> f32 {
+        self.simulation.time()
+    }
+
+    // Size of the whole domain
+    pub fn space_size(&self) -> [usize; 2] {
+        self.space_size
+    }
+
+    // Global columns owned by this process
+    pub fn owned_columns(&self) -> Range<usize> {
+        self.layout.owned.clone()
+    }
+
+    // The tile including ghost columns, its x = 0 is global column local_columns().start
+    pub fn tile(&self) -> &Simulation {
+        &self.simulation
+    }
+
+    pub fn local_columns(&self) -> Range<usize> {
+        self.layout.local.clone()
+    }
+
+    // Collective, every process has to call it
+    pub fn iterate_one_timestep(&mut self) {
+        let mut halo = TileHalo {
+            communicator: &self.communicator,
+            layout: &self.layout,
+        };
+        self.simulation.step(&mut halo);
+    }
+
+    // Collective, returns the whole domain on rank 0 and None elsewhere
+    pub fn gather(&mut self) -> Option<Checkpoint> {
+        let space_domain = self.simulation.space_domain();
+        let owned: Vec<f32> = self
+            .layout
+            .owned
+            .clone()
+            .flat_map(|x| pack_column(space_domain, self.layout.to_local(x)))
+            .collect();
+
+        let Some(cells) = self.gathered_cells.as_mut() else {
+            self.communicator.send(0, owned);
+            return None;
+        };
+
+        let size = self.communicator.size();
+        for rank in 0..size {
+            let values = if rank == 0 {
+                owned.clone()
+            } else {
+                self.communicator.receive(rank)
+            };
+            let layout = TileLayout::new(rank, size, self.space_size[0]);
+            let strip = &mut cells
+                [layout.owned.start * self.space_size[1]..layout.owned.end * self.space_size[1]];
+            for (cell, values) in strip.iter_mut().zip(values.chunks(VALUES_PER_CELL)) {
+                unpack_cell(cell, values);
+            }
+        }
+
+        Some(Checkpoint {
+            time: self.simulation.time(),
+            space_size: self.space_size,
+            cells: cells.clone(),
+            metadata: self.simulation.metadata().cloned(),
+        })
+    }
+}
+
+fn void_cell() -> Cell {
+    Cell {
+        cell_type: CellType::VoidCell,
+        ..Cell::default()
+    }
+}
+
+fn pack_column(space_domain: &SpaceDomain, x: usize) -> Vec<f32> {
+    (0..space_domain.space_size()[1])
+        .flat_map(|y| {
+            let cell = space_domain.get_cell(x, y);
+            [
+                cell.velocity[0],
+                cell.velocity[1],
+                cell.pressure,
+                cell.rhs,
+                cell.f,
+                cell.g,
+                cell.psi,
+                cell.dye,
+            ]
+        })
+        .collect()
+}
+
+fn unpack_column(space_domain: &mut SpaceDomain, x: usize, column: &[f32]) {
+    for (y, values) in column.chunks(VALUES_PER_CELL).enumerate() {
+        let mut cell = space_domain.get_cell(x, y);
+        unpack_cell(&mut cell, values);
+        space_domain.set_cell(x, y, cell);
+    }
+}
+
+fn unpack_cell(cell: &mut Cell, values: &[f32]) {
+    cell.velocity = [values[0], values[1]];
+    cell.pressure = values[2];
+    cell.rhs = values[3];
+    cell.f = values[4];
+    cell.g = values[5];
+    cell.psi = values[6];
+    cell.dye = values[7];
+}
@@ -0,0 +1,245 @@
+use crate::cell::{BoundaryConditionCell, CellType};
+use crate::solver::FluidSolver;
+
+use std::io::{self, Write};
+
+// Kinetic energy and its sources and sinks in one state of a staggered grid solver,
+// per unit depth with density 1 and viscosity 1 / reynolds as in the momentum
+// equations. Faces between two fluid cells count fully, faces between a fluid cell
+// and a boundary half, as the cell centred energy of the MAC scheme.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EnergyState {
+    pub time: f32,           // seconds
+    pub kinetic_energy: f32, // meters^4/seconds^2
+    // Rates in meters^4/seconds^3, dissipation is removed, the others added
+    pub dissipation: f32,
+    pub body_force_work: f32,
+    // Kinetic energy carried in, pressure work and viscous work of moving walls
+    pub boundary_flux: f32,
+}
+
+impl EnergyState {
+    pub fn from_solver(solver: &dyn FluidSolver, reynolds: f32, acceleration: [f32; 2]) -> Self {
+        let mut state = Self {
+            time: solver.time(),
+            ..Self::default()
+        };
+        for component in 0..2 {
+            state.add_component(solver, component, 1.0 / reynolds, acceleration);
+        }
+        state
+    }
+
+    // Terms of the faces carrying velocity component, 0 for u on right faces and 1
+    // for v on top faces
+    fn add_component(
+        &mut self,
+        solver: &dyn FluidSolver,
+        component: usize,
+        viscosity: f32,
+        acceleration: [f32; 2],
+    ) {
+        let size = solver.space_size();
+        let delta = solver.delta_space();
+        let area = delta[0] * delta[1];
+        let fields = solver.fields();
+        let velocities = if component == 0 { &fields.u } else { &fields.v };
+        // Direction along the faces
+        let tangent = 1 - component;
+
+        let index = |position: [usize; 2]| position[0] * size[1] + position[1];
+        let cell_type = |position: [usize; 2]| fields.cell_type[index(position)];
+        let is_fluid = |position: [usize; 2]| matches!(cell_type(position), CellType::FluidCell);
+        let shifted = |mut position: [usize; 2], axis: usize| {
+            position[axis] += 1;
+            position
+        };
+        // Share of a face in the fluid, the face between cells position and the next
+        let weight = |position: [usize; 2]| {
+            (is_fluid(position) as u8 + is_fluid(shifted(position, component)) as u8) as f32 / 2.0
+        };
+
+        for x in 0..size[0] {
+            for y in 0..size[1] {
+                let position = [x, y];
+                let velocity = velocities[index(position)];
+
+                // Normal derivative inside fluid cells, their faces set by the solver
+                if is_fluid(position) {
+                    let mut previous = position;
+                    previous[component] -= 1;
+                    let gradient = (velocity - velocities[index(previous)]) / delta[component];
+                    self.dissipation += viscosity * gradient.powi(2) * area;
+                }
+
+                if position[component] + 1 >= size[component] {
+                    continue;
+                }
+                let face_weight = weight(position);
+                let next = shifted(position, component);
+
+                if face_weight > 0.0 {
+                    self.kinetic_energy += 0.5 * face_weight * velocity.powi(2) * area;
+                    self.body_force_work += face_weight * acceleration[component] * velocity * area;
+                }
+                // Open boundaries and walls between a fluid cell and a boundary cell
+                if face_weight == 0.5 {
+                    let (fluid, inward) = if is_fluid(next) {
+                        (next, velocity)
+                    } else {
+                        (position, -velocity)
+                    };
+                    let tangential = solver.get_centered_velocity(fluid[0], fluid[1])[tangent];
+                    let energy = 0.5 * (velocity.powi(2) + tangential.powi(2))
+                        + fields.pressure[index(fluid)];
+                    self.boundary_flux += energy * inward * delta[tangent];
+                }
+
+                // Tangential derivative between this face and the next one along it
+                let neighbor = shifted(position, tangent);
+                if neighbor[tangent] >= size[tangent] {
+                    continue;
+                }
+                let neighbor_weight = weight(neighbor);
+                let neighbor_velocity = velocities[index(neighbor)];
+                if face_weight > 0.0 && neighbor_weight > 0.0 {
+                    let gradient = (neighbor_velocity - velocity) / delta[tangent];
+                    self.dissipation += viscosity * gradient.powi(2) * area;
+                } else if face_weight > 0.0 || neighbor_weight > 0.0 {
+                    // A wall half a cell from the fluid face
+                    let (fluid_face, ghost_face, ghost_velocity, fluid_velocity) =
+                        if face_weight > 0.0 {
+                            (position, neighbor, neighbor_velocity, velocity)
+                        } else {
+                            (neighbor, position, velocity, neighbor_velocity)
+                        };
+                    let wall = if is_fluid(fluid_face) {
+                        ghost_face
+                    } else {
+                        shifted(ghost_face, component)
+                    };
+                    let wall_velocity = match cell_type(wall) {
+                        CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                            boundary_condition_velocity,
+                        }) => boundary_condition_velocity[component],
+                        // No shear
+                        CellType::BoundaryConditionCell(
+                            BoundaryConditionCell::FreeSlipCell
+                            | BoundaryConditionCell::OutFlowCell,
+                        ) => continue,
+                        // The ghost face mirrors around the boundary value
+                        _ => (fluid_velocity + ghost_velocity) / 2.0,
+                    };
+                    let gradient = (wall_velocity - fluid_velocity) / (delta[tangent] / 2.0);
+                    self.dissipation += viscosity * gradient.powi(2) * area / 2.0;
+                    self.boundary_flux += viscosity * wall_velocity * gradient * delta[component];
+                }
+            }
+        }
+    }
+}
+
+// Energy balance over one timestep. The rates are averaged over the start and end of
+// the step, what the balance misses is removed by the discretization.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnergyBudget {
+    pub time: f32,       // seconds, at the end of the step
+    pub delta_time: f32, // seconds
+    pub kinetic_energy: f32,
+    // Change of the kinetic energy over the step divided by delta_time
+    pub kinetic_energy_rate: f32,
+    pub dissipation: f32,
+    pub body_force_work: f32,
+    pub boundary_flux: f32,
+    // body_force_work + boundary_flux - dissipation - kinetic_energy_rate, positive
+    // when the scheme dissipates on top of the viscosity
+    pub numerical_dissipation: f32,
+}
+
+impl EnergyBudget {
+    pub fn between(start: &EnergyState, end: &EnergyState) -> Self {
+        let delta_time = end.time - start.time;
+        assert!(delta_time > 0.0, "energy states must be in time order");
+        let average = |value: fn(&EnergyState) -> f32| (value(start) + value(end)) / 2.0;
+
+        let kinetic_energy_rate = (end.kinetic_energy - start.kinetic_energy) / delta_time;
+        let dissipation = average(|state| state.dissipation);
+        let body_force_work = average(|state| state.body_force_work);
+        let boundary_flux = average(|state| state.boundary_flux);
+        Self {
+            time: end.time,
+            delta_time,
+            kinetic_energy: end.kinetic_energy,
+            kinetic_energy_rate,
+            dissipation,
+            body_force_work,
+            boundary_flux,
+            numerical_dissipation: body_force_work + boundary_flux
+                - dissipation
+                - kinetic_energy_rate,
+        }
+    }
+}
+
+// Budget of every recorded timestep
+#[derive(Default)]
+pub struct EnergyBudgetTracker {
+    previous: Option<EnergyState>,
+    budgets: Vec<EnergyBudget>,
+}
+
+impl EnergyBudgetTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Call with the initial state and after every timestep, returns the budget of
+    // the step since the previous call
+    pub fn record(
+        &mut self,
+        solver: &dyn FluidSolver,
+        reynolds: f32,
+        acceleration: [f32; 2],
+    ) -> Option<&EnergyBudget> {
+        let state = EnergyState::from_solver(solver, reynolds, acceleration);
+        let previous = self.previous.replace(state)?;
+        self.budgets.push(EnergyBudget::between(&previous, &state));
+        self.budgets.last()
+    }
+
+    pub fn budgets(&self) -> &[EnergyBudget] {
+        &self.budgets
+    }
+
+    pub fn latest(&self) -> Option<&EnergyBudget> {
+        self.budgets.last()
+    }
+
+    // Start over, the next record is an initial state again
+    pub fn clear(&mut self) {
+        self.previous = None;
+        self.budgets.clear();
+    }
+
+    pub fn write_csv(&self, file: &mut impl Write) -> io::Result<()> {
+        writeln!(
+            file,
+            "time,delta_time,kinetic_energy,kinetic_energy_rate,dissipation,body_force_work,boundary_flux,numerical_dissipation"
+        )?;
+        for budget in self.budgets.iter() {
+            writeln!(
+                file,
+                "{},{},{},{},{},{},{},{}",
+                budget.time,
+                budget.delta_time,
+                budget.kinetic_energy,
+                budget.kinetic_energy_rate,
+                budget.dissipation,
+                budget.body_force_work,
+                budget.boundary_flux,
+                budget.numerical_dissipation
+            )?;
+        }
+        Ok(())
+    }
+}
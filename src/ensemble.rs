@@ -0,0 +1,302 @@
+use crate::cell::CellFlags;
+use crate::experiments::characteristic_scales;
+use crate::forces;
+use crate::inflow_turbulence::InflowTurbulence;
+use crate::perturbation::{Perturbation, SplitMix64};
+use crate::presets::PresetFn;
+use crate::run_controller::RunController;
+use crate::shedding::{self, SheddingAnalysis};
+use crate::simulation::Simulation;
+use crate::solver::FluidSolver;
+use crate::space_domain::SpaceDomain;
+
+use rayon::prelude::*;
+
+// What sets one copy of the scene apart from the others
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Member {
+    // Seeds the perturbation and the inflow turbulence of the member
+    pub seed: u64,
+    pub reynolds: f32,
+    // Factor on the inflow velocity of the preset
+    pub inflow_scale: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberResult {
+    pub member: Member,
+    // (time, [drag, lift]) after every step, per unit depth
+    pub forces: Vec<(f32, [f32; 2])>,
+    // From the lift, None without obstacle, inflow or periodic shedding
+    pub shedding: Option<SheddingAnalysis>,
+}
+
+// Per cell mean and unbiased variance over the members, in cell order
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldStatistics {
+    pub mean: Vec<f32>,
+    pub variance: Vec<f32>,
+}
+
+// Spread of a quantity over the members after every step
+#[derive(Debug, Clone, PartialEq)]
+pub struct Envelope {
+    // Mean over the members, they differ with adaptive timesteps
+    pub time: Vec<f32>,
+    pub min: Vec<f32>,
+    pub mean: Vec<f32>,
+    pub max: Vec<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnsembleResult {
+    // In the order of Ensemble::members
+    pub members: Vec<MemberResult>,
+    // Of the final fields
+    pub u: FieldStatistics,
+    pub v: FieldStatistics,
+    pub pressure: FieldStatistics,
+    pub drag: Envelope,
+    pub lift: Envelope,
+}
+
+impl EnsembleResult {
+    // Fraction of the members that shed vortices
+    pub fn shedding_fraction(&self) -> f32 {
+        let shedding = self
+            .members
+            .iter()
+            .filter(|result| result.shedding.is_some())
+            .count();
+        shedding as f32 / self.members.len() as f32
+    }
+}
+
+// Runs copies of a scene that differ in their random seeds and jittered parameters
+// for the same number of steps, to tell how sensitive the flow is to them.
+pub struct Ensemble {
+    preset: PresetFn,
+    space_size: [usize; 2],
+    members: usize,
+    steps: usize,
+    seed: u64,
+    // Seed replaced per member
+    perturbation: Option<Perturbation>,
+    inflow_turbulence: Option<InflowTurbulence>,
+    // Relative standard deviations, zero for the value of the preset
+    reynolds_jitter: f32,
+    inflow_jitter: f32,
+    is_parallel: bool,
+}
+
+impl Ensemble {
+    pub fn new(preset: PresetFn, space_size: [usize; 2], members: usize, steps: usize) -> Self {
+        assert!(members > 0, "an ensemble needs members");
+        assert!(steps > 0, "steps must be positive");
+        Self {
+            preset,
+            space_size,
+            members,
+            steps,
+            seed: 0,
+            perturbation: None,
+            inflow_turbulence: None,
+            reynolds_jitter: 0.0,
+            inflow_jitter: 0.0,
+            is_parallel: false,
+        }
+    }
+
+    // The member seeds and jitters are drawn from it, the same seed gives the same
+    // ensemble
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn perturbation(mut self, perturbation: Option<Perturbation>) -> Self {
+        self.perturbation = perturbation;
+        self
+    }
+
+    pub fn inflow_turbulence(mut self, inflow_turbulence: Option<InflowTurbulence>) -> Self {
+        self.inflow_turbulence = inflow_turbulence;
+        self
+    }
+
+    pub fn reynolds_jitter(mut self, reynolds_jitter: f32) -> Self {
+        assert!(reynolds_jitter >= 0.0, "jitter must not be negative");
+        self.reynolds_jitter = reynolds_jitter;
+        self
+    }
+
+    pub fn inflow_jitter(mut self, inflow_jitter: f32) -> Self {
+        assert!(inflow_jitter >= 0.0, "jitter must not be negative");
+        self.inflow_jitter = inflow_jitter;
+        self
+    }
+
+    // Run the members on the rayon thread pool
+    pub fn parallel(mut self, is_parallel: bool) -> Self {
+        self.is_parallel = is_parallel;
+        self
+    }
+
+    pub fn members(&self) -> Vec<Member> {
+        let reynolds = (self.preset)(self.space_size).reynolds;
+        let mut rng = SplitMix64::new(self.seed);
+        (0..self.members)
+            .map(|_| {
+                let seed = rng.next_u64();
+                let mut jitter = SplitMix64::new(seed);
+                Member {
+                    seed,
+                    reynolds: reynolds * (1.0 + self.reynolds_jitter * jitter.next_normal()),
+                    inflow_scale: 1.0 + self.inflow_jitter * jitter.next_normal(),
+                }
+            })
+            .collect()
+    }
+
+    pub fn run(&self) -> EnsembleResult {
+        let members = self.members();
+        let runs: Vec<(MemberResult, Simulation)> = if self.is_parallel {
+            members
+                .par_iter()
+                .map(|member| self.run_member(member))
+                .collect()
+        } else {
+            members
+                .iter()
+                .map(|member| self.run_member(member))
+                .collect()
+        };
+
+        let final_fields = |field: fn(&Simulation) -> &[f32]| {
+            field_statistics(runs.iter().map(|(_, simulation)| field(simulation)))
+        };
+        let u = final_fields(|simulation| &simulation.fields().u);
+        let v = final_fields(|simulation| &simulation.fields().v);
+        let pressure = final_fields(|simulation| &simulation.fields().pressure);
+        let results: Vec<MemberResult> = runs.into_iter().map(|(result, _)| result).collect();
+        EnsembleResult {
+            drag: envelope(&results, 0),
+            lift: envelope(&results, 1),
+            members: results,
+            u,
+            v,
+            pressure,
+        }
+    }
+
+    // The member's result and its simulation at the end of the run
+    pub fn run_member(&self, member: &Member) -> (MemberResult, Simulation) {
+        let mut preset = (self.preset)(self.space_size);
+        scale_inflow(&mut preset.space_domain, member.inflow_scale);
+        if let Some(perturbation) = self.perturbation {
+            preset = preset.perturbed(&Perturbation {
+                seed: member.seed,
+                ..perturbation
+            });
+        }
+        let mut simulation = Simulation::from_preset(preset);
+        simulation.set_reynolds(member.reynolds);
+        if let Some(turbulence) = self.inflow_turbulence {
+            simulation.set_inflow_turbulence(Some(InflowTurbulence {
+                seed: member.seed,
+                ..turbulence
+            }));
+        }
+        let scales = characteristic_scales(&simulation);
+
+        let mut forces = Vec::with_capacity(self.steps);
+        let mut controller = RunController::new()
+            .max_steps(self.steps)
+            .on_step(|solver| {
+                let force = forces::obstacle_force(
+                    solver.fields(),
+                    solver.space_size(),
+                    solver.delta_space(),
+                    member.reynolds,
+                );
+                forces.push((solver.time(), force));
+            });
+        controller.run(&mut simulation);
+        drop(controller);
+
+        let lift: Vec<(f32, f32)> = forces
+            .iter()
+            .map(|&(time, [_, lift])| (time, lift))
+            .collect();
+        let shedding =
+            scales.and_then(|[length, velocity]| shedding::analyze_lift(&lift, length, velocity));
+        let result = MemberResult {
+            member: *member,
+            forces,
+            shedding,
+        };
+        (result, simulation)
+    }
+}
+
+fn scale_inflow(space_domain: &mut SpaceDomain, factor: f32) {
+    let boundary_cells = space_domain.boundary_cells().to_vec();
+    let (fields, flags) = space_domain.fields_and_flags_mut();
+    for index in boundary_cells {
+        if flags[index].contains(CellFlags::INFLOW) {
+            fields.u[index] *= factor;
+            fields.v[index] *= factor;
+        }
+    }
+}
+
+fn field_statistics<'a>(fields: impl Iterator<Item = &'a [f32]> + Clone) -> FieldStatistics {
+    let count = fields.clone().count();
+    let len = fields.clone().next().map_or(0, |field| field.len());
+    let mut mean = vec![0.0; len];
+    for field in fields.clone() {
+        for (mean, value) in mean.iter_mut().zip(field) {
+            *mean += value / count as f32;
+        }
+    }
+    let mut variance = vec![0.0; len];
+    if count > 1 {
+        for field in fields {
+            for ((variance, mean), value) in variance.iter_mut().zip(&mean).zip(field) {
+                *variance += (value - mean).powi(2) / (count - 1) as f32;
+            }
+        }
+    }
+    FieldStatistics { mean, variance }
+}
+
+// Over the steps every member reached
+fn envelope(results: &[MemberResult], component: usize) -> Envelope {
+    let steps = results
+        .iter()
+        .map(|result| result.forces.len())
+        .min()
+        .unwrap_or(0);
+    let count = results.len() as f32;
+    let mut envelope = Envelope {
+        time: Vec::with_capacity(steps),
+        min: Vec::with_capacity(steps),
+        mean: Vec::with_capacity(steps),
+        max: Vec::with_capacity(steps),
+    };
+    for step in 0..steps {
+        let samples = results.iter().map(|result| result.forces[step]);
+        envelope
+            .time
+            .push(samples.clone().map(|(time, _)| time).sum::<f32>() / count);
+        let values = samples.map(|(_, force)| force[component]);
+        envelope
+            .min
+            .push(values.clone().fold(f32::INFINITY, f32::min));
+        envelope
+            .max
+            .push(values.clone().fold(f32::NEG_INFINITY, f32::max));
+        envelope.mean.push(values.sum::<f32>() / count);
+    }
+    envelope
+}
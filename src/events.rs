@@ -0,0 +1,66 @@
+use crate::simulation::SurfaceWind;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    // Velocity of every inflow cell
+    SetInflowVelocity([f32; 2]),
+    SetObstacle {
+        x: usize,
+        y: usize,
+        is_obstacle: bool,
+    },
+    // Gravity or any other uniform body acceleration, meters/seconds^2
+    SetAcceleration([f32; 2]),
+    // Wind over the free slip tops, None calms it
+    SetSurfaceWind(Option<SurfaceWind>),
+    InjectDye {
+        position: [f32; 2], // meters
+        radius: f32,        // meters
+        amount: f32,
+    },
+}
+
+// Events sorted by time, each one fires once at the start of the timestep closest to its time
+#[derive(Debug, Clone, Default)]
+pub struct EventSchedule {
+    events: Vec<(f32, Event)>,
+    next_event: usize,
+}
+
+impl EventSchedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, time: f32, event: Event) {
+        // Events at the same time keep their insertion order
+        let index = self.events.partition_point(|(t, _)| *t <= time);
+        self.events
+            .insert(index.max(self.next_event), (time, event));
+    }
+
+    pub fn with_event(mut self, time: f32, event: Event) -> Self {
+        self.add(time, event);
+        self
+    }
+
+    pub fn events(&self) -> &[(f32, Event)] {
+        &self.events
+    }
+
+    pub fn pending_events(&self) -> &[(f32, Event)] {
+        &self.events[self.next_event..]
+    }
+
+    // Mark every event up to and including time as fired and return them
+    pub fn take_due(&mut self, time: f32) -> Vec<Event> {
+        let start = self.next_event;
+        while self.next_event < self.events.len() && self.events[self.next_event].0 <= time {
+            self.next_event += 1;
+        }
+        self.events[start..self.next_event]
+            .iter()
+            .map(|(_, event)| *event)
+            .collect()
+    }
+}
@@ -0,0 +1,286 @@
+use crate::cell::{BoundaryConditionCell, CellType};
+use crate::forces;
+use crate::presets::PresetFn;
+use crate::run_controller::{RunController, StopReason};
+use crate::simulation::{AdvectionScheme, Simulation};
+
+use rayon::prelude::*;
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+// Upward zero crossings of the lift needed for a shedding frequency
+const MIN_CROSSINGS: usize = 3;
+// Smaller lift oscillations, relative to the drag, count as steady flow
+const MIN_LIFT_AMPLITUDE: f32 = 1e-3;
+
+// One combination of the swept parameters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Case {
+    pub reynolds: f32,
+    pub delta_time: f32, // seconds
+    pub space_size: [usize; 2],
+    pub advection_scheme: AdvectionScheme,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaseResult {
+    pub case: Case,
+    pub reason: StopReason,
+    pub steps: usize,
+    pub time: f32, // seconds
+    pub wall_time: Duration,
+    // Averaged over the second half of the run, per unit depth
+    pub mean_drag: f32,
+    pub mean_lift: f32,
+    // From the lift oscillation in the second half of the run, None without
+    // obstacle, inflow or enough oscillations
+    pub strouhal: Option<f32>,
+    // Steps until the steady state tolerance was met
+    pub steady_state_steps: Option<usize>,
+}
+
+// Runs the cartesian product of the parameter lists headlessly. An empty list keeps
+// the value of the preset.
+pub struct Experiment {
+    preset: PresetFn,
+    default_space_size: [usize; 2],
+    reynolds: Vec<f32>,
+    delta_times: Vec<f32>,
+    space_sizes: Vec<[usize; 2]>,
+    advection_schemes: Vec<AdvectionScheme>,
+    max_steps: usize,
+    steady_state_tolerance: Option<f32>,
+    is_parallel: bool,
+}
+
+impl Experiment {
+    pub fn new(preset: PresetFn, default_space_size: [usize; 2], max_steps: usize) -> Self {
+        assert!(max_steps > 0, "max steps must be positive");
+        Self {
+            preset,
+            default_space_size,
+            reynolds: Vec::new(),
+            delta_times: Vec::new(),
+            space_sizes: Vec::new(),
+            advection_schemes: Vec::new(),
+            max_steps,
+            steady_state_tolerance: None,
+            is_parallel: false,
+        }
+    }
+
+    pub fn reynolds(mut self, reynolds: &[f32]) -> Self {
+        self.reynolds = reynolds.to_vec();
+        self
+    }
+
+    pub fn delta_times(mut self, delta_times: &[f32]) -> Self {
+        self.delta_times = delta_times.to_vec();
+        self
+    }
+
+    pub fn space_sizes(mut self, space_sizes: &[[usize; 2]]) -> Self {
+        self.space_sizes = space_sizes.to_vec();
+        self
+    }
+
+    pub fn advection_schemes(mut self, advection_schemes: &[AdvectionScheme]) -> Self {
+        self.advection_schemes = advection_schemes.to_vec();
+        self
+    }
+
+    // Stop a case early once it stops changing, see RunController::steady_state
+    pub fn steady_state(mut self, tolerance: f32) -> Self {
+        self.steady_state_tolerance = Some(tolerance);
+        self
+    }
+
+    // Run the cases on the rayon thread pool
+    pub fn parallel(mut self, is_parallel: bool) -> Self {
+        self.is_parallel = is_parallel;
+        self
+    }
+
+    pub fn cases(&self) -> Vec<Case> {
+        let space_sizes = or_default(&self.space_sizes, self.default_space_size);
+        let mut cases = Vec::new();
+        for space_size in space_sizes {
+            let preset = (self.preset)(space_size);
+            for reynolds in or_default(&self.reynolds, preset.reynolds) {
+                for delta_time in or_default(&self.delta_times, preset.delta_time) {
+                    for advection_scheme in
+                        or_default(&self.advection_schemes, AdvectionScheme::default())
+                    {
+                        cases.push(Case {
+                            reynolds,
+                            delta_time,
+                            space_size,
+                            advection_scheme,
+                        });
+                    }
+                }
+            }
+        }
+        cases
+    }
+
+    // Results in the order of cases()
+    pub fn run(&self) -> Vec<CaseResult> {
+        let cases = self.cases();
+        if self.is_parallel {
+            cases.par_iter().map(|case| self.run_case(case)).collect()
+        } else {
+            cases.iter().map(|case| self.run_case(case)).collect()
+        }
+    }
+
+    pub fn run_case(&self, case: &Case) -> CaseResult {
+        let mut simulation = Simulation::from_preset((self.preset)(case.space_size));
+        simulation.set_reynolds(case.reynolds);
+        simulation.set_delta_time(case.delta_time);
+        simulation.set_advection_scheme(case.advection_scheme);
+        let scales = characteristic_scales(&simulation);
+
+        // (time, [drag, lift]) after every step
+        let mut forces = Vec::with_capacity(self.max_steps);
+        let start = Instant::now();
+        let mut controller = RunController::new()
+            .max_steps(self.max_steps)
+            .on_step(|solver| {
+                let force = forces::obstacle_force(
+                    solver.fields(),
+                    solver.space_size(),
+                    solver.delta_space(),
+                    case.reynolds,
+                );
+                forces.push((solver.time(), force));
+            });
+        if let Some(tolerance) = self.steady_state_tolerance {
+            controller = controller.steady_state(tolerance);
+        }
+        let report = controller.run(&mut simulation);
+        drop(controller);
+
+        let second_half = &forces[forces.len() / 2..];
+        let mean = |component: usize| {
+            second_half
+                .iter()
+                .map(|(_, force)| force[component])
+                .sum::<f32>()
+                / second_half.len() as f32
+        };
+        let (mean_drag, mean_lift) = (mean(0), mean(1));
+        let strouhal = shedding_frequency(second_half, mean_drag, mean_lift)
+            .zip(scales)
+            .map(|(frequency, [length, velocity])| frequency * length / velocity);
+
+        CaseResult {
+            case: *case,
+            reason: report.reason,
+            steps: report.progress.steps,
+            time: report.progress.time,
+            wall_time: start.elapsed(),
+            mean_drag,
+            mean_lift,
+            strouhal,
+            steady_state_steps: (report.reason == StopReason::SteadyState)
+                .then_some(report.progress.steps),
+        }
+    }
+}
+
+// One row per case
+pub fn write_csv(file: &mut impl Write, results: &[CaseResult]) -> io::Result<()> {
+    writeln!(
+        file,
+        "reynolds,delta_time,nx,ny,advection_scheme,reason,steps,time,wall_seconds,mean_drag,mean_lift,strouhal,steady_state_steps"
+    )?;
+    for result in results {
+        let case = result.case;
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        writeln!(
+            file,
+            "{},{},{},{},{:?},{:?},{},{},{},{},{},{},{}",
+            case.reynolds,
+            case.delta_time,
+            case.space_size[0],
+            case.space_size[1],
+            case.advection_scheme,
+            result.reason,
+            result.steps,
+            result.time,
+            result.wall_time.as_secs_f32(),
+            result.mean_drag,
+            result.mean_lift,
+            optional(result.strouhal.map(|strouhal| strouhal.to_string())),
+            optional(result.steady_state_steps.map(|steps| steps.to_string())),
+        )?;
+    }
+    Ok(())
+}
+
+// Obstacle height and mean inflow speed, None without obstacle or inflow
+pub(crate) fn characteristic_scales(simulation: &Simulation) -> Option<[f32; 2]> {
+    let [nx, ny] = simulation.space_size();
+    let delta_space = simulation.delta_space();
+
+    let obstacle_rows = (1..ny - 1)
+        .filter(|&y| {
+            (1..nx - 1).any(|x| {
+                matches!(
+                    simulation.get_cell(x, y).cell_type,
+                    CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell { .. })
+                )
+            })
+        })
+        .count();
+
+    let inflow_velocities: Vec<f32> = (0..nx)
+        .flat_map(|x| (0..ny).map(move |y| (x, y)))
+        .map(|(x, y)| simulation.get_cell(x, y))
+        .filter(|cell| {
+            matches!(
+                cell.cell_type,
+                CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell)
+            )
+        })
+        .map(|cell| (cell.velocity[0].powi(2) + cell.velocity[1].powi(2)).sqrt())
+        .collect();
+
+    if obstacle_rows == 0 || inflow_velocities.is_empty() {
+        return None;
+    }
+    let velocity = inflow_velocities.iter().sum::<f32>() / inflow_velocities.len() as f32;
+    (velocity > 0.0).then_some([obstacle_rows as f32 * delta_space[1], velocity])
+}
+
+// Frequency from the upward crossings of the mean lift. The lift has to dip below
+// the noise floor between crossings, so noise around the mean is not counted.
+fn shedding_frequency(forces: &[(f32, [f32; 2])], mean_drag: f32, mean_lift: f32) -> Option<f32> {
+    let noise_floor = MIN_LIFT_AMPLITUDE * mean_drag.abs();
+
+    let mut crossings = Vec::new();
+    let mut is_below = false;
+    for &(time, [_, lift]) in forces {
+        if lift < mean_lift - noise_floor {
+            is_below = true;
+        } else if is_below && lift >= mean_lift {
+            is_below = false;
+            crossings.push(time);
+        }
+    }
+    if crossings.len() < MIN_CROSSINGS {
+        return None;
+    }
+    let periods = (crossings.len() - 1) as f32;
+    Some(periods / (crossings.last().unwrap() - crossings.first().unwrap()))
+}
+
+fn or_default<T: Copy>(values: &[T], default: T) -> Vec<T> {
+    if values.is_empty() {
+        vec![default]
+    } else {
+        values.to_vec()
+    }
+}
@@ -0,0 +1,224 @@
+// C interface for embedding the Navier-Stokes solver. include/flow2d.h declares it,
+// tests/ffi.rs checks the two agree. After changes the header can be regenerated with
+//     cbindgen --config cbindgen.toml --output include/flow2d.h src/ffi.rs
+// Handles are owned by the caller, every function accepts null and then does
+// nothing or reports Flow2dStatus::InvalidArgument.
+use crate::field::Field;
+use crate::parameters::Parameters;
+use crate::presets;
+use crate::simulation::Simulation;
+
+use std::ffi::{c_char, CStr};
+
+/// Opaque simulation handle
+pub struct Flow2dSimulation {
+    simulation: Simulation,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow2dStatus {
+    Ok = 0,
+    /// Null pointer, unknown name or field, or a parameter out of range
+    InvalidArgument = 1,
+    /// The buffer holds fewer values than the field
+    BufferTooSmall = 2,
+}
+
+/// Fields for flow2d_simulation_copy_field
+pub const FLOW2D_FIELD_PRESSURE: u32 = 0;
+pub const FLOW2D_FIELD_U: u32 = 1;
+pub const FLOW2D_FIELD_V: u32 = 2;
+pub const FLOW2D_FIELD_PSI: u32 = 3;
+
+/// Parameters that may change between timesteps, see Parameters
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Flow2dParameters {
+    pub reynolds: f32,
+    /// meters/seconds^2
+    pub acceleration: [f32; 2],
+    /// SOR relaxation of the pressure solve, 0 < omega < 2
+    pub omega: f32,
+    /// seconds
+    pub delta_time: f32,
+}
+
+fn preset_simulation(preset: *const c_char) -> Option<Simulation> {
+    if preset.is_null() {
+        return None;
+    }
+    // SAFETY: checked for null, the caller passes a NUL terminated string
+    let name = unsafe { CStr::from_ptr(preset) }.to_str().ok()?;
+    presets::by_name(name).map(Simulation::from_preset)
+}
+
+/// Creates a simulation from one of the presets by name, such as
+/// "lid_driven_cavity", null for an unknown name.
+///
+/// # Safety
+/// preset is null or a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn flow2d_simulation_create(preset: *const c_char) -> *mut Flow2dSimulation {
+    match preset_simulation(preset) {
+        Some(simulation) => Box::into_raw(Box::new(Flow2dSimulation { simulation })),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// simulation is null or a handle from flow2d_simulation_create that is not used
+/// afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn flow2d_simulation_destroy(simulation: *mut Flow2dSimulation) {
+    if !simulation.is_null() {
+        drop(Box::from_raw(simulation));
+    }
+}
+
+/// Replaces the state of the handle with a preset, the handle stays valid.
+///
+/// # Safety
+/// simulation is null or a live handle, preset is null or a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn flow2d_simulation_load_preset(
+    simulation: *mut Flow2dSimulation,
+    preset: *const c_char,
+) -> Flow2dStatus {
+    match (simulation.as_mut(), preset_simulation(preset)) {
+        (Some(handle), Some(simulation)) => {
+            handle.simulation = simulation;
+            Flow2dStatus::Ok
+        }
+        _ => Flow2dStatus::InvalidArgument,
+    }
+}
+
+/// # Safety
+/// simulation is null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn flow2d_simulation_step(simulation: *mut Flow2dSimulation, steps: u32) {
+    if let Some(handle) = simulation.as_mut() {
+        for _ in 0..steps {
+            handle.simulation.iterate_one_timestep();
+        }
+    }
+}
+
+/// Simulated time in seconds, 0 for null.
+///
+/// # Safety
+/// simulation is null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn flow2d_simulation_time(simulation: *const Flow2dSimulation) -> f32 {
+    simulation
+        .as_ref()
+        .map_or(0.0, |handle| handle.simulation.time())
+}
+
+/// Cells in x and y, fields hold width * height values.
+///
+/// # Safety
+/// simulation is null or a live handle, width and height are null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn flow2d_simulation_size(
+    simulation: *const Flow2dSimulation,
+    width: *mut usize,
+    height: *mut usize,
+) -> Flow2dStatus {
+    let (Some(handle), Some(width), Some(height)) =
+        (simulation.as_ref(), width.as_mut(), height.as_mut())
+    else {
+        return Flow2dStatus::InvalidArgument;
+    };
+    [*width, *height] = handle.simulation.space_size();
+    Flow2dStatus::Ok
+}
+
+/// Copies a FLOW2D_FIELD_* into buffer, value of cell (x, y) at x * height + y.
+///
+/// # Safety
+/// simulation is null or a live handle, buffer is null or holds length floats.
+#[no_mangle]
+pub unsafe extern "C" fn flow2d_simulation_copy_field(
+    simulation: *const Flow2dSimulation,
+    field: u32,
+    buffer: *mut f32,
+    length: usize,
+) -> Flow2dStatus {
+    let (Some(handle), Some(&field), false) = (
+        simulation.as_ref(),
+        Field::ALL.get(field as usize),
+        buffer.is_null(),
+    ) else {
+        return Flow2dStatus::InvalidArgument;
+    };
+    let values = handle.simulation.field(field).to_vec();
+    if length < values.len() {
+        return Flow2dStatus::BufferTooSmall;
+    }
+    std::slice::from_raw_parts_mut(buffer, values.len()).copy_from_slice(&values);
+    Flow2dStatus::Ok
+}
+
+/// # Safety
+/// simulation is null or a live handle, parameters is null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn flow2d_simulation_get_parameters(
+    simulation: *const Flow2dSimulation,
+    parameters: *mut Flow2dParameters,
+) -> Flow2dStatus {
+    let (Some(handle), Some(parameters)) = (simulation.as_ref(), parameters.as_mut()) else {
+        return Flow2dStatus::InvalidArgument;
+    };
+    let current = handle.simulation.parameters();
+    *parameters = Flow2dParameters {
+        reynolds: current.reynolds,
+        acceleration: current.acceleration,
+        omega: current.omega,
+        delta_time: current.delta_time,
+    };
+    Flow2dStatus::Ok
+}
+
+/// Applied from the next timestep on, out of range values leave the simulation
+/// unchanged.
+///
+/// # Safety
+/// simulation is null or a live handle, parameters is null or readable.
+#[no_mangle]
+pub unsafe extern "C" fn flow2d_simulation_set_parameters(
+    simulation: *mut Flow2dSimulation,
+    parameters: *const Flow2dParameters,
+) -> Flow2dStatus {
+    let (Some(handle), Some(parameters)) = (simulation.as_mut(), parameters.as_ref()) else {
+        return Flow2dStatus::InvalidArgument;
+    };
+    let parameters = Parameters {
+        reynolds: parameters.reynolds,
+        acceleration: parameters.acceleration,
+        inflow_velocity: None,
+        omega: parameters.omega,
+        delta_time: parameters.delta_time,
+    };
+    if parameters.check().is_err() {
+        return Flow2dStatus::InvalidArgument;
+    }
+    handle.simulation.set_parameters(parameters);
+    Flow2dStatus::Ok
+}
+
+/// Velocity of every inflow cell in meters/seconds.
+///
+/// # Safety
+/// simulation is null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn flow2d_simulation_set_inflow_velocity(
+    simulation: *mut Flow2dSimulation,
+    u: f32,
+    v: f32,
+) {
+    if let Some(handle) = simulation.as_mut() {
+        handle.simulation.set_inflow_velocity([u, v]);
+    }
+}
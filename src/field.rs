@@ -0,0 +1,338 @@
+use crate::cell::CellType;
+use crate::space_domain::Fields;
+
+use std::ops::Range;
+
+// Quantities stored per cell on the staggered grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    // Cell centre
+    Pressure,
+    // Right face
+    U,
+    // Top face
+    V,
+    // Top right corner
+    Psi,
+}
+
+// Where on the staggered grid the value of a cell lives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    CellCentre,
+    RightFace,
+    TopFace,
+    TopRightCorner,
+}
+
+impl Location {
+    // In cells from the bottom left corner of the cell
+    pub fn offset(&self) -> [f32; 2] {
+        match self {
+            Location::CellCentre => [0.5, 0.5],
+            Location::RightFace => [1.0, 0.5],
+            Location::TopFace => [0.5, 1.0],
+            Location::TopRightCorner => [1.0, 1.0],
+        }
+    }
+}
+
+impl Field {
+    pub const ALL: [Field; 4] = [Field::Pressure, Field::U, Field::V, Field::Psi];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Field::Pressure => "pressure",
+            Field::U => "u",
+            Field::V => "v",
+            Field::Psi => "psi",
+        }
+    }
+
+    pub fn location(&self) -> Location {
+        match self {
+            Field::Pressure => Location::CellCentre,
+            Field::U => Location::RightFace,
+            Field::V => Location::TopFace,
+            Field::Psi => Location::TopRightCorner,
+        }
+    }
+
+    // Where the value of cell (x, y) lives, in cells from its bottom left corner
+    pub fn offset(&self) -> [f32; 2] {
+        self.location().offset()
+    }
+
+    fn values<'a>(&self, fields: &'a Fields) -> &'a [f32] {
+        match self {
+            Field::Pressure => &fields.pressure,
+            Field::U => &fields.u,
+            Field::V => &fields.v,
+            Field::Psi => &fields.psi,
+        }
+    }
+}
+
+// Read only window onto one field, x and y count from the window's corner. Statistics
+// only look at fluid cells, the values of walls are boundary conditions.
+#[derive(Debug, Clone, Copy)]
+pub struct FieldView<'a> {
+    // None for derived values, like statistics, that aren't a stored field
+    field: Option<Field>,
+    location: Location,
+    values: &'a [f32],
+    cell_types: &'a [CellType],
+    // Column length of the underlying arrays
+    stride: usize,
+    origin: [usize; 2],
+    shape: [usize; 2],
+    delta_space: [f32; 2], // meters
+}
+
+impl<'a> FieldView<'a> {
+    pub fn new(
+        field: Field,
+        fields: &'a Fields,
+        space_size: [usize; 2],
+        delta_space: [f32; 2],
+    ) -> Self {
+        Self {
+            field: Some(field),
+            ..Self::from_values(
+                field.location(),
+                field.values(fields),
+                &fields.cell_type,
+                space_size,
+                delta_space,
+            )
+        }
+    }
+
+    // View onto any per cell values, like derived statistics, living at location
+    pub fn from_values(
+        location: Location,
+        values: &'a [f32],
+        cell_types: &'a [CellType],
+        space_size: [usize; 2],
+        delta_space: [f32; 2],
+    ) -> Self {
+        assert!(
+            values.len() == space_size[0] * space_size[1] && cell_types.len() == values.len(),
+            "cell count mismatch"
+        );
+        Self {
+            field: None,
+            location,
+            values,
+            cell_types,
+            stride: space_size[1],
+            origin: [0, 0],
+            shape: space_size,
+            delta_space,
+        }
+    }
+
+    pub fn field(&self) -> Option<Field> {
+        self.field
+    }
+
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    // Cells in x and y
+    pub fn shape(&self) -> [usize; 2] {
+        self.shape
+    }
+
+    pub fn delta_space(&self) -> [f32; 2] {
+        self.delta_space
+    }
+
+    // Size in meters
+    pub fn extent(&self) -> [f32; 2] {
+        [
+            self.shape[0] as f32 * self.delta_space[0],
+            self.shape[1] as f32 * self.delta_space[1],
+        ]
+    }
+
+    // Position of the value of cell (x, y) in meters, relative to the domain origin
+    pub fn position(&self, x: usize, y: usize) -> [f32; 2] {
+        let offset = self.location.offset();
+        [
+            ((self.origin[0] + x) as f32 + offset[0]) * self.delta_space[0],
+            ((self.origin[1] + y) as f32 + offset[1]) * self.delta_space[1],
+        ]
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> f32 {
+        self.values[self.index(x, y)]
+    }
+
+    pub fn is_fluid(&self, x: usize, y: usize) -> bool {
+        matches!(self.cell_types[self.index(x, y)], CellType::FluidCell)
+    }
+
+    // ([x, y], value) column by column, the order of the underlying arrays
+    pub fn iter(&self) -> impl Iterator<Item = ([usize; 2], f32)> + '_ {
+        (0..self.shape[0])
+            .flat_map(move |x| (0..self.shape[1]).map(move |y| ([x, y], self.get(x, y))))
+    }
+
+    pub fn fluid_iter(&self) -> impl Iterator<Item = ([usize; 2], f32)> + '_ {
+        self.iter().filter(|&([x, y], _)| self.is_fluid(x, y))
+    }
+
+    // Over fluid cells, None if there are none. NaN is ignored.
+    pub fn min(&self) -> Option<f32> {
+        self.range().map(|range| range[0])
+    }
+
+    pub fn max(&self) -> Option<f32> {
+        self.range().map(|range| range[1])
+    }
+
+    pub fn range(&self) -> Option<[f32; 2]> {
+        let mut fluid_values = self.fluid_iter().map(|(_, value)| value).peekable();
+        fluid_values.peek()?;
+        Some(
+            fluid_values.fold([f32::INFINITY, f32::NEG_INFINITY], |range, value| {
+                [range[0].min(value), range[1].max(value)]
+            }),
+        )
+    }
+
+    // Fluid cell holding the largest value with that value, None if there are none.
+    // NaN is ignored, ties go to the first cell in column order.
+    pub fn max_cell(&self) -> Option<([usize; 2], f32)> {
+        self.fluid_iter()
+            .filter(|(_, value)| !value.is_nan())
+            .fold(None, |max, (cell, value)| match max {
+                Some((_, max_value)) if max_value >= value => max,
+                _ => Some((cell, value)),
+            })
+    }
+
+    // Contiguous values of column x
+    pub fn column(&self, x: usize) -> &'a [f32] {
+        let start = self.index(x, 0);
+        &self.values[start..start + self.shape[1]]
+    }
+
+    pub fn row(&self, y: usize) -> impl Iterator<Item = f32> + '_ {
+        (0..self.shape[0]).map(move |x| self.get(x, y))
+    }
+
+    // Window over the cells x and y of this view
+    pub fn slice(&self, x: Range<usize>, y: Range<usize>) -> FieldView<'a> {
+        assert!(
+            x.start <= x.end
+                && x.end <= self.shape[0]
+                && y.start <= y.end
+                && y.end <= self.shape[1],
+            "slice {x:?}, {y:?} outside a field of {:?} cells",
+            self.shape
+        );
+        FieldView {
+            origin: [self.origin[0] + x.start, self.origin[1] + y.start],
+            shape: [x.len(), y.len()],
+            ..*self
+        }
+    }
+
+    // Box average over blocks of factor x factor cells of the view, for thumbnails and
+    // coarse analysis. Only fluid cells count, blocks reaching past the view's edge
+    // average the cells they have.
+    pub fn downsample(&self, factor: usize) -> DownsampledField {
+        assert!(factor > 0, "downsampling factor must be positive");
+        let shape = self.shape.map(|cells| cells.div_ceil(factor));
+        let mut values = Vec::with_capacity(shape[0] * shape[1]);
+        let mut fluid_fraction = Vec::with_capacity(shape[0] * shape[1]);
+        let mut cell_types = Vec::with_capacity(shape[0] * shape[1]);
+        for block_x in 0..shape[0] {
+            for block_y in 0..shape[1] {
+                let xs = block_x * factor..((block_x + 1) * factor).min(self.shape[0]);
+                let ys = block_y * factor..((block_y + 1) * factor).min(self.shape[1]);
+                let cells = xs.len() * ys.len();
+                let (sum, fluid_cells) = xs
+                    .clone()
+                    .flat_map(|x| ys.clone().map(move |y| (x, y)))
+                    .filter(|&(x, y)| self.is_fluid(x, y))
+                    .fold((0.0f64, 0), |(sum, count), (x, y)| {
+                        (sum + self.get(x, y) as f64, count + 1)
+                    });
+                values.push(if fluid_cells > 0 {
+                    (sum / fluid_cells as f64) as f32
+                } else {
+                    0.0
+                });
+                fluid_fraction.push(fluid_cells as f32 / cells as f32);
+                cell_types.push(if fluid_cells > 0 {
+                    CellType::FluidCell
+                } else {
+                    self.cell_types[self.index(xs.start, ys.start)]
+                });
+            }
+        }
+        DownsampledField {
+            field: self.field,
+            location: self.location,
+            factor,
+            shape,
+            delta_space: self.delta_space.map(|delta| delta * factor as f32),
+            values,
+            fluid_fraction,
+            cell_types,
+        }
+    }
+
+    // Copy indexed x * ny + y with the view's own ny
+    pub fn to_vec(&self) -> Vec<f32> {
+        (0..self.shape[0])
+            .flat_map(|x| self.column(x).iter().copied())
+            .collect()
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        assert!(
+            x < self.shape[0] && y < self.shape[1],
+            "cell ({x}, {y}) outside a field of {:?} cells",
+            self.shape
+        );
+        (self.origin[0] + x) * self.stride + self.origin[1] + y
+    }
+}
+
+// Block averages of a field, see FieldView::downsample. Blocks without fluid hold zero
+// and the type of their first cell, the others count as fluid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownsampledField {
+    pub field: Option<Field>,
+    pub location: Location,
+    pub factor: usize,
+    // Blocks in x and y
+    pub shape: [usize; 2],
+    pub delta_space: [f32; 2], // meters per block
+    // Indexed x * shape[1] + y
+    pub values: Vec<f32>,
+    // Share of the cells of each block that are fluid
+    pub fluid_fraction: Vec<f32>,
+    pub cell_types: Vec<CellType>,
+}
+
+impl DownsampledField {
+    // One block per cell, relative to the corner of the downsampled view
+    pub fn view(&self) -> FieldView<'_> {
+        FieldView {
+            field: self.field,
+            ..FieldView::from_values(
+                self.location,
+                &self.values,
+                &self.cell_types,
+                self.shape,
+                self.delta_space,
+            )
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use crate::cell::{Cell, CellType};
+use crate::field::{FieldView, Location};
+use crate::field_snapshot::FieldSnapshot;
+use crate::output_sink::OutputSink;
+use crate::run_controller::Progress;
+use crate::solver::{Checkpoint, FluidSolver};
+
+use std::io::{self, Write};
+
+// Times an average takes samples at, both ends included, None for no limit
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AveragingWindow {
+    pub start: Option<f32>, // seconds
+    pub stop: Option<f32>,  // seconds
+}
+
+impl AveragingWindow {
+    pub fn contains(&self, time: f32) -> bool {
+        self.start.is_none_or(|start| time >= start) && self.stop.is_none_or(|stop| time <= stop)
+    }
+}
+
+// Running mean of the u, v and pressure fields, one sample per call to record or
+// per timestep as a sink. Every sample counts the same, so with adaptive timesteps
+// record at fixed time intervals instead. Samples are only taken inside the window
+// and while not paused, which leaves out the start up transient. With fluctuations
+// tracked it also sums the second moments behind the Reynolds stresses.
+#[derive(Debug, Clone, Default)]
+pub struct TimeAverage {
+    window: AveragingWindow,
+    is_paused: bool,
+    samples: usize,
+    time_range: [f32; 2],
+    space_size: [usize; 2],
+    delta_space: [f32; 2],
+    // Of the latest sample
+    cell_type: Vec<CellType>,
+    // Sums of u, v and pressure at their staggered locations
+    sums: [Vec<f64>; 3],
+    is_tracking_fluctuations: bool,
+    // Per cell sums of the cell centred u, v and dye and of u u, v v, u v and dye dye
+    moments: Vec<[f64; 7]>,
+}
+
+// Mean of the samples of an average
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeanField {
+    pub samples: usize,
+    // Of the first and the latest sample, seconds
+    pub time_range: [f32; 2],
+    pub space_size: [usize; 2],
+    pub delta_space: [f32; 2], // meters
+    pub cell_type: Vec<CellType>,
+    // At the staggered locations like Fields, in cell order
+    pub u: Vec<f32>,
+    pub v: Vec<f32>,
+    pub pressure: Vec<f32>,
+}
+
+// Cell centred variances and covariance of the samples of an average, as E[a b] -
+// E[a] E[b] over the samples. In the wake of an obstacle the Reynolds stresses show
+// where and how strongly the flow is unsteady.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FluctuationField {
+    pub samples: usize,
+    // Of the first and the latest sample, seconds
+    pub time_range: [f32; 2],
+    pub space_size: [usize; 2],
+    pub delta_space: [f32; 2], // meters
+    pub cell_type: Vec<CellType>,
+    // Reynolds stresses per unit density, in cell order, meters^2/seconds^2
+    pub uu: Vec<f32>,
+    pub vv: Vec<f32>,
+    pub uv: Vec<f32>,
+    pub dye_variance: Vec<f32>,
+}
+
+impl TimeAverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_window(mut self, window: AveragingWindow) -> Self {
+        self.window = window;
+        self
+    }
+
+    // Also accumulate the second moments for fluctuations, off by default
+    pub fn with_fluctuations(mut self, is_tracking_fluctuations: bool) -> Self {
+        assert_eq!(
+            self.samples, 0,
+            "fluctuations must be chosen before the first sample"
+        );
+        self.is_tracking_fluctuations = is_tracking_fluctuations;
+        self
+    }
+
+    pub fn is_tracking_fluctuations(&self) -> bool {
+        self.is_tracking_fluctuations
+    }
+
+    pub fn window(&self) -> AveragingWindow {
+        self.window
+    }
+
+    pub fn pause(&mut self) {
+        self.is_paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.is_paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    // Returns whether the sample was taken
+    pub fn record(&mut self, solver: &dyn FluidSolver) -> bool {
+        let time = solver.time();
+        if self.is_paused || !self.window.contains(time) {
+            return false;
+        }
+        let fields = solver.fields();
+        if self.samples == 0 {
+            self.time_range[0] = time;
+            self.space_size = solver.space_size();
+            self.delta_space = solver.delta_space();
+            self.sums =
+                [&fields.u, &fields.v, &fields.pressure].map(|field| vec![0.0; field.len()]);
+            if self.is_tracking_fluctuations {
+                self.moments = vec![[0.0; 7]; fields.len()];
+            }
+        }
+        assert_eq!(
+            self.space_size,
+            solver.space_size(),
+            "grid size changed while averaging"
+        );
+        for (sum, field) in self
+            .sums
+            .iter_mut()
+            .zip([&fields.u, &fields.v, &fields.pressure])
+        {
+            for (sum, &value) in sum.iter_mut().zip(field) {
+                *sum += value as f64;
+            }
+        }
+        if self.is_tracking_fluctuations {
+            let ny = self.space_size[1];
+            for (index, moments) in self.moments.iter_mut().enumerate() {
+                // Like Checkpoint::centered_velocity, the faces at the domain edge count twice
+                let left = if index >= ny { index - ny } else { index };
+                let bottom = if index % ny > 0 { index - 1 } else { index };
+                let u = (fields.u[left] as f64 + fields.u[index] as f64) / 2.0;
+                let v = (fields.v[bottom] as f64 + fields.v[index] as f64) / 2.0;
+                let dye = fields.dye[index] as f64;
+                for (moment, value) in
+                    moments
+                        .iter_mut()
+                        .zip([u, v, dye, u * u, v * v, u * v, dye * dye])
+                {
+                    *moment += value;
+                }
+            }
+        }
+        self.cell_type.clone_from(&fields.cell_type);
+        self.time_range[1] = time;
+        self.samples += 1;
+        true
+    }
+
+    // None before the first sample
+    pub fn mean(&self) -> Option<MeanField> {
+        if self.samples == 0 {
+            return None;
+        }
+        let [u, v, pressure] = self.sums.clone().map(|sum| {
+            sum.into_iter()
+                .map(|sum| (sum / self.samples as f64) as f32)
+                .collect()
+        });
+        Some(MeanField {
+            samples: self.samples,
+            time_range: self.time_range,
+            space_size: self.space_size,
+            delta_space: self.delta_space,
+            cell_type: self.cell_type.clone(),
+            u,
+            v,
+            pressure,
+        })
+    }
+
+    // None before the first sample or without tracking fluctuations
+    pub fn fluctuations(&self) -> Option<FluctuationField> {
+        if self.samples == 0 || !self.is_tracking_fluctuations {
+            return None;
+        }
+        let samples = self.samples as f64;
+        let covariance = |a: usize, b: usize, ab: usize| -> Vec<f32> {
+            self.moments
+                .iter()
+                .map(|moments| {
+                    let covariance =
+                        moments[ab] / samples - moments[a] / samples * (moments[b] / samples);
+                    // Rounding can leave variances of steady cells slightly negative
+                    if a == b {
+                        covariance.max(0.0) as f32
+                    } else {
+                        covariance as f32
+                    }
+                })
+                .collect()
+        };
+        Some(FluctuationField {
+            samples: self.samples,
+            time_range: self.time_range,
+            space_size: self.space_size,
+            delta_space: self.delta_space,
+            cell_type: self.cell_type.clone(),
+            uu: covariance(0, 0, 3),
+            vv: covariance(1, 1, 4),
+            uv: covariance(0, 1, 5),
+            dye_variance: covariance(2, 2, 6),
+        })
+    }
+
+    // Forget the samples, the window, pausing and tracking fluctuations stay
+    pub fn clear(&mut self) {
+        *self = Self {
+            window: self.window,
+            is_paused: self.is_paused,
+            is_tracking_fluctuations: self.is_tracking_fluctuations,
+            ..Self::default()
+        };
+    }
+}
+
+impl OutputSink for TimeAverage {
+    fn on_step(&mut self, solver: &dyn FluidSolver, _progress: &Progress) -> io::Result<()> {
+        self.record(solver);
+        Ok(())
+    }
+}
+
+impl MeanField {
+    // Cells holding the mean fields, at the time of the latest sample
+    pub fn checkpoint(&self) -> Checkpoint {
+        let cells = (0..self.cell_type.len())
+            .map(|index| Cell {
+                cell_type: self.cell_type[index],
+                velocity: [self.u[index], self.v[index]],
+                pressure: self.pressure[index],
+                ..Cell::default()
+            })
+            .collect();
+        Checkpoint {
+            time: self.time_range[1],
+            space_size: self.space_size,
+            cells,
+            metadata: None,
+        }
+    }
+
+    // Cell centred u, v and pressure, see FieldSnapshot::save
+    pub fn to_snapshot(&self) -> FieldSnapshot {
+        let mut snapshot = FieldSnapshot::from_checkpoint(&self.checkpoint(), self.delta_space);
+        snapshot
+            .fields
+            .retain(|(name, _)| ["u", "v", "pressure"].contains(&name.as_str()));
+        snapshot
+    }
+
+    // One row per cell with its center in meters and the cell centred means
+    pub fn write_csv(&self, file: &mut impl Write) -> io::Result<()> {
+        let checkpoint = self.checkpoint();
+        let [nx, ny] = self.space_size;
+        let [dx, dy] = self.delta_space;
+        writeln!(file, "x,y,is_fluid,u,v,pressure")?;
+        for x in 0..nx {
+            for y in 0..ny {
+                let [u, v] = checkpoint.centered_velocity(x, y);
+                let cell = checkpoint.get_cell(x, y);
+                writeln!(
+                    file,
+                    "{},{},{},{},{},{}",
+                    (x as f32 + 0.5) * dx,
+                    (y as f32 + 0.5) * dy,
+                    matches!(cell.cell_type, CellType::FluidCell) as u8,
+                    u,
+                    v,
+                    cell.pressure
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FluctuationField {
+    pub fn uu(&self) -> FieldView<'_> {
+        self.view(&self.uu)
+    }
+
+    pub fn vv(&self) -> FieldView<'_> {
+        self.view(&self.vv)
+    }
+
+    pub fn uv(&self) -> FieldView<'_> {
+        self.view(&self.uv)
+    }
+
+    pub fn dye_variance(&self) -> FieldView<'_> {
+        self.view(&self.dye_variance)
+    }
+
+    // (u'u' + v'v') / 2 per cell
+    pub fn turbulent_kinetic_energy(&self) -> Vec<f32> {
+        self.uu
+            .iter()
+            .zip(&self.vv)
+            .map(|(uu, vv)| (uu + vv) / 2.0)
+            .collect()
+    }
+
+    fn view<'a>(&'a self, values: &'a [f32]) -> FieldView<'a> {
+        FieldView::from_values(
+            Location::CellCentre,
+            values,
+            &self.cell_type,
+            self.space_size,
+            self.delta_space,
+        )
+    }
+}
@@ -0,0 +1,259 @@
+use crate::cell::CellType;
+use crate::solver::{Checkpoint, FluidSolver};
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"F2DS";
+const VERSION: u32 = 1;
+const WORST_CELL_COUNT: usize = 5;
+
+// Cell centred fields of one solver state, comparable across backends
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldSnapshot {
+    pub time: f32, // seconds
+    pub space_size: [usize; 2],
+    pub delta_space: [f32; 2], // meters
+    // Only fluid cells are compared
+    pub is_fluid: Vec<bool>,
+    // Named fields indexed like the cells, x * ny + y
+    pub fields: Vec<(String, Vec<f32>)>,
+}
+
+impl FieldSnapshot {
+    pub fn from_checkpoint(checkpoint: &Checkpoint, delta_space: [f32; 2]) -> Self {
+        let [nx, ny] = checkpoint.space_size;
+        let positions = || (0..nx).flat_map(|x| (0..ny).map(move |y| (x, y)));
+        let field = |value: &dyn Fn(usize, usize) -> f32| -> Vec<f32> {
+            positions().map(|(x, y)| value(x, y)).collect()
+        };
+
+        Self {
+            time: checkpoint.time,
+            space_size: checkpoint.space_size,
+            delta_space,
+            is_fluid: checkpoint
+                .cells
+                .iter()
+                .map(|cell| matches!(cell.cell_type, CellType::FluidCell))
+                .collect(),
+            fields: vec![
+                (
+                    "u".to_string(),
+                    field(&|x, y| checkpoint.centered_velocity(x, y)[0]),
+                ),
+                (
+                    "v".to_string(),
+                    field(&|x, y| checkpoint.centered_velocity(x, y)[1]),
+                ),
+                (
+                    "pressure".to_string(),
+                    field(&|x, y| checkpoint.get_cell(x, y).pressure),
+                ),
+                (
+                    "psi".to_string(),
+                    field(&|x, y| checkpoint.get_cell(x, y).psi),
+                ),
+                (
+                    "dye".to_string(),
+                    field(&|x, y| checkpoint.get_cell(x, y).dye),
+                ),
+            ],
+        }
+    }
+
+    pub fn from_solver(solver: &dyn FluidSolver) -> Self {
+        Self::from_checkpoint(&solver.checkpoint(), solver.delta_space())
+    }
+
+    pub fn field(&self, name: &str) -> Option<&[f32]> {
+        self.fields
+            .iter()
+            .find(|(field_name, _)| field_name == name)
+            .map(|(_, values)| values.as_slice())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+
+        file.write_all(MAGIC)?;
+        file.write_all(&VERSION.to_le_bytes())?;
+        file.write_all(&self.time.to_le_bytes())?;
+        for size in self.space_size {
+            file.write_all(&(size as u32).to_le_bytes())?;
+        }
+        for delta in self.delta_space {
+            file.write_all(&delta.to_le_bytes())?;
+        }
+        let is_fluid: Vec<u8> = self
+            .is_fluid
+            .iter()
+            .map(|&is_fluid| is_fluid as u8)
+            .collect();
+        file.write_all(&is_fluid)?;
+
+        file.write_all(&(self.fields.len() as u32).to_le_bytes())?;
+        for (name, values) in self.fields.iter() {
+            file.write_all(&(name.len() as u32).to_le_bytes())?;
+            file.write_all(name.as_bytes())?;
+            for value in values {
+                file.write_all(&value.to_le_bytes())?;
+            }
+        }
+        file.flush()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; 4];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a field snapshot"));
+        }
+        let version = read_u32(&mut file)?;
+        if version != VERSION {
+            return Err(invalid_data(&format!(
+                "unsupported field snapshot version {version}"
+            )));
+        }
+
+        let time = read_f32(&mut file)?;
+        let space_size = [read_u32(&mut file)? as usize, read_u32(&mut file)? as usize];
+        let delta_space = [read_f32(&mut file)?, read_f32(&mut file)?];
+        let cell_count = space_size[0] * space_size[1];
+
+        let mut is_fluid = vec![0; cell_count];
+        file.read_exact(&mut is_fluid)?;
+
+        let field_count = read_u32(&mut file)?;
+        let mut fields = Vec::with_capacity(field_count as usize);
+        for _ in 0..field_count {
+            let mut name = vec![0; read_u32(&mut file)? as usize];
+            file.read_exact(&mut name)?;
+            let name = String::from_utf8(name).map_err(|_| invalid_data("field name"))?;
+            let values = (0..cell_count)
+                .map(|_| read_f32(&mut file))
+                .collect::<io::Result<Vec<f32>>>()?;
+            fields.push((name, values));
+        }
+
+        Ok(Self {
+            time,
+            space_size,
+            delta_space,
+            is_fluid: is_fluid.into_iter().map(|is_fluid| is_fluid != 0).collect(),
+            fields,
+        })
+    }
+
+    // Compare fields present in both snapshots over cells that are fluid in both
+    pub fn compare(&self, other: &FieldSnapshot, tolerance: f32) -> FieldDiff {
+        assert_eq!(self.space_size, other.space_size, "snapshot size mismatch");
+        let ny = self.space_size[1];
+
+        let fields = self
+            .fields
+            .iter()
+            .filter_map(|(name, values)| {
+                let other_values = other.field(name)?;
+
+                let mut differences: Vec<([usize; 2], f32)> = values
+                    .iter()
+                    .zip(other_values)
+                    .enumerate()
+                    .filter(|&(index, _)| self.is_fluid[index] && other.is_fluid[index])
+                    .map(|(index, (a, b))| ([index / ny, index % ny], (a - b).abs()))
+                    .collect();
+
+                let mean = if differences.is_empty() {
+                    0.0
+                } else {
+                    differences
+                        .iter()
+                        .map(|(_, difference)| difference)
+                        .sum::<f32>()
+                        / differences.len() as f32
+                };
+
+                // NaN sorts as the worst difference
+                differences.sort_by(|a, b| b.1.total_cmp(&a.1));
+                differences.truncate(WORST_CELL_COUNT);
+
+                Some(FieldDifference {
+                    name: name.clone(),
+                    max: differences
+                        .first()
+                        .map_or(0.0, |(_, difference)| *difference),
+                    mean,
+                    worst_cells: differences,
+                })
+            })
+            .collect();
+
+        FieldDiff { tolerance, fields }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldDifference {
+    pub name: String,
+    pub max: f32,
+    pub mean: f32,
+    // ([x, y], absolute difference), largest first
+    pub worst_cells: Vec<([usize; 2], f32)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldDiff {
+    pub tolerance: f32,
+    pub fields: Vec<FieldDifference>,
+}
+
+impl FieldDiff {
+    pub fn is_within_tolerance(&self) -> bool {
+        self.fields.iter().all(|field| field.max <= self.tolerance)
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldDifference> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+impl fmt::Display for FieldDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for field in self.fields.iter() {
+            write!(
+                f,
+                "{:<10} max {:.3e} mean {:.3e}",
+                field.name, field.max, field.mean
+            )?;
+            if field.max > self.tolerance {
+                write!(f, " > {:.3e}, worst", self.tolerance)?;
+                for ([x, y], difference) in field.worst_cells.iter() {
+                    write!(f, " ({x}, {y}): {difference:.3e}")?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+pub(crate) fn read_u32(file: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    file.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+pub(crate) fn read_f32(file: &mut impl Read) -> io::Result<f32> {
+    let mut bytes = [0; 4];
+    file.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
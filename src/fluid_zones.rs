@@ -0,0 +1,103 @@
+use crate::cell::CellFlags;
+use crate::space_domain::{SpaceDomain, FACE_NEIGHBORS};
+
+// Fluid cells split into zones that exchange no fluid, like two unconnected channels.
+// Cells sharing a face are connected, and so are cells on either side of periodic
+// cells. Every pressure boundary condition is Neumann, so each zone has a pressure
+// level of its own that the solve leaves open and that Simulation anchors per zone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FluidZones {
+    // Zone of every cell in cell order, None outside the fluid
+    labels: Vec<Option<usize>>,
+    // Cell indices in cell order per zone, largest zone first
+    zones: Vec<Vec<usize>>,
+    // Whether a zone touches inflow or outflow cells
+    is_open: Vec<bool>,
+}
+
+impl FluidZones {
+    pub fn label(space_domain: &SpaceDomain) -> Self {
+        let flags = space_domain.cell_flags();
+        let mut labels = vec![None; flags.len()];
+        let mut zones: Vec<(usize, Vec<usize>, bool)> = Vec::new();
+
+        for &start in space_domain.fluid_cells() {
+            if labels[start].is_some() {
+                continue;
+            }
+            let label = zones.len();
+            let mut cells = Vec::new();
+            let mut is_open = false;
+            let mut stack = vec![start];
+            labels[start] = Some(label);
+            while let Some(index) = stack.pop() {
+                cells.push(index);
+                let (x, y) = space_domain.position(index);
+                for offset in FACE_NEIGHBORS {
+                    let Some((mut nx, mut ny)) = space_domain.neighbor_position(x, y, offset)
+                    else {
+                        continue;
+                    };
+                    let neighbor_flags = space_domain.flags(nx, ny);
+                    is_open |= neighbor_flags.intersects(CellFlags::INFLOW | CellFlags::OUTFLOW);
+                    if neighbor_flags.contains(CellFlags::PERIODIC) {
+                        (nx, ny) = space_domain.periodic_source(nx, ny);
+                    }
+                    let neighbor = space_domain.index(nx, ny);
+                    if flags[neighbor].is_fluid() && labels[neighbor].is_none() {
+                        labels[neighbor] = Some(label);
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            cells.sort_unstable();
+            zones.push((label, cells, is_open));
+        }
+
+        // Largest first, then in the order of their first cell
+        zones.sort_by_key(|(_, cells, _)| std::cmp::Reverse(cells.len()));
+        let mut relabel = vec![0; zones.len()];
+        for (new, &(old, _, _)) in zones.iter().enumerate() {
+            relabel[old] = new;
+        }
+        for label in labels.iter_mut().flatten() {
+            *label = relabel[*label];
+        }
+        let (zones, is_open) = zones
+            .into_iter()
+            .map(|(_, cells, is_open)| (cells, is_open))
+            .unzip();
+
+        Self {
+            labels,
+            zones,
+            is_open,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.zones.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+
+    // Zone of every cell in cell order, x * ny + y
+    pub fn labels(&self) -> &[Option<usize>] {
+        &self.labels
+    }
+
+    pub fn zone_of(&self, index: usize) -> Option<usize> {
+        self.labels[index]
+    }
+
+    pub fn cells(&self, zone: usize) -> &[usize] {
+        &self.zones[zone]
+    }
+
+    // Fluid enters or leaves the zone through inflow or outflow cells
+    pub fn is_open(&self, zone: usize) -> bool {
+        self.is_open[zone]
+    }
+}
@@ -0,0 +1,138 @@
+use crate::solver::FluidSolver;
+use crate::space_domain::Fields;
+
+use std::io::{self, Write};
+use std::ops::Range;
+
+// Row of staggered velocity faces, in cell indices
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlSurface {
+    // Right faces of cells (x, y) for y in the range, positive flux in +x
+    Vertical { x: usize, y: Range<usize> },
+    // Top faces of cells (x, y) for x in the range, positive flux in +y
+    Horizontal { y: usize, x: Range<usize> },
+}
+
+impl ControlSurface {
+    // Volume flux per unit depth in meters^2/seconds, the sum of the face fluxes
+    pub fn flux(&self, fields: &Fields, space_size: [usize; 2], delta_space: [f32; 2]) -> f32 {
+        let index = |x: usize, y: usize| x * space_size[1] + y;
+        match self {
+            ControlSurface::Vertical { x, y } => {
+                assert!(
+                    *x < space_size[0] && y.end <= space_size[1],
+                    "control surface outside the domain"
+                );
+                y.clone().map(|y| fields.u[index(*x, y)]).sum::<f32>() * delta_space[1]
+            }
+            ControlSurface::Horizontal { y, x } => {
+                assert!(
+                    *y < space_size[1] && x.end <= space_size[0],
+                    "control surface outside the domain"
+                );
+                x.clone().map(|x| fields.v[index(x, *y)]).sum::<f32>() * delta_space[0]
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FluxMonitor {
+    pub name: String,
+    pub surface: ControlSurface,
+    // (time in seconds, flux)
+    pub samples: Vec<(f32, f32)>,
+}
+
+impl FluxMonitor {
+    pub fn latest(&self) -> Option<f32> {
+        self.samples.last().map(|&(_, flux)| flux)
+    }
+}
+
+// Flux time series of named control surfaces
+#[derive(Clone, Default)]
+pub struct FluxMonitors {
+    monitors: Vec<FluxMonitor>,
+}
+
+impl FluxMonitors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Replaces a monitor of the same name
+    pub fn add(&mut self, name: &str, surface: ControlSurface) {
+        self.remove(name);
+        self.monitors.push(FluxMonitor {
+            name: name.to_string(),
+            surface,
+            samples: Vec::new(),
+        });
+    }
+
+    // Returns whether a monitor was removed
+    pub fn remove(&mut self, name: &str) -> bool {
+        let count = self.monitors.len();
+        self.monitors.retain(|monitor| monitor.name != name);
+        self.monitors.len() != count
+    }
+
+    pub fn monitors(&self) -> &[FluxMonitor] {
+        &self.monitors
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FluxMonitor> {
+        self.monitors.iter().find(|monitor| monitor.name == name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.monitors.is_empty()
+    }
+
+    // Forget the recorded samples, keeping the surfaces
+    pub fn clear(&mut self) {
+        for monitor in self.monitors.iter_mut() {
+            monitor.samples.clear();
+        }
+    }
+
+    // Call once per timestep
+    pub fn record(&mut self, solver: &dyn FluidSolver) {
+        self.record_cells(
+            solver.time(),
+            solver.fields(),
+            solver.space_size(),
+            solver.delta_space(),
+        );
+    }
+
+    pub(crate) fn record_cells(
+        &mut self,
+        time: f32,
+        fields: &Fields,
+        space_size: [usize; 2],
+        delta_space: [f32; 2],
+    ) {
+        for monitor in self.monitors.iter_mut() {
+            let flux = monitor.surface.flux(fields, space_size, delta_space);
+            monitor.samples.push((time, flux));
+        }
+    }
+
+    // Inflow minus outflow at the latest sample, positive when mass accumulates
+    pub fn imbalance(&self, inflow: &str, outflow: &str) -> Option<f32> {
+        Some(self.get(inflow)?.latest()? - self.get(outflow)?.latest()?)
+    }
+
+    // One name,time,flux row per sample
+    pub fn write_csv(&self, file: &mut impl Write) -> io::Result<()> {
+        writeln!(file, "name,time,flux")?;
+        for monitor in self.monitors.iter() {
+            for (time, flux) in monitor.samples.iter() {
+                writeln!(file, "{},{},{}", monitor.name, time, flux)?;
+            }
+        }
+        Ok(())
+    }
+}
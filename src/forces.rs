@@ -0,0 +1,82 @@
+use crate::cell::{BoundaryConditionCell, CellType};
+use crate::space_domain::Fields;
+
+// Force per unit depth the fluid exerts on the no-slip cells away from the domain
+// edge, [drag, lift] for flow in +x. Density is 1 and the viscosity 1 / reynolds, as
+// in the momentum equations.
+pub fn obstacle_force(
+    fields: &Fields,
+    space_size: [usize; 2],
+    delta_space: [f32; 2],
+    reynolds: f32,
+) -> [f32; 2] {
+    let [nx, ny] = space_size;
+    let solid_cells = (1..nx - 1)
+        .flat_map(|x| (1..ny - 1).map(move |y| (x, y)))
+        .filter(|&(x, y)| {
+            matches!(
+                fields.cell_type[x * ny + y],
+                CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell { .. })
+            )
+        });
+    force_on_cells(fields, space_size, delta_space, reynolds, solid_cells)
+}
+
+// Force per unit depth on the given cells away from the domain edge. Pressure acts
+// on every face shared with a fluid cell, wall shear of no-slip cells uses the
+// tangential velocity of the fluid cell center relative to the wall.
+pub fn force_on_cells(
+    fields: &Fields,
+    space_size: [usize; 2],
+    delta_space: [f32; 2],
+    reynolds: f32,
+    solid_cells: impl IntoIterator<Item = (usize, usize)>,
+) -> [f32; 2] {
+    let ny = space_size[1];
+    let [dx, dy] = delta_space;
+    let index = |x: usize, y: usize| x * ny + y;
+    let is_fluid =
+        |x: usize, y: usize| matches!(fields.cell_type[index(x, y)], CellType::FluidCell);
+    let centered_velocity = |x: usize, y: usize| {
+        [
+            (fields.u[index(x - 1, y)] + fields.u[index(x, y)]) / 2.0,
+            (fields.v[index(x, y - 1)] + fields.v[index(x, y)]) / 2.0,
+        ]
+    };
+
+    let mut force = [0.0, 0.0];
+    for (x, y) in solid_cells {
+        let wall_velocity = match fields.cell_type[index(x, y)] {
+            CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                boundary_condition_velocity,
+            }) => Some(boundary_condition_velocity),
+            _ => None,
+        };
+
+        // (neighbour, outward normal of the obstacle face)
+        for ((fx, fy), normal) in [
+            ((x + 1, y), [1.0, 0.0]),
+            ((x - 1, y), [-1.0, 0.0]),
+            ((x, y + 1), [0.0, 1.0]),
+            ((x, y - 1), [0.0, -1.0]),
+        ] {
+            if !is_fluid(fx, fy) {
+                continue;
+            }
+            let pressure = fields.pressure[index(fx, fy)];
+            let velocity = centered_velocity(fx, fy);
+            if normal[0] != 0.0 {
+                force[0] -= pressure * normal[0] * dy;
+                if let Some(wall_velocity) = wall_velocity {
+                    force[1] += (velocity[1] - wall_velocity[1]) / (dx / 2.0) / reynolds * dy;
+                }
+            } else {
+                force[1] -= pressure * normal[1] * dx;
+                if let Some(wall_velocity) = wall_velocity {
+                    force[0] += (velocity[0] - wall_velocity[0]) / (dy / 2.0) / reynolds * dx;
+                }
+            }
+        }
+    }
+    force
+}
@@ -0,0 +1,135 @@
+use crate::control::Actuator;
+use crate::field_average::{AveragingWindow, MeanField, TimeAverage};
+use crate::output_sink::OutputSink;
+use crate::run_controller::Progress;
+use crate::shedding::SheddingAnalysis;
+use crate::solver::FluidSolver;
+
+use std::f32::consts::PI;
+use std::io;
+
+// Sinusoidal actuation, mean + amplitude * sin(2 pi frequency t + phase), applied by
+// the simulation before every timestep at the middle of the step
+#[derive(Debug, Clone, PartialEq)]
+pub struct Oscillation {
+    pub actuator: Actuator,
+    pub mean: f32,
+    pub amplitude: f32,
+    pub frequency: f32, // hertz
+    pub phase: f32,     // radians
+}
+
+impl Oscillation {
+    pub fn value(&self, time: f32) -> f32 {
+        self.mean + self.amplitude * (2.0 * PI * self.frequency * time + self.phase).sin()
+    }
+
+    pub fn period(&self) -> f32 {
+        assert!(self.frequency > 0.0, "frequency must be positive");
+        1.0 / self.frequency
+    }
+}
+
+// Largest timestep up to max_delta_time that divides the period into whole steps,
+// with the steps per period. Samples every step then land on the same phases in
+// every period.
+pub fn locked_delta_time(period: f32, max_delta_time: f32) -> (f32, usize) {
+    assert!(
+        period > 0.0 && max_delta_time > 0.0,
+        "period and timestep must be positive"
+    );
+    let steps = (period / max_delta_time).ceil().max(1.0) as usize;
+    (period / steps as f32, steps)
+}
+
+// Fields averaged per phase of a period, like the period of an Oscillation or of
+// shedding. Each sample goes to the nearest of the bins evenly spaced over the
+// period, starting at phase zero, which is time zero.
+pub struct PhaseAverage {
+    period: f32, // seconds
+    bins: Vec<TimeAverage>,
+}
+
+// Mean of the samples of one phase bin
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhaseField {
+    pub phase: f32, // radians
+    pub field: MeanField,
+}
+
+impl PhaseAverage {
+    pub fn new(period: f32, bins: usize) -> Self {
+        assert!(period > 0.0, "period must be positive");
+        assert!(bins > 0, "phase averages need bins");
+        Self {
+            period,
+            bins: vec![TimeAverage::new(); bins],
+        }
+    }
+
+    // Keyed to the shedding period, the phase of the shedding itself is arbitrary
+    pub fn from_shedding(analysis: &SheddingAnalysis, bins: usize) -> Self {
+        Self::new(1.0 / analysis.frequency, bins)
+    }
+
+    // See TimeAverage, the same for every bin
+    pub fn with_window(mut self, window: AveragingWindow) -> Self {
+        self.bins = self
+            .bins
+            .into_iter()
+            .map(|bin| bin.with_window(window))
+            .collect();
+        self
+    }
+
+    pub fn pause(&mut self) {
+        self.bins.iter_mut().for_each(TimeAverage::pause);
+    }
+
+    pub fn resume(&mut self) {
+        self.bins.iter_mut().for_each(TimeAverage::resume);
+    }
+
+    pub fn period(&self) -> f32 {
+        self.period
+    }
+
+    pub fn bins(&self) -> usize {
+        self.bins.len()
+    }
+
+    // Bin of the phase at time
+    pub fn bin(&self, time: f32) -> usize {
+        let phase = (time / self.period).rem_euclid(1.0);
+        (phase * self.bins() as f32).round() as usize % self.bins()
+    }
+
+    // Returns whether the sample was taken
+    pub fn record(&mut self, solver: &dyn FluidSolver) -> bool {
+        let bin = self.bin(solver.time());
+        self.bins[bin].record(solver)
+    }
+
+    pub fn count(&self, bin: usize) -> usize {
+        self.bins[bin].samples()
+    }
+
+    // None before the first sample of the bin
+    pub fn mean(&self, bin: usize) -> Option<PhaseField> {
+        self.bins[bin].mean().map(|field| PhaseField {
+            phase: 2.0 * PI * bin as f32 / self.bins() as f32,
+            field,
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.bins.iter_mut().for_each(TimeAverage::clear);
+    }
+}
+
+impl OutputSink for PhaseAverage {
+    fn on_step(&mut self, solver: &dyn FluidSolver, _progress: &Progress) -> io::Result<()> {
+        self.record(solver);
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+use crate::cell::CellType;
+use crate::colormap::{symmetric_range, Colormap};
+use crate::png::{self, PngColor};
+use crate::solver::FluidSolver;
+use crate::vortex::VortexField;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+const BOUNDARY_COLOR: [u8; 4] = [128, 128, 128, 255];
+const VOID_COLOR: [u8; 4] = [0, 0, 0, 255];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RenderField {
+    #[default]
+    Pressure,
+    Speed,
+    // Cell centred, the range is symmetric around zero
+    Vorticity,
+    // Clamped to [0, 1]
+    Dye,
+    // Bottom elevation of solvers with terrain, zero for the others
+    Terrain,
+    // Cell centred, see VortexField, the range is symmetric around zero
+    QCriterion,
+}
+
+// Rasterizes one field of a solver, pixels_per_cell square pixels per cell
+pub struct FrameRenderer {
+    pub field: RenderField,
+    pub colormap: Colormap,
+    pub pixels_per_cell: usize,
+}
+
+// RGBA pixels, rows top to bottom
+pub struct Image {
+    pub size: [usize; 2],
+    pub pixels: Vec<u8>,
+}
+
+impl FrameRenderer {
+    pub fn new(field: RenderField, colormap: Colormap, pixels_per_cell: usize) -> Self {
+        assert!(pixels_per_cell > 0, "pixels per cell must be positive");
+        Self {
+            field,
+            colormap,
+            pixels_per_cell,
+        }
+    }
+
+    pub fn render(&self, solver: &dyn FluidSolver) -> Image {
+        let [nx, ny] = solver.space_size();
+        let values = self.field_values(solver);
+        let range = match self.field {
+            RenderField::Dye => [0.0, 1.0],
+            RenderField::Vorticity | RenderField::QCriterion => {
+                symmetric_range(fluid_range(solver, &values))
+            }
+            RenderField::Terrain => fluid_range(solver, &values),
+            RenderField::Speed => solver.speed_range(),
+            RenderField::Pressure => solver.pressure_range(),
+        };
+
+        rasterize(
+            &self.colormap,
+            &values,
+            &solver.fields().cell_type,
+            range,
+            [nx, ny],
+            self.pixels_per_cell,
+        )
+    }
+
+    fn field_values(&self, solver: &dyn FluidSolver) -> Vec<f32> {
+        if self.field == RenderField::QCriterion {
+            return VortexField::new(solver).q;
+        }
+        let [nx, ny] = solver.space_size();
+        let delta_space = solver.delta_space();
+        let fields = solver.fields();
+
+        (0..nx)
+            .flat_map(|x| (0..ny).map(move |y| (x, y)))
+            .map(|(x, y)| {
+                let index = x * ny + y;
+                if !matches!(fields.cell_type[index], CellType::FluidCell) {
+                    return 0.0;
+                }
+                match self.field {
+                    RenderField::Pressure => fields.pressure[index],
+                    RenderField::Speed => {
+                        let [u, v] = solver.get_centered_velocity(x, y);
+                        (u.powi(2) + v.powi(2)).sqrt()
+                    }
+                    RenderField::Vorticity => {
+                        // Average of the four corner vorticities, fluid cells are never
+                        // on the domain edge
+                        let corner = |x: usize, y: usize| {
+                            (fields.v[(x + 1) * ny + y] - fields.v[x * ny + y]) / delta_space[0]
+                                - (fields.u[x * ny + y + 1] - fields.u[x * ny + y]) / delta_space[1]
+                        };
+                        (corner(x, y) + corner(x - 1, y) + corner(x, y - 1) + corner(x - 1, y - 1))
+                            / 4.0
+                    }
+                    RenderField::Dye => fields.dye[index],
+                    RenderField::QCriterion => unreachable!(),
+                    RenderField::Terrain => solver
+                        .terrain_elevations()
+                        .map_or(0.0, |terrain| terrain[x * ny + y]),
+                }
+            })
+            .collect()
+    }
+}
+
+// Colors the values of the fluid cells over range, indexed x * ny + y, as square
+// blocks of pixels with y pointing up
+pub(crate) fn rasterize(
+    colormap: &Colormap,
+    values: &[f32],
+    cell_types: &[CellType],
+    range: [f32; 2],
+    space_size: [usize; 2],
+    pixels_per_cell: usize,
+) -> Image {
+    let [nx, ny] = space_size;
+    let cell_colors: Vec<[u8; 4]> = (0..nx * ny)
+        .map(|index| match cell_types[index] {
+            CellType::FluidCell => {
+                let [r, g, b] = colormap
+                    .map(values[index], range)
+                    .map(|c| (c * 255.0).round() as u8);
+                [r, g, b, 255]
+            }
+            CellType::BoundaryConditionCell(_) => BOUNDARY_COLOR,
+            CellType::VoidCell => VOID_COLOR,
+        })
+        .collect();
+
+    let size = [nx * pixels_per_cell, ny * pixels_per_cell];
+    let mut pixels = Vec::with_capacity(size[0] * size[1] * 4);
+    for row in 0..size[1] {
+        let y = ny - 1 - row / pixels_per_cell;
+        for column in 0..size[0] {
+            let x = column / pixels_per_cell;
+            pixels.extend_from_slice(&cell_colors[x * ny + y]);
+        }
+    }
+    Image { size, pixels }
+}
+
+// Extremes over the fluid cells, zero without any
+fn fluid_range(solver: &dyn FluidSolver, values: &[f32]) -> [f32; 2] {
+    let range = values
+        .iter()
+        .zip(solver.fields().cell_type.iter())
+        .filter(|(_, cell_type)| matches!(cell_type, CellType::FluidCell))
+        .fold([f32::INFINITY, f32::NEG_INFINITY], |range, (&value, _)| {
+            [range[0].min(value), range[1].max(value)]
+        });
+    if range[0] > range[1] {
+        [0.0, 0.0]
+    } else {
+        range
+    }
+}
+
+pub enum FrameOutput {
+    // frame_000000.png, frame_000001.png, ... in the directory
+    PngFiles(PathBuf),
+    // Pipe raw frames into an ffmpeg process writing an H.264 movie
+    Ffmpeg {
+        path: PathBuf,
+        frames_per_second: u32,
+    },
+}
+
+// Renders every n-th timestep of a headless run
+pub struct FrameRecorder {
+    renderer: FrameRenderer,
+    output: FrameOutput,
+    every: usize,
+    steps: usize,
+    frames: usize,
+    ffmpeg: Option<Child>,
+}
+
+impl FrameRecorder {
+    pub fn new(renderer: FrameRenderer, output: FrameOutput, every: usize) -> io::Result<Self> {
+        assert!(every > 0, "frame interval must be positive");
+        if let FrameOutput::PngFiles(directory) = &output {
+            std::fs::create_dir_all(directory)?;
+        }
+        Ok(Self {
+            renderer,
+            output,
+            every,
+            steps: 0,
+            frames: 0,
+            ffmpeg: None,
+        })
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    // Call once per timestep, returns whether a frame was written
+    pub fn record(&mut self, solver: &dyn FluidSolver) -> io::Result<bool> {
+        let is_due = self.steps.is_multiple_of(self.every);
+        self.steps += 1;
+        if !is_due {
+            return Ok(false);
+        }
+
+        let image = self.renderer.render(solver);
+        match &self.output {
+            FrameOutput::PngFiles(directory) => {
+                let path = directory.join(format!("frame_{:06}.png", self.frames));
+                let mut file = BufWriter::new(File::create(path)?);
+                png::write_png(&mut file, image.size, PngColor::Rgba, &image.pixels)?;
+                file.flush()?;
+            }
+            FrameOutput::Ffmpeg {
+                path,
+                frames_per_second,
+            } => {
+                if self.ffmpeg.is_none() {
+                    self.ffmpeg = Some(spawn_ffmpeg(path, image.size, *frames_per_second)?);
+                }
+                let stdin = self.ffmpeg.as_mut().unwrap().stdin.as_mut().unwrap();
+                stdin.write_all(&image.pixels)?;
+            }
+        }
+        self.frames += 1;
+        Ok(true)
+    }
+
+    // Close the movie and wait for ffmpeg to finish encoding
+    pub fn finish(mut self) -> io::Result<()> {
+        if let Some(mut ffmpeg) = self.ffmpeg.take() {
+            drop(ffmpeg.stdin.take());
+            let status = ffmpeg.wait()?;
+            if !status.success() {
+                return Err(io::Error::other(format!("ffmpeg exited with {status}")));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn spawn_ffmpeg(path: &Path, size: [usize; 2], frames_per_second: u32) -> io::Result<Child> {
+    Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pix_fmt",
+            "rgba",
+        ])
+        .args(["-s", &format!("{}x{}", size[0], size[1])])
+        .args(["-r", &frames_per_second.to_string()])
+        .args(["-i", "-"])
+        // yuv420p needs even dimensions
+        .args(["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2"])
+        .args(["-c:v", "libx264", "-pix_fmt", "yuv420p"])
+        .arg(path)
+        .stdin(Stdio::piped())
+        .spawn()
+}
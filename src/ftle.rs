@@ -0,0 +1,281 @@
+use crate::cell::CellType;
+use crate::field::{FieldView, Location};
+use crate::output_sink::OutputSink;
+use crate::run_controller::Progress;
+use crate::solver::FluidSolver;
+use crate::space_domain::interpolate_velocity;
+
+use rayon::prelude::*;
+use std::io;
+
+// Largest fraction of a cell a tracer may move in one integration step
+const MAX_CELL_FRACTION: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Direction {
+    // From the first frame to the last, ridges are repelling structures
+    #[default]
+    Forward,
+    // From the last frame to the first, ridges are attracting structures
+    Backward,
+}
+
+// Velocity fields of a run over a time window, one frame per call to record or per
+// timestep as a sink, for following massless tracers through the unsteady flow
+// afterwards. Every frame keeps a copy of u and v.
+#[derive(Debug, Clone, Default)]
+pub struct FlowRecording {
+    space_size: [usize; 2],
+    delta_space: [f32; 2],
+    // Of the first frame
+    cell_type: Vec<CellType>,
+    frames: Vec<Frame>,
+}
+
+#[derive(Debug, Clone)]
+struct Frame {
+    time: f32,
+    u: Vec<f32>,
+    v: Vec<f32>,
+    // Largest cells per second a tracer crosses along x and y
+    max_rate: f32,
+}
+
+// Finite-time Lyapunov exponent of the tracers seeded at the centers of a grid of
+// resolution seeds over the domain, ln(stretching) / |integration time|. Seeds
+// starting outside the fluid hold zero.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FtleField {
+    pub direction: Direction,
+    pub integration_time: f32, // seconds
+    pub resolution: [usize; 2],
+    // Distance between seeds, meters
+    pub spacing: [f32; 2],
+    // Type of the cell each seed starts in
+    pub cell_type: Vec<CellType>,
+    // 1/seconds, indexed x * resolution[1] + y
+    pub values: Vec<f32>,
+}
+
+impl FlowRecording {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, solver: &dyn FluidSolver) {
+        let fields = solver.fields();
+        if self.frames.is_empty() {
+            self.space_size = solver.space_size();
+            self.delta_space = solver.delta_space();
+            self.cell_type.clone_from(&fields.cell_type);
+        }
+        assert_eq!(
+            self.space_size,
+            solver.space_size(),
+            "grid size changed while recording"
+        );
+        if let Some(last) = self.frames.last() {
+            assert!(
+                solver.time() > last.time,
+                "frames must move forward in time"
+            );
+        }
+        let max = |values: &[f32]| {
+            values
+                .iter()
+                .fold(0.0f32, |max, value| max.max(value.abs()))
+        };
+        self.frames.push(Frame {
+            time: solver.time(),
+            u: fields.u.clone(),
+            v: fields.v.clone(),
+            max_rate: max(&fields.u) / self.delta_space[0] + max(&fields.v) / self.delta_space[1],
+        });
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    // Times of the first and the latest frame, None before two frames
+    pub fn time_range(&self) -> Option<[f32; 2]> {
+        match self.frames.as_slice() {
+            [first, .., last] => Some([first.time, last.time]),
+            _ => None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    // Where tracers released at positions, in meters, are after the whole recording in
+    // the direction. Tracers stop at the last position inside the fluid when they hit
+    // a wall or leave the domain.
+    pub fn flow_map(&self, positions: &[[f32; 2]], direction: Direction) -> Vec<[f32; 2]> {
+        assert!(self.frames.len() >= 2, "flow maps need at least two frames");
+        positions
+            .par_iter()
+            .map(|&position| self.advect(position, direction))
+            .collect()
+    }
+
+    pub fn ftle(&self, resolution: [usize; 2], direction: Direction) -> FtleField {
+        assert!(
+            resolution[0] >= 2 && resolution[1] >= 2,
+            "ftle needs at least two seeds along each axis"
+        );
+        let [time_start, time_end] = self.time_range().expect("ftle needs at least two frames");
+        let [sx, sy] = resolution;
+        let spacing = [
+            self.space_size[0] as f32 * self.delta_space[0] / sx as f32,
+            self.space_size[1] as f32 * self.delta_space[1] / sy as f32,
+        ];
+        let seeds: Vec<[f32; 2]> = (0..sx)
+            .flat_map(|x| {
+                (0..sy).map(move |y| [(x as f32 + 0.5) * spacing[0], (y as f32 + 0.5) * spacing[1]])
+            })
+            .collect();
+        let cell_type: Vec<CellType> = seeds
+            .iter()
+            .map(|&seed| {
+                let (x, y) = self.cell(seed).expect("seeds lie inside the domain");
+                self.cell_type[x * self.space_size[1] + y]
+            })
+            .collect();
+        let ends = self.flow_map(&seeds, direction);
+
+        let integration_time = time_end - time_start;
+        let end = |x: usize, y: usize| ends[x * sy + y];
+        let values = (0..sx)
+            .flat_map(|x| (0..sy).map(move |y| (x, y)))
+            .map(|(x, y)| {
+                if !matches!(cell_type[x * sy + y], CellType::FluidCell) {
+                    return 0.0;
+                }
+                // Flow map gradient, central differences inside and one-sided at the
+                // edges of the seed grid
+                let [left, right] = [x.saturating_sub(1), (x + 1).min(sx - 1)];
+                let [bottom, top] = [y.saturating_sub(1), (y + 1).min(sy - 1)];
+                let step = [
+                    (right - left) as f32 * spacing[0],
+                    (top - bottom) as f32 * spacing[1],
+                ];
+                let [a, c] =
+                    [0, 1].map(|axis| (end(right, y)[axis] - end(left, y)[axis]) / step[0]);
+                let [b, d] =
+                    [0, 1].map(|axis| (end(x, top)[axis] - end(x, bottom)[axis]) / step[1]);
+                // Largest eigenvalue of the Cauchy-Green tensor F^T F
+                let [m_xx, m_xy, m_yy] = [a * a + c * c, a * b + c * d, b * b + d * d];
+                let mean = (m_xx + m_yy) / 2.0;
+                let largest = mean + (((m_xx - m_yy) / 2.0).powi(2) + m_xy.powi(2)).sqrt();
+                largest.max(f32::MIN_POSITIVE).sqrt().ln() / integration_time
+            })
+            .collect();
+        FtleField {
+            direction,
+            integration_time,
+            resolution,
+            spacing,
+            cell_type,
+            values,
+        }
+    }
+
+    fn advect(&self, mut position: [f32; 2], direction: Direction) -> [f32; 2] {
+        if !self.is_fluid(position) {
+            return position;
+        }
+        let intervals = self.frames.len() - 1;
+        for interval in 0..intervals {
+            let index = match direction {
+                Direction::Forward => interval,
+                Direction::Backward => intervals - 1 - interval,
+            };
+            let [start, end] = [&self.frames[index], &self.frames[index + 1]];
+            let duration = end.time - start.time;
+            let steps = (duration * start.max_rate.max(end.max_rate) / MAX_CELL_FRACTION)
+                .ceil()
+                .max(1.0) as usize;
+            let step = duration / steps as f32;
+            for step_index in 0..steps {
+                // Fraction of the interval at the start of the step, and the signed step
+                let (fraction, signed_step) = match direction {
+                    Direction::Forward => (step_index as f32 / steps as f32, step),
+                    Direction::Backward => (1.0 - step_index as f32 / steps as f32, -step),
+                };
+                let half = 0.5 * signed_step / duration;
+                // Midpoint rule
+                let k1 = self.velocity(start, end, fraction, position);
+                let midpoint = [
+                    position[0] + 0.5 * signed_step * k1[0],
+                    position[1] + 0.5 * signed_step * k1[1],
+                ];
+                let k2 = self.velocity(start, end, fraction + half, midpoint);
+                let next = [
+                    position[0] + signed_step * k2[0],
+                    position[1] + signed_step * k2[1],
+                ];
+                if !self.is_fluid(next) {
+                    return position;
+                }
+                position = next;
+            }
+        }
+        position
+    }
+
+    // Interpolated in space and linearly in time between two frames
+    fn velocity(&self, start: &Frame, end: &Frame, fraction: f32, position: [f32; 2]) -> [f32; 2] {
+        let sample = |frame: &Frame| {
+            interpolate_velocity(
+                self.space_size,
+                self.delta_space,
+                &frame.u,
+                &frame.v,
+                position,
+            )
+        };
+        let [a, b] = [sample(start), sample(end)];
+        [0, 1].map(|axis| a[axis] + fraction * (b[axis] - a[axis]))
+    }
+
+    fn cell(&self, position: [f32; 2]) -> Option<(usize, usize)> {
+        let x = (position[0] / self.delta_space[0]).floor();
+        let y = (position[1] / self.delta_space[1]).floor();
+        (x >= 0.0
+            && y >= 0.0
+            && (x as usize) < self.space_size[0]
+            && (y as usize) < self.space_size[1])
+            .then_some((x as usize, y as usize))
+    }
+
+    fn is_fluid(&self, position: [f32; 2]) -> bool {
+        self.cell(position).is_some_and(|(x, y)| {
+            matches!(
+                self.cell_type[x * self.space_size[1] + y],
+                CellType::FluidCell
+            )
+        })
+    }
+}
+
+impl OutputSink for FlowRecording {
+    fn on_step(&mut self, solver: &dyn FluidSolver, _progress: &Progress) -> io::Result<()> {
+        self.record(solver);
+        Ok(())
+    }
+}
+
+impl FtleField {
+    // Seed centered, x and y count seeds
+    pub fn view(&self) -> FieldView<'_> {
+        FieldView::from_values(
+            Location::CellCentre,
+            &self.values,
+            &self.cell_type,
+            self.resolution,
+            self.spacing,
+        )
+    }
+}
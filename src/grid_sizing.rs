@@ -0,0 +1,269 @@
+use crate::cell::CellType;
+use crate::simulation::Simulation;
+use crate::units::DeltaTimeLimits;
+
+use std::fmt;
+use std::mem::size_of;
+
+// Fraction of the stable timestep recommended, like the tau of Griebel et al.
+const SAFETY_FACTOR: f32 = 0.5;
+// Fewest cells along an axis of a recommended grid
+const MIN_CELLS: usize = 4;
+// Work arrays of the pressure solver and boundary updates, in f32 per cell, on top
+// of the fields
+const SCRATCH_VALUES: usize = 4;
+const BISECTION_STEPS: usize = 40;
+
+// What a scene needs resolved, in solver units
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridRequirements {
+    pub extent: [f32; 2],
+    pub reynolds: f32,
+    // Largest velocity expected anywhere in the flow
+    pub max_velocity: f32,
+    // Size of the smallest obstacle, gap or wall feature that matters
+    pub smallest_feature: f32,
+    pub cells_per_feature: f32,
+    // Largest reynolds * max_velocity * delta_space allowed, None for no limit. The
+    // donor cell upwinding keeps higher ones stable but smears the flow.
+    pub max_cell_reynolds: Option<f32>,
+    // Time the scene is run for
+    pub duration: f32,
+}
+
+impl GridRequirements {
+    pub fn new(extent: [f32; 2], reynolds: f32, smallest_feature: f32) -> Self {
+        Self {
+            extent,
+            reynolds,
+            max_velocity: 1.0,
+            smallest_feature,
+            cells_per_feature: 10.0,
+            max_cell_reynolds: None,
+            duration: 1.0,
+        }
+    }
+
+    pub fn with_max_velocity(mut self, max_velocity: f32) -> Self {
+        self.max_velocity = max_velocity;
+        self
+    }
+
+    pub fn with_cells_per_feature(mut self, cells_per_feature: f32) -> Self {
+        self.cells_per_feature = cells_per_feature;
+        self
+    }
+
+    pub fn with_max_cell_reynolds(mut self, max_cell_reynolds: Option<f32>) -> Self {
+        self.max_cell_reynolds = max_cell_reynolds;
+        self
+    }
+
+    pub fn with_duration(mut self, duration: f32) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    // Grid spacing meeting every requirement
+    fn target_spacing(&self) -> f32 {
+        let spacing = self.smallest_feature / self.cells_per_feature;
+        match self.max_cell_reynolds {
+            Some(cell_reynolds) => spacing.min(cell_reynolds / (self.reynolds * self.max_velocity)),
+            None => spacing,
+        }
+    }
+
+    fn validate(&self) {
+        assert!(
+            self.extent.iter().all(|&length| length > 0.0),
+            "extent must be positive"
+        );
+        assert!(
+            self.reynolds > 0.0 && self.max_velocity > 0.0,
+            "reynolds number and max velocity must be positive"
+        );
+        assert!(
+            self.smallest_feature > 0.0 && self.cells_per_feature > 0.0,
+            "smallest feature and cells per feature must be positive"
+        );
+        assert!(
+            self.max_cell_reynolds.is_none_or(|limit| limit > 0.0),
+            "max cell reynolds number must be positive"
+        );
+        assert!(self.duration >= 0.0, "duration must not be negative");
+    }
+}
+
+// Resources a run may take
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Budget {
+    pub memory: usize, // bytes
+    // Cells times timesteps, the wall time allowed times the cell updates per second
+    // the machine manages
+    pub cell_steps: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SizingIssue {
+    // The budget forced a coarser grid than the smallest feature asks for
+    UnderResolved { cells_per_feature: f32 },
+    CellReynoldsTooHigh { cell_reynolds: f32 },
+    // Even the coarsest grid exceeds the budget
+    OverMemory { memory: usize },
+    OverCellSteps { cell_steps: f64 },
+}
+
+impl fmt::Display for SizingIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SizingIssue::UnderResolved { cells_per_feature } => write!(
+                f,
+                "under resolved: {} cells across the smallest feature",
+                cells_per_feature
+            ),
+            SizingIssue::CellReynoldsTooHigh { cell_reynolds } => {
+                write!(f, "cell reynolds number too high: {}", cell_reynolds)
+            }
+            SizingIssue::OverMemory { memory } => {
+                write!(f, "over the memory budget: {} bytes", memory)
+            }
+            SizingIssue::OverCellSteps { cell_steps } => {
+                write!(f, "over the time budget: {} cell steps", cell_steps)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridRecommendation {
+    pub space_size: [usize; 2],
+    pub delta_space: [f32; 2],
+    pub delta_time: f32,
+    pub steps: usize,
+    // Estimates of the run
+    pub memory: usize, // bytes
+    pub cell_steps: f64,
+    // Reached by the grid
+    pub cells_per_feature: f32,
+    pub cell_reynolds: f32,
+    // Empty when the grid meets the requirements within the budget
+    pub issues: Vec<SizingIssue>,
+}
+
+impl GridRecommendation {
+    pub fn is_resolved(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    // Resamples the simulation onto the recommended grid and timestep. Its extent
+    // should be that of the requirements.
+    pub fn apply(&self, simulation: &mut Simulation) {
+        simulation.resample(self.space_size);
+        simulation.set_delta_time(self.delta_time);
+    }
+}
+
+impl fmt::Display for GridRecommendation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "grid: {}x{} cells of {:?}, delta time {} for {} steps",
+            self.space_size[0], self.space_size[1], self.delta_space, self.delta_time, self.steps
+        )?;
+        write!(
+            f,
+            "{} bytes, {} cell steps, {} cells per feature, cell reynolds number {}",
+            self.memory, self.cell_steps, self.cells_per_feature, self.cell_reynolds
+        )?;
+        for issue in &self.issues {
+            write!(f, "\n{}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+// Estimated memory of a simulation per cell, bytes
+pub fn bytes_per_cell() -> usize {
+    // cell_type, u, v, pressure, rhs, f, g, psi and dye of the fields
+    size_of::<CellType>() + (8 + SCRATCH_VALUES) * size_of::<f32>()
+}
+
+// Finest grid within the budget up to the spacing the requirements ask for, with a
+// timestep at a safety factor below the stable one. Falling short of the
+// requirements or the budget is reported in the issues rather than hidden, so an
+// under resolved run is a choice.
+pub fn recommend_grid(requirements: &GridRequirements, budget: &Budget) -> GridRecommendation {
+    requirements.validate();
+    let target = requirements.target_spacing();
+    let coarsest = requirements.extent[0].max(requirements.extent[1]) / MIN_CELLS as f32;
+    let fits = |grid: &GridRecommendation| {
+        grid.memory <= budget.memory && grid.cell_steps <= budget.cell_steps
+    };
+
+    let mut grid = sized(requirements, target);
+    if !fits(&grid) && target < coarsest {
+        // The cost falls as the spacing grows, find the finest spacing within budget
+        let [mut fine, mut coarse] = [target, coarsest];
+        for _ in 0..BISECTION_STEPS {
+            let spacing = (fine * coarse).sqrt();
+            if fits(&sized(requirements, spacing)) {
+                coarse = spacing;
+            } else {
+                fine = spacing;
+            }
+        }
+        grid = sized(requirements, coarse);
+    }
+
+    if grid.cells_per_feature < requirements.cells_per_feature * (1.0 - 1e-3) {
+        grid.issues.push(SizingIssue::UnderResolved {
+            cells_per_feature: grid.cells_per_feature,
+        });
+    }
+    if let Some(limit) = requirements.max_cell_reynolds {
+        if grid.cell_reynolds > limit * (1.0 + 1e-3) {
+            grid.issues.push(SizingIssue::CellReynoldsTooHigh {
+                cell_reynolds: grid.cell_reynolds,
+            });
+        }
+    }
+    if grid.memory > budget.memory {
+        grid.issues.push(SizingIssue::OverMemory {
+            memory: grid.memory,
+        });
+    }
+    if grid.cell_steps > budget.cell_steps {
+        grid.issues.push(SizingIssue::OverCellSteps {
+            cell_steps: grid.cell_steps,
+        });
+    }
+    grid
+}
+
+// Grid of about the given spacing fitted to the extent
+fn sized(requirements: &GridRequirements, spacing: f32) -> GridRecommendation {
+    let space_size = requirements
+        .extent
+        .map(|length| ((length / spacing).ceil() as usize).max(MIN_CELLS));
+    let delta_space = [
+        requirements.extent[0] / space_size[0] as f32,
+        requirements.extent[1] / space_size[1] as f32,
+    ];
+    let velocity = requirements.max_velocity;
+    let delta_time = SAFETY_FACTOR
+        * DeltaTimeLimits::new(requirements.reynolds, delta_space, [velocity, velocity]).stable();
+    let steps = (requirements.duration / delta_time).ceil() as usize;
+    let cells = space_size[0] * space_size[1];
+    let coarser = delta_space[0].max(delta_space[1]);
+    GridRecommendation {
+        space_size,
+        delta_space,
+        delta_time,
+        steps,
+        memory: cells * bytes_per_cell(),
+        cell_steps: cells as f64 * steps as f64,
+        cells_per_feature: requirements.smallest_feature / coarser,
+        cell_reynolds: requirements.reynolds * velocity * coarser,
+        issues: Vec::new(),
+    }
+}
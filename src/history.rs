@@ -0,0 +1,92 @@
+use crate::solver::{Checkpoint, FluidSolver};
+
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+
+// Recent states of a solver for scrubbing backwards. Every keyframe_interval-th
+// recorded state is kept and the states in between are re-simulated on rewind.
+// Parameters such as the acceleration are not part of a checkpoint, re-simulation
+// uses the current ones.
+pub struct History {
+    capacity: usize,
+    keyframe_interval: usize,
+    // (step, state), oldest first
+    keyframes: VecDeque<(usize, Checkpoint)>,
+    // Step of the state the solver is in, None before the first record
+    current_step: Option<usize>,
+    // States after the current one stay reachable until the next record
+    newest_step: usize,
+}
+
+impl History {
+    // Keeps the last capacity keyframes, a keyframe_interval of 1 stores every state
+    pub fn new(capacity: usize, keyframe_interval: usize) -> Self {
+        assert!(capacity > 0, "history capacity must be positive");
+        assert!(keyframe_interval > 0, "keyframe interval must be positive");
+        Self {
+            capacity,
+            keyframe_interval,
+            keyframes: VecDeque::with_capacity(capacity),
+            current_step: None,
+            newest_step: 0,
+        }
+    }
+
+    // Call with the initial state and after every timestep. Recording after a rewind
+    // forgets the states that were ahead of it.
+    pub fn record(&mut self, solver: &dyn FluidSolver) {
+        let step = self.current_step.map_or(0, |step| step + 1);
+        while self
+            .keyframes
+            .back()
+            .is_some_and(|(keyframe_step, _)| *keyframe_step >= step)
+        {
+            self.keyframes.pop_back();
+        }
+
+        if step.is_multiple_of(self.keyframe_interval) {
+            if self.keyframes.len() == self.capacity {
+                self.keyframes.pop_front();
+            }
+            self.keyframes.push_back((step, solver.checkpoint()));
+        }
+        self.current_step = Some(step);
+        self.newest_step = step;
+    }
+
+    pub fn current_step(&self) -> Option<usize> {
+        self.current_step
+    }
+
+    // Steps that can be restored
+    pub fn steps(&self) -> Option<RangeInclusive<usize>> {
+        let (oldest, _) = self.keyframes.front()?;
+        Some(*oldest..=self.newest_step)
+    }
+
+    // Restore the state of step, returns false if it is no longer or not yet recorded
+    pub fn rewind(&mut self, solver: &mut dyn FluidSolver, step: usize) -> bool {
+        if !self.steps().is_some_and(|steps| steps.contains(&step)) {
+            return false;
+        }
+        let (keyframe_step, keyframe) = self
+            .keyframes
+            .iter()
+            .rev()
+            .find(|(keyframe_step, _)| *keyframe_step <= step)
+            .unwrap();
+
+        solver.restore(keyframe);
+        for _ in *keyframe_step..step {
+            solver.iterate_one_timestep();
+        }
+        self.current_step = Some(step);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+        self.current_step = None;
+        self.newest_step = 0;
+    }
+}
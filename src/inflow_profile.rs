@@ -0,0 +1,110 @@
+use crate::cell::{BoundaryConditionCell, CellFlags, CellType};
+use crate::space_domain::{SpaceDomain, FACE_NEIGHBORS};
+
+// Velocity across an inlet, a run of neighbouring inflow cells facing the fluid on
+// the same side. The flow enters the fluid along the normal of the inlet, speeds are
+// meters/seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InflowProfile {
+    // The same speed across the inlet
+    Uniform { speed: f32 },
+    // Fully developed laminar flow between the ends of the inlet, Poiseuille's
+    // parabola with max_speed in the middle and two thirds of it on average
+    Parabolic { max_speed: f32 },
+    // Fully developed turbulent flow, a plug with thin boundary layers at the ends
+    // following the 1/7 power law in the distance to the nearer end
+    PowerLaw { max_speed: f32 },
+}
+
+impl InflowProfile {
+    // Speed at fraction of the way across the inlet, from 0 at one end to 1 at the
+    // other
+    pub fn speed(&self, fraction: f32) -> f32 {
+        let fraction = fraction.clamp(0.0, 1.0);
+        match *self {
+            InflowProfile::Uniform { speed } => speed,
+            InflowProfile::Parabolic { max_speed } => 4.0 * max_speed * fraction * (1.0 - fraction),
+            InflowProfile::PowerLaw { max_speed } => {
+                max_speed * (2.0 * fraction.min(1.0 - fraction)).powf(1.0 / 7.0)
+            }
+        }
+    }
+
+    // Sets the velocity of every inflow cell next to the fluid, each inlet taking the
+    // whole profile. Cells are sampled at their centers, so the speed at the ends of
+    // the inlet, at the walls, is never used.
+    pub fn apply(&self, space_domain: &mut SpaceDomain) {
+        for inlet in inlets(space_domain) {
+            let length = inlet.cells.len() as f32;
+            for (position, &(x, y)) in inlet.cells.iter().enumerate() {
+                let speed = self.speed((position as f32 + 0.5) / length);
+                let velocity = inlet.normal.map(|component| component as f32 * speed);
+                space_domain.set_velocity(x, y, velocity);
+            }
+        }
+    }
+}
+
+// Run of inflow cells along an edge of the fluid, in order along the edge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inlet {
+    // Unit offset from the inflow cells towards the fluid
+    pub normal: [isize; 2],
+    pub cells: Vec<(usize, usize)>,
+}
+
+// Inlets of the domain, ordered by normal and then along the edges. Inflow cells with
+// fluid on more than one side, say in a corner, belong to the inlet of the first side
+// in FACE_NEIGHBORS order.
+pub fn inlets(space_domain: &SpaceDomain) -> Vec<Inlet> {
+    let [nx, ny] = space_domain.space_size();
+    let mut cells: Vec<([isize; 2], (usize, usize))> = Vec::new();
+    for x in 0..nx {
+        for y in 0..ny {
+            if !matches!(
+                space_domain.cell_type(x, y),
+                CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell)
+            ) {
+                continue;
+            }
+            let flags = space_domain.flags(x, y);
+            let normal = FACE_NEIGHBORS
+                .into_iter()
+                .zip(CellFlags::FACE_NEIGHBOR_FLAGS)
+                .find(|&(_, flag)| flags.contains(flag));
+            if let Some((normal, _)) = normal {
+                cells.push((normal, (x, y)));
+            }
+        }
+    }
+    // Grouped by side, then by the line along the edge, then in order along it
+    cells.sort_by_key(|&(normal, (x, y))| {
+        if normal[0] != 0 {
+            (normal, x, y)
+        } else {
+            (normal, y, x)
+        }
+    });
+
+    let mut inlets: Vec<Inlet> = Vec::new();
+    for (normal, (x, y)) in cells {
+        let continues = inlets.last().is_some_and(|inlet| {
+            let &(last_x, last_y) = inlet.cells.last().unwrap();
+            inlet.normal == normal
+                && if normal[0] != 0 {
+                    last_x == x && last_y + 1 == y
+                } else {
+                    last_y == y && last_x + 1 == x
+                }
+        });
+        if continues {
+            inlets.last_mut().unwrap().cells.push((x, y));
+        } else {
+            inlets.push(Inlet {
+                normal,
+                cells: vec![(x, y)],
+            });
+        }
+    }
+    inlets
+}
@@ -0,0 +1,187 @@
+use crate::cell::{BoundaryConditionCell, CellType};
+use crate::perturbation::SplitMix64;
+use crate::space_domain::SpaceDomain;
+
+use std::f32::consts::PI;
+
+// Synthetic turbulence at the inflow cells by the digital filter method of Klein et
+// al. with the exponential time correlation of Xie and Castro. Every timestep draws
+// white noise per inflow cell, filters it with a Gaussian kernel of the length scale
+// along the inlet and blends it into the previous fluctuation, so the fluctuations
+// decorrelate over length scale / mean speed in time as frozen eddies passing by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InflowTurbulence {
+    // Root mean square of each velocity component relative to the mean inflow speed
+    pub intensity: f32,
+    pub length_scale: f32, // meters
+    pub seed: u64,
+}
+
+#[derive(Clone)]
+struct InletCell {
+    index: usize,
+    mean: [f32; 2],        // meters/seconds
+    fluctuation: [f32; 2], // meters/seconds
+    // Normalized weights of the inlet cells within two length scales
+    kernel: Vec<(u32, f32)>,
+}
+
+// State of the generator for one grid, the mean velocities are those of the inflow
+// cells when it was created
+#[derive(Clone)]
+pub struct TurbulentInflow {
+    turbulence: InflowTurbulence,
+    cells: Vec<InletCell>,
+    rng: SplitMix64,
+    // Reused between timesteps
+    noise: Vec<[f32; 2]>,
+}
+
+impl TurbulentInflow {
+    pub fn new(turbulence: InflowTurbulence, space_domain: &SpaceDomain) -> Self {
+        assert!(
+            turbulence.intensity >= 0.0,
+            "turbulence intensity must not be negative"
+        );
+        assert!(
+            turbulence.length_scale > 0.0,
+            "turbulence length scale must be positive"
+        );
+        let [nx, ny] = space_domain.space_size();
+        let [dx, dy] = space_domain.delta_space();
+        let fields = space_domain.fields();
+
+        let inlet: Vec<usize> = (0..nx * ny)
+            .filter(|&index| {
+                matches!(
+                    fields.cell_type[index],
+                    CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell)
+                )
+            })
+            .collect();
+        let position = |index: usize| [(index / ny) as f32 * dx, (index % ny) as f32 * dy];
+        let length_scale = turbulence.length_scale;
+        let cells = inlet
+            .iter()
+            .map(|&index| {
+                let [x, y] = position(index);
+                let mut kernel: Vec<(u32, f32)> = inlet
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(other, &other_index)| {
+                        let [other_x, other_y] = position(other_index);
+                        let squared_distance = (x - other_x).powi(2) + (y - other_y).powi(2);
+                        (squared_distance <= (2.0 * length_scale).powi(2)).then(|| {
+                            let weight =
+                                (-PI * squared_distance / (2.0 * length_scale.powi(2))).exp();
+                            (other as u32, weight)
+                        })
+                    })
+                    .collect();
+                // Unit variance of the filtered noise
+                let norm = kernel
+                    .iter()
+                    .map(|(_, weight)| weight.powi(2))
+                    .sum::<f32>()
+                    .sqrt();
+                for (_, weight) in kernel.iter_mut() {
+                    *weight /= norm;
+                }
+                InletCell {
+                    index,
+                    mean: [fields.u[index], fields.v[index]],
+                    fluctuation: [0.0, 0.0],
+                    kernel,
+                }
+            })
+            .collect();
+
+        Self {
+            turbulence,
+            cells,
+            rng: SplitMix64::new(turbulence.seed),
+            noise: vec![[0.0, 0.0]; inlet.len()],
+        }
+    }
+
+    pub fn turbulence(&self) -> InflowTurbulence {
+        self.turbulence
+    }
+
+    // Number of inflow cells driven by the generator
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    // Mean velocity of every inflow cell, like SpaceDomain::set_inflow_velocity
+    pub fn set_mean_velocity(&mut self, velocity: [f32; 2]) {
+        for cell in self.cells.iter_mut() {
+            cell.mean = velocity;
+        }
+    }
+
+    // Advances the fluctuations by delta_time and sets the inflow cells to mean plus
+    // fluctuation. The fluctuations average to zero over the inlet so the inflow rate
+    // stays that of the mean velocities.
+    pub fn advance(&mut self, delta_time: f32, space_domain: &mut SpaceDomain) {
+        for noise in self.noise.iter_mut() {
+            *noise = [self.rng.next_normal(), self.rng.next_normal()];
+        }
+
+        let mut sum = [0.0; 2];
+        for cell in self.cells.iter_mut() {
+            let speed = (cell.mean[0].powi(2) + cell.mean[1].powi(2)).sqrt();
+            if speed == 0.0 {
+                cell.fluctuation = [0.0, 0.0];
+                continue;
+            }
+            let time_scale = self.turbulence.length_scale / speed;
+            let memory = (-PI * delta_time / (2.0 * time_scale)).exp();
+            let renewal = (1.0 - (-PI * delta_time / time_scale).exp()).sqrt();
+            let rms = self.turbulence.intensity * speed;
+            for (component, (fluctuation, sum)) in
+                cell.fluctuation.iter_mut().zip(sum.iter_mut()).enumerate()
+            {
+                let filtered: f32 = cell
+                    .kernel
+                    .iter()
+                    .map(|&(other, weight)| weight * self.noise[other as usize][component])
+                    .sum();
+                *fluctuation = memory * *fluctuation + renewal * rms * filtered;
+                *sum += *fluctuation;
+            }
+        }
+
+        let mean_fluctuation = sum.map(|sum| sum / self.cells.len().max(1) as f32);
+        let fields = space_domain.fields_mut();
+        for cell in self.cells.iter_mut() {
+            for (fluctuation, mean) in cell.fluctuation.iter_mut().zip(mean_fluctuation) {
+                *fluctuation -= mean;
+            }
+            // The geometry may have changed since the generator was created
+            if let CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell) =
+                fields.cell_type[cell.index]
+            {
+                fields.u[cell.index] = cell.mean[0] + cell.fluctuation[0];
+                fields.v[cell.index] = cell.mean[1] + cell.fluctuation[1];
+            }
+        }
+    }
+
+    // Sets the inflow cells back to their mean velocities
+    pub fn reset(&self, space_domain: &mut SpaceDomain) {
+        let fields = space_domain.fields_mut();
+        for cell in self.cells.iter() {
+            if let CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell) =
+                fields.cell_type[cell.index]
+            {
+                fields.u[cell.index] = cell.mean[0];
+                fields.v[cell.index] = cell.mean[1];
+            }
+        }
+    }
+}
@@ -0,0 +1,458 @@
+use crate::cell::BoundaryConditionCell;
+use crate::cell::Cell;
+use crate::cell::CellType;
+use crate::colormap::RangeMode;
+use crate::memory::{self, AllocationError};
+use crate::presets;
+use crate::solver::{Checkpoint, FluidSolver};
+use crate::space_domain::{Fields, SpaceDomain};
+
+// D2Q9 lattice
+const VELOCITIES: [[i32; 2]; 9] = [
+    [0, 0],
+    [1, 0],
+    [0, 1],
+    [-1, 0],
+    [0, -1],
+    [1, 1],
+    [-1, 1],
+    [-1, -1],
+    [1, -1],
+];
+const WEIGHTS: [f32; 9] = [
+    4.0 / 9.0,
+    1.0 / 9.0,
+    1.0 / 9.0,
+    1.0 / 9.0,
+    1.0 / 9.0,
+    1.0 / 36.0,
+    1.0 / 36.0,
+    1.0 / 36.0,
+    1.0 / 36.0,
+];
+const OPPOSITE: [usize; 9] = [0, 3, 4, 1, 2, 7, 8, 5, 6];
+
+// Lattice velocity of the fastest boundary, kept low for a small Mach number
+const MAX_LATTICE_VELOCITY: f32 = 0.1;
+
+// Single relaxation time (BGK) lattice Boltzmann solver.
+// Uses the same cell types and presets as the Navier-Stokes solver and writes
+// the macroscopic fields back into the cells, so both can be viewed the same way.
+pub struct LatticeBoltzmann {
+    space_domain: SpaceDomain,
+    distributions: Vec<[f32; 9]>,
+
+    delta_time: f32,        // seconds, advanced per iterate_one_timestep
+    substeps: usize,        // lattice steps per delta_time
+    acceleration: [f32; 2], // meters/seconds^2
+    tau: f32,               // relaxation time
+    time: f32,              // seconds
+}
+
+impl LatticeBoltzmann {
+    pub fn from_preset(preset: presets::SimulationPreset) -> Self {
+        Self::try_from_preset(preset).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    // from_preset with the distributions, nine values per cell, reserved fallibly
+    pub fn try_from_preset(preset: presets::SimulationPreset) -> Result<Self, AllocationError> {
+        let space_domain = preset.space_domain;
+        let delta_space = space_domain.delta_space();
+        assert!(
+            (delta_space[0] - delta_space[1]).abs() <= 1e-4 * delta_space[0],
+            "lattice Boltzmann requires square cells"
+        );
+
+        // Fastest prescribed velocity in the domain sets the lattice time step
+        let fields = space_domain.fields();
+        let max_velocity = (0..fields.len())
+            .map(|index| fields.cell(index))
+            .map(|cell| match cell.cell_type {
+                CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                    boundary_condition_velocity,
+                }) => boundary_condition_velocity,
+                _ => cell.velocity,
+            })
+            .map(|velocity| velocity[0].abs().max(velocity[1].abs()))
+            .fold(0.0, f32::max)
+            .max(1e-6);
+        let lattice_delta_time = MAX_LATTICE_VELOCITY * delta_space[0] / max_velocity;
+        let substeps = (preset.delta_time / lattice_delta_time).ceil().max(1.0) as usize;
+        let lattice_delta_time = preset.delta_time / substeps as f32;
+
+        // Nondimensional Navier-Stokes equations, viscosity is 1 / Re
+        let lattice_viscosity = lattice_delta_time / (preset.reynolds * delta_space[0].powi(2));
+
+        let space_size = space_domain.space_size();
+        let distributions =
+            memory::try_with_capacity(space_size[0] * space_size[1], space_size, "distributions")?;
+        let mut solver = Self {
+            space_domain,
+            distributions,
+            delta_time: preset.delta_time,
+            substeps,
+            acceleration: preset.acceleration,
+            tau: 3.0 * lattice_viscosity + 0.5,
+            time: 0.0,
+        };
+        solver.reset_distributions();
+        solver.update_macroscopic_fields();
+        Ok(solver)
+    }
+
+    pub fn tau(&self) -> f32 {
+        self.tau
+    }
+
+    pub fn substeps(&self) -> usize {
+        self.substeps
+    }
+
+    // Lattice density of the cell, 1 at the reference pressure
+    pub fn density(&self, x: usize, y: usize) -> f32 {
+        moments(&self.distributions[self.index(x, y)]).0
+    }
+
+    // Physical velocity of one lattice velocity unit
+    fn velocity_scale(&self) -> f32 {
+        self.space_domain.delta_space()[0] * self.substeps as f32 / self.delta_time
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        x * self.space_domain.space_size()[1] + y
+    }
+
+    // Equilibrium at the cell velocities, refilled in place
+    fn reset_distributions(&mut self) {
+        let space_size = self.space_domain.space_size();
+        let velocity_scale = self.velocity_scale();
+
+        let mut distributions = std::mem::take(&mut self.distributions);
+        distributions.clear();
+        distributions.extend(
+            (0..space_size[0])
+                .flat_map(|x| (0..space_size[1]).map(move |y| (x, y)))
+                .map(|(x, y)| {
+                    let velocity = match self.space_domain.cell_type(x, y) {
+                        CellType::FluidCell => self.space_domain.get_centered_velocity(x, y),
+                        _ => self.space_domain.velocity(x, y),
+                    };
+                    equilibrium(
+                        1.0,
+                        [velocity[0] / velocity_scale, velocity[1] / velocity_scale],
+                    )
+                }),
+        );
+        self.distributions = distributions;
+    }
+
+    fn lattice_step(&mut self) {
+        let space_size = self.space_domain.space_size();
+        let velocity_scale = self.velocity_scale();
+        let lattice_acceleration = [
+            self.acceleration[0] * self.delta_time / self.substeps as f32 / velocity_scale,
+            self.acceleration[1] * self.delta_time / self.substeps as f32 / velocity_scale,
+        ];
+
+        // Collision, and open boundaries behave like reservoirs
+        let mut post_collision = self.distributions.clone();
+        for x in 0..space_size[0] {
+            for y in 0..space_size[1] {
+                let index = self.index(x, y);
+                match self.space_domain.cell_type(x, y) {
+                    CellType::FluidCell => {
+                        let (density, velocity) = moments(&self.distributions[index]);
+                        // Body force through a velocity shift of the equilibrium
+                        let shifted_velocity = [
+                            velocity[0] + self.tau * lattice_acceleration[0],
+                            velocity[1] + self.tau * lattice_acceleration[1],
+                        ];
+                        let equilibrium = equilibrium(density, shifted_velocity);
+                        for i in 0..9 {
+                            post_collision[index][i] -=
+                                (self.distributions[index][i] - equilibrium[i]) / self.tau;
+                        }
+                    }
+                    CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell) => {
+                        let velocity = self.space_domain.velocity(x, y);
+                        let density = self
+                            .fluid_neighbor(x, y)
+                            .map(|neighbor| moments(&self.distributions[neighbor]).0)
+                            .unwrap_or(1.0);
+                        post_collision[index] = equilibrium(
+                            density,
+                            [velocity[0] / velocity_scale, velocity[1] / velocity_scale],
+                        );
+                    }
+                    CellType::BoundaryConditionCell(BoundaryConditionCell::OutFlowCell) => {
+                        // Zero gradient, with the density pinned to the reference density
+                        if let Some(neighbor) = self.fluid_neighbor(x, y) {
+                            let (_, velocity) = moments(&self.distributions[neighbor]);
+                            post_collision[index] = equilibrium(1.0, velocity);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // Streaming with halfway bounce-back on walls
+        for x in 0..space_size[0] {
+            for y in 0..space_size[1] {
+                if !matches!(self.space_domain.cell_type(x, y), CellType::FluidCell) {
+                    continue;
+                }
+                let index = self.index(x, y);
+
+                for i in 0..9 {
+                    let offset = [-VELOCITIES[i][0] as isize, -VELOCITIES[i][1] as isize];
+                    // Outside the domain behaves like a void cell
+                    let Some((source_x, source_y)) =
+                        self.space_domain.neighbor_position(x, y, offset)
+                    else {
+                        self.distributions[index][i] = post_collision[index][OPPOSITE[i]];
+                        continue;
+                    };
+                    let source_index = self.index(source_x, source_y);
+
+                    self.distributions[index][i] = match self
+                        .space_domain
+                        .cell_type(source_x, source_y)
+                    {
+                        CellType::FluidCell
+                        | CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell)
+                        | CellType::BoundaryConditionCell(BoundaryConditionCell::OutFlowCell) => {
+                            post_collision[source_index][i]
+                        }
+                        CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                            boundary_condition_velocity,
+                        }) => {
+                            // Collision conserves mass, so this is also the density before
+                            // streaming overwrote part of the cell
+                            let (density, _) = moments(&post_collision[index]);
+                            let wall_velocity = [
+                                boundary_condition_velocity[0] / velocity_scale,
+                                boundary_condition_velocity[1] / velocity_scale,
+                            ];
+                            post_collision[index][OPPOSITE[i]]
+                                + 6.0
+                                    * WEIGHTS[i]
+                                    * density
+                                    * (VELOCITIES[i][0] as f32 * wall_velocity[0]
+                                        + VELOCITIES[i][1] as f32 * wall_velocity[1])
+                        }
+                        CellType::BoundaryConditionCell(BoundaryConditionCell::FreeSlipCell) => {
+                            let (reflected_index, direction) = self.specular_reflection(x, y, i);
+                            post_collision[reflected_index][direction]
+                        }
+                        CellType::BoundaryConditionCell(BoundaryConditionCell::PeriodicCell) => {
+                            let (wrapped_x, wrapped_y) =
+                                self.space_domain.periodic_source(source_x, source_y);
+                            post_collision[self.index(wrapped_x, wrapped_y)][i]
+                        }
+                        CellType::VoidCell => post_collision[index][OPPOSITE[i]],
+                    };
+                }
+            }
+        }
+    }
+
+    // Cell and direction of the particle that arrives in (x, y) in direction i after a
+    // specular reflection halfway to a free slip wall. Along a flat wall it left the
+    // fluid neighbour beside (x, y) towards the wall, in corners it bounces back.
+    fn specular_reflection(&self, x: usize, y: usize, i: usize) -> (usize, usize) {
+        let [cx, cy] = VELOCITIES[i];
+        let bounce_back = (self.index(x, y), OPPOSITE[i]);
+        if cx == 0 || cy == 0 {
+            return bounce_back;
+        }
+
+        let fluid_neighbor = |offset: [isize; 2]| {
+            self.space_domain
+                .neighbor_position(x, y, offset)
+                .filter(|&(nx, ny)| {
+                    matches!(self.space_domain.cell_type(nx, ny), CellType::FluidCell)
+                })
+        };
+        let direction = |c: [i32; 2]| VELOCITIES.iter().position(|v| *v == c).unwrap();
+        match (
+            fluid_neighbor([-cx as isize, 0]),
+            fluid_neighbor([0, -cy as isize]),
+        ) {
+            // Wall behind in x, the particle travelled along it from the cell below or above
+            (None, Some((nx, ny))) => (self.index(nx, ny), direction([-cx, cy])),
+            // Wall behind in y, the particle travelled along it from the cell beside
+            (Some((nx, ny)), None) => (self.index(nx, ny), direction([cx, -cy])),
+            _ => bounce_back,
+        }
+    }
+
+    fn fluid_neighbor(&self, x: usize, y: usize) -> Option<usize> {
+        self.space_domain
+            .face_neighbors(x, y)
+            .find(|(_, cell)| matches!(cell.cell_type, CellType::FluidCell))
+            .map(|((nx, ny), _)| self.index(nx, ny))
+    }
+
+    // Write velocity and pressure back into the staggered cells
+    fn update_macroscopic_fields(&mut self) {
+        let space_size = self.space_domain.space_size();
+        let velocity_scale = self.velocity_scale();
+
+        let centered: Vec<(f32, [f32; 2])> = self
+            .distributions
+            .iter()
+            .map(|distribution| {
+                let (density, velocity) = moments(distribution);
+                (
+                    density,
+                    [velocity[0] * velocity_scale, velocity[1] * velocity_scale],
+                )
+            })
+            .collect();
+
+        for x in 0..space_size[0] {
+            for y in 0..space_size[1] {
+                if !matches!(self.space_domain.cell_type(x, y), CellType::FluidCell) {
+                    continue;
+                }
+                let (density, velocity) = centered[self.index(x, y)];
+
+                // Outside the domain the face is closed like next to a void cell
+                let face_velocity = |offset: [isize; 2], component: usize| match (
+                    self.space_domain.neighbor_position(x, y, offset),
+                    self.space_domain.neighbor_type(x, y, offset),
+                ) {
+                    (Some((nx, ny)), CellType::FluidCell) => {
+                        (velocity[component] + centered[self.index(nx, ny)].1[component]) / 2.0
+                    }
+                    (
+                        Some((nx, ny)),
+                        CellType::BoundaryConditionCell(BoundaryConditionCell::PeriodicCell),
+                    ) => {
+                        let (source_x, source_y) = self.space_domain.periodic_source(nx, ny);
+                        (velocity[component]
+                            + centered[self.index(source_x, source_y)].1[component])
+                            / 2.0
+                    }
+                    (
+                        _,
+                        CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                            ..
+                        })
+                        | CellType::BoundaryConditionCell(BoundaryConditionCell::FreeSlipCell)
+                        | CellType::VoidCell,
+                    ) => 0.0,
+                    _ => velocity[component],
+                };
+                let u = face_velocity([1, 0], 0);
+                let v = face_velocity([0, 1], 1);
+
+                self.space_domain.set_velocity(x, y, [u, v]);
+                // p = c_s^2 (rho - 1) with unit reference density
+                *self.space_domain.pressure_mut(x, y) =
+                    (density - 1.0) / 3.0 * velocity_scale.powi(2);
+            }
+        }
+
+        // Faces on the left and bottom of the fluid are stored in the periodic cells
+        self.space_domain.update_periodic_cells();
+        self.space_domain.update_psi();
+        self.space_domain.update_pressure_and_speed_range();
+    }
+}
+
+impl FluidSolver for LatticeBoltzmann {
+    fn iterate_one_timestep(&mut self) {
+        for _ in 0..self.substeps {
+            self.lattice_step();
+        }
+        self.update_macroscopic_fields();
+        self.time += self.delta_time;
+    }
+
+    fn time(&self) -> f32 {
+        self.time
+    }
+
+    fn delta_space(&self) -> [f32; 2] {
+        self.space_domain.delta_space()
+    }
+
+    fn space_size(&self) -> [usize; 2] {
+        self.space_domain.space_size()
+    }
+
+    fn get_cell(&self, x: usize, y: usize) -> Cell {
+        self.space_domain.get_cell(x, y)
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+        self.space_domain.cells()
+    }
+
+    fn fields(&self) -> &Fields {
+        self.space_domain.fields()
+    }
+
+    fn get_centered_velocity(&self, x: usize, y: usize) -> [f32; 2] {
+        self.space_domain.get_centered_velocity(x, y)
+    }
+
+    fn pressure_range(&self) -> [f32; 2] {
+        self.space_domain.pressure_range()
+    }
+
+    fn speed_range(&self) -> [f32; 2] {
+        self.space_domain.speed_range()
+    }
+
+    fn set_range_modes(&mut self, pressure: RangeMode, speed: RangeMode) {
+        self.space_domain.set_range_modes(pressure, speed);
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            time: self.time,
+            space_size: self.space_domain.space_size(),
+            cells: self.space_domain.cells(),
+            metadata: None,
+        }
+    }
+
+    // Distributions are rebuilt at equilibrium from the stored macroscopic fields
+    fn restore(&mut self, checkpoint: &Checkpoint) {
+        assert_eq!(
+            checkpoint.space_size,
+            self.space_domain.space_size(),
+            "checkpoint size mismatch"
+        );
+        self.space_domain.set_cells(&checkpoint.cells);
+        self.time = checkpoint.time;
+        self.reset_distributions();
+        self.update_macroscopic_fields();
+    }
+}
+
+fn moments(distribution: &[f32; 9]) -> (f32, [f32; 2]) {
+    let density: f32 = distribution.iter().sum();
+    let momentum = distribution
+        .iter()
+        .zip(VELOCITIES.iter())
+        .fold([0.0, 0.0], |momentum, (f, c)| {
+            [momentum[0] + f * c[0] as f32, momentum[1] + f * c[1] as f32]
+        });
+    (density, [momentum[0] / density, momentum[1] / density])
+}
+
+fn equilibrium(density: f32, velocity: [f32; 2]) -> [f32; 9] {
+    let velocity_squared = velocity[0].powi(2) + velocity[1].powi(2);
+    let mut equilibrium = [0.0; 9];
+    for i in 0..9 {
+        let c_dot_u = VELOCITIES[i][0] as f32 * velocity[0] + VELOCITIES[i][1] as f32 * velocity[1];
+        equilibrium[i] = WEIGHTS[i]
+            * density
+            * (1.0 + 3.0 * c_dot_u + 4.5 * c_dot_u.powi(2) - 1.5 * velocity_squared);
+    }
+    equilibrium
+}
@@ -1,4 +1,71 @@
+pub mod acoustics;
+pub mod boundary_rule;
+pub mod boundary_segments;
 pub mod cell;
+pub mod colormap;
+pub mod control;
+pub mod dirty_regions;
+pub mod distributed;
+pub mod energy_budget;
+pub mod ensemble;
+pub mod events;
+pub mod experiments;
+pub mod ffi;
+pub mod field;
+pub mod field_average;
+pub mod field_snapshot;
+pub mod fluid_zones;
+pub mod flux_monitor;
+pub mod forces;
+pub mod forcing;
+pub mod frame_renderer;
+pub mod ftle;
+pub mod grid_sizing;
+pub mod history;
+pub mod inflow_profile;
+pub mod inflow_turbulence;
+pub mod lattice_boltzmann;
+pub mod lic;
+pub mod linear_solver;
+pub mod membrane;
+pub mod memory;
+pub mod mesh_import;
+pub mod metadata;
+pub mod netcdf;
+pub mod observer;
+pub mod obstacles;
+pub mod output_sink;
+pub mod parameters;
+pub mod particles;
+pub mod pathlines;
+pub mod perturbation;
+pub mod png;
 pub mod presets;
+pub mod preview;
+pub mod projection_error;
+pub mod quiver;
+pub mod reduction;
+pub mod region_statistics;
+pub mod resample;
+pub mod run_controller;
+pub mod sediment;
+pub mod sensitivity;
+pub mod separation;
+pub mod shallow_water;
+pub mod shedding;
 pub mod simulation;
+pub mod simulation_runner;
+pub mod snapshot_writer;
+pub mod solver;
 pub mod space_domain;
+pub mod stability;
+pub mod streamfunction_vorticity;
+pub mod svg_export;
+pub mod telemetry;
+pub mod terrain;
+pub mod units;
+pub mod validation;
+pub mod vortex;
+pub mod wall_shear;
+#[cfg(feature = "wasm")]
+pub mod wasm;
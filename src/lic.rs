@@ -0,0 +1,102 @@
+use rayon::prelude::*;
+
+// Streamline steps traced in each direction, in half pixels
+const KERNEL_LENGTH: usize = 30;
+
+// Line integral convolution of white noise along the flow. velocity returns None
+// outside the fluid, those pixels stay black. Returns width * height grayscale
+// values, rows top to bottom.
+pub fn line_integral_convolution(
+    domain_size: [f32; 2], // meters
+    width: usize,
+    height: usize,
+    velocity: impl Fn([f32; 2]) -> Option<[f32; 2]> + Sync,
+) -> Vec<u8> {
+    assert!(width > 0 && height > 0, "image must not be empty");
+    let pixel_size = [
+        domain_size[0] / width as f32,
+        domain_size[1] / height as f32,
+    ];
+
+    // Pixel coordinates with y pointing up, to physical coordinates
+    let to_physical = |pixel: [f32; 2]| [pixel[0] * pixel_size[0], pixel[1] * pixel_size[1]];
+
+    let noise = |x: f32, y: f32| -> f32 {
+        let (x, y) = (x.floor(), y.floor());
+        if x < 0.0 || y < 0.0 || x >= width as f32 || y >= height as f32 {
+            return 0.5;
+        }
+        white_noise(x as usize, y as usize)
+    };
+
+    let convolved: Vec<Option<f32>> = (0..height)
+        .into_par_iter()
+        .flat_map_iter(|row| {
+            let y = (height - 1 - row) as f32 + 0.5;
+            let velocity = &velocity;
+            (0..width).map(move |column| {
+                let start = [column as f32 + 0.5, y];
+                velocity(to_physical(start))?;
+
+                let mut sum = noise(start[0], start[1]);
+                let mut count = 1.0;
+                for direction in [1.0, -1.0] {
+                    let mut point = start;
+                    for _ in 0..KERNEL_LENGTH {
+                        let Some(flow) = velocity(to_physical(point)) else {
+                            break;
+                        };
+                        // Direction in pixel space, half a pixel per step
+                        let flow = [flow[0] / pixel_size[0], flow[1] / pixel_size[1]];
+                        let length = (flow[0].powi(2) + flow[1].powi(2)).sqrt();
+                        if length == 0.0 || !length.is_finite() {
+                            break;
+                        }
+                        point = [
+                            point[0] + direction * 0.5 * flow[0] / length,
+                            point[1] + direction * 0.5 * flow[1] / length,
+                        ];
+                        sum += noise(point[0], point[1]);
+                        count += 1.0;
+                    }
+                }
+                Some(sum / count)
+            })
+        })
+        .collect();
+
+    // Averaging shrinks the contrast, stretch mean +- 2 standard deviations to the
+    // full gray range
+    let values: Vec<f32> = convolved.iter().flatten().copied().collect();
+    if values.is_empty() {
+        return vec![0; width * height];
+    }
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    let deviation = (values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f32>()
+        / values.len() as f32)
+        .sqrt()
+        .max(f32::EPSILON);
+
+    convolved
+        .into_iter()
+        .map(|value| match value {
+            Some(value) => {
+                ((value - mean) / (4.0 * deviation) * 255.0 + 127.5).clamp(0.0, 255.0) as u8
+            }
+            None => 0,
+        })
+        .collect()
+}
+
+// Deterministic hash noise in [0, 1)
+fn white_noise(x: usize, y: usize) -> f32 {
+    let mut hash = (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
@@ -0,0 +1,325 @@
+use crate::cell::{BoundaryConditionCell, CellType};
+use crate::space_domain::SpaceDomain;
+
+use rayon::prelude::*;
+
+// Matrix-free linear system A x = b, the solvers only ever multiply by A
+pub trait LinearOperator {
+    // Number of unknowns
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // y = A x
+    fn apply(&self, x: &[f32], y: &mut [f32]);
+
+    // Entry A[unknown][unknown], for smoothers and Jacobi preconditioning
+    fn diagonal(&self, unknown: usize) -> f32;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolveReport {
+    pub iterations: usize,
+    // RMS of b - A x at the end
+    pub residual: f32,
+    pub converged: bool,
+}
+
+// For symmetric positive (semi)definite operators, improves x in place until the RMS
+// residual is below tolerance
+pub fn conjugate_gradient(
+    operator: &impl LinearOperator,
+    b: &[f32],
+    x: &mut [f32],
+    tolerance: f32,
+    max_iterations: usize,
+) -> SolveReport {
+    let n = check_sizes(operator, b, x);
+    let mut r = residual(operator, b, x);
+    let mut p = r.clone();
+    let mut ap = vec![0.0; n];
+    let mut rr = dot(&r, &r);
+
+    for iteration in 0..max_iterations {
+        if rms(rr, n) < tolerance {
+            return report(iteration, rr, n, tolerance);
+        }
+        operator.apply(&p, &mut ap);
+        let pap = dot(&p, &ap);
+        if pap <= 0.0 {
+            // p lies in the null space, no further progress possible
+            return report(iteration, rr, n, tolerance);
+        }
+        let alpha = (rr / pap) as f32;
+        axpy(alpha, &p, x);
+        axpy(-alpha, &ap, &mut r);
+
+        let rr_new = dot(&r, &r);
+        let beta = (rr_new / rr) as f32;
+        for (p, r) in p.iter_mut().zip(r.iter()) {
+            *p = r + beta * *p;
+        }
+        rr = rr_new;
+    }
+    report(max_iterations, rr, n, tolerance)
+}
+
+// Stabilized biconjugate gradients, for operators that are not symmetric. Every
+// iteration applies the operator twice.
+pub fn bicgstab(
+    operator: &impl LinearOperator,
+    b: &[f32],
+    x: &mut [f32],
+    tolerance: f32,
+    max_iterations: usize,
+) -> SolveReport {
+    let n = check_sizes(operator, b, x);
+    let mut r = residual(operator, b, x);
+    let shadow = r.clone();
+    let mut p = vec![0.0; n];
+    let mut v = vec![0.0; n];
+    let mut s = vec![0.0; n];
+    let mut t = vec![0.0; n];
+    let (mut rho, mut alpha, mut omega) = (1.0, 1.0, 1.0);
+    let mut rr = dot(&r, &r);
+
+    for iteration in 0..max_iterations {
+        if rms(rr, n) < tolerance {
+            return report(iteration, rr, n, tolerance);
+        }
+        let rho_new = dot(&shadow, &r);
+        if rho_new == 0.0 || omega == 0.0 {
+            // Breakdown, the shadow residual became orthogonal to the residual
+            return report(iteration, rr, n, tolerance);
+        }
+        let beta = ((rho_new / rho) * (alpha / omega)) as f32;
+        for ((p, r), v) in p.iter_mut().zip(r.iter()).zip(v.iter()) {
+            *p = r + beta * (*p - omega as f32 * v);
+        }
+        operator.apply(&p, &mut v);
+        let shadow_v = dot(&shadow, &v);
+        if shadow_v == 0.0 {
+            return report(iteration, rr, n, tolerance);
+        }
+        alpha = rho_new / shadow_v;
+        for ((s, r), v) in s.iter_mut().zip(r.iter()).zip(v.iter()) {
+            *s = r - alpha as f32 * v;
+        }
+
+        let ss = dot(&s, &s);
+        if rms(ss, n) < tolerance {
+            axpy(alpha as f32, &p, x);
+            return report(iteration + 1, ss, n, tolerance);
+        }
+        operator.apply(&s, &mut t);
+        let tt = dot(&t, &t);
+        omega = if tt > 0.0 { dot(&t, &s) / tt } else { 0.0 };
+        axpy(alpha as f32, &p, x);
+        axpy(omega as f32, &s, x);
+        for ((r, s), t) in r.iter_mut().zip(s.iter()).zip(t.iter()) {
+            *r = s - omega as f32 * t;
+        }
+        rr = dot(&r, &r);
+        rho = rho_new;
+    }
+    report(max_iterations, rr, n, tolerance)
+}
+
+fn check_sizes(operator: &impl LinearOperator, b: &[f32], x: &[f32]) -> usize {
+    let n = operator.len();
+    assert!(
+        b.len() == n && x.len() == n,
+        "operator of {n} unknowns applied to vectors of {} and {}",
+        b.len(),
+        x.len()
+    );
+    n
+}
+
+fn residual(operator: &impl LinearOperator, b: &[f32], x: &[f32]) -> Vec<f32> {
+    let mut r = vec![0.0; b.len()];
+    operator.apply(x, &mut r);
+    for (r, b) in r.iter_mut().zip(b.iter()) {
+        *r = b - *r;
+    }
+    r
+}
+
+// Accumulated in f64, the solvers divide by differences of these
+fn dot(a: &[f32], b: &[f32]) -> f64 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(&a, &b)| a as f64 * b as f64)
+        .sum()
+}
+
+// y += a x
+fn axpy(a: f32, x: &[f32], y: &mut [f32]) {
+    for (y, x) in y.iter_mut().zip(x.iter()) {
+        *y += a * x;
+    }
+}
+
+fn rms(squared_sum: f64, n: usize) -> f32 {
+    (squared_sum / n.max(1) as f64).sqrt() as f32
+}
+
+fn report(iterations: usize, squared_sum: f64, n: usize, tolerance: f32) -> SolveReport {
+    let residual = rms(squared_sum, n);
+    SolveReport {
+        iterations,
+        residual,
+        converged: residual < tolerance,
+    }
+}
+
+// Neighbours of the five point stencil
+pub(crate) const STENCIL_OFFSETS: [[isize; 2]; 4] = [[1, 0], [-1, 0], [0, 1], [0, -1]];
+
+// The discrete Laplacian of the pressure on the grid, the one definition behind the
+// SOR sweep, its residual and PoissonOperator. The boundary handling is
+// SpaceDomain::pressure_neighbor_position, walls mirror the pressure of the fluid cell.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PoissonStencil {
+    // dx^2 and dy^2
+    squared_deltas: [f32; 2],
+}
+
+impl PoissonStencil {
+    pub(crate) fn new(delta_space: [f32; 2]) -> Self {
+        Self {
+            squared_deltas: delta_space.map(|delta| delta.powi(2)),
+        }
+    }
+
+    pub(crate) fn weight(&self, offset: [isize; 2]) -> f32 {
+        1.0 / self.squared_deltas[(offset[0] == 0) as usize]
+    }
+
+    // Coefficient of the cell itself, with walls counted as neighbours
+    pub(crate) fn diagonal(&self) -> f32 {
+        2.0 / self.squared_deltas[0] + 2.0 / self.squared_deltas[1]
+    }
+
+    // Weighted pressures of the neighbours of the fluid cell (x, y)
+    pub(crate) fn neighbor_sum(&self, space_domain: &SpaceDomain, x: usize, y: usize) -> f32 {
+        let pressure = |offset| space_domain.neighbor_pressure(x, y, offset);
+        (pressure([1, 0]) + pressure([-1, 0])) / self.squared_deltas[0]
+            + (pressure([0, 1]) + pressure([0, -1])) / self.squared_deltas[1]
+    }
+
+    pub(crate) fn laplacian(&self, space_domain: &SpaceDomain, x: usize, y: usize) -> f32 {
+        self.neighbor_sum(space_domain, x, y) - self.diagonal() * space_domain.pressure(x, y)
+    }
+
+    // Gauss-Seidel pressure of the fluid cell (x, y) over-relaxed by omega
+    pub(crate) fn relaxed_pressure(
+        &self,
+        space_domain: &SpaceDomain,
+        omega: f32,
+        x: usize,
+        y: usize,
+    ) -> f32 {
+        (1.0 - omega) * space_domain.pressure(x, y)
+            + omega * (self.neighbor_sum(space_domain, x, y) - space_domain.rhs(x, y))
+                / self.diagonal()
+    }
+}
+
+// Negative PoissonStencil as a matrix over the fluid cells, one unknown per fluid
+// cell. Periodic cells are copies, their neighbour is the fluid cell they mirror. The
+// operator is symmetric positive semidefinite with the constants in its null space.
+pub struct PoissonOperator {
+    stencil: PoissonStencil,
+    // Cell index of every unknown
+    cells: Vec<usize>,
+    // Unknowns along STENCIL_OFFSETS, None behind a wall
+    neighbors: Vec<[Option<u32>; 4]>,
+}
+
+impl PoissonOperator {
+    pub fn new(space_domain: &SpaceDomain) -> Self {
+        let [nx, ny] = space_domain.space_size();
+
+        let mut unknowns = vec![None; nx * ny];
+        let mut cells = Vec::new();
+        for x in 0..nx {
+            for y in 0..ny {
+                if let CellType::FluidCell = space_domain.cell_type(x, y) {
+                    unknowns[x * ny + y] = Some(cells.len() as u32);
+                    cells.push(x * ny + y);
+                }
+            }
+        }
+
+        let neighbors = cells
+            .iter()
+            .map(|&index| {
+                let (x, y) = (index / ny, index % ny);
+                STENCIL_OFFSETS.map(|offset| {
+                    let (x, y) = space_domain.pressure_neighbor_position(x, y, offset)?;
+                    let (x, y) = match space_domain.cell_type(x, y) {
+                        CellType::BoundaryConditionCell(BoundaryConditionCell::PeriodicCell) => {
+                            space_domain.periodic_source(x, y)
+                        }
+                        _ => (x, y),
+                    };
+                    unknowns[x * ny + y]
+                })
+            })
+            .collect();
+
+        Self {
+            stencil: PoissonStencil::new(space_domain.delta_space()),
+            cells,
+            neighbors,
+        }
+    }
+
+    // Values of the unknowns from a field indexed like the cells
+    pub fn gather(&self, field: &[f32]) -> Vec<f32> {
+        self.cells.iter().map(|&index| field[index]).collect()
+    }
+
+    pub fn scatter(&self, values: &[f32], field: &mut [f32]) {
+        for (&index, &value) in self.cells.iter().zip(values.iter()) {
+            field[index] = value;
+        }
+    }
+}
+
+impl LinearOperator for PoissonOperator {
+    fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn apply(&self, x: &[f32], y: &mut [f32]) {
+        y.par_iter_mut()
+            .zip(self.neighbors.par_iter())
+            .enumerate()
+            .for_each(|(unknown, (y, neighbors))| {
+                let center = x[unknown];
+                *y = neighbors
+                    .iter()
+                    .zip(STENCIL_OFFSETS)
+                    .filter_map(|(neighbor, offset)| {
+                        neighbor.map(|neighbor| {
+                            (center - x[neighbor as usize]) * self.stencil.weight(offset)
+                        })
+                    })
+                    .sum();
+            });
+    }
+
+    // A wall neighbour mirrors the cell and cancels its share of the stencil's diagonal
+    fn diagonal(&self, unknown: usize) -> f32 {
+        self.neighbors[unknown]
+            .iter()
+            .zip(STENCIL_OFFSETS)
+            .filter(|(neighbor, _)| neighbor.is_some())
+            .map(|(_, offset)| self.stencil.weight(offset))
+            .sum()
+    }
+}
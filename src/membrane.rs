@@ -0,0 +1,215 @@
+use crate::space_domain::SpaceDomain;
+
+use std::f32::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Elasticity {
+    // Tension per unit strain of a segment
+    pub stretching: f32, // newtons per meter of span
+    // Resistance to curvature, bending towards a straight chain
+    pub bending: f32, // newton meters per meter of span
+}
+
+// Chain of massless points immersed in the fluid by the immersed boundary method of
+// Peskin. The points move with the fluid velocity interpolated around them and
+// their elastic forces are spread back onto the faces around them, both with the
+// four point cosine kernel, so the fluid carries the membrane and feels its
+// stiffness. Tethered points are pulled towards their anchors by a stiff spring.
+// The forces are explicit, stiffness beyond about the fluid mass per cell over the
+// squared timestep makes the membrane unstable.
+#[derive(Clone)]
+pub struct Membrane {
+    elasticity: Elasticity,
+    points: Vec<[f32; 2]>,  // meters
+    rest_lengths: Vec<f32>, // meters, one per segment
+    // (point, anchor in meters, stiffness in newtons/meter per meter of span)
+    tethers: Vec<(usize, [f32; 2], f32)>,
+    // Acceleration of the u and v faces, indexed x * ny + y
+    force: Vec<[f32; 2]>,
+}
+
+impl Membrane {
+    // Relaxed in the shape of points, two or more
+    pub fn new(points: Vec<[f32; 2]>, elasticity: Elasticity) -> Self {
+        assert!(points.len() >= 2, "a membrane needs at least 2 points");
+        assert!(
+            elasticity.stretching >= 0.0 && elasticity.bending >= 0.0,
+            "membrane elasticity must not be negative"
+        );
+        let rest_lengths = points
+            .windows(2)
+            .map(|segment| distance(segment[0], segment[1]))
+            .collect();
+        Self {
+            elasticity,
+            points,
+            rest_lengths,
+            tethers: Vec::new(),
+            force: Vec::new(),
+        }
+    }
+
+    // point_count points evenly spaced from start to end
+    pub fn straight(
+        start: [f32; 2],
+        end: [f32; 2],
+        point_count: usize,
+        elasticity: Elasticity,
+    ) -> Self {
+        assert!(point_count >= 2, "a membrane needs at least 2 points");
+        let points = (0..point_count)
+            .map(|i| {
+                let t = i as f32 / (point_count - 1) as f32;
+                [
+                    start[0] + t * (end[0] - start[0]),
+                    start[1] + t * (end[1] - start[1]),
+                ]
+            })
+            .collect();
+        Self::new(points, elasticity)
+    }
+
+    // Anchors point where it is now
+    pub fn with_tether(mut self, point: usize, stiffness: f32) -> Self {
+        assert!(stiffness > 0.0, "tether stiffness must be positive");
+        self.tethers.push((point, self.points[point], stiffness));
+        self
+    }
+
+    pub fn elasticity(&self) -> Elasticity {
+        self.elasticity
+    }
+
+    pub fn points(&self) -> &[[f32; 2]] {
+        &self.points
+    }
+
+    // Deformed without moving the fluid, the rest shape stays
+    pub fn set_points(&mut self, points: Vec<[f32; 2]>) {
+        assert_eq!(points.len(), self.points.len(), "point count mismatch");
+        self.points = points;
+    }
+
+    pub fn length(&self) -> f32 {
+        self.points
+            .windows(2)
+            .map(|segment| distance(segment[0], segment[1]))
+            .sum()
+    }
+
+    pub fn rest_length(&self) -> f32 {
+        self.rest_lengths.iter().sum()
+    }
+
+    // Empty until the first timestep on a grid
+    pub fn force(&self) -> &[[f32; 2]] {
+        &self.force
+    }
+
+    // Elastic and tether force on every point, newtons per meter of span
+    pub fn point_forces(&self) -> Vec<[f32; 2]> {
+        let mut forces = vec![[0.0, 0.0]; self.points.len()];
+        let mut add = |point: usize, force: [f32; 2], scale: f32| {
+            forces[point][0] += scale * force[0];
+            forces[point][1] += scale * force[1];
+        };
+
+        for (i, &rest_length) in self.rest_lengths.iter().enumerate() {
+            let [a, b] = [self.points[i], self.points[i + 1]];
+            let length = distance(a, b);
+            if length > 0.0 {
+                let tension = self.elasticity.stretching * (length / rest_length - 1.0);
+                let direction = [(b[0] - a[0]) / length, (b[1] - a[1]) / length];
+                add(i, direction, tension);
+                add(i + 1, direction, -tension);
+            }
+        }
+
+        // Gradient of bending / (2 ds^3) sum |X(i+1) - 2 X(i) + X(i-1)|^2
+        for i in 1..self.points.len() - 1 {
+            let spacing = (self.rest_lengths[i - 1] + self.rest_lengths[i]) / 2.0;
+            let scale = self.elasticity.bending / spacing.powi(3);
+            let [previous, point, next] = [self.points[i - 1], self.points[i], self.points[i + 1]];
+            let curvature = [
+                next[0] - 2.0 * point[0] + previous[0],
+                next[1] - 2.0 * point[1] + previous[1],
+            ];
+            add(i - 1, curvature, -scale);
+            add(i, curvature, 2.0 * scale);
+            add(i + 1, curvature, -scale);
+        }
+
+        for &(point, anchor, stiffness) in &self.tethers {
+            let position = self.points[point];
+            add(
+                point,
+                [anchor[0] - position[0], anchor[1] - position[1]],
+                stiffness,
+            );
+        }
+        forces
+    }
+
+    // Move the points with the fluid and spread their forces for the next timestep
+    pub fn advance(&mut self, delta_time: f32, space_domain: &SpaceDomain) {
+        let space_size = space_domain.space_size();
+        let [dx, dy] = space_domain.delta_space();
+        let fields = space_domain.fields();
+
+        for point in self.points.iter_mut() {
+            let u: f32 = kernel_weights(space_domain, *point, [1.0, 0.5])
+                .map(|(index, weight)| weight * fields.u[index])
+                .sum();
+            let v: f32 = kernel_weights(space_domain, *point, [0.5, 1.0])
+                .map(|(index, weight)| weight * fields.v[index])
+                .sum();
+            point[0] += delta_time * u;
+            point[1] += delta_time * v;
+        }
+
+        self.force.clear();
+        self.force.resize(space_size[0] * space_size[1], [0.0, 0.0]);
+        for (point, force) in self.points.iter().zip(self.point_forces()) {
+            // u faces sit on the right and v faces on the top of the cells
+            for (axis, offset) in [(0, [1.0, 0.5]), (1, [0.5, 1.0])] {
+                for (index, weight) in kernel_weights(space_domain, *point, offset) {
+                    self.force[index][axis] += weight * force[axis] / (dx * dy);
+                }
+            }
+        }
+    }
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt()
+}
+
+// Peskin's cosine kernel of a distance in cells, adding up to 1 over the samples
+fn cosine_kernel(r: f32) -> f32 {
+    if r.abs() < 2.0 {
+        (1.0 + (PI * r / 2.0).cos()) / 4.0
+    } else {
+        0.0
+    }
+}
+
+// Samples at (x + offset) * delta_space within two cells of position with their
+// kernel weights, those outside the domain left out
+fn kernel_weights(
+    space_domain: &SpaceDomain,
+    position: [f32; 2],
+    offset: [f32; 2],
+) -> impl Iterator<Item = (usize, f32)> {
+    let [nx, ny] = space_domain.space_size();
+    let [dx, dy] = space_domain.delta_space();
+    let fx = position[0] / dx - offset[0];
+    let fy = position[1] / dy - offset[1];
+    let [x0, y0] = [fx.floor() as isize - 1, fy.floor() as isize - 1];
+    (x0..x0 + 4)
+        .flat_map(move |x| (y0..y0 + 4).map(move |y| (x, y)))
+        .filter(move |&(x, y)| x >= 0 && y >= 0 && (x as usize) < nx && (y as usize) < ny)
+        .map(move |(x, y)| {
+            let weight = cosine_kernel(fx - x as f32) * cosine_kernel(fy - y as f32);
+            (x as usize * ny + y as usize, weight)
+        })
+}
@@ -0,0 +1,126 @@
+use std::fmt;
+use std::mem::size_of;
+
+const MEBIBYTE: f64 = 1024.0 * 1024.0;
+
+// Bytes held by each buffer of a simulation, by name in the order they were added
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryFootprint {
+    pub buffers: Vec<(&'static str, usize)>,
+}
+
+impl MemoryFootprint {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Counts the capacity, what the buffer actually holds on to
+    pub fn add<T>(&mut self, name: &'static str, buffer: &Vec<T>) {
+        self.buffers
+            .push((name, buffer.capacity() * size_of::<T>()));
+    }
+
+    pub fn bytes(&self, name: &str) -> Option<usize> {
+        self.buffers
+            .iter()
+            .find(|(buffer, _)| *buffer == name)
+            .map(|&(_, bytes)| bytes)
+    }
+
+    pub fn total(&self) -> usize {
+        self.buffers.iter().map(|(_, bytes)| bytes).sum()
+    }
+}
+
+impl fmt::Display for MemoryFootprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, bytes) in &self.buffers {
+            writeln!(f, "{name}: {:.1} MiB", *bytes as f64 / MEBIBYTE)?;
+        }
+        write!(f, "total: {:.1} MiB", self.total() as f64 / MEBIBYTE)
+    }
+}
+
+// A buffer the allocator refused, returned instead of aborting the process so a
+// front end can report it and carry on with the domain it has
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllocationError {
+    pub space_size: [usize; 2],
+    pub buffer: &'static str,
+    // None when the size doesn't fit in usize
+    pub bytes: Option<usize>,
+}
+
+impl fmt::Display for AllocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [nx, ny] = self.space_size;
+        match self.bytes {
+            Some(bytes) => write!(
+                f,
+                "could not allocate {:.1} MiB for the {} of a {nx} x {ny} grid",
+                bytes as f64 / MEBIBYTE,
+                self.buffer
+            ),
+            None => write!(
+                f,
+                "the {} of a {nx} x {ny} grid would be larger than the address space",
+                self.buffer
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AllocationError {}
+
+// Room for additional more elements, or the error naming the buffer
+pub(crate) fn try_reserve<T>(
+    vector: &mut Vec<T>,
+    additional: usize,
+    space_size: [usize; 2],
+    buffer: &'static str,
+) -> Result<(), AllocationError> {
+    vector
+        .try_reserve_exact(additional)
+        .map_err(|_| AllocationError {
+            space_size,
+            buffer,
+            bytes: vector
+                .len()
+                .checked_add(additional)
+                .and_then(|len| len.checked_mul(size_of::<T>())),
+        })
+}
+
+// Empty vector with room for capacity elements
+pub(crate) fn try_with_capacity<T>(
+    capacity: usize,
+    space_size: [usize; 2],
+    buffer: &'static str,
+) -> Result<Vec<T>, AllocationError> {
+    let mut vector = Vec::new();
+    try_reserve(&mut vector, capacity, space_size, buffer)?;
+    Ok(vector)
+}
+
+// len copies of value
+pub(crate) fn try_filled<T: Clone>(
+    len: usize,
+    value: T,
+    space_size: [usize; 2],
+    buffer: &'static str,
+) -> Result<Vec<T>, AllocationError> {
+    let mut vector = try_with_capacity(len, space_size, buffer)?;
+    vector.resize(len, value);
+    Ok(vector)
+}
+
+// Cells of a grid, an error when the count overflows usize
+pub(crate) fn cell_count(space_size: [usize; 2]) -> Result<usize, AllocationError> {
+    space_size[0]
+        .checked_mul(space_size[1])
+        .ok_or(AllocationError {
+            space_size,
+            buffer: "domain",
+            bytes: None,
+        })
+}
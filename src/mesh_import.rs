@@ -0,0 +1,553 @@
+use crate::boundary_segments::BoundarySegment;
+use crate::cell::{BoundaryConditionCell, Cell, CellType};
+use crate::events::EventSchedule;
+use crate::field_snapshot::invalid_data;
+use crate::obstacles::Shape;
+use crate::presets::SimulationPreset;
+use crate::space_domain::{SpaceDomain, FACE_NEIGHBORS};
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::path::Path;
+use std::{fs, io};
+
+// Gmsh element type of 4-node quadrangles
+const GMSH_QUADRANGLE: usize = 3;
+// Gmsh element types of the other surface elements, triangles and higher order ones
+const GMSH_SURFACES: [usize; 6] = [2, 9, 10, 16, 20, 21];
+// Relative slack of the spacing checks, for coordinates rounded in the file
+const SPACING_TOLERANCE: f32 = 1e-3;
+
+// Plane mesh of quadrilaterals from a meshing tool, in mesh units taken as meters.
+// The fluid is the area the quadrilaterals cover, everything else is solid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuadMesh {
+    pub nodes: Vec<[f32; 2]>,
+    // Node indices, in order around the element
+    pub quads: Vec<[usize; 4]>,
+}
+
+// Why a mesh doesn't map onto a cell grid of the given spacing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MeshError {
+    // The extent along the axis isn't a whole number of cells, so the grid would cut
+    // off or pad the mesh
+    SpacingMismatch {
+        axis: usize,
+        extent: f32,
+        delta_space: f32,
+    },
+    // Cells larger than the smallest element along the axis merge or lose elements
+    TooCoarse {
+        axis: usize,
+        element_size: f32,
+        delta_space: f32,
+    },
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |axis: usize| ["x", "y"][axis];
+        match *self {
+            MeshError::SpacingMismatch {
+                axis,
+                extent,
+                delta_space,
+            } => write!(
+                f,
+                "the mesh extent {extent} along {} is not a whole number of cells of {delta_space}",
+                name(axis)
+            ),
+            MeshError::TooCoarse {
+                axis,
+                element_size,
+                delta_space,
+            } => write!(
+                f,
+                "cells of {delta_space} along {} don't resolve the smallest element of {element_size}",
+                name(axis)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+// Cells of a mesh on the Cartesian grid, surrounded by a ring of walls so no fluid
+// cell is on the domain edge
+#[derive(Debug, Clone)]
+pub struct MeshDomain {
+    pub cells: Vec<Vec<Cell>>,
+    pub delta_space: [f32; 2], // meters
+    // Mesh coordinates of the lower left corner of cell (0, 0)
+    pub origin: [f32; 2],
+    // Walls around the mesh as "outer_wall" and each hole inside it as "hole_0",
+    // "hole_1" and so on, in the order of their first cell
+    pub segments: Vec<BoundarySegment>,
+}
+
+impl QuadMesh {
+    pub fn new(nodes: Vec<[f32; 2]>, quads: Vec<[usize; 4]>) -> Self {
+        assert!(!quads.is_empty(), "a mesh needs elements");
+        assert!(
+            quads.iter().flatten().all(|&node| node < nodes.len()),
+            "element node out of range"
+        );
+        Self { nodes, quads }
+    }
+
+    // Gmsh MSH file in the ASCII formats 2.2 or 4.1. Quadrangles make up the mesh,
+    // points and lines are skipped and other surface elements are an error.
+    pub fn load_msh(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse_msh(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse_msh(text: &str) -> io::Result<Self> {
+        let section = |name: &str| -> io::Result<Vec<&str>> {
+            let start = format!("${name}");
+            let end = format!("$End{name}");
+            let mut lines = text.lines().map(str::trim);
+            if !lines.any(|line| line == start) {
+                return Err(invalid_data(&format!("MSH file without {name}")));
+            }
+            Ok(lines
+                .take_while(|line| *line != end)
+                .flat_map(str::split_ascii_whitespace)
+                .collect())
+        };
+        let format = section("MeshFormat")?;
+        let [version, file_type, ..] = format.as_slice() else {
+            return Err(invalid_data("malformed MSH format"));
+        };
+        if *file_type != "0" {
+            return Err(invalid_data("binary MSH files are not supported"));
+        }
+        let (nodes, elements) = match *version {
+            "2.2" | "2" => parse_msh2(&section("Nodes")?, &section("Elements")?)?,
+            version if version.starts_with("4.") => {
+                parse_msh4(&section("Nodes")?, &section("Elements")?)?
+            }
+            version => return Err(invalid_data(&format!("unsupported MSH version {version}"))),
+        };
+
+        // Gmsh numbers nodes by tags that may have gaps
+        let mut index = HashMap::new();
+        let mut points = Vec::with_capacity(nodes.len());
+        for (tag, point) in nodes {
+            index.insert(tag, points.len());
+            points.push(point);
+        }
+        let mut quads = Vec::new();
+        for (element_type, node_tags) in elements {
+            if GMSH_SURFACES.contains(&element_type) {
+                return Err(invalid_data(&format!(
+                    "only quadrangles are supported, found element type {element_type}"
+                )));
+            }
+            if element_type != GMSH_QUADRANGLE {
+                continue;
+            }
+            let mut quad = [0; 4];
+            for (corner, tag) in quad.iter_mut().zip(&node_tags) {
+                *corner = *index
+                    .get(tag)
+                    .ok_or_else(|| invalid_data(&format!("element of unknown node {tag}")))?;
+            }
+            quads.push(quad);
+        }
+        if quads.is_empty() {
+            return Err(invalid_data("MSH file without quadrangles"));
+        }
+        Ok(Self::new(points, quads))
+    }
+
+    // Single block structured grid in the plain text layout of 2D Plot3D files: the
+    // node counts ni and nj, then the x and then the y of every node with i counting
+    // fastest. Neighbouring nodes make up the quadrilaterals.
+    pub fn load_structured(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse_structured(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse_structured(text: &str) -> io::Result<Self> {
+        let mut tokens = text.split_ascii_whitespace();
+        let mut count = || -> io::Result<usize> {
+            let text = tokens
+                .next()
+                .ok_or_else(|| invalid_data("truncated structured mesh header"))?;
+            text.parse()
+                .map_err(|_| invalid_data(&format!("malformed node count {text}")))
+        };
+        let [ni, nj] = [count()?, count()?];
+        if ni < 2 || nj < 2 {
+            return Err(invalid_data("a structured mesh needs at least 2 x 2 nodes"));
+        }
+        let coordinates = numbers(tokens, 2 * ni * nj)?;
+        let nodes = (0..ni * nj)
+            .map(|node| [coordinates[node], coordinates[ni * nj + node]])
+            .collect();
+        let node = |i: usize, j: usize| j * ni + i;
+        let quads = (0..nj - 1)
+            .flat_map(|j| (0..ni - 1).map(move |i| (i, j)))
+            .map(|(i, j)| {
+                [
+                    node(i, j),
+                    node(i + 1, j),
+                    node(i + 1, j + 1),
+                    node(i, j + 1),
+                ]
+            })
+            .collect();
+        Ok(Self::new(nodes, quads))
+    }
+
+    // [min, max] corners of the nodes
+    pub fn bounds(&self) -> [[f32; 2]; 2] {
+        self.nodes.iter().fold(
+            [[f32::INFINITY; 2], [f32::NEG_INFINITY; 2]],
+            |[min, max], node| {
+                [
+                    [min[0].min(node[0]), min[1].min(node[1])],
+                    [max[0].max(node[0]), max[1].max(node[1])],
+                ]
+            },
+        )
+    }
+
+    // Smallest extent of an element along each axis, the coarsest cells that still
+    // resolve every element
+    pub fn element_size(&self) -> [f32; 2] {
+        self.quads.iter().fold([f32::INFINITY; 2], |size, quad| {
+            let [min, max] = quad_bounds(&self.quad(quad));
+            [0, 1].map(|axis| size[axis].min(max[axis] - min[axis]))
+        })
+    }
+
+    // Cells whose centers lie in an element are fluid. Solid cells connected to the
+    // outside of the mesh are walls where they touch the fluid and void elsewhere,
+    // solid cells enclosed by the fluid are no-slip obstacles.
+    pub fn to_domain(&self, delta_space: [f32; 2]) -> Result<MeshDomain, MeshError> {
+        assert!(
+            delta_space[0] > 0.0 && delta_space[1] > 0.0,
+            "cell size must be positive"
+        );
+        let [min, max] = self.bounds();
+        let element_size = self.element_size();
+        let mut fluid_size = [0; 2];
+        for axis in [0, 1] {
+            let extent = max[axis] - min[axis];
+            let cells = extent / delta_space[axis];
+            if (cells - cells.round()).abs() > SPACING_TOLERANCE * cells.max(1.0) {
+                return Err(MeshError::SpacingMismatch {
+                    axis,
+                    extent,
+                    delta_space: delta_space[axis],
+                });
+            }
+            if delta_space[axis] > element_size[axis] * (1.0 + SPACING_TOLERANCE) {
+                return Err(MeshError::TooCoarse {
+                    axis,
+                    element_size: element_size[axis],
+                    delta_space: delta_space[axis],
+                });
+            }
+            fluid_size[axis] = cells.round() as usize;
+        }
+
+        // One ring of cells outside the bounds
+        let [nx, ny] = fluid_size.map(|size| size + 2);
+        let origin = [0, 1].map(|axis| min[axis] - delta_space[axis]);
+        let center = |x: usize, y: usize| {
+            [
+                origin[0] + (x as f32 + 0.5) * delta_space[0],
+                origin[1] + (y as f32 + 0.5) * delta_space[1],
+            ]
+        };
+        let mut is_fluid = vec![false; nx * ny];
+        for quad in &self.quads {
+            let corners = self.quad(quad);
+            let [quad_min, quad_max] = quad_bounds(&corners);
+            let first = |axis: usize| {
+                ((quad_min[axis] - origin[axis]) / delta_space[axis] - 0.5)
+                    .floor()
+                    .max(0.0) as usize
+            };
+            let last = |axis: usize, size: usize| {
+                (((quad_max[axis] - origin[axis]) / delta_space[axis] - 0.5).ceil() as usize)
+                    .min(size - 1)
+            };
+            let shape = Shape::Polygon(corners.to_vec());
+            for x in first(0)..=last(0, nx) {
+                for y in first(1)..=last(1, ny) {
+                    if shape.contains(center(x, y)) {
+                        is_fluid[x * ny + y] = true;
+                    }
+                }
+            }
+        }
+
+        let label = label_solid_regions(&is_fluid, [nx, ny]);
+        let wall = CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+            boundary_condition_velocity: [0.0, 0.0],
+        });
+        let mut outer_wall = Vec::new();
+        let mut holes: Vec<Vec<(usize, usize)>> = Vec::new();
+        let mut cells = vec![vec![Cell::default(); ny]; nx];
+        for x in 0..nx {
+            for y in 0..ny {
+                let cell_type = match label[x * ny + y] {
+                    None => CellType::FluidCell,
+                    // The outside
+                    Some(0) => {
+                        let touches_fluid = FACE_NEIGHBORS.into_iter().any(|offset| {
+                            let neighbor = [x as isize + offset[0], y as isize + offset[1]];
+                            (0..nx as isize).contains(&neighbor[0])
+                                && (0..ny as isize).contains(&neighbor[1])
+                                && is_fluid[neighbor[0] as usize * ny + neighbor[1] as usize]
+                        });
+                        if touches_fluid {
+                            outer_wall.push((x, y));
+                            wall
+                        } else {
+                            CellType::VoidCell
+                        }
+                    }
+                    Some(hole) => {
+                        if holes.len() < hole {
+                            holes.resize(hole, Vec::new());
+                        }
+                        holes[hole - 1].push((x, y));
+                        wall
+                    }
+                };
+                cells[x][y].cell_type = cell_type;
+            }
+        }
+
+        let mut segments = vec![BoundarySegment::new("outer_wall", outer_wall)];
+        segments.extend(
+            holes
+                .into_iter()
+                .enumerate()
+                .map(|(hole, cells)| BoundarySegment::new(&format!("hole_{hole}"), cells)),
+        );
+        Ok(MeshDomain {
+            cells,
+            delta_space,
+            origin,
+            segments,
+        })
+    }
+
+    fn quad(&self, quad: &[usize; 4]) -> [[f32; 2]; 4] {
+        quad.map(|node| self.nodes[node])
+    }
+}
+
+impl MeshDomain {
+    // Preset at rest with the segments of the domain, the walls are no-slip until set
+    // otherwise, see SimulationPreset::with_segment and
+    // SpaceDomain::set_segment_condition
+    pub fn into_preset(self, delta_time: f32, reynolds: f32) -> SimulationPreset {
+        let mut space_domain = SpaceDomain::new(self.cells, self.delta_space, 0.9);
+        for segment in self.segments {
+            space_domain.add_segment(segment);
+        }
+        SimulationPreset {
+            space_domain,
+            delta_time,
+            acceleration: [0.0, 0.0],
+            reynolds,
+            events: EventSchedule::new(),
+            obstacles: Vec::new(),
+            dye_buoyancy: None,
+        }
+    }
+}
+
+// Per cell None for fluid, otherwise the solid region: 0 for the one connected to the
+// domain edge, the outside of the mesh, and 1, 2 and so on for the holes in the order
+// of their first cell
+fn label_solid_regions(is_fluid: &[bool], space_size: [usize; 2]) -> Vec<Option<usize>> {
+    let [nx, ny] = space_size;
+    let mut label: Vec<Option<usize>> = vec![None; nx * ny];
+    let fill = |start: usize, region: usize, label: &mut Vec<Option<usize>>| {
+        let mut queue = VecDeque::from([start]);
+        label[start] = Some(region);
+        while let Some(index) = queue.pop_front() {
+            let (x, y) = (index / ny, index % ny);
+            for offset in FACE_NEIGHBORS {
+                let neighbor = [x as isize + offset[0], y as isize + offset[1]];
+                if !(0..nx as isize).contains(&neighbor[0])
+                    || !(0..ny as isize).contains(&neighbor[1])
+                {
+                    continue;
+                }
+                let neighbor = neighbor[0] as usize * ny + neighbor[1] as usize;
+                if !is_fluid[neighbor] && label[neighbor].is_none() {
+                    label[neighbor] = Some(region);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+    };
+    // The ring around the mesh is never fluid
+    fill(0, 0, &mut label);
+    let mut regions = 1;
+    for index in 0..nx * ny {
+        if !is_fluid[index] && label[index].is_none() {
+            fill(index, regions, &mut label);
+            regions += 1;
+        }
+    }
+    label
+}
+
+fn quad_bounds(corners: &[[f32; 2]; 4]) -> [[f32; 2]; 2] {
+    corners.iter().fold(
+        [[f32::INFINITY; 2], [f32::NEG_INFINITY; 2]],
+        |[min, max], corner| {
+            [
+                [min[0].min(corner[0]), min[1].min(corner[1])],
+                [max[0].max(corner[0]), max[1].max(corner[1])],
+            ]
+        },
+    )
+}
+
+fn numbers<'a>(tokens: impl Iterator<Item = &'a str>, count: usize) -> io::Result<Vec<f32>> {
+    let numbers = tokens
+        .take(count)
+        .map(|text| {
+            text.parse()
+                .map_err(|_| invalid_data(&format!("malformed number {text}")))
+        })
+        .collect::<io::Result<Vec<f32>>>()?;
+    if numbers.len() < count {
+        return Err(invalid_data("truncated mesh"));
+    }
+    Ok(numbers)
+}
+
+// Reads whitespace separated integers and reals off a section
+struct Tokens<'a> {
+    tokens: std::slice::Iter<'a, &'a str>,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(tokens: &'a [&'a str]) -> Self {
+        Self {
+            tokens: tokens.iter(),
+        }
+    }
+
+    fn next<T: std::str::FromStr>(&mut self) -> io::Result<T> {
+        let text = self
+            .tokens
+            .next()
+            .ok_or_else(|| invalid_data("truncated MSH section"))?;
+        text.parse()
+            .map_err(|_| invalid_data(&format!("malformed MSH value {text}")))
+    }
+}
+
+type Nodes = Vec<(usize, [f32; 2])>;
+// Element type and node tags
+type Elements = Vec<(usize, Vec<usize>)>;
+
+// Nodes as tag x y z, elements as tag type, tag count, tags and node tags
+fn parse_msh2(nodes: &[&str], elements: &[&str]) -> io::Result<(Nodes, Elements)> {
+    let mut tokens = Tokens::new(nodes);
+    let node_count: usize = tokens.next()?;
+    let mut parsed_nodes = Vec::with_capacity(node_count);
+    for _ in 0..node_count {
+        let tag = tokens.next()?;
+        let point = [tokens.next()?, tokens.next()?];
+        tokens.next::<f32>()?;
+        parsed_nodes.push((tag, point));
+    }
+
+    let mut tokens = Tokens::new(elements);
+    let element_count: usize = tokens.next()?;
+    let mut parsed_elements = Vec::with_capacity(element_count);
+    for _ in 0..element_count {
+        tokens.next::<usize>()?;
+        let element_type: usize = tokens.next()?;
+        let tag_count: usize = tokens.next()?;
+        for _ in 0..tag_count {
+            tokens.next::<isize>()?;
+        }
+        let node_count = gmsh_node_count(element_type)?;
+        let node_tags = (0..node_count)
+            .map(|_| tokens.next())
+            .collect::<io::Result<Vec<usize>>>()?;
+        parsed_elements.push((element_type, node_tags));
+    }
+    Ok((parsed_nodes, parsed_elements))
+}
+
+// Nodes and elements in entity blocks, the node tags of a block before its
+// coordinates
+fn parse_msh4(nodes: &[&str], elements: &[&str]) -> io::Result<(Nodes, Elements)> {
+    let mut tokens = Tokens::new(nodes);
+    let block_count: usize = tokens.next()?;
+    let node_count: usize = tokens.next()?;
+    tokens.next::<usize>()?;
+    tokens.next::<usize>()?;
+    let mut parsed_nodes = Vec::with_capacity(node_count);
+    for _ in 0..block_count {
+        tokens.next::<usize>()?;
+        tokens.next::<isize>()?;
+        let parametric: usize = tokens.next()?;
+        if parametric != 0 {
+            return Err(invalid_data("parametric MSH nodes are not supported"));
+        }
+        let count: usize = tokens.next()?;
+        let tags = (0..count)
+            .map(|_| tokens.next())
+            .collect::<io::Result<Vec<usize>>>()?;
+        for tag in tags {
+            let point = [tokens.next()?, tokens.next()?];
+            tokens.next::<f32>()?;
+            parsed_nodes.push((tag, point));
+        }
+    }
+
+    let mut tokens = Tokens::new(elements);
+    let block_count: usize = tokens.next()?;
+    let element_count: usize = tokens.next()?;
+    tokens.next::<usize>()?;
+    tokens.next::<usize>()?;
+    let mut parsed_elements = Vec::with_capacity(element_count);
+    for _ in 0..block_count {
+        tokens.next::<usize>()?;
+        tokens.next::<isize>()?;
+        let element_type: usize = tokens.next()?;
+        let count: usize = tokens.next()?;
+        let node_count = gmsh_node_count(element_type)?;
+        for _ in 0..count {
+            tokens.next::<usize>()?;
+            let node_tags = (0..node_count)
+                .map(|_| tokens.next())
+                .collect::<io::Result<Vec<usize>>>()?;
+            parsed_elements.push((element_type, node_tags));
+        }
+    }
+    Ok((parsed_nodes, parsed_elements))
+}
+
+// Nodes per element of the Gmsh element types a plane mesh holds
+fn gmsh_node_count(element_type: usize) -> io::Result<usize> {
+    match element_type {
+        15 => Ok(1),
+        1 => Ok(2),
+        2 | 8 => Ok(3),
+        3 => Ok(4),
+        9 => Ok(6),
+        16 => Ok(8),
+        10 | 20 => Ok(9),
+        21 => Ok(10),
+        element_type => Err(invalid_data(&format!(
+            "unsupported MSH element type {element_type}"
+        ))),
+    }
+}
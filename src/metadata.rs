@@ -0,0 +1,150 @@
+use crate::parameters::Parameters;
+use crate::simulation::Simulation;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const CRATE_VERSION: &str = env!("CARGO_PKG_VERSION");
+// Commit the crate was built from, see build.rs
+pub const GIT_HASH: Option<&str> = option_env!("FLOW2D_GIT_HASH");
+
+// Where a run comes from, written into its checkpoints and exports so a result can
+// be traced back to the scene, settings and code that produced it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationMetadata {
+    pub scene: String,
+    // At the start of the run
+    pub parameters: Parameters,
+    pub solver: SolverConfig,
+    pub crate_version: String,
+    pub git_hash: Option<String>,
+    pub start_time: u64, // seconds since the unix epoch
+}
+
+// Numerical choices of a Simulation beyond its parameters, by their names
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolverConfig {
+    pub advection_scheme: String,
+    pub pressure_solver: String,
+    pub sor_ordering: String,
+    pub tolerance_schedule: String,
+    pub warm_start: bool,
+}
+
+impl SimulationMetadata {
+    // The current settings of simulation, started now by this build
+    pub fn new(scene: impl Into<String>, simulation: &Simulation) -> Self {
+        Self {
+            scene: scene.into(),
+            parameters: simulation.parameters(),
+            solver: SolverConfig {
+                advection_scheme: format!("{:?}", simulation.advection_scheme()),
+                pressure_solver: format!("{:?}", simulation.pressure_solver()),
+                sor_ordering: format!("{:?}", simulation.sor_ordering()),
+                tolerance_schedule: format!("{:?}", simulation.tolerance_schedule()),
+                warm_start: simulation.warm_start(),
+            },
+            crate_version: CRATE_VERSION.to_string(),
+            git_hash: GIT_HASH.map(str::to_string),
+            start_time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs()),
+        }
+    }
+
+    // Inverse of the Display format, None if a key is missing or a number malformed
+    pub fn parse(text: &str) -> Option<Self> {
+        let values: HashMap<&str, &str> = text
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .collect();
+        let text = |key: &str| values.get(key).map(|value| value.to_string());
+        let number = |key: &str| values.get(key)?.parse::<f32>().ok();
+        let inflow_velocity = match values.get("inflow_velocity_x") {
+            Some(_) => Some([number("inflow_velocity_x")?, number("inflow_velocity_y")?]),
+            None => None,
+        };
+
+        Some(Self {
+            scene: unescape(values.get("scene")?),
+            parameters: Parameters {
+                reynolds: number("reynolds")?,
+                acceleration: [number("acceleration_x")?, number("acceleration_y")?],
+                inflow_velocity,
+                omega: number("omega")?,
+                delta_time: number("delta_time")?,
+            },
+            solver: SolverConfig {
+                advection_scheme: text("advection_scheme")?,
+                pressure_solver: text("pressure_solver")?,
+                sor_ordering: text("sor_ordering")?,
+                tolerance_schedule: text("tolerance_schedule")?,
+                warm_start: values.get("warm_start")?.parse().ok()?,
+            },
+            crate_version: text("crate_version")?,
+            git_hash: text("git_hash"),
+            start_time: values.get("start_time")?.parse().ok()?,
+        })
+    }
+}
+
+// One key=value line per entry, the format of checkpoints and of the comment lines
+// of statistics files
+impl fmt::Display for SimulationMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parameters = &self.parameters;
+        writeln!(f, "scene={}", escape(&self.scene))?;
+        writeln!(f, "reynolds={}", parameters.reynolds)?;
+        writeln!(f, "acceleration_x={}", parameters.acceleration[0])?;
+        writeln!(f, "acceleration_y={}", parameters.acceleration[1])?;
+        if let Some([x, y]) = parameters.inflow_velocity {
+            writeln!(f, "inflow_velocity_x={x}")?;
+            writeln!(f, "inflow_velocity_y={y}")?;
+        }
+        writeln!(f, "omega={}", parameters.omega)?;
+        writeln!(f, "delta_time={}", parameters.delta_time)?;
+        writeln!(f, "advection_scheme={}", self.solver.advection_scheme)?;
+        writeln!(f, "pressure_solver={}", self.solver.pressure_solver)?;
+        writeln!(f, "sor_ordering={}", self.solver.sor_ordering)?;
+        writeln!(f, "tolerance_schedule={}", self.solver.tolerance_schedule)?;
+        writeln!(f, "warm_start={}", self.solver.warm_start)?;
+        writeln!(f, "crate_version={}", self.crate_version)?;
+        if let Some(git_hash) = &self.git_hash {
+            writeln!(f, "git_hash={git_hash}")?;
+        }
+        write!(f, "start_time={}", self.start_time)
+    }
+}
+
+// Keeps a scene name on its line, backslashes and line breaks become \\, \n and \r
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut characters = text.chars();
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            unescaped.push(character);
+            continue;
+        }
+        match characters.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
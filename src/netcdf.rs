@@ -0,0 +1,357 @@
+use crate::cell::CellType;
+use crate::field::Field;
+use crate::metadata::SimulationMetadata;
+use crate::solver::FluidSolver;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+// netCDF classic format with 64-bit offsets, readable by xarray, ParaView and the
+// netCDF tools without linking the C library
+const MAGIC: &[u8; 4] = b"CDF\x02";
+const NUMRECS_OFFSET: u64 = 4;
+
+const NC_DIMENSION: u32 = 0x0A;
+const NC_VARIABLE: u32 = 0x0B;
+const NC_ATTRIBUTE: u32 = 0x0C;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NcType {
+    Byte = 1,
+    Char = 2,
+    Float = 5,
+    Double = 6,
+}
+
+impl NcType {
+    fn size(&self) -> usize {
+        match self {
+            NcType::Byte | NcType::Char => 1,
+            NcType::Float => 4,
+            NcType::Double => 8,
+        }
+    }
+}
+
+// Global attribute values
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attribute {
+    Text(String),
+    Number(f64),
+}
+
+// Dimension ids in the header, time is the record dimension
+const TIME: usize = 0;
+const X: usize = 1;
+const Y: usize = 2;
+const X_FACE: usize = 3;
+const Y_FACE: usize = 4;
+
+struct Variable {
+    name: &'static str,
+    dimensions: Vec<usize>,
+    nc_type: NcType,
+    attributes: Vec<(&'static str, Attribute)>,
+}
+
+impl Variable {
+    fn new(name: &'static str, dimensions: Vec<usize>, nc_type: NcType) -> Self {
+        Self {
+            name,
+            dimensions,
+            nc_type,
+            attributes: Vec::new(),
+        }
+    }
+
+    fn attribute(mut self, name: &'static str, value: &str) -> Self {
+        self.attributes
+            .push((name, Attribute::Text(value.to_string())));
+        self
+    }
+}
+
+// Bytes per record for record variables, padded to four
+fn variable_size(variable: &Variable, dimensions: &[(&str, usize)]) -> usize {
+    let values: usize = variable
+        .dimensions
+        .iter()
+        .filter(|&&dimension| dimension != TIME)
+        .map(|&dimension| dimensions[dimension].1)
+        .product();
+    padded(values * variable.nc_type.size())
+}
+
+// Appends the state of a solver as one record per call, so a long run ends up as a
+// single file with a time axis instead of one snapshot file per step. Every field is
+// stored where it lives on the staggered grid, u on the x_face coordinates, v on
+// y_face and psi on both.
+pub struct NetcdfWriter {
+    file: BufWriter<File>,
+    space_size: [usize; 2],
+    records: usize,
+}
+
+impl NetcdfWriter {
+    pub fn create(
+        path: impl AsRef<Path>,
+        solver: &dyn FluidSolver,
+        attributes: &[(&str, Attribute)],
+    ) -> io::Result<Self> {
+        let space_size = solver.space_size();
+        let delta_space = solver.delta_space();
+        let dimensions = [
+            ("time", 0),
+            ("x", space_size[0]),
+            ("y", space_size[1]),
+            ("x_face", space_size[0]),
+            ("y_face", space_size[1]),
+        ];
+        let variables = variables();
+
+        let mut global_attributes = vec![
+            ("Conventions", Attribute::Text("CF-1.8".to_string())),
+            ("source", Attribute::Text("flow2d_rs".to_string())),
+            ("delta_x", Attribute::Number(delta_space[0] as f64)),
+            ("delta_y", Attribute::Number(delta_space[1] as f64)),
+        ];
+        if let Some(metadata) = solver.metadata() {
+            global_attributes.extend(metadata_attributes(metadata));
+        }
+        global_attributes.extend(attributes.iter().cloned());
+
+        // Fixed variables follow the header, then the records with one slab of every
+        // record variable each
+        let mut offset = header(&dimensions, &global_attributes, &variables, &[]).len() as u64;
+        let begins: Vec<u64> = variables
+            .iter()
+            .map(|variable| {
+                let begin = offset;
+                offset += variable_size(variable, &dimensions) as u64;
+                begin
+            })
+            .collect();
+
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(&header(
+            &dimensions,
+            &global_attributes,
+            &variables,
+            &begins,
+        ))?;
+
+        // Coordinates of cell centres and faces
+        let coordinates = [
+            (space_size[0], delta_space[0], 0.5),
+            (space_size[1], delta_space[1], 0.5),
+            (space_size[0], delta_space[0], 1.0),
+            (space_size[1], delta_space[1], 1.0),
+        ];
+        for (count, delta, offset) in coordinates {
+            let values: Vec<f32> = (0..count)
+                .map(|index| (index as f32 + offset) * delta)
+                .collect();
+            write_floats(&mut file, &values)?;
+        }
+        file.flush()?;
+
+        Ok(Self {
+            file,
+            space_size,
+            records: 0,
+        })
+    }
+
+    pub fn records(&self) -> usize {
+        self.records
+    }
+
+    // The record count in the header is rewritten after every record, so the file
+    // stays readable while the run goes on
+    pub fn append(&mut self, solver: &dyn FluidSolver) -> io::Result<()> {
+        assert_eq!(
+            solver.space_size(),
+            self.space_size,
+            "solver size changed since the file was created"
+        );
+
+        self.file.seek(SeekFrom::End(0))?;
+        write_floats(&mut self.file, &[solver.time()])?;
+        for field in Field::ALL {
+            write_floats(&mut self.file, &solver.field(field).to_vec())?;
+        }
+        let cell_types: Vec<u8> = solver.fields().cell_type.iter().map(CellType::id).collect();
+        self.file.write_all(&cell_types)?;
+        self.file.write_all(&[0; 3][..padding(cell_types.len())])?;
+
+        self.records += 1;
+        self.file.seek(SeekFrom::Start(NUMRECS_OFFSET))?;
+        self.file.write_all(&(self.records as u32).to_be_bytes())?;
+        self.file.flush()
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+// The provenance of the run with numbers as numbers
+fn metadata_attributes(metadata: &SimulationMetadata) -> Vec<(&'static str, Attribute)> {
+    let text = |value: &str| Attribute::Text(value.to_string());
+    let number = |value: f32| Attribute::Number(value as f64);
+    let parameters = &metadata.parameters;
+    let solver = &metadata.solver;
+    let mut attributes = vec![
+        ("scene", text(&metadata.scene)),
+        ("reynolds", number(parameters.reynolds)),
+        ("acceleration_x", number(parameters.acceleration[0])),
+        ("acceleration_y", number(parameters.acceleration[1])),
+        ("omega", number(parameters.omega)),
+        ("delta_time", number(parameters.delta_time)),
+        ("advection_scheme", text(&solver.advection_scheme)),
+        ("pressure_solver", text(&solver.pressure_solver)),
+        ("sor_ordering", text(&solver.sor_ordering)),
+        ("tolerance_schedule", text(&solver.tolerance_schedule)),
+        ("warm_start", text(&solver.warm_start.to_string())),
+        ("crate_version", text(&metadata.crate_version)),
+        ("start_time", Attribute::Number(metadata.start_time as f64)),
+    ];
+    if let Some([x, y]) = parameters.inflow_velocity {
+        attributes.push(("inflow_velocity_x", number(x)));
+        attributes.push(("inflow_velocity_y", number(y)));
+    }
+    if let Some(git_hash) = &metadata.git_hash {
+        attributes.push(("git_hash", text(git_hash)));
+    }
+    attributes
+}
+
+// Fixed variables before record variables, the order of their data in the file
+fn variables() -> Vec<Variable> {
+    let mut variables = vec![
+        Variable::new("x", vec![X], NcType::Float)
+            .attribute("units", "m")
+            .attribute("long_name", "cell centre x"),
+        Variable::new("y", vec![Y], NcType::Float)
+            .attribute("units", "m")
+            .attribute("long_name", "cell centre y"),
+        Variable::new("x_face", vec![X_FACE], NcType::Float)
+            .attribute("units", "m")
+            .attribute("long_name", "right cell face x"),
+        Variable::new("y_face", vec![Y_FACE], NcType::Float)
+            .attribute("units", "m")
+            .attribute("long_name", "top cell face y"),
+        Variable::new("time", vec![TIME], NcType::Float).attribute("units", "s"),
+    ];
+    for field in Field::ALL {
+        let offset = field.offset();
+        let dimensions = vec![
+            TIME,
+            if offset[0] == 1.0 { X_FACE } else { X },
+            if offset[1] == 1.0 { Y_FACE } else { Y },
+        ];
+        let variable = Variable::new(field.name(), dimensions, NcType::Float);
+        variables.push(match field {
+            Field::Pressure => variable.attribute("long_name", "pressure"),
+            Field::U => variable
+                .attribute("units", "m s-1")
+                .attribute("long_name", "x velocity"),
+            Field::V => variable
+                .attribute("units", "m s-1")
+                .attribute("long_name", "y velocity"),
+            Field::Psi => variable
+                .attribute("units", "m2 s-1")
+                .attribute("long_name", "stream function"),
+        });
+    }
+    variables.push(
+        Variable::new("cell_type", vec![TIME, X, Y], NcType::Byte)
+            .attribute("long_name", "0 fluid, 1 boundary condition, 2 void"),
+    );
+    variables
+}
+
+// begins holds the data offset of every variable, or is empty to measure the header
+fn header(
+    dimensions: &[(&str, usize)],
+    attributes: &[(&str, Attribute)],
+    variables: &[Variable],
+    begins: &[u64],
+) -> Vec<u8> {
+    let mut header = Vec::new();
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&0u32.to_be_bytes()); // records
+
+    header.extend_from_slice(&NC_DIMENSION.to_be_bytes());
+    header.extend_from_slice(&(dimensions.len() as u32).to_be_bytes());
+    for &(name, length) in dimensions {
+        push_name(&mut header, name);
+        header.extend_from_slice(&(length as u32).to_be_bytes());
+    }
+
+    push_attributes(&mut header, attributes);
+
+    header.extend_from_slice(&NC_VARIABLE.to_be_bytes());
+    header.extend_from_slice(&(variables.len() as u32).to_be_bytes());
+    for (index, variable) in variables.iter().enumerate() {
+        push_name(&mut header, variable.name);
+        header.extend_from_slice(&(variable.dimensions.len() as u32).to_be_bytes());
+        for &dimension in variable.dimensions.iter() {
+            header.extend_from_slice(&(dimension as u32).to_be_bytes());
+        }
+        push_attributes(&mut header, &variable.attributes);
+        header.extend_from_slice(&(variable.nc_type as u32).to_be_bytes());
+
+        let size = variable_size(variable, dimensions) as u32;
+        header.extend_from_slice(&size.to_be_bytes());
+        header.extend_from_slice(&begins.get(index).copied().unwrap_or(0).to_be_bytes());
+    }
+    header
+}
+
+fn push_attributes(header: &mut Vec<u8>, attributes: &[(&str, Attribute)]) {
+    if attributes.is_empty() {
+        header.extend_from_slice(&[0; 8]);
+        return;
+    }
+    header.extend_from_slice(&NC_ATTRIBUTE.to_be_bytes());
+    header.extend_from_slice(&(attributes.len() as u32).to_be_bytes());
+    for (name, value) in attributes {
+        push_name(header, name);
+        match value {
+            Attribute::Text(text) => {
+                header.extend_from_slice(&(NcType::Char as u32).to_be_bytes());
+                push_name(header, text);
+            }
+            Attribute::Number(number) => {
+                header.extend_from_slice(&(NcType::Double as u32).to_be_bytes());
+                header.extend_from_slice(&1u32.to_be_bytes());
+                header.extend_from_slice(&number.to_be_bytes());
+            }
+        }
+    }
+}
+
+// Length followed by the bytes, zero padded to four
+fn push_name(header: &mut Vec<u8>, name: &str) {
+    header.extend_from_slice(&(name.len() as u32).to_be_bytes());
+    header.extend_from_slice(name.as_bytes());
+    header.extend_from_slice(&[0; 3][..padding(name.len())]);
+}
+
+fn write_floats(file: &mut impl Write, values: &[f32]) -> io::Result<()> {
+    let bytes: Vec<u8> = values
+        .iter()
+        .flat_map(|value| value.to_be_bytes())
+        .collect();
+    file.write_all(&bytes)
+}
+
+fn padding(length: usize) -> usize {
+    (4 - length % 4) % 4
+}
+
+fn padded(length: usize) -> usize {
+    length + padding(length)
+}
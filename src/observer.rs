@@ -0,0 +1,27 @@
+// Per timestep instrumentation, the library itself never prints
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepEvent {
+    // Counted from 0 since the simulation was created
+    pub step: usize,
+    pub time: f32,       // seconds, at the end of the step
+    pub delta_time: f32, // seconds
+    // Iterations of the pressure solve, SOR sweeps or Krylov iterations
+    pub pressure_iterations: usize,
+    // RMS residual of the last convergence check
+    pub pressure_residual: f32,
+    pub pressure_range: [f32; 2],
+    pub speed_range: [f32; 2],
+    pub psi_range: [f32; 2],
+}
+
+pub trait StepObserver {
+    fn on_step(&mut self, event: &StepEvent);
+}
+
+// Closures subscribe directly
+impl<F: FnMut(&StepEvent)> StepObserver for F {
+    fn on_step(&mut self, event: &StepEvent) {
+        self(event)
+    }
+}
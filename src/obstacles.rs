@@ -0,0 +1,446 @@
+use crate::cell::{BoundaryConditionCell, Cell, CellType};
+use crate::forces;
+use crate::space_domain::{Fields, SpaceDomain};
+
+use std::f32::consts::PI;
+
+// Samples per axis and cell when estimating how much of a cell a shape covers
+const COVERAGE_SAMPLES: usize = 4;
+
+// Outline of an obstacle, positions in meters
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Circle { center: [f32; 2], radius: f32 },
+    Rectangle { min: [f32; 2], max: [f32; 2] },
+    // Vertices in order, either orientation
+    Polygon(Vec<[f32; 2]>),
+}
+
+impl Shape {
+    // Symmetric NACA 00xx airfoil with the leading edge at the origin and the chord
+    // along +x, thickness relative to the chord, e.g. 0.12 for NACA 0012
+    pub fn naca_airfoil(thickness: f32, chord: f32, points_per_side: usize) -> Self {
+        assert!(
+            points_per_side >= 2,
+            "an airfoil needs at least 2 points per side"
+        );
+        let half_thickness = |x: f32| {
+            5.0 * thickness
+                * chord
+                * (0.2969 * x.sqrt() - 0.1260 * x - 0.3516 * x.powi(2) + 0.2843 * x.powi(3)
+                    - 0.1036 * x.powi(4))
+        };
+        // Cosine spacing resolves the rounded leading edge
+        let stations: Vec<f32> = (0..=points_per_side)
+            .map(|i| (1.0 - (PI * i as f32 / points_per_side as f32).cos()) / 2.0)
+            .collect();
+
+        let upper = stations
+            .iter()
+            .rev()
+            .map(|&x| [x * chord, half_thickness(x)]);
+        let lower = stations[1..stations.len() - 1]
+            .iter()
+            .map(|&x| [x * chord, -half_thickness(x)]);
+        Shape::Polygon(upper.chain(lower).collect())
+    }
+
+    // Same shape moved by offset, in meters
+    pub fn translated(&self, offset: [f32; 2]) -> Self {
+        let move_point = |point: &[f32; 2]| [point[0] + offset[0], point[1] + offset[1]];
+        match self {
+            Shape::Circle { center, radius } => Shape::Circle {
+                center: move_point(center),
+                radius: *radius,
+            },
+            Shape::Rectangle { min, max } => Shape::Rectangle {
+                min: move_point(min),
+                max: move_point(max),
+            },
+            Shape::Polygon(vertices) => Shape::Polygon(vertices.iter().map(move_point).collect()),
+        }
+    }
+
+    // Same shape turned counterclockwise by angle radians around pivot, rectangles
+    // become polygons
+    pub fn rotated(&self, angle: f32, pivot: [f32; 2]) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        let rotate = |point: &[f32; 2]| {
+            let [x, y] = [point[0] - pivot[0], point[1] - pivot[1]];
+            [pivot[0] + cos * x - sin * y, pivot[1] + sin * x + cos * y]
+        };
+        match self {
+            Shape::Circle { center, radius } => Shape::Circle {
+                center: rotate(center),
+                radius: *radius,
+            },
+            Shape::Rectangle { min, max } => Shape::Polygon(
+                [
+                    [min[0], min[1]],
+                    [max[0], min[1]],
+                    [max[0], max[1]],
+                    [min[0], max[1]],
+                ]
+                .iter()
+                .map(rotate)
+                .collect(),
+            ),
+            Shape::Polygon(vertices) => Shape::Polygon(vertices.iter().map(rotate).collect()),
+        }
+    }
+
+    pub fn contains(&self, point: [f32; 2]) -> bool {
+        match self {
+            Shape::Circle { center, radius } => {
+                (point[0] - center[0]).powi(2) + (point[1] - center[1]).powi(2) < radius.powi(2)
+            }
+            Shape::Rectangle { min, max } => {
+                (min[0]..max[0]).contains(&point[0]) && (min[1]..max[1]).contains(&point[1])
+            }
+            Shape::Polygon(vertices) => {
+                // Even-odd rule with a ray in +x
+                let mut is_inside = false;
+                for (i, a) in vertices.iter().enumerate() {
+                    let b = vertices[(i + 1) % vertices.len()];
+                    if (a[1] > point[1]) != (b[1] > point[1])
+                        && point[0] < a[0] + (point[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0])
+                    {
+                        is_inside = !is_inside;
+                    }
+                }
+                is_inside
+            }
+        }
+    }
+}
+
+// How well the wall cells represent a shape
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RasterReport {
+    // meters^2, estimated from the coverage samples
+    pub shape_area: f32,
+    pub wall_area: f32,
+    // Partially covered cells left as fluid without a wall cell of the obstacle around
+    // them, a feature thinner than a cell was lost there
+    pub unresolved_cells: Vec<(usize, usize)>,
+}
+
+impl RasterReport {
+    pub fn is_complete(&self) -> bool {
+        self.unresolved_cells.is_empty()
+    }
+}
+
+// Linear spring and dashpot holding a rigid body where its shape was given, the same
+// along both axes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpringMount {
+    pub stiffness: f32, // newtons/meter per meter of span
+    pub damping: f32,   // newton seconds/meter per meter of span
+}
+
+// Obstacle translating under the force of the fluid, without rotation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RigidBody {
+    // Per meter of span, relative to the fluid of unit density like the forces
+    pub mass: f32,
+    pub spring: Option<SpringMount>,
+    // Axes the body moves along, [false, true] for vibrations across a flow in +x
+    pub is_free: [bool; 2],
+    pub displacement: [f32; 2], // meters from where its shape was given
+    pub velocity: [f32; 2],     // meters/seconds
+}
+
+impl RigidBody {
+    // At rest and free along both axes without a spring
+    pub fn new(mass: f32) -> Self {
+        assert!(mass > 0.0, "rigid body mass must be positive");
+        Self {
+            mass,
+            spring: None,
+            is_free: [true, true],
+            displacement: [0.0, 0.0],
+            velocity: [0.0, 0.0],
+        }
+    }
+
+    pub fn with_spring(mut self, spring: SpringMount) -> Self {
+        self.spring = Some(spring);
+        self
+    }
+
+    pub fn with_free_axes(mut self, is_free: [bool; 2]) -> Self {
+        self.is_free = is_free;
+        self
+    }
+
+    // Hertz, undamped and in vacuum
+    pub fn natural_frequency(&self) -> Option<f32> {
+        self.spring
+            .map(|spring| (spring.stiffness / self.mass).sqrt() / (2.0 * PI))
+    }
+
+    // Semi-implicit Euler with the spring and dashpot taken implicitly, stable for
+    // any stiffness
+    fn integrate(&mut self, force: [f32; 2], delta_time: f32) {
+        let SpringMount { stiffness, damping } = self.spring.unwrap_or(SpringMount {
+            stiffness: 0.0,
+            damping: 0.0,
+        });
+        for (axis, force) in force.into_iter().enumerate() {
+            if !self.is_free[axis] {
+                continue;
+            }
+            self.velocity[axis] = (self.velocity[axis]
+                + delta_time / self.mass * (force - stiffness * self.displacement[axis]))
+                / (1.0
+                    + delta_time * damping / self.mass
+                    + delta_time.powi(2) * stiffness / self.mass);
+            self.displacement[axis] += delta_time * self.velocity[axis];
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Obstacle {
+    pub name: String,
+    pub shape: Shape,
+    // NoSlipCell or FreeSlipCell
+    pub wall: BoundaryConditionCell,
+    // Record the force after every timestep
+    pub monitor_force: bool,
+    // (time in seconds, [drag, lift]), see forces::force_on_cells
+    pub force_samples: Vec<(f32, [f32; 2])>,
+    // Some moves the obstacle with the force of the fluid after every timestep, its
+    // no-slip walls taking the velocity of the body
+    pub rigid_body: Option<RigidBody>,
+    cells: Vec<(usize, usize)>,
+    raster_report: RasterReport,
+}
+
+impl Obstacle {
+    // Resting no-slip wall without force monitoring
+    pub fn new(name: &str, shape: Shape) -> Self {
+        Self {
+            name: name.to_string(),
+            shape,
+            wall: BoundaryConditionCell::NoSlipCell {
+                boundary_condition_velocity: [0.0, 0.0],
+            },
+            monitor_force: false,
+            force_samples: Vec::new(),
+            rigid_body: None,
+            cells: Vec::new(),
+            raster_report: RasterReport::default(),
+        }
+    }
+
+    // The shape where a rigid body moved it
+    pub fn current_shape(&self) -> Shape {
+        match self.rigid_body {
+            Some(body) => self.shape.translated(body.displacement),
+            None => self.shape.clone(),
+        }
+    }
+
+    // Cells turned into walls, empty until placed in a domain
+    pub fn cells(&self) -> &[(usize, usize)] {
+        &self.cells
+    }
+
+    pub fn raster_report(&self) -> &RasterReport {
+        &self.raster_report
+    }
+
+    pub fn force(
+        &self,
+        fields: &Fields,
+        space_size: [usize; 2],
+        delta_space: [f32; 2],
+        reynolds: f32,
+    ) -> [f32; 2] {
+        forces::force_on_cells(
+            fields,
+            space_size,
+            delta_space,
+            reynolds,
+            self.cells.iter().copied(),
+        )
+    }
+
+    // Turn the cells away from the domain edge that are more than half covered by the
+    // shape into walls, so walls never stick out of the shape by more than half a cell
+    pub(crate) fn rasterize(&mut self, space_domain: &mut SpaceDomain) {
+        assert!(
+            matches!(
+                self.wall,
+                BoundaryConditionCell::NoSlipCell { .. } | BoundaryConditionCell::FreeSlipCell
+            ),
+            "obstacle walls must be no-slip or free-slip"
+        );
+        assert!(
+            self.rigid_body.is_none()
+                || matches!(self.wall, BoundaryConditionCell::NoSlipCell { .. }),
+            "rigid bodies need no-slip walls"
+        );
+        let [nx, ny] = space_domain.space_size();
+        let [dx, dy] = space_domain.delta_space();
+
+        let coverage = self.coverage(space_domain);
+        let is_wall = |x: usize, y: usize| coverage[x * ny + y] > 0.5;
+
+        self.cells.clear();
+        let mut unresolved_cells = Vec::new();
+        for x in 1..nx - 1 {
+            for y in 1..ny - 1 {
+                if is_wall(x, y) {
+                    space_domain.set_cell(
+                        x,
+                        y,
+                        Cell {
+                            cell_type: self.wall_cell_type(),
+                            ..Default::default()
+                        },
+                    );
+                    self.cells.push((x, y));
+                } else if coverage[x * ny + y] > 0.0
+                    && !(x - 1..=x + 1).any(|nx| (y - 1..=y + 1).any(|ny| is_wall(nx, ny)))
+                {
+                    unresolved_cells.push((x, y));
+                }
+            }
+        }
+
+        self.raster_report = RasterReport {
+            shape_area: coverage.iter().sum::<f32>() * dx * dy,
+            wall_area: self.cells.len() as f32 * dx * dy,
+            unresolved_cells,
+        };
+    }
+
+    // Fraction of every cell away from the domain edge covered by the current shape
+    fn coverage(&self, space_domain: &SpaceDomain) -> Vec<f32> {
+        let [nx, ny] = space_domain.space_size();
+        let [dx, dy] = space_domain.delta_space();
+        let shape = self.current_shape();
+
+        let mut coverage = vec![0.0; nx * ny];
+        for x in 1..nx - 1 {
+            for y in 1..ny - 1 {
+                let inside = (0..COVERAGE_SAMPLES)
+                    .flat_map(|i| (0..COVERAGE_SAMPLES).map(move |j| (i, j)))
+                    .filter(|&(i, j)| {
+                        shape.contains([
+                            (x as f32 + (i as f32 + 0.5) / COVERAGE_SAMPLES as f32) * dx,
+                            (y as f32 + (j as f32 + 0.5) / COVERAGE_SAMPLES as f32) * dy,
+                        ])
+                    })
+                    .count();
+                coverage[x * ny + y] = inside as f32 / COVERAGE_SAMPLES.pow(2) as f32;
+            }
+        }
+        coverage
+    }
+
+    fn wall_cell_type(&self) -> CellType {
+        match (self.wall, self.rigid_body) {
+            (BoundaryConditionCell::NoSlipCell { .. }, Some(body)) => {
+                CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                    boundary_condition_velocity: body.velocity,
+                })
+            }
+            (wall, _) => CellType::BoundaryConditionCell(wall),
+        }
+    }
+
+    // Move a rigid body by the force of the fluid on it over a timestep. Fluid cells
+    // the shape moved over become walls, cells it left behind become fluid moving with
+    // the body. Other walls stay. Returns whether any cell changed.
+    pub(crate) fn move_rigid_body(
+        &mut self,
+        force: [f32; 2],
+        delta_time: f32,
+        space_domain: &mut SpaceDomain,
+    ) -> bool {
+        let Some(body) = self.rigid_body.as_mut() else {
+            return false;
+        };
+        body.integrate(force, delta_time);
+        let velocity = body.velocity;
+        let [nx, ny] = space_domain.space_size();
+
+        let coverage = self.coverage(space_domain);
+        let mut is_own = vec![false; nx * ny];
+        for &(x, y) in &self.cells {
+            is_own[x * ny + y] = true;
+        }
+        let mut changed = false;
+
+        for &(x, y) in &self.cells {
+            if coverage[x * ny + y] <= 0.5 {
+                let pressure = space_domain.fluid_neighbor_average_pressure(x, y);
+                space_domain.set_cell(
+                    x,
+                    y,
+                    Cell {
+                        velocity,
+                        pressure,
+                        ..Default::default()
+                    },
+                );
+                *space_domain.u_mut(x - 1, y) = velocity[0];
+                *space_domain.v_mut(x, y - 1) = velocity[1];
+                changed = true;
+            }
+        }
+
+        let wall = self.wall_cell_type();
+        self.cells.clear();
+        for x in 1..nx - 1 {
+            for y in 1..ny - 1 {
+                let index = x * ny + y;
+                if coverage[index] <= 0.5 {
+                    continue;
+                }
+                if is_own[index] {
+                    space_domain.set_cell_type(x, y, wall);
+                } else if matches!(space_domain.cell_type(x, y), CellType::FluidCell) {
+                    let pressure = space_domain.fluid_neighbor_average_pressure(x, y);
+                    space_domain.set_cell(
+                        x,
+                        y,
+                        Cell {
+                            cell_type: wall,
+                            pressure,
+                            ..Default::default()
+                        },
+                    );
+                    changed = true;
+                } else {
+                    continue;
+                }
+                self.cells.push((x, y));
+            }
+        }
+        changed
+    }
+
+    // Cells taken over by an obstacle placed later
+    pub(crate) fn release_cells(&mut self, cells: &[(usize, usize)]) {
+        self.cells.retain(|cell| !cells.contains(cell));
+    }
+
+    pub(crate) fn record_force(
+        &mut self,
+        time: f32,
+        fields: &Fields,
+        space_size: [usize; 2],
+        delta_space: [f32; 2],
+        reynolds: f32,
+    ) {
+        if self.monitor_force {
+            let force = self.force(fields, space_size, delta_space, reynolds);
+            self.force_samples.push((time, force));
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use crate::field::Field;
+use crate::run_controller::{Progress, RunReport};
+use crate::snapshot_writer::{self, SnapshotFormat};
+use crate::solver::FluidSolver;
+
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+// Output plugged into a RunController, see RunController::sink. Every hook has an
+// empty default so a sink only implements what it needs. An error stops the run with
+// StopReason::OutputFailed.
+pub trait OutputSink {
+    // After every timestep
+    fn on_step(&mut self, _solver: &dyn FluidSolver, _progress: &Progress) -> io::Result<()> {
+        Ok(())
+    }
+
+    // Every snapshot interval steps, index counts the snapshots of the controller
+    fn on_snapshot(&mut self, _solver: &dyn FluidSolver, _index: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    // Once when the run stops, also after an error of another sink
+    fn on_finish(&mut self, _solver: &dyn FluidSolver, _report: &RunReport) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Lend a sink to a controller and keep using it after the run
+impl<S: OutputSink + ?Sized> OutputSink for &mut S {
+    fn on_step(&mut self, solver: &dyn FluidSolver, progress: &Progress) -> io::Result<()> {
+        (**self).on_step(solver, progress)
+    }
+
+    fn on_snapshot(&mut self, solver: &dyn FluidSolver, index: usize) -> io::Result<()> {
+        (**self).on_snapshot(solver, index)
+    }
+
+    fn on_finish(&mut self, solver: &dyn FluidSolver, report: &RunReport) -> io::Result<()> {
+        (**self).on_finish(solver, report)
+    }
+}
+
+// One row of run statistics every n-th step and one for the last step. The metadata
+// of the solver, if any, comes first as # key=value comment lines.
+pub struct CsvStatsSink {
+    file: BufWriter<File>,
+    every: usize,
+    // Step of the last row, so the final one is not written twice
+    last_step: Option<usize>,
+}
+
+impl CsvStatsSink {
+    pub fn create(path: impl Into<PathBuf>, every: usize) -> io::Result<Self> {
+        assert!(every > 0, "statistics interval must be positive");
+        Ok(Self {
+            file: BufWriter::new(File::create(path.into())?),
+            every,
+            last_step: None,
+        })
+    }
+
+    // The header waits for the first row, when the solver is known
+    fn write_header(&mut self, solver: &dyn FluidSolver) -> io::Result<()> {
+        if let Some(metadata) = solver.metadata() {
+            for line in metadata.to_string().lines() {
+                writeln!(self.file, "# {line}")?;
+            }
+        }
+        writeln!(
+            self.file,
+            "step,time,elapsed,velocity_change,max_speed,pressure_min,pressure_max"
+        )
+    }
+
+    fn write_row(&mut self, solver: &dyn FluidSolver, progress: &Progress) -> io::Result<()> {
+        if self.last_step.is_none() {
+            self.write_header(solver)?;
+        }
+        let [pressure_min, pressure_max] =
+            solver.field(Field::Pressure).range().unwrap_or([0.0, 0.0]);
+        writeln!(
+            self.file,
+            "{},{},{},{},{},{pressure_min},{pressure_max}",
+            progress.steps,
+            progress.time,
+            progress.elapsed.as_secs_f32(),
+            progress.velocity_change,
+            progress.max_speed
+        )?;
+        self.last_step = Some(progress.steps);
+        Ok(())
+    }
+}
+
+impl OutputSink for CsvStatsSink {
+    fn on_step(&mut self, solver: &dyn FluidSolver, progress: &Progress) -> io::Result<()> {
+        if progress.steps.is_multiple_of(self.every) {
+            self.write_row(solver, progress)?;
+        }
+        Ok(())
+    }
+
+    fn on_finish(&mut self, solver: &dyn FluidSolver, report: &RunReport) -> io::Result<()> {
+        if self.last_step != Some(report.progress.steps) {
+            self.write_row(solver, &report.progress)?;
+        }
+        self.file.flush()
+    }
+}
+
+// Writes every snapshot as directory/snapshot_<index>.<extension> in one format, on
+// the stepping thread. SnapshotWriter writes in the background instead.
+pub struct SnapshotSink {
+    directory: PathBuf,
+    format: SnapshotFormat,
+}
+
+impl SnapshotSink {
+    pub fn new(directory: impl Into<PathBuf>, format: SnapshotFormat) -> io::Result<Self> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+        Ok(Self { directory, format })
+    }
+}
+
+impl OutputSink for SnapshotSink {
+    fn on_snapshot(&mut self, solver: &dyn FluidSolver, index: usize) -> io::Result<()> {
+        snapshot_writer::write_snapshot(
+            &self.directory,
+            index,
+            self.format,
+            &solver.checkpoint(),
+            solver.delta_space(),
+        )
+    }
+}
+
+// Keeps the latest state in one file for restarting with Checkpoint::load, updated
+// every snapshot and when the run stops
+pub struct CheckpointSink {
+    path: PathBuf,
+    saved: usize,
+}
+
+impl CheckpointSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            saved: 0,
+        }
+    }
+
+    pub fn saved(&self) -> usize {
+        self.saved
+    }
+
+    // A crash while saving leaves the previous checkpoint intact
+    fn save(&mut self, solver: &dyn FluidSolver) -> io::Result<()> {
+        let mut partial = OsString::from(self.path.as_os_str());
+        partial.push(".partial");
+        solver.checkpoint().save(&partial)?;
+        std::fs::rename(&partial, &self.path)?;
+        self.saved += 1;
+        Ok(())
+    }
+}
+
+impl OutputSink for CheckpointSink {
+    fn on_snapshot(&mut self, solver: &dyn FluidSolver, _index: usize) -> io::Result<()> {
+        self.save(solver)
+    }
+
+    fn on_finish(&mut self, solver: &dyn FluidSolver, _report: &RunReport) -> io::Result<()> {
+        self.save(solver)
+    }
+}
@@ -0,0 +1,69 @@
+use std::sync::{Arc, Mutex};
+
+// Physical and numerical parameters that may change between timesteps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Parameters {
+    pub reynolds: f32,
+    pub acceleration: [f32; 2], // meters/seconds^2
+    // Velocity of every inflow cell, None leaves them alone
+    pub inflow_velocity: Option<[f32; 2]>,
+    // SOR relaxation of the pressure solve, 0 < omega < 2
+    pub omega: f32,
+    pub delta_time: f32, // seconds
+}
+
+impl Parameters {
+    pub fn validate(&self) {
+        if let Err(message) = self.check() {
+            panic!("{message}");
+        }
+    }
+
+    // For callers that cannot panic, like the C interface
+    pub fn check(&self) -> Result<(), &'static str> {
+        // Written so NaN fails every check
+        let is_positive = |value: f32| value > 0.0;
+        if !is_positive(self.reynolds) {
+            Err("reynolds number must be positive")
+        } else if !(self.omega > 0.0 && self.omega < 2.0) {
+            Err("omega must be in (0, 2)")
+        } else if !is_positive(self.delta_time) {
+            Err("delta time must be positive")
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// Handle shared between threads, the simulation reads it at the start of every
+// timestep and applies it whenever it changed
+#[derive(Debug, Clone)]
+pub struct SharedParameters {
+    parameters: Arc<Mutex<Parameters>>,
+}
+
+impl SharedParameters {
+    pub fn new(parameters: Parameters) -> Self {
+        parameters.validate();
+        Self {
+            parameters: Arc::new(Mutex::new(parameters)),
+        }
+    }
+
+    pub fn get(&self) -> Parameters {
+        *self.parameters.lock().unwrap()
+    }
+
+    pub fn set(&self, parameters: Parameters) {
+        parameters.validate();
+        *self.parameters.lock().unwrap() = parameters;
+    }
+
+    pub fn update(&self, change: impl FnOnce(&mut Parameters)) {
+        let mut parameters = self.parameters.lock().unwrap();
+        let mut changed = *parameters;
+        change(&mut changed);
+        changed.validate();
+        *parameters = changed;
+    }
+}
@@ -0,0 +1,193 @@
+use crate::cell::{BoundaryConditionCell, CellType};
+use crate::space_domain::{bilinear_weights, SpaceDomain};
+
+// Largest fraction of a cell a particle may move between collision checks
+const MAX_CELL_FRACTION: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    pub position: [f32; 2], // meters
+    pub velocity: [f32; 2], // meters/seconds
+    // Stokes relaxation time towards the velocity of the fluid around it
+    pub response_time: f32, // seconds
+    // Relative to the fluid of unit density, the mass of that much fluid per meter of
+    // span, so the fluid feels the drag of a particle as strongly as it would a body
+    // of its own of area mass
+    pub mass: f32,
+    // Fraction of the normal velocity kept when bouncing off a wall
+    pub restitution: f32,
+}
+
+// Finite inertia particles dragged along by the fluid, which feels the opposite drag
+// in return. Every timestep the particles relax towards the interpolated fluid
+// velocity implicitly, so response times shorter than the timestep stay stable, and
+// the reaction is spread onto the faces around each particle with the weights of the
+// interpolation. It acts on the fluid in the next timestep. Particles bounce off
+// walls and inflow cells, wrap through periodic cells and leave through outflow
+// cells. The reaction is explicit, particles of a mass beyond that of the fluid in a
+// cell over a response time shorter than the timestep make it unstable.
+#[derive(Clone)]
+pub struct Particles {
+    // Acceleration of the particles, gravity less the buoyancy of the fluid they
+    // displace, as the fluid at rest carries no gravity of its own
+    gravity: [f32; 2], // meters/seconds^2
+    particles: Vec<Particle>,
+    // Acceleration of the u and v faces by the drag, indexed x * ny + y
+    reaction: Vec<[f32; 2]>,
+}
+
+impl Particles {
+    pub fn new(gravity: [f32; 2]) -> Self {
+        Self {
+            gravity,
+            particles: Vec::new(),
+            reaction: Vec::new(),
+        }
+    }
+
+    pub fn with_particle(mut self, particle: Particle) -> Self {
+        self.add(particle);
+        self
+    }
+
+    pub fn add(&mut self, particle: Particle) {
+        assert!(
+            particle.response_time > 0.0,
+            "particle response time must be positive"
+        );
+        assert!(particle.mass >= 0.0, "particle mass must not be negative");
+        assert!(
+            (0.0..=1.0).contains(&particle.restitution),
+            "particle restitution must be between 0 and 1"
+        );
+        self.particles.push(particle);
+    }
+
+    pub fn gravity(&self) -> [f32; 2] {
+        self.gravity
+    }
+
+    pub fn set_gravity(&mut self, gravity: [f32; 2]) {
+        self.gravity = gravity;
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    // Empty until the first timestep on a grid
+    pub fn reaction(&self) -> &[[f32; 2]] {
+        &self.reaction
+    }
+
+    // Total momentum of the particles per meter of span
+    pub fn momentum(&self) -> [f32; 2] {
+        self.particles
+            .iter()
+            .fold([0.0, 0.0], |momentum, particle| {
+                [
+                    momentum[0] + particle.mass * particle.velocity[0],
+                    momentum[1] + particle.mass * particle.velocity[1],
+                ]
+            })
+    }
+
+    pub fn advance(&mut self, delta_time: f32, space_domain: &SpaceDomain) {
+        let space_size = space_domain.space_size();
+        let delta_space = space_domain.delta_space();
+        let [dx, dy] = delta_space;
+        self.reaction.clear();
+        self.reaction
+            .resize(space_size[0] * space_size[1], [0.0, 0.0]);
+
+        let gravity = self.gravity;
+        let reaction = &mut self.reaction;
+        self.particles.retain_mut(|particle| {
+            let fluid = space_domain.interpolate_velocity(particle.position);
+            let relaxation = delta_time / particle.response_time;
+            for axis in 0..2 {
+                let velocity = (particle.velocity[axis]
+                    + relaxation * fluid[axis]
+                    + delta_time * gravity[axis])
+                    / (1.0 + relaxation);
+                let drag = (fluid[axis] - velocity) / particle.response_time;
+                particle.velocity[axis] = velocity;
+
+                // u faces sit on the right and v faces on the top of the cells
+                let offset = if axis == 0 { [1.0, 0.5] } else { [0.5, 1.0] };
+                for (x, y, weight) in
+                    bilinear_weights(space_size, delta_space, particle.position, offset)
+                {
+                    reaction[x * space_size[1] + y][axis] -=
+                        weight * particle.mass * drag / (dx * dy);
+                }
+            }
+
+            let speed = particle.velocity[0].abs() / dx + particle.velocity[1].abs() / dy;
+            let substeps = (delta_time * speed / MAX_CELL_FRACTION).ceil().max(1.0) as usize;
+            (0..substeps)
+                .all(|_| move_particle(particle, delta_time / substeps as f32, space_domain))
+        });
+    }
+}
+
+// False once the particle left the domain
+fn move_particle(particle: &mut Particle, delta_time: f32, space_domain: &SpaceDomain) -> bool {
+    let delta_space = space_domain.delta_space();
+    let [nx, ny] = space_domain.space_size();
+    let cell = |position: [f32; 2]| {
+        let x = (position[0] / delta_space[0]).floor();
+        let y = (position[1] / delta_space[1]).floor();
+        (x >= 0.0 && y >= 0.0 && (x as usize) < nx && (y as usize) < ny)
+            .then_some((x as usize, y as usize))
+    };
+
+    // One axis at a time, reflecting off the face of a wall ahead
+    for axis in 0..2 {
+        let mut position = particle.position;
+        position[axis] += delta_time * particle.velocity[axis];
+        let Some((x, y)) = cell(position) else {
+            return false;
+        };
+        let is_wall = !matches!(
+            space_domain.cell_type(x, y),
+            CellType::FluidCell
+                | CellType::BoundaryConditionCell(
+                    BoundaryConditionCell::OutFlowCell | BoundaryConditionCell::PeriodicCell
+                )
+        );
+        if is_wall {
+            let current = (particle.position[axis] / delta_space[axis]).floor();
+            let (face, direction) = if particle.velocity[axis] > 0.0 {
+                ((current + 1.0) * delta_space[axis], 1.0)
+            } else {
+                (current * delta_space[axis], -1.0)
+            };
+            // The way past the face is travelled back at the reduced speed, kept just
+            // off the face to stay in the fluid cell
+            let overshoot = (position[axis] - face).abs();
+            position[axis] =
+                face - direction * (particle.restitution * overshoot).max(1e-4 * delta_space[axis]);
+            particle.velocity[axis] *= -particle.restitution;
+        }
+        particle.position = position;
+    }
+
+    let Some((x, y)) = cell(particle.position) else {
+        return false;
+    };
+    match space_domain.cell_type(x, y) {
+        CellType::BoundaryConditionCell(BoundaryConditionCell::OutFlowCell) => false,
+        CellType::BoundaryConditionCell(BoundaryConditionCell::PeriodicCell) => {
+            let (source_x, source_y) = space_domain.periodic_source(x, y);
+            particle.position[0] += (source_x as f32 - x as f32) * delta_space[0];
+            particle.position[1] += (source_y as f32 - y as f32) * delta_space[1];
+            true
+        }
+        _ => true,
+    }
+}
@@ -0,0 +1,229 @@
+use crate::output_sink::OutputSink;
+use crate::run_controller::Progress;
+use crate::solver::FluidSolver;
+use crate::space_domain::interpolate_velocity;
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+
+// Largest fraction of a cell a tracer may move in one integration step
+const MAX_CELL_FRACTION: f32 = 0.5;
+
+// (time in seconds, position in meters) along a line
+pub type TimedPoint = (f32, [f32; 2]);
+
+// Markers released from fixed seed points on every release, the lines through all
+// markers of one seed are the streaklines dye injected there would paint. Markers
+// move with the velocity of the latest state between records and are dropped when
+// they leave the fluid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Streaklines {
+    seeds: Vec<[f32; 2]>,
+    release_every: usize,
+    max_markers: Option<usize>,
+    records: usize,
+    last_time: Option<f32>,
+    // Per seed the markers with their release time, newest first
+    markers: Vec<VecDeque<TimedPoint>>,
+}
+
+// Tracers released once at given points, each remembering where it went. A
+// pathline ends where its tracer left the fluid.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pathlines {
+    starts: Vec<[f32; 2]>,
+    last_time: Option<f32>,
+    // Samples of each tracer oldest first, with whether it is still in the fluid
+    lines: Vec<(Vec<TimedPoint>, bool)>,
+}
+
+impl Streaklines {
+    // Seeds in meters, they should lie in fluid cells
+    pub fn new(seeds: &[[f32; 2]]) -> Self {
+        assert!(!seeds.is_empty(), "streaklines need seeds");
+        Self {
+            seeds: seeds.to_vec(),
+            release_every: 1,
+            max_markers: None,
+            records: 0,
+            last_time: None,
+            markers: vec![VecDeque::new(); seeds.len()],
+        }
+    }
+
+    // Release a marker every n records, one by default
+    pub fn with_release_every(mut self, release_every: usize) -> Self {
+        assert!(release_every > 0, "release interval must be positive");
+        self.release_every = release_every;
+        self
+    }
+
+    // Oldest markers beyond this many per seed are dropped, None for no limit
+    pub fn with_max_markers(mut self, max_markers: Option<usize>) -> Self {
+        assert!(max_markers != Some(0), "streaklines need markers");
+        self.max_markers = max_markers;
+        self
+    }
+
+    pub fn seeds(&self) -> &[[f32; 2]] {
+        &self.seeds
+    }
+
+    // Moves the markers on to the time of solver, then releases new ones
+    pub fn record(&mut self, solver: &dyn FluidSolver) {
+        let time = solver.time();
+        if let Some(last_time) = self.last_time {
+            let delta_time = time - last_time;
+            for markers in self.markers.iter_mut() {
+                markers.retain_mut(|(_, position)| {
+                    advance(solver, *position, delta_time).is_some_and(|next| {
+                        *position = next;
+                        true
+                    })
+                });
+            }
+        }
+        if self.records.is_multiple_of(self.release_every) {
+            for (markers, seed) in self.markers.iter_mut().zip(&self.seeds) {
+                markers.push_front((time, *seed));
+                if let Some(max_markers) = self.max_markers {
+                    markers.truncate(max_markers);
+                }
+            }
+        }
+        self.records += 1;
+        self.last_time = Some(time);
+    }
+
+    // Markers of each seed from the seed outwards, with their release times
+    pub fn lines(&self) -> Vec<Vec<TimedPoint>> {
+        self.markers
+            .iter()
+            .map(|markers| markers.iter().copied().collect())
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.markers.iter_mut().for_each(VecDeque::clear);
+        self.records = 0;
+        self.last_time = None;
+    }
+
+    // One row per marker, see write_polylines
+    pub fn write_csv(&self, file: &mut impl Write) -> io::Result<()> {
+        write_polylines(&self.lines(), file)
+    }
+}
+
+impl OutputSink for Streaklines {
+    fn on_step(&mut self, solver: &dyn FluidSolver, _progress: &Progress) -> io::Result<()> {
+        self.record(solver);
+        Ok(())
+    }
+}
+
+impl Pathlines {
+    // The tracers start where they are at the first record
+    pub fn new(positions: &[[f32; 2]]) -> Self {
+        assert!(!positions.is_empty(), "pathlines need tracers");
+        Self {
+            starts: positions.to_vec(),
+            last_time: None,
+            lines: vec![(Vec::new(), true); positions.len()],
+        }
+    }
+
+    pub fn record(&mut self, solver: &dyn FluidSolver) {
+        let time = solver.time();
+        let delta_time = self.last_time.map(|last_time| time - last_time);
+        for ((points, is_active), &start) in self.lines.iter_mut().zip(&self.starts) {
+            if !*is_active {
+                continue;
+            }
+            let Some(delta_time) = delta_time else {
+                *is_active = is_fluid(solver, start);
+                if *is_active {
+                    points.push((time, start));
+                }
+                continue;
+            };
+            match advance(solver, points.last().unwrap().1, delta_time) {
+                Some(position) => points.push((time, position)),
+                None => *is_active = false,
+            }
+        }
+        self.last_time = Some(time);
+    }
+
+    // Samples of each tracer, oldest first
+    pub fn lines(&self) -> Vec<Vec<TimedPoint>> {
+        self.lines
+            .iter()
+            .map(|(points, _)| points.clone())
+            .collect()
+    }
+
+    // Whether the tracer is still in the fluid
+    pub fn is_active(&self, tracer: usize) -> bool {
+        self.lines[tracer].1
+    }
+
+    // One row per sample, see write_polylines
+    pub fn write_csv(&self, file: &mut impl Write) -> io::Result<()> {
+        write_polylines(&self.lines(), file)
+    }
+}
+
+impl OutputSink for Pathlines {
+    fn on_step(&mut self, solver: &dyn FluidSolver, _progress: &Progress) -> io::Result<()> {
+        self.record(solver);
+        Ok(())
+    }
+}
+
+// Polylines as rows of line number, point number along the line, time and position
+// in meters
+pub fn write_polylines(lines: &[Vec<TimedPoint>], file: &mut impl Write) -> io::Result<()> {
+    writeln!(file, "line,point,time,x,y")?;
+    for (line, points) in lines.iter().enumerate() {
+        for (point, (time, [x, y])) in points.iter().enumerate() {
+            writeln!(file, "{line},{point},{time},{x},{y}")?;
+        }
+    }
+    Ok(())
+}
+
+// Massless tracer at position moved on by delta_time with the velocity of solver,
+// None once it leaves the fluid
+fn advance(solver: &dyn FluidSolver, mut position: [f32; 2], delta_time: f32) -> Option<[f32; 2]> {
+    let space_size = solver.space_size();
+    let [dx, dy] = solver.delta_space();
+    let fields = solver.fields();
+    let velocity =
+        |position| interpolate_velocity(space_size, [dx, dy], &fields.u, &fields.v, position);
+
+    let start = velocity(position);
+    let rate = start[0].abs() / dx + start[1].abs() / dy;
+    let steps = (delta_time.abs() * rate / MAX_CELL_FRACTION)
+        .ceil()
+        .max(1.0) as usize;
+    let step = delta_time / steps as f32;
+    for _ in 0..steps {
+        // Midpoint rule
+        let k1 = velocity(position);
+        let midpoint = [
+            position[0] + 0.5 * step * k1[0],
+            position[1] + 0.5 * step * k1[1],
+        ];
+        let k2 = velocity(midpoint);
+        position = [position[0] + step * k2[0], position[1] + step * k2[1]];
+        if !is_fluid(solver, position) {
+            return None;
+        }
+    }
+    Some(position)
+}
+
+fn is_fluid(solver: &dyn FluidSolver, position: [f32; 2]) -> bool {
+    solver.velocity_at(position).is_ok()
+}
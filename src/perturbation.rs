@@ -0,0 +1,104 @@
+use crate::cell::CellType;
+use crate::space_domain::SpaceDomain;
+
+use std::f32::consts::PI;
+
+// Plane waves summed into the random streamfunction
+const MODES: usize = 16;
+
+// Random divergence-free velocity perturbation, the same seed gives the same field.
+// The streamfunction is a sum of plane waves of the given wavelength with random
+// directions and phases, sampled at the cell corners and zero at corners touching
+// non-fluid cells. Face velocities are its differences along the faces, so the
+// perturbation adds no divergence and no flow through walls.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Perturbation {
+    pub amplitude: f32,  // root mean square speed, meters/seconds
+    pub wavelength: f32, // meters
+    pub seed: u64,
+}
+
+impl Perturbation {
+    pub fn apply(&self, space_domain: &mut SpaceDomain) {
+        assert!(self.wavelength > 0.0, "wavelength must be positive");
+        let [nx, ny] = space_domain.space_size();
+        let [dx, dy] = space_domain.delta_space();
+
+        let wavenumber = 2.0 * PI / self.wavelength;
+        let coefficient = self.amplitude / (wavenumber * (MODES as f32 / 2.0).sqrt());
+        let mut rng = SplitMix64::new(self.seed);
+        let modes: Vec<([f32; 2], f32)> = (0..MODES)
+            .map(|_| {
+                let direction = 2.0 * PI * rng.next_f32();
+                let phase = 2.0 * PI * rng.next_f32();
+                (
+                    [wavenumber * direction.cos(), wavenumber * direction.sin()],
+                    phase,
+                )
+            })
+            .collect();
+
+        // Streamfunction at the top right corner of cell (x, y)
+        let is_fluid =
+            |x: usize, y: usize| matches!(space_domain.cell_type(x, y), CellType::FluidCell);
+        let mut psi = vec![0.0; nx * ny];
+        for x in 0..nx - 1 {
+            for y in 0..ny - 1 {
+                if !(is_fluid(x, y)
+                    && is_fluid(x + 1, y)
+                    && is_fluid(x, y + 1)
+                    && is_fluid(x + 1, y + 1))
+                {
+                    continue;
+                }
+                let position = [(x + 1) as f32 * dx, (y + 1) as f32 * dy];
+                psi[x * ny + y] = coefficient
+                    * modes
+                        .iter()
+                        .map(|(k, phase)| (k[0] * position[0] + k[1] * position[1] + phase).cos())
+                        .sum::<f32>();
+            }
+        }
+
+        for x in 1..nx - 1 {
+            for y in 1..ny - 1 {
+                if !matches!(space_domain.cell_type(x, y), CellType::FluidCell) {
+                    continue;
+                }
+                *space_domain.u_mut(x, y) += (psi[x * ny + y] - psi[x * ny + y - 1]) / dy;
+                *space_domain.v_mut(x, y) -= (psi[x * ny + y] - psi[(x - 1) * ny + y]) / dx;
+            }
+        }
+    }
+}
+
+// Small seedable generator, statistical quality is plenty for perturbations
+#[derive(Clone)]
+pub(crate) struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1)
+    pub(crate) fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // Standard normal by the Box-Muller transform
+    pub(crate) fn next_normal(&mut self) -> f32 {
+        let radius = (-2.0 * (1.0 - self.next_f32()).ln()).sqrt();
+        radius * (2.0 * PI * self.next_f32()).cos()
+    }
+}
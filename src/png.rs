@@ -0,0 +1,99 @@
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PngColor {
+    // One byte per pixel
+    Grayscale,
+    // Four bytes per pixel
+    Rgba,
+}
+
+impl PngColor {
+    fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PngColor::Grayscale => 1,
+            PngColor::Rgba => 4,
+        }
+    }
+}
+
+// 8 bit PNG from rows top to bottom, uncompressed deflate so no encoder dependency is
+// needed
+pub fn write_png(
+    file: &mut impl Write,
+    size: [usize; 2],
+    color: PngColor,
+    pixels: &[u8],
+) -> io::Result<()> {
+    let row_length = size[0] * color.bytes_per_pixel();
+    assert_eq!(pixels.len(), row_length * size[1], "pixel count mismatch");
+
+    // Each row starts with filter type 0
+    let mut rows = Vec::with_capacity((row_length + 1) * size[1]);
+    for row in pixels.chunks(row_length.max(1)).take(size[1]) {
+        rows.push(0);
+        rows.extend_from_slice(row);
+    }
+
+    let color_type = match color {
+        PngColor::Grayscale => 0,
+        PngColor::Rgba => 6,
+    };
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(size[0] as u32).to_be_bytes());
+    header.extend_from_slice(&(size[1] as u32).to_be_bytes());
+    header.extend_from_slice(&[8, color_type, 0, 0, 0]); // bit depth, color, deflate, filter, no interlace
+
+    file.write_all(b"\x89PNG\r\n\x1a\n")?;
+    write_png_chunk(file, b"IHDR", &header)?;
+    write_png_chunk(file, b"IDAT", &zlib_stored(&rows))?;
+    write_png_chunk(file, b"IEND", &[])
+}
+
+fn write_png_chunk(file: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    file.write_all(&(data.len() as u32).to_be_bytes())?;
+    file.write_all(kind)?;
+    file.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data.iter()));
+    file.write_all(&crc.to_be_bytes())
+}
+
+// zlib stream made of stored (uncompressed) deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_last = blocks.peek().is_none();
+        let length = block.len() as u16;
+        stream.push(is_last as u8);
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    stream.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    stream
+}
+
+fn crc32<'a>(bytes: impl Iterator<Item = &'a u8>) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
@@ -1,241 +1,508 @@
+use crate::boundary_segments::{BoundarySegment, Edge};
 use crate::cell::BoundaryConditionCell;
 use crate::cell::Cell;
 use crate::cell::CellType;
+use crate::events::EventSchedule;
+use crate::inflow_profile::InflowProfile;
+use crate::memory::AllocationError;
+use crate::obstacles::{Obstacle, Shape};
+use crate::perturbation::Perturbation;
 use crate::space_domain::SpaceDomain;
+use crate::validation::{self, DomainIssue};
+
+// Builds the initial state for a grid size in cells
+pub type PresetFn = fn([usize; 2]) -> SimulationPreset;
 
 pub struct SimulationPreset {
     pub space_domain: SpaceDomain,
     pub delta_time: f32,        // seconds,
     pub acceleration: [f32; 2], // meters/seconds^2
     pub reynolds: f32,
+    pub events: EventSchedule,
+    pub obstacles: Vec<Obstacle>,
+    // See Simulation::set_dye_buoyancy
+    pub dye_buoyancy: Option<[f32; 2]>, // meters/seconds^2
 }
 
-pub fn lid_driven_cavity() -> SimulationPreset {
-    let x_length = 1.0;
-    let y_length = 1.0;
-    let x: usize = 128;
-    let y: usize = 128;
-
-    let mut space_domain: Vec<Vec<Cell>> = Vec::with_capacity(x);
-    for _ in 0..x {
-        let mut row = Vec::with_capacity(y);
-        for _ in 0..y {
-            row.push(Cell::default());
+// Initial conditions from functions of the position (x, y) in meters, applied to
+// the fluid cells. Each velocity component is sampled at its staggered face.
+impl SimulationPreset {
+    pub fn initial_velocity(mut self, velocity: impl Fn(f32, f32) -> [f32; 2]) -> Self {
+        let [nx, ny] = self.space_domain.space_size();
+        let [dx, dy] = self.space_domain.delta_space();
+        let is_boundary = |cell: &Cell| cell.cell_type.blocks_flow();
+
+        for x in 0..nx {
+            for y in 0..ny {
+                if !matches!(self.space_domain.cell_type(x, y), CellType::FluidCell) {
+                    continue;
+                }
+                // Faces towards boundary cells are set by the boundary conditions
+                let right = x + 1 < nx && !is_boundary(&self.space_domain.get_cell(x + 1, y));
+                let top = y + 1 < ny && !is_boundary(&self.space_domain.get_cell(x, y + 1));
+                let center = [(x as f32 + 0.5) * dx, (y as f32 + 0.5) * dy];
+
+                if right {
+                    *self.space_domain.u_mut(x, y) = velocity(center[0] + dx / 2.0, center[1])[0];
+                }
+                if top {
+                    *self.space_domain.v_mut(x, y) = velocity(center[0], center[1] + dy / 2.0)[1];
+                }
+            }
         }
-        space_domain.push(row);
+        self.space_domain.update_periodic_cells();
+        self
     }
 
-    for xi in 0..x {
-        for yi in 0..y {
-            if xi == 0 || xi == x - 1 || yi == 0 {
-                space_domain[xi][yi] = Cell {
-                    cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
-                        boundary_condition_velocity: [0.0, 0.0],
-                    }),
-                    ..Default::default()
-                };
-            }
-            if yi == y - 1 {
-                space_domain[xi][yi] = Cell {
-                    cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
-                        boundary_condition_velocity: [1.0, 0.0],
-                    }),
-                    ..Default::default()
+    // Sampled at the cell centers
+    pub fn initial_pressure(mut self, pressure: impl Fn(f32, f32) -> f32) -> Self {
+        let [nx, ny] = self.space_domain.space_size();
+        let [dx, dy] = self.space_domain.delta_space();
+
+        for x in 0..nx {
+            for y in 0..ny {
+                if matches!(self.space_domain.cell_type(x, y), CellType::FluidCell) {
+                    *self.space_domain.pressure_mut(x, y) =
+                        pressure((x as f32 + 0.5) * dx, (y as f32 + 0.5) * dy);
                 }
             }
         }
+        self.space_domain.update_periodic_cells();
+        self
     }
-    for xi in [0, x - 1] {
-        for yi in [0, y - 1] {
-            space_domain[xi][yi] = Cell {
-                cell_type: CellType::VoidCell,
-                ..Default::default()
-            };
+
+    // Added on top of the initial velocity
+    pub fn perturbed(mut self, perturbation: &Perturbation) -> Self {
+        perturbation.apply(&mut self.space_domain);
+        self.space_domain.update_periodic_cells();
+        self
+    }
+
+    // Geometry the solver can't handle, empty for a usable preset
+    pub fn validate(&self) -> Vec<DomainIssue> {
+        validation::validate(&self.space_domain)
+    }
+
+    // Preset with the issues fixed, see validation::repair
+    pub fn repaired(mut self) -> (Self, Vec<DomainIssue>) {
+        let fixed = validation::repair(&mut self.space_domain);
+        (self, fixed)
+    }
+
+    // Velocity of the inflow cells, see InflowProfile::apply
+    pub fn with_inflow_profile(mut self, profile: InflowProfile) -> Self {
+        profile.apply(&mut self.space_domain);
+        self
+    }
+
+    // Replaces a segment of the same name, see BoundarySegment
+    pub fn with_segment(mut self, segment: BoundarySegment) -> Self {
+        self.space_domain.add_segment(segment);
+        self
+    }
+
+    // Segment of the boundary cells along an edge, see BoundarySegment::on_edge
+    pub fn with_edge_segment(self, name: &str, edge: Edge) -> Self {
+        let segment = BoundarySegment::on_edge(name, &self.space_domain, edge);
+        self.with_segment(segment)
+    }
+
+    // Turns the cells under the shape into walls, later obstacles take over the cells
+    // they share with earlier ones. Obstacle::raster_report tells what got lost.
+    pub fn with_obstacle(mut self, mut obstacle: Obstacle) -> Self {
+        assert!(
+            self.obstacles
+                .iter()
+                .all(|other| other.name != obstacle.name),
+            "obstacle names must be unique"
+        );
+        obstacle.rasterize(&mut self.space_domain);
+        for other in self.obstacles.iter_mut() {
+            other.release_cells(obstacle.cells());
         }
+        self.obstacles.push(obstacle);
+        self
     }
+}
+
+pub const NAMES: [&str; 5] = [
+    "lid_driven_cavity",
+    "backward_facing_step",
+    "cylinder_cross_flow",
+    "kelvin_helmholtz",
+    "buoyant_plume",
+];
+
+// Preset with its default size by the name of its function, for callers outside Rust
+pub fn by_name(name: &str) -> Option<SimulationPreset> {
+    try_by_name(name).map(|preset| preset.unwrap_or_else(|error| panic!("{error}")))
+}
+
+// by_name without aborting when the grid doesn't fit in memory
+pub fn try_by_name(name: &str) -> Option<Result<SimulationPreset, AllocationError>> {
+    match name {
+        "lid_driven_cavity" => Some(try_lid_driven_cavity_sized([128, 128])),
+        "backward_facing_step" => Some(try_backward_facing_step()),
+        "cylinder_cross_flow" => Some(try_cylinder_cross_flow_sized([110, 41])),
+        "kelvin_helmholtz" => Some(try_kelvin_helmholtz_sized([128, 128])),
+        "buoyant_plume" => Some(try_buoyant_plume_sized([64, 128])),
+        _ => None,
+    }
+}
+
+// Preset by name like try_by_name but with space_size cells, None for names without
+// a sized variant
+pub fn by_name_sized(
+    name: &str,
+    space_size: [usize; 2],
+) -> Option<Result<SimulationPreset, AllocationError>> {
+    let preset: fn([usize; 2]) -> Result<SimulationPreset, AllocationError> = match name {
+        "lid_driven_cavity" => try_lid_driven_cavity_sized,
+        "cylinder_cross_flow" => try_cylinder_cross_flow_sized,
+        "kelvin_helmholtz" => try_kelvin_helmholtz_sized,
+        "buoyant_plume" => try_buoyant_plume_sized,
+        _ => return None,
+    };
+    Some(preset(space_size))
+}
+
+pub fn lid_driven_cavity() -> SimulationPreset {
+    lid_driven_cavity_sized([128, 128])
+}
+
+// Same cavity with space_size cells
+pub fn lid_driven_cavity_sized(space_size: [usize; 2]) -> SimulationPreset {
+    try_lid_driven_cavity_sized(space_size).unwrap_or_else(|error| panic!("{error}"))
+}
+
+pub fn try_lid_driven_cavity_sized(
+    space_size: [usize; 2],
+) -> Result<SimulationPreset, AllocationError> {
+    let x_length = 1.0;
+    let y_length = 1.0;
+    let [x, y] = space_size;
+    assert!(x >= 3 && y >= 3, "the cavity needs at least 3 x 3 cells");
+
+    let layout = |xi: usize, yi: usize| {
+        let cell_type = if (xi == 0 || xi == x - 1) && (yi == 0 || yi == y - 1) {
+            CellType::VoidCell
+        } else if yi == y - 1 {
+            CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                boundary_condition_velocity: [1.0, 0.0],
+            })
+        } else if xi == 0 || xi == x - 1 || yi == 0 {
+            CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                boundary_condition_velocity: [0.0, 0.0],
+            })
+        } else {
+            CellType::FluidCell
+        };
+        Cell {
+            cell_type,
+            ..Default::default()
+        }
+    };
 
     let delta_space = [x_length / (x as f32), y_length / (y as f32)];
     let gamma = 0.9;
 
-    SimulationPreset {
-        space_domain: SpaceDomain::new(space_domain, delta_space, gamma),
+    Ok(SimulationPreset {
+        space_domain: SpaceDomain::try_new(space_size, delta_space, gamma, layout)?,
         delta_time: 0.005,
         reynolds: 1000.0,
         acceleration: [0.0, 0.0],
+        events: EventSchedule::new(),
+        obstacles: Vec::new(),
+        dye_buoyancy: None,
     }
+    .with_edge_segment("lid", Edge::Top))
 }
 
 pub fn backward_facing_step() -> SimulationPreset {
+    try_backward_facing_step().unwrap_or_else(|error| panic!("{error}"))
+}
+
+pub fn try_backward_facing_step() -> Result<SimulationPreset, AllocationError> {
     let x_length = 15.0;
     let y_length = 1.5;
     let x: usize = 150;
     let y: usize = 75;
+    // Cells under the step, with its walls on the last column and row
+    let step = [75, 37];
 
     let inflow_x_velocity = 1.0;
 
-    let mut space_domain: Vec<Vec<Cell>> = Vec::with_capacity(x);
-    for _ in 0..x {
-        let mut row = Vec::with_capacity(y);
-        for _ in 0..y {
+    let layout = |xi: usize, yi: usize| {
+        let cell_type = if xi <= step[0] && yi <= step[1] && (xi == step[0] || yi == step[1]) {
+            CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                boundary_condition_velocity: [0.0, 0.0],
+            })
+        } else if (xi < step[0] && yi < step[1])
+            || ((xi == 0 || xi == x - 1) && (yi == 0 || yi == y - 1))
+        {
+            CellType::VoidCell
+        } else if xi == 0 {
+            CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell)
+        } else if xi == x - 1 {
+            CellType::BoundaryConditionCell(BoundaryConditionCell::OutFlowCell)
+        } else if yi == y - 1 || yi == 0 {
+            CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                boundary_condition_velocity: [0.0, 0.0],
+            })
+        } else {
             // Set initial fluid velocity to be equal to the inflow velocity to
             // speed up convergence at the beginning of the simulation.
-            row.push(Cell { velocity: [inflow_x_velocity, 0.0], ..Default::default() });
-        }
-        space_domain.push(row);
-    }
-
-    for xi in 0..x {
-        for yi in 0..y {
-            if xi == 0 {
-                space_domain[xi][yi] = Cell {
-                    cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell),
-                    velocity: [inflow_x_velocity, 0.0],
-                    ..Default::default()
-                };
-                continue;
-            }
-            if xi == x - 1 {
-                space_domain[xi][yi] = Cell {
-                    cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::OutFlowCell),
-                    ..Default::default()
-                };
-                continue;
-            }
-            if yi == y - 1 || yi == 0 {
-                space_domain[xi][yi] = Cell {
-                    cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
-                        boundary_condition_velocity: [0.0, 0.0],
-                    }),
-                    ..Default::default()
-                };
-                continue;
-            }
-        }
-    }
-    for xi in [0, x - 1] {
-        for yi in [0, y - 1] {
-            space_domain[xi][yi] = Cell {
-                cell_type: CellType::VoidCell,
-                ..Default::default()
-            };
-        }
-    }
-
-    for xi in 0..75 {
-        for yi in 0..37 {
-            space_domain[xi][yi] = Cell {
-                cell_type: CellType::VoidCell,
+            return Cell {
+                velocity: [inflow_x_velocity, 0.0],
                 ..Default::default()
             };
+        };
+        Cell {
+            cell_type,
+            ..Default::default()
         }
-    }
-
-    for xi in 0..76 {
-        for yi in 0..38 {
-            if xi == 75 || yi == 37 {
-                space_domain[xi][yi] = Cell {
-                    cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
-                        boundary_condition_velocity: [0.0, 0.0],
-                    }),
-                    ..Default::default()
-                };
-            }
-        }
-    }
+    };
 
     let delta_space = [x_length / (x as f32), y_length / (y as f32)];
     let gamma = 0.9;
-    SimulationPreset {
-        space_domain: SpaceDomain::new(space_domain, delta_space, gamma),
+    Ok(SimulationPreset {
+        space_domain: SpaceDomain::try_new([x, y], delta_space, gamma, layout)?,
         delta_time: 0.005,
         reynolds: 1000.0,
         acceleration: [0.0, 0.0],
+        events: EventSchedule::new(),
+        obstacles: Vec::new(),
+        dye_buoyancy: None,
     }
+    .with_inflow_profile(InflowProfile::Uniform {
+        speed: inflow_x_velocity,
+    })
+    .with_edge_segment("inlet", Edge::Left)
+    .with_edge_segment("outlet", Edge::Right)
+    .with_edge_segment("top_wall", Edge::Top))
 }
 
+// Reference scales of cylinder_cross_flow for the Strouhal number, meters and
+// meters/seconds
+pub const CYLINDER_DIAMETER: f32 = 1.0;
+pub const CYLINDER_INFLOW_VELOCITY: f32 = 1.5;
+
 pub fn cylinder_cross_flow() -> SimulationPreset {
+    cylinder_cross_flow_sized([110, 41])
+}
+
+// Same channel and cylinder with space_size cells
+pub fn cylinder_cross_flow_sized(space_size: [usize; 2]) -> SimulationPreset {
+    try_cylinder_cross_flow_sized(space_size).unwrap_or_else(|error| panic!("{error}"))
+}
+
+pub fn try_cylinder_cross_flow_sized(
+    space_size: [usize; 2],
+) -> Result<SimulationPreset, AllocationError> {
     let x_length = 11.0;
     let y_length = 4.1;
-    let x: usize = 110;
-    let y: usize = 41;
+    let [x, y] = space_size;
+    assert!(x >= 11 && y >= 5, "the channel needs at least 11 x 5 cells");
 
-    let inflow_x_velocity = 1.5;
+    let inflow_x_velocity = CYLINDER_INFLOW_VELOCITY;
 
-    let mut space_domain: Vec<Vec<Cell>> = Vec::with_capacity(x);
-    for _ in 0..x {
-        let mut row = Vec::with_capacity(y);
-        for _ in 0..y {
+    let layout = |xi: usize, yi: usize| {
+        let cell_type = if (xi == 0 || xi == x - 1) && (yi == 0 || yi == y - 1) {
+            CellType::VoidCell
+        } else if xi == 0 {
+            CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell)
+        } else if xi == x - 1 {
+            CellType::BoundaryConditionCell(BoundaryConditionCell::OutFlowCell)
+        } else if yi == y - 1 || yi == 0 {
+            CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                boundary_condition_velocity: [0.0, 0.0],
+            })
+        } else {
             // Set initial fluid velocity to be equal to the inflow velocity to
             // speed up convergence at the beginning of the simulation.
-            row.push(Cell { velocity: [inflow_x_velocity, 0.0], ..Default::default() });
+            return Cell {
+                velocity: [inflow_x_velocity, 0.0],
+                ..Default::default()
+            };
+        };
+        Cell {
+            cell_type,
+            ..Default::default()
         }
-        space_domain.push(row);
-    }
+    };
 
-    for xi in 0..x {
-        for yi in 0..y {
-            if xi == 0 {
-                space_domain[xi][yi] = Cell {
-                    cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell),
-                    velocity: [inflow_x_velocity, 0.0],
-                    ..Default::default()
-                };
-                continue;
-            }
-            if xi == x - 1 {
-                space_domain[xi][yi] = Cell {
-                    cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::OutFlowCell),
-                    ..Default::default()
-                };
-                continue;
-            }
-            if yi == y - 1 || yi == 0 {
-                space_domain[xi][yi] = Cell {
-                    cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
-                        boundary_condition_velocity: [0.0, 0.0],
-                    }),
-                    ..Default::default()
-                };
-                continue;
-            }
-        }
+    let delta_space = [x_length / (x as f32), y_length / (y as f32)];
+    let gamma = 0.9;
+
+    let preset = SimulationPreset {
+        space_domain: SpaceDomain::try_new(space_size, delta_space, gamma, layout)?,
+        delta_time: 0.005,
+        reynolds: 100.0,
+        acceleration: [0.0, 0.0],
+        events: EventSchedule::new(),
+        obstacles: Vec::new(),
+        dye_buoyancy: None,
     }
-    for xi in [0, x - 1] {
-        for yi in [0, y - 1] {
-            space_domain[xi][yi] = Cell {
-                cell_type: CellType::VoidCell,
+    .with_inflow_profile(InflowProfile::Uniform {
+        speed: inflow_x_velocity,
+    })
+    .with_obstacle(Obstacle::new(
+        "cylinder",
+        Shape::Circle {
+            center: [2.05, 2.05],
+            radius: CYLINDER_DIAMETER / 2.0,
+        },
+    ))
+    .with_edge_segment("inlet", Edge::Left)
+    .with_edge_segment("outlet", Edge::Right)
+    .with_edge_segment("bottom_wall", Edge::Bottom)
+    .with_edge_segment("top_wall", Edge::Top);
+    let cylinder = preset.obstacles[0].cells().to_vec();
+    Ok(preset.with_segment(BoundarySegment::new("cylinder", cylinder)))
+}
+
+pub fn kelvin_helmholtz() -> SimulationPreset {
+    kelvin_helmholtz_sized([128, 128])
+}
+
+// Doubly periodic unit square with two shear layers, the fluid in the middle band
+// moves right and carries dye, the rest moves left. A seeded perturbation makes the
+// layers roll up the same way on every run.
+pub fn kelvin_helmholtz_sized(space_size: [usize; 2]) -> SimulationPreset {
+    try_kelvin_helmholtz_sized(space_size).unwrap_or_else(|error| panic!("{error}"))
+}
+
+pub fn try_kelvin_helmholtz_sized(
+    space_size: [usize; 2],
+) -> Result<SimulationPreset, AllocationError> {
+    let length = 1.0;
+    let [x, y] = space_size;
+    assert!(x >= 4 && y >= 4, "the domain needs at least 4 x 4 cells");
+
+    let shear_velocity = 1.0;
+    let layer_thickness = length / 30.0;
+
+    let delta_space = [length / ((x - 2) as f32), length / ((y - 2) as f32)];
+    let gamma = 0.9;
+
+    // A ring of periodic cells around the fluid
+    let layout = |xi: usize, yi: usize| {
+        if xi == 0 || xi == x - 1 || yi == 0 || yi == y - 1 {
+            return Cell {
+                cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::PeriodicCell),
                 ..Default::default()
             };
         }
+        let position = (yi as f32 - 0.5) * delta_space[1];
+        Cell {
+            dye: if (0.25..0.75).contains(&position) {
+                1.0
+            } else {
+                0.0
+            },
+            ..Default::default()
+        }
+    };
+    let space_domain = SpaceDomain::try_new(space_size, delta_space, gamma, layout)?;
+
+    Ok(SimulationPreset {
+        space_domain,
+        delta_time: 0.002,
+        reynolds: 5000.0,
+        acceleration: [0.0, 0.0],
+        events: EventSchedule::new(),
+        obstacles: Vec::new(),
+        dye_buoyancy: None,
     }
+    // Positions include the periodic cells on the left and bottom
+    .initial_velocity(|_, position_y| {
+        let position = position_y - delta_space[1];
+        let profile = if position < 0.5 {
+            ((position - 0.25) / layer_thickness).tanh()
+        } else {
+            ((0.75 - position) / layer_thickness).tanh()
+        };
+        [shear_velocity * profile, 0.0]
+    })
+    .perturbed(&Perturbation {
+        amplitude: 0.01 * shear_velocity,
+        wavelength: length / 2.0,
+        seed: 0,
+    }))
+}
+
+pub fn buoyant_plume() -> SimulationPreset {
+    buoyant_plume_sized([64, 128])
+}
+
+// Smoke entering slowly through an inlet in the middle of the floor of a tall box open
+// at the top. The dye is lighter than the surrounding fluid, buoyancy accelerates it
+// upwards and the rising plume rolls up into a mushroom cap.
+pub fn buoyant_plume_sized(space_size: [usize; 2]) -> SimulationPreset {
+    try_buoyant_plume_sized(space_size).unwrap_or_else(|error| panic!("{error}"))
+}
+
+pub fn try_buoyant_plume_sized(
+    space_size: [usize; 2],
+) -> Result<SimulationPreset, AllocationError> {
+    let x_length = 1.0;
+    let y_length = 2.0;
+    let [x, y] = space_size;
+    assert!(x >= 8 && y >= 5, "the box needs at least 8 x 5 cells");
+
+    let inflow_y_velocity = 0.1;
+    let inlet = x / 2 - x / 16..x / 2 + x / 16;
 
-    let radius = 5.0;
-    let center = [20, 20];
-
-    for xi in 14..26 {
-        for yi in 14..26 {
-            let x_dist = xi - center[0];
-            let y_dist = yi - center[1];
-            let distance = ((x_dist * x_dist + y_dist * y_dist) as f32).sqrt();
-
-            if distance < radius {
-                space_domain[xi as usize][yi as usize] = Cell {
-                    cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
-                        boundary_condition_velocity: [0.0, 0.0],
-                    }),
-                    ..Default::default()
-                };
+    let layout = |xi: usize, yi: usize| {
+        if (xi == 0 || xi == x - 1) && (yi == 0 || yi == y - 1) {
+            Cell {
+                cell_type: CellType::VoidCell,
+                ..Default::default()
+            }
+        } else if yi == y - 1 {
+            Cell {
+                cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::OutFlowCell),
+                ..Default::default()
+            }
+        } else if yi == 0 && inlet.contains(&xi) {
+            Cell {
+                cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell),
+                dye: 1.0,
+                ..Default::default()
             }
+        } else if xi == 0 || xi == x - 1 || yi == 0 {
+            Cell {
+                cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                    boundary_condition_velocity: [0.0, 0.0],
+                }),
+                ..Default::default()
+            }
+        } else if inlet.contains(&xi) {
+            // A column rising at the inflow velocity carries the inflow to the
+            // outflow from the start, like the channel presets
+            Cell {
+                velocity: [0.0, inflow_y_velocity],
+                ..Default::default()
+            }
+        } else {
+            Cell::default()
         }
-    }
+    };
 
     let delta_space = [x_length / (x as f32), y_length / (y as f32)];
     let gamma = 0.9;
 
-    SimulationPreset {
-        space_domain: SpaceDomain::new(space_domain, delta_space, gamma),
+    Ok(SimulationPreset {
+        space_domain: SpaceDomain::try_new(space_size, delta_space, gamma, layout)?,
         delta_time: 0.005,
-        reynolds: 100.0,
+        reynolds: 1000.0,
         acceleration: [0.0, 0.0],
+        events: EventSchedule::new(),
+        obstacles: Vec::new(),
+        // Undiluted dye is ten percent lighter than the fluid under gravity
+        dye_buoyancy: Some([0.0, 0.1 * 9.81]),
     }
+    .with_inflow_profile(InflowProfile::Uniform {
+        speed: inflow_y_velocity,
+    }))
 }
@@ -0,0 +1,77 @@
+use crate::presets::PresetFn;
+use crate::resample::FieldSampler;
+use crate::simulation::Simulation;
+use crate::solver::FluidSolver;
+
+// Quick look at a preset on a grid coarsened by factor in both directions, with a
+// timestep factor times larger. Once the flow gets interesting refine() continues
+// from the coarse state on the full grid instead of starting over.
+pub struct Preview {
+    preset: PresetFn,
+    space_size: [usize; 2],
+    factor: usize,
+    simulation: Simulation,
+}
+
+impl Preview {
+    pub fn new(preset: PresetFn, space_size: [usize; 2], factor: usize) -> Self {
+        assert!(factor > 0, "coarsening factor must be positive");
+        let mut simulation =
+            Simulation::from_preset(preset([space_size[0] / factor, space_size[1] / factor]));
+        simulation.set_delta_time(simulation.delta_time() * factor as f32);
+
+        Self {
+            preset,
+            space_size,
+            factor,
+            simulation,
+        }
+    }
+
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+
+    // Size of the refined grid in cells
+    pub fn space_size(&self) -> [usize; 2] {
+        self.space_size
+    }
+
+    // The coarse run
+    pub fn simulation(&self) -> &Simulation {
+        &self.simulation
+    }
+
+    pub fn simulation_mut(&mut self) -> &mut Simulation {
+        &mut self.simulation
+    }
+
+    // Full resolution simulation continuing at the time of the coarse run, with its
+    // parameters and the timestep scaled back down. The geometry is built at full
+    // resolution from the preset.
+    pub fn refine(self) -> Simulation {
+        let mut simulation = Simulation::from_preset((self.preset)(self.space_size));
+        let mut parameters = self.simulation.parameters();
+        parameters.delta_time /= self.factor as f32;
+        simulation.set_parameters(parameters);
+        simulation.set_advection_scheme(self.simulation.advection_scheme());
+
+        interpolate_state(&self.simulation, &mut simulation);
+        simulation
+    }
+}
+
+// Interpolate velocity, pressure and dye of source onto the fluid cells of target,
+// which has the same physical extent, see resample_cells
+pub fn interpolate_state(source: &dyn FluidSolver, target: &mut dyn FluidSolver) {
+    let cells = source.cells();
+    let sampler = FieldSampler::new(&cells, source.space_size(), source.delta_space());
+    let mut checkpoint = target.checkpoint();
+    sampler.fill_fluid_cells(
+        &mut checkpoint.cells,
+        target.space_size(),
+        target.delta_space(),
+    );
+    checkpoint.time = source.time();
+    target.restore(&checkpoint);
+}
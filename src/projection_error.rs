@@ -0,0 +1,143 @@
+use crate::cell::CellType;
+use crate::colormap::{symmetric_range, Colormap};
+use crate::field::{FieldView, Location};
+use crate::field_snapshot::FieldSnapshot;
+use crate::frame_renderer::{self, Image};
+use crate::linear_solver::PoissonStencil;
+use crate::simulation::Simulation;
+use crate::solver::FluidSolver;
+
+// How far the latest timestep is from incompressible, per fluid cell. The divergence
+// du/dx + dv/dy of the projected velocity and the residual laplacian(p) - rhs of the
+// pressure solve gather where the solver struggles, like thin gaps and sharp
+// corners. Other cells hold zero.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectionError {
+    pub time: f32, // seconds
+    pub space_size: [usize; 2],
+    pub delta_space: [f32; 2], // meters
+    pub cell_type: Vec<CellType>,
+    pub divergence: Vec<f32>, // 1/seconds
+    pub residual: Vec<f32>,
+}
+
+impl ProjectionError {
+    pub fn new(simulation: &Simulation) -> Self {
+        let space_domain = simulation.space_domain();
+        let [nx, ny] = simulation.space_size();
+        let [dx, dy] = simulation.delta_space();
+        let stencil = PoissonStencil::new([dx, dy]);
+
+        let mut divergence = vec![0.0; nx * ny];
+        let mut residual = vec![0.0; nx * ny];
+        for &index in space_domain.fluid_cells() {
+            let (x, y) = space_domain.position(index);
+            divergence[index] = (space_domain.u(x, y) - space_domain.u(x - 1, y)) / dx
+                + (space_domain.v(x, y) - space_domain.v(x, y - 1)) / dy;
+            residual[index] = stencil.laplacian(space_domain, x, y) - space_domain.rhs(x, y);
+        }
+        Self {
+            time: simulation.time(),
+            space_size: [nx, ny],
+            delta_space: [dx, dy],
+            cell_type: simulation.fields().cell_type.clone(),
+            divergence,
+            residual,
+        }
+    }
+
+    pub fn divergence(&self) -> FieldView<'_> {
+        self.view(&self.divergence)
+    }
+
+    pub fn residual(&self) -> FieldView<'_> {
+        self.view(&self.residual)
+    }
+
+    // Fluid cell with the largest absolute divergence and its divergence, None
+    // without fluid cells
+    pub fn worst_divergence(&self) -> Option<([usize; 2], f32)> {
+        worst(self.divergence())
+    }
+
+    pub fn worst_residual(&self) -> Option<([usize; 2], f32)> {
+        worst(self.residual())
+    }
+
+    // Root mean square over the fluid cells, the residual one is the norm the pressure
+    // solve stops on. Zero without fluid cells.
+    pub fn rms_divergence(&self) -> f32 {
+        rms(self.divergence())
+    }
+
+    pub fn rms_residual(&self) -> f32 {
+        rms(self.residual())
+    }
+
+    // Divergence and residual as named fields, see FieldSnapshot::save
+    pub fn to_snapshot(&self) -> FieldSnapshot {
+        FieldSnapshot {
+            time: self.time,
+            space_size: self.space_size,
+            delta_space: self.delta_space,
+            is_fluid: self
+                .cell_type
+                .iter()
+                .map(|cell_type| matches!(cell_type, CellType::FluidCell))
+                .collect(),
+            fields: vec![
+                ("divergence".to_string(), self.divergence.clone()),
+                ("residual".to_string(), self.residual.clone()),
+            ],
+        }
+    }
+
+    // Over a range symmetric around zero, suited to diverging colormaps
+    pub fn divergence_heatmap(&self, colormap: &Colormap, pixels_per_cell: usize) -> Image {
+        self.heatmap(&self.divergence, colormap, pixels_per_cell)
+    }
+
+    pub fn residual_heatmap(&self, colormap: &Colormap, pixels_per_cell: usize) -> Image {
+        self.heatmap(&self.residual, colormap, pixels_per_cell)
+    }
+
+    fn heatmap(&self, values: &[f32], colormap: &Colormap, pixels_per_cell: usize) -> Image {
+        assert!(pixels_per_cell > 0, "pixels per cell must be positive");
+        let range = self.view(values).range().unwrap_or([0.0, 0.0]);
+        frame_renderer::rasterize(
+            colormap,
+            values,
+            &self.cell_type,
+            symmetric_range(range),
+            self.space_size,
+            pixels_per_cell,
+        )
+    }
+
+    fn view<'a>(&'a self, values: &'a [f32]) -> FieldView<'a> {
+        FieldView::from_values(
+            Location::CellCentre,
+            values,
+            &self.cell_type,
+            self.space_size,
+            self.delta_space,
+        )
+    }
+}
+
+fn worst(view: FieldView) -> Option<([usize; 2], f32)> {
+    view.fluid_iter()
+        .fold(None, |worst, (cell, value)| match worst {
+            Some((_, worst_value)) if f32::abs(worst_value) >= value.abs() => worst,
+            _ => Some((cell, value)),
+        })
+}
+
+fn rms(view: FieldView) -> f32 {
+    let (sum, count) = view
+        .fluid_iter()
+        .fold((0.0, 0), |(sum, count), (_, value)| {
+            (sum + value as f64 * value as f64, count + 1)
+        });
+    (sum / count.max(1) as f64).sqrt() as f32
+}
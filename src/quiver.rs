@@ -0,0 +1,37 @@
+use crate::cell::CellType;
+use crate::solver::FluidSolver;
+
+// One velocity glyph, in meters with the origin at the bottom left of the domain
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arrow {
+    pub position: [f32; 2], // cell center
+    pub vector: [f32; 2],   // velocity * scale
+}
+
+// Arrows for the fluid cells of every stride-th row and column, sampled from the
+// middle of each stride x stride block
+pub fn quiver(solver: &dyn FluidSolver, stride: usize, scale: f32) -> Vec<Arrow> {
+    assert!(stride > 0, "stride must be positive");
+    let space_size = solver.space_size();
+    let delta_space = solver.delta_space();
+
+    (stride / 2..space_size[0])
+        .step_by(stride)
+        .flat_map(|x| {
+            (stride / 2..space_size[1])
+                .step_by(stride)
+                .map(move |y| (x, y))
+        })
+        .filter(|&(x, y)| matches!(solver.get_cell(x, y).cell_type, CellType::FluidCell))
+        .map(|(x, y)| {
+            let velocity = solver.get_centered_velocity(x, y);
+            Arrow {
+                position: [
+                    (x as f32 + 0.5) * delta_space[0],
+                    (y as f32 + 0.5) * delta_space[1],
+                ],
+                vector: [velocity[0] * scale, velocity[1] * scale],
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,50 @@
+use rayon::prelude::*;
+
+// Below this length pairwise reductions run sequentially
+const SEQUENTIAL_LENGTH: usize = 1024;
+
+// How sums over the domain (residual norms, ranges) are reduced
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Reduction {
+    // Single threaded, left to right
+    #[default]
+    Sequential,
+    // Multithreaded with rayon's adaptive splitting, results may vary between runs
+    Parallel,
+    // Multithreaded pairwise tree with fixed split points, bit-identical across
+    // runs and thread counts
+    Deterministic,
+}
+
+pub fn sum(values: &[f32], reduction: Reduction) -> f32 {
+    match reduction {
+        Reduction::Sequential => values.iter().sum(),
+        Reduction::Parallel => values.par_iter().sum(),
+        Reduction::Deterministic => pairwise_sum(values),
+    }
+}
+
+// [min, max], ignoring NaN
+pub fn min_max(values: &[f32], reduction: Reduction) -> [f32; 2] {
+    let identity = [f32::INFINITY, f32::NEG_INFINITY];
+    let combine = |a: [f32; 2], b: [f32; 2]| [a[0].min(b[0]), a[1].max(b[1])];
+
+    match reduction {
+        Reduction::Sequential => values
+            .iter()
+            .fold(identity, |range, &value| combine(range, [value, value])),
+        Reduction::Parallel | Reduction::Deterministic => values
+            .par_iter()
+            .fold(|| identity, |range, &value| combine(range, [value, value]))
+            .reduce(|| identity, combine),
+    }
+}
+
+fn pairwise_sum(values: &[f32]) -> f32 {
+    if values.len() <= SEQUENTIAL_LENGTH {
+        return values.iter().sum();
+    }
+    let (left, right) = values.split_at(values.len() / 2);
+    let (left, right) = rayon::join(|| pairwise_sum(left), || pairwise_sum(right));
+    left + right
+}
@@ -0,0 +1,189 @@
+use crate::cell::CellType;
+use crate::fluid_zones::FluidZones;
+use crate::solver::FluidSolver;
+use crate::space_domain::bilinear;
+
+// Line integrals use samples this many times denser than the grid
+const SAMPLES_PER_CELL: f32 = 2.0;
+
+// Statistics over the fluid cells whose centers lie inside a rectangle, or of a
+// fluid zone
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegionStatistics {
+    pub fluid_cell_count: usize,
+    pub mean_velocity: [f32; 2], // meters/seconds
+    pub mean_speed: f32,
+    pub max_speed: f32,
+    pub mean_pressure: f32,
+    pub pressure_range: [f32; 2],
+}
+
+// Statistics along a line section
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SectionStatistics {
+    pub length: f32, // meters
+    // Volume flux per unit depth in meters^2/seconds, positive for flow crossing
+    // from the left to the right side when walking from start to end
+    pub flow_rate: f32,
+    pub mean_normal_velocity: f32,
+    // Over the samples next to fluid cells, None if there are none
+    pub mean_pressure: Option<f32>,
+}
+
+// min and max are opposite corners in meters, None if no fluid cell is inside
+pub fn rectangle_statistics(
+    solver: &dyn FluidSolver,
+    min: [f32; 2],
+    max: [f32; 2],
+) -> Option<RegionStatistics> {
+    let [nx, ny] = solver.space_size();
+    let [dx, dy] = solver.delta_space();
+    let cells = (0..nx)
+        .flat_map(|x| (0..ny).map(move |y| (x, y)))
+        .filter(|&(x, y)| {
+            let center = [(x as f32 + 0.5) * dx, (y as f32 + 0.5) * dy];
+            (min[0]..=max[0]).contains(&center[0])
+                && (min[1]..=max[1]).contains(&center[1])
+                && matches!(solver.get_cell(x, y).cell_type, CellType::FluidCell)
+        });
+    statistics(solver, cells)
+}
+
+// Statistics of every fluid zone, in the order of the zones. Zones have pressure
+// levels of their own, see FluidZones.
+pub fn zone_statistics(solver: &dyn FluidSolver, zones: &FluidZones) -> Vec<RegionStatistics> {
+    let ny = solver.space_size()[1];
+    (0..zones.len())
+        .filter_map(|zone| {
+            let cells = zones
+                .cells(zone)
+                .iter()
+                .map(|&index| (index / ny, index % ny));
+            statistics(solver, cells)
+        })
+        .collect()
+}
+
+// Over the fluid cells given, None if there are none
+fn statistics(
+    solver: &dyn FluidSolver,
+    cells: impl Iterator<Item = (usize, usize)>,
+) -> Option<RegionStatistics> {
+    let mut fluid_cell_count = 0;
+    let mut velocity_sum = [0.0, 0.0];
+    let mut speed_sum = 0.0;
+    let mut max_speed = 0.0f32;
+    let mut pressure_sum = 0.0;
+    let mut pressure_range = [f32::INFINITY, f32::NEG_INFINITY];
+
+    for (x, y) in cells {
+        let velocity = solver.get_centered_velocity(x, y);
+        let speed = (velocity[0].powi(2) + velocity[1].powi(2)).sqrt();
+        let pressure = solver.get_cell(x, y).pressure;
+        fluid_cell_count += 1;
+        velocity_sum[0] += velocity[0];
+        velocity_sum[1] += velocity[1];
+        speed_sum += speed;
+        max_speed = max_speed.max(speed);
+        pressure_sum += pressure;
+        pressure_range = [
+            pressure_range[0].min(pressure),
+            pressure_range[1].max(pressure),
+        ];
+    }
+
+    if fluid_cell_count == 0 {
+        return None;
+    }
+    let count = fluid_cell_count as f32;
+    Some(RegionStatistics {
+        fluid_cell_count,
+        mean_velocity: [velocity_sum[0] / count, velocity_sum[1] / count],
+        mean_speed: speed_sum / count,
+        max_speed,
+        mean_pressure: pressure_sum / count,
+        pressure_range,
+    })
+}
+
+// Midpoint rule over the staggered velocities, so a section along cell faces sums
+// the face fluxes. Faces that do not touch a fluid cell carry no flow.
+pub fn section_statistics(
+    solver: &dyn FluidSolver,
+    start: [f32; 2],
+    end: [f32; 2],
+) -> SectionStatistics {
+    let space_size = solver.space_size();
+    let delta_space = solver.delta_space();
+    let cells = solver.cells();
+    let cell = |x: usize, y: usize| &cells[x * space_size[1] + y];
+    let is_fluid = |x: usize, y: usize| {
+        x < space_size[0]
+            && y < space_size[1]
+            && matches!(cell(x, y).cell_type, CellType::FluidCell)
+    };
+
+    let direction = [end[0] - start[0], end[1] - start[1]];
+    let length = (direction[0].powi(2) + direction[1].powi(2)).sqrt();
+    if length == 0.0 {
+        return SectionStatistics {
+            length,
+            flow_rate: 0.0,
+            mean_normal_velocity: 0.0,
+            mean_pressure: None,
+        };
+    }
+    // Right hand normal
+    let normal = [direction[1] / length, -direction[0] / length];
+
+    let sample_count =
+        ((length / delta_space[0].min(delta_space[1]) * SAMPLES_PER_CELL).ceil() as usize).max(1);
+    let step = length / sample_count as f32;
+
+    let mut flow_rate = 0.0;
+    let mut pressure_sum = 0.0;
+    let mut pressure_count = 0;
+    for sample in 0..sample_count {
+        let t = (sample as f32 + 0.5) / sample_count as f32;
+        let position = [start[0] + t * direction[0], start[1] + t * direction[1]];
+
+        let u = bilinear(space_size, delta_space, position, [1.0, 0.5], |x, y| {
+            if is_fluid(x, y) || is_fluid(x + 1, y) {
+                cell(x, y).velocity[0]
+            } else {
+                0.0
+            }
+        });
+        let v = bilinear(space_size, delta_space, position, [0.5, 1.0], |x, y| {
+            if is_fluid(x, y) || is_fluid(x, y + 1) {
+                cell(x, y).velocity[1]
+            } else {
+                0.0
+            }
+        });
+        flow_rate += (u * normal[0] + v * normal[1]) * step;
+
+        // Pressure interpolated from the surrounding fluid cells only
+        let fluid_weight = bilinear(space_size, delta_space, position, [0.5, 0.5], |x, y| {
+            is_fluid(x, y) as u8 as f32
+        });
+        if fluid_weight > 0.0 {
+            let pressure = bilinear(space_size, delta_space, position, [0.5, 0.5], |x, y| {
+                if is_fluid(x, y) {
+                    cell(x, y).pressure
+                } else {
+                    0.0
+                }
+            });
+            pressure_sum += pressure / fluid_weight;
+            pressure_count += 1;
+        }
+    }
+
+    SectionStatistics {
+        length,
+        flow_rate,
+        mean_normal_velocity: flow_rate / length,
+        mean_pressure: (pressure_count > 0).then(|| pressure_sum / pressure_count as f32),
+    }
+}
@@ -0,0 +1,136 @@
+use crate::cell::{Cell, CellType};
+use crate::space_domain::bilinear;
+
+// Midpoint samples per source cell when averaging over a face or cell
+const SAMPLES_PER_CELL: usize = 2;
+
+// Cells of a grid resampled to new_size cells covering the same extent. Every cell
+// starts as a copy of the nearest source cell, edge cells only look along the edge
+// so the domain stays closed. Obstacles thinner than a new cell may vanish when
+// coarsening. Fluid cells then get their face velocities averaged over the new face
+// and pressure and dye averaged over the new cell, so flow rates carry over.
+pub fn resample_cells(
+    cells: &[Cell],
+    space_size: [usize; 2],
+    delta_space: [f32; 2],
+    new_size: [usize; 2],
+) -> Vec<Cell> {
+    assert!(
+        new_size[0] >= 3 && new_size[1] >= 3,
+        "the domain needs at least 3 x 3 cells"
+    );
+    let [nx, ny] = new_size;
+    let nearest = |i: usize, axis: usize| {
+        let (n, new_n) = (space_size[axis], new_size[axis]);
+        if i == 0 {
+            0
+        } else if i == new_n - 1 {
+            n - 1
+        } else {
+            (((i as f32 + 0.5) * n as f32 / new_n as f32) as usize).clamp(1, n - 2)
+        }
+    };
+
+    let mut resampled = Vec::with_capacity(nx * ny);
+    for x in 0..nx {
+        for y in 0..ny {
+            resampled.push(cells[nearest(x, 0) * space_size[1] + nearest(y, 1)].clone());
+        }
+    }
+
+    let new_delta_space = [
+        delta_space[0] * space_size[0] as f32 / nx as f32,
+        delta_space[1] * space_size[1] as f32 / ny as f32,
+    ];
+    let sampler = FieldSampler::new(cells, space_size, delta_space);
+    sampler.fill_fluid_cells(&mut resampled, new_size, new_delta_space);
+    resampled
+}
+
+// Averages of the fields of a grid over faces and cells of another grid with the
+// same extent, bilinear when refining
+pub(crate) struct FieldSampler<'a> {
+    cells: &'a [Cell],
+    space_size: [usize; 2],
+    delta_space: [f32; 2],
+}
+
+impl<'a> FieldSampler<'a> {
+    pub(crate) fn new(cells: &'a [Cell], space_size: [usize; 2], delta_space: [f32; 2]) -> Self {
+        Self {
+            cells,
+            space_size,
+            delta_space,
+        }
+    }
+
+    // Overwrite velocity, pressure and dye of the fluid cells of target. Faces towards
+    // boundary cells and the boundary cells themselves keep their values, the next
+    // timestep enforces them.
+    pub(crate) fn fill_fluid_cells(
+        &self,
+        target: &mut [Cell],
+        space_size: [usize; 2],
+        delta_space: [f32; 2],
+    ) {
+        let [nx, ny] = space_size;
+        let [dx, dy] = delta_space;
+        let is_boundary = |cell: &Cell| cell.cell_type.blocks_flow();
+
+        for x in 0..nx {
+            for y in 0..ny {
+                if !matches!(target[x * ny + y].cell_type, CellType::FluidCell) {
+                    continue;
+                }
+                let right = x + 1 < nx && !is_boundary(&target[(x + 1) * ny + y]);
+                let top = y + 1 < ny && !is_boundary(&target[x * ny + y + 1]);
+                let min = [x as f32 * dx, y as f32 * dy];
+                let max = [min[0] + dx, min[1] + dy];
+
+                let cell = &mut target[x * ny + y];
+                if right {
+                    cell.velocity[0] =
+                        self.mean([max[0], min[1]], [max[0], max[1]], [1.0, 0.5], |cell| {
+                            cell.velocity[0]
+                        });
+                }
+                if top {
+                    cell.velocity[1] =
+                        self.mean([min[0], max[1]], [max[0], max[1]], [0.5, 1.0], |cell| {
+                            cell.velocity[1]
+                        });
+                }
+                cell.pressure = self.mean(min, max, [0.5, 0.5], |cell| cell.pressure);
+                cell.dye = self.mean(min, max, [0.5, 0.5], |cell| cell.dye);
+            }
+        }
+    }
+
+    // Midpoint rule over the rectangle min..max in meters, which may be a face
+    fn mean(&self, min: [f32; 2], max: [f32; 2], offset: [f32; 2], value: fn(&Cell) -> f32) -> f32 {
+        let samples = [0, 1].map(|axis| {
+            (((max[axis] - min[axis]) / self.delta_space[axis] * SAMPLES_PER_CELL as f32).ceil()
+                as usize)
+                .max(1)
+        });
+        let ny = self.space_size[1];
+
+        let mut sum = 0.0;
+        for i in 0..samples[0] {
+            for j in 0..samples[1] {
+                let position = [
+                    min[0] + (max[0] - min[0]) * (i as f32 + 0.5) / samples[0] as f32,
+                    min[1] + (max[1] - min[1]) * (j as f32 + 0.5) / samples[1] as f32,
+                ];
+                sum += bilinear(
+                    self.space_size,
+                    self.delta_space,
+                    position,
+                    offset,
+                    |x, y| value(&self.cells[x * ny + y]),
+                );
+            }
+        }
+        sum / (samples[0] * samples[1]) as f32
+    }
+}
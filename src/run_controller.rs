@@ -0,0 +1,254 @@
+use crate::output_sink::OutputSink;
+use crate::solver::FluidSolver;
+
+use std::io;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    MaxTime,
+    MaxSteps,
+    // The largest velocity change of a timestep fell below the tolerance
+    SteadyState,
+    // Non-finite values, or a speed above the speed limit
+    Unstable,
+    WallClockBudget,
+    // An output sink returned an error, see RunController::take_output_error
+    OutputFailed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub steps: usize,
+    pub time: f32, // seconds of simulated time
+    pub elapsed: Duration,
+    // Largest change of a velocity component in the last timestep, meters/seconds
+    pub velocity_change: f32,
+    pub max_speed: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunReport {
+    pub reason: StopReason,
+    pub progress: Progress,
+}
+
+type ProgressCallback<'a> = Box<dyn FnMut(&Progress) + 'a>;
+type StepCallback<'a> = Box<dyn FnMut(&dyn FluidSolver) + 'a>;
+
+// Steps a solver until the first stopping criterion is met, criteria are set
+// with the builder methods
+#[derive(Default)]
+pub struct RunController<'a> {
+    max_time: Option<f32>,
+    max_steps: Option<usize>,
+    steady_state_tolerance: Option<f32>,
+    speed_limit: Option<f32>,
+    wall_clock_budget: Option<Duration>,
+    progress_interval: usize,
+    on_progress: Option<ProgressCallback<'a>>,
+    on_step: Option<StepCallback<'a>>,
+    sinks: Vec<Box<dyn OutputSink + 'a>>,
+    snapshot_interval: Option<usize>,
+    // Snapshots taken by every run of this controller
+    snapshots: usize,
+    output_error: Option<io::Error>,
+}
+
+impl<'a> RunController<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Stop once the solver time reaches max_time seconds
+    pub fn max_time(mut self, max_time: f32) -> Self {
+        self.max_time = Some(max_time);
+        self
+    }
+
+    pub fn max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    // Stop once no velocity component changes by more than tolerance in a timestep
+    pub fn steady_state(mut self, tolerance: f32) -> Self {
+        assert!(tolerance > 0.0, "steady state tolerance must be positive");
+        self.steady_state_tolerance = Some(tolerance);
+        self
+    }
+
+    // Treat speeds above speed_limit as unstable, non-finite values always are
+    pub fn speed_limit(mut self, speed_limit: f32) -> Self {
+        self.speed_limit = Some(speed_limit);
+        self
+    }
+
+    pub fn wall_clock_budget(mut self, budget: Duration) -> Self {
+        self.wall_clock_budget = Some(budget);
+        self
+    }
+
+    // Called every interval steps and once more when the run stops
+    pub fn on_progress(mut self, interval: usize, callback: impl FnMut(&Progress) + 'a) -> Self {
+        assert!(interval > 0, "progress interval must be positive");
+        self.progress_interval = interval;
+        self.on_progress = Some(Box::new(callback));
+        self
+    }
+
+    // Called after every timestep with the solver, for sampling time series
+    pub fn on_step(mut self, callback: impl FnMut(&dyn FluidSolver) + 'a) -> Self {
+        self.on_step = Some(Box::new(callback));
+        self
+    }
+
+    // Sinks are called in the order they were added
+    pub fn sink(mut self, sink: impl OutputSink + 'a) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    // Call OutputSink::on_snapshot every interval steps
+    pub fn snapshot_interval(mut self, interval: usize) -> Self {
+        assert!(interval > 0, "snapshot interval must be positive");
+        self.snapshot_interval = Some(interval);
+        self
+    }
+
+    // The first sink error of the last run that stopped with StopReason::OutputFailed
+    pub fn take_output_error(&mut self) -> Option<io::Error> {
+        self.output_error.take()
+    }
+
+    pub fn run(&mut self, solver: &mut dyn FluidSolver) -> RunReport {
+        assert!(
+            self.max_time.is_some()
+                || self.max_steps.is_some()
+                || self.steady_state_tolerance.is_some()
+                || self.wall_clock_budget.is_some(),
+            "a run needs a stopping criterion"
+        );
+
+        self.output_error = None;
+        let start = Instant::now();
+        let mut previous_velocities: Vec<[f32; 2]> = solver.fields().velocities().collect();
+        let mut steps = 0;
+
+        loop {
+            let previous_time = solver.time();
+            solver.iterate_one_timestep();
+            steps += 1;
+            let delta_time = solver.time() - previous_time;
+            if let Some(on_step) = self.on_step.as_mut() {
+                on_step(solver);
+            }
+
+            let mut velocity_change = 0.0f32;
+            let mut max_speed = 0.0f32;
+            let mut is_finite = true;
+            let fields = solver.fields();
+            for ((velocity, pressure), previous) in fields
+                .velocities()
+                .zip(fields.pressure.iter())
+                .zip(previous_velocities.iter_mut())
+            {
+                let [u, v] = velocity;
+                is_finite &= u.is_finite() && v.is_finite() && pressure.is_finite();
+                velocity_change = velocity_change
+                    .max((u - previous[0]).abs())
+                    .max((v - previous[1]).abs());
+                max_speed = max_speed.max((u.powi(2) + v.powi(2)).sqrt());
+                *previous = velocity;
+            }
+
+            let progress = Progress {
+                steps,
+                time: solver.time(),
+                elapsed: start.elapsed(),
+                velocity_change,
+                max_speed,
+            };
+            let mut reason = self.stop_reason(&progress, delta_time, is_finite);
+            if let Err(error) = self.write_outputs(solver, &progress) {
+                self.output_error = Some(error);
+                reason = Some(StopReason::OutputFailed);
+            }
+
+            if let Some(on_progress) = self.on_progress.as_mut() {
+                if reason.is_some() || steps.is_multiple_of(self.progress_interval) {
+                    on_progress(&progress);
+                }
+            }
+            if let Some(reason) = reason {
+                return self.finish_outputs(solver, RunReport { reason, progress });
+            }
+        }
+    }
+
+    fn write_outputs(&mut self, solver: &dyn FluidSolver, progress: &Progress) -> io::Result<()> {
+        let is_snapshot_due = self
+            .snapshot_interval
+            .is_some_and(|interval| progress.steps.is_multiple_of(interval));
+        for sink in self.sinks.iter_mut() {
+            sink.on_step(solver, progress)?;
+            if is_snapshot_due {
+                sink.on_snapshot(solver, self.snapshots)?;
+            }
+        }
+        if is_snapshot_due {
+            self.snapshots += 1;
+        }
+        Ok(())
+    }
+
+    // Every sink finishes, the first error is kept
+    fn finish_outputs(&mut self, solver: &dyn FluidSolver, mut report: RunReport) -> RunReport {
+        for sink in self.sinks.iter_mut() {
+            if let Err(error) = sink.on_finish(solver, &report) {
+                if self.output_error.is_none() {
+                    self.output_error = Some(error);
+                }
+                report.reason = StopReason::OutputFailed;
+            }
+        }
+        report
+    }
+
+    fn stop_reason(
+        &self,
+        progress: &Progress,
+        delta_time: f32,
+        is_finite: bool,
+    ) -> Option<StopReason> {
+        let is_too_fast = self
+            .speed_limit
+            .is_some_and(|speed_limit| progress.max_speed > speed_limit);
+        if !is_finite || is_too_fast {
+            Some(StopReason::Unstable)
+        } else if self
+            .steady_state_tolerance
+            .is_some_and(|tolerance| progress.velocity_change < tolerance)
+        {
+            Some(StopReason::SteadyState)
+        } else if self
+            .max_steps
+            .is_some_and(|max_steps| progress.steps >= max_steps)
+        {
+            Some(StopReason::MaxSteps)
+        } else if self
+            .max_time
+            // Half a timestep of slack against the rounding of the accumulated time
+            .is_some_and(|max_time| progress.time + delta_time / 2.0 >= max_time)
+        {
+            Some(StopReason::MaxTime)
+        } else if self
+            .wall_clock_budget
+            .is_some_and(|budget| progress.elapsed >= budget)
+        {
+            Some(StopReason::WallClockBudget)
+        } else {
+            None
+        }
+    }
+}
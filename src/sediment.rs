@@ -0,0 +1,202 @@
+use crate::cell::{BoundaryConditionCell, CellType};
+use crate::space_domain::SpaceDomain;
+
+// Largest fraction of a cell the concentration may cross per substep
+const COURANT_NUMBER: f32 = 0.9;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SedimentSettings {
+    pub settling_velocity: f32, // meters/seconds, downwards along -y
+    // Concentration of the fluid entering through inflow cells
+    pub inflow_concentration: f32,
+}
+
+// Dilute particles carried by the flow and sinking through it at the settling
+// velocity. The concentration is a volume fraction at the cell centers moved with
+// conservative first order upwind fluxes through the cell faces, so no sediment is
+// created or lost except through inflow and outflow cells. Particles settling onto
+// a no slip cell below the fluid stay there in its deposit, the thickness of
+// sediment (volume per area) it collected. Other walls hold the particles back.
+#[derive(Clone)]
+pub struct Sediment {
+    settings: SedimentSettings,
+    delta_space: [f32; 2], // meters
+    concentration: Vec<f32>,
+    deposit: Vec<f32>, // meters
+    // Reused between timesteps
+    change: Vec<f32>,
+}
+
+impl Sediment {
+    pub fn new(settings: SedimentSettings, space_domain: &SpaceDomain) -> Self {
+        assert!(
+            settings.settling_velocity >= 0.0,
+            "settling velocity must not be negative"
+        );
+        assert!(
+            settings.inflow_concentration >= 0.0,
+            "inflow concentration must not be negative"
+        );
+        let [nx, ny] = space_domain.space_size();
+        Self {
+            settings,
+            delta_space: space_domain.delta_space(),
+            concentration: vec![0.0; nx * ny],
+            deposit: vec![0.0; nx * ny],
+            change: vec![0.0; nx * ny],
+        }
+    }
+
+    pub fn settings(&self) -> SedimentSettings {
+        self.settings
+    }
+
+    // Indexed x * ny + y, zero outside the fluid
+    pub fn concentrations(&self) -> &[f32] {
+        &self.concentration
+    }
+
+    pub fn deposits(&self) -> &[f32] {
+        &self.deposit
+    }
+
+    // Initial or added concentration from a function of the position (x, y) in
+    // meters, sampled at the centers of the fluid cells
+    pub fn set_concentration(
+        &mut self,
+        space_domain: &SpaceDomain,
+        concentration: impl Fn(f32, f32) -> f32,
+    ) {
+        let [nx, ny] = space_domain.space_size();
+        let [dx, dy] = space_domain.delta_space();
+        for x in 0..nx {
+            for y in 0..ny {
+                if matches!(space_domain.cell_type(x, y), CellType::FluidCell) {
+                    self.concentration[x * ny + y] =
+                        concentration((x as f32 + 0.5) * dx, (y as f32 + 0.5) * dy).max(0.0);
+                }
+            }
+        }
+    }
+
+    // Sediment in suspension, meters^3 per meter of span
+    pub fn suspended(&self) -> f32 {
+        self.concentration.iter().sum::<f32>() * self.delta_space[0] * self.delta_space[1]
+    }
+
+    // Sediment on the bed, meters^3 per meter of span
+    pub fn deposited(&self) -> f32 {
+        self.deposit.iter().sum::<f32>() * self.delta_space[0]
+    }
+
+    // Concentration entering through a face from a boundary cell, None for walls
+    fn boundary_concentration(
+        &self,
+        space_domain: &SpaceDomain,
+        x: usize,
+        y: usize,
+    ) -> Option<f32> {
+        match space_domain.cell_type(x, y) {
+            CellType::FluidCell => Some(self.concentration[space_domain.index(x, y)]),
+            CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell) => {
+                Some(self.settings.inflow_concentration)
+            }
+            // Nothing comes back in through an outflow
+            CellType::BoundaryConditionCell(BoundaryConditionCell::OutFlowCell) => Some(0.0),
+            CellType::BoundaryConditionCell(BoundaryConditionCell::PeriodicCell) => {
+                let (source_x, source_y) = space_domain.periodic_source(x, y);
+                Some(self.concentration[space_domain.index(source_x, source_y)])
+            }
+            _ => None,
+        }
+    }
+
+    pub fn advance(&mut self, delta_time: f32, space_domain: &SpaceDomain) {
+        let [nx, ny] = space_domain.space_size();
+        let [dx, dy] = space_domain.delta_space();
+        let fields = space_domain.fields();
+        let settling = self.settings.settling_velocity;
+
+        // Substeps keeping the upwind fluxes stable
+        let rate = (0..nx * ny)
+            .filter(|&index| matches!(fields.cell_type[index], CellType::FluidCell))
+            .map(|index| {
+                let u = fields.u[index].abs().max(fields.u[index - ny].abs());
+                let v = (fields.v[index] - settling)
+                    .abs()
+                    .max((fields.v[index - 1] - settling).abs());
+                u / dx + v / dy
+            })
+            .fold(0.0, f32::max);
+        let substeps = (delta_time * rate / COURANT_NUMBER).ceil().max(1.0) as usize;
+        let delta_time = delta_time / substeps as f32;
+
+        for _ in 0..substeps {
+            self.change.fill(0.0);
+            for x in 0..nx {
+                for y in 0..ny {
+                    let index = x * ny + y;
+                    let is_fluid = matches!(fields.cell_type[index], CellType::FluidCell);
+                    // Right face, then top face with the settling velocity
+                    for (other_x, other_y, velocity, length) in [
+                        (x + 1, y, fields.u[index], dx),
+                        (x, y + 1, fields.v[index] - settling, dy),
+                    ] {
+                        if other_x >= nx || other_y >= ny {
+                            continue;
+                        }
+                        let other = other_x * ny + other_y;
+                        let other_is_fluid = matches!(fields.cell_type[other], CellType::FluidCell);
+                        if !is_fluid && !other_is_fluid {
+                            continue;
+                        }
+
+                        let flux = match (
+                            self.boundary_concentration(space_domain, x, y),
+                            self.boundary_concentration(space_domain, other_x, other_y),
+                        ) {
+                            (Some(concentration), Some(other_concentration)) => {
+                                velocity
+                                    * if velocity > 0.0 {
+                                        concentration
+                                    } else {
+                                        other_concentration
+                                    }
+                            }
+                            // Particles settle onto a no slip cell under the fluid
+                            (None, Some(other_concentration)) if other_y == y + 1 => {
+                                if let CellType::BoundaryConditionCell(
+                                    BoundaryConditionCell::NoSlipCell { .. },
+                                ) = fields.cell_type[index]
+                                {
+                                    let flux = -settling * other_concentration;
+                                    self.deposit[index] -= delta_time * flux;
+                                    flux
+                                } else {
+                                    0.0
+                                }
+                            }
+                            _ => 0.0,
+                        };
+                        self.change[index] -= flux / length;
+                        self.change[other] += flux / length;
+                    }
+                }
+            }
+
+            for (index, (concentration, change)) in self
+                .concentration
+                .iter_mut()
+                .zip(self.change.iter())
+                .enumerate()
+            {
+                // Cells that stopped being fluid lose their sediment
+                *concentration = if matches!(fields.cell_type[index], CellType::FluidCell) {
+                    (*concentration + delta_time * change).max(0.0)
+                } else {
+                    0.0
+                };
+            }
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::cell::{BoundaryConditionCell, CellType};
+use crate::run_controller::RunController;
+use crate::simulation::Simulation;
+use crate::solver::FluidSolver;
+
+use rayon::prelude::*;
+
+// Finite difference step relative to the magnitude of a control, at least one
+const DEFAULT_RELATIVE_STEP: f32 = 1e-2;
+
+// Parameters an objective can be differentiated by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    // Component of the velocity of every inflow cell
+    InflowVelocity(usize),
+    // Component of the body force per unit mass
+    Acceleration(usize),
+    Reynolds,
+}
+
+impl Control {
+    pub fn value(&self, simulation: &Simulation) -> f32 {
+        match *self {
+            Control::InflowVelocity(component) => inflow_velocity(simulation)[component],
+            Control::Acceleration(component) => simulation.acceleration()[component],
+            Control::Reynolds => simulation.reynolds(),
+        }
+    }
+
+    pub fn set(&self, simulation: &mut Simulation, value: f32) {
+        match *self {
+            Control::InflowVelocity(component) => {
+                let mut velocity = inflow_velocity(simulation);
+                velocity[component] = value;
+                simulation.set_inflow_velocity(velocity);
+            }
+            Control::Acceleration(component) => {
+                let mut acceleration = simulation.acceleration();
+                acceleration[component] = value;
+                simulation.set_acceleration(acceleration);
+            }
+            Control::Reynolds => simulation.set_reynolds(value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectiveGradient {
+    pub objective: f32,
+    // Derivative by each control, in the order of the controls
+    pub gradient: Vec<f32>,
+}
+
+// Sensitivity of a scalar objective, evaluated after running a copy of a simulation
+// for a time horizon, to the controls applied at the start. The derivatives are
+// central differences of whole runs, so the objective should change smoothly over
+// the horizon; with shedding, average it over periods rather than sampling it.
+pub struct Sensitivity {
+    initial: Simulation,
+    controls: Vec<Control>,
+    horizon: f32, // seconds
+    objective: fn(&Simulation) -> f32,
+    relative_step: f32,
+    is_parallel: bool,
+}
+
+impl Sensitivity {
+    pub fn new(
+        simulation: &Simulation,
+        controls: &[Control],
+        horizon: f32,
+        objective: fn(&Simulation) -> f32,
+    ) -> Self {
+        assert!(!controls.is_empty(), "sensitivity needs controls");
+        assert!(horizon > 0.0, "horizon must be positive");
+        Self {
+            initial: simulation.clone(),
+            controls: controls.to_vec(),
+            horizon,
+            objective,
+            relative_step: DEFAULT_RELATIVE_STEP,
+            is_parallel: false,
+        }
+    }
+
+    pub fn relative_step(mut self, relative_step: f32) -> Self {
+        assert!(relative_step > 0.0, "relative step must be positive");
+        self.relative_step = relative_step;
+        self
+    }
+
+    // Run the perturbed copies on the rayon thread pool
+    pub fn parallel(mut self, is_parallel: bool) -> Self {
+        self.is_parallel = is_parallel;
+        self
+    }
+
+    pub fn controls(&self) -> &[Control] {
+        &self.controls
+    }
+
+    // Values of the controls in the initial simulation
+    pub fn values(&self) -> Vec<f32> {
+        self.controls
+            .iter()
+            .map(|control| control.value(&self.initial))
+            .collect()
+    }
+
+    pub fn evaluate(&self, values: &[f32]) -> f32 {
+        run(self.controlled(values), self.horizon, self.objective)
+    }
+
+    pub fn gradient(&self, values: &[f32]) -> ObjectiveGradient {
+        let steps: Vec<f32> = values
+            .iter()
+            .map(|value| self.relative_step * value.abs().max(1.0))
+            .collect();
+        // The values themselves, then each control stepped down and up
+        let mut copies = vec![self.controlled(values)];
+        for (control, step) in steps.iter().enumerate() {
+            for sign in [-1.0, 1.0] {
+                let mut stepped = values.to_vec();
+                stepped[control] += sign * step;
+                copies.push(self.controlled(&stepped));
+            }
+        }
+
+        // The initial simulation may hold observers that can't be shared by threads
+        let (horizon, objective) = (self.horizon, self.objective);
+        let objectives: Vec<f32> = if self.is_parallel {
+            copies
+                .into_par_iter()
+                .map(|simulation| run(simulation, horizon, objective))
+                .collect()
+        } else {
+            copies
+                .into_iter()
+                .map(|simulation| run(simulation, horizon, objective))
+                .collect()
+        };
+        let gradient = steps
+            .iter()
+            .enumerate()
+            .map(|(control, step)| {
+                (objectives[2 + 2 * control] - objectives[1 + 2 * control]) / (2.0 * step)
+            })
+            .collect();
+        ObjectiveGradient {
+            objective: objectives[0],
+            gradient,
+        }
+    }
+
+    fn controlled(&self, values: &[f32]) -> Simulation {
+        assert_eq!(
+            values.len(),
+            self.controls.len(),
+            "one value per control needed"
+        );
+        let mut simulation = self.initial.clone();
+        for (control, &value) in self.controls.iter().zip(values) {
+            control.set(&mut simulation, value);
+        }
+        simulation
+    }
+}
+
+fn run(mut simulation: Simulation, horizon: f32, objective: fn(&Simulation) -> f32) -> f32 {
+    let end = simulation.time() + horizon;
+    RunController::new().max_time(end).run(&mut simulation);
+    objective(&simulation)
+}
+
+// Drag per unit depth on the obstacles, see Simulation::obstacle_force
+pub fn drag(simulation: &Simulation) -> f32 {
+    simulation.obstacle_force()[0]
+}
+
+// Mean velocity of the inflow cells, zero without any
+fn inflow_velocity(simulation: &Simulation) -> [f32; 2] {
+    let fields = simulation.fields();
+    let mut sum = [0.0; 2];
+    let mut count = 0;
+    for (index, cell_type) in fields.cell_type.iter().enumerate() {
+        if matches!(
+            cell_type,
+            CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell)
+        ) {
+            sum[0] += fields.u[index];
+            sum[1] += fields.v[index];
+            count += 1;
+        }
+    }
+    sum.map(|sum| sum / count.max(1) as f32)
+}
@@ -0,0 +1,184 @@
+use crate::simulation::Simulation;
+use crate::wall_shear::WallFace;
+
+use std::ops::Range;
+
+// Walls to scan for separation
+#[derive(Debug, Clone, PartialEq)]
+pub enum Wall {
+    // Faces of the no-slip cells in a rectangle of cells whose normal, pointing into
+    // the fluid, is normal, ordered along their tangent
+    Faces {
+        x: Range<usize>,
+        y: Range<usize>,
+        normal: [f32; 2],
+    },
+    // The closed profile around a named obstacle, see Simulation::obstacle_wall_shear
+    Obstacle(String),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WallPointKind {
+    // The flow along the wall converges from both sides and leaves the wall
+    #[default]
+    Separation,
+    // The flow along the wall diverges to both sides, like at a stagnation point
+    Reattachment,
+}
+
+// Zero of the wall shear stress between two neighbouring faces, interpolated
+// linearly between them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WallPoint {
+    pub kind: WallPointKind,
+    // Distance along the faces from the first one, meters
+    pub s: f32,
+    pub position: [f32; 2], // meters
+}
+
+impl Wall {
+    // Faces in profile order with whether the profile closes on itself, None for an
+    // unknown obstacle
+    pub fn faces(&self, simulation: &Simulation) -> Option<(Vec<WallFace>, bool)> {
+        match self {
+            Wall::Faces { x, y, normal } => {
+                let mut faces: Vec<WallFace> = simulation
+                    .wall_shear()
+                    .into_iter()
+                    .filter(|face| {
+                        x.contains(&face.wall[0])
+                            && y.contains(&face.wall[1])
+                            && face.normal == *normal
+                    })
+                    .collect();
+                let along = |face: &WallFace| {
+                    let tangent = face.tangent();
+                    face.position[0] * tangent[0] + face.position[1] * tangent[1]
+                };
+                faces.sort_by(|a, b| along(a).total_cmp(&along(b)));
+                Some((faces, false))
+            }
+            Wall::Obstacle(name) => Some((simulation.obstacle_wall_shear(name)?, true)),
+        }
+    }
+}
+
+// Sign changes of the wall shear stress along the faces in their order. Taken along
+// the direction the profile is walked in, shear falling through zero means the wall
+// flow converges, which is separation, and rising through zero reattachment. Closed
+// profiles also check from the last face back to the first.
+pub fn wall_points(faces: &[WallFace], is_closed: bool) -> Vec<WallPoint> {
+    let count = faces.len();
+    if count < 2 {
+        return Vec::new();
+    }
+    let distance =
+        |a: [f32; 2], b: [f32; 2]| ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt();
+    let mut s = vec![0.0; count];
+    for index in 1..count {
+        s[index] = s[index - 1] + distance(faces[index - 1].position, faces[index].position);
+    }
+
+    // Shear along the walking direction, from the neighbours on either side
+    let mut previous_sign = 1.0;
+    let walked: Vec<f32> = (0..count)
+        .map(|index| {
+            let before = if index > 0 {
+                faces[index - 1].position
+            } else if is_closed {
+                faces[count - 1].position
+            } else {
+                faces[index].position
+            };
+            let after = if index + 1 < count {
+                faces[index + 1].position
+            } else if is_closed {
+                faces[0].position
+            } else {
+                faces[index].position
+            };
+            let tangent = faces[index].tangent();
+            let along = (after[0] - before[0]) * tangent[0] + (after[1] - before[1]) * tangent[1];
+            if along != 0.0 {
+                previous_sign = along.signum();
+            }
+            previous_sign * faces[index].shear_stress
+        })
+        .collect();
+
+    let pairs = if is_closed { count } else { count - 1 };
+    let mut points = Vec::new();
+    for index in 0..pairs {
+        let next = (index + 1) % count;
+        let [a, b] = [walked[index], walked[next]];
+        let kind = if a > 0.0 && b <= 0.0 {
+            WallPointKind::Separation
+        } else if a < 0.0 && b >= 0.0 {
+            WallPointKind::Reattachment
+        } else {
+            continue;
+        };
+        let fraction = a / (a - b);
+        let [start, end] = [faces[index].position, faces[next].position];
+        let length = if next > index {
+            s[next] - s[index]
+        } else {
+            distance(start, end)
+        };
+        points.push(WallPoint {
+            kind,
+            s: s[index] + fraction * length,
+            position: [
+                start[0] + fraction * (end[0] - start[0]),
+                start[1] + fraction * (end[1] - start[1]),
+            ],
+        });
+    }
+    points
+}
+
+// Separation and reattachment points of one wall over time, record once per timestep
+// or at any interval
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeparationMonitor {
+    pub wall: Wall,
+    // (time in seconds, points in profile order)
+    pub history: Vec<(f32, Vec<WallPoint>)>,
+}
+
+impl SeparationMonitor {
+    pub fn new(wall: Wall) -> Self {
+        Self {
+            wall,
+            history: Vec::new(),
+        }
+    }
+
+    // Returns the points found now
+    pub fn record(&mut self, simulation: &Simulation) -> &[WallPoint] {
+        let (faces, is_closed) = self
+            .wall
+            .faces(simulation)
+            .expect("no obstacle of that name");
+        let points = wall_points(&faces, is_closed);
+        self.history.push((simulation.time(), points));
+        &self.history.last().unwrap().1
+    }
+
+    pub fn latest(&self) -> Option<&[WallPoint]> {
+        self.history.last().map(|(_, points)| points.as_slice())
+    }
+
+    // (time, s) of every point of a kind, for plotting how it moves
+    pub fn track(&self, kind: WallPointKind) -> Vec<(f32, f32)> {
+        self.history
+            .iter()
+            .flat_map(|(time, points)| {
+                points
+                    .iter()
+                    .filter(move |point| point.kind == kind)
+                    .map(move |point| (*time, point.s))
+            })
+            .collect()
+    }
+}
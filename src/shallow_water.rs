@@ -0,0 +1,598 @@
+use crate::cell::BoundaryConditionCell;
+use crate::cell::Cell;
+use crate::cell::CellType;
+use crate::colormap::RangeMode;
+use crate::events::EventSchedule;
+use crate::memory::{self, AllocationError};
+use crate::presets::{self, SimulationPreset};
+use crate::solver::{Checkpoint, FluidSolver};
+use crate::space_domain::{Fields, SpaceDomain};
+
+pub const GRAVITY: f32 = 9.81; // meters/seconds^2
+
+// Fraction of the largest stable time step taken by each substep
+const COURANT_NUMBER: f32 = 0.45;
+// Cells shallower than this are dry and hold no momentum
+const DRY_DEPTH: f32 = 1e-4; // meters
+
+// Depth, normal and tangential velocity on one side of a face
+type FaceState = [f32; 3];
+
+// Shallow-water equations for the water depth and the depth averaged velocity,
+// solved with first order finite volumes at the cell centers. Fluxes are HLL with
+// the hydrostatic reconstruction of Audusse et al., so water at rest over terrain
+// stays at rest and cells can dry out and flood.
+// Uses the same cell types and presets as the Navier-Stokes solver: walls and
+// obstacles reflect, inflow cells prescribe the velocity and outflow cells let
+// waves leave. The equations are inviscid, the preset's Reynolds number is unused
+// and its acceleration acts along the bed. The viewer sees gravity times the free
+// surface elevation as the pressure, the kinematic pressure driving the flow.
+pub struct ShallowWater {
+    space_domain: SpaceDomain,
+    terrain: Vec<f32>,        // meters, bed elevation
+    depth: Vec<f32>,          // meters
+    discharge: Vec<[f32; 2]>, // meters^2/seconds, depth times velocity
+
+    delta_time: f32,        // seconds, advanced per iterate_one_timestep
+    acceleration: [f32; 2], // meters/seconds^2
+    gravity: f32,           // meters/seconds^2
+    substeps: usize,        // of the last timestep
+    time: f32,              // seconds
+}
+
+impl ShallowWater {
+    // Terrain and initial free surface elevation are functions of the position
+    // (x, y) in meters, sampled at the cell centers, like Heightmap::elevation. The
+    // initial velocity is the preset's, cells where the terrain is above the surface
+    // start dry.
+    pub fn from_preset(
+        preset: SimulationPreset,
+        terrain: impl Fn(f32, f32) -> f32,
+        surface: impl Fn(f32, f32) -> f32,
+    ) -> Self {
+        Self::try_from_preset(preset, terrain, surface).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    // from_preset with the terrain, depth and discharge reserved fallibly
+    pub fn try_from_preset(
+        preset: SimulationPreset,
+        terrain: impl Fn(f32, f32) -> f32,
+        surface: impl Fn(f32, f32) -> f32,
+    ) -> Result<Self, AllocationError> {
+        let space_domain = preset.space_domain;
+        let space_size = space_domain.space_size();
+        let [nx, ny] = space_size;
+        let [dx, dy] = space_domain.delta_space();
+
+        let mut solver = Self {
+            space_domain,
+            terrain: memory::try_filled(nx * ny, 0.0, space_size, "terrain")?,
+            depth: memory::try_filled(nx * ny, 0.0, space_size, "depth")?,
+            discharge: memory::try_filled(nx * ny, [0.0, 0.0], space_size, "discharge")?,
+            delta_time: preset.delta_time,
+            acceleration: preset.acceleration,
+            gravity: GRAVITY,
+            substeps: 0,
+            time: 0.0,
+        };
+        for x in 0..nx {
+            for y in 0..ny {
+                let index = x * ny + y;
+                let center = [(x as f32 + 0.5) * dx, (y as f32 + 0.5) * dy];
+                solver.terrain[index] = terrain(center[0], center[1]);
+                solver.depth[index] =
+                    (surface(center[0], center[1]) - solver.terrain[index]).max(0.0);
+            }
+        }
+        solver.update_discharge_from_cells();
+        solver.update_cells();
+        Ok(solver)
+    }
+
+    pub fn gravity(&self) -> f32 {
+        self.gravity
+    }
+
+    pub fn set_gravity(&mut self, gravity: f32) {
+        assert!(gravity > 0.0, "gravity must be positive");
+        self.gravity = gravity;
+        self.update_cells();
+    }
+
+    // Substeps the last timestep was split into to stay stable
+    pub fn substeps(&self) -> usize {
+        self.substeps
+    }
+
+    pub fn depth(&self, x: usize, y: usize) -> f32 {
+        self.depth[x * self.space_domain.space_size()[1] + y]
+    }
+
+    pub fn terrain(&self, x: usize, y: usize) -> f32 {
+        self.terrain[x * self.space_domain.space_size()[1] + y]
+    }
+
+    pub fn surface_elevation(&self, x: usize, y: usize) -> f32 {
+        self.depth(x, y) + self.terrain(x, y)
+    }
+
+    // Depth averaged velocity at the cell center, zero in dry cells
+    pub fn velocity(&self, x: usize, y: usize) -> [f32; 2] {
+        self.cell_velocity(x * self.space_domain.space_size()[1] + y)
+    }
+
+    // Water in the fluid cells, meters^3
+    pub fn volume(&self) -> f32 {
+        let [dx, dy] = self.space_domain.delta_space();
+        let fields = self.space_domain.fields();
+        (0..fields.len())
+            .filter(|&index| matches!(fields.cell_type[index], CellType::FluidCell))
+            .map(|index| self.depth[index])
+            .sum::<f32>()
+            * dx
+            * dy
+    }
+
+    fn cell_velocity(&self, index: usize) -> [f32; 2] {
+        let depth = self.depth[index];
+        if depth < DRY_DEPTH {
+            [0.0, 0.0]
+        } else {
+            [
+                self.discharge[index][0] / depth,
+                self.discharge[index][1] / depth,
+            ]
+        }
+    }
+
+    // Cell centered velocities of the fluid cells from the staggered ones
+    fn update_discharge_from_cells(&mut self) {
+        let [nx, ny] = self.space_domain.space_size();
+        for x in 0..nx {
+            for y in 0..ny {
+                let index = x * ny + y;
+                self.discharge[index] = match self.space_domain.cell_type(x, y) {
+                    CellType::FluidCell if self.depth[index] >= DRY_DEPTH => {
+                        let velocity = self.space_domain.get_centered_velocity(x, y);
+                        [
+                            self.depth[index] * velocity[0],
+                            self.depth[index] * velocity[1],
+                        ]
+                    }
+                    _ => [0.0, 0.0],
+                };
+            }
+        }
+    }
+
+    // State a boundary cell presents to the fluid cell next to it across a face
+    // along axis, with the terrain of that side
+    fn ghost_state(&self, fluid: usize, boundary: (usize, usize), axis: usize) -> (FaceState, f32) {
+        let tangential = 1 - axis;
+        let velocity = self.cell_velocity(fluid);
+        let depth = self.depth[fluid];
+        match self.space_domain.cell_type(boundary.0, boundary.1) {
+            CellType::BoundaryConditionCell(BoundaryConditionCell::PeriodicCell) => {
+                let (x, y) = self.space_domain.periodic_source(boundary.0, boundary.1);
+                let index = self.space_domain.index(x, y);
+                let velocity = self.cell_velocity(index);
+                (
+                    [self.depth[index], velocity[axis], velocity[tangential]],
+                    self.terrain[index],
+                )
+            }
+            CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell) => {
+                let velocity = self.space_domain.velocity(boundary.0, boundary.1);
+                (
+                    [depth, velocity[axis], velocity[tangential]],
+                    self.terrain[fluid],
+                )
+            }
+            CellType::BoundaryConditionCell(BoundaryConditionCell::OutFlowCell) => (
+                [depth, velocity[axis], velocity[tangential]],
+                self.terrain[fluid],
+            ),
+            // Walls, obstacles and void cells mirror the normal velocity
+            _ => (
+                [depth, -velocity[axis], velocity[tangential]],
+                self.terrain[fluid],
+            ),
+        }
+    }
+
+    // Largest stable substep over the wet fluid cells
+    fn stable_delta_time(&self) -> f32 {
+        let [dx, dy] = self.space_domain.delta_space();
+        let fields = self.space_domain.fields();
+        let rate = (0..fields.len())
+            .filter(|&index| {
+                matches!(fields.cell_type[index], CellType::FluidCell)
+                    && self.depth[index] >= DRY_DEPTH
+            })
+            .map(|index| {
+                let velocity = self.cell_velocity(index);
+                let celerity = (self.gravity * self.depth[index]).sqrt();
+                (velocity[0].abs() + celerity) / dx + (velocity[1].abs() + celerity) / dy
+            })
+            .fold(0.0, f32::max);
+        if rate > 0.0 {
+            COURANT_NUMBER / rate
+        } else {
+            f32::INFINITY
+        }
+    }
+
+    fn substep(&mut self, delta_time: f32) {
+        let [nx, ny] = self.space_domain.space_size();
+        let delta_space = self.space_domain.delta_space();
+        let is_fluid =
+            |x: usize, y: usize| matches!(self.space_domain.cell_type(x, y), CellType::FluidCell);
+
+        // Rate of change of depth and discharge from the fluxes through every face
+        let mut change = vec![[0.0f32; 3]; nx * ny];
+        for x in 0..nx {
+            for y in 0..ny {
+                for (axis, (right_x, right_y)) in [(0, (x + 1, y)), (1, (x, y + 1))] {
+                    if right_x >= nx || right_y >= ny {
+                        continue;
+                    }
+                    let [left_fluid, right_fluid] = [is_fluid(x, y), is_fluid(right_x, right_y)];
+                    if !left_fluid && !right_fluid {
+                        continue;
+                    }
+                    let left = x * ny + y;
+                    let right = right_x * ny + right_y;
+                    let tangential = 1 - axis;
+                    let state = |index: usize| {
+                        let velocity = self.cell_velocity(index);
+                        (
+                            [self.depth[index], velocity[axis], velocity[tangential]],
+                            self.terrain[index],
+                        )
+                    };
+
+                    let left_state = if left_fluid {
+                        state(left)
+                    } else {
+                        self.ghost_state(right, (x, y), axis)
+                    };
+                    let right_state = if right_fluid {
+                        state(right)
+                    } else {
+                        self.ghost_state(left, (right_x, right_y), axis)
+                    };
+
+                    let [left_flux, right_flux] =
+                        face_fluxes(self.gravity, left_state, right_state);
+                    let mut apply = |index: usize, flux: [f32; 3], sign: f32| {
+                        let change: &mut [f32; 3] = &mut change[index];
+                        change[0] += sign * flux[0] / delta_space[axis];
+                        change[1 + axis] += sign * flux[1] / delta_space[axis];
+                        change[1 + tangential] += sign * flux[2] / delta_space[axis];
+                    };
+                    apply(left, left_flux, -1.0);
+                    apply(right, right_flux, 1.0);
+                }
+            }
+        }
+
+        for x in 0..nx {
+            for y in 0..ny {
+                if !is_fluid(x, y) {
+                    continue;
+                }
+                let index = x * ny + y;
+                let depth = self.depth[index];
+                let new_depth = (depth + delta_time * change[index][0]).max(0.0);
+                self.discharge[index] = if new_depth < DRY_DEPTH {
+                    [0.0, 0.0]
+                } else {
+                    [
+                        self.discharge[index][0]
+                            + delta_time * (change[index][1] + depth * self.acceleration[0]),
+                        self.discharge[index][1]
+                            + delta_time * (change[index][2] + depth * self.acceleration[1]),
+                    ]
+                };
+                self.depth[index] = new_depth;
+            }
+        }
+    }
+
+    // Staggered velocities and pressure for the viewer and exporters
+    fn update_cells(&mut self) {
+        let [nx, ny] = self.space_domain.space_size();
+
+        for x in 0..nx {
+            for y in 0..ny {
+                if !matches!(self.space_domain.cell_type(x, y), CellType::FluidCell) {
+                    continue;
+                }
+                let index = x * ny + y;
+                *self.space_domain.pressure_mut(x, y) =
+                    self.gravity * (self.depth[index] + self.terrain[index]);
+            }
+        }
+
+        // Face velocities owned by the cell on the left or bottom of each face
+        for x in 0..nx {
+            for y in 0..ny {
+                let index = x * ny + y;
+                let cell_type = self.space_domain.cell_type(x, y);
+                if let CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell) =
+                    cell_type
+                {
+                    continue;
+                }
+                let mut velocity = self.space_domain.velocity(x, y);
+                for (axis, (other_x, other_y)) in [(0, (x + 1, y)), (1, (x, y + 1))] {
+                    if other_x >= nx || other_y >= ny {
+                        continue;
+                    }
+                    let other = other_x * ny + other_y;
+                    let other_type = self.space_domain.cell_type(other_x, other_y);
+                    let face_velocity = |fluid: usize, boundary: CellType| match boundary {
+                        CellType::FluidCell => {
+                            (self.cell_velocity(index)[axis] + self.cell_velocity(other)[axis])
+                                / 2.0
+                        }
+                        CellType::BoundaryConditionCell(BoundaryConditionCell::OutFlowCell)
+                        | CellType::BoundaryConditionCell(BoundaryConditionCell::PeriodicCell) => {
+                            self.cell_velocity(fluid)[axis]
+                        }
+                        _ => 0.0,
+                    };
+                    velocity[axis] = match (cell_type, other_type) {
+                        (CellType::FluidCell, other_type) => face_velocity(index, other_type),
+                        (_, CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell)) => {
+                            continue
+                        }
+                        (_, CellType::FluidCell) => face_velocity(other, cell_type),
+                        _ => continue,
+                    };
+                }
+                self.space_domain.set_velocity(x, y, velocity);
+            }
+        }
+
+        self.space_domain.update_periodic_cells();
+        self.space_domain.update_psi();
+        self.space_domain.update_pressure_and_speed_range();
+    }
+}
+
+impl FluidSolver for ShallowWater {
+    fn iterate_one_timestep(&mut self) {
+        let mut remaining = self.delta_time;
+        self.substeps = 0;
+        while remaining > 0.0 {
+            let delta_time = self.stable_delta_time().min(remaining);
+            self.substep(delta_time);
+            remaining -= delta_time;
+            self.substeps += 1;
+        }
+        self.update_cells();
+        self.space_domain.advect_dye(self.delta_time);
+        self.time += self.delta_time;
+    }
+
+    fn time(&self) -> f32 {
+        self.time
+    }
+
+    fn delta_space(&self) -> [f32; 2] {
+        self.space_domain.delta_space()
+    }
+
+    fn space_size(&self) -> [usize; 2] {
+        self.space_domain.space_size()
+    }
+
+    fn get_cell(&self, x: usize, y: usize) -> Cell {
+        self.space_domain.get_cell(x, y)
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+        self.space_domain.cells()
+    }
+
+    fn fields(&self) -> &Fields {
+        self.space_domain.fields()
+    }
+
+    fn get_centered_velocity(&self, x: usize, y: usize) -> [f32; 2] {
+        self.space_domain.get_centered_velocity(x, y)
+    }
+
+    fn pressure_range(&self) -> [f32; 2] {
+        self.space_domain.pressure_range()
+    }
+
+    fn speed_range(&self) -> [f32; 2] {
+        self.space_domain.speed_range()
+    }
+
+    fn set_range_modes(&mut self, pressure: RangeMode, speed: RangeMode) {
+        self.space_domain.set_range_modes(pressure, speed);
+    }
+
+    fn terrain_elevations(&self) -> Option<&[f32]> {
+        Some(&self.terrain)
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            time: self.time,
+            space_size: self.space_domain.space_size(),
+            cells: self.space_domain.cells(),
+            metadata: None,
+        }
+    }
+
+    // Depth is recovered from the stored pressure over the solver's terrain, and
+    // the cell centered velocities from the staggered ones
+    fn restore(&mut self, checkpoint: &Checkpoint) {
+        assert_eq!(
+            checkpoint.space_size,
+            self.space_domain.space_size(),
+            "checkpoint size mismatch"
+        );
+        self.space_domain.set_cells(&checkpoint.cells);
+        self.time = checkpoint.time;
+
+        let fields = self.space_domain.fields();
+        for index in 0..fields.len() {
+            if matches!(fields.cell_type[index], CellType::FluidCell) {
+                self.depth[index] =
+                    (fields.pressure[index] / self.gravity - self.terrain[index]).max(0.0);
+            }
+        }
+        self.update_discharge_from_cells();
+        self.update_cells();
+    }
+}
+
+// Fluxes of depth, normal and tangential discharge leaving the left cell and
+// entering the right one. The hydrostatic reconstruction lowers both depths to the
+// higher bed at the face and corrects each side's pressure term for the step.
+fn face_fluxes(gravity: f32, left: (FaceState, f32), right: (FaceState, f32)) -> [[f32; 3]; 2] {
+    let ([left_depth, left_normal, left_tangential], left_terrain) = left;
+    let ([right_depth, right_normal, right_tangential], right_terrain) = right;
+    let face_terrain = left_terrain.max(right_terrain);
+    let left_face_depth = (left_depth + left_terrain - face_terrain).max(0.0);
+    let right_face_depth = (right_depth + right_terrain - face_terrain).max(0.0);
+
+    let flux = hll_flux(
+        gravity,
+        [left_face_depth, left_normal, left_tangential],
+        [right_face_depth, right_normal, right_tangential],
+    );
+    let pressure_step =
+        |depth: f32, face_depth: f32| gravity / 2.0 * (depth.powi(2) - face_depth.powi(2));
+    [
+        [
+            flux[0],
+            flux[1] + pressure_step(left_depth, left_face_depth),
+            flux[2],
+        ],
+        [
+            flux[0],
+            flux[1] + pressure_step(right_depth, right_face_depth),
+            flux[2],
+        ],
+    ]
+}
+
+// HLL approximate Riemann solver with the wave speeds of Toro for dry states
+fn hll_flux(gravity: f32, left: FaceState, right: FaceState) -> [f32; 3] {
+    let left_dry = left[0] < DRY_DEPTH;
+    let right_dry = right[0] < DRY_DEPTH;
+    if left_dry && right_dry {
+        return [0.0; 3];
+    }
+    let physical_flux = |[depth, normal, tangential]: FaceState| {
+        [
+            depth * normal,
+            depth * normal.powi(2) + gravity / 2.0 * depth.powi(2),
+            depth * normal * tangential,
+        ]
+    };
+    let conserved =
+        |[depth, normal, tangential]: FaceState| [depth, depth * normal, depth * tangential];
+    let [left_celerity, right_celerity] = [left, right].map(|state| (gravity * state[0]).sqrt());
+
+    let left_speed = if left_dry {
+        right[1] - 2.0 * right_celerity
+    } else if right_dry {
+        left[1] - left_celerity
+    } else {
+        (left[1] - left_celerity).min(right[1] - right_celerity)
+    };
+    let right_speed = if right_dry {
+        left[1] + 2.0 * left_celerity
+    } else if left_dry {
+        right[1] + right_celerity
+    } else {
+        (left[1] + left_celerity).max(right[1] + right_celerity)
+    };
+
+    // Dry sides carry no velocity
+    let left = if left_dry { [0.0; 3] } else { left };
+    let right = if right_dry { [0.0; 3] } else { right };
+    if left_speed >= 0.0 {
+        physical_flux(left)
+    } else if right_speed <= 0.0 {
+        physical_flux(right)
+    } else {
+        let [left_flux, right_flux] = [physical_flux(left), physical_flux(right)];
+        let [left_conserved, right_conserved] = [conserved(left), conserved(right)];
+        [0, 1, 2].map(|i| {
+            (right_speed * left_flux[i] - left_speed * right_flux[i]
+                + left_speed * right_speed * (right_conserved[i] - left_conserved[i]))
+                / (right_speed - left_speed)
+        })
+    }
+}
+
+pub fn dam_break() -> ShallowWater {
+    dam_break_sized([128, 64])
+}
+
+// 4 x 2 meter basin with free slip walls. A meter of water behind a dam at x = 1.5
+// meters floods a shallow pool around a mound that rises above it.
+pub fn dam_break_sized(space_size: [usize; 2]) -> ShallowWater {
+    let x_length = 4.0;
+    let y_length = 2.0;
+    let [x, y] = space_size;
+    assert!(x >= 3 && y >= 3, "the basin needs at least 3 x 3 cells");
+
+    let wall = Cell {
+        cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::FreeSlipCell),
+        ..Default::default()
+    };
+    let cells = (0..x)
+        .map(|i| {
+            (0..y)
+                .map(|j| {
+                    if i == 0 || j == 0 || i == x - 1 || j == y - 1 {
+                        wall.clone()
+                    } else {
+                        Cell::default()
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let preset = SimulationPreset {
+        space_domain: SpaceDomain::new(cells, [x_length / x as f32, y_length / y as f32], 0.9),
+        delta_time: 0.01,
+        acceleration: [0.0, 0.0],
+        reynolds: f32::INFINITY,
+        events: EventSchedule::new(),
+        obstacles: Vec::new(),
+        dye_buoyancy: None,
+    };
+
+    let mound = |x: f32, y: f32| {
+        let squared_distance = (x - 2.8).powi(2) + (y - 1.0).powi(2);
+        0.4 * (-squared_distance / 0.1).exp()
+    };
+    let mut solver =
+        ShallowWater::from_preset(preset, mound, |x, _| if x < 1.5 { 1.0 } else { 0.2 });
+    // Dye marks the water released by the dam
+    let dx = solver.delta_space()[0];
+    for i in 1..x - 1 {
+        for j in 1..y - 1 {
+            if (i as f32 + 0.5) * dx < 1.5 {
+                *solver.space_domain.dye_mut(i, j) = 1.0;
+            }
+        }
+    }
+    solver
+}
+
+// Flow past the cylinder of presets::cylinder_cross_flow over a flat bed a meter
+// deep, the water piles up in front of the cylinder
+pub fn cylinder_in_a_channel() -> ShallowWater {
+    ShallowWater::from_preset(presets::cylinder_cross_flow(), |_, _| 0.0, |_, _| 1.0)
+}
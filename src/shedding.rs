@@ -0,0 +1,145 @@
+use crate::presets;
+use crate::simulation::Simulation;
+
+// Fewer full lift periods than this are not reported as shedding
+const MIN_PERIODS: usize = 2;
+// Periods used for the frequency and the limit cycle check, the latest ones
+const ANALYZED_PERIODS: usize = 5;
+// Fraction of the largest lift excursion the signal has to fall below the mean between
+// two upward crossings, so noise around the mean is not counted as periods
+const HYSTERESIS: f32 = 0.1;
+// Largest relative spread of the analyzed periods and amplitudes of a limit cycle
+const PERIOD_TOLERANCE: f32 = 0.02;
+const AMPLITUDE_TOLERANCE: f32 = 0.05;
+// Smallest lift amplitude of cylinder_shedding relative to the drag
+const MIN_LIFT_TO_DRAG: f32 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SheddingAnalysis {
+    pub frequency: f32, // hertz
+    // frequency * diameter / velocity
+    pub strouhal: f32,
+    // Half the peak to peak lift of the latest period
+    pub amplitude: f32,
+    // Over the analyzed periods
+    pub mean_lift: f32,
+    // Consecutive full periods at the end of the series
+    pub periods: usize,
+    // The latest five periods and amplitudes agree within the tolerances
+    pub is_limit_cycle: bool,
+}
+
+// Finds periodic shedding in a (time in seconds, lift) series from the upward
+// crossings of the mean lift of its second half, the transient start settles there.
+// None until at least two full periods are found, however small the lift.
+pub fn analyze_lift(
+    samples: &[(f32, f32)],
+    diameter: f32,
+    velocity: f32,
+) -> Option<SheddingAnalysis> {
+    assert!(
+        diameter > 0.0 && velocity > 0.0,
+        "diameter and velocity must be positive"
+    );
+    let settled = &samples[samples.len() / 2..];
+    if settled.is_empty() {
+        return None;
+    }
+    let mean_lift = settled.iter().map(|&(_, lift)| lift).sum::<f32>() / settled.len() as f32;
+    let threshold = HYSTERESIS
+        * settled
+            .iter()
+            .fold(0.0f32, |max, &(_, lift)| max.max((lift - mean_lift).abs()));
+
+    // Upward crossings as (interpolated time, index of the sample after it)
+    let mut crossings = Vec::new();
+    let mut is_below = false;
+    for (index, window) in samples.windows(2).enumerate() {
+        let [(time_a, lift_a), (time_b, lift_b)] = [window[0], window[1]];
+        let [a, b] = [lift_a - mean_lift, lift_b - mean_lift];
+        if a < -threshold {
+            is_below = true;
+        }
+        if is_below && a < 0.0 && b >= 0.0 {
+            crossings.push((time_a + (time_b - time_a) * -a / (b - a), index + 1));
+            is_below = false;
+        }
+    }
+    let periods: Vec<f32> = crossings
+        .windows(2)
+        .map(|pair| pair[1].0 - pair[0].0)
+        .collect();
+    // While the oscillation grows, a cycle that misses the hysteresis merges with the
+    // next one. Only the trailing run of periods like the latest one counts.
+    let &latest = periods.last()?;
+    let consistent = periods
+        .iter()
+        .rev()
+        .take_while(|&&period| (period - latest).abs() < latest / 2.0)
+        .count();
+    if consistent < MIN_PERIODS {
+        return None;
+    }
+
+    let analyzed = consistent.min(ANALYZED_PERIODS);
+    let crossings = &crossings[crossings.len() - analyzed - 1..];
+    let periods = &periods[periods.len() - analyzed..];
+    let amplitudes: Vec<f32> = crossings
+        .windows(2)
+        .map(|pair| {
+            let (min, max) = samples[pair[0].1..pair[1].1].iter().fold(
+                (f32::INFINITY, f32::NEG_INFINITY),
+                |(min, max), &(_, lift)| (min.min(lift), max.max(lift)),
+            );
+            (max - min) / 2.0
+        })
+        .collect();
+
+    let mean = |values: &[f32]| values.iter().sum::<f32>() / values.len() as f32;
+    let spread = |values: &[f32]| {
+        let mean = mean(values);
+        values
+            .iter()
+            .fold(0.0f32, |spread, value| spread.max((value - mean).abs()))
+            / mean
+    };
+    let frequency = 1.0 / mean(periods);
+    // Over whole periods, the crossing level is biased by the partial one at the end
+    let cycles = &samples[crossings[0].1..crossings[analyzed].1];
+    let cycle_mean_lift = cycles.iter().map(|&(_, lift)| lift).sum::<f32>() / cycles.len() as f32;
+
+    Some(SheddingAnalysis {
+        frequency,
+        strouhal: frequency * diameter / velocity,
+        amplitude: *amplitudes.last().unwrap(),
+        mean_lift: cycle_mean_lift,
+        periods: consistent,
+        is_limit_cycle: analyzed == ANALYZED_PERIODS
+            && spread(periods) < PERIOD_TOLERANCE
+            && spread(&amplitudes) < AMPLITUDE_TOLERANCE,
+    })
+}
+
+// Shedding behind the cylinder of presets::cylinder_cross_flow, from the force samples
+// of its "cylinder" obstacle. Force monitoring is off in the preset, switch it on with
+// Simulation::obstacle_mut before stepping. Lift oscillations below a hundredth of the
+// mean drag are taken for noise.
+pub fn cylinder_shedding(simulation: &Simulation) -> Option<SheddingAnalysis> {
+    let cylinder = simulation
+        .obstacle("cylinder")
+        .expect("not a cylinder cross flow simulation");
+    let samples = &cylinder.force_samples;
+    let lift: Vec<(f32, f32)> = samples
+        .iter()
+        .map(|&(time, [_, lift])| (time, lift))
+        .collect();
+    let analysis = analyze_lift(
+        &lift,
+        presets::CYLINDER_DIAMETER,
+        presets::CYLINDER_INFLOW_VELOCITY,
+    )?;
+
+    let settled = &samples[samples.len() / 2..];
+    let mean_drag = settled.iter().map(|&(_, [drag, _])| drag).sum::<f32>() / settled.len() as f32;
+    (analysis.amplitude >= MIN_LIFT_TO_DRAG * mean_drag.abs()).then_some(analysis)
+}
@@ -1,12 +1,225 @@
+use crate::boundary_segments::{BoundarySegment, SegmentStatistics};
+use crate::cell::BoundaryConditionCell;
 use crate::cell::Cell;
+use crate::cell::CellFlags;
 use crate::cell::CellType;
-use crate::space_domain::SpaceDomain;
+use crate::colormap::RangeMode;
+use crate::control::{Actuator, RegionForce};
+use crate::dirty_regions::{DirtyRegion, DirtyTracker};
+use crate::events::{Event, EventSchedule};
+use crate::field::{DownsampledField, Field, FieldView};
+use crate::fluid_zones::FluidZones;
+use crate::flux_monitor::FluxMonitors;
+use crate::forces;
+use crate::forcing::Oscillation;
+use crate::inflow_turbulence::{InflowTurbulence, TurbulentInflow};
+use crate::lic;
+use crate::linear_solver::{self, LinearOperator, PoissonOperator, PoissonStencil};
+use crate::membrane::Membrane;
+use crate::memory::MemoryFootprint;
+use crate::metadata::SimulationMetadata;
+use crate::observer::{StepEvent, StepObserver};
+use crate::obstacles::Obstacle;
+use crate::parameters::{Parameters, SharedParameters};
+use crate::particles::Particles;
+use crate::solver::{Checkpoint, FluidSolver};
+use crate::space_domain::{Fields, SpaceDomain, UpdateMode, FACE_NEIGHBORS};
+use crate::stability::StabilityField;
+use crate::validation::DomainIssue;
+use crate::wall_shear::{self, WallFace};
 
 use crate::presets;
+use crate::projection_error::ProjectionError;
+use crate::reduction::{self, Reduction};
+use crate::resample;
+use crate::sediment::{Sediment, SedimentSettings};
 
-const OMEGA: f32 = 1.7; // 0 <= OMEGA <= 2
-const ITR_MAX: usize = 100;
-const POISSON_EPSILON: f32 = 0.001;
+use rayon::prelude::*;
+
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+// Poisson iteration defaults, shared with the stream function solver
+pub(crate) const OMEGA: f32 = 1.7; // 0 <= OMEGA <= 2
+pub(crate) const ITR_MAX: usize = 100;
+pub(crate) const POISSON_EPSILON: f32 = 0.001;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AdvectionScheme {
+    // Donor-cell / central differencing blend controlled by gamma
+    #[default]
+    DonorCell,
+    // Backtrace + bilinear interpolation, unconditionally stable
+    SemiLagrangian,
+}
+
+// How the pressure level is fixed. Every boundary condition on pressure is Neumann, so
+// the solve determines it only up to a constant that drifts from step to step. The
+// velocities do not depend on the choice. Disconnected fluid zones each get their
+// own level, see FluidZones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PressureGauge {
+    // Whatever level the solver converges to
+    #[default]
+    Floating,
+    // Shift so the pressure of the fluid cell (x, y) is zero, other zones than its own
+    // to a zero mean
+    ReferenceCell(usize, usize),
+    // Shift so the mean pressure of the fluid cells of every zone is zero
+    ZeroMean,
+}
+
+// Order in which an SOR sweep visits the fluid cells. Both converge to the same
+// pressure, the ordering changes the path there.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SorOrdering {
+    // Column by column, every cell sees the values already updated in this sweep
+    #[default]
+    Lexicographic,
+    // Checkerboard, all cells with even x + y first and then the odd ones, each
+    // half sweep in parallel
+    RedBlack,
+}
+
+// Method of the pressure Poisson solve. The Krylov solvers work on a single domain,
+// decomposed runs always use SOR.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PressureSolver {
+    // Successive over-relaxation with omega and the SOR ordering
+    #[default]
+    Sor,
+    ConjugateGradient,
+    // Twice the operator applications of conjugate gradients per iteration
+    BiCgStab,
+}
+
+// Effort of each pressure solve, its target residual and iteration cap
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ToleranceSchedule {
+    // The same every step
+    #[default]
+    Fixed,
+    Adaptive(AdaptiveTolerance),
+}
+
+// Loose solves while the flow changes quickly and tight ones near a steady state.
+// Each solve aims at reduction times the residual it starts from, which is large in
+// transients and small once the right hand side hardly changes between steps. An SOR
+// solve also stops once its residual stalls, when the last stall_window sweeps
+// lowered it by less than the factor stall_reduction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveTolerance {
+    pub reduction: f32,
+    // Bounds of the target residual, scaled by the initial pressure norm above 1 like
+    // the fixed tolerance
+    pub tight: f32,
+    pub loose: f32,
+    pub stall_window: usize,
+    pub stall_reduction: f32,
+    pub max_iterations: usize,
+}
+
+impl Default for AdaptiveTolerance {
+    fn default() -> Self {
+        Self {
+            reduction: 0.3,
+            tight: POISSON_EPSILON / 10.0,
+            loose: POISSON_EPSILON * 10.0,
+            stall_window: 10,
+            stall_reduction: 0.99,
+            max_iterations: 2 * ITR_MAX,
+        }
+    }
+}
+
+// Reference frame rotating counterclockwise, adding the Coriolis force -2 rate x u
+// and optionally the centrifugal force to the body force. Both are explicit like the
+// rest of F and G, 2 rate delta_time should stay well below 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RotatingFrame {
+    pub rate: f32, // radians/seconds
+    // Axis of the centrifugal force rate^2 r, None leaves it out like an f-plane
+    // model does. It is a pressure gradient that only shapes the pressure of enclosed
+    // flows and has no periodic potential on periodic domains.
+    pub centrifugal_center: Option<[f32; 2]>, // meters
+}
+
+// Air to water density ratio 1.2e-3 times a drag coefficient of 1.3e-3
+pub const AIR_WATER_DRAG: f32 = 1.5e-6;
+
+// Wind blowing over the free slip cells on top of the fluid, pulling the surface
+// along with the stress drag speed^2. The fluid balances it by the gradient of u
+// below the surface times the viscosity 1 / Re.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceWind {
+    pub speed: f32, // meters/seconds
+    // Radians counterclockwise from x, only the part along x drives the flow
+    pub direction: f32,
+    // Kinematic stress per squared wind speed, AIR_WATER_DRAG for wind over water
+    pub drag: f32,
+}
+
+impl SurfaceWind {
+    // Kinematic stress along x, meters^2/seconds^2
+    pub fn stress(&self) -> f32 {
+        self.drag * self.speed.powi(2) * self.direction.cos()
+    }
+}
+
+// Pressure of the previous step for warm starting the pressure solve
+#[derive(Clone, Default)]
+struct PressureHistory {
+    previous: Vec<f32>,
+    // The solve that started from previous converged without extrapolation
+    is_extrapolable: bool,
+}
+
+// Lets a tile of a decomposed domain take part in a timestep, see distributed.rs
+pub(crate) trait Halo {
+    // Local columns whose values this tile is responsible for
+    fn owned_columns(&self, space_size: [usize; 2]) -> Range<usize>;
+    // Refresh ghost columns from the neighbouring tiles
+    fn exchange(&mut self, space_domain: &mut SpaceDomain);
+    // Sum a value over all tiles
+    fn sum(&mut self, value: f32) -> f32;
+}
+
+// A single tile covering the whole domain
+struct NoHalo;
+
+impl Halo for NoHalo {
+    fn owned_columns(&self, space_size: [usize; 2]) -> Range<usize> {
+        0..space_size[0]
+    }
+
+    fn exchange(&mut self, _space_domain: &mut SpaceDomain) {}
+
+    fn sum(&mut self, value: f32) -> f32 {
+        value
+    }
+}
+
+// Fields interpolated at a point between the grid samples
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointSample {
+    pub velocity: [f32; 2], // meters/seconds
+    pub pressure: f32,
+    pub vorticity: f32, // 1/seconds
+    pub psi: f32,
+}
+
+// Totals over the timesteps of one step_n or step_for call
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StepStats {
+    pub steps: usize,
+    pub simulated_time: f32, // seconds
+    // Summed over the steps
+    pub pressure_iterations: usize,
+    pub max_pressure_iterations: usize,
+    // Largest RMS residual any pressure solve ended with
+    pub max_pressure_residual: f32,
+    pub wall_time: Duration,
+}
 
 pub struct Simulation {
     space_domain: SpaceDomain,
@@ -14,9 +227,89 @@ pub struct Simulation {
     delta_time: f32,        // seconds,
     acceleration: [f32; 2], // meters/seconds^2
     reynolds: f32,
-    time: f32, // seconds
+    omega: f32, // 0 < omega < 2
+    time: f32,  // seconds
     initial_pressure_norm: Option<f32>,
     fluid_cell_count: Option<u32>,
+    fluid_zones: Option<FluidZones>,
+    advection_scheme: AdvectionScheme,
+    pressure_gauge: PressureGauge,
+    sor_ordering: SorOrdering,
+    pressure_solver: PressureSolver,
+    tolerance_schedule: ToleranceSchedule,
+    // Some while warm starting
+    pressure_history: Option<PressureHistory>,
+    // Psi and the color ranges are brought up to date on demand rather than every step
+    lazy_analytics: bool,
+    // Lazy analytics skipped since the last update
+    is_analytics_stale: bool,
+    vorticity_confinement: Option<f32>, // epsilon
+    dye_buoyancy: Option<[f32; 2]>,     // meters/seconds^2
+    rotating_frame: Option<RotatingFrame>,
+    surface_wind: Option<SurfaceWind>,
+    sediment: Option<Sediment>,
+    particles: Option<Particles>,
+    membranes: Vec<Membrane>,
+    region_forces: Vec<RegionForce>,
+    oscillations: Vec<Oscillation>,
+    turbulent_inflow: Option<TurbulentInflow>,
+    events: EventSchedule,
+    reduction: Reduction,
+    dirty_tracker: Option<DirtyTracker>,
+    flux_monitors: FluxMonitors,
+    obstacles: Vec<Obstacle>,
+    step: usize,
+    observers: Vec<Box<dyn StepObserver + Send>>,
+    // With the value last applied
+    shared_parameters: Option<(SharedParameters, Parameters)>,
+    metadata: Option<SimulationMetadata>,
+}
+
+// A deep copy that continues on its own. Observers stay with the original and the
+// copy no longer follows shared parameters, so the two never act on each other.
+// Everything else, caches and random generators included, is copied, so the copy
+// steps exactly like the original would.
+impl Clone for Simulation {
+    fn clone(&self) -> Self {
+        Self {
+            space_domain: self.space_domain.clone(),
+            delta_time: self.delta_time,
+            acceleration: self.acceleration,
+            reynolds: self.reynolds,
+            omega: self.omega,
+            time: self.time,
+            initial_pressure_norm: self.initial_pressure_norm,
+            fluid_cell_count: self.fluid_cell_count,
+            fluid_zones: self.fluid_zones.clone(),
+            advection_scheme: self.advection_scheme,
+            pressure_gauge: self.pressure_gauge,
+            sor_ordering: self.sor_ordering,
+            pressure_solver: self.pressure_solver,
+            tolerance_schedule: self.tolerance_schedule,
+            pressure_history: self.pressure_history.clone(),
+            lazy_analytics: self.lazy_analytics,
+            is_analytics_stale: self.is_analytics_stale,
+            vorticity_confinement: self.vorticity_confinement,
+            dye_buoyancy: self.dye_buoyancy,
+            rotating_frame: self.rotating_frame,
+            surface_wind: self.surface_wind,
+            sediment: self.sediment.clone(),
+            particles: self.particles.clone(),
+            membranes: self.membranes.clone(),
+            region_forces: self.region_forces.clone(),
+            oscillations: self.oscillations.clone(),
+            turbulent_inflow: self.turbulent_inflow.clone(),
+            events: self.events.clone(),
+            reduction: self.reduction,
+            dirty_tracker: self.dirty_tracker.clone(),
+            flux_monitors: self.flux_monitors.clone(),
+            obstacles: self.obstacles.clone(),
+            step: self.step,
+            observers: Vec::new(),
+            shared_parameters: None,
+            metadata: self.metadata.clone(),
+        }
+    }
 }
 
 impl Default for Simulation {
@@ -37,10 +330,49 @@ impl Simulation {
             space_domain: preset.space_domain,
             delta_time: preset.delta_time,
             reynolds: preset.reynolds,
+            omega: OMEGA,
             acceleration: preset.acceleration,
             time: 0.0,
             initial_pressure_norm: None,
             fluid_cell_count: None,
+            fluid_zones: None,
+            advection_scheme: AdvectionScheme::default(),
+            pressure_gauge: PressureGauge::default(),
+            sor_ordering: SorOrdering::default(),
+            pressure_solver: PressureSolver::default(),
+            tolerance_schedule: ToleranceSchedule::default(),
+            pressure_history: None,
+            lazy_analytics: false,
+            is_analytics_stale: false,
+            vorticity_confinement: None,
+            dye_buoyancy: preset.dye_buoyancy,
+            rotating_frame: None,
+            surface_wind: None,
+            sediment: None,
+            particles: None,
+            membranes: Vec::new(),
+            region_forces: Vec::new(),
+            oscillations: Vec::new(),
+            turbulent_inflow: None,
+            events: preset.events,
+            reduction: Reduction::default(),
+            dirty_tracker: None,
+            flux_monitors: FluxMonitors::new(),
+            obstacles: preset.obstacles,
+            step: 0,
+            observers: Vec::new(),
+            shared_parameters: None,
+            metadata: None,
+        }
+    }
+
+    // from_preset after checking the geometry, see SimulationPreset::repaired
+    pub fn try_from_preset(preset: presets::SimulationPreset) -> Result<Self, Vec<DomainIssue>> {
+        let issues = preset.validate();
+        if issues.is_empty() {
+            Ok(Self::from_preset(preset))
+        } else {
+            Err(issues)
         }
     }
 
@@ -56,19 +388,492 @@ impl Simulation {
         self.time
     }
 
+    pub fn acceleration(&self) -> [f32; 2] {
+        self.acceleration
+    }
+
+    pub fn set_acceleration(&mut self, acceleration: [f32; 2]) {
+        self.acceleration = acceleration;
+    }
+
+    pub fn set_inflow_velocity(&mut self, velocity: [f32; 2]) {
+        self.space_domain.set_inflow_velocity(velocity);
+        if let Some(turbulent_inflow) = self.turbulent_inflow.as_mut() {
+            turbulent_inflow.set_mean_velocity(velocity);
+        }
+    }
+
+    pub fn reynolds(&self) -> f32 {
+        self.reynolds
+    }
+
+    pub fn set_reynolds(&mut self, reynolds: f32) {
+        assert!(reynolds > 0.0, "reynolds number must be positive");
+        self.reynolds = reynolds;
+    }
+
+    pub fn delta_time(&self) -> f32 {
+        self.delta_time
+    }
+
+    pub fn set_delta_time(&mut self, delta_time: f32) {
+        assert!(delta_time > 0.0, "delta time must be positive");
+        self.delta_time = delta_time;
+    }
+
+    pub fn omega(&self) -> f32 {
+        self.omega
+    }
+
+    pub fn set_omega(&mut self, omega: f32) {
+        assert!(omega > 0.0 && omega < 2.0, "omega must be in (0, 2)");
+        self.omega = omega;
+    }
+
+    // Set omega to optimal_omega for this grid, returns the value
+    pub fn set_optimal_omega(&mut self) -> f32 {
+        self.omega = optimal_omega(self.space_size(), self.delta_space());
+        self.omega
+    }
+
+    // The inflow velocity is not tracked and comes back as None
+    pub fn parameters(&self) -> Parameters {
+        Parameters {
+            reynolds: self.reynolds,
+            acceleration: self.acceleration,
+            inflow_velocity: None,
+            omega: self.omega,
+            delta_time: self.delta_time,
+        }
+    }
+
+    pub fn set_parameters(&mut self, parameters: Parameters) {
+        parameters.validate();
+        self.reynolds = parameters.reynolds;
+        self.acceleration = parameters.acceleration;
+        if let Some(velocity) = parameters.inflow_velocity {
+            self.set_inflow_velocity(velocity);
+        }
+        self.omega = parameters.omega;
+        self.delta_time = parameters.delta_time;
+    }
+
+    // Handle for changing parameters from another thread while running, applied at the
+    // start of the next timestep. Setters and events still work in between changes.
+    pub fn share_parameters(&mut self) -> SharedParameters {
+        let parameters = self.parameters();
+        let shared = SharedParameters::new(parameters);
+        self.shared_parameters = Some((shared.clone(), parameters));
+        shared
+    }
+
+    pub fn events(&self) -> &EventSchedule {
+        &self.events
+    }
+
+    pub fn schedule_event(&mut self, time: f32, event: Event) {
+        self.events.add(time, event);
+    }
+
+    // Computed from the current state when lazy analytics left it stale, without
+    // advancing running or smoothed ranges, see refresh_analytics
     pub fn pressure_range(&self) -> [f32; 2] {
+        if self.is_analytics_stale {
+            return self.space_domain.pending_pressure_and_speed_range()[0];
+        }
         self.space_domain.pressure_range()
     }
 
     pub fn speed_range(&self) -> [f32; 2] {
+        if self.is_analytics_stale {
+            return self.space_domain.pending_pressure_and_speed_range()[1];
+        }
         self.space_domain.speed_range()
     }
 
-    pub fn psi_range(&self) -> [f32; 2] {
-        self.space_domain.psi_range()
+    pub fn field(&self, field: Field) -> FieldView<'_> {
+        self.space_domain.field(field)
+    }
+
+    // Field averaged over blocks of factor x factor cells, see FieldView::downsample
+    pub fn downsample_field(&self, field: Field, factor: usize) -> DownsampledField {
+        self.field(field).downsample(factor)
+    }
+
+    // Divergence and pressure residual left by the latest timestep
+    pub fn projection_error(&self) -> ProjectionError {
+        ProjectionError::new(self)
+    }
+
+    // Courant and diffusion numbers of the current flow at the current timestep
+    pub fn stability(&self) -> StabilityField {
+        StabilityField::new(self, self.reynolds, self.delta_time)
+    }
+
+    // Bytes held by the per cell buffers of the domain and the solver
+    pub fn memory_footprint(&self) -> MemoryFootprint {
+        let mut footprint = MemoryFootprint::new();
+        self.space_domain.memory_footprint(&mut footprint);
+        if let Some(history) = &self.pressure_history {
+            footprint.add("previous pressure", &history.previous);
+        }
+        footprint
+    }
+
+    pub fn range_modes(&self) -> [RangeMode; 2] {
+        self.space_domain.range_modes()
+    }
+
+    pub fn set_range_modes(&mut self, pressure: RangeMode, speed: RangeMode) {
+        self.space_domain.set_range_modes(pressure, speed);
+    }
+
+    pub fn advection_scheme(&self) -> AdvectionScheme {
+        self.advection_scheme
+    }
+
+    pub fn set_advection_scheme(&mut self, advection_scheme: AdvectionScheme) {
+        self.advection_scheme = advection_scheme;
+    }
+
+    pub fn sor_ordering(&self) -> SorOrdering {
+        self.sor_ordering
+    }
+
+    pub fn set_sor_ordering(&mut self, sor_ordering: SorOrdering) {
+        self.sor_ordering = sor_ordering;
+    }
+
+    pub fn pressure_solver(&self) -> PressureSolver {
+        self.pressure_solver
+    }
+
+    pub fn set_pressure_solver(&mut self, pressure_solver: PressureSolver) {
+        self.pressure_solver = pressure_solver;
+    }
+
+    pub fn tolerance_schedule(&self) -> ToleranceSchedule {
+        self.tolerance_schedule
+    }
+
+    pub fn set_tolerance_schedule(&mut self, tolerance_schedule: ToleranceSchedule) {
+        if let ToleranceSchedule::Adaptive(adaptive) = tolerance_schedule {
+            assert!(
+                0.0 < adaptive.reduction && adaptive.reduction < 1.0,
+                "reduction must be in (0, 1)"
+            );
+            assert!(
+                0.0 < adaptive.tight && adaptive.tight <= adaptive.loose,
+                "tolerance bounds must be positive and ordered"
+            );
+            assert!(
+                0.0 < adaptive.stall_reduction && adaptive.stall_reduction <= 1.0,
+                "stall reduction must be in (0, 1]"
+            );
+        }
+        self.tolerance_schedule = tolerance_schedule;
+    }
+
+    pub fn warm_start(&self) -> bool {
+        self.pressure_history.is_some()
+    }
+
+    // Start pressure solves from p_n + (p_n - p_n-1), the pressure extrapolated from
+    // the last two solutions, instead of p_n, see extrapolate_pressure
+    pub fn set_warm_start(&mut self, warm_start: bool) {
+        if warm_start != self.warm_start() {
+            self.pressure_history = warm_start.then(PressureHistory::default);
+        }
+    }
+
+    pub fn lazy_analytics(&self) -> bool {
+        self.lazy_analytics
+    }
+
+    // Skip the stream function and the color ranges in timesteps, which nothing in
+    // the step reads, for headless runs. Observers and dirty tracking still get them
+    // every step. psi brings the stream function up to date when read, the range
+    // getters compute the current ranges. Checkpoints carry psi as last updated.
+    pub fn set_lazy_analytics(&mut self, lazy_analytics: bool) {
+        self.lazy_analytics = lazy_analytics;
+        if !lazy_analytics {
+            self.refresh_analytics();
+        }
+    }
+
+    // Updates psi and the color ranges if timesteps skipped them
+    pub fn refresh_analytics(&mut self) {
+        if self.is_analytics_stale {
+            self.space_domain.update_psi();
+            self.space_domain.update_pressure_and_speed_range();
+            self.is_analytics_stale = false;
+        }
+    }
+
+    // The stream function, up to date even with lazy analytics
+    pub fn psi(&mut self) -> FieldView<'_> {
+        self.refresh_analytics();
+        self.space_domain.field(Field::Psi)
+    }
+
+    // Fluid zones of the current geometry, see FluidZones
+    pub fn fluid_zones(&self) -> FluidZones {
+        FluidZones::label(&self.space_domain)
+    }
+
+    pub fn pressure_gauge(&self) -> PressureGauge {
+        self.pressure_gauge
+    }
+
+    pub fn set_pressure_gauge(&mut self, pressure_gauge: PressureGauge) {
+        if let PressureGauge::ReferenceCell(x, y) = pressure_gauge {
+            let [nx, ny] = self.space_domain.space_size();
+            assert!(
+                x < nx
+                    && y < ny
+                    && matches!(self.space_domain.cell_type(x, y), CellType::FluidCell),
+                "pressure reference ({x}, {y}) is not a fluid cell"
+            );
+        }
+        self.pressure_gauge = pressure_gauge;
+    }
+
+    pub fn update_mode(&self) -> UpdateMode {
+        self.space_domain.update_mode()
+    }
+
+    pub fn set_update_mode(&mut self, update_mode: UpdateMode) {
+        self.space_domain.set_update_mode(update_mode);
+    }
+
+    pub fn reduction(&self) -> Reduction {
+        self.reduction
+    }
+
+    // Reduction::Deterministic gives bit-identical runs regardless of the thread count
+    pub fn set_reduction(&mut self, reduction: Reduction) {
+        self.reduction = reduction;
+        self.space_domain.set_reduction(reduction);
+    }
+
+    // Report tiles of tile_size cells where a value moved more than threshold since
+    // the tile was last reported
+    pub fn enable_dirty_tracking(&mut self, tile_size: usize, threshold: f32) {
+        let mut dirty_tracker = DirtyTracker::new(tile_size, threshold);
+        dirty_tracker.update(self.space_domain.fields(), self.space_domain.space_size());
+        self.dirty_tracker = Some(dirty_tracker);
+    }
+
+    pub fn disable_dirty_tracking(&mut self) {
+        self.dirty_tracker = None;
+    }
+
+    // Regions changed by the last timestep, empty when tracking is disabled
+    pub fn dirty_regions(&self) -> &[DirtyRegion] {
+        self.dirty_tracker
+            .as_ref()
+            .map_or(&[], |dirty_tracker| dirty_tracker.dirty_regions())
+    }
+
+    // Control surfaces whose flux is recorded after every timestep
+    pub fn flux_monitors(&self) -> &FluxMonitors {
+        &self.flux_monitors
+    }
+
+    pub fn flux_monitors_mut(&mut self) -> &mut FluxMonitors {
+        &mut self.flux_monitors
+    }
+
+    // Named obstacles of the preset, cells edited later are not tracked
+    pub fn obstacles(&self) -> &[Obstacle] {
+        &self.obstacles
+    }
+
+    pub fn obstacle(&self, name: &str) -> Option<&Obstacle> {
+        self.obstacles.iter().find(|obstacle| obstacle.name == name)
+    }
+
+    pub fn obstacle_mut(&mut self, name: &str) -> Option<&mut Obstacle> {
+        self.obstacles
+            .iter_mut()
+            .find(|obstacle| obstacle.name == name)
+    }
+
+    // Called with a StepEvent at the end of every timestep
+    pub fn add_observer(&mut self, observer: impl StepObserver + Send + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+
+    pub fn clear_observers(&mut self) {
+        self.observers.clear();
+    }
+
+    // Branch of the run from now on, see Clone. Its metadata, if any, starts over as a
+    // run of its own whose scene names the original and the time of the fork.
+    pub fn fork(&self) -> Self {
+        let mut fork = self.clone();
+        if let Some(metadata) = self.metadata.as_ref() {
+            let scene = format!("{} fork at {}", metadata.scene, self.time);
+            fork.metadata = Some(SimulationMetadata::new(scene, &fork));
+        }
+        fork
+    }
+
+    pub fn metadata(&self) -> Option<&SimulationMetadata> {
+        self.metadata.as_ref()
+    }
+
+    // Provenance carried by the checkpoints and exports of the run, see
+    // SimulationMetadata::new
+    pub fn set_metadata(&mut self, metadata: Option<SimulationMetadata>) {
+        self.metadata = metadata;
+    }
+
+    pub fn inflow_turbulence(&self) -> Option<InflowTurbulence> {
+        self.turbulent_inflow
+            .as_ref()
+            .map(TurbulentInflow::turbulence)
+    }
+
+    // Fluctuations around the current inflow velocities, which become their means.
+    // None sets the inflow cells back to their means.
+    pub fn set_inflow_turbulence(&mut self, turbulence: Option<InflowTurbulence>) {
+        if let Some(turbulent_inflow) = self.turbulent_inflow.take() {
+            turbulent_inflow.reset(&mut self.space_domain);
+        }
+        self.turbulent_inflow =
+            turbulence.map(|turbulence| TurbulentInflow::new(turbulence, &self.space_domain));
+    }
+
+    pub fn vorticity_confinement(&self) -> Option<f32> {
+        self.vorticity_confinement
+    }
+
+    // None disables the confinement force
+    pub fn set_vorticity_confinement(&mut self, epsilon: Option<f32>) {
+        self.vorticity_confinement = epsilon;
+    }
+
+    pub fn dye_buoyancy(&self) -> Option<[f32; 2]> {
+        self.dye_buoyancy
+    }
+
+    // Boussinesq buoyancy of the dye, the acceleration of undiluted dye relative to
+    // the fluid around it. Dye lighter than the fluid by the fraction d under gravity
+    // g rises with -d g, diluted dye with its share. Gravity itself stays out of the
+    // acceleration, on the fluid at rest it is balanced by a hydrostatic pressure that
+    // changes nothing but would swamp the pressure solve. None keeps the dye passive.
+    pub fn set_dye_buoyancy(&mut self, dye_buoyancy: Option<[f32; 2]>) {
+        self.dye_buoyancy = dye_buoyancy;
     }
 
-    pub fn get_cell(&self, x: usize, y: usize) -> &Cell {
+    pub fn rotating_frame(&self) -> Option<RotatingFrame> {
+        self.rotating_frame
+    }
+
+    // None simulates in an inertial frame
+    pub fn set_rotating_frame(&mut self, rotating_frame: Option<RotatingFrame>) {
+        self.rotating_frame = rotating_frame;
+    }
+
+    pub fn surface_wind(&self) -> Option<SurfaceWind> {
+        self.surface_wind
+    }
+
+    // None leaves the free slip cells without stress
+    pub fn set_surface_wind(&mut self, surface_wind: Option<SurfaceWind>) {
+        self.surface_wind = surface_wind;
+    }
+
+    pub fn sediment(&self) -> Option<&Sediment> {
+        self.sediment.as_ref()
+    }
+
+    // Starts without suspended or deposited sediment, see set_sediment_concentration.
+    // The sediment is carried by the flow without acting on it and is not part of
+    // checkpoints. None removes it.
+    pub fn set_sediment(&mut self, settings: Option<SedimentSettings>) {
+        self.sediment = settings.map(|settings| Sediment::new(settings, &self.space_domain));
+    }
+
+    // Sets the concentration of the fluid cells from a function of the position (x, y)
+    // in meters
+    pub fn set_sediment_concentration(&mut self, concentration: impl Fn(f32, f32) -> f32) {
+        self.sediment
+            .as_mut()
+            .expect("no sediment, see set_sediment")
+            .set_concentration(&self.space_domain, concentration);
+    }
+
+    pub fn particles(&self) -> Option<&Particles> {
+        self.particles.as_ref()
+    }
+
+    pub fn particles_mut(&mut self) -> Option<&mut Particles> {
+        self.particles.as_mut()
+    }
+
+    // Inertial particles coupled both ways with the flow, not part of checkpoints.
+    // None removes them and their drag on the fluid.
+    pub fn set_particles(&mut self, particles: Option<Particles>) {
+        self.particles = particles;
+    }
+
+    pub fn membranes(&self) -> &[Membrane] {
+        &self.membranes
+    }
+
+    // Immersed in the fluid from the next timestep, not part of checkpoints
+    pub fn add_membrane(&mut self, membrane: Membrane) {
+        self.membranes.push(membrane);
+    }
+
+    pub fn clear_membranes(&mut self) {
+        self.membranes.clear();
+    }
+
+    // Body forces on rectangles of cells, see control::Actuator::BodyForce
+    pub fn region_forces(&self) -> &[RegionForce] {
+        &self.region_forces
+    }
+
+    pub fn region_force_mut(&mut self, name: &str) -> Option<&mut RegionForce> {
+        self.region_forces
+            .iter_mut()
+            .find(|region_force| region_force.name == name)
+    }
+
+    pub fn add_region_force(&mut self, region_force: RegionForce) {
+        let [nx, ny] = self.space_domain.space_size();
+        assert!(
+            region_force.x.end <= nx && region_force.y.end <= ny,
+            "region force outside the domain"
+        );
+        self.region_forces.push(region_force);
+    }
+
+    pub fn clear_region_forces(&mut self) {
+        self.region_forces.clear();
+    }
+
+    // Sinusoidal forcing applied before every timestep, in the order added
+    pub fn oscillations(&self) -> &[Oscillation] {
+        &self.oscillations
+    }
+
+    pub fn add_oscillation(&mut self, oscillation: Oscillation) {
+        self.oscillations.push(oscillation);
+    }
+
+    pub fn clear_oscillations(&mut self) {
+        self.oscillations.clear();
+    }
+
+    pub(crate) fn space_domain(&self) -> &SpaceDomain {
+        &self.space_domain
+    }
+
+    pub fn get_cell(&self, x: usize, y: usize) -> Cell {
         self.space_domain.get_cell(x, y)
     }
 
@@ -77,262 +882,1191 @@ impl Simulation {
     }
 
     pub fn iterate_one_timestep(&mut self) {
+        self.step(&mut NoHalo);
+    }
+
+    // Steps timesteps in one call. No step reads psi or the color ranges, so unless
+    // observers or a dirty tracker look at every step they are only brought up to
+    // date after the last one, and smoothed color ranges see one sample per call.
+    // With lazy analytics they are left stale.
+    pub fn step_n(&mut self, steps: usize) -> StepStats {
+        self.step_while(|stats| stats.steps < steps)
+    }
+
+    // Timesteps until the next one would likely run past budget, at least one, as
+    // for step_n
+    pub fn step_for(&mut self, budget: Duration) -> StepStats {
+        self.step_while(|stats| {
+            stats.steps == 0 || stats.wall_time + stats.wall_time / stats.steps as u32 <= budget
+        })
+    }
+
+    fn step_while(&mut self, mut proceed: impl FnMut(&StepStats) -> bool) -> StepStats {
+        let start = Instant::now();
+        let start_time = self.time;
+        let is_deferring = self.observers.is_empty() && self.dirty_tracker.is_none();
+        let mut stats = StepStats::default();
+        while proceed(&stats) {
+            let (pressure_iterations, pressure_residual) = self.advance(&mut NoHalo, !is_deferring);
+            stats.steps += 1;
+            stats.pressure_iterations += pressure_iterations;
+            stats.max_pressure_iterations = stats.max_pressure_iterations.max(pressure_iterations);
+            stats.max_pressure_residual = stats.max_pressure_residual.max(pressure_residual);
+            stats.wall_time = start.elapsed();
+        }
+        if !self.lazy_analytics {
+            self.refresh_analytics();
+        }
+        stats.simulated_time = self.time - start_time;
+        stats.wall_time = start.elapsed();
+        stats
+    }
+
+    // One timestep, exchanging halos wherever neighbouring values must be up to date
+    pub(crate) fn step(&mut self, halo: &mut impl Halo) {
+        let is_updating_analytics = !self.lazy_analytics || self.needs_analytics_every_step();
+        self.advance(halo, is_updating_analytics);
+    }
+
+    fn needs_analytics_every_step(&self) -> bool {
+        !self.observers.is_empty() || self.dirty_tracker.is_some()
+    }
+
+    // Returns the iterations and residual of the pressure solve. Without
+    // is_updating_analytics psi and the color ranges are left stale.
+    fn advance(&mut self, halo: &mut impl Halo, is_updating_analytics: bool) -> (usize, f32) {
+        if let Some((shared, applied)) = self.shared_parameters.as_mut() {
+            let parameters = shared.get();
+            if parameters != *applied {
+                *applied = parameters;
+                self.set_parameters(parameters);
+            }
+        }
+
+        // Fire scheduled events whose time is closest to the start of this timestep
+        for event in self.events.take_due(self.time + self.delta_time / 2.0) {
+            self.apply_event(event);
+        }
+        if !self.oscillations.is_empty() {
+            let oscillations = std::mem::take(&mut self.oscillations);
+            for oscillation in &oscillations {
+                let value = oscillation.value(self.time + self.delta_time / 2.0);
+                oscillation.actuator.apply(self, value);
+            }
+            self.oscillations = oscillations;
+        }
+        if let Some(turbulent_inflow) = self.turbulent_inflow.as_mut() {
+            turbulent_inflow.advance(self.delta_time, &mut self.space_domain);
+        }
+
         // Change boundary cells and fluid cells next to boundary cells
         // velocity, pressure, f, g
         self.space_domain.update_boundary_velocities(); // O(n^2)
         self.space_domain.update_boundary_pressures_and_fg();
+        halo.exchange(&mut self.space_domain);
 
         // Change fluid cells f, g
         self.update_fg(); // O(n^2)
+        self.space_domain.update_periodic_cells();
+        halo.exchange(&mut self.space_domain);
 
         // Change fluid cells rhs
         self.update_rhs(); // O(n^2)
 
         // Change fluid and boundary cells pressure
-        self.solve_poisson_pressure_equation(); // O(m*n^2)
+        let warm_started = self.extrapolate_pressure(halo); // O(n^2)
+        let (tolerance, max_iterations) = self.pressure_solve_targets(halo); // O(n^2)
+        let (pressure_iterations, pressure_residual) =
+            self.solve_poisson_pressure_equation(halo, tolerance, max_iterations); // O(m*n^2)
+        if let Some(history) = self.pressure_history.as_mut() {
+            history.is_extrapolable = !warm_started && pressure_iterations < max_iterations;
+        }
+        self.apply_pressure_gauge(halo); // O(n^2)
+        halo.exchange(&mut self.space_domain);
 
         // Change fluid cells velocity
         self.update_velocity(); // O(n^2)
+        self.space_domain.update_periodic_cells();
+        halo.exchange(&mut self.space_domain);
+
+        // Move dye with the updated velocity field
+        self.space_domain.advect_dye(self.delta_time); // O(n^2)
+        halo.exchange(&mut self.space_domain);
+        if let Some(sediment) = self.sediment.as_mut() {
+            sediment.advance(self.delta_time, &self.space_domain); // O(n^2)
+        }
+        if let Some(particles) = self.particles.as_mut() {
+            particles.advance(self.delta_time, &self.space_domain);
+        }
+        for membrane in self.membranes.iter_mut() {
+            membrane.advance(self.delta_time, &self.space_domain);
+        }
+
+        if is_updating_analytics {
+            // Change psi of fluid cells and boundary cell on the left and bottom
+            self.space_domain.update_psi(); // O(n^2)
+
+            // For coloring
+            self.space_domain.update_pressure_and_speed_range(); // O(n^2)
+            self.is_analytics_stale = false;
+        } else {
+            self.is_analytics_stale = true;
+        }
+
+        self.time += self.delta_time;
+
+        let space_size = self.space_domain.space_size();
+        let delta_space = self.space_domain.delta_space();
+        let fields = self.space_domain.fields();
+        if let Some(dirty_tracker) = self.dirty_tracker.as_mut() {
+            dirty_tracker.update(fields, space_size);
+        }
+        self.flux_monitors
+            .record_cells(self.time, fields, space_size, delta_space);
+        for obstacle in self.obstacles.iter_mut() {
+            obstacle.record_force(self.time, fields, space_size, delta_space, self.reynolds);
+        }
+
+        // Rigid bodies move with the force at the end of the timestep
+        if self
+            .obstacles
+            .iter()
+            .any(|obstacle| obstacle.rigid_body.is_some())
+        {
+            let mut changed = false;
+            for obstacle in self.obstacles.iter_mut() {
+                if obstacle.rigid_body.is_some() {
+                    let force = obstacle.force(
+                        self.space_domain.fields(),
+                        space_size,
+                        delta_space,
+                        self.reynolds,
+                    );
+                    changed |=
+                        obstacle.move_rigid_body(force, self.delta_time, &mut self.space_domain);
+                }
+            }
+            if changed {
+                self.reset_geometry_caches();
+            }
+        }
+
+        if !self.observers.is_empty() {
+            let event = StepEvent {
+                step: self.step,
+                time: self.time,
+                delta_time: self.delta_time,
+                pressure_iterations,
+                pressure_residual,
+                pressure_range: self.space_domain.pressure_range(),
+                speed_range: self.space_domain.speed_range(),
+                psi_range: self
+                    .space_domain
+                    .field(Field::Psi)
+                    .range()
+                    .unwrap_or([0.0, 0.0]),
+            };
+            for observer in self.observers.iter_mut() {
+                observer.on_step(&event);
+            }
+        }
+        self.step += 1;
+        (pressure_iterations, pressure_residual)
+    }
+}
+
+impl FluidSolver for Simulation {
+    fn iterate_one_timestep(&mut self) {
+        Simulation::iterate_one_timestep(self)
+    }
+
+    fn time(&self) -> f32 {
+        self.time
+    }
 
-        // Change psi of fluid cells and boundary cell on the left and bottom
-        self.space_domain.update_psi(); // O(n^2)
+    fn delta_space(&self) -> [f32; 2] {
+        self.space_domain.delta_space()
+    }
+
+    fn space_size(&self) -> [usize; 2] {
+        self.space_domain.space_size()
+    }
+
+    fn get_cell(&self, x: usize, y: usize) -> Cell {
+        self.space_domain.get_cell(x, y)
+    }
 
-        // For coloring
-        self.space_domain.update_pressure_and_speed_range(); // O(n^2)
+    fn cells(&self) -> Vec<Cell> {
+        self.space_domain.cells()
+    }
+
+    fn fields(&self) -> &Fields {
+        self.space_domain.fields()
+    }
+
+    fn get_centered_velocity(&self, x: usize, y: usize) -> [f32; 2] {
+        self.space_domain.get_centered_velocity(x, y)
+    }
+
+    fn pressure_range(&self) -> [f32; 2] {
+        Simulation::pressure_range(self)
+    }
 
-        self.time += self.delta_time
+    fn speed_range(&self) -> [f32; 2] {
+        Simulation::speed_range(self)
+    }
+
+    fn set_range_modes(&mut self, pressure: RangeMode, speed: RangeMode) {
+        Simulation::set_range_modes(self, pressure, speed)
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            time: self.time,
+            space_size: self.space_domain.space_size(),
+            cells: self.space_domain.cells(),
+            metadata: self.metadata.clone(),
+        }
+    }
+
+    fn metadata(&self) -> Option<&SimulationMetadata> {
+        Simulation::metadata(self)
+    }
+
+    fn restore(&mut self, checkpoint: &Checkpoint) {
+        assert_eq!(
+            checkpoint.space_size,
+            self.space_domain.space_size(),
+            "checkpoint size mismatch"
+        );
+        self.space_domain.set_cells(&checkpoint.cells);
+        self.time = checkpoint.time;
+        // A restarted run continues the one that saved the checkpoint
+        if checkpoint.metadata.is_some() {
+            self.metadata = checkpoint.metadata.clone();
+        }
+
+        // The geometry may differ from the current one
+        self.reset_geometry_caches();
+
+        self.space_domain.update_psi();
+        self.space_domain.update_pressure_and_speed_range();
+
+        if let Some(dirty_tracker) = self.dirty_tracker.as_mut() {
+            dirty_tracker.mark_all_dirty();
+            dirty_tracker.update(self.space_domain.fields(), self.space_domain.space_size());
+        }
     }
 }
 
+// Interactive forcing, safe to call between timesteps
+// position and radius are in meters
 impl Simulation {
-    fn update_velocity(&mut self) {
+    // Add a velocity impulse with a gaussian falloff around position
+    pub fn apply_impulse(&mut self, position: [f32; 2], radius: f32, impulse: [f32; 2]) {
+        self.space_domain.add_velocity(position, radius, impulse);
+    }
+
+    // Drag the velocity towards the brush velocity, optionally depositing dye
+    pub fn apply_velocity_brush(
+        &mut self,
+        position: [f32; 2],
+        radius: f32,
+        velocity: [f32; 2],
+        dye: Option<f32>,
+    ) {
+        self.space_domain.blend_velocity(position, radius, velocity);
+        if let Some(dye) = dye {
+            self.space_domain.add_dye(position, radius, dye);
+        }
+    }
+}
+
+impl Simulation {
+    pub fn apply_event(&mut self, event: Event) {
+        match event {
+            Event::SetInflowVelocity(velocity) => self.set_inflow_velocity(velocity),
+            Event::SetObstacle { x, y, is_obstacle } => {
+                self.set_obstacle(x, y, is_obstacle);
+            }
+            Event::SetAcceleration(acceleration) => self.set_acceleration(acceleration),
+            Event::SetSurfaceWind(surface_wind) => self.set_surface_wind(surface_wind),
+            Event::InjectDye {
+                position,
+                radius,
+                amount,
+            } => self.space_domain.add_dye(position, radius, amount),
+        }
+    }
+}
+
+impl Simulation {
+    // Grayscale flow texture, width * height values with rows top to bottom
+    pub fn render_lic(&self, width: usize, height: usize) -> Vec<u8> {
         let space_size = self.space_domain.space_size();
         let delta_space = self.space_domain.delta_space();
+        let domain_size = [
+            space_size[0] as f32 * delta_space[0],
+            space_size[1] as f32 * delta_space[1],
+        ];
 
-        for x in 0..space_size[0] {
-            for y in 0..space_size[1] {
-                if let CellType::FluidCell = self.space_domain.get_cell(x, y).cell_type {
-                    let right_cell_type: Option<CellType> = (x + 1 < space_size[0])
-                        .then(|| self.space_domain.get_cell(x + 1, y).cell_type);
-                    let top_cell_type: Option<CellType> = (y + 1 < space_size[1])
-                        .then(|| self.space_domain.get_cell(x, y + 1).cell_type);
-
-                    if let Some(right_cell_type) = right_cell_type {
-                        if let CellType::BoundaryConditionCell(_) = right_cell_type {
-                        } else {
-                            self.space_domain.get_cell_mut(x, y).velocity[0] =
-                                self.space_domain.get_cell(x, y).f
-                                    - self.delta_time
-                                        * (self.space_domain.get_cell(x + 1, y).pressure
-                                            - self.space_domain.get_cell(x, y).pressure)
-                                        / delta_space[0]
-                        }
-                    }
+        let space_domain = &self.space_domain;
+        lic::line_integral_convolution(domain_size, width, height, |position| {
+            space_domain.velocity_at(position).ok()
+        })
+    }
+}
 
-                    if let Some(top_cell_type) = top_cell_type {
-                        if let CellType::BoundaryConditionCell(_) = top_cell_type {
-                        } else {
-                            self.space_domain.get_cell_mut(x, y).velocity[1] =
-                                self.space_domain.get_cell(x, y).g
-                                    - self.delta_time
-                                        * (self.space_domain.get_cell(x, y + 1).pressure
-                                            - self.space_domain.get_cell(x, y).pressure)
-                                        / delta_space[1]
-                        }
-                    }
-                }
+impl Simulation {
+    // Bilinear interpolation of each field from its own staggered location, px and py
+    // in meters from the bottom left corner. None outside the domain and inside cells
+    // that aren't fluid, whose values are ghosts of the boundary conditions.
+    pub fn sample_at_point(&self, px: f32, py: f32) -> Option<PointSample> {
+        let position = [px, py];
+        let velocity = self.space_domain.velocity_at(position).ok()?;
+        Some(PointSample {
+            velocity,
+            pressure: self.space_domain.interpolate_pressure(position),
+            vorticity: self.space_domain.interpolate_vorticity(position),
+            psi: self.space_domain.interpolate_psi(position),
+        })
+    }
+}
+
+impl Simulation {
+    // [drag, lift] per unit depth on the obstacles inside the domain
+    pub fn obstacle_force(&self) -> [f32; 2] {
+        forces::obstacle_force(
+            self.space_domain.fields(),
+            self.space_domain.space_size(),
+            self.space_domain.delta_space(),
+            self.reynolds,
+        )
+    }
+
+    // [drag, lift] per unit depth on one named obstacle
+    pub fn named_obstacle_force(&self, name: &str) -> Option<[f32; 2]> {
+        self.obstacle(name).map(|obstacle| {
+            obstacle.force(
+                self.space_domain.fields(),
+                self.space_domain.space_size(),
+                self.space_domain.delta_space(),
+                self.reynolds,
+            )
+        })
+    }
+
+    // Shear stress and y+ on every face of a no-slip cell next to fluid, domain walls
+    // included, see wall_shear::wall_faces
+    pub fn wall_shear(&self) -> Vec<WallFace> {
+        let space_domain = &self.space_domain;
+        let [nx, ny] = space_domain.space_size();
+        wall_shear::wall_faces(
+            space_domain.fields(),
+            [nx, ny],
+            space_domain.delta_space(),
+            self.reynolds,
+            (0..nx).flat_map(|x| (0..ny).map(move |y| (x, y))),
+        )
+    }
+
+    // Flow rate, pressure and force on one named boundary segment
+    pub fn segment_statistics(&self, name: &str) -> Option<SegmentStatistics> {
+        self.space_domain
+            .segment(name)
+            .map(|segment| segment.statistics(&self.space_domain, self.reynolds))
+    }
+
+    // Faces of one named obstacle as a profile around the center of its cells
+    pub fn obstacle_wall_shear(&self, name: &str) -> Option<Vec<WallFace>> {
+        let obstacle = self.obstacle(name)?;
+        let space_domain = &self.space_domain;
+        let mut faces = wall_shear::wall_faces(
+            space_domain.fields(),
+            space_domain.space_size(),
+            space_domain.delta_space(),
+            self.reynolds,
+            obstacle.cells().iter().copied(),
+        );
+        let [dx, dy] = space_domain.delta_space();
+        let count = obstacle.cells().len().max(1) as f32;
+        let center = obstacle.cells().iter().fold([0.0, 0.0], |sum, &(x, y)| {
+            [
+                sum[0] + (x as f32 + 0.5) * dx / count,
+                sum[1] + (y as f32 + 0.5) * dy / count,
+            ]
+        });
+        wall_shear::sort_around(&mut faces, center);
+        Some(faces)
+    }
+}
+
+// Geometry editing, safe to call between timesteps
+impl Simulation {
+    // Velocity of the no-slip wall cell (x, y), returns whether it is one
+    pub fn set_wall_velocity(&mut self, x: usize, y: usize, velocity: [f32; 2]) -> bool {
+        let is_wall = matches!(
+            self.space_domain.cell_type(x, y),
+            CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell { .. })
+        );
+        if is_wall {
+            self.space_domain.set_cell_type(
+                x,
+                y,
+                CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                    boundary_condition_velocity: velocity,
+                }),
+            );
+        }
+        is_wall
+    }
+
+    // Named boundary segments of the preset or added later, see BoundarySegment
+    pub fn segments(&self) -> &[BoundarySegment] {
+        self.space_domain.segments()
+    }
+
+    pub fn segment(&self, name: &str) -> Option<&BoundarySegment> {
+        self.space_domain.segment(name)
+    }
+
+    // Replaces a segment of the same name
+    pub fn add_segment(&mut self, segment: BoundarySegment) {
+        self.space_domain.add_segment(segment);
+    }
+
+    pub fn remove_segment(&mut self, name: &str) -> bool {
+        self.space_domain.remove_segment(name)
+    }
+
+    // Boundary condition of every cell of the named segment, returns whether there is
+    // such a segment
+    pub fn set_segment_condition(&mut self, name: &str, condition: BoundaryConditionCell) -> bool {
+        let found = self.space_domain.set_segment_condition(name, condition);
+        if found {
+            self.reset_geometry_caches();
+        }
+        found
+    }
+
+    // Returns whether the cell changed
+    pub fn set_obstacle(&mut self, x: usize, y: usize, is_obstacle: bool) -> bool {
+        let changed = self.space_domain.set_obstacle(x, y, is_obstacle);
+        if changed {
+            self.reset_geometry_caches();
+        }
+        changed
+    }
+
+    // Continue on a grid of new_size cells covering the same extent, see
+    // resample_cells. Flux monitors and boundary segments are given in cells and are
+    // removed, named obstacles are rasterized again. Sediment starts over on the new grid, particles and
+    // membranes keep their positions.
+    pub fn resample(&mut self, new_size: [usize; 2]) {
+        // Rebuilt for the new inflow cells around the resampled means
+        let inflow_turbulence = self.inflow_turbulence();
+        self.set_inflow_turbulence(None);
+        let space_size = self.space_domain.space_size();
+        let delta_space = self.space_domain.delta_space();
+        let cells = resample::resample_cells(
+            &self.space_domain.cells(),
+            space_size,
+            delta_space,
+            new_size,
+        );
+        let new_delta_space = [
+            delta_space[0] * space_size[0] as f32 / new_size[0] as f32,
+            delta_space[1] * space_size[1] as f32 / new_size[1] as f32,
+        ];
+
+        let [pressure_mode, speed_mode] = self.space_domain.range_modes();
+        let mut space_domain = SpaceDomain::new(
+            cells.chunks(new_size[1]).map(<[Cell]>::to_vec).collect(),
+            new_delta_space,
+            self.space_domain.gamma(),
+        );
+        space_domain.set_update_mode(self.space_domain.update_mode());
+        space_domain.set_reduction(self.reduction);
+        space_domain.set_range_modes(pressure_mode, speed_mode);
+        for i in 0..self.obstacles.len() {
+            let (earlier, later) = self.obstacles.split_at_mut(i);
+            later[0].rasterize(&mut space_domain);
+            for other in earlier {
+                other.release_cells(later[0].cells());
             }
         }
+        space_domain.update_psi();
+        space_domain.update_pressure_and_speed_range();
+        self.space_domain = space_domain;
+
+        self.reset_geometry_caches();
+        self.flux_monitors.clear();
+        self.region_forces.clear();
+        // Only the inflow doesn't refer to cells or region forces
+        self.oscillations
+            .retain(|oscillation| matches!(oscillation.actuator, Actuator::Inflow { .. }));
+        if let Some(dirty_tracker) = self.dirty_tracker.as_ref() {
+            self.enable_dirty_tracking(dirty_tracker.tile_size(), dirty_tracker.threshold());
+        }
+        self.set_inflow_turbulence(inflow_turbulence);
+        let sediment = self.sediment.as_ref().map(Sediment::settings);
+        self.set_sediment(sediment);
+    }
+}
+
+impl Simulation {
+    fn update_velocity(&mut self) {
+        let delta_time = self.delta_time;
+        self.sweep_fluid_cells(
+            |space_domain, x, y| Self::new_velocity(space_domain, delta_time, x, y),
+            |fields, index, [u, v]| {
+                if let Some(u) = u {
+                    fields.u[index] = u;
+                }
+                if let Some(v) = v {
+                    fields.v[index] = v;
+                }
+            },
+        );
+    }
+
+    fn new_velocity(
+        space_domain: &SpaceDomain,
+        delta_time: f32,
+        x: usize,
+        y: usize,
+    ) -> [Option<f32>; 2] {
+        let delta_space = space_domain.delta_space();
+
+        let is_open = |flags: CellFlags| flags.intersects(CellFlags::OPEN);
+
+        let u = is_open(space_domain.neighbor_flags(x, y, [1, 0])).then(|| {
+            space_domain.f(x, y)
+                - delta_time * (space_domain.pressure(x + 1, y) - space_domain.pressure(x, y))
+                    / delta_space[0]
+        });
+
+        let v = is_open(space_domain.neighbor_flags(x, y, [0, 1])).then(|| {
+            space_domain.g(x, y)
+                - delta_time * (space_domain.pressure(x, y + 1) - space_domain.pressure(x, y))
+                    / delta_space[1]
+        });
+
+        [u, v]
+    }
+
+    // Compute a new value pair for every fluid cell in parallel, then commit them. The
+    // computations only read values the sweep doesn't write, so no buffering is needed.
+    fn sweep_fluid_cells<T: Send>(
+        &mut self,
+        compute: impl Fn(&SpaceDomain, usize, usize) -> T + Sync,
+        commit: impl Fn(&mut Fields, usize, T),
+    ) {
+        let space_domain = &self.space_domain;
+        let values: Vec<(usize, T)> = space_domain
+            .fluid_cells()
+            .par_iter()
+            .map(|&index| {
+                let (x, y) = space_domain.position(index);
+                (index, compute(space_domain, x, y))
+            })
+            .collect();
+        let fields = self.space_domain.fields_mut();
+        for (index, value) in values {
+            commit(fields, index, value);
+        }
     }
 
-    fn get_initial_pressure_norm(&mut self) -> (f32, u32) {
+    fn get_initial_pressure_norm(&mut self, halo: &mut impl Halo) -> (f32, u32) {
         if let Some(x) = self.initial_pressure_norm {
             return (x, self.fluid_cell_count.unwrap());
         }
-        let mut fluid_cell_count = 0;
         let space_size = self.space_domain.space_size();
-        let mut initial_pressure_norm: f32 = (0..space_size[0])
-            .map(|x| -> f32 {
-                (0..space_size[1])
-                    .map(|y| -> f32 {
-                        match self.space_domain.get_cell(x, y).cell_type {
-                            CellType::FluidCell => {
-                                fluid_cell_count += 1;
-                                (self.space_domain.get_cell(x, y)).pressure.powi(2)
-                            }
-                            _ => 0.0,
-                        }
-                    })
-                    .sum()
-            })
-            .sum::<f32>()
-            / (fluid_cell_count as f32);
+        let pressure = &self.space_domain.fields().pressure;
+        let squared_pressures: Vec<f32> = self.space_domain.fluid_cells()[self
+            .space_domain
+            .fluid_cell_range(halo.owned_columns(space_size))]
+        .iter()
+        .map(|&index| pressure[index].powi(2))
+        .collect();
+        let fluid_cell_count = halo.sum(squared_pressures.len() as f32) as u32;
 
-        initial_pressure_norm = initial_pressure_norm.sqrt();
+        let initial_pressure_norm = (halo.sum(reduction::sum(&squared_pressures, self.reduction))
+            / (fluid_cell_count as f32))
+            .sqrt();
         self.initial_pressure_norm = Some(initial_pressure_norm);
         self.fluid_cell_count = Some(fluid_cell_count);
         (initial_pressure_norm, fluid_cell_count)
     }
 
-    fn solve_poisson_pressure_equation(&mut self) {
+    fn squared_pressure_residuals(&self, columns: Range<usize>) -> Vec<f32> {
+        let stencil = PoissonStencil::new(self.space_domain.delta_space());
+        let space_domain = &self.space_domain;
+
+        space_domain.fluid_cells()[space_domain.fluid_cell_range(columns)]
+            .par_iter()
+            .map(|&index| {
+                let (x, y) = space_domain.position(index);
+                (stencil.laplacian(space_domain, x, y) - space_domain.rhs(x, y)).powi(2)
+            })
+            .collect()
+    }
+
+    // Starts the solve from p_n + (p_n - p_n-1) when warm starting and remembers p_n
+    // for the next step, returns whether it did. The solve stops at a residual that
+    // hardly sees smooth errors, and extrapolating from a warm started or unconverged
+    // solve carries its error on to the next step, where it adds up over the steps. So
+    // only every other step is warm started at most, and only when the extrapolation
+    // lowers the residual.
+    fn extrapolate_pressure(&mut self, halo: &mut impl Halo) -> bool {
+        let Some(mut history) = self.pressure_history.take() else {
+            return false;
+        };
+        let pressure = &self.space_domain.fields().pressure;
+        if !history.is_extrapolable || history.previous.len() != pressure.len() {
+            history.previous.clone_from(pressure);
+            self.pressure_history = Some(history);
+            return false;
+        }
+
+        let (_, fluid_cell_count) = self.get_initial_pressure_norm(halo);
+        let current_residual = self.pressure_residual_norm(halo, fluid_cell_count);
+        self.space_domain
+            .fields_mut()
+            .pressure
+            .par_iter_mut()
+            .zip(history.previous.par_iter_mut())
+            .for_each(|(pressure, previous)| {
+                let current = *pressure;
+                *pressure = 2.0 * current - *previous;
+                *previous = current;
+            });
+        let residual = self.pressure_residual_norm(halo, fluid_cell_count);
+        let warm_started = residual < current_residual;
+        if !warm_started {
+            self.space_domain
+                .fields_mut()
+                .pressure
+                .copy_from_slice(&history.previous);
+        }
+        self.pressure_history = Some(history);
+        warm_started
+    }
+
+    // Forget what was derived from the old geometry
+    fn reset_geometry_caches(&mut self) {
+        self.initial_pressure_norm = None;
+        self.fluid_cell_count = None;
+        self.fluid_zones = None;
+        self.clear_pressure_history();
+    }
+
+    fn clear_pressure_history(&mut self) {
+        if let Some(history) = self.pressure_history.as_mut() {
+            *history = PressureHistory::default();
+        }
+    }
+
+    // Tolerance and iteration cap of this step's pressure solve
+    fn pressure_solve_targets(&mut self, halo: &mut impl Halo) -> (f32, usize) {
+        let (initial_pressure_norm, fluid_cell_count) = self.get_initial_pressure_norm(halo);
+        let scale = initial_pressure_norm.max(1.0);
+        let ToleranceSchedule::Adaptive(adaptive) = self.tolerance_schedule else {
+            return (POISSON_EPSILON * scale, ITR_MAX);
+        };
+
+        halo.exchange(&mut self.space_domain);
+        let initial_residual = self.pressure_residual_norm(halo, fluid_cell_count);
+        let tolerance = (adaptive.reduction * initial_residual)
+            .clamp(adaptive.tight * scale, adaptive.loose * scale);
+        (tolerance, adaptive.max_iterations)
+    }
+
+    // RMS residual over the fluid cells of all tiles
+    fn pressure_residual_norm(&self, halo: &mut impl Halo, fluid_cell_count: u32) -> f32 {
+        let space_size = self.space_domain.space_size();
+        // Every tile sees the same global norm, so they all stop together
+        let squared_residuals = self.squared_pressure_residuals(halo.owned_columns(space_size));
+        let squared_sum = halo.sum(reduction::sum(&squared_residuals, self.reduction));
+        (squared_sum / (fluid_cell_count as f32)).sqrt()
+    }
+
+    // Returns the number of sweeps and the last residual norm
+    fn solve_poisson_pressure_equation(
+        &mut self,
+        halo: &mut impl Halo,
+        tolerance: f32,
+        max_iterations: usize,
+    ) -> (usize, f32) {
         let space_size = self.space_domain.space_size();
-        let delta_space = self.space_domain.delta_space();
 
-        let (initial_pressure_norm, fluid_cell_count) = self.get_initial_pressure_norm();
-
-        for _ in 0..ITR_MAX {
-            let mut residual_norm: f32 = (0..space_size[0])
-                .map(|x| -> f32 {
-                    (0..space_size[1])
-                        .map(|y| -> f32 {
-                            if let CellType::FluidCell = self.space_domain.get_cell(x, y).cell_type
-                            {
-                                ((self.space_domain.get_cell(x + 1, y).pressure
-                                    - 2.0 * self.space_domain.get_cell(x, y).pressure
-                                    + self.space_domain.get_cell(x - 1, y).pressure)
-                                    / delta_space[0].powi(2)
-                                    + (self.space_domain.get_cell(x, y + 1).pressure
-                                        - 2.0 * self.space_domain.get_cell(x, y).pressure
-                                        + self.space_domain.get_cell(x, y - 1).pressure)
-                                        / delta_space[1].powi(2)
-                                    - self.space_domain.get_cell(x, y).rhs)
-                                    .powi(2)
-                            } else {
-                                0.0
-                            }
-                        })
-                        .sum()
-                })
-                .sum();
-
-            residual_norm = (residual_norm / (fluid_cell_count as f32)).sqrt();
-
-            if residual_norm < POISSON_EPSILON
-                || residual_norm < initial_pressure_norm * POISSON_EPSILON
-            {
-                break;
+        let (_, fluid_cell_count) = self.get_initial_pressure_norm(halo);
+        if self.pressure_solver != PressureSolver::Sor
+            && halo.owned_columns(space_size) == (0..space_size[0])
+        {
+            return self.solve_pressure_krylov(tolerance, max_iterations, fluid_cell_count);
+        }
+
+        let stall = match self.tolerance_schedule {
+            ToleranceSchedule::Fixed => None,
+            ToleranceSchedule::Adaptive(adaptive) => {
+                Some((adaptive.stall_window, adaptive.stall_reduction))
+            }
+        };
+        let mut residual_norms = Vec::new();
+        for iteration in 0..max_iterations {
+            halo.exchange(&mut self.space_domain);
+            let residual_norm = self.pressure_residual_norm(halo, fluid_cell_count);
+            residual_norms.push(residual_norm);
+            if residual_norm < tolerance {
+                return (iteration, residual_norm);
+            }
+            if let Some((window, reduction)) = stall {
+                if iteration >= window
+                    && residual_norm > reduction * residual_norms[iteration - window]
+                {
+                    return (iteration, residual_norm);
+                }
             }
 
+            // Boundary cells in ghost columns miss neighbours outside the tile
             self.update_pressures_for_boundary_cells();
+            halo.exchange(&mut self.space_domain);
 
-            for x in 0..space_size[0] {
-                for y in 0..space_size[1] {
-                    if let CellType::FluidCell = self.space_domain.get_cell(x, y).cell_type {
-                        self.space_domain.get_cell_mut(x, y).pressure = (1.0 - OMEGA)
-                            * self.space_domain.get_cell(x, y).pressure
-                            + OMEGA
-                                * ((self.space_domain.get_cell(x + 1, y).pressure
-                                    + (self.space_domain.get_cell(x - 1, y).pressure))
-                                    / delta_space[0].powi(2)
-                                    + (self.space_domain.get_cell(x, y + 1).pressure
-                                        + (self.space_domain.get_cell(x, y - 1).pressure))
-                                        / delta_space[1].powi(2)
-                                    - self.space_domain.get_cell(x, y).rhs)
-                                / (2.0 / delta_space[0].powi(2) + 2.0 / delta_space[1].powi(2));
+            // Ghost columns keep the neighbouring tile's values during the sweep
+            let columns = halo.owned_columns(space_size);
+            match self.sor_ordering {
+                SorOrdering::Lexicographic => {
+                    let stencil = PoissonStencil::new(self.space_domain.delta_space());
+                    for position in self.space_domain.fluid_cell_range(columns) {
+                        let index = self.space_domain.fluid_cells()[position];
+                        let (x, y) = self.space_domain.position(index);
+                        *self.space_domain.pressure_mut(x, y) =
+                            stencil.relaxed_pressure(&self.space_domain, self.omega, x, y);
                     }
                 }
+                SorOrdering::RedBlack => {
+                    self.sor_half_sweep(columns.clone(), 0);
+                    // The second color reads the first one's values in ghost columns
+                    halo.exchange(&mut self.space_domain);
+                    self.sor_half_sweep(columns, 1);
+                }
             }
         }
+        let residual_norm = residual_norms.last().copied().unwrap_or(f32::NAN);
+        (max_iterations, residual_norm)
     }
 
-    fn update_pressures_for_boundary_cells(&mut self) {
-        let space_size = self.space_domain.space_size();
+    // Returns the number of iterations and the residual norm as the SOR sweep measures it
+    fn solve_pressure_krylov(
+        &mut self,
+        tolerance: f32,
+        max_iterations: usize,
+        fluid_cell_count: u32,
+    ) -> (usize, f32) {
+        let operator = PoissonOperator::new(&self.space_domain);
+        let fields = self.space_domain.fields();
+        let mut pressure = operator.gather(&fields.pressure);
+        // The operator is the negative Laplacian. Its constant null space makes the
+        // system solvable only for a right hand side without mean, which the outflow
+        // of an unbalanced domain breaks slightly.
+        let mut b: Vec<f32> = operator
+            .gather(&fields.rhs)
+            .iter()
+            .map(|rhs| -rhs)
+            .collect();
+        let mean = b.iter().sum::<f32>() / operator.len().max(1) as f32;
+        b.iter_mut().for_each(|b| *b -= mean);
 
-        for x in 0..space_size[0] {
-            for y in 0..space_size[1] {
-                let cell_type = &self.space_domain.get_cell(x, y).cell_type;
-
-                if let CellType::BoundaryConditionCell(_) = cell_type {
-                    let neighboring_cells = [
-                        (x.wrapping_sub(1), y),
-                        (x + 1, y),
-                        (x, y.wrapping_sub(1)),
-                        (x, y + 1),
-                    ];
-
-                    let mut neighboring_fluid_count = 0;
-                    self.space_domain.get_cell_mut(x, y).pressure = 0.0;
-
-                    for (dx, dy) in neighboring_cells.iter() {
-                        if let Some(cell) = self.space_domain.try_get_cell(*dx, *dy) {
-                            match cell.cell_type {
-                                CellType::FluidCell => {
-                                    self.space_domain.get_cell_mut(x, y).pressure += cell.pressure;
-                                    neighboring_fluid_count += 1;
-                                }
-                                _ => {}
-                            }
-                        }
+        let report = match self.pressure_solver {
+            PressureSolver::ConjugateGradient => linear_solver::conjugate_gradient(
+                &operator,
+                &b,
+                &mut pressure,
+                tolerance,
+                max_iterations,
+            ),
+            _ => linear_solver::bicgstab(&operator, &b, &mut pressure, tolerance, max_iterations),
+        };
+        operator.scatter(&pressure, &mut self.space_domain.fields_mut().pressure);
+        self.update_pressures_for_boundary_cells();
+
+        let residual_norm = self.pressure_residual_norm(&mut NoHalo, fluid_cell_count);
+        (report.iterations, residual_norm)
+    }
+
+    // Updates the fluid cells with (x + y) % 2 == color. Their neighbours all have the
+    // other color, so the cells are computed in parallel and written afterwards.
+    fn sor_half_sweep(&mut self, columns: Range<usize>, color: usize) {
+        let ny = self.space_domain.space_size()[1];
+        let cells = columns.start * ny..columns.end * ny;
+        let is_colored_fluid =
+            |flags: &[CellFlags], x: usize, y: usize| (x + y) % 2 == color && flags[y].is_fluid();
+        let mut updated = vec![0.0; cells.len()];
+
+        let space_domain = &self.space_domain;
+        let stencil = PoissonStencil::new(space_domain.delta_space());
+        let omega = self.omega;
+        updated
+            .par_chunks_mut(ny)
+            .zip(columns.clone())
+            .for_each(|(column, x)| {
+                let flags = &space_domain.cell_flags()[x * ny..(x + 1) * ny];
+                for (y, value) in column.iter_mut().enumerate() {
+                    if is_colored_fluid(flags, x, y) {
+                        *value = stencil.relaxed_pressure(space_domain, omega, x, y);
                     }
+                }
+            });
 
-                    if neighboring_fluid_count != 0 {
-                        self.space_domain.get_cell_mut(x, y).pressure =
-                            self.space_domain.get_cell(x, y).pressure
-                                / (neighboring_fluid_count as f32);
+        let (Fields { pressure, .. }, flags) = self.space_domain.fields_and_flags_mut();
+        pressure[cells.clone()]
+            .par_chunks_mut(ny)
+            .zip(updated.par_chunks(ny))
+            .zip(flags[cells].par_chunks(ny))
+            .zip(columns)
+            .for_each(|(((column, updated), flags), x)| {
+                for y in 0..ny {
+                    if is_colored_fluid(flags, x, y) {
+                        column[y] = updated[y];
                     }
                 }
-            }
-        }
+            });
     }
 
-    fn update_rhs(&mut self) {
+    // Shifts fluid and boundary cells alike, leaving every pressure difference as solved
+    fn apply_pressure_gauge(&mut self, halo: &mut impl Halo) {
         let space_size = self.space_domain.space_size();
-        let delta_space = self.space_domain.delta_space();
+        if self.pressure_gauge == PressureGauge::Floating {
+            return;
+        }
+        // Zones are labelled on the whole domain, a tile of a decomposed domain shifts
+        // all its fluid as one
+        if halo.owned_columns(space_size) == (0..space_size[0]) {
+            self.anchor_fluid_zones();
+            return;
+        }
 
-        for x in 0..space_size[0] {
+        let reference = match self.pressure_gauge {
+            PressureGauge::Floating => return,
+            PressureGauge::ReferenceCell(x, y) => self.space_domain.pressure(x, y),
+            PressureGauge::ZeroMean => {
+                let pressure = &self.space_domain.fields().pressure;
+                let fluid_cells = &self.space_domain.fluid_cells()[self
+                    .space_domain
+                    .fluid_cell_range(halo.owned_columns(space_size))];
+                let sum: f32 = fluid_cells.iter().map(|&index| pressure[index]).sum();
+                halo.sum(sum) / halo.sum(fluid_cells.len() as f32)
+            }
+        };
+
+        for x in halo.owned_columns(space_size) {
             for y in 0..space_size[1] {
-                if let CellType::FluidCell = self.space_domain.get_cell(x, y).cell_type {
-                    self.space_domain.get_cell_mut(x, y).rhs =
-                        ((self.space_domain.get_cell(x, y).f
-                            - self.space_domain.get_cell(x - 1, y).f)
-                            / delta_space[0]
-                            + (self.space_domain.get_cell(x, y).g
-                                - self.space_domain.get_cell(x, y - 1).g)
-                                / delta_space[1])
-                            / self.delta_time;
+                if self
+                    .space_domain
+                    .flags(x, y)
+                    .intersects(CellFlags::FLUID | CellFlags::BOUNDARY)
+                {
+                    *self.space_domain.pressure_mut(x, y) -= reference;
                 }
             }
         }
     }
 
-    fn update_fg(&mut self) {
+    // The gauge applied to every fluid zone on its own. The zone of a reference cell
+    // is anchored to it and the other zones to their mean. Boundary cells follow the
+    // zone of their first fluid neighbour, the largest zone if they have none.
+    fn anchor_fluid_zones(&mut self) {
+        let space_domain = &self.space_domain;
+        let zones = self
+            .fluid_zones
+            .get_or_insert_with(|| FluidZones::label(space_domain));
+        if zones.is_empty() {
+            return;
+        }
+
+        let pressure = &space_domain.fields().pressure;
+        let reference_cell = match self.pressure_gauge {
+            PressureGauge::ReferenceCell(x, y) => Some(space_domain.index(x, y)),
+            _ => None,
+        };
+        let reference_zone = reference_cell.and_then(|index| zones.zone_of(index));
+        let references: Vec<f32> = (0..zones.len())
+            .map(|zone| match reference_cell {
+                Some(index) if reference_zone == Some(zone) => pressure[index],
+                _ => {
+                    let cells = zones.cells(zone);
+                    let sum: f32 = cells.iter().map(|&index| pressure[index]).sum();
+                    sum / cells.len() as f32
+                }
+            })
+            .collect();
+        let boundary_zones: Vec<(usize, usize)> = space_domain
+            .boundary_cells()
+            .iter()
+            .map(|&index| {
+                let (x, y) = space_domain.position(index);
+                let zone = FACE_NEIGHBORS
+                    .into_iter()
+                    .filter_map(|offset| space_domain.neighbor_position(x, y, offset))
+                    .find_map(|(nx, ny)| zones.zone_of(space_domain.index(nx, ny)))
+                    .unwrap_or(0);
+                (index, zone)
+            })
+            .collect();
+
+        let zones = self.fluid_zones.as_ref().unwrap();
+        let pressure = &mut self.space_domain.fields_mut().pressure;
+        for (zone, reference) in references.iter().enumerate() {
+            for &index in zones.cells(zone) {
+                pressure[index] -= reference;
+            }
+        }
+        for (index, zone) in boundary_zones {
+            pressure[index] -= references[zone];
+        }
+    }
+
+    fn update_pressures_for_boundary_cells(&mut self) {
+        for position in 0..self.space_domain.boundary_cells().len() {
+            let index = self.space_domain.boundary_cells()[position];
+            let (x, y) = self.space_domain.position(index);
+            *self.space_domain.pressure_mut(x, y) =
+                if self.space_domain.flags(x, y).contains(CellFlags::PERIODIC) {
+                    let (source_x, source_y) = self.space_domain.periodic_source(x, y);
+                    self.space_domain.pressure(source_x, source_y)
+                } else {
+                    self.space_domain.fluid_neighbor_average_pressure(x, y)
+                };
+        }
+    }
+
+    fn update_rhs(&mut self) {
+        let delta_space = self.space_domain.delta_space();
+
+        for position in 0..self.space_domain.fluid_cells().len() {
+            let index = self.space_domain.fluid_cells()[position];
+            let (x, y) = self.space_domain.position(index);
+            let fields = self.space_domain.fields();
+            let f_left = self.space_domain.neighbor_value(&fields.f, x, y, [-1, 0]);
+            let g_below = self.space_domain.neighbor_value(&fields.g, x, y, [0, -1]);
+            *self.space_domain.rhs_mut(x, y) = ((self.space_domain.f(x, y) - f_left)
+                / delta_space[0]
+                + (self.space_domain.g(x, y) - g_below) / delta_space[1])
+                / self.delta_time;
+        }
+    }
+
+    // Body force per unit mass at the u and v locations of each cell
+    fn body_force(&self) -> Vec<[f32; 2]> {
         let space_size = self.space_domain.space_size();
-        for x in 0..space_size[0] {
-            for y in 0..space_size[1] {
-                if let CellType::FluidCell = self.space_domain.get_cell(x, y).cell_type {
-                    if let Some(CellType::FluidCell) = self
-                        .space_domain
-                        .try_get_cell(x + 1, y)
-                        .map(|cell| cell.cell_type)
-                    {
-                        self.space_domain.get_cell_mut(x, y).f =
-                            self.space_domain.get_cell(x, y).velocity[0]
-                                + self.delta_time
-                                    * ((self.space_domain.d2udx2(x, y)
-                                        + self.space_domain.d2udy2(x, y))
-                                        / self.reynolds
-                                        - self.space_domain.du2dx(x, y)
-                                        - self.space_domain.duvdy(x, y)
-                                        + self.acceleration[0]);
-                    }
+        let mut body_force = vec![self.acceleration; space_size[0] * space_size[1]];
+
+        if let Some(epsilon) = self.vorticity_confinement {
+            let confinement = self.space_domain.vorticity_confinement_force(epsilon);
+            for x in 0..space_size[0] - 1 {
+                for y in 0..space_size[1] - 1 {
+                    let index = x * space_size[1] + y;
+                    body_force[index][0] +=
+                        (confinement[index][0] + confinement[index + space_size[1]][0]) / 2.0;
+                    body_force[index][1] +=
+                        (confinement[index][1] + confinement[index + 1][1]) / 2.0;
+                }
+            }
+        }
 
-                    if let Some(CellType::FluidCell) = self
-                        .space_domain
-                        .try_get_cell(x, y + 1)
-                        .map(|cell| cell.cell_type)
+        if let Some(dye_buoyancy) = self.dye_buoyancy {
+            // Dye averaged onto the u and v faces
+            let dye = &self.space_domain.fields().dye;
+            let ny = space_size[1];
+            for x in 0..space_size[0] - 1 {
+                for y in 0..ny - 1 {
+                    let index = x * ny + y;
+                    body_force[index][0] += dye_buoyancy[0] * (dye[index] + dye[index + ny]) / 2.0;
+                    body_force[index][1] += dye_buoyancy[1] * (dye[index] + dye[index + 1]) / 2.0;
+                }
+            }
+        }
+
+        if let Some(surface_wind) = self.surface_wind {
+            // The stress enters the u faces under free slip walls as a flux through
+            // the top of their control volume
+            let ny = space_size[1];
+            let force = surface_wind.stress() / self.space_domain.delta_space()[1];
+            let cell_type = &self.space_domain.fields().cell_type;
+            for x in 0..space_size[0] - 1 {
+                for y in 0..ny - 1 {
+                    let index = x * ny + y;
+                    if matches!(cell_type[index], CellType::FluidCell)
+                        && matches!(cell_type[index + ny], CellType::FluidCell)
+                        && matches!(
+                            cell_type[index + 1],
+                            CellType::BoundaryConditionCell(BoundaryConditionCell::FreeSlipCell)
+                        )
+                        && !matches!(cell_type[index + ny + 1], CellType::FluidCell)
                     {
-                        self.space_domain.get_cell_mut(x, y).g =
-                            self.space_domain.get_cell(x, y).velocity[1]
-                                + self.delta_time
-                                    * ((self.space_domain.d2vdx2(x, y)
-                                        + self.space_domain.d2vdy2(x, y))
-                                        / self.reynolds
-                                        - self.space_domain.duvdx(x, y)
-                                        - self.space_domain.dv2dy(x, y)
-                                        + self.acceleration[1])
+                        body_force[index][0] += force;
                     }
                 }
             }
         }
+
+        // Drag of the particles from the last timestep, until then on another grid
+        // after resampling
+        if let Some(particles) = self.particles.as_ref() {
+            if particles.reaction().len() == body_force.len() {
+                for (force, reaction) in body_force.iter_mut().zip(particles.reaction()) {
+                    force[0] += reaction[0];
+                    force[1] += reaction[1];
+                }
+            }
+        }
+
+        for region_force in &self.region_forces {
+            for x in region_force.x.clone() {
+                for y in region_force.y.clone() {
+                    let force = &mut body_force[x * space_size[1] + y];
+                    force[0] += region_force.force[0];
+                    force[1] += region_force.force[1];
+                }
+            }
+        }
+
+        // Elastic forces of the membranes where they ended the last timestep
+        for membrane in &self.membranes {
+            if membrane.force().len() == body_force.len() {
+                for (force, membrane_force) in body_force.iter_mut().zip(membrane.force()) {
+                    force[0] += membrane_force[0];
+                    force[1] += membrane_force[1];
+                }
+            }
+        }
+
+        if let Some(frame) = self.rotating_frame {
+            let [dx, dy] = self.space_domain.delta_space();
+            let ny = space_size[1];
+            let fields = self.space_domain.fields();
+            // Along one axis at a position on it
+            let centrifugal = |position: f32, axis: usize| match frame.centrifugal_center {
+                Some(center) => frame.rate.powi(2) * (position - center[axis]),
+                None => 0.0,
+            };
+            for x in 1..space_size[0] - 1 {
+                for y in 1..ny - 1 {
+                    let index = x * ny + y;
+                    // v around the u face and u around the v face
+                    let v = (fields.v[index]
+                        + fields.v[index + ny]
+                        + fields.v[index - 1]
+                        + fields.v[index + ny - 1])
+                        / 4.0;
+                    let u = (fields.u[index]
+                        + fields.u[index - ny]
+                        + fields.u[index + 1]
+                        + fields.u[index - ny + 1])
+                        / 4.0;
+                    body_force[index][0] +=
+                        2.0 * frame.rate * v + centrifugal((x + 1) as f32 * dx, 0);
+                    body_force[index][1] +=
+                        -2.0 * frame.rate * u + centrifugal((y + 1) as f32 * dy, 1);
+                }
+            }
+        }
+
+        body_force
     }
+
+    fn update_fg(&mut self) {
+        let body_force = self.body_force();
+        let y_size = self.space_domain.space_size()[1];
+        let (delta_time, reynolds, advection_scheme) =
+            (self.delta_time, self.reynolds, self.advection_scheme);
+
+        self.sweep_fluid_cells(
+            |space_domain, x, y| {
+                let force = body_force[x * y_size + y];
+                Self::new_fg(
+                    space_domain,
+                    x,
+                    y,
+                    force,
+                    delta_time,
+                    reynolds,
+                    advection_scheme,
+                )
+            },
+            |fields, index, [f, g]| {
+                if let Some(f) = f {
+                    fields.f[index] = f;
+                }
+                if let Some(g) = g {
+                    fields.g[index] = g;
+                }
+            },
+        );
+    }
+
+    fn new_fg(
+        space_domain: &SpaceDomain,
+        x: usize,
+        y: usize,
+        force: [f32; 2],
+        delta_time: f32,
+        reynolds: f32,
+        advection_scheme: AdvectionScheme,
+    ) -> [Option<f32>; 2] {
+        let is_open = |offset: [isize; 2]| {
+            space_domain
+                .neighbor_flags(x, y, offset)
+                .intersects(CellFlags::OPEN)
+        };
+
+        let f = is_open([1, 0]).then(|| match advection_scheme {
+            AdvectionScheme::DonorCell => {
+                space_domain.u(x, y)
+                    + delta_time
+                        * ((space_domain.d2udx2(x, y) + space_domain.d2udy2(x, y)) / reynolds
+                            - space_domain.du2dx(x, y)
+                            - space_domain.duvdy(x, y)
+                            + force[0])
+            }
+            AdvectionScheme::SemiLagrangian => {
+                space_domain.semi_lagrangian_u(x, y, delta_time)
+                    + delta_time
+                        * ((space_domain.d2udx2(x, y) + space_domain.d2udy2(x, y)) / reynolds
+                            + force[0])
+            }
+        });
+
+        let g = is_open([0, 1]).then(|| match advection_scheme {
+            AdvectionScheme::DonorCell => {
+                space_domain.v(x, y)
+                    + delta_time
+                        * ((space_domain.d2vdx2(x, y) + space_domain.d2vdy2(x, y)) / reynolds
+                            - space_domain.duvdx(x, y)
+                            - space_domain.dv2dy(x, y)
+                            + force[1])
+            }
+            AdvectionScheme::SemiLagrangian => {
+                space_domain.semi_lagrangian_v(x, y, delta_time)
+                    + delta_time
+                        * ((space_domain.d2vdx2(x, y) + space_domain.d2vdy2(x, y)) / reynolds
+                            + force[1])
+            }
+        });
+
+        [f, g]
+    }
+}
+
+// SOR relaxation factor that converges fastest for the Poisson equation on the grid
+// inside a one cell boundary layer, 2 / (1 + sqrt(1 - rho^2)) with rho the spectral
+// radius of the Jacobi iteration. Obstacles shrink the fluid region and make it an
+// estimate.
+pub fn optimal_omega(space_size: [usize; 2], delta_space: [f32; 2]) -> f32 {
+    let [nx, ny] = space_size.map(|size| size.saturating_sub(2).max(1) as f32);
+    let [wx, wy] = delta_space.map(|delta| 1.0 / delta.powi(2));
+    let rho = ((std::f32::consts::PI / nx).cos() * wx + (std::f32::consts::PI / ny).cos() * wy)
+        / (wx + wy);
+    2.0 / (1.0 + (1.0 - rho.powi(2)).sqrt())
 }
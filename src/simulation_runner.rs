@@ -0,0 +1,183 @@
+use crate::events::Event;
+use crate::field::Field;
+use crate::simulation::Simulation;
+use crate::solver::{Checkpoint, FluidSolver};
+
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+type Configure = Box<dyn FnOnce(&mut Simulation) + Send>;
+
+pub enum Command {
+    Play,
+    Pause,
+    // One timestep, also while playing
+    Step,
+    // None steps as fast as possible while playing
+    SetStepInterval(Option<Duration>),
+    Apply(Event),
+    // Any other parameter change, run between timesteps
+    Configure(Configure),
+    RequestSnapshot(Sender<Checkpoint>),
+}
+
+// Everything needed to draw a frame, published after every timestep
+#[derive(Clone)]
+pub struct RunnerState {
+    pub checkpoint: Checkpoint,
+    pub delta_space: [f32; 2], // meters
+    pub pressure_range: [f32; 2],
+    pub speed_range: [f32; 2],
+    pub psi_range: [f32; 2],
+    pub is_playing: bool,
+}
+
+impl RunnerState {
+    fn new(simulation: &Simulation, is_playing: bool) -> Self {
+        Self {
+            checkpoint: simulation.checkpoint(),
+            delta_space: simulation.delta_space(),
+            pressure_range: simulation.pressure_range(),
+            speed_range: simulation.speed_range(),
+            psi_range: simulation.field(Field::Psi).range().unwrap_or([0.0, 0.0]),
+            is_playing,
+        }
+    }
+}
+
+// Owns a Simulation on a background thread so a long pressure solve never blocks
+// the caller. Commands are handled in order between timesteps.
+pub struct SimulationRunner {
+    commands: Option<Sender<Command>>,
+    latest: Arc<Mutex<Option<RunnerState>>>,
+    worker: Option<JoinHandle<Simulation>>,
+}
+
+impl SimulationRunner {
+    // Starts paused
+    pub fn new(simulation: Simulation) -> Self {
+        let (commands, receiver) = channel();
+        let latest = Arc::new(Mutex::new(Some(RunnerState::new(&simulation, false))));
+        let mailbox = latest.clone();
+        let worker = thread::spawn(move || run(simulation, receiver, &mailbox));
+
+        Self {
+            commands: Some(commands),
+            latest,
+            worker: Some(worker),
+        }
+    }
+
+    // Returns false once the worker has stopped
+    pub fn send(&self, command: Command) -> bool {
+        self.commands.as_ref().unwrap().send(command).is_ok()
+    }
+
+    pub fn play(&self) {
+        self.send(Command::Play);
+    }
+
+    pub fn pause(&self) {
+        self.send(Command::Pause);
+    }
+
+    pub fn step(&self) {
+        self.send(Command::Step);
+    }
+
+    pub fn configure(&self, configure: impl FnOnce(&mut Simulation) + Send + 'static) {
+        self.send(Command::Configure(Box::new(configure)));
+    }
+
+    // The reply arrives once the commands queued before it are handled
+    pub fn request_snapshot(&self) -> Receiver<Checkpoint> {
+        let (sender, receiver) = channel();
+        self.send(Command::RequestSnapshot(sender));
+        receiver
+    }
+
+    // State after the newest timestep, None if nothing changed since the last call
+    pub fn take_latest(&self) -> Option<RunnerState> {
+        self.latest.lock().unwrap().take()
+    }
+
+    // Stop the worker and hand the simulation back
+    pub fn stop(mut self) -> Simulation {
+        self.join().unwrap()
+    }
+
+    fn join(&mut self) -> Option<Simulation> {
+        // Closing the channel lets the worker exit after the queued commands
+        self.commands = None;
+        self.worker
+            .take()
+            .map(|worker| worker.join().expect("simulation runner panicked"))
+    }
+}
+
+impl Drop for SimulationRunner {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+fn run(
+    mut simulation: Simulation,
+    commands: Receiver<Command>,
+    latest: &Mutex<Option<RunnerState>>,
+) -> Simulation {
+    let mut is_playing = false;
+    let mut step_interval = None;
+    let mut next_step = Instant::now();
+
+    let publish = |simulation: &Simulation, is_playing: bool| {
+        *latest.lock().unwrap() = Some(RunnerState::new(simulation, is_playing));
+    };
+
+    loop {
+        // Block while paused, otherwise wait for commands until the next step is due
+        let received = if is_playing {
+            commands.recv_timeout(next_step.saturating_duration_since(Instant::now()))
+        } else {
+            commands.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        };
+
+        match received {
+            Ok(Command::Play) => {
+                is_playing = true;
+                next_step = Instant::now();
+            }
+            Ok(Command::Pause) => {
+                is_playing = false;
+                publish(&simulation, is_playing);
+            }
+            Ok(Command::Step) => {
+                simulation.iterate_one_timestep();
+                publish(&simulation, is_playing);
+            }
+            Ok(Command::SetStepInterval(interval)) => step_interval = interval,
+            Ok(Command::Apply(event)) => {
+                simulation.apply_event(event);
+                publish(&simulation, is_playing);
+            }
+            Ok(Command::Configure(configure)) => {
+                configure(&mut simulation);
+                publish(&simulation, is_playing);
+            }
+            Ok(Command::RequestSnapshot(reply)) => {
+                let _ = reply.send(simulation.checkpoint());
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                simulation.iterate_one_timestep();
+                publish(&simulation, is_playing);
+                next_step = match step_interval {
+                    Some(interval) => (next_step + interval).max(Instant::now()),
+                    None => Instant::now(),
+                };
+            }
+            Err(RecvTimeoutError::Disconnected) => return simulation,
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use crate::cell::Cell;
+use crate::cell::CellType;
+use crate::png::{self, PngColor};
+use crate::solver::{Checkpoint, FluidSolver};
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    // Legacy VTK structured points with cell data, readable by ParaView
+    Vtk,
+    // One row per cell with cell centred values
+    Csv,
+    // Grayscale speed, one pixel per cell
+    Png,
+}
+
+impl SnapshotFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SnapshotFormat::Vtk => "vtk",
+            SnapshotFormat::Csv => "csv",
+            SnapshotFormat::Png => "png",
+        }
+    }
+}
+
+// What submit does when the queue is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backpressure {
+    // Wait for the writer, stepping slows down to the disk speed
+    #[default]
+    Block,
+    // Drop the snapshot and keep stepping
+    Drop,
+}
+
+type CellValue = fn(&Cell) -> f32;
+
+struct Snapshot {
+    index: usize,
+    delta_space: [f32; 2],
+    checkpoint: Checkpoint,
+}
+
+// Serializes snapshots on a worker thread so stepping does not wait for the disk
+pub struct SnapshotWriter {
+    sender: Option<SyncSender<Snapshot>>,
+    worker: Option<JoinHandle<io::Result<usize>>>,
+    backpressure: Backpressure,
+    submitted: usize,
+    dropped: usize,
+}
+
+impl SnapshotWriter {
+    // Files are named snapshot_000000.<extension> in directory, at most capacity
+    // snapshots wait in memory
+    pub fn new(
+        directory: impl Into<PathBuf>,
+        formats: &[SnapshotFormat],
+        capacity: usize,
+        backpressure: Backpressure,
+    ) -> io::Result<Self> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+
+        let formats = formats.to_vec();
+        let (sender, receiver) = sync_channel(capacity);
+        let worker = thread::spawn(move || write_snapshots(&directory, &formats, receiver));
+
+        Ok(Self {
+            sender: Some(sender),
+            worker: Some(worker),
+            backpressure,
+            submitted: 0,
+            dropped: 0,
+        })
+    }
+
+    // Returns whether the snapshot was queued
+    pub fn submit(&mut self, delta_space: [f32; 2], checkpoint: Checkpoint) -> bool {
+        let snapshot = Snapshot {
+            index: self.submitted + self.dropped,
+            delta_space,
+            checkpoint,
+        };
+        let sender = self.sender.as_ref().unwrap();
+
+        let queued = match self.backpressure {
+            Backpressure::Block => sender.send(snapshot).is_ok(),
+            Backpressure::Drop => match sender.try_send(snapshot) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => false,
+            },
+        };
+
+        if queued {
+            self.submitted += 1;
+        } else {
+            self.dropped += 1;
+        }
+        queued
+    }
+
+    // Freeze the current state of any solver and queue it
+    pub fn submit_from(&mut self, solver: &dyn FluidSolver) -> bool {
+        self.submit(solver.delta_space(), solver.checkpoint())
+    }
+
+    pub fn submitted(&self) -> usize {
+        self.submitted
+    }
+
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    // Wait for the queue to drain, returns the number of snapshots written or the
+    // first error, after which the worker stops writing
+    pub fn finish(mut self) -> io::Result<usize> {
+        self.join().expect("snapshot writer panicked")
+    }
+
+    fn join(&mut self) -> thread::Result<io::Result<usize>> {
+        // Closing the channel lets the worker exit once the queue is empty
+        self.sender = None;
+        match self.worker.take() {
+            Some(worker) => worker.join(),
+            None => Ok(Ok(0)),
+        }
+    }
+}
+
+impl Drop for SnapshotWriter {
+    // A panic of the worker is not raised again here, drop may run while unwinding
+    fn drop(&mut self) {
+        let _ = self.join();
+    }
+}
+
+fn write_snapshots(
+    directory: &Path,
+    formats: &[SnapshotFormat],
+    receiver: Receiver<Snapshot>,
+) -> io::Result<usize> {
+    let mut written = 0;
+    for snapshot in receiver {
+        for &format in formats {
+            write_snapshot(
+                directory,
+                snapshot.index,
+                format,
+                &snapshot.checkpoint,
+                snapshot.delta_space,
+            )?;
+        }
+        written += 1;
+    }
+    Ok(written)
+}
+
+// Writes directory/snapshot_<index>.<extension>
+pub(crate) fn write_snapshot(
+    directory: &Path,
+    index: usize,
+    format: SnapshotFormat,
+    checkpoint: &Checkpoint,
+    delta_space: [f32; 2],
+) -> io::Result<()> {
+    let path = directory.join(format!("snapshot_{index:06}.{}", format.extension()));
+    let mut file = BufWriter::new(File::create(path)?);
+    match format {
+        SnapshotFormat::Vtk => write_vtk(&mut file, checkpoint, delta_space)?,
+        SnapshotFormat::Csv => write_csv(&mut file, checkpoint, delta_space)?,
+        SnapshotFormat::Png => write_png(&mut file, checkpoint)?,
+    }
+    file.flush()
+}
+
+fn write_vtk(
+    file: &mut impl Write,
+    checkpoint: &Checkpoint,
+    delta_space: [f32; 2],
+) -> io::Result<()> {
+    let [nx, ny] = checkpoint.space_size;
+    let [dx, dy] = delta_space;
+    let cells = &checkpoint.cells;
+
+    writeln!(file, "# vtk DataFile Version 3.0")?;
+    writeln!(file, "{}", vtk_title(checkpoint))?;
+    writeln!(file, "ASCII")?;
+    writeln!(file, "DATASET STRUCTURED_POINTS")?;
+    if let Some(metadata) = &checkpoint.metadata {
+        // Numbers of the run as field data of the dataset, the text is in the title
+        let parameters = &metadata.parameters;
+        let values = [
+            ("TIME", checkpoint.time as f64),
+            ("reynolds", parameters.reynolds as f64),
+            ("omega", parameters.omega as f64),
+            ("delta_time", parameters.delta_time as f64),
+            ("start_time", metadata.start_time as f64),
+        ];
+        writeln!(file, "FIELD FieldData {}", values.len())?;
+        for (name, value) in values {
+            writeln!(file, "{name} 1 1 double")?;
+            writeln!(file, "{value}")?;
+        }
+    }
+    writeln!(file, "DIMENSIONS {} {} 1", nx + 1, ny + 1)?;
+    writeln!(file, "ORIGIN 0 0 0")?;
+    writeln!(file, "SPACING {dx} {dy} 1")?;
+    writeln!(file, "CELL_DATA {}", nx * ny)?;
+
+    // VTK orders cells with x varying fastest
+    let row_major = || (0..ny).flat_map(|y| (0..nx).map(move |x| (x, y)));
+
+    writeln!(file, "SCALARS cell_type int 1")?;
+    writeln!(file, "LOOKUP_TABLE default")?;
+    for (x, y) in row_major() {
+        writeln!(file, "{}", cells[x * ny + y].cell_type.id())?;
+    }
+
+    let scalars: [(&str, CellValue); 3] = [
+        ("pressure", |cell| cell.pressure),
+        ("psi", |cell| cell.psi),
+        ("dye", |cell| cell.dye),
+    ];
+    for (name, value) in scalars {
+        writeln!(file, "SCALARS {name} float 1")?;
+        writeln!(file, "LOOKUP_TABLE default")?;
+        for (x, y) in row_major() {
+            writeln!(file, "{}", value(&cells[x * ny + y]))?;
+        }
+    }
+
+    writeln!(file, "VECTORS velocity float")?;
+    for (x, y) in row_major() {
+        let [u, v] = checkpoint.centered_velocity(x, y);
+        writeln!(file, "{u} {v} 0")?;
+    }
+    Ok(())
+}
+
+// The header line of legacy VTK files holds at most 256 characters and no line break
+fn vtk_title(checkpoint: &Checkpoint) -> String {
+    let mut title = match &checkpoint.metadata {
+        Some(metadata) => format!(
+            "flow2d_rs {} ({}) {} snapshot at t = {}",
+            metadata.crate_version,
+            metadata.git_hash.as_deref().unwrap_or("unknown commit"),
+            metadata.scene.replace(['\n', '\r'], " "),
+            checkpoint.time
+        ),
+        None => format!("flow2d_rs snapshot at t = {}", checkpoint.time),
+    };
+    while title.len() > 255 {
+        title.pop();
+    }
+    title
+}
+
+fn write_csv(
+    file: &mut impl Write,
+    checkpoint: &Checkpoint,
+    delta_space: [f32; 2],
+) -> io::Result<()> {
+    let [nx, ny] = checkpoint.space_size;
+    let [dx, dy] = delta_space;
+
+    writeln!(file, "x,y,cell_type,u,v,pressure,psi,dye")?;
+    for x in 0..nx {
+        for y in 0..ny {
+            let cell = &checkpoint.cells[x * ny + y];
+            let [u, v] = checkpoint.centered_velocity(x, y);
+            writeln!(
+                file,
+                "{},{},{},{u},{v},{},{},{}",
+                (x as f32 + 0.5) * dx,
+                (y as f32 + 0.5) * dy,
+                cell.cell_type.id(),
+                cell.pressure,
+                cell.psi,
+                cell.dye
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn write_png(file: &mut impl Write, checkpoint: &Checkpoint) -> io::Result<()> {
+    let [nx, ny] = checkpoint.space_size;
+
+    let speeds: Vec<f32> = (0..nx)
+        .flat_map(|x| (0..ny).map(move |y| (x, y)))
+        .map(|(x, y)| {
+            let [u, v] = checkpoint.centered_velocity(x, y);
+            (u.powi(2) + v.powi(2)).sqrt()
+        })
+        .collect();
+    let max_speed = speeds
+        .iter()
+        .zip(checkpoint.cells.iter())
+        .filter(|(_, cell)| matches!(cell.cell_type, CellType::FluidCell))
+        .fold(0.0f32, |max, (&speed, _)| max.max(speed));
+
+    // Rows top to bottom
+    let mut pixels = Vec::with_capacity(nx * ny);
+    for y in (0..ny).rev() {
+        for x in 0..nx {
+            let pixel = match checkpoint.cells[x * ny + y].cell_type {
+                CellType::FluidCell if max_speed > 0.0 => {
+                    (speeds[x * ny + y] / max_speed * 255.0).clamp(0.0, 255.0) as u8
+                }
+                _ => 0,
+            };
+            pixels.push(pixel);
+        }
+    }
+
+    png::write_png(file, [nx, ny], PngColor::Grayscale, &pixels)
+}
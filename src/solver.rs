@@ -0,0 +1,281 @@
+use crate::cell::CellType;
+use crate::cell::{BoundaryConditionCell, Cell};
+use crate::colormap::RangeMode;
+use crate::field::{Field, FieldView};
+use crate::field_snapshot::{invalid_data, read_f32, read_u32};
+use crate::metadata::SimulationMetadata;
+use crate::space_domain::{self, Fields, VelocityError};
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const CHECKPOINT_MAGIC: &[u8; 4] = b"F2DC";
+// Version 2 added the metadata, version 1 files still load without it
+const CHECKPOINT_VERSION: u32 = 2;
+
+// Common interface of the fluid solvers so the viewer and exporters can be
+// shared between backends
+pub trait FluidSolver {
+    fn iterate_one_timestep(&mut self);
+
+    fn time(&self) -> f32; // seconds
+
+    fn delta_space(&self) -> [f32; 2]; // meters
+
+    fn space_size(&self) -> [usize; 2];
+
+    fn get_cell(&self, x: usize, y: usize) -> Cell;
+
+    // Gathered copy of all cells, indexed by x * ny + y
+    fn cells(&self) -> Vec<Cell>;
+
+    // All quantities without copying, one array each indexed by x * ny + y
+    fn fields(&self) -> &Fields;
+
+    fn get_centered_velocity(&self, x: usize, y: usize) -> [f32; 2];
+
+    // Velocity at any point of the fluid in meters, interpolated from the staggered
+    // faces, see space_domain::velocity_at
+    fn velocity_at(&self, position: [f32; 2]) -> Result<[f32; 2], VelocityError> {
+        space_domain::velocity_at(
+            self.fields(),
+            self.space_size(),
+            self.delta_space(),
+            position,
+        )
+    }
+
+    // One quantity with its grid geometry, the common input of exporters
+    fn field(&self, field: Field) -> FieldView<'_> {
+        FieldView::new(field, self.fields(), self.space_size(), self.delta_space())
+    }
+
+    // Display ranges for coloring, following the range modes. The extremes of a
+    // field are in FieldView::range.
+    fn pressure_range(&self) -> [f32; 2];
+
+    fn speed_range(&self) -> [f32; 2];
+
+    // Display range modes for pressure_range and speed_range
+    fn set_range_modes(&mut self, pressure: RangeMode, speed: RangeMode);
+
+    fn checkpoint(&self) -> Checkpoint;
+
+    fn restore(&mut self, checkpoint: &Checkpoint);
+
+    // Bottom elevation in meters of solvers with terrain, indexed x * ny + y
+    fn terrain_elevations(&self) -> Option<&[f32]> {
+        None
+    }
+
+    // Provenance of the run for exporters, see SimulationMetadata
+    fn metadata(&self) -> Option<&SimulationMetadata> {
+        None
+    }
+
+    // Refill buffer in image order, rows top to bottom. Reusing the buffer between
+    // frames avoids allocating once it has grown to the domain size.
+    fn pack_render_buffer(&self, buffer: &mut Vec<RenderCell>) {
+        let [nx, ny] = self.space_size();
+        let fields = self.fields();
+
+        buffer.clear();
+        buffer.extend((0..ny).rev().flat_map(|y| {
+            (0..nx).map(move |x| {
+                let index = x * ny + y;
+                let is_fluid = matches!(fields.cell_type[index], CellType::FluidCell);
+                // Faces outside the domain belong to the ghost cell at rest, see
+                // SpaceDomain::neighbor
+                let left = x.checked_sub(1).map_or(0.0, |left| fields.u[left * ny + y]);
+                let below = y
+                    .checked_sub(1)
+                    .map_or(0.0, |below| fields.v[x * ny + below]);
+                RenderCell {
+                    velocity: if is_fluid {
+                        [
+                            (fields.u[index] + left) / 2.0,
+                            (fields.v[index] + below) / 2.0,
+                        ]
+                    } else {
+                        [0.0, 0.0]
+                    },
+                    pressure: fields.pressure[index],
+                    psi: fields.psi[index],
+                    dye: fields.dye[index],
+                    is_fluid: is_fluid as u32,
+                }
+            })
+        }));
+    }
+}
+
+// Plain values of one cell for GPU upload, velocity is cell centred
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[repr(C)]
+pub struct RenderCell {
+    pub velocity: [f32; 2],
+    pub pressure: f32,
+    pub psi: f32,
+    pub dye: f32,
+    pub is_fluid: u32,
+}
+
+// Copy of the solver state at a given time
+#[derive(Clone)]
+pub struct Checkpoint {
+    pub time: f32, // seconds
+    pub space_size: [usize; 2],
+    pub cells: Vec<Cell>,
+    pub metadata: Option<SimulationMetadata>,
+}
+
+impl Checkpoint {
+    pub fn get_cell(&self, x: usize, y: usize) -> &Cell {
+        &self.cells[x * self.space_size[1] + y]
+    }
+
+    // Average of the staggered face velocities around the cell center, cells on the
+    // left and bottom edge use their own face twice
+    pub fn centered_velocity(&self, x: usize, y: usize) -> [f32; 2] {
+        let cell = self.get_cell(x, y);
+        let left = if x > 0 {
+            self.get_cell(x - 1, y).velocity[0]
+        } else {
+            cell.velocity[0]
+        };
+        let bottom = if y > 0 {
+            self.get_cell(x, y - 1).velocity[1]
+        } else {
+            cell.velocity[1]
+        };
+        [
+            (left + cell.velocity[0]) / 2.0,
+            (bottom + cell.velocity[1]) / 2.0,
+        ]
+    }
+
+    // Every value of every cell, so a run restored from the file continues exactly
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+
+        file.write_all(CHECKPOINT_MAGIC)?;
+        file.write_all(&CHECKPOINT_VERSION.to_le_bytes())?;
+        file.write_all(&self.time.to_le_bytes())?;
+        for size in self.space_size {
+            file.write_all(&(size as u32).to_le_bytes())?;
+        }
+        // Length prefixed key=value lines, empty without metadata
+        let metadata = self
+            .metadata
+            .as_ref()
+            .map_or(String::new(), |metadata| metadata.to_string());
+        file.write_all(&(metadata.len() as u32).to_le_bytes())?;
+        file.write_all(metadata.as_bytes())?;
+        for cell in self.cells.iter() {
+            let (tag, boundary_velocity) = match cell.cell_type {
+                CellType::FluidCell => (0u8, [0.0, 0.0]),
+                CellType::VoidCell => (1, [0.0, 0.0]),
+                CellType::BoundaryConditionCell(boundary) => match boundary {
+                    BoundaryConditionCell::NoSlipCell {
+                        boundary_condition_velocity,
+                    } => (2, boundary_condition_velocity),
+                    BoundaryConditionCell::FreeSlipCell => (3, [0.0, 0.0]),
+                    BoundaryConditionCell::OutFlowCell => (4, [0.0, 0.0]),
+                    BoundaryConditionCell::InflowCell => (5, [0.0, 0.0]),
+                    BoundaryConditionCell::PeriodicCell => (6, [0.0, 0.0]),
+                },
+            };
+            file.write_all(&[tag])?;
+            let values = [
+                boundary_velocity[0],
+                boundary_velocity[1],
+                cell.velocity[0],
+                cell.velocity[1],
+                cell.pressure,
+                cell.rhs,
+                cell.f,
+                cell.g,
+                cell.psi,
+                cell.dye,
+            ];
+            for value in values {
+                file.write_all(&value.to_le_bytes())?;
+            }
+        }
+        file.flush()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; 4];
+        file.read_exact(&mut magic)?;
+        if &magic != CHECKPOINT_MAGIC {
+            return Err(invalid_data("not a checkpoint"));
+        }
+        let version = read_u32(&mut file)?;
+        if version != 1 && version != CHECKPOINT_VERSION {
+            return Err(invalid_data(&format!(
+                "unsupported checkpoint version {version}"
+            )));
+        }
+
+        let time = read_f32(&mut file)?;
+        let space_size = [read_u32(&mut file)? as usize, read_u32(&mut file)? as usize];
+        let metadata = match version {
+            1 => None,
+            _ => {
+                let mut text = vec![0; read_u32(&mut file)? as usize];
+                file.read_exact(&mut text)?;
+                if text.is_empty() {
+                    None
+                } else {
+                    let metadata = String::from_utf8(text)
+                        .ok()
+                        .and_then(|text| SimulationMetadata::parse(&text));
+                    Some(metadata.ok_or_else(|| invalid_data("malformed checkpoint metadata"))?)
+                }
+            }
+        };
+        let cells = (0..space_size[0] * space_size[1])
+            .map(|_| {
+                let mut tag = [0];
+                file.read_exact(&mut tag)?;
+                let mut values = [0.0; 10];
+                for value in values.iter_mut() {
+                    *value = read_f32(&mut file)?;
+                }
+                let cell_type = match tag[0] {
+                    0 => CellType::FluidCell,
+                    1 => CellType::VoidCell,
+                    2 => CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                        boundary_condition_velocity: [values[0], values[1]],
+                    }),
+                    3 => CellType::BoundaryConditionCell(BoundaryConditionCell::FreeSlipCell),
+                    4 => CellType::BoundaryConditionCell(BoundaryConditionCell::OutFlowCell),
+                    5 => CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell),
+                    6 => CellType::BoundaryConditionCell(BoundaryConditionCell::PeriodicCell),
+                    tag => return Err(invalid_data(&format!("unknown cell type {tag}"))),
+                };
+                Ok(Cell {
+                    cell_type,
+                    velocity: [values[2], values[3]],
+                    pressure: values[4],
+                    rhs: values[5],
+                    f: values[6],
+                    g: values[7],
+                    psi: values[8],
+                    dye: values[9],
+                })
+            })
+            .collect::<io::Result<Vec<Cell>>>()?;
+
+        Ok(Self {
+            time,
+            space_size,
+            cells,
+            metadata,
+        })
+    }
+}
@@ -1,9 +1,175 @@
+use crate::boundary_segments::BoundarySegment;
 use crate::cell::BoundaryConditionCell;
 use crate::cell::Cell;
+use crate::cell::CellFlags;
 use crate::cell::CellType;
+use crate::colormap::{ColorRange, RangeMode};
+use crate::field::{Field, FieldView};
+use crate::memory::{self, AllocationError, MemoryFootprint};
+use crate::reduction::{self, Reduction};
+
+use std::fmt;
+use std::ops::Range;
+
+// How the boundary velocity pass sees velocities written during the same pass.
+// Boundary cells next to each other read each other's faces, the other sweeps only
+// read values they don't write and come out the same either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UpdateMode {
+    // Read the velocities from before the pass, independent of the cell order
+    #[default]
+    DoubleBuffered,
+    // Read velocities as they are being updated
+    InPlace,
+}
+
+// Why there is no fluid velocity at a point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VelocityError {
+    // Position in meters
+    OutsideDomain([f32; 2]),
+    // Walls, inflows and the like hold boundary values rather than the flow
+    NotFluid {
+        cell: (usize, usize),
+        cell_type: CellType,
+    },
+}
+
+impl fmt::Display for VelocityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VelocityError::OutsideDomain(position) => {
+                write!(f, "{position:?} lies outside the domain")
+            }
+            VelocityError::NotFluid { cell, cell_type } => {
+                write!(f, "cell {cell:?} is a {cell_type:?}, not fluid")
+            }
+        }
+    }
+}
+
+impl std::error::Error for VelocityError {}
+
+// Offsets of the cells sharing a face with a cell: left, right, bottom, top
+pub const FACE_NEIGHBORS: [[isize; 2]; 4] = [[-1, 0], [1, 0], [0, -1], [0, 1]];
+
+// Per cell quantities with one array each, in cell order x * ny + y. Sweeps over a
+// single quantity read contiguous memory instead of striding over whole cells.
+#[derive(Debug, Clone, Default)]
+pub struct Fields {
+    pub cell_type: Vec<CellType>,
+    pub u: Vec<f32>,
+    pub v: Vec<f32>,
+    pub pressure: Vec<f32>,
+    pub rhs: Vec<f32>,
+    pub f: Vec<f32>,
+    pub g: Vec<f32>,
+    pub psi: Vec<f32>,
+    pub dye: Vec<f32>,
+}
+
+impl Fields {
+    pub fn from_cells(cells: &[Cell]) -> Self {
+        let mut fields = Self::default();
+        for cell in cells {
+            fields.push(cell);
+        }
+        fields
+    }
+
+    // Empty arrays with room for every cell of the grid
+    pub fn try_with_capacity(space_size: [usize; 2]) -> Result<Self, AllocationError> {
+        let len = memory::cell_count(space_size)?;
+        Ok(Self {
+            cell_type: memory::try_with_capacity(len, space_size, "cell types")?,
+            u: memory::try_with_capacity(len, space_size, "u")?,
+            v: memory::try_with_capacity(len, space_size, "v")?,
+            pressure: memory::try_with_capacity(len, space_size, "pressure")?,
+            rhs: memory::try_with_capacity(len, space_size, "rhs")?,
+            f: memory::try_with_capacity(len, space_size, "f")?,
+            g: memory::try_with_capacity(len, space_size, "g")?,
+            psi: memory::try_with_capacity(len, space_size, "psi")?,
+            dye: memory::try_with_capacity(len, space_size, "dye")?,
+        })
+    }
+
+    pub fn memory_footprint(&self, footprint: &mut MemoryFootprint) {
+        footprint.add("cell types", &self.cell_type);
+        footprint.add("u", &self.u);
+        footprint.add("v", &self.v);
+        footprint.add("pressure", &self.pressure);
+        footprint.add("rhs", &self.rhs);
+        footprint.add("f", &self.f);
+        footprint.add("g", &self.g);
+        footprint.add("psi", &self.psi);
+        footprint.add("dye", &self.dye);
+    }
+
+    pub fn len(&self) -> usize {
+        self.cell_type.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cell_type.is_empty()
+    }
 
+    // Gathered view of one cell
+    pub fn cell(&self, index: usize) -> Cell {
+        Cell {
+            cell_type: self.cell_type[index],
+            velocity: [self.u[index], self.v[index]],
+            pressure: self.pressure[index],
+            rhs: self.rhs[index],
+            f: self.f[index],
+            g: self.g[index],
+            psi: self.psi[index],
+            dye: self.dye[index],
+        }
+    }
+
+    pub fn set_cell(&mut self, index: usize, cell: &Cell) {
+        self.cell_type[index] = cell.cell_type;
+        self.u[index] = cell.velocity[0];
+        self.v[index] = cell.velocity[1];
+        self.pressure[index] = cell.pressure;
+        self.rhs[index] = cell.rhs;
+        self.f[index] = cell.f;
+        self.g[index] = cell.g;
+        self.psi[index] = cell.psi;
+        self.dye[index] = cell.dye;
+    }
+
+    // [u, v] pairs in cell order
+    pub fn velocities(&self) -> impl Iterator<Item = [f32; 2]> + '_ {
+        self.u.iter().zip(self.v.iter()).map(|(&u, &v)| [u, v])
+    }
+
+    pub fn to_cells(&self) -> Vec<Cell> {
+        (0..self.len()).map(|index| self.cell(index)).collect()
+    }
+
+    fn push(&mut self, cell: &Cell) {
+        self.cell_type.push(cell.cell_type);
+        self.u.push(cell.velocity[0]);
+        self.v.push(cell.velocity[1]);
+        self.pressure.push(cell.pressure);
+        self.rhs.push(cell.rhs);
+        self.f.push(cell.f);
+        self.g.push(cell.g);
+        self.psi.push(cell.psi);
+        self.dye.push(cell.dye);
+    }
+}
+
+#[derive(Clone)]
 pub struct SpaceDomain {
-    space_domain: Vec<Cell>,
+    fields: Fields,
+    // Follows the cell types, see update_flags
+    flags: Vec<CellFlags>,
+    // Sorted indices of the fluid cells and the boundary condition cells, so sweeps
+    // skip obstacles and void
+    fluid_cells: Vec<usize>,
+    boundary_cells: Vec<usize>,
     space_size: [usize; 2],
     delta_space: [f32; 2], // meters
 
@@ -13,21 +179,73 @@ pub struct SpaceDomain {
     // For coloring
     pressure_range: [f32; 2],
     speed_range: [f32; 2],
-    psi_range: [f32; 2],
+
+    pressure_color_range: ColorRange,
+    speed_color_range: ColorRange,
+
+    update_mode: UpdateMode,
+    reduction: Reduction,
+
+    // See BoundarySegment, in the order they were added
+    segments: Vec<BoundarySegment>,
 }
 
+// Stands in for every cell outside the stored domain, see neighbor
+const GHOST: Cell = Cell {
+    cell_type: CellType::VoidCell,
+    velocity: [0.0, 0.0],
+    pressure: 0.0,
+    rhs: 0.0,
+    f: 0.0,
+    g: 0.0,
+    psi: 0.0,
+    dye: 0.0,
+};
+
 impl SpaceDomain {
+    // Panics with the AllocationError when the grid doesn't fit in memory, see try_new
     pub fn new(space_domain: Vec<Vec<Cell>>, delta_space: [f32; 2], gamma: f32) -> Self {
         let space_size = [space_domain.len(), space_domain[0].len()];
-        Self {
-            space_domain: space_domain.into_iter().flatten().collect(),
+        let mut cells = space_domain.into_iter().flatten();
+        Self::try_new(space_size, delta_space, gamma, |_, _| {
+            cells.next().expect("columns of equal length")
+        })
+        .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    // Reserves every buffer of the domain fallibly and fills it with the cells
+    // layout returns for (x, y), called in cell order. A grid too large for the
+    // machine is an error instead of an aborted process.
+    pub fn try_new(
+        space_size: [usize; 2],
+        delta_space: [f32; 2],
+        gamma: f32,
+        mut layout: impl FnMut(usize, usize) -> Cell,
+    ) -> Result<Self, AllocationError> {
+        let mut fields = Fields::try_with_capacity(space_size)?;
+        for x in 0..space_size[0] {
+            for y in 0..space_size[1] {
+                fields.push(&layout(x, y));
+            }
+        }
+        let mut space_domain = Self {
+            fields,
+            flags: Vec::new(),
+            fluid_cells: Vec::new(),
+            boundary_cells: Vec::new(),
             space_size,
             delta_space,
             gamma,
             pressure_range: [0.0, 0.0],
             speed_range: [0.0, 0.0],
-            psi_range: [0.0, 0.0],
-        }
+            pressure_color_range: ColorRange::default(),
+            speed_color_range: ColorRange::default(),
+            update_mode: UpdateMode::default(),
+            reduction: Reduction::default(),
+            segments: Vec::new(),
+        };
+        space_domain.try_update_flags()?;
+        Ok(space_domain)
     }
 }
 
@@ -41,6 +259,10 @@ impl SpaceDomain {
         self.space_size
     }
 
+    pub fn gamma(&self) -> f32 {
+        self.gamma
+    }
+
     pub fn pressure_range(&self) -> [f32; 2] {
         self.pressure_range
     }
@@ -49,310 +271,1166 @@ impl SpaceDomain {
         self.speed_range
     }
 
-    pub fn psi_range(&self) -> [f32; 2] {
-        self.psi_range
+    pub fn field(&self, field: Field) -> FieldView<'_> {
+        FieldView::new(field, &self.fields, self.space_size, self.delta_space)
     }
 
-    pub fn get_cell(&self, x: usize, y: usize) -> &Cell {
-        &self.space_domain[x * self.space_size[1] + y]
+    pub fn range_modes(&self) -> [RangeMode; 2] {
+        [
+            self.pressure_color_range.mode(),
+            self.speed_color_range.mode(),
+        ]
     }
 
-    pub fn try_get_cell(&self, x: usize, y: usize) -> Option<&Cell> {
-        if x < self.space_size[0] && y < self.space_size[1] {
-            Some(&self.space_domain[x * self.space_size[1] + y])
-        } else {
-            None
+    pub fn update_mode(&self) -> UpdateMode {
+        self.update_mode
+    }
+
+    pub fn memory_footprint(&self, footprint: &mut MemoryFootprint) {
+        self.fields.memory_footprint(footprint);
+        footprint.add("flags", &self.flags);
+        footprint.add("fluid cells", &self.fluid_cells);
+        footprint.add("boundary cells", &self.boundary_cells);
+    }
+
+    pub fn fields(&self) -> &Fields {
+        &self.fields
+    }
+
+    // Gathered copy of every cell, prefer fields for sweeps over one quantity
+    pub fn cells(&self) -> Vec<Cell> {
+        self.fields.to_cells()
+    }
+
+    // Borrowed views in cell order, x * ny + y
+    pub fn pressure_iter(&self) -> impl Iterator<Item = f32> + '_ {
+        self.fields.pressure.iter().copied()
+    }
+
+    pub fn velocity_iter(&self) -> impl Iterator<Item = [f32; 2]> + '_ {
+        self.fields.velocities()
+    }
+
+    pub fn index(&self, x: usize, y: usize) -> usize {
+        x * self.space_size[1] + y
+    }
+
+    pub fn get_cell(&self, x: usize, y: usize) -> Cell {
+        self.fields.cell(self.index(x, y))
+    }
+
+    pub fn try_get_cell(&self, x: usize, y: usize) -> Option<Cell> {
+        (x < self.space_size[0] && y < self.space_size[1]).then(|| self.get_cell(x, y))
+    }
+
+    pub fn cell_type(&self, x: usize, y: usize) -> CellType {
+        self.fields.cell_type[self.index(x, y)]
+    }
+
+    pub fn flags(&self, x: usize, y: usize) -> CellFlags {
+        self.flags[self.index(x, y)]
+    }
+
+    // Flags of every cell in cell order, x * ny + y
+    pub fn cell_flags(&self) -> &[CellFlags] {
+        &self.flags
+    }
+
+    // Indices of the fluid cells in cell order
+    pub fn fluid_cells(&self) -> &[usize] {
+        &self.fluid_cells
+    }
+
+    // Indices of the boundary condition cells in cell order
+    pub fn boundary_cells(&self) -> &[usize] {
+        &self.boundary_cells
+    }
+
+    // Positions in fluid_cells of the fluid cells in the columns
+    pub fn fluid_cell_range(&self, columns: Range<usize>) -> Range<usize> {
+        let ny = self.space_size[1];
+        let start = self
+            .fluid_cells
+            .partition_point(|&index| index < columns.start * ny);
+        let end = self
+            .fluid_cells
+            .partition_point(|&index| index < columns.end * ny);
+        start..end
+    }
+
+    // (x, y) of a cell index, the inverse of index
+    pub fn position(&self, index: usize) -> (usize, usize) {
+        (index / self.space_size[1], index % self.space_size[1])
+    }
+
+    pub fn velocity(&self, x: usize, y: usize) -> [f32; 2] {
+        let index = self.index(x, y);
+        [self.fields.u[index], self.fields.v[index]]
+    }
+
+    pub fn u(&self, x: usize, y: usize) -> f32 {
+        self.fields.u[self.index(x, y)]
+    }
+
+    pub fn v(&self, x: usize, y: usize) -> f32 {
+        self.fields.v[self.index(x, y)]
+    }
+
+    pub fn pressure(&self, x: usize, y: usize) -> f32 {
+        self.fields.pressure[self.index(x, y)]
+    }
+
+    pub fn rhs(&self, x: usize, y: usize) -> f32 {
+        self.fields.rhs[self.index(x, y)]
+    }
+
+    pub fn f(&self, x: usize, y: usize) -> f32 {
+        self.fields.f[self.index(x, y)]
+    }
+
+    pub fn g(&self, x: usize, y: usize) -> f32 {
+        self.fields.g[self.index(x, y)]
+    }
+
+    pub fn psi(&self, x: usize, y: usize) -> f32 {
+        self.fields.psi[self.index(x, y)]
+    }
+
+    pub fn dye(&self, x: usize, y: usize) -> f32 {
+        self.fields.dye[self.index(x, y)]
+    }
+
+    // Position of the cell at offset from (x, y), None outside the domain
+    pub fn neighbor_position(
+        &self,
+        x: usize,
+        y: usize,
+        offset: [isize; 2],
+    ) -> Option<(usize, usize)> {
+        let neighbor_x = x.checked_add_signed(offset[0])?;
+        let neighbor_y = y.checked_add_signed(offset[1])?;
+        (neighbor_x < self.space_size[0] && neighbor_y < self.space_size[1])
+            .then_some((neighbor_x, neighbor_y))
+    }
+
+    // Cell at offset from (x, y). Outside the domain a void ghost cell at rest is
+    // returned, so stencils next to the edge read it instead of panicking.
+    pub fn neighbor(&self, x: usize, y: usize, offset: [isize; 2]) -> Cell {
+        match self.neighbor_position(x, y, offset) {
+            Some((neighbor_x, neighbor_y)) => self.get_cell(neighbor_x, neighbor_y),
+            None => GHOST,
+        }
+    }
+
+    // Type of the cell at offset from (x, y), VoidCell outside the domain
+    pub fn neighbor_type(&self, x: usize, y: usize, offset: [isize; 2]) -> CellType {
+        match self.neighbor_position(x, y, offset) {
+            Some((neighbor_x, neighbor_y)) => self.cell_type(neighbor_x, neighbor_y),
+            None => GHOST.cell_type,
+        }
+    }
+
+    // Flags of the cell at offset from (x, y), empty outside the domain
+    pub fn neighbor_flags(&self, x: usize, y: usize, offset: [isize; 2]) -> CellFlags {
+        match self.neighbor_position(x, y, offset) {
+            Some((neighbor_x, neighbor_y)) => self.flags(neighbor_x, neighbor_y),
+            None => CellFlags::EMPTY,
+        }
+    }
+
+    // One quantity of the cell at offset from (x, y), reading a single array. The
+    // ghost cell holds zeros.
+    pub(crate) fn neighbor_value(
+        &self,
+        values: &[f32],
+        x: usize,
+        y: usize,
+        offset: [isize; 2],
+    ) -> f32 {
+        match self.neighbor_position(x, y, offset) {
+            Some((neighbor_x, neighbor_y)) => values[self.index(neighbor_x, neighbor_y)],
+            None => 0.0,
         }
     }
 
+    // Positions and cells sharing a face with (x, y) inside the domain
+    pub fn face_neighbors(
+        &self,
+        x: usize,
+        y: usize,
+    ) -> impl Iterator<Item = ((usize, usize), Cell)> + '_ {
+        FACE_NEIGHBORS.into_iter().filter_map(move |offset| {
+            self.neighbor_position(x, y, offset)
+                .map(|(nx, ny)| ((nx, ny), self.get_cell(nx, ny)))
+        })
+    }
+
     pub fn get_centered_velocity(&self, x: usize, y: usize) -> [f32; 2] {
-        match self.get_cell(x, y).cell_type {
+        match self.cell_type(x, y) {
             CellType::FluidCell => [
-                (self.get_cell(x, y).velocity[0] + self.get_cell(x - 1, y).velocity[0]) / 2.0,
-                (self.get_cell(x, y).velocity[1] + self.get_cell(x, y - 1).velocity[1]) / 2.0,
+                (self.u(x, y) + self.neighbor_value(&self.fields.u, x, y, [-1, 0])) / 2.0,
+                (self.v(x, y) + self.neighbor_value(&self.fields.v, x, y, [0, -1])) / 2.0,
             ],
             _ => panic!("Can only call get_centered_velocity on Fluid Cell"),
         }
     }
 }
 
+// Typed mutable access, one quantity of one cell
+impl SpaceDomain {
+    pub fn set_cell(&mut self, x: usize, y: usize, cell: Cell) {
+        let index = self.index(x, y);
+        let is_retyped = self.fields.cell_type[index] != cell.cell_type;
+        self.fields.set_cell(index, &cell);
+        if is_retyped {
+            self.update_flags_around(x, y);
+        }
+    }
+
+    pub fn set_cell_type(&mut self, x: usize, y: usize, cell_type: CellType) {
+        let index = self.index(x, y);
+        if self.fields.cell_type[index] != cell_type {
+            self.fields.cell_type[index] = cell_type;
+            self.update_flags_around(x, y);
+        }
+    }
+
+    pub fn set_velocity(&mut self, x: usize, y: usize, velocity: [f32; 2]) {
+        let index = self.index(x, y);
+        self.fields.u[index] = velocity[0];
+        self.fields.v[index] = velocity[1];
+    }
+
+    // u for axis 0, v for axis 1
+    pub fn set_velocity_component(&mut self, x: usize, y: usize, axis: usize, velocity: f32) {
+        let index = self.index(x, y);
+        match axis {
+            0 => self.fields.u[index] = velocity,
+            _ => self.fields.v[index] = velocity,
+        }
+    }
+
+    pub fn u_mut(&mut self, x: usize, y: usize) -> &mut f32 {
+        let index = self.index(x, y);
+        &mut self.fields.u[index]
+    }
+
+    pub fn v_mut(&mut self, x: usize, y: usize) -> &mut f32 {
+        let index = self.index(x, y);
+        &mut self.fields.v[index]
+    }
+
+    pub fn pressure_mut(&mut self, x: usize, y: usize) -> &mut f32 {
+        let index = self.index(x, y);
+        &mut self.fields.pressure[index]
+    }
+
+    pub fn rhs_mut(&mut self, x: usize, y: usize) -> &mut f32 {
+        let index = self.index(x, y);
+        &mut self.fields.rhs[index]
+    }
+
+    pub fn f_mut(&mut self, x: usize, y: usize) -> &mut f32 {
+        let index = self.index(x, y);
+        &mut self.fields.f[index]
+    }
+
+    pub fn g_mut(&mut self, x: usize, y: usize) -> &mut f32 {
+        let index = self.index(x, y);
+        &mut self.fields.g[index]
+    }
+
+    pub fn psi_mut(&mut self, x: usize, y: usize) -> &mut f32 {
+        let index = self.index(x, y);
+        &mut self.fields.psi[index]
+    }
+
+    pub fn dye_mut(&mut self, x: usize, y: usize) -> &mut f32 {
+        let index = self.index(x, y);
+        &mut self.fields.dye[index]
+    }
+
+    // Whole arrays for sweeps, their lengths and the cell types must not change
+    pub(crate) fn fields_mut(&mut self) -> &mut Fields {
+        &mut self.fields
+    }
+
+    // fields_mut alongside the flags it leaves as they are
+    pub(crate) fn fields_and_flags_mut(&mut self) -> (&mut Fields, &[CellFlags]) {
+        (&mut self.fields, &self.flags)
+    }
+}
+
 // Update functions
 impl SpaceDomain {
-    pub fn get_cell_mut(&mut self, x: usize, y: usize) -> &mut Cell {
-        &mut self.space_domain[x * self.space_size[1] + y]
+    pub fn set_update_mode(&mut self, update_mode: UpdateMode) {
+        self.update_mode = update_mode;
     }
 
-    pub fn update_psi(&mut self) {
-        self.psi_range = [0.0, 0.0];
+    // How pressure_range and speed_range follow the field between timesteps
+    pub fn set_range_modes(&mut self, pressure: RangeMode, speed: RangeMode) {
+        self.pressure_color_range.set_mode(pressure);
+        self.speed_color_range.set_mode(speed);
+    }
+
+    pub fn set_reduction(&mut self, reduction: Reduction) {
+        self.reduction = reduction;
+    }
+
+    pub fn set_cells(&mut self, cells: &[Cell]) {
+        assert_eq!(cells.len(), self.fields.len(), "cell count mismatch");
+        self.fields = Fields::from_cells(cells);
+        self.update_flags();
+    }
 
+    // Recompute the flags and cell lists of every cell from the cell types
+    pub fn update_flags(&mut self) {
+        self.try_update_flags()
+            .unwrap_or_else(|error| panic!("{error}"));
+    }
+
+    // Refills the flags and cell indices in their buffers, growing them fallibly
+    fn try_update_flags(&mut self) -> Result<(), AllocationError> {
+        let space_size = self.space_size;
+        let mut flags = std::mem::take(&mut self.flags);
+        flags.clear();
+        memory::try_reserve(&mut flags, self.fields.len(), space_size, "flags")?;
+        flags.extend(
+            (0..space_size[0])
+                .flat_map(|x| (0..space_size[1]).map(move |y| (x, y)))
+                .map(|(x, y)| self.compute_flags(x, y)),
+        );
+        self.flags = flags;
+
+        for (cells, flag, buffer) in [
+            (&mut self.fluid_cells, CellFlags::FLUID, "fluid cells"),
+            (
+                &mut self.boundary_cells,
+                CellFlags::BOUNDARY,
+                "boundary cells",
+            ),
+        ] {
+            let count = self
+                .flags
+                .iter()
+                .filter(|flags| flags.intersects(flag))
+                .count();
+            cells.clear();
+            memory::try_reserve(cells, count, space_size, buffer)?;
+            cells.extend((0..self.flags.len()).filter(|&index| self.flags[index].intersects(flag)));
+        }
+        Ok(())
+    }
+
+    // The flags of (x, y) and its face neighbors, after its type changed
+    fn update_flags_around(&mut self, x: usize, y: usize) {
+        let index = self.index(x, y);
+        let previous = self.flags[index];
+        for offset in [[0, 0]].into_iter().chain(FACE_NEIGHBORS) {
+            if let Some((nx, ny)) = self.neighbor_position(x, y, offset) {
+                let index = self.index(nx, ny);
+                self.flags[index] = self.compute_flags(nx, ny);
+            }
+        }
+
+        let flags = self.flags[index];
+        for (cells, flag) in [
+            (&mut self.fluid_cells, CellFlags::FLUID),
+            (&mut self.boundary_cells, CellFlags::BOUNDARY),
+        ] {
+            match (previous.intersects(flag), flags.intersects(flag)) {
+                (false, true) => {
+                    let position = cells.partition_point(|&other| other < index);
+                    cells.insert(position, index);
+                }
+                (true, false) => {
+                    let position = cells.partition_point(|&other| other < index);
+                    cells.remove(position);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn compute_flags(&self, x: usize, y: usize) -> CellFlags {
+        let mut flags = CellFlags::of_type(self.cell_type(x, y));
+        for (offset, neighbor_flag) in FACE_NEIGHBORS
+            .into_iter()
+            .zip(CellFlags::FACE_NEIGHBOR_FLAGS)
+        {
+            if let CellType::FluidCell = self.neighbor_type(x, y, offset) {
+                flags |= neighbor_flag;
+            }
+        }
+        if !flags.is_fluid() && flags.intersects(CellFlags::FLUID_NEIGHBORS) {
+            flags |= CellFlags::SURFACE;
+        }
+        flags
+    }
+
+    pub fn update_psi(&mut self) {
         (0..self.space_size[0]).for_each(|x| {
-            self.get_cell_mut(x, 0).psi = 0.0;
+            *self.psi_mut(x, 0) = 0.0;
 
             for y in 1..self.space_size[1] {
-                match self.get_cell(x, y).cell_type {
-                    CellType::FluidCell => {
-                        self.get_cell_mut(x, y).psi = self.get_cell(x, y - 1).psi
-                            + self.get_cell(x, y).velocity[0] * self.delta_space[1];
-                        if self.get_cell(x, y).psi < self.psi_range[0] {
-                            self.psi_range[0] = self.get_cell(x, y).psi;
-                        }
-                        if self.get_cell(x, y).psi > self.psi_range[1] {
-                            self.psi_range[1] = self.get_cell(x, y).psi;
-                        }
-                    }
-                    _ => {
-                        self.get_cell_mut(x, y).psi = self.get_cell(x, y - 1).psi;
-                    }
-                }
+                *self.psi_mut(x, y) = if self.flags(x, y).is_fluid() {
+                    self.psi(x, y - 1) + self.u(x, y) * self.delta_space[1]
+                } else {
+                    self.psi(x, y - 1)
+                };
             }
         });
     }
 
     pub fn update_pressure_and_speed_range(&mut self) {
-        let (min_pressure, max_pressure, min_speed, max_speed) = self
-            .space_domain
+        let [pressure, speed] = self.instantaneous_ranges();
+        self.pressure_range = self.pressure_color_range.update(pressure);
+        self.speed_range = self.speed_color_range.update(speed);
+    }
+
+    // The ranges update_pressure_and_speed_range would set, leaving the history of
+    // running and smoothed color ranges as it is
+    pub fn pending_pressure_and_speed_range(&self) -> [[f32; 2]; 2] {
+        let [pressure, speed] = self.instantaneous_ranges();
+        [
+            self.pressure_color_range.clone().update(pressure),
+            self.speed_color_range.clone().update(speed),
+        ]
+    }
+
+    fn instantaneous_ranges(&self) -> [[f32; 2]; 2] {
+        let fields = &self.fields;
+        let (pressures, speeds): (Vec<f32>, Vec<f32>) = self
+            .fluid_cells
             .iter()
-            .filter(|cell| matches!(cell.cell_type, CellType::FluidCell))
-            .map(|cell| {
-                let pressure = cell.pressure;
-                let speed = (cell.velocity[0].powi(2) + cell.velocity[1].powi(2)).sqrt();
+            .map(|&index| {
+                let pressure = fields.pressure[index];
+                let speed = (fields.u[index].powi(2) + fields.v[index].powi(2)).sqrt();
                 (pressure, speed)
             })
-            .fold(
-                (
-                    f32::INFINITY,
-                    f32::NEG_INFINITY,
-                    f32::INFINITY,
-                    f32::NEG_INFINITY,
-                ),
-                |(min_p, max_p, min_s, max_s), (pressure, speed)| {
-                    (
-                        min_p.min(pressure),
-                        max_p.max(pressure),
-                        min_s.min(speed),
-                        max_s.max(speed),
-                    )
-                },
-            );
+            .unzip();
+        [
+            reduction::min_max(&pressures, self.reduction),
+            reduction::min_max(&speeds, self.reduction),
+        ]
+    }
 
-        self.pressure_range = [min_pressure, max_pressure];
-        self.speed_range = [min_speed, max_speed];
+    pub fn set_inflow_velocity(&mut self, velocity: [f32; 2]) {
+        let fields = &mut self.fields;
+        for &index in &self.boundary_cells {
+            if self.flags[index].contains(CellFlags::INFLOW) {
+                fields.u[index] = velocity[0];
+                fields.v[index] = velocity[1];
+            }
+        }
     }
 
     // Set u, v, boundary conditions
     pub fn update_boundary_velocities(&mut self) {
-        let x_size = self.space_size[0];
-        let y_size = self.space_size[1];
-
-        for x in 0..x_size {
-            for y in 0..y_size {
-                if let CellType::BoundaryConditionCell(bc_cell_type) = self.get_cell(x, y).cell_type
-                {
-                    let left_cell_type: Option<CellType> =
-                        (x > 0).then(|| self.get_cell(x - 1, y).cell_type);
-                    let right_cell_type: Option<CellType> =
-                        (x + 1 < self.space_size[0]).then(|| self.get_cell(x + 1, y).cell_type);
-                    let bottom_cell_type: Option<CellType> =
-                        (y > 0).then(|| self.get_cell(x, y - 1).cell_type);
-                    let top_cell_type: Option<CellType> =
-                        (y + 1 < self.space_size[1]).then(|| self.get_cell(x, y + 1).cell_type);
-
-                    match bc_cell_type {
-                        BoundaryConditionCell::NoSlipCell {
-                            boundary_condition_velocity,
-                        } => {
-                            if let Some(CellType::FluidCell) = left_cell_type {
-                                self.get_cell_mut(x - 1, y).velocity[0] = 0.0;
-
-                                if let Some(CellType::FluidCell) = top_cell_type {
-                                    self.get_cell_mut(x, y).velocity[1] = 0.0;
-                                } else {
-                                    self.get_cell_mut(x, y).velocity[1] = 2.0
-                                        * boundary_condition_velocity[1]
-                                        - self.get_cell(x - 1, y).velocity[1];
-                                }
-                            }
-
-                            if let Some(CellType::FluidCell) = right_cell_type {
-                                self.get_cell_mut(x, y).velocity[0] = 0.0;
-
-                                if let Some(CellType::FluidCell) = top_cell_type {
-                                    self.get_cell_mut(x, y).velocity[1] = 0.0;
-                                } else {
-                                    self.get_cell_mut(x, y).velocity[1] = 2.0
-                                        * boundary_condition_velocity[1]
-                                        - self.get_cell(x + 1, y).velocity[1];
-                                }
-                            }
-
-                            if let Some(CellType::FluidCell) = bottom_cell_type {
-                                self.get_cell_mut(x, y - 1).velocity[1] = 0.0;
-
-                                if let Some(CellType::FluidCell) = right_cell_type {
-                                    self.get_cell_mut(x, y).velocity[0] = 0.0;
-                                } else {
-                                    self.get_cell_mut(x, y).velocity[0] = 2.0
-                                        * boundary_condition_velocity[0]
-                                        - self.get_cell(x, y - 1).velocity[0];
-                                }
-                            }
-
-                            if let Some(CellType::FluidCell) = top_cell_type {
-                                self.get_cell_mut(x, y).velocity[1] = 0.0;
-
-                                if let Some(CellType::FluidCell) = right_cell_type {
-                                    self.get_cell_mut(x, y).velocity[0] = 0.0;
-                                } else {
-                                    self.get_cell_mut(x, y).velocity[0] = 2.0
-                                        * boundary_condition_velocity[0]
-                                        - self.get_cell(x, y + 1).velocity[0];
-                                }
-                            }
-                        }
-
-                        BoundaryConditionCell::FreeSlipCell => {
-                            if let Some(CellType::FluidCell) = left_cell_type {
-                                self.get_cell_mut(x - 1, y).velocity[0] = 0.0;
-
-                                if let Some(CellType::FluidCell) = top_cell_type {
-                                    self.get_cell_mut(x, y).velocity[1] = 0.0;
-                                } else {
-                                    self.get_cell_mut(x, y).velocity[1] =
-                                        self.get_cell(x - 1, y).velocity[1];
-                                }
-                            }
-
-                            if let Some(CellType::FluidCell) = right_cell_type {
-                                self.get_cell_mut(x, y).velocity[0] = 0.0;
-
-                                if let Some(CellType::FluidCell) = top_cell_type {
-                                    self.get_cell_mut(x, y).velocity[1] = 0.0;
-                                } else {
-                                    self.get_cell_mut(x, y).velocity[1] =
-                                        self.get_cell(x + 1, y).velocity[1];
-                                }
-                            }
-
-                            if let Some(CellType::FluidCell) = bottom_cell_type {
-                                self.get_cell_mut(x, y - 1).velocity[1] = 0.0;
-
-                                if let Some(CellType::FluidCell) = right_cell_type {
-                                    self.get_cell_mut(x, y).velocity[0] = 0.0;
-                                } else {
-                                    self.get_cell_mut(x, y).velocity[0] =
-                                        self.get_cell(x, y - 1).velocity[0];
-                                }
-                            }
-
-                            if let Some(CellType::FluidCell) = top_cell_type {
-                                self.get_cell_mut(x, y).velocity[1] = 0.0;
-
-                                if let Some(CellType::FluidCell) = right_cell_type {
-                                    self.get_cell_mut(x, y).velocity[0] = 0.0;
-                                } else {
-                                    self.get_cell_mut(x, y).velocity[0] =
-                                        self.get_cell(x, y + 1).velocity[0];
-                                }
-                            }
-                        }
-
-                        BoundaryConditionCell::OutFlowCell => {
-                            if let Some(CellType::FluidCell) = left_cell_type {
-                                self.get_cell_mut(x - 1, y).velocity[0] =
-                                    self.get_cell(x - 2, y).velocity[0];
-                                self.get_cell_mut(x, y).velocity[1] =
-                                    self.get_cell(x - 1, y).velocity[1];
-                            }
-
-                            if let Some(CellType::FluidCell) = right_cell_type {
-                                self.get_cell_mut(x, y).velocity = [
-                                    self.get_cell(x + 1, y).velocity[0],
-                                    self.get_cell(x + 1, y).velocity[1],
-                                ];
-                            }
-                            if let Some(CellType::FluidCell) = bottom_cell_type {
-                                self.get_cell_mut(x, y).velocity[0] =
-                                    self.get_cell(x, y - 1).velocity[0];
-                                self.get_cell_mut(x, y - 1).velocity[1] =
-                                    self.get_cell(x, y - 2).velocity[1];
-                            }
-                            if let Some(CellType::FluidCell) = top_cell_type {
-                                self.get_cell_mut(x, y).velocity = [
-                                    self.get_cell(x, y + 1).velocity[0],
-                                    self.get_cell(x, y + 1).velocity[1],
-                                ];
-                            }
-                        }
-
-                        BoundaryConditionCell::InflowCell => {
-                            if let Some(CellType::FluidCell) = left_cell_type {
-                                self.get_cell_mut(x - 1, y).velocity[0] =
-                                    self.get_cell(x, y).velocity[0];
-                            }
-                            if let Some(CellType::FluidCell) = right_cell_type {}
-                            if let Some(CellType::FluidCell) = bottom_cell_type {
-                                self.get_cell_mut(x, y - 1).velocity[1] =
-                                    self.get_cell(x, y).velocity[1];
-                            }
-                            if let Some(CellType::FluidCell) = top_cell_type {}
-                        }
-                    }
+        let cells: Vec<(usize, usize)> = self
+            .boundary_cells
+            .iter()
+            .map(|&index| self.position(index))
+            .collect();
+        self.update_boundary_velocities_for(&cells);
+    }
+
+    // Set u, v, boundary conditions of the given cells, non boundary cells are skipped.
+    // When double buffered, the result doesn't depend on the order of the cells.
+    pub fn update_boundary_velocities_for(&mut self, cells: &[(usize, usize)]) {
+        let previous: Option<[Vec<f32>; 2]> = match self.update_mode {
+            UpdateMode::DoubleBuffered => Some([self.fields.u.clone(), self.fields.v.clone()]),
+            UpdateMode::InPlace => None,
+        };
+
+        for &(x, y) in cells {
+            let flags = self.flags(x, y);
+            // Only cells next to the fluid act on it, periodic cells copy theirs anyway
+            if !flags.intersects(CellFlags::SURFACE | CellFlags::PERIODIC) {
+                continue;
+            }
+            let CellType::BoundaryConditionCell(condition) = self.cell_type(x, y) else {
+                continue;
+            };
+            if condition == BoundaryConditionCell::PeriodicCell {
+                let (source_x, source_y) = self.periodic_source(x, y);
+                self.set_velocity(x, y, self.read_velocity(&previous, source_x, source_y));
+                continue;
+            }
+
+            // Faces shared with the fluid, each owned by the cell on its left or bottom
+            for (offset, neighbor_flag) in FACE_NEIGHBORS
+                .into_iter()
+                .zip(CellFlags::FACE_NEIGHBOR_FLAGS)
+            {
+                if !flags.contains(neighbor_flag) {
+                    continue;
                 }
+                let axis = if offset[0] != 0 { 0 } else { 1 };
+                let rule = condition.rule(axis).unwrap();
+                let Some((fluid_x, fluid_y)) = self.neighbor_position(x, y, offset) else {
+                    continue;
+                };
+                // The far face of a fluid cell on the edge belongs to the ghost cell
+                let (face, far) = if offset[axis] < 0 {
+                    (
+                        (fluid_x, fluid_y),
+                        self.neighbor_position(fluid_x, fluid_y, offset),
+                    )
+                } else {
+                    ((x, y), Some((fluid_x, fluid_y)))
+                };
+                let velocity =
+                    rule.normal_velocity(self.read_velocity(&previous, x, y)[axis], || {
+                        far.map_or(0.0, |(far_x, far_y)| {
+                            self.read_velocity(&previous, far_x, far_y)[axis]
+                        })
+                    });
+                self.set_velocity_component(face.0, face.1, axis, velocity);
             }
+
+            // Own faces along the wall, which stand in for the fluid across it
+            for axis in [0, 1] {
+                let Some(fluid) = self.tangential_fluid_velocity(&previous, x, y, axis) else {
+                    continue;
+                };
+                if let Some(ghost) = condition.rule(1 - axis).unwrap().tangential_velocity(fluid) {
+                    self.set_velocity_component(x, y, axis, ghost);
+                }
+            }
+        }
+    }
+
+    // Fluid velocity along axis next to the own face of the boundary cell (x, y) along
+    // axis, the u faces below and above it or the v faces left and right of it. None
+    // when that face is shared with the fluid or has no fluid beside it. With fluid on
+    // both sides, faces between two fluid cells win over faces against walls, whose
+    // ghost nothing reads, and two of a kind are averaged so the result doesn't depend
+    // on the orientation of the wall.
+    fn tangential_fluid_velocity(
+        &self,
+        previous: &Option<[Vec<f32>; 2]>,
+        x: usize,
+        y: usize,
+        axis: usize,
+    ) -> Option<f32> {
+        let along: [isize; 2] = if axis == 0 { [1, 0] } else { [0, 1] };
+        let is_fluid =
+            |offset: [isize; 2]| matches!(self.neighbor_type(x, y, offset), CellType::FluidCell);
+        if is_fluid(along) {
+            return None;
+        }
+        let across: [[isize; 2]; 2] = if axis == 0 {
+            [[0, -1], [0, 1]]
+        } else {
+            [[-1, 0], [1, 0]]
+        };
+        let is_open = |side: &[isize; 2]| is_fluid([side[0] + along[0], side[1] + along[1]]);
+        let sides = across.into_iter().filter(|&side| is_fluid(side));
+        let is_any_open = sides.clone().any(|side| is_open(&side));
+        let (sum, count) = sides.filter(|side| !is_any_open || is_open(side)).fold(
+            (0.0, 0),
+            |(sum, count), side| {
+                let (fx, fy) = self.neighbor_position(x, y, side).unwrap();
+                (sum + self.read_velocity(previous, fx, fy)[axis], count + 1)
+            },
+        );
+        (count > 0).then(|| sum / count as f32)
+    }
+
+    // Velocity as seen by a sweep, from before the sweep when double buffered
+    fn read_velocity(&self, previous: &Option<[Vec<f32>; 2]>, x: usize, y: usize) -> [f32; 2] {
+        match previous {
+            Some([u, v]) => [u[self.index(x, y)], v[self.index(x, y)]],
+            None => self.velocity(x, y),
         }
     }
 
     // Set F, G, p boundary conditions
     pub fn update_boundary_pressures_and_fg(&mut self) {
-        let x_size = self.space_size[0];
-        let y_size = self.space_size[1];
-
-        for x in 0..x_size {
-            for y in 0..y_size {
-                if let CellType::BoundaryConditionCell(_) = self.get_cell(x, y).cell_type {
-                    self.get_cell_mut(x, y).pressure = 0.0;
-                    let mut neighboring_fluid_count = 0;
-
-                    let neighboring_cells = [
-                        (x > 0, x.wrapping_sub(1), y),
-                        (x + 1 < self.space_size[0], x + 1, y),
-                        (y > 0, x, y.wrapping_sub(1)),
-                        (y + 1 < self.space_size[1], x, y + 1),
+        for position in 0..self.boundary_cells.len() {
+            let (x, y) = self.position(self.boundary_cells[position]);
+            let flags = self.flags(x, y);
+            // Copied over from the other side instead, see update_periodic_cells
+            if flags.contains(CellFlags::PERIODIC) {
+                continue;
+            }
+            let CellType::BoundaryConditionCell(condition) = self.cell_type(x, y) else {
+                continue;
+            };
+            *self.pressure_mut(x, y) = 0.0;
+            let mut neighboring_fluid_count = 0;
+
+            for (offset, neighbor_flag) in FACE_NEIGHBORS
+                .into_iter()
+                .zip(CellFlags::FACE_NEIGHBOR_FLAGS)
+            {
+                if flags.contains(neighbor_flag) {
+                    let Some((nx, ny)) = self.neighbor_position(x, y, offset) else {
+                        continue;
+                    };
+                    let axis = if offset[0] != 0 { 0 } else { 1 };
+                    let pressure = condition
+                        .rule(axis)
+                        .unwrap()
+                        .pressure(self.pressure(nx, ny));
+                    *self.pressure_mut(x, y) += pressure;
+                    neighboring_fluid_count += 1;
+
+                    match offset {
+                        [-1, 0] => *self.f_mut(nx, ny) = self.u(nx, ny),
+                        [1, 0] => *self.f_mut(x, y) = self.u(x, y),
+                        [0, -1] => *self.g_mut(nx, ny) = self.v(nx, ny),
+                        _ => *self.g_mut(x, y) = self.v(x, y),
+                    }
+                }
+            }
+
+            if neighboring_fluid_count != 0 {
+                *self.pressure_mut(x, y) /= neighboring_fluid_count as f32;
+            }
+        }
+    }
+}
+
+// Named boundary segments
+impl SpaceDomain {
+    // Replaces a segment of the same name
+    pub fn add_segment(&mut self, segment: BoundarySegment) {
+        assert!(
+            segment
+                .cells
+                .iter()
+                .all(|&(x, y)| x < self.space_size[0] && y < self.space_size[1]),
+            "segment outside the domain"
+        );
+        self.remove_segment(&segment.name);
+        self.segments.push(segment);
+    }
+
+    // Returns whether a segment was removed
+    pub fn remove_segment(&mut self, name: &str) -> bool {
+        let count = self.segments.len();
+        self.segments.retain(|segment| segment.name != name);
+        self.segments.len() != count
+    }
+
+    pub fn segments(&self) -> &[BoundarySegment] {
+        &self.segments
+    }
+
+    pub fn segment(&self, name: &str) -> Option<&BoundarySegment> {
+        self.segments.iter().find(|segment| segment.name == name)
+    }
+
+    // Turns every cell of the named segment into the boundary condition, returns
+    // whether there is such a segment
+    pub fn set_segment_condition(&mut self, name: &str, condition: BoundaryConditionCell) -> bool {
+        let Some(segment) = self.segment(name) else {
+            return false;
+        };
+        for (x, y) in segment.cells.clone() {
+            self.set_cell_type(x, y, CellType::BoundaryConditionCell(condition));
+        }
+        true
+    }
+}
+
+// Periodic boundaries
+impl SpaceDomain {
+    // Cell a periodic cell mirrors, wrapping around along the edges it lies on
+    pub fn periodic_source(&self, x: usize, y: usize) -> (usize, usize) {
+        let wrap = |i: usize, n: usize| {
+            if i == 0 {
+                n - 2
+            } else if i == n - 1 {
+                1
+            } else {
+                i
+            }
+        };
+        (wrap(x, self.space_size[0]), wrap(y, self.space_size[1]))
+    }
+
+    // Copy velocity, pressure, F, G and dye into the periodic cells
+    pub fn update_periodic_cells(&mut self) {
+        for position in 0..self.boundary_cells.len() {
+            let (x, y) = self.position(self.boundary_cells[position]);
+            if self.flags(x, y).contains(CellFlags::PERIODIC) {
+                let (source_x, source_y) = self.periodic_source(x, y);
+                let source = self.get_cell(source_x, source_y);
+                self.set_velocity(x, y, source.velocity);
+                *self.pressure_mut(x, y) = source.pressure;
+                *self.f_mut(x, y) = source.f;
+                *self.g_mut(x, y) = source.g;
+                *self.dye_mut(x, y) = source.dye;
+            }
+        }
+    }
+}
+
+// Vorticity
+impl SpaceDomain {
+    // Vorticity at the top right corner of cell (x, y)
+    pub fn vorticity(&self, x: usize, y: usize) -> f32 {
+        if x + 1 >= self.space_size[0] || y + 1 >= self.space_size[1] {
+            return 0.0;
+        }
+        (self.v(x + 1, y) - self.v(x, y)) / self.delta_space[0]
+            - (self.u(x, y + 1) - self.u(x, y)) / self.delta_space[1]
+    }
+
+    // Vorticity averaged from the four corners of cell (x, y)
+    pub fn centered_vorticity(&self, x: usize, y: usize) -> f32 {
+        if x == 0 || y == 0 {
+            return 0.0;
+        }
+        (self.vorticity(x, y)
+            + self.vorticity(x - 1, y)
+            + self.vorticity(x, y - 1)
+            + self.vorticity(x - 1, y - 1))
+            / 4.0
+    }
+
+    // Cell centered vorticity confinement force epsilon * h * (N x omega),
+    // where N is the normalized gradient of |omega|
+    pub fn vorticity_confinement_force(&self, epsilon: f32) -> Vec<[f32; 2]> {
+        let [x_size, y_size] = self.space_size;
+        let h = self.delta_space[0].min(self.delta_space[1]);
+
+        let vorticity: Vec<f32> = (0..x_size)
+            .flat_map(|x| (0..y_size).map(move |y| (x, y)))
+            .map(|(x, y)| self.centered_vorticity(x, y))
+            .collect();
+        let magnitude = |x: usize, y: usize| vorticity[x * y_size + y].abs();
+
+        let mut force = vec![[0.0; 2]; x_size * y_size];
+        for x in 1..x_size - 1 {
+            for y in 1..y_size - 1 {
+                if let CellType::FluidCell = self.cell_type(x, y) {
+                    let gradient = [
+                        (magnitude(x + 1, y) - magnitude(x - 1, y)) / (2.0 * self.delta_space[0]),
+                        (magnitude(x, y + 1) - magnitude(x, y - 1)) / (2.0 * self.delta_space[1]),
                     ];
+                    let length = (gradient[0].powi(2) + gradient[1].powi(2)).sqrt() + 1e-10;
+                    let omega = vorticity[x * y_size + y];
 
-                    for (has_neighbor, nx, ny) in neighboring_cells.iter() {
-                        if *has_neighbor {
-                            if let CellType::FluidCell = self.get_cell(*nx, *ny).cell_type {
-                                self.get_cell_mut(x, y).pressure +=
-                                    self.get_cell(*nx, *ny).pressure;
-                                neighboring_fluid_count += 1;
-
-                                match (*nx as i32 - x as i32, *ny as i32 - y as i32) {
-                                    (-1, 0) => {
-                                        self.get_cell_mut(*nx, *ny).f =
-                                            self.get_cell(*nx, *ny).velocity[0]
-                                    }
-                                    (1, 0) => {
-                                        self.get_cell_mut(x, y).f = self.get_cell(x, y).velocity[0]
-                                    }
-                                    (0, -1) => {
-                                        self.get_cell_mut(*nx, *ny).g =
-                                            self.get_cell(*nx, *ny).velocity[1]
-                                    }
-                                    (0, 1) => {
-                                        self.get_cell_mut(x, y).g = self.get_cell(x, y).velocity[1]
-                                    }
-                                    _ => (),
-                                }
-                            }
-                        }
-                    }
+                    force[x * y_size + y] = [
+                        epsilon * h * gradient[1] / length * omega,
+                        -epsilon * h * gradient[0] / length * omega,
+                    ];
+                }
+            }
+        }
+        force
+    }
+}
 
-                    if neighboring_fluid_count != 0 {
-                        self.get_cell_mut(x, y).pressure /= neighboring_fluid_count as f32;
-                    }
+// Interpolation on the staggered grid
+// Physical coordinates have their origin at the bottom left corner of cell (0, 0)
+impl SpaceDomain {
+    pub fn interpolate_u(&self, position: [f32; 2]) -> f32 {
+        // u is stored on the right edge of each cell
+        self.interpolate(position, [1.0, 0.5], |x, y| self.u(x, y))
+    }
+
+    pub fn interpolate_v(&self, position: [f32; 2]) -> f32 {
+        // v is stored on the top edge of each cell
+        self.interpolate(position, [0.5, 1.0], |x, y| self.v(x, y))
+    }
+
+    // Both components from their own faces, clamped to the domain without checking
+    // what the position lies in
+    pub fn interpolate_velocity(&self, position: [f32; 2]) -> [f32; 2] {
+        interpolate_velocity(
+            self.space_size,
+            self.delta_space,
+            &self.fields.u,
+            &self.fields.v,
+            position,
+        )
+    }
+
+    // Velocity at a point of a fluid cell, see velocity_at
+    pub fn velocity_at(&self, position: [f32; 2]) -> Result<[f32; 2], VelocityError> {
+        velocity_at(&self.fields, self.space_size, self.delta_space, position)
+    }
+
+    pub fn interpolate_dye(&self, position: [f32; 2]) -> f32 {
+        self.interpolate(position, [0.5, 0.5], |x, y| self.dye(x, y))
+    }
+
+    pub fn interpolate_pressure(&self, position: [f32; 2]) -> f32 {
+        self.interpolate(position, [0.5, 0.5], |x, y| self.pressure(x, y))
+    }
+
+    pub fn interpolate_psi(&self, position: [f32; 2]) -> f32 {
+        // psi is stored on the top right corner of each cell
+        self.interpolate(position, [1.0, 1.0], |x, y| self.psi(x, y))
+    }
+
+    pub fn interpolate_vorticity(&self, position: [f32; 2]) -> f32 {
+        self.interpolate(position, [1.0, 1.0], |x, y| self.vorticity(x, y))
+    }
+
+    fn interpolate(
+        &self,
+        position: [f32; 2],
+        offset: [f32; 2],
+        value: impl Fn(usize, usize) -> f32,
+    ) -> f32 {
+        bilinear(self.space_size, self.delta_space, position, offset, value)
+    }
+}
+
+// Bilinear interpolation of a value sampled at (x + offset) * delta_space for every
+// cell (x, y), clamped to the domain
+pub(crate) fn bilinear(
+    space_size: [usize; 2],
+    delta_space: [f32; 2],
+    position: [f32; 2],
+    offset: [f32; 2],
+    value: impl Fn(usize, usize) -> f32,
+) -> f32 {
+    bilinear_weights(space_size, delta_space, position, offset)
+        .into_iter()
+        .map(|(x, y, weight)| weight * value(x, y))
+        .sum()
+}
+
+// Velocity at position from staggered u and v arrays indexed x * ny + y, as
+// SpaceDomain::interpolate_u and interpolate_v do for the current fields
+pub(crate) fn interpolate_velocity(
+    space_size: [usize; 2],
+    delta_space: [f32; 2],
+    u: &[f32],
+    v: &[f32],
+    position: [f32; 2],
+) -> [f32; 2] {
+    let ny = space_size[1];
+    [
+        bilinear(space_size, delta_space, position, [1.0, 0.5], |x, y| {
+            u[x * ny + y]
+        }),
+        bilinear(space_size, delta_space, position, [0.5, 1.0], |x, y| {
+            v[x * ny + y]
+        }),
+    ]
+}
+
+// Velocity at position in meters interpolated from the staggered faces, or why the
+// point has none. Points on the top and right edges of the domain belong to the
+// last cells.
+pub fn velocity_at(
+    fields: &Fields,
+    space_size: [usize; 2],
+    delta_space: [f32; 2],
+    position: [f32; 2],
+) -> Result<[f32; 2], VelocityError> {
+    let [nx, ny] = space_size;
+    let [x, y] = [0, 1].map(|axis| position[axis] / delta_space[axis]);
+    if !((0.0..=nx as f32).contains(&x) && (0.0..=ny as f32).contains(&y)) {
+        return Err(VelocityError::OutsideDomain(position));
+    }
+    let cell = ((x as usize).min(nx - 1), (y as usize).min(ny - 1));
+    let cell_type = fields.cell_type[cell.0 * ny + cell.1];
+    if !matches!(cell_type, CellType::FluidCell) {
+        return Err(VelocityError::NotFluid { cell, cell_type });
+    }
+    Ok(interpolate_velocity(
+        space_size,
+        delta_space,
+        &fields.u,
+        &fields.v,
+        position,
+    ))
+}
+
+// The four samples of bilinear with their weights, which also spread a value at
+// position onto the samples
+pub(crate) fn bilinear_weights(
+    space_size: [usize; 2],
+    delta_space: [f32; 2],
+    position: [f32; 2],
+    offset: [f32; 2],
+) -> [(usize, usize, f32); 4] {
+    let fx = (position[0] / delta_space[0] - offset[0]).clamp(0.0, (space_size[0] - 1) as f32);
+    let fy = (position[1] / delta_space[1] - offset[1]).clamp(0.0, (space_size[1] - 1) as f32);
+
+    let x0 = (fx.floor() as usize).min(space_size[0] - 2);
+    let y0 = (fy.floor() as usize).min(space_size[1] - 2);
+    let tx = fx - x0 as f32;
+    let ty = fy - y0 as f32;
+
+    [
+        (x0, y0, (1.0 - tx) * (1.0 - ty)),
+        (x0 + 1, y0, tx * (1.0 - ty)),
+        (x0, y0 + 1, (1.0 - tx) * ty),
+        (x0 + 1, y0 + 1, tx * ty),
+    ]
+}
+
+// Semi-Lagrangian advection
+impl SpaceDomain {
+    // Trace the u sample point of cell (x, y) back in time and interpolate u there
+    pub fn semi_lagrangian_u(&self, x: usize, y: usize, delta_time: f32) -> f32 {
+        let position = [
+            (x as f32 + 1.0) * self.delta_space[0],
+            (y as f32 + 0.5) * self.delta_space[1],
+        ];
+        let velocity = [self.u(x, y), self.interpolate_v(position)];
+
+        self.interpolate_u([
+            position[0] - delta_time * velocity[0],
+            position[1] - delta_time * velocity[1],
+        ])
+    }
+
+    // Trace the v sample point of cell (x, y) back in time and interpolate v there
+    pub fn semi_lagrangian_v(&self, x: usize, y: usize, delta_time: f32) -> f32 {
+        let position = [
+            (x as f32 + 0.5) * self.delta_space[0],
+            (y as f32 + 1.0) * self.delta_space[1],
+        ];
+        let velocity = [self.interpolate_u(position), self.v(x, y)];
+
+        self.interpolate_v([
+            position[0] - delta_time * velocity[0],
+            position[1] - delta_time * velocity[1],
+        ])
+    }
+
+    // Dye is a passive scalar stored at cell centers and only moves inside fluid cells
+    pub fn advect_dye(&mut self, delta_time: f32) {
+        let dye: Vec<f32> = (0..self.space_size[0])
+            .flat_map(|x| (0..self.space_size[1]).map(move |y| (x, y)))
+            .map(|(x, y)| {
+                if !self.flags(x, y).is_fluid() {
+                    return self.dye(x, y);
                 }
+                let position = [
+                    (x as f32 + 0.5) * self.delta_space[0],
+                    (y as f32 + 0.5) * self.delta_space[1],
+                ];
+                let velocity = [self.interpolate_u(position), self.interpolate_v(position)];
+                self.interpolate_dye([
+                    position[0] - delta_time * velocity[0],
+                    position[1] - delta_time * velocity[1],
+                ])
+            })
+            .collect();
+
+        self.fields.dye = dye;
+    }
+}
+
+// Interactive forcing
+// Brushes have a gaussian profile and only touch velocities between two fluid cells,
+// so boundary conditions stay valid and they can be applied between timesteps
+impl SpaceDomain {
+    pub fn add_velocity(&mut self, position: [f32; 2], radius: f32, delta_velocity: [f32; 2]) {
+        self.brush_velocity(position, radius, |velocity, component, weight| {
+            velocity + weight * delta_velocity[component]
+        });
+    }
+
+    pub fn blend_velocity(&mut self, position: [f32; 2], radius: f32, target_velocity: [f32; 2]) {
+        self.brush_velocity(position, radius, |velocity, component, weight| {
+            velocity + weight * (target_velocity[component] - velocity)
+        });
+    }
+
+    pub fn add_dye(&mut self, position: [f32; 2], radius: f32, amount: f32) {
+        for (x, y) in self.cells_near(position, radius) {
+            if let CellType::FluidCell = self.cell_type(x, y) {
+                let center = [
+                    (x as f32 + 0.5) * self.delta_space[0],
+                    (y as f32 + 0.5) * self.delta_space[1],
+                ];
+                *self.dye_mut(x, y) += amount * brush_weight(center, position, radius);
+            }
+        }
+    }
+
+    fn brush_velocity(
+        &mut self,
+        position: [f32; 2],
+        radius: f32,
+        update: impl Fn(f32, usize, f32) -> f32,
+    ) {
+        for (x, y) in self.cells_near(position, radius) {
+            if !matches!(self.cell_type(x, y), CellType::FluidCell) {
+                continue;
+            }
+
+            if let Some(CellType::FluidCell) = self.try_get_cell(x + 1, y).map(|c| c.cell_type) {
+                let face = [
+                    (x as f32 + 1.0) * self.delta_space[0],
+                    (y as f32 + 0.5) * self.delta_space[1],
+                ];
+                let weight = brush_weight(face, position, radius);
+                let u = self.u(x, y);
+                *self.u_mut(x, y) = update(u, 0, weight);
+            }
+
+            if let Some(CellType::FluidCell) = self.try_get_cell(x, y + 1).map(|c| c.cell_type) {
+                let face = [
+                    (x as f32 + 0.5) * self.delta_space[0],
+                    (y as f32 + 1.0) * self.delta_space[1],
+                ];
+                let weight = brush_weight(face, position, radius);
+                let v = self.v(x, y);
+                *self.v_mut(x, y) = update(v, 1, weight);
+            }
+        }
+    }
+
+    // Cells inside the bounding box of twice the brush radius
+    fn cells_near(&self, position: [f32; 2], radius: f32) -> Vec<(usize, usize)> {
+        let range = |axis: usize| {
+            let low = ((position[axis] - 2.0 * radius) / self.delta_space[axis]).floor();
+            let high = ((position[axis] + 2.0 * radius) / self.delta_space[axis]).ceil();
+            (low.max(0.0) as usize)..(high.max(0.0) as usize).min(self.space_size[axis])
+        };
+
+        range(0)
+            .flat_map(|x| range(1).map(move |y| (x, y)))
+            .collect()
+    }
+}
+
+fn brush_weight(point: [f32; 2], position: [f32; 2], radius: f32) -> f32 {
+    let distance_squared = (point[0] - position[0]).powi(2) + (point[1] - position[1]).powi(2);
+    (-distance_squared / radius.powi(2)).exp()
+}
+
+// Geometry editing
+impl SpaceDomain {
+    // Turn a fluid cell into a no-slip obstacle or back, returns whether the cell changed.
+    // Cells on the edge of the domain and void cells are left untouched.
+    pub fn set_obstacle(&mut self, x: usize, y: usize, is_obstacle: bool) -> bool {
+        if x == 0 || y == 0 || x + 1 >= self.space_size[0] || y + 1 >= self.space_size[1] {
+            return false;
+        }
+
+        let cell_type = match (self.cell_type(x, y), is_obstacle) {
+            (CellType::FluidCell, true) => {
+                CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                    boundary_condition_velocity: [0.0, 0.0],
+                })
             }
+            (CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell { .. }), false) => {
+                CellType::FluidCell
+            }
+            _ => return false,
+        };
+
+        let pressure = self.fluid_neighbor_average_pressure(x, y);
+        let psi = self.psi(x, y - 1);
+        self.set_cell(
+            x,
+            y,
+            Cell {
+                cell_type,
+                pressure,
+                psi,
+                ..Default::default()
+            },
+        );
+
+        // Faces of the changed cell start at rest, the boundary conditions take it from there
+        *self.u_mut(x - 1, y) = 0.0;
+        *self.v_mut(x, y - 1) = 0.0;
+        *self.f_mut(x - 1, y) = 0.0;
+        *self.g_mut(x, y - 1) = 0.0;
+
+        true
+    }
+
+    pub(crate) fn fluid_neighbor_average_pressure(&self, x: usize, y: usize) -> f32 {
+        let (sum, count) = self
+            .face_neighbors(x, y)
+            .filter(|(_, cell)| matches!(cell.cell_type, CellType::FluidCell))
+            .fold((0.0, 0), |(sum, count), (_, cell)| {
+                (sum + cell.pressure, count + 1)
+            });
+
+        if count == 0 {
+            0.0
+        } else {
+            sum / count as f32
+        }
+    }
+}
+
+// Values of boundary cells as seen from one fluid neighbour. A wall one cell thick has
+// fluid on opposite sides, which would need different ghost values on each side than
+// the single one stored in the cell.
+impl SpaceDomain {
+    // u of the cell below or above the fluid cell (x, y). When that face lies inside a
+    // wall, the ghost value mirrors the fluid velocity.
+    pub fn neighbor_u(&self, x: usize, y: usize, offset: [isize; 2]) -> f32 {
+        let is_face_in_wall = !matches!(
+            self.neighbor_type(x, y, [offset[0] + 1, offset[1]]),
+            CellType::FluidCell
+        );
+        let fluid_u = self.u(x, y);
+        match self.neighbor_type(x, y, offset) {
+            CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                boundary_condition_velocity,
+            }) if is_face_in_wall => 2.0 * boundary_condition_velocity[0] - fluid_u,
+            CellType::BoundaryConditionCell(BoundaryConditionCell::FreeSlipCell)
+                if is_face_in_wall =>
+            {
+                fluid_u
+            }
+            _ => self.neighbor_value(&self.fields.u, x, y, offset),
+        }
+    }
+
+    // v of the cell left or right of the fluid cell (x, y)
+    pub fn neighbor_v(&self, x: usize, y: usize, offset: [isize; 2]) -> f32 {
+        let is_face_in_wall = !matches!(
+            self.neighbor_type(x, y, [offset[0], offset[1] + 1]),
+            CellType::FluidCell
+        );
+        let fluid_v = self.v(x, y);
+        match self.neighbor_type(x, y, offset) {
+            CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                boundary_condition_velocity,
+            }) if is_face_in_wall => 2.0 * boundary_condition_velocity[1] - fluid_v,
+            CellType::BoundaryConditionCell(BoundaryConditionCell::FreeSlipCell)
+                if is_face_in_wall =>
+            {
+                fluid_v
+            }
+            _ => self.neighbor_value(&self.fields.v, x, y, offset),
+        }
+    }
+
+    // Cell whose pressure stands for the neighbour at offset from the fluid cell (x, y):
+    // fluid and periodic neighbours themselves, None for walls and the ghost cell, which
+    // take the pressure of the fluid cell so no pressure gradient points into them
+    pub fn pressure_neighbor_position(
+        &self,
+        x: usize,
+        y: usize,
+        offset: [isize; 2],
+    ) -> Option<(usize, usize)> {
+        let (neighbor_x, neighbor_y) = self.neighbor_position(x, y, offset)?;
+        match self.cell_type(neighbor_x, neighbor_y) {
+            CellType::BoundaryConditionCell(BoundaryConditionCell::PeriodicCell)
+            | CellType::FluidCell => Some((neighbor_x, neighbor_y)),
+            _ => None,
+        }
+    }
+
+    // Pressure of the cell at offset from the fluid cell (x, y), see
+    // pressure_neighbor_position
+    pub fn neighbor_pressure(&self, x: usize, y: usize, offset: [isize; 2]) -> f32 {
+        match self.pressure_neighbor_position(x, y, offset) {
+            Some((neighbor_x, neighbor_y)) => self.pressure(neighbor_x, neighbor_y),
+            None => self.pressure(x, y),
         }
     }
 }
@@ -360,11 +1438,11 @@ impl SpaceDomain {
 // Spatial derivatives
 impl SpaceDomain {
     pub fn d2udx2(&self, x: usize, y: usize) -> f32 {
-        match self.get_cell(x, y).cell_type {
+        match self.cell_type(x, y) {
             CellType::FluidCell => {
-                let ui = self.get_cell(x, y).velocity[0];
-                let uip1 = self.get_cell(x + 1, y).velocity[0];
-                let uim1 = self.get_cell(x - 1, y).velocity[0];
+                let ui = self.u(x, y);
+                let uip1 = self.neighbor_value(&self.fields.u, x, y, [1, 0]);
+                let uim1 = self.neighbor_value(&self.fields.u, x, y, [-1, 0]);
                 (uip1 - 2.0 * ui + uim1) / (self.delta_space[0].powi(2))
             }
             _ => panic!("derivative on non fluid cell"),
@@ -372,11 +1450,11 @@ impl SpaceDomain {
     }
 
     pub fn d2udy2(&self, x: usize, y: usize) -> f32 {
-        match self.get_cell(x, y).cell_type {
+        match self.cell_type(x, y) {
             CellType::FluidCell => {
-                let uj = self.get_cell(x, y).velocity[0];
-                let ujp1 = self.get_cell(x, y + 1).velocity[0];
-                let ujm1 = self.get_cell(x, y - 1).velocity[0];
+                let uj = self.u(x, y);
+                let ujp1 = self.neighbor_u(x, y, [0, 1]);
+                let ujm1 = self.neighbor_u(x, y, [0, -1]);
                 (ujp1 - 2.0 * uj + ujm1) / (self.delta_space[1].powi(2))
             }
             _ => panic!("derivative on non fluid cell"),
@@ -384,11 +1462,11 @@ impl SpaceDomain {
     }
 
     pub fn d2vdx2(&self, x: usize, y: usize) -> f32 {
-        match self.get_cell(x, y).cell_type {
+        match self.cell_type(x, y) {
             CellType::FluidCell => {
-                let vi = self.get_cell(x, y).velocity[1];
-                let vip1 = self.get_cell(x + 1, y).velocity[1];
-                let vim1 = self.get_cell(x - 1, y).velocity[1];
+                let vi = self.v(x, y);
+                let vip1 = self.neighbor_v(x, y, [1, 0]);
+                let vim1 = self.neighbor_v(x, y, [-1, 0]);
 
                 (vip1 - 2.0 * vi + vim1) / (self.delta_space[0].powi(2))
             }
@@ -397,11 +1475,11 @@ impl SpaceDomain {
     }
 
     pub fn d2vdy2(&self, x: usize, y: usize) -> f32 {
-        match self.get_cell(x, y).cell_type {
+        match self.cell_type(x, y) {
             CellType::FluidCell => {
-                let vj = self.get_cell(x, y).velocity[1];
-                let vjp1 = self.get_cell(x, y + 1).velocity[1];
-                let vjm1 = self.get_cell(x, y - 1).velocity[1];
+                let vj = self.v(x, y);
+                let vjp1 = self.neighbor_value(&self.fields.v, x, y, [0, 1]);
+                let vjm1 = self.neighbor_value(&self.fields.v, x, y, [0, -1]);
 
                 (vjp1 - 2.0 * vj + vjm1) / (self.delta_space[1].powi(2))
             }
@@ -410,11 +1488,11 @@ impl SpaceDomain {
     }
 
     pub fn du2dx(&self, x: usize, y: usize) -> f32 {
-        match self.get_cell(x, y).cell_type {
+        match self.cell_type(x, y) {
             CellType::FluidCell => {
-                let ui = self.get_cell(x, y).velocity[0];
-                let uip1 = self.get_cell(x + 1, y).velocity[0];
-                let uim1 = self.get_cell(x - 1, y).velocity[0];
+                let ui = self.u(x, y);
+                let uip1 = self.neighbor_value(&self.fields.u, x, y, [1, 0]);
+                let uim1 = self.neighbor_value(&self.fields.u, x, y, [-1, 0]);
 
                 ((ui + uip1).powi(2) - (uim1 + ui).powi(2)) / 4.0 / self.delta_space[0]
                     + self.gamma
@@ -427,11 +1505,11 @@ impl SpaceDomain {
     }
 
     pub fn dv2dy(&self, x: usize, y: usize) -> f32 {
-        match self.get_cell(x, y).cell_type {
+        match self.cell_type(x, y) {
             CellType::FluidCell => {
-                let vj = self.get_cell(x, y).velocity[1];
-                let vjp1 = self.get_cell(x, y + 1).velocity[1];
-                let vjm1 = self.get_cell(x, y - 1).velocity[1];
+                let vj = self.v(x, y);
+                let vjp1 = self.neighbor_value(&self.fields.v, x, y, [0, 1]);
+                let vjm1 = self.neighbor_value(&self.fields.v, x, y, [0, -1]);
 
                 ((vj + vjp1).powi(2) - (vjm1 + vj).powi(2)) / 4.0 / self.delta_space[1]
                     + self.gamma
@@ -444,19 +1522,19 @@ impl SpaceDomain {
     }
 
     pub fn duvdx(&self, x: usize, y: usize) -> f32 {
-        match self.get_cell(x, y).cell_type {
+        match self.cell_type(x, y) {
             CellType::FluidCell => {
-                let uij = self.get_cell(x, y).velocity[0];
-                let vij = self.get_cell(x, y).velocity[1];
+                let uij = self.u(x, y);
+                let vij = self.v(x, y);
 
-                let vip1 = self.get_cell(x + 1, y).velocity[1];
-                let vim1 = self.get_cell(x - 1, y).velocity[1];
+                let vip1 = self.neighbor_v(x, y, [1, 0]);
+                let vim1 = self.neighbor_v(x, y, [-1, 0]);
 
-                let uim1 = self.get_cell(x - 1, y).velocity[0];
+                let uim1 = self.neighbor_value(&self.fields.u, x, y, [-1, 0]);
 
-                let ujp1 = self.get_cell(x, y + 1).velocity[0];
+                let ujp1 = self.neighbor_value(&self.fields.u, x, y, [0, 1]);
 
-                let uim1jp1 = self.get_cell(x - 1, y + 1).velocity[0];
+                let uim1jp1 = self.neighbor_value(&self.fields.u, x, y, [-1, 1]);
 
                 ((uij + ujp1) * (vij + vip1) - (uim1 + uim1jp1) * (vim1 + vij))
                     / 4.0
@@ -472,19 +1550,19 @@ impl SpaceDomain {
     }
 
     pub fn duvdy(&self, x: usize, y: usize) -> f32 {
-        match self.get_cell(x, y).cell_type {
+        match self.cell_type(x, y) {
             CellType::FluidCell => {
-                let uij = self.get_cell(x, y).velocity[0];
-                let vij = self.get_cell(x, y).velocity[1];
+                let uij = self.u(x, y);
+                let vij = self.v(x, y);
 
-                let ujp1 = self.get_cell(x, y + 1).velocity[0];
-                let ujm1 = self.get_cell(x, y - 1).velocity[0];
+                let ujp1 = self.neighbor_u(x, y, [0, 1]);
+                let ujm1 = self.neighbor_u(x, y, [0, -1]);
 
-                let vjm1 = self.get_cell(x, y - 1).velocity[1];
+                let vjm1 = self.neighbor_value(&self.fields.v, x, y, [0, -1]);
 
-                let vip1 = self.get_cell(x + 1, y).velocity[1];
+                let vip1 = self.neighbor_value(&self.fields.v, x, y, [1, 0]);
 
-                let vip1jm1 = self.get_cell(x + 1, y - 1).velocity[1];
+                let vip1jm1 = self.neighbor_value(&self.fields.v, x, y, [1, -1]);
 
                 ((vij + vip1) * (uij + ujp1) - (vjm1 + vip1jm1) * (ujm1 + uij))
                     / 4.0
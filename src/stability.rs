@@ -0,0 +1,118 @@
+use crate::cell::CellType;
+use crate::colormap::Colormap;
+use crate::field::{FieldView, Location};
+use crate::frame_renderer::{self, Image};
+use crate::solver::FluidSolver;
+
+// Local stability numbers of the explicit scheme for a timestep, per fluid cell.
+// The Courant number is max(|u| dt / dx, |v| dt / dy) over the faces of a cell and
+// must stay below one; the diffusion number dt / Re (1 / dx^2 + 1 / dy^2) must stay
+// below one half, see DeltaTimeLimits. Other cells hold zero.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StabilityField {
+    pub delta_time: f32, // seconds
+    pub space_size: [usize; 2],
+    pub delta_space: [f32; 2], // meters
+    pub cell_type: Vec<CellType>,
+    pub courant: Vec<f32>,
+    // The viscosity is uniform, so this is the same in every fluid cell
+    pub diffusion: Vec<f32>,
+    // Largest fraction of either limit, one or more is unstable
+    pub stability: Vec<f32>,
+}
+
+impl StabilityField {
+    pub fn new(solver: &dyn FluidSolver, reynolds: f32, delta_time: f32) -> Self {
+        assert!(
+            reynolds > 0.0 && delta_time > 0.0,
+            "reynolds number and timestep must be positive"
+        );
+        let [nx, ny] = solver.space_size();
+        let [dx, dy] = solver.delta_space();
+        let fields = solver.fields();
+        let diffusion = delta_time / reynolds * (1.0 / dx.powi(2) + 1.0 / dy.powi(2));
+
+        let mut courant = vec![0.0; nx * ny];
+        let mut diffusion_field = vec![0.0; nx * ny];
+        let mut stability = vec![0.0; nx * ny];
+        for index in 0..nx * ny {
+            if !matches!(fields.cell_type[index], CellType::FluidCell) {
+                continue;
+            }
+            // Fluid cells are never on the domain edge
+            let u = fields.u[index].abs().max(fields.u[index - ny].abs());
+            let v = fields.v[index].abs().max(fields.v[index - 1].abs());
+            courant[index] = (u * delta_time / dx).max(v * delta_time / dy);
+            diffusion_field[index] = diffusion;
+            stability[index] = courant[index].max(2.0 * diffusion);
+        }
+        Self {
+            delta_time,
+            space_size: [nx, ny],
+            delta_space: [dx, dy],
+            cell_type: fields.cell_type.clone(),
+            courant,
+            diffusion: diffusion_field,
+            stability,
+        }
+    }
+
+    pub fn courant(&self) -> FieldView<'_> {
+        self.view(&self.courant)
+    }
+
+    pub fn diffusion(&self) -> FieldView<'_> {
+        self.view(&self.diffusion)
+    }
+
+    pub fn stability(&self) -> FieldView<'_> {
+        self.view(&self.stability)
+    }
+
+    // Cell with the largest Courant number, None without fluid cells
+    pub fn max_courant(&self) -> Option<([usize; 2], f32)> {
+        self.courant().max_cell()
+    }
+
+    // Cell closest to either limit, None without fluid cells
+    pub fn most_marginal(&self) -> Option<([usize; 2], f32)> {
+        self.stability().max_cell()
+    }
+
+    pub fn is_stable(&self) -> bool {
+        self.stability()
+            .max()
+            .is_none_or(|stability| stability < 1.0)
+    }
+
+    // Timestep at which the most marginal cell reaches its limit, both numbers grow
+    // linearly with it. Infinite without fluid cells.
+    pub fn limiting_delta_time(&self) -> f32 {
+        self.delta_time / self.stability().max().unwrap_or(0.0)
+    }
+
+    // The stability per cell over [0, max(1, most marginal)], so every unstable cell
+    // stands out against the stable ones
+    pub fn heatmap(&self, colormap: &Colormap, pixels_per_cell: usize) -> Image {
+        assert!(pixels_per_cell > 0, "pixels per cell must be positive");
+        let max = self.stability().max().unwrap_or(0.0).max(1.0);
+        frame_renderer::rasterize(
+            colormap,
+            &self.stability,
+            &self.cell_type,
+            [0.0, max],
+            self.space_size,
+            pixels_per_cell,
+        )
+    }
+
+    fn view<'a>(&'a self, values: &'a [f32]) -> FieldView<'a> {
+        FieldView::from_values(
+            Location::CellCentre,
+            values,
+            &self.cell_type,
+            self.space_size,
+            self.delta_space,
+        )
+    }
+}
@@ -0,0 +1,591 @@
+use crate::cell::BoundaryConditionCell;
+use crate::cell::Cell;
+use crate::cell::CellType;
+use crate::colormap::RangeMode;
+use crate::memory::{self, AllocationError};
+use crate::presets;
+use crate::simulation::{ITR_MAX, OMEGA, POISSON_EPSILON};
+use crate::solver::{Checkpoint, FluidSolver};
+use crate::space_domain::{Fields, SpaceDomain, FACE_NEIGHBORS};
+
+use std::fmt;
+
+// Why a preset can't be run in the stream function formulation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamfunctionError {
+    // Flow across periodic edges can carry a net flux, so psi isn't periodic
+    PeriodicCell((usize, usize)),
+    Allocation(AllocationError),
+}
+
+impl From<AllocationError> for StreamfunctionError {
+    fn from(error: AllocationError) -> Self {
+        StreamfunctionError::Allocation(error)
+    }
+}
+
+impl fmt::Display for StreamfunctionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamfunctionError::PeriodicCell(cell) => write!(
+                f,
+                "cell {cell:?} is periodic, which the stream function formulation doesn't support"
+            ),
+            StreamfunctionError::Allocation(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for StreamfunctionError {}
+
+// Stream function and vorticity live on the top right corner of each cell,
+// which is where the Navier-Stokes solver's psi is reported as well
+#[derive(Clone, Copy)]
+enum Corner {
+    // All four surrounding cells are fluid
+    Interior,
+    // On a wall or obstacle, indexes the wall bodies
+    Wall(usize),
+    // Stream function fixed by the inflow profile
+    Inflow,
+    // Zero normal gradient, copies the corner at the given index
+    Outflow(usize),
+    // Not surrounded by cells
+    Outside,
+}
+
+struct WallBody {
+    psi: f32,
+    // Floating bodies (obstacles) take the mean stream function of the fluid around them
+    is_floating: bool,
+}
+
+// 2D stream function-vorticity formulation, no pressure is computed
+pub struct StreamfunctionVorticity {
+    space_domain: SpaceDomain,
+    corners: Vec<Corner>,
+    bodies: Vec<WallBody>,
+    psi: Vec<f32>,
+    vorticity: Vec<f32>,
+
+    delta_time: f32, // seconds
+    reynolds: f32,
+    gamma: f32, // upwind blend, 0 <= gamma <= 1
+    time: f32,  // seconds
+}
+
+impl StreamfunctionVorticity {
+    pub fn from_preset(preset: presets::SimulationPreset) -> Self {
+        Self::try_from_preset(preset).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    pub fn try_from_preset(preset: presets::SimulationPreset) -> Result<Self, StreamfunctionError> {
+        let space_domain = preset.space_domain;
+        let [x_size, y_size] = space_domain.space_size();
+        if let Some(index) = space_domain
+            .fields()
+            .cell_type
+            .iter()
+            .position(|cell_type| {
+                matches!(
+                    cell_type,
+                    CellType::BoundaryConditionCell(BoundaryConditionCell::PeriodicCell)
+                )
+            })
+        {
+            return Err(StreamfunctionError::PeriodicCell((
+                index / y_size,
+                index % y_size,
+            )));
+        }
+        let delta_space = space_domain.delta_space();
+
+        // Integrate the initial u along y to get a consistent initial stream function
+        let space_size = [x_size, y_size];
+        let mut psi = memory::try_filled(x_size * y_size, 0.0, space_size, "psi")?;
+        for x in 0..x_size {
+            let mut running = 0.0;
+            for y in 0..y_size {
+                running += space_domain.u(x, y) * delta_space[1];
+                psi[x * y_size + y] = running;
+            }
+        }
+
+        let body_of_cell = label_wall_bodies(&space_domain)?;
+        let body_count = body_of_cell.iter().flatten().max().map_or(0, |&b| b + 1);
+        let has_inflow = space_domain.fields().cell_type.iter().any(|cell_type| {
+            matches!(
+                cell_type,
+                CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell)
+            )
+        });
+
+        let mut corners =
+            memory::try_filled(x_size * y_size, Corner::Outside, space_size, "corners")?;
+        // Per body: sum of psi and corner count, over all corners and over the
+        // corners touching an inflow cell
+        let mut body_stats = vec![[(0.0, 0); 2]; body_count];
+        for x in 0..x_size - 1 {
+            for y in 0..y_size - 1 {
+                let cells = [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)];
+                let cell_types = cells.map(|(cx, cy)| space_domain.cell_type(cx, cy));
+                let touches_inflow = cell_types.iter().any(|cell_type| {
+                    matches!(
+                        cell_type,
+                        CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell)
+                    )
+                });
+
+                let wall = cells
+                    .iter()
+                    .find_map(|&(cx, cy)| body_of_cell[cx * y_size + cy]);
+                let outflow = cells.iter().position(|&(cx, cy)| {
+                    matches!(
+                        space_domain.cell_type(cx, cy),
+                        CellType::BoundaryConditionCell(BoundaryConditionCell::OutFlowCell)
+                    )
+                });
+
+                corners[x * y_size + y] = if let Some(body) = wall {
+                    for (sum, count) in
+                        body_stats[body]
+                            .iter_mut()
+                            .take(if touches_inflow { 2 } else { 1 })
+                    {
+                        *sum += psi[x * y_size + y];
+                        *count += 1;
+                    }
+                    Corner::Wall(body)
+                } else if touches_inflow {
+                    Corner::Inflow
+                } else if let Some(position) = outflow {
+                    // Copy from the neighboring corner further inside the domain
+                    let (cx, cy) = cells[position];
+                    let source = if cx == x_size - 1 {
+                        (x - 1, y)
+                    } else if cx == 0 {
+                        (x + 1, y)
+                    } else if cy == y_size - 1 {
+                        (x, y - 1)
+                    } else {
+                        (x, y + 1)
+                    };
+                    Corner::Outflow(source.0 * y_size + source.1)
+                } else if cell_types
+                    .iter()
+                    .all(|cell_type| matches!(cell_type, CellType::FluidCell))
+                {
+                    Corner::Interior
+                } else {
+                    Corner::Outside
+                };
+            }
+        }
+
+        // Bodies attached to an inflow are fixed by the inflow profile
+        let bodies = body_stats
+            .iter()
+            .map(|&[(sum, count), (inflow_sum, inflow_count)]| WallBody {
+                psi: if inflow_count > 0 {
+                    inflow_sum / inflow_count as f32
+                } else {
+                    sum / count.max(1) as f32
+                },
+                is_floating: has_inflow && inflow_count == 0,
+            })
+            .collect();
+
+        let mut solver = Self {
+            space_domain,
+            corners,
+            bodies,
+            psi,
+            vorticity: memory::try_filled(x_size * y_size, 0.0, space_size, "vorticity")?,
+            delta_time: preset.delta_time,
+            reynolds: preset.reynolds,
+            gamma: 0.9,
+            time: 0.0,
+        };
+        solver.apply_psi_boundary_conditions();
+        solver.solve_poisson_psi();
+        solver.update_cells();
+        Ok(solver)
+    }
+
+    pub fn corner_psi(&self, x: usize, y: usize) -> f32 {
+        self.psi[x * self.space_domain.space_size()[1] + y]
+    }
+
+    pub fn corner_vorticity(&self, x: usize, y: usize) -> f32 {
+        self.vorticity[x * self.space_domain.space_size()[1] + y]
+    }
+
+    fn apply_psi_boundary_conditions(&mut self) {
+        for index in 0..self.corners.len() {
+            match self.corners[index] {
+                Corner::Wall(body) => self.psi[index] = self.bodies[body].psi,
+                Corner::Outflow(source) => self.psi[index] = self.psi[source],
+                _ => {}
+            }
+        }
+    }
+
+    // Mean stream function of the interior corners next to each floating body
+    fn update_floating_bodies(&mut self) {
+        let y_size = self.space_domain.space_size()[1];
+        let mut sums = vec![(0.0, 0); self.bodies.len()];
+
+        for (index, corner) in self.corners.iter().enumerate() {
+            if let Corner::Wall(body) = *corner {
+                if !self.bodies[body].is_floating {
+                    continue;
+                }
+                let (x, y) = (index / y_size, index % y_size);
+                for offset in FACE_NEIGHBORS {
+                    let Some((nx, ny)) = self.space_domain.neighbor_position(x, y, offset) else {
+                        continue;
+                    };
+                    let neighbor = nx * y_size + ny;
+                    if let Corner::Interior = self.corners[neighbor] {
+                        sums[body].0 += self.psi[neighbor];
+                        sums[body].1 += 1;
+                    }
+                }
+            }
+        }
+
+        for (body, (sum, count)) in self.bodies.iter_mut().zip(sums) {
+            if body.is_floating && count > 0 {
+                body.psi = sum / count as f32;
+            }
+        }
+    }
+
+    fn solve_poisson_psi(&mut self) {
+        let y_size = self.space_domain.space_size()[1];
+        let delta_space = self.space_domain.delta_space();
+        let dx2 = delta_space[0].powi(2);
+        let dy2 = delta_space[1].powi(2);
+
+        for _ in 0..ITR_MAX {
+            let mut residual_norm = 0.0;
+            let mut interior_count = 0;
+
+            for index in 0..self.corners.len() {
+                if let Corner::Interior = self.corners[index] {
+                    let laplacian = (self.psi[index + y_size] - 2.0 * self.psi[index]
+                        + self.psi[index - y_size])
+                        / dx2
+                        + (self.psi[index + 1] - 2.0 * self.psi[index] + self.psi[index - 1]) / dy2;
+                    residual_norm += (laplacian + self.vorticity[index]).powi(2);
+                    interior_count += 1;
+
+                    self.psi[index] = (1.0 - OMEGA) * self.psi[index]
+                        + OMEGA
+                            * ((self.psi[index + y_size] + self.psi[index - y_size]) / dx2
+                                + (self.psi[index + 1] + self.psi[index - 1]) / dy2
+                                + self.vorticity[index])
+                            / (2.0 / dx2 + 2.0 / dy2);
+                }
+            }
+
+            self.update_floating_bodies();
+            self.apply_psi_boundary_conditions();
+
+            let residual_norm = (residual_norm / interior_count.max(1) as f32).sqrt();
+            if residual_norm < POISSON_EPSILON {
+                break;
+            }
+        }
+    }
+
+    // Velocity at a corner from central differences of the stream function
+    fn corner_velocity(&self, index: usize) -> [f32; 2] {
+        let y_size = self.space_domain.space_size()[1];
+        let delta_space = self.space_domain.delta_space();
+        [
+            (self.psi[index + 1] - self.psi[index - 1]) / (2.0 * delta_space[1]),
+            -(self.psi[index + y_size] - self.psi[index - y_size]) / (2.0 * delta_space[0]),
+        ]
+    }
+
+    // Thom's formula on no-slip walls, zero vorticity on free slip walls and inflows
+    fn update_boundary_vorticity(&mut self) {
+        let [x_size, y_size] = self.space_domain.space_size();
+        let delta_space = self.space_domain.delta_space();
+
+        for x in 0..x_size - 1 {
+            for y in 0..y_size - 1 {
+                let index = x * y_size + y;
+                self.vorticity[index] = match self.corners[index] {
+                    Corner::Wall(_) => {
+                        let wall_velocity = self.wall_velocity(x, y);
+                        // Interior neighbor, distance, and the wall tangential velocity
+                        // expressed as the normal derivative of psi
+                        let normals = [
+                            ([1, 0], delta_space[0], -wall_velocity[1]),
+                            ([-1, 0], delta_space[0], wall_velocity[1]),
+                            ([0, 1], delta_space[1], wall_velocity[0]),
+                            ([0, -1], delta_space[1], -wall_velocity[0]),
+                        ];
+                        let (sum, count) = normals
+                            .iter()
+                            .filter_map(|&(offset, h, dpsi_dn)| {
+                                let (nx, ny) = self.space_domain.neighbor_position(x, y, offset)?;
+                                let neighbor = nx * y_size + ny;
+                                matches!(self.corners[neighbor], Corner::Interior)
+                                    .then_some((neighbor, h, dpsi_dn))
+                            })
+                            .fold((0.0, 0), |(sum, count), (neighbor, h, dpsi_dn)| {
+                                let omega = -2.0
+                                    * (self.psi[neighbor] - self.psi[index] - dpsi_dn * h)
+                                    / h.powi(2);
+                                (sum + omega, count + 1)
+                            });
+
+                        if count == 0 || self.is_free_slip(x, y) {
+                            0.0
+                        } else {
+                            sum / count as f32
+                        }
+                    }
+                    Corner::Outflow(source) => self.vorticity[source],
+                    Corner::Interior => self.vorticity[index],
+                    Corner::Inflow | Corner::Outside => 0.0,
+                };
+            }
+        }
+    }
+
+    fn surrounding_cells(&self, x: usize, y: usize) -> [Cell; 4] {
+        [
+            self.space_domain.get_cell(x, y),
+            self.space_domain.get_cell(x + 1, y),
+            self.space_domain.get_cell(x, y + 1),
+            self.space_domain.get_cell(x + 1, y + 1),
+        ]
+    }
+
+    fn wall_velocity(&self, x: usize, y: usize) -> [f32; 2] {
+        self.surrounding_cells(x, y)
+            .iter()
+            .find_map(|cell| match cell.cell_type {
+                CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                    boundary_condition_velocity,
+                }) => Some(boundary_condition_velocity),
+                _ => None,
+            })
+            .unwrap_or([0.0, 0.0])
+    }
+
+    fn is_free_slip(&self, x: usize, y: usize) -> bool {
+        self.surrounding_cells(x, y).iter().any(|cell| {
+            matches!(
+                cell.cell_type,
+                CellType::BoundaryConditionCell(BoundaryConditionCell::FreeSlipCell)
+            )
+        })
+    }
+
+    fn update_vorticity(&mut self) {
+        let y_size = self.space_domain.space_size()[1];
+        let delta_space = self.space_domain.delta_space();
+        let dx2 = delta_space[0].powi(2);
+        let dy2 = delta_space[1].powi(2);
+
+        let vorticity = &self.vorticity;
+        let updated: Vec<f32> = (0..self.corners.len())
+            .map(|index| match self.corners[index] {
+                Corner::Interior => {
+                    let omega = vorticity[index];
+                    let [east, west] = [vorticity[index + y_size], vorticity[index - y_size]];
+                    let [north, south] = [vorticity[index + 1], vorticity[index - 1]];
+                    let velocity = self.corner_velocity(index);
+
+                    let laplacian =
+                        (east - 2.0 * omega + west) / dx2 + (north - 2.0 * omega + south) / dy2;
+
+                    // Blend of central and upwind differences for the convective terms
+                    let convection = |velocity: f32, plus: f32, minus: f32, h: f32| {
+                        let central = (plus - minus) / (2.0 * h);
+                        let upwind = if velocity > 0.0 {
+                            (omega - minus) / h
+                        } else {
+                            (plus - omega) / h
+                        };
+                        velocity * ((1.0 - self.gamma) * central + self.gamma * upwind)
+                    };
+
+                    omega
+                        + self.delta_time
+                            * (laplacian / self.reynolds
+                                - convection(velocity[0], east, west, delta_space[0])
+                                - convection(velocity[1], north, south, delta_space[1]))
+                }
+                _ => vorticity[index],
+            })
+            .collect();
+        self.vorticity = updated;
+    }
+
+    // Staggered velocities and psi for the viewer
+    fn update_cells(&mut self) {
+        let [x_size, y_size] = self.space_domain.space_size();
+        let delta_space = self.space_domain.delta_space();
+
+        for x in 0..x_size {
+            for y in 0..y_size {
+                let cell_type = self.space_domain.cell_type(x, y);
+                if let CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell) =
+                    cell_type
+                {
+                    continue;
+                }
+                let index = x * y_size + y;
+
+                let mut velocity = self.space_domain.velocity(x, y);
+                if y > 0 {
+                    velocity[0] = (self.psi[index] - self.psi[index - 1]) / delta_space[1];
+                }
+                if x > 0 {
+                    velocity[1] = -(self.psi[index] - self.psi[index - y_size]) / delta_space[0];
+                }
+
+                self.space_domain.set_velocity(x, y, velocity);
+                *self.space_domain.pressure_mut(x, y) = 0.0;
+            }
+        }
+
+        self.space_domain.update_psi();
+        self.space_domain.update_pressure_and_speed_range();
+    }
+}
+
+impl FluidSolver for StreamfunctionVorticity {
+    fn iterate_one_timestep(&mut self) {
+        self.update_boundary_vorticity();
+        self.update_vorticity();
+        self.solve_poisson_psi();
+        self.update_cells();
+        self.time += self.delta_time;
+    }
+
+    fn time(&self) -> f32 {
+        self.time
+    }
+
+    fn delta_space(&self) -> [f32; 2] {
+        self.space_domain.delta_space()
+    }
+
+    fn space_size(&self) -> [usize; 2] {
+        self.space_domain.space_size()
+    }
+
+    fn get_cell(&self, x: usize, y: usize) -> Cell {
+        self.space_domain.get_cell(x, y)
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+        self.space_domain.cells()
+    }
+
+    fn fields(&self) -> &Fields {
+        self.space_domain.fields()
+    }
+
+    fn get_centered_velocity(&self, x: usize, y: usize) -> [f32; 2] {
+        self.space_domain.get_centered_velocity(x, y)
+    }
+
+    fn pressure_range(&self) -> [f32; 2] {
+        self.space_domain.pressure_range()
+    }
+
+    fn speed_range(&self) -> [f32; 2] {
+        self.space_domain.speed_range()
+    }
+
+    fn set_range_modes(&mut self, pressure: RangeMode, speed: RangeMode) {
+        self.space_domain.set_range_modes(pressure, speed);
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            time: self.time,
+            space_size: self.space_domain.space_size(),
+            cells: self.space_domain.cells(),
+            metadata: None,
+        }
+    }
+
+    // Vorticity is recomputed from the stored staggered velocities. The Poisson solve
+    // starts from the stored psi, so the result doesn't depend on the state before.
+    fn restore(&mut self, checkpoint: &Checkpoint) {
+        assert_eq!(
+            checkpoint.space_size,
+            self.space_domain.space_size(),
+            "checkpoint size mismatch"
+        );
+        self.space_domain.set_cells(&checkpoint.cells);
+        self.time = checkpoint.time;
+
+        let [x_size, y_size] = self.space_domain.space_size();
+        for x in 0..x_size {
+            for y in 0..y_size {
+                self.vorticity[x * y_size + y] = match self.corners[x * y_size + y] {
+                    Corner::Interior => self.space_domain.vorticity(x, y),
+                    _ => 0.0,
+                };
+                self.psi[x * y_size + y] = self.space_domain.psi(x, y);
+            }
+        }
+        self.apply_psi_boundary_conditions();
+        self.solve_poisson_psi();
+        self.update_cells();
+    }
+}
+
+// Label connected walls, obstacles and void cells (8-connectivity)
+fn label_wall_bodies(space_domain: &SpaceDomain) -> Result<Vec<Option<usize>>, AllocationError> {
+    let [x_size, y_size] = space_domain.space_size();
+    let is_wall = |x: usize, y: usize| {
+        matches!(
+            space_domain.cell_type(x, y),
+            CellType::VoidCell
+                | CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell { .. })
+                | CellType::BoundaryConditionCell(BoundaryConditionCell::FreeSlipCell)
+        )
+    };
+
+    let mut labels = memory::try_filled(x_size * y_size, None, [x_size, y_size], "wall bodies")?;
+    let mut body_count = 0;
+    for x in 0..x_size {
+        for y in 0..y_size {
+            if !is_wall(x, y) || labels[x * y_size + y].is_some() {
+                continue;
+            }
+
+            let mut stack = vec![(x, y)];
+            labels[x * y_size + y] = Some(body_count);
+            while let Some((cx, cy)) = stack.pop() {
+                for dx in -1..=1_i32 {
+                    for dy in -1..=1_i32 {
+                        let nx = cx as i32 + dx;
+                        let ny = cy as i32 + dy;
+                        if nx < 0 || ny < 0 || nx >= x_size as i32 || ny >= y_size as i32 {
+                            continue;
+                        }
+                        let (nx, ny) = (nx as usize, ny as usize);
+                        if is_wall(nx, ny) && labels[nx * y_size + ny].is_none() {
+                            labels[nx * y_size + ny] = Some(body_count);
+                            stack.push((nx, ny));
+                        }
+                    }
+                }
+            }
+            body_count += 1;
+        }
+    }
+    Ok(labels)
+}
@@ -0,0 +1,183 @@
+use crate::cell::CellType;
+use crate::field::Field;
+use crate::solver::FluidSolver;
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+const BOUNDARY_FILL: &str = "#808080";
+const VOID_FILL: &str = "#000000";
+const STREAMLINE_STROKE: &str = "#1f4e9c";
+
+pub struct SvgOptions {
+    pub pixels_per_meter: f32,
+    // Evenly spaced psi levels drawn as streamlines
+    pub streamline_count: usize,
+    pub stroke_width: f32, // pixels
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            pixels_per_meter: 100.0,
+            streamline_count: 30,
+            stroke_width: 1.0,
+        }
+    }
+}
+
+// Line segment in meters
+pub type Segment = [[f32; 2]; 2];
+
+// Domain outline, obstacles and psi isolines. In 2D incompressible flow the isolines
+// of the stream function are the streamlines.
+pub fn write_svg(
+    file: &mut impl Write,
+    solver: &dyn FluidSolver,
+    options: &SvgOptions,
+) -> io::Result<()> {
+    let [nx, ny] = solver.space_size();
+    let [dx, dy] = solver.delta_space();
+    let scale = options.pixels_per_meter;
+    let size = [nx as f32 * dx * scale, ny as f32 * dy * scale];
+    // Meters with y up to SVG pixels with y down
+    let to_svg = |point: [f32; 2]| [point[0] * scale, size[1] - point[1] * scale];
+
+    writeln!(
+        file,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+        size[0], size[1], size[0], size[1]
+    )?;
+    writeln!(
+        file,
+        r#"<rect x="0" y="0" width="{}" height="{}" fill="white" stroke="black" stroke-width="{}"/>"#,
+        size[0], size[1], options.stroke_width
+    )?;
+
+    // Non-fluid cells merged into vertical runs of the same fill per column
+    writeln!(file, r#"<g stroke="none">"#)?;
+    for x in 0..nx {
+        let mut y = 0;
+        while y < ny {
+            let Some(fill) = cell_fill(solver.get_cell(x, y).cell_type) else {
+                y += 1;
+                continue;
+            };
+            let start = y;
+            while y < ny && cell_fill(solver.get_cell(x, y).cell_type) == Some(fill) {
+                y += 1;
+            }
+            let top_left = to_svg([x as f32 * dx, y as f32 * dy]);
+            writeln!(
+                file,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{fill}"/>"#,
+                top_left[0],
+                top_left[1],
+                dx * scale,
+                (y - start) as f32 * dy * scale
+            )?;
+        }
+    }
+    writeln!(file, "</g>")?;
+
+    writeln!(
+        file,
+        r#"<g fill="none" stroke="{STREAMLINE_STROKE}" stroke-width="{}">"#,
+        options.stroke_width
+    )?;
+    for segments in streamlines(solver, options.streamline_count) {
+        if segments.is_empty() {
+            continue;
+        }
+        let mut path = String::new();
+        for [start, end] in segments {
+            let [start, end] = [to_svg(start), to_svg(end)];
+            write!(path, "M{} {}L{} {}", start[0], start[1], end[0], end[1]).unwrap();
+        }
+        writeln!(file, r#"<path d="{path}"/>"#)?;
+    }
+    writeln!(file, "</g>")?;
+
+    writeln!(file, "</svg>")
+}
+
+fn cell_fill(cell_type: CellType) -> Option<&'static str> {
+    match cell_type {
+        CellType::FluidCell => None,
+        CellType::BoundaryConditionCell(_) => Some(BOUNDARY_FILL),
+        CellType::VoidCell => Some(VOID_FILL),
+    }
+}
+
+// Isolines of psi at count evenly spaced levels strictly inside its range over the
+// fluid cells
+pub fn streamlines(solver: &dyn FluidSolver, count: usize) -> Vec<Vec<Segment>> {
+    let psi = solver.field(Field::Psi);
+    let psi_range = psi.range().unwrap_or([0.0, 0.0]);
+    let origin = psi.position(0, 0);
+    let values = psi.to_vec();
+
+    (1..=count)
+        .map(|level| {
+            let level =
+                psi_range[0] + (psi_range[1] - psi_range[0]) * level as f32 / (count + 1) as f32;
+            contour(&values, psi.shape(), origin, psi.delta_space(), level)
+        })
+        .collect()
+}
+
+// Marching squares over values sampled on a grid of points, value of point (x, y)
+// at values[x * size[1] + y] and position origin + (x, y) * spacing
+pub fn contour(
+    values: &[f32],
+    size: [usize; 2],
+    origin: [f32; 2],
+    spacing: [f32; 2],
+    level: f32,
+) -> Vec<Segment> {
+    assert_eq!(values.len(), size[0] * size[1], "value count mismatch");
+    let value = |x: usize, y: usize| values[x * size[1] + y] - level;
+    let position = |x: f32, y: f32| [origin[0] + x * spacing[0], origin[1] + y * spacing[1]];
+
+    let mut segments = Vec::new();
+    for x in 0..size[0].saturating_sub(1) {
+        for y in 0..size[1].saturating_sub(1) {
+            // Corners counter clockwise from the bottom left
+            let corners = [
+                ([x as f32, y as f32], value(x, y)),
+                ([x as f32 + 1.0, y as f32], value(x + 1, y)),
+                ([x as f32 + 1.0, y as f32 + 1.0], value(x + 1, y + 1)),
+                ([x as f32, y as f32 + 1.0], value(x, y + 1)),
+            ];
+
+            let crossings: Vec<[f32; 2]> = (0..4)
+                .filter_map(|edge| {
+                    let (a, value_a) = corners[edge];
+                    let (b, value_b) = corners[(edge + 1) % 4];
+                    if (value_a < 0.0) == (value_b < 0.0) {
+                        return None;
+                    }
+                    let t = value_a / (value_a - value_b);
+                    Some(position(a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])))
+                })
+                .collect();
+
+            match crossings.len() {
+                2 => segments.push([crossings[0], crossings[1]]),
+                4 => {
+                    // Saddle, the center value decides which corners connect
+                    let center = corners.iter().map(|(_, value)| value).sum::<f32>() / 4.0;
+                    if (center < 0.0) == (corners[0].1 < 0.0) {
+                        segments.push([crossings[0], crossings[1]]);
+                        segments.push([crossings[2], crossings[3]]);
+                    } else {
+                        segments.push([crossings[3], crossings[0]]);
+                        segments.push([crossings[1], crossings[2]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    segments
+}
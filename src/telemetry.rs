@@ -0,0 +1,366 @@
+use crate::cell::CellType;
+use crate::field::Field;
+use crate::solver::FluidSolver;
+
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+// Frames waiting for a slow client before newer ones are dropped
+const CLIENT_QUEUE: usize = 4;
+const ACCEPT_POLL: Duration = Duration::from_millis(50);
+// Gives up on a client that never finishes its request
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+
+type Message = Arc<(u8, Vec<u8>)>;
+
+// Streams downsampled fields of a running solver to WebSocket clients. Every published
+// frame is a JSON text message with the statistics followed by a binary message with
+// the fields of Field::ALL, each as little endian f32 indexed x * ny + y in the
+// downsampled grid. Clients that fall behind miss frames instead of slowing the run.
+pub struct TelemetryServer {
+    address: SocketAddr,
+    every: usize,
+    stride: usize,
+    steps: usize,
+    clients: Arc<Mutex<Vec<SyncSender<Message>>>>,
+    is_stopping: Arc<AtomicBool>,
+    acceptor: Option<JoinHandle<()>>,
+}
+
+impl TelemetryServer {
+    // Publishes every n-th recorded step, averaging blocks of stride x stride cells
+    pub fn bind(address: impl ToSocketAddrs, every: usize, stride: usize) -> io::Result<Self> {
+        assert!(every > 0, "telemetry interval must be positive");
+        assert!(stride > 0, "telemetry stride must be positive");
+
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let is_stopping = Arc::new(AtomicBool::new(false));
+        let acceptor = {
+            let clients = clients.clone();
+            let is_stopping = is_stopping.clone();
+            thread::spawn(move || accept_clients(listener, &clients, &is_stopping))
+        };
+
+        Ok(Self {
+            address,
+            every,
+            stride,
+            steps: 0,
+            clients,
+            is_stopping,
+            acceptor: Some(acceptor),
+        })
+    }
+
+    // Useful after binding port 0
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn clients(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    // Call once per timestep, returns whether a frame was published
+    pub fn record(&mut self, solver: &dyn FluidSolver) -> bool {
+        let is_due = self.steps.is_multiple_of(self.every);
+        self.steps += 1;
+        if is_due {
+            self.publish(solver);
+        }
+        is_due
+    }
+
+    pub fn publish(&mut self, solver: &dyn FluidSolver) {
+        let mut clients = self.clients.lock().unwrap();
+        if clients.is_empty() {
+            return;
+        }
+
+        let (statistics, fields) = frame(solver, self.stride);
+        let messages = [
+            Arc::new((OPCODE_TEXT, statistics.into_bytes())),
+            Arc::new((OPCODE_BINARY, fields)),
+        ];
+        // A full queue drops this frame for the client, a closed one the client
+        clients.retain(|client| {
+            messages
+                .iter()
+                .all(|message| match client.try_send(message.clone()) {
+                    Ok(()) | Err(TrySendError::Full(_)) => true,
+                    Err(TrySendError::Disconnected(_)) => false,
+                })
+        });
+    }
+}
+
+impl Drop for TelemetryServer {
+    fn drop(&mut self) {
+        self.is_stopping.store(true, Ordering::Relaxed);
+        if let Some(acceptor) = self.acceptor.take() {
+            let _ = acceptor.join();
+        }
+        // Closing the queues ends the client threads
+        self.clients.lock().unwrap().clear();
+    }
+}
+
+fn accept_clients(
+    listener: TcpListener,
+    clients: &Arc<Mutex<Vec<SyncSender<Message>>>>,
+    is_stopping: &Arc<AtomicBool>,
+) {
+    while !is_stopping.load(Ordering::Relaxed) {
+        match listener.accept() {
+            // Each client shakes hands on its own thread, so a silent one only
+            // stalls itself
+            Ok((stream, _)) => {
+                let clients = clients.clone();
+                let is_stopping = is_stopping.clone();
+                thread::spawn(move || serve_client(stream, &clients, &is_stopping));
+            }
+            // No pending connection, or one that failed before it was accepted
+            Err(_) => thread::sleep(ACCEPT_POLL),
+        }
+    }
+}
+
+// Sends the published frames until the client goes away or the server stops
+fn serve_client(
+    stream: TcpStream,
+    clients: &Mutex<Vec<SyncSender<Message>>>,
+    is_stopping: &AtomicBool,
+) {
+    let Ok(mut stream) = handshake(stream) else {
+        return;
+    };
+    let (sender, receiver) = sync_channel::<Message>(CLIENT_QUEUE);
+    {
+        // Checked under the lock, the server clears the queues after stopping
+        let mut clients = clients.lock().unwrap();
+        if is_stopping.load(Ordering::Relaxed) {
+            return;
+        }
+        clients.push(sender);
+    }
+    for message in receiver {
+        let (opcode, payload) = &*message;
+        if write_frame(&mut stream, *opcode, payload).is_err() {
+            break;
+        }
+    }
+}
+
+// Answers the HTTP upgrade request, anything else is refused
+fn handshake(stream: TcpStream) -> io::Result<TcpStream> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+    let mut key = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("sec-websocket-key") {
+                key = Some(value.trim().to_string());
+            }
+        }
+    }
+
+    let mut stream = reader.into_inner();
+    let Some(key) = key else {
+        stream.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")?;
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a WebSocket request",
+        ));
+    };
+    let accept = base64(&sha1(format!("{key}{WEBSOCKET_GUID}").as_bytes()));
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {accept}\r\n\r\n"
+    )?;
+    Ok(stream)
+}
+
+// Statistics as JSON and the block averaged fields
+fn frame(solver: &dyn FluidSolver, stride: usize) -> (String, Vec<u8>) {
+    let [nx, ny] = solver.space_size();
+    let shape = [nx.div_ceil(stride), ny.div_ceil(stride)];
+
+    let fields = solver.fields();
+    let max_speed = fields
+        .velocities()
+        .zip(fields.cell_type.iter())
+        .filter(|(_, cell_type)| matches!(cell_type, CellType::FluidCell))
+        .fold(0.0f32, |max_speed, ([u, v], _)| {
+            max_speed.max((u.powi(2) + v.powi(2)).sqrt())
+        });
+
+    let mut statistics = format!(
+        "{{\"time\":{},\"shape\":[{},{}],\"stride\":{stride},\"max_speed\":{}",
+        json_number(solver.time()),
+        shape[0],
+        shape[1],
+        json_number(max_speed)
+    );
+    let mut values = Vec::with_capacity(Field::ALL.len() * shape[0] * shape[1] * 4);
+    for field in Field::ALL {
+        let view = solver.field(field);
+        let [min, max] = view.range().unwrap_or([0.0, 0.0]);
+        let _ = write!(
+            statistics,
+            ",\"{}\":[{},{}]",
+            field.name(),
+            json_number(min),
+            json_number(max)
+        );
+
+        for x in 0..shape[0] {
+            for y in 0..shape[1] {
+                let block = view.slice(
+                    x * stride..((x + 1) * stride).min(nx),
+                    y * stride..((y + 1) * stride).min(ny),
+                );
+                let (sum, count) = block
+                    .fluid_iter()
+                    .fold((0.0, 0), |(sum, count), (_, value)| {
+                        (sum + value, count + 1)
+                    });
+                let mean = if count > 0 { sum / count as f32 } else { 0.0 };
+                values.extend_from_slice(&mean.to_le_bytes());
+            }
+        }
+    }
+    statistics.push('}');
+    (statistics, values)
+}
+
+// JSON has no NaN or infinity
+fn json_number(value: f32) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+// Unmasked, as the server side of a connection sends
+fn write_frame(stream: &mut impl Write, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mut header = vec![0x80 | opcode];
+    match payload.len() {
+        length @ 0..=125 => header.push(length as u8),
+        length @ 126..=0xffff => {
+            header.push(126);
+            header.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            header.push(127);
+            header.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+    stream.write_all(&header)?;
+    stream.write_all(payload)?;
+    stream.flush()
+}
+
+fn sha1(message: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [
+        0x6745_2301,
+        0xefcd_ab89,
+        0x98ba_dcfe,
+        0x1032_5476,
+        0xc3d2_e1f0,
+    ];
+
+    let mut data = message.to_vec();
+    data.push(0x80);
+    while data.len() % 64 != 56 {
+        data.push(0);
+    }
+    data.extend_from_slice(&(message.len() as u64 * 8).to_be_bytes());
+
+    for block in data.chunks(64) {
+        let mut words = [0u32; 80];
+        for (index, word) in block.chunks(4).enumerate() {
+            words[index] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for index in 16..80 {
+            words[index] =
+                (words[index - 3] ^ words[index - 8] ^ words[index - 14] ^ words[index - 16])
+                    .rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (index, &word) in words.iter().enumerate() {
+            let (f, k) = match index {
+                0..=19 => ((b & c) | (!b & d), 0x5a82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ed9_eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+                _ => (b ^ c ^ d, 0xca62_c1d6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (value, added) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(added);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (bytes, value) in digest.chunks_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (index, &byte)| {
+                group | (byte as u32) << (16 - 8 * index)
+            });
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
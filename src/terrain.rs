@@ -0,0 +1,189 @@
+use crate::field_snapshot::invalid_data;
+use crate::space_domain::bilinear;
+
+use std::path::Path;
+use std::{fs, io};
+
+// Bottom elevation on a regular grid of samples covering extent, the samples sit at
+// the centers of the pixels of a heightmap image. Indexed x * height + y with y
+// counting up, like the cells of a domain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heightmap {
+    size: [usize; 2],
+    extent: [f32; 2],     // meters
+    elevations: Vec<f32>, // meters
+}
+
+impl Heightmap {
+    pub fn new(size: [usize; 2], extent: [f32; 2], elevations: Vec<f32>) -> Self {
+        assert!(
+            size[0] >= 2 && size[1] >= 2,
+            "a heightmap needs at least 2 x 2 samples"
+        );
+        assert!(
+            extent[0] > 0.0 && extent[1] > 0.0,
+            "heightmap extent must be positive"
+        );
+        assert_eq!(elevations.len(), size[0] * size[1], "sample count mismatch");
+        Self {
+            size,
+            extent,
+            elevations,
+        }
+    }
+
+    // Grayscale pixels in rows top to bottom scaled from black at elevation[0] to
+    // white at elevation[1], white being max_value
+    pub fn from_grayscale(
+        size: [usize; 2],
+        extent: [f32; 2],
+        pixels: &[u16],
+        max_value: u16,
+        elevation: [f32; 2],
+    ) -> Self {
+        assert_eq!(pixels.len(), size[0] * size[1], "pixel count mismatch");
+        assert!(max_value > 0, "max value must be positive");
+        let elevations = (0..size[0])
+            .flat_map(|x| (0..size[1]).map(move |y| (x, y)))
+            .map(|(x, y)| {
+                let pixel = pixels[(size[1] - 1 - y) * size[0] + x];
+                let t = pixel.min(max_value) as f32 / max_value as f32;
+                elevation[0] + t * (elevation[1] - elevation[0])
+            })
+            .collect();
+        Self::new(size, extent, elevations)
+    }
+
+    // Binary (P5) or plain (P2) PGM file, 8 or 16 bits per pixel
+    pub fn load_pgm(
+        path: impl AsRef<Path>,
+        extent: [f32; 2],
+        elevation: [f32; 2],
+    ) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+
+        // Magic, width, height and max value separated by whitespace and comments,
+        // binary pixels start after a single whitespace character
+        let mut position = 0;
+        let mut header = Vec::with_capacity(4);
+        while header.len() < 4 {
+            match bytes.get(position) {
+                None => return Err(invalid_data("truncated PGM header")),
+                Some(b'#') => {
+                    while bytes.get(position).is_some_and(|&byte| byte != b'\n') {
+                        position += 1;
+                    }
+                }
+                Some(byte) if byte.is_ascii_whitespace() => position += 1,
+                Some(_) => {
+                    let start = position;
+                    while bytes
+                        .get(position)
+                        .is_some_and(|byte| !byte.is_ascii_whitespace())
+                    {
+                        position += 1;
+                    }
+                    header.push(String::from_utf8_lossy(&bytes[start..position]).to_string());
+                }
+            }
+        }
+        let number = |text: &str| {
+            text.parse::<usize>()
+                .map_err(|_| invalid_data(&format!("malformed PGM header value {text}")))
+        };
+        let size = [number(&header[1])?, number(&header[2])?];
+        let max_value = number(&header[3])?;
+        if size[0] < 2 || size[1] < 2 {
+            return Err(invalid_data("a heightmap needs at least 2 x 2 pixels"));
+        }
+        if max_value == 0 || max_value > u16::MAX as usize {
+            return Err(invalid_data(&format!(
+                "unsupported PGM max value {max_value}"
+            )));
+        }
+        let count = size[0] * size[1];
+
+        let pixels: Vec<u16> = match header[0].as_str() {
+            "P5" => {
+                let data = &bytes[(position + 1).min(bytes.len())..];
+                let width = if max_value > 255 { 2 } else { 1 };
+                if data.len() < count * width {
+                    return Err(invalid_data("truncated PGM pixels"));
+                }
+                data.chunks_exact(width)
+                    .take(count)
+                    .map(|pixel| {
+                        if width == 2 {
+                            u16::from_be_bytes([pixel[0], pixel[1]])
+                        } else {
+                            pixel[0] as u16
+                        }
+                    })
+                    .collect()
+            }
+            "P2" => {
+                let pixels = String::from_utf8_lossy(&bytes[position..])
+                    .split_ascii_whitespace()
+                    .take(count)
+                    .map(|text| number(text).map(|value| value.min(u16::MAX as usize) as u16))
+                    .collect::<io::Result<Vec<u16>>>()?;
+                if pixels.len() < count {
+                    return Err(invalid_data("truncated PGM pixels"));
+                }
+                pixels
+            }
+            magic => return Err(invalid_data(&format!("not a PGM file, magic {magic}"))),
+        };
+
+        Ok(Self::from_grayscale(
+            size,
+            extent,
+            &pixels,
+            max_value as u16,
+            elevation,
+        ))
+    }
+
+    pub fn size(&self) -> [usize; 2] {
+        self.size
+    }
+
+    pub fn extent(&self) -> [f32; 2] {
+        self.extent
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> f32 {
+        self.elevations[x * self.size[1] + y]
+    }
+
+    pub fn delta_space(&self) -> [f32; 2] {
+        [
+            self.extent[0] / self.size[0] as f32,
+            self.extent[1] / self.size[1] as f32,
+        ]
+    }
+
+    // Bilinear interpolation between the samples, clamped to the outermost ones
+    pub fn elevation(&self, position: [f32; 2]) -> f32 {
+        bilinear(
+            self.size,
+            self.delta_space(),
+            position,
+            [0.5, 0.5],
+            |x, y| self.get(x, y),
+        )
+    }
+
+    // Elevation at the center of every cell of a grid, indexed x * ny + y
+    pub fn cell_elevations(&self, space_size: [usize; 2], delta_space: [f32; 2]) -> Vec<f32> {
+        (0..space_size[0])
+            .flat_map(|x| (0..space_size[1]).map(move |y| (x, y)))
+            .map(|(x, y)| {
+                self.elevation([
+                    (x as f32 + 0.5) * delta_space[0],
+                    (y as f32 + 0.5) * delta_space[1],
+                ])
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,227 @@
+use crate::simulation::Simulation;
+use crate::solver::FluidSolver;
+
+use std::fmt;
+
+// A fluid and its flow in SI units. The solver works in units of the characteristic
+// length and velocity, where the grid spacing, boundary velocities and times of a
+// preset are given, and only the Reynolds number carries the fluid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicalFlow {
+    pub density: f32,           // kilograms/meters^3
+    pub dynamic_viscosity: f32, // pascals*seconds
+    pub length: f32,            // meters
+    pub velocity: f32,          // meters/seconds
+    pub gravity: [f32; 2],      // meters/seconds^2
+}
+
+impl PhysicalFlow {
+    // Water at 20 degrees celsius with the given scales and no gravity
+    pub fn water(length: f32, velocity: f32) -> Self {
+        Self {
+            density: 998.2,
+            dynamic_viscosity: 1.002e-3,
+            length,
+            velocity,
+            gravity: [0.0, 0.0],
+        }
+    }
+
+    // Air at 20 degrees celsius and sea level pressure with the given scales and no
+    // gravity
+    pub fn air(length: f32, velocity: f32) -> Self {
+        Self {
+            density: 1.204,
+            dynamic_viscosity: 1.825e-5,
+            length,
+            velocity,
+            gravity: [0.0, 0.0],
+        }
+    }
+
+    pub fn with_gravity(mut self, gravity: [f32; 2]) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    // meters^2/seconds
+    pub fn kinematic_viscosity(&self) -> f32 {
+        self.dynamic_viscosity / self.density
+    }
+
+    pub fn reynolds(&self) -> f32 {
+        self.velocity * self.length / self.kinematic_viscosity()
+    }
+
+    pub fn scales(&self) -> Scales {
+        self.validate();
+        Scales {
+            length: self.length,
+            velocity: self.velocity,
+            time: self.length / self.velocity,
+            pressure: self.density * self.velocity.powi(2),
+            acceleration: self.velocity.powi(2) / self.length,
+        }
+    }
+
+    // Gravity in solver units, 1 / Froude^2 along each axis
+    pub fn acceleration(&self) -> [f32; 2] {
+        let scale = self.scales().acceleration;
+        self.gravity.map(|gravity| gravity / scale)
+    }
+
+    // Sets the Reynolds number and acceleration of the simulation and reports the
+    // derived numbers for its current grid and flow
+    pub fn configure(&self, simulation: &mut Simulation) -> FlowReport {
+        simulation.set_reynolds(self.reynolds());
+        simulation.set_acceleration(self.acceleration());
+        self.report(simulation)
+    }
+
+    pub fn report(&self, solver: &dyn FluidSolver) -> FlowReport {
+        let reynolds = self.reynolds();
+        FlowReport {
+            reynolds,
+            acceleration: self.acceleration(),
+            scales: self.scales(),
+            delta_time_limits: DeltaTimeLimits::new(
+                reynolds,
+                solver.delta_space(),
+                max_velocity(solver),
+            ),
+        }
+    }
+
+    fn validate(&self) {
+        // Written so NaN fails every check
+        let is_positive = |value: f32| value > 0.0;
+        assert!(is_positive(self.density), "density must be positive");
+        assert!(
+            is_positive(self.dynamic_viscosity),
+            "viscosity must be positive"
+        );
+        assert!(
+            is_positive(self.length) && is_positive(self.velocity),
+            "characteristic length and velocity must be positive"
+        );
+    }
+}
+
+// SI value of one solver unit of each quantity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scales {
+    pub length: f32,       // meters
+    pub velocity: f32,     // meters/seconds
+    pub time: f32,         // seconds
+    pub pressure: f32,     // pascals
+    pub acceleration: f32, // meters/seconds^2
+}
+
+impl Scales {
+    pub fn to_meters(&self, length: f32) -> f32 {
+        length * self.length
+    }
+
+    pub fn to_meters_per_second(&self, velocity: f32) -> f32 {
+        velocity * self.velocity
+    }
+
+    pub fn to_seconds(&self, time: f32) -> f32 {
+        time * self.time
+    }
+
+    pub fn to_pascals(&self, pressure: f32) -> f32 {
+        pressure * self.pressure
+    }
+
+    pub fn from_meters(&self, meters: f32) -> f32 {
+        meters / self.length
+    }
+
+    pub fn from_meters_per_second(&self, meters_per_second: f32) -> f32 {
+        meters_per_second / self.velocity
+    }
+
+    pub fn from_seconds(&self, seconds: f32) -> f32 {
+        seconds / self.time
+    }
+
+    pub fn from_pascals(&self, pascals: f32) -> f32 {
+        pascals / self.pressure
+    }
+}
+
+// Largest stable timestep of the explicit scheme in solver units, from the stability
+// conditions of Griebel et al. Practical timesteps keep a safety factor below them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeltaTimeLimits {
+    // Viscous diffusion, Re / 2 / (1 / dx^2 + 1 / dy^2)
+    pub diffusive: f32,
+    // No fluid crosses more than a cell per step, infinite at rest
+    pub convective: f32,
+}
+
+impl DeltaTimeLimits {
+    // For a flow whose largest velocity components are max_velocity
+    pub fn new(reynolds: f32, delta_space: [f32; 2], max_velocity: [f32; 2]) -> Self {
+        let [dx, dy] = delta_space;
+        let diffusive = reynolds / 2.0 / (1.0 / dx.powi(2) + 1.0 / dy.powi(2));
+        let convective = (dx / max_velocity[0].abs()).min(dy / max_velocity[1].abs());
+        Self {
+            diffusive,
+            convective,
+        }
+    }
+
+    pub fn stable(&self) -> f32 {
+        self.diffusive.min(self.convective)
+    }
+}
+
+// Largest velocity components of the flow, at least the characteristic velocity so a
+// flow starting at rest gets a convective limit
+fn max_velocity(solver: &dyn FluidSolver) -> [f32; 2] {
+    let fields = solver.fields();
+    let max = |values: &[f32]| {
+        values
+            .iter()
+            .fold(1.0f32, |max, value| max.max(value.abs()))
+    };
+    [max(&fields.u), max(&fields.v)]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlowReport {
+    pub reynolds: f32,
+    // Solver units
+    pub acceleration: [f32; 2],
+    pub scales: Scales,
+    pub delta_time_limits: DeltaTimeLimits,
+}
+
+impl fmt::Display for FlowReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scales = &self.scales;
+        let limit = self.delta_time_limits.stable();
+        writeln!(f, "reynolds number: {}", self.reynolds)?;
+        writeln!(
+            f,
+            "acceleration: {:?} ({:?} m/s^2)",
+            self.acceleration,
+            self.acceleration.map(|value| value * scales.acceleration)
+        )?;
+        writeln!(
+            f,
+            "scales: {} m, {} m/s, {} s, {} Pa",
+            scales.length, scales.velocity, scales.time, scales.pressure
+        )?;
+        write!(
+            f,
+            "stable delta time: {} ({} s), diffusive {}, convective {}",
+            limit,
+            scales.to_seconds(limit),
+            self.delta_time_limits.diffusive,
+            self.delta_time_limits.convective
+        )
+    }
+}
@@ -0,0 +1,113 @@
+use crate::cell::{BoundaryConditionCell, Cell, CellType};
+use crate::fluid_zones::FluidZones;
+use crate::space_domain::SpaceDomain;
+
+use std::fmt;
+
+// Geometry the solver can't handle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DomainIssue {
+    // Stencils would read outside the domain
+    FluidOnEdge { cell: (usize, usize) },
+    // Fluid cell between two walls
+    NarrowChannel { cell: (usize, usize) },
+    // Fluid neither connected to the largest fluid region nor to an inflow or
+    // outflow, so nothing drives it. Separate open channels are fine.
+    IsolatedPocket { cells: Vec<(usize, usize)> },
+}
+
+impl fmt::Display for DomainIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DomainIssue::FluidOnEdge { cell } => {
+                write!(f, "fluid cell {cell:?} on the domain edge")
+            }
+            DomainIssue::NarrowChannel { cell } => {
+                write!(f, "fluid cell {cell:?} lies in a channel one cell wide")
+            }
+            DomainIssue::IsolatedPocket { cells } => {
+                write!(
+                    f,
+                    "{} fluid cells around {:?} are cut off from the rest",
+                    cells.len(),
+                    cells[0]
+                )
+            }
+        }
+    }
+}
+
+pub fn validate(space_domain: &SpaceDomain) -> Vec<DomainIssue> {
+    let [nx, ny] = space_domain.space_size();
+    let cell_type = |x: usize, y: usize| space_domain.cell_type(x, y);
+    let is_fluid = |x: usize, y: usize| matches!(cell_type(x, y), CellType::FluidCell);
+    let is_wall = |x: usize, y: usize| cell_type(x, y).blocks_flow();
+
+    let mut issues = Vec::new();
+    for x in 0..nx {
+        for y in 0..ny {
+            let is_edge = x == 0 || y == 0 || x == nx - 1 || y == ny - 1;
+            if is_edge {
+                if is_fluid(x, y) {
+                    issues.push(DomainIssue::FluidOnEdge { cell: (x, y) });
+                }
+                continue;
+            }
+
+            // Walls one cell thick are fine, see SpaceDomain::neighbor_u
+            let is_between_walls = (is_wall(x - 1, y) && is_wall(x + 1, y))
+                || (is_wall(x, y - 1) && is_wall(x, y + 1));
+            if is_fluid(x, y) && is_between_walls {
+                issues.push(DomainIssue::NarrowChannel { cell: (x, y) });
+            }
+        }
+    }
+
+    let zones = FluidZones::label(space_domain);
+    issues.extend(
+        (1..zones.len())
+            .filter(|&zone| !zones.is_open(zone))
+            .map(|zone| DomainIssue::IsolatedPocket {
+                cells: zones
+                    .cells(zone)
+                    .iter()
+                    .map(|&index| space_domain.position(index))
+                    .collect(),
+            }),
+    );
+    issues
+}
+
+// Fix the issues until none are left and return the ones that were fixed. Fluid on
+// the edge, narrow channels and pockets become resting no-slip walls. Obstacle cell
+// lists are not updated.
+pub fn repair(space_domain: &mut SpaceDomain) -> Vec<DomainIssue> {
+    let wall = Cell {
+        cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+            boundary_condition_velocity: [0.0, 0.0],
+        }),
+        ..Default::default()
+    };
+
+    let mut fixed = Vec::new();
+    loop {
+        let issues = validate(space_domain);
+        if issues.is_empty() {
+            return fixed;
+        }
+        for issue in issues.iter() {
+            match issue {
+                DomainIssue::FluidOnEdge { cell: (x, y) }
+                | DomainIssue::NarrowChannel { cell: (x, y) } => {
+                    space_domain.set_cell(*x, *y, wall.clone());
+                }
+                DomainIssue::IsolatedPocket { cells } => {
+                    for &(x, y) in cells {
+                        space_domain.set_cell(x, y, wall.clone());
+                    }
+                }
+            }
+        }
+        fixed.extend(issues);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::cell::CellType;
+use crate::field::{FieldView, Location};
+use crate::solver::FluidSolver;
+use crate::svg_export::{self, Segment};
+
+// Vortex identification from the cell centred velocity gradient tensor J, split into
+// strain S = (J + J^T) / 2 and rotation O = (J - J^T) / 2. Vortex cores are where
+// rotation outweighs strain, Q > 0 or lambda2 < 0, unlike vorticity which is also
+// large in the shear layers along walls. Other cells hold zero.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VortexField {
+    pub space_size: [usize; 2],
+    pub delta_space: [f32; 2], // meters
+    pub cell_type: Vec<CellType>,
+    // (|O|^2 - |S|^2) / 2, 1/seconds^2
+    pub q: Vec<f32>,
+    // Middle eigenvalue of S^2 + O^2 with the plane flow taken as a slice of a 3D
+    // one, so the out of plane eigenvalue is zero. For divergence free flow it is -Q.
+    pub lambda2: Vec<f32>,
+}
+
+impl VortexField {
+    pub fn new(solver: &dyn FluidSolver) -> Self {
+        let [nx, ny] = solver.space_size();
+        let [dx, dy] = solver.delta_space();
+        let fields = solver.fields();
+        let index = |x: usize, y: usize| x * ny + y;
+        let centered_u =
+            |x: usize, y: usize| (fields.u[index(x, y)] + fields.u[index(x - 1, y)]) / 2.0;
+        let centered_v =
+            |x: usize, y: usize| (fields.v[index(x, y)] + fields.v[index(x, y - 1)]) / 2.0;
+
+        let mut q = vec![0.0; nx * ny];
+        let mut lambda2 = vec![0.0; nx * ny];
+        for x in 1..nx.saturating_sub(1) {
+            for y in 1..ny.saturating_sub(1) {
+                let cell = index(x, y);
+                if !matches!(fields.cell_type[cell], CellType::FluidCell) {
+                    continue;
+                }
+                // Face differences along the staggering, central differences of the
+                // centred velocities across it
+                let du_dx = (fields.u[cell] - fields.u[index(x - 1, y)]) / dx;
+                let dv_dy = (fields.v[cell] - fields.v[index(x, y - 1)]) / dy;
+                let du_dy = (centered_u(x, y + 1) - centered_u(x, y - 1)) / (2.0 * dy);
+                let dv_dx = (centered_v(x + 1, y) - centered_v(x - 1, y)) / (2.0 * dx);
+
+                let shear = (du_dy + dv_dx) / 2.0;
+                let rotation = (du_dy - dv_dx) / 2.0;
+                let strain_norm = du_dx.powi(2) + dv_dy.powi(2) + 2.0 * shear.powi(2);
+                q[cell] = (2.0 * rotation.powi(2) - strain_norm) / 2.0;
+
+                // S^2 + O^2 in the plane, symmetric
+                let m_xx = du_dx.powi(2) + shear.powi(2) - rotation.powi(2);
+                let m_yy = dv_dy.powi(2) + shear.powi(2) - rotation.powi(2);
+                let m_xy = shear * (du_dx + dv_dy);
+                let mean = (m_xx + m_yy) / 2.0;
+                let radius = (((m_xx - m_yy) / 2.0).powi(2) + m_xy.powi(2)).sqrt();
+                // Middle of mean - radius, mean + radius and zero
+                lambda2[cell] = (mean - radius).max(0.0f32.min(mean + radius));
+            }
+        }
+        Self {
+            space_size: [nx, ny],
+            delta_space: [dx, dy],
+            cell_type: fields.cell_type.clone(),
+            q,
+            lambda2,
+        }
+    }
+
+    pub fn q(&self) -> FieldView<'_> {
+        self.view(&self.q)
+    }
+
+    pub fn lambda2(&self) -> FieldView<'_> {
+        self.view(&self.lambda2)
+    }
+
+    // Outlines of the regions where Q exceeds level, a small positive fraction of the
+    // largest Q picks out the cores. Segments in meters, see svg_export::contour.
+    pub fn q_contour(&self, level: f32) -> Vec<Segment> {
+        self.contour(&self.q, level)
+    }
+
+    pub fn lambda2_contour(&self, level: f32) -> Vec<Segment> {
+        self.contour(&self.lambda2, level)
+    }
+
+    fn contour(&self, values: &[f32], level: f32) -> Vec<Segment> {
+        let view = self.view(values);
+        svg_export::contour(
+            values,
+            self.space_size,
+            view.position(0, 0),
+            self.delta_space,
+            level,
+        )
+    }
+
+    fn view<'a>(&'a self, values: &'a [f32]) -> FieldView<'a> {
+        FieldView::from_values(
+            Location::CellCentre,
+            values,
+            &self.cell_type,
+            self.space_size,
+            self.delta_space,
+        )
+    }
+}
@@ -0,0 +1,152 @@
+use crate::cell::{BoundaryConditionCell, CellType};
+use crate::space_domain::Fields;
+
+use std::io::{self, Write};
+
+// One face between a no-slip cell and a fluid cell. The tangent is the normal turned
+// clockwise, along +x on a bottom wall. Density is 1 and the viscosity 1 / reynolds,
+// as in the momentum equations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WallFace {
+    pub wall: [usize; 2],
+    pub fluid: [usize; 2],
+    pub position: [f32; 2], // face center, meters
+    // Unit normal pointing into the fluid
+    pub normal: [f32; 2],
+    // Along the tangent, the shear the fluid exerts on the wall
+    pub shear_stress: f32,
+    // sqrt(|shear stress| / density)
+    pub friction_velocity: f32,
+    // Wall distance of the first fluid cell center in viscous units, below about one
+    // the viscous sublayer is resolved
+    pub y_plus: f32,
+}
+
+impl WallFace {
+    pub fn tangent(&self) -> [f32; 2] {
+        [self.normal[1], -self.normal[0]]
+    }
+}
+
+// Faces of the given no-slip cells shared with fluid cells, in the order of the cells
+// and then right, left, top, bottom. The wall normal velocity gradient is one-sided:
+// second order through the wall and the first two fluid cell centers, first order
+// where the second cell isn't fluid.
+pub fn wall_faces(
+    fields: &Fields,
+    space_size: [usize; 2],
+    delta_space: [f32; 2],
+    reynolds: f32,
+    wall_cells: impl IntoIterator<Item = (usize, usize)>,
+) -> Vec<WallFace> {
+    let [nx, ny] = space_size;
+    let is_fluid = |x: isize, y: isize| {
+        (0..nx as isize).contains(&x)
+            && (0..ny as isize).contains(&y)
+            && matches!(
+                fields.cell_type[x as usize * ny + y as usize],
+                CellType::FluidCell
+            )
+    };
+    // Fluid cells are never on the domain edge
+    let centered_velocity = |x: usize, y: usize| {
+        let index = x * ny + y;
+        [
+            (fields.u[index - ny] + fields.u[index]) / 2.0,
+            (fields.v[index - 1] + fields.v[index]) / 2.0,
+        ]
+    };
+
+    let mut faces = Vec::new();
+    for (x, y) in wall_cells {
+        let CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+            boundary_condition_velocity,
+        }) = fields.cell_type[x * ny + y]
+        else {
+            continue;
+        };
+        for offset in [[1, 0], [-1, 0], [0, 1], [0, -1]] {
+            let [fx, fy] = [x as isize + offset[0], y as isize + offset[1]];
+            if !is_fluid(fx, fy) {
+                continue;
+            }
+            let normal = offset.map(|component| component as f32);
+            let tangent = [normal[1], -normal[0]];
+            let along = |velocity: [f32; 2]| {
+                (velocity[0] - boundary_condition_velocity[0]) * tangent[0]
+                    + (velocity[1] - boundary_condition_velocity[1]) * tangent[1]
+            };
+            // Cell size across the wall
+            let spacing = if offset[0] != 0 {
+                delta_space[0]
+            } else {
+                delta_space[1]
+            };
+
+            let first = along(centered_velocity(fx as usize, fy as usize));
+            let [sx, sy] = [fx + offset[0], fy + offset[1]];
+            let gradient = if is_fluid(sx, sy) {
+                // Quadratic through the wall, spacing / 2 and 3 spacing / 2
+                let second = along(centered_velocity(sx as usize, sy as usize));
+                (9.0 * first - second) / (3.0 * spacing)
+            } else {
+                first / (spacing / 2.0)
+            };
+            let shear_stress = gradient / reynolds;
+            let friction_velocity = shear_stress.abs().sqrt();
+
+            let center = [
+                (x as f32 + 0.5) * delta_space[0],
+                (y as f32 + 0.5) * delta_space[1],
+            ];
+            faces.push(WallFace {
+                wall: [x, y],
+                fluid: [fx as usize, fy as usize],
+                position: [
+                    center[0] + normal[0] * delta_space[0] / 2.0,
+                    center[1] + normal[1] * delta_space[1] / 2.0,
+                ],
+                normal,
+                shear_stress,
+                friction_velocity,
+                y_plus: friction_velocity * spacing / 2.0 * reynolds,
+            });
+        }
+    }
+    faces
+}
+
+// Orders faces by their angle around center, counterclockwise from the upstream
+// side, the usual order of a profile over a bluff body
+pub fn sort_around(faces: &mut [WallFace], center: [f32; 2]) {
+    let angle =
+        |face: &WallFace| (face.position[1] - center[1]).atan2(face.position[0] - center[0]);
+    faces.sort_by(|a, b| angle(a).total_cmp(&angle(b)));
+}
+
+// One row per face in the given order, with the distance walked along the faces
+// from the first one as s in meters
+pub fn write_csv(faces: &[WallFace], file: &mut impl Write) -> io::Result<()> {
+    writeln!(file, "s,x,y,normal_x,normal_y,shear_stress,y_plus")?;
+    let mut s = 0.0;
+    for (index, face) in faces.iter().enumerate() {
+        if index > 0 {
+            let previous = faces[index - 1].position;
+            s += ((face.position[0] - previous[0]).powi(2)
+                + (face.position[1] - previous[1]).powi(2))
+            .sqrt();
+        }
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{}",
+            s,
+            face.position[0],
+            face.position[1],
+            face.normal[0],
+            face.normal[1],
+            face.shear_stress,
+            face.y_plus
+        )?;
+    }
+    Ok(())
+}
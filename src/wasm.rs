@@ -0,0 +1,101 @@
+// JavaScript facing wrapper, built with
+//     wasm-pack build --target web -- --features wasm
+// rayon runs everything on the calling thread on wasm32-unknown-unknown, so no
+// worker setup is needed
+use crate::cell::CellType;
+use crate::field::Field;
+use crate::presets;
+use crate::simulation::Simulation;
+use crate::solver::FluidSolver;
+
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct WasmSimulation {
+    simulation: Simulation,
+}
+
+#[wasm_bindgen]
+impl WasmSimulation {
+    // One of presets::NAMES
+    #[wasm_bindgen(constructor)]
+    pub fn new(preset: &str) -> Result<WasmSimulation, JsError> {
+        let preset = presets::by_name(preset)
+            .ok_or_else(|| JsError::new(&format!("unknown preset {preset}")))?;
+        Ok(Self {
+            simulation: Simulation::from_preset(preset),
+        })
+    }
+
+    pub fn step(&mut self, steps: u32) {
+        for _ in 0..steps {
+            self.simulation.iterate_one_timestep();
+        }
+    }
+
+    pub fn time(&self) -> f32 {
+        self.simulation.time()
+    }
+
+    // Cells in x
+    pub fn width(&self) -> usize {
+        self.simulation.space_size()[0]
+    }
+
+    pub fn height(&self) -> usize {
+        self.simulation.space_size()[1]
+    }
+
+    // Cell size in meters, [dx, dy]
+    pub fn delta_space(&self) -> Vec<f32> {
+        self.simulation.delta_space().to_vec()
+    }
+
+    // "pressure", "u", "v" or "psi" as a Float32Array indexed x * height + y
+    pub fn field(&self, name: &str) -> Result<Vec<f32>, JsError> {
+        let field = Field::ALL
+            .into_iter()
+            .find(|field| field.name() == name)
+            .ok_or_else(|| JsError::new(&format!("unknown field {name}")))?;
+        Ok(self.simulation.field(field).to_vec())
+    }
+
+    // Dye as a Float32Array indexed like field
+    pub fn dye(&self) -> Vec<f32> {
+        self.simulation.fields().dye.clone()
+    }
+
+    // CellType::id of every cell as a Uint8Array indexed like field
+    pub fn cell_types(&self) -> Vec<u8> {
+        self.simulation
+            .fields()
+            .cell_type
+            .iter()
+            .map(CellType::id)
+            .collect()
+    }
+
+    // Mouse forcing, position and radius in meters
+    pub fn apply_impulse(&mut self, x: f32, y: f32, radius: f32, impulse_x: f32, impulse_y: f32) {
+        self.simulation
+            .apply_impulse([x, y], radius, [impulse_x, impulse_y]);
+    }
+
+    pub fn apply_velocity_brush(
+        &mut self,
+        x: f32,
+        y: f32,
+        radius: f32,
+        u: f32,
+        v: f32,
+        dye: Option<f32>,
+    ) {
+        self.simulation
+            .apply_velocity_brush([x, y], radius, [u, v], dye);
+    }
+
+    // Returns whether the cell changed
+    pub fn set_obstacle(&mut self, x: usize, y: usize, is_obstacle: bool) -> bool {
+        self.simulation.set_obstacle(x, y, is_obstacle)
+    }
+}
@@ -0,0 +1,95 @@
+use flow2d_rs::acoustics::{cylinder_far_field, far_field_spectrum, Microphone};
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+// Lift at frequency and drag at twice that, sampled every 5 ms
+fn force_series(frequency: f32, lift: f32, drag: f32) -> Vec<(f32, [f32; 2])> {
+    (0..4000)
+        .map(|step| {
+            let time = step as f32 * 0.005;
+            let phase = TAU * frequency * time;
+            (time, [1.0 + drag * (2.0 * phase).cos(), lift * phase.sin()])
+        })
+        .collect()
+}
+
+#[test]
+fn a_fluctuating_lift_radiates_as_a_dipole() {
+    let speed_of_sound = 340.0;
+    let samples = force_series(0.5, 0.4, 0.05);
+    let above = Microphone {
+        distance: 100.0,
+        angle: FRAC_PI_2,
+    };
+    let spectrum = far_field_spectrum(&samples, above, speed_of_sound).unwrap();
+    assert_eq!(spectrum.frequencies.len(), 2000);
+    let (frequency, level) = spectrum.peak().unwrap();
+    assert!((frequency - 0.5).abs() < 1e-3, "{frequency}");
+
+    let wavenumber = TAU * 0.5 / speed_of_sound;
+    let expected = (wavenumber / (8.0 * PI * 100.0)).sqrt() * 0.4;
+    let bin = spectrum
+        .frequencies
+        .iter()
+        .position(|&bin| (bin - 0.5).abs() < 1e-3)
+        .unwrap();
+    let amplitude = spectrum.amplitudes[bin];
+    assert!(
+        (amplitude - expected).abs() < 0.02 * expected,
+        "{amplitude} != {expected}"
+    );
+    let expected_level = 20.0 * (expected / 2f32.sqrt() / 2e-5).log10();
+    assert!((level - expected_level).abs() < 0.2, "{level}");
+
+    // Four times as far is 6 dB quieter, cylindrical spreading
+    let farther = far_field_spectrum(
+        &samples,
+        Microphone {
+            distance: 400.0,
+            ..above
+        },
+        speed_of_sound,
+    )
+    .unwrap();
+    let difference = level - farther.levels[bin];
+    assert!((difference - 6.02).abs() < 0.01, "{difference}");
+
+    // Downstream only the drag is heard, at twice the frequency
+    let downstream = far_field_spectrum(
+        &samples,
+        Microphone {
+            distance: 100.0,
+            angle: 0.0,
+        },
+        speed_of_sound,
+    )
+    .unwrap();
+    assert!(downstream.amplitudes[bin] < 1e-3 * amplitude);
+    let (frequency, _) = downstream.peak().unwrap();
+    assert!((frequency - 1.0).abs() < 1e-3, "{frequency}");
+}
+
+#[test]
+fn the_cylinder_spectrum_needs_force_samples() {
+    let microphone = Microphone {
+        distance: 50.0,
+        angle: FRAC_PI_2,
+    };
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow_sized([55, 21]));
+    simulation.iterate_one_timestep();
+    assert!(cylinder_far_field(&simulation, microphone, 340.0).is_none());
+
+    simulation.obstacle_mut("cylinder").unwrap().monitor_force = true;
+    for _ in 0..40 {
+        simulation.iterate_one_timestep();
+    }
+    let spectrum = cylinder_far_field(&simulation, microphone, 340.0).unwrap();
+    assert_eq!(spectrum.frequencies.len(), 10);
+    assert!((spectrum.frequencies[0] - 1.0 / (20.0 * simulation.delta_time())).abs() < 1e-2);
+    assert!(spectrum
+        .amplitudes
+        .iter()
+        .all(|amplitude| amplitude.is_finite()));
+}
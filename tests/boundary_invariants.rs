@@ -0,0 +1,279 @@
+use flow2d_rs::cell::{BoundaryConditionCell, Cell, CellType};
+use flow2d_rs::space_domain::{SpaceDomain, FACE_NEIGHBORS};
+
+use proptest::prelude::*;
+
+// Random domain: fluid with scattered no-slip and free-slip walls inside a ring of
+// no-slip, free-slip and outflow cells, and random velocities and pressures
+#[derive(Debug, Clone)]
+struct Layout {
+    space_size: [usize; 2],
+    cells: Vec<Cell>,
+}
+
+fn layout() -> impl Strategy<Value = Layout> {
+    (5usize..=10, 5usize..=10).prop_flat_map(|(nx, ny)| {
+        let cell = (
+            0u8..10,
+            [-1.0f32..1.0, -1.0f32..1.0],
+            [-2.0f32..2.0, -2.0f32..2.0],
+            -1.0f32..1.0,
+        );
+        prop::collection::vec(cell, nx * ny).prop_map(move |cells| {
+            let cells = cells
+                .into_iter()
+                .enumerate()
+                .map(|(index, (kind, wall_velocity, velocity, pressure))| {
+                    let (x, y) = (index / ny, index % ny);
+                    let is_ring = x == 0 || y == 0 || x == nx - 1 || y == ny - 1;
+                    let cell_type = match (is_ring, kind) {
+                        (true, kind) if kind % 3 == 0 => no_slip(wall_velocity),
+                        (true, kind) if kind % 3 == 1 => {
+                            CellType::BoundaryConditionCell(BoundaryConditionCell::FreeSlipCell)
+                        }
+                        (true, _) => {
+                            CellType::BoundaryConditionCell(BoundaryConditionCell::OutFlowCell)
+                        }
+                        (false, 0..=6) => CellType::FluidCell,
+                        (false, 7) => no_slip([0.0, 0.0]),
+                        (false, 8) => no_slip(wall_velocity),
+                        (false, _) => {
+                            CellType::BoundaryConditionCell(BoundaryConditionCell::FreeSlipCell)
+                        }
+                    };
+                    Cell {
+                        cell_type,
+                        velocity,
+                        pressure,
+                        ..Default::default()
+                    }
+                })
+                .collect();
+            Layout {
+                space_size: [nx, ny],
+                cells,
+            }
+        })
+    })
+}
+
+fn no_slip(boundary_condition_velocity: [f32; 2]) -> CellType {
+    CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+        boundary_condition_velocity,
+    })
+}
+
+impl Layout {
+    fn space_domain(&self) -> SpaceDomain {
+        let [_, ny] = self.space_size;
+        let columns = self
+            .cells
+            .chunks(ny)
+            .map(|column| column.to_vec())
+            .collect();
+        SpaceDomain::new(columns, [0.1, 0.1], 0.9)
+    }
+
+    // Mirrored along the axis, velocity components and wall velocities along it
+    // change sign and the staggered faces along it shift by one cell
+    fn mirrored(&self, axis: usize) -> Layout {
+        let [nx, ny] = self.space_size;
+        let mut cells = self.cells.clone();
+        for x in 0..nx {
+            for y in 0..ny {
+                let [mx, my] = mirror(self.space_size, axis, [x, y]);
+                let mut cell = self.cells[x * ny + y].clone();
+                if let CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                    boundary_condition_velocity: mut velocity,
+                }) = cell.cell_type
+                {
+                    velocity[axis] = -velocity[axis];
+                    cell.cell_type = no_slip(velocity);
+                }
+                // The face velocity comes from the face on the other side
+                let [fx, fy] = face_source(self.space_size, axis, [mx, my]);
+                cell.velocity[axis] = match (fx, fy) {
+                    (Some(fx), Some(fy)) => -self.cells[fx * ny + fy].velocity[axis],
+                    _ => 0.0,
+                };
+                cell.velocity[1 - axis] = self.cells[x * ny + y].velocity[1 - axis];
+                cells[mx * ny + my] = cell;
+            }
+        }
+        Layout {
+            space_size: self.space_size,
+            cells,
+        }
+    }
+}
+
+fn mirror(space_size: [usize; 2], axis: usize, mut cell: [usize; 2]) -> [usize; 2] {
+    cell[axis] = space_size[axis] - 1 - cell[axis];
+    cell
+}
+
+// Cell whose face along axis maps onto the face of cell in the mirrored domain, none
+// for the outermost face, which has no counterpart
+fn face_source(space_size: [usize; 2], axis: usize, cell: [usize; 2]) -> [Option<usize>; 2] {
+    let mut source = cell.map(Some);
+    source[axis] = (space_size[axis] - 2).checked_sub(cell[axis]);
+    source
+}
+
+fn is_fluid(space_domain: &SpaceDomain, x: usize, y: usize) -> bool {
+    matches!(space_domain.cell_type(x, y), CellType::FluidCell)
+}
+
+// Faces the momentum stencils can read: faces of fluid cells, and the faces beside a
+// face between two fluid cells, which hold its ghost value across a wall. A ghost
+// face between two different conditions, say the corner of a moving and a resting
+// wall, takes the condition of the cell on its left or bottom, which mirroring swaps.
+fn is_relevant(space_domain: &SpaceDomain, axis: usize, x: usize, y: usize) -> bool {
+    let [nx, ny] = space_domain.space_size();
+    let along = |offset: usize| {
+        if axis == 0 {
+            [x + offset, y]
+        } else {
+            [x, y + offset]
+        }
+    };
+    let is_face_fluid = |[x, y]: [usize; 2]| {
+        let [ax, ay] = if axis == 0 { [x + 1, y] } else { [x, y + 1] };
+        ax < nx && ay < ny && is_fluid(space_domain, x, y) && is_fluid(space_domain, ax, ay)
+    };
+    let [ax, ay] = along(1);
+    if is_fluid(space_domain, x, y) || (ax < nx && ay < ny && is_fluid(space_domain, ax, ay)) {
+        return true;
+    }
+    // Neighbouring faces across the axis
+    let across = |delta: isize| -> Option<[usize; 2]> {
+        let mut cell = [x, y];
+        let index = 1 - axis;
+        cell[index] = cell[index].checked_add_signed(delta)?;
+        (cell[0] < nx && cell[1] < ny).then_some(cell)
+    };
+    let is_uniform =
+        ax < nx && ay < ny && space_domain.cell_type(x, y) == space_domain.cell_type(ax, ay);
+    is_uniform && [-1, 1].into_iter().filter_map(across).any(is_face_fluid)
+}
+
+// Means are summed in mirrored order
+fn is_close(a: f32, b: f32) -> bool {
+    (a - b).abs() <= 1e-6 * a.abs().max(1.0)
+}
+
+fn apply_boundary_conditions(space_domain: &mut SpaceDomain) {
+    space_domain.update_boundary_velocities();
+    space_domain.update_boundary_pressures_and_fg();
+}
+
+proptest! {
+    // Each boundary cell with one fluid neighbour, seen from the fluid, whichever of
+    // the four sides the fluid lies on
+    #[test]
+    fn single_faces_satisfy_their_conditions(layout in layout()) {
+        let before = layout.space_domain();
+        let mut after = before.clone();
+        apply_boundary_conditions(&mut after);
+        let [nx, ny] = before.space_size();
+
+        for x in 0..nx {
+            for y in 0..ny {
+                let CellType::BoundaryConditionCell(condition) = before.cell_type(x, y) else {
+                    continue;
+                };
+                let fluid: Vec<[isize; 2]> = FACE_NEIGHBORS
+                    .into_iter()
+                    .filter(|offset| {
+                        before
+                            .neighbor_position(x, y, *offset)
+                            .is_some_and(|(fx, fy)| is_fluid(&before, fx, fy))
+                    })
+                    .collect();
+                let [offset] = fluid.as_slice() else {
+                    continue;
+                };
+                let at = |delta: [isize; 2]| {
+                    [
+                        x.checked_add_signed(delta[0]).unwrap(),
+                        y.checked_add_signed(delta[1]).unwrap(),
+                    ]
+                };
+                let normal = if offset[0] != 0 { 0 } else { 1 };
+                let tangent = 1 - normal;
+                let face = |domain: &SpaceDomain, component: usize, [x, y]: [usize; 2]| {
+                    domain.velocity(x, y)[component]
+                };
+                // The face shared with the fluid cell, its face across from the wall,
+                // and the tangential velocities on either side of the wall face
+                let shared = at(offset.map(|delta| delta.min(0)));
+                let far = at([0, 1].map(|axis| offset[axis] + offset[axis].min(0)));
+                let fluid_cell = at(*offset);
+                let shared_after = face(&after, normal, shared);
+                let ghost_after = face(&after, tangent, [x, y]);
+                let fluid_before = face(&before, tangent, fluid_cell);
+
+                match condition {
+                    BoundaryConditionCell::NoSlipCell { boundary_condition_velocity } => {
+                        prop_assert_eq!(shared_after, boundary_condition_velocity[normal]);
+                        // The wall velocity halfway between ghost and fluid velocity
+                        let mean = (ghost_after + fluid_before) / 2.0;
+                        prop_assert!((mean - boundary_condition_velocity[tangent]).abs() < 1e-6);
+                    }
+                    BoundaryConditionCell::FreeSlipCell => {
+                        prop_assert_eq!(shared_after, 0.0);
+                        prop_assert_eq!(ghost_after, fluid_before);
+                    }
+                    BoundaryConditionCell::OutFlowCell => {
+                        // Zero gradient through the wall
+                        prop_assert_eq!(shared_after, face(&before, normal, far));
+                        prop_assert_eq!(ghost_after, fluid_before);
+                    }
+                    _ => {}
+                }
+
+                // No pressure gradient and no F or G correction into the wall
+                let [fx, fy] = fluid_cell;
+                prop_assert_eq!(after.pressure(x, y), before.pressure(fx, fy));
+                let [sx, sy] = shared;
+                let fg = if normal == 0 { after.f(sx, sy) } else { after.g(sx, sy) };
+                prop_assert_eq!(fg, shared_after);
+            }
+        }
+    }
+
+    // Mirroring a domain and then applying the boundary conditions gives the mirror
+    // of the original result, on every face the stencils read
+    #[test]
+    fn conditions_commute_with_mirroring(layout in layout(), axis in 0usize..2) {
+        let mut original = layout.space_domain();
+        apply_boundary_conditions(&mut original);
+        let mut mirrored = layout.mirrored(axis).space_domain();
+        apply_boundary_conditions(&mut mirrored);
+
+        let space_size = original.space_size();
+        let [nx, ny] = space_size;
+        for x in 0..nx {
+            for y in 0..ny {
+                let [mx, my] = mirror(space_size, axis, [x, y]);
+                prop_assert!(is_close(original.pressure(x, y), mirrored.pressure(mx, my)));
+                let across = 1 - axis;
+                if is_relevant(&original, across, x, y) {
+                    prop_assert!(
+                        is_close(original.velocity(x, y)[across], mirrored.velocity(mx, my)[across]),
+                        "component {} of {:?}", across, (x, y)
+                    );
+                }
+                let [Some(fx), Some(fy)] = face_source(space_size, axis, [mx, my]) else {
+                    continue;
+                };
+                if is_relevant(&original, axis, fx, fy) {
+                    prop_assert!(
+                        is_close(original.velocity(fx, fy)[axis], -mirrored.velocity(mx, my)[axis]),
+                        "component {} of {:?}", axis, (fx, fy)
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use flow2d_rs::cell::{BoundaryConditionCell, Cell, CellType};
+use flow2d_rs::space_domain::SpaceDomain;
+
+#[test]
+fn rules_of_each_face_follow_the_condition() {
+    let wall = BoundaryConditionCell::NoSlipCell {
+        boundary_condition_velocity: [1.0, -2.0],
+    };
+    for axis in [0, 1] {
+        let rule = wall.rule(axis).unwrap();
+        let [normal, tangential] = [axis, 1 - axis].map(|axis| [1.0, -2.0][axis]);
+        // The far face isn't read for a fixed normal velocity
+        assert_eq!(rule.normal_velocity(5.0, || unreachable!()), normal);
+        // The wall velocity halfway between the ghost and the fluid
+        let ghost = rule.tangential_velocity(0.5).unwrap();
+        assert_eq!((ghost + 0.5) / 2.0, tangential);
+        assert_eq!(rule.pressure(3.0), 3.0);
+    }
+
+    let outflow = BoundaryConditionCell::OutFlowCell.rule(1).unwrap();
+    assert_eq!(outflow.normal_velocity(5.0, || 7.0), 7.0);
+    assert_eq!(outflow.tangential_velocity(0.5), Some(0.5));
+    let inflow = BoundaryConditionCell::InflowCell.rule(0).unwrap();
+    assert_eq!(inflow.normal_velocity(5.0, || unreachable!()), 5.0);
+    assert_eq!(inflow.tangential_velocity(0.5), None);
+    assert_eq!(BoundaryConditionCell::PeriodicCell.rule(0), None);
+}
+
+// A single fluid cell in a ring of walls sees the same rule through all four faces
+#[test]
+fn faces_on_every_side_apply_their_rule() {
+    let wall = BoundaryConditionCell::NoSlipCell {
+        boundary_condition_velocity: [0.5, -0.25],
+    };
+    let mut cells = vec![
+        vec![
+            Cell {
+                cell_type: CellType::BoundaryConditionCell(wall),
+                velocity: [3.0, 4.0],
+                pressure: 9.0,
+                ..Default::default()
+            };
+            3
+        ];
+        3
+    ];
+    cells[1][1] = Cell {
+        velocity: [1.0, 2.0],
+        pressure: 6.0,
+        ..Default::default()
+    };
+    let mut space_domain = SpaceDomain::new(cells, [0.1, 0.1], 0.9);
+    space_domain.update_boundary_velocities();
+    space_domain.update_boundary_pressures_and_fg();
+
+    let [rule_x, rule_y] = [0, 1].map(|axis| wall.rule(axis).unwrap());
+    // Left and right, bottom and top faces of the fluid cell
+    for (x, y) in [(0, 1), (1, 1)] {
+        assert_eq!(space_domain.u(x, y), rule_x.normal_velocity(3.0, || 1.0));
+    }
+    for (x, y) in [(1, 0), (1, 1)] {
+        assert_eq!(space_domain.v(x, y), rule_y.normal_velocity(4.0, || 2.0));
+    }
+    for (x, y) in [(0, 1), (2, 1), (1, 0), (1, 2)] {
+        assert_eq!(space_domain.pressure(x, y), 6.0);
+    }
+}
+
+// Fluid in column 0 next to walls in column 1, the far faces of the fluid lie outside
+// the domain
+#[test]
+fn faces_of_fluid_on_the_domain_edge_apply_their_rule() {
+    for (wall, expected) in [
+        (
+            BoundaryConditionCell::NoSlipCell {
+                boundary_condition_velocity: [0.5, 0.0],
+            },
+            0.5,
+        ),
+        // Zero gradient reads the ghost face at rest
+        (BoundaryConditionCell::OutFlowCell, 0.0),
+    ] {
+        let cells = (0..2)
+            .map(|x| {
+                (0..3)
+                    .map(|_| Cell {
+                        cell_type: if x == 0 {
+                            CellType::FluidCell
+                        } else {
+                            CellType::BoundaryConditionCell(wall)
+                        },
+                        velocity: [3.0, 0.0],
+                        ..Default::default()
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut space_domain = SpaceDomain::new(cells, [0.1, 0.1], 0.9);
+        space_domain.update_boundary_velocities();
+        space_domain.update_boundary_pressures_and_fg();
+
+        for y in 0..3 {
+            assert_eq!(space_domain.u(0, y), expected, "{wall:?} at {y}");
+        }
+    }
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use common::cylinder;
+use flow2d_rs::boundary_segments::BoundarySegment;
+use flow2d_rs::cell::{BoundaryConditionCell, CellType};
+use flow2d_rs::control::Probe;
+use flow2d_rs::presets;
+
+#[test]
+fn channel_segments_report_their_flow_and_force() {
+    let mut simulation = cylinder();
+    let names: Vec<&str> = simulation
+        .segments()
+        .iter()
+        .map(|segment| segment.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        ["inlet", "outlet", "bottom_wall", "top_wall", "cylinder"]
+    );
+
+    // The whole channel height at the inflow velocity
+    let [_, ny] = simulation.space_size();
+    let [_, dy] = simulation.delta_space();
+    let inlet = simulation.segment_statistics("inlet").unwrap();
+    assert_eq!(inlet.face_count, ny - 2);
+    assert!((inlet.length - (ny - 2) as f32 * dy).abs() < 1e-4);
+    assert!((inlet.mean_normal_velocity - presets::CYLINDER_INFLOW_VELOCITY).abs() < 1e-5);
+    assert!(simulation.segment_statistics("nowhere").is_none());
+
+    simulation.step_n(20);
+    let cylinder = simulation.segment_statistics("cylinder").unwrap();
+    assert_eq!(cylinder.flow_rate, 0.0);
+    assert!(cylinder.force[0] > 0.0, "{:?}", cylinder.force);
+    let probe = Probe::SegmentForce {
+        name: "cylinder".to_string(),
+        component: 0,
+    };
+    assert_eq!(probe.read(&simulation), cylinder.force[0]);
+    // Mass leaves through the outlet as it enters through the inlet
+    let outlet = simulation.segment_statistics("outlet").unwrap();
+    let inlet = simulation.segment_statistics("inlet").unwrap();
+    assert!((inlet.flow_rate + outlet.flow_rate).abs() < 1e-2 * inlet.flow_rate);
+}
+
+#[test]
+fn conditions_are_set_by_segment_name() {
+    let mut simulation = cylinder();
+    let wall = BoundaryConditionCell::NoSlipCell {
+        boundary_condition_velocity: [0.0, 0.0],
+    };
+    assert!(simulation.set_segment_condition("outlet", wall));
+    assert!(!simulation.set_segment_condition("nowhere", wall));
+    let outlet = simulation.segment("outlet").unwrap().clone();
+    for &(x, y) in &outlet.cells {
+        assert_eq!(
+            simulation.get_cell(x, y).cell_type,
+            CellType::BoundaryConditionCell(wall)
+        );
+    }
+
+    // Same name replaces, the new segment goes last
+    let [nx, _] = simulation.space_size();
+    simulation.add_segment(BoundarySegment::new("inlet", vec![(0, 1)]));
+    assert_eq!(simulation.segment("inlet").unwrap().cells, [(0, 1)]);
+    assert_eq!(simulation.segments().last().unwrap().name, "inlet");
+    assert!(simulation.remove_segment("inlet"));
+    assert!(!simulation.remove_segment("inlet"));
+    // The right edge without its corners
+    assert_eq!(outlet.cells.len(), simulation.space_size()[1] - 2);
+    assert!(outlet.cells.iter().all(|&(x, _)| x == nx - 1));
+}
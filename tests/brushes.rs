@@ -0,0 +1,95 @@
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+
+use std::f32::consts::PI;
+
+const N: usize = 64;
+
+fn cavity_at_rest() -> Simulation {
+    Simulation::from_preset(presets::lid_driven_cavity_sized([N, N]))
+}
+
+// Integral of u and v over the domain
+fn momentum(simulation: &Simulation) -> [f32; 2] {
+    let [dx, dy] = simulation.delta_space();
+    simulation
+        .cells()
+        .iter()
+        .fold([0.0, 0.0], |momentum, cell| {
+            [
+                momentum[0] + cell.velocity[0] * dx * dy,
+                momentum[1] + cell.velocity[1] * dx * dy,
+            ]
+        })
+}
+
+fn dye_amount(simulation: &Simulation) -> f32 {
+    let [dx, dy] = simulation.delta_space();
+    simulation
+        .cells()
+        .iter()
+        .map(|cell| cell.dye * dx * dy)
+        .sum()
+}
+
+#[test]
+fn an_impulse_adds_its_gaussian_momentum() {
+    let mut simulation = cavity_at_rest();
+    let radius = 0.05;
+    simulation.apply_impulse([0.5, 0.5], radius, [2.0, 0.0]);
+
+    // The gaussian integrates to pi r^2, less the small tail beyond twice the radius
+    let expected = 2.0 * PI * radius.powi(2);
+    let [x, y] = momentum(&simulation);
+    assert!((x - expected).abs() < 0.02 * expected, "{x} != {expected}");
+    assert!(y.abs() < 1e-6);
+}
+
+#[test]
+fn a_brush_deposits_its_dye_and_drags_the_fluid_along() {
+    let mut simulation = cavity_at_rest();
+    let radius = 0.05;
+    simulation.apply_velocity_brush([0.5, 0.5], radius, [0.0, 1.0], Some(3.0));
+
+    let expected = 3.0 * PI * radius.powi(2);
+    let dye = dye_amount(&simulation);
+    assert!(
+        (dye - expected).abs() < 0.02 * expected,
+        "{dye} != {expected}"
+    );
+
+    // From rest the blend adds the same momentum as an impulse of the brush velocity
+    let [x, y] = momentum(&simulation);
+    let expected = PI * radius.powi(2);
+    assert!(x.abs() < 1e-6);
+    assert!((y - expected).abs() < 0.02 * expected, "{y} != {expected}");
+    // and it never overshoots the brush velocity
+    assert!(simulation
+        .cells()
+        .iter()
+        .all(|cell| (0.0..=1.0).contains(&cell.velocity[1])));
+}
+
+#[test]
+fn brushes_over_walls_change_nothing() {
+    let mut simulation = cavity_at_rest();
+    for x in 20..44 {
+        for y in 20..44 {
+            simulation.set_obstacle(x, y, true);
+        }
+    }
+    simulation.iterate_one_timestep();
+    let before = simulation.cells();
+
+    // The brushes reach two radii, 3 cells, from the center of the obstacle
+    let [dx, _] = simulation.delta_space();
+    let radius = 1.5 * dx;
+    simulation.apply_impulse([0.5, 0.5], radius, [1.0, 1.0]);
+    simulation.apply_velocity_brush([0.5, 0.5], radius, [1.0, 1.0], Some(1.0));
+
+    for (cell, before) in simulation.cells().iter().zip(&before) {
+        assert_eq!(cell.velocity, before.velocity);
+        assert_eq!(cell.dye, before.dye);
+    }
+}
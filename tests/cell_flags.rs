@@ -0,0 +1,106 @@
+use flow2d_rs::cell::{BoundaryConditionCell, Cell, CellFlags, CellType};
+use flow2d_rs::space_domain::SpaceDomain;
+
+fn wall() -> Cell {
+    Cell {
+        cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+            boundary_condition_velocity: [0.0, 0.0],
+        }),
+        ..Cell::default()
+    }
+}
+
+// 5 x 4 cells of fluid inside a ring of walls
+fn boxed() -> SpaceDomain {
+    let cells = (0..5)
+        .map(|x| {
+            (0..4)
+                .map(|y| {
+                    if x == 0 || y == 0 || x == 4 || y == 3 {
+                        wall()
+                    } else {
+                        Cell::default()
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    SpaceDomain::new(cells, [0.1, 0.1], 0.9)
+}
+
+#[test]
+fn flags_summarize_cells_and_their_fluid_neighbors() {
+    let space_domain = boxed();
+    assert_eq!(
+        space_domain.flags(0, 1),
+        CellFlags::OBSTACLE | CellFlags::SURFACE | CellFlags::FLUID_EAST
+    );
+    // Corners touch no fluid
+    assert_eq!(space_domain.flags(0, 0), CellFlags::OBSTACLE);
+    assert_eq!(
+        space_domain.flags(2, 2),
+        CellFlags::FLUID | CellFlags::FLUID_WEST | CellFlags::FLUID_EAST | CellFlags::FLUID_SOUTH
+    );
+    assert!(space_domain.flags(1, 1).is_fluid());
+    assert!(!space_domain.flags(1, 1).intersects(CellFlags::BOUNDARY));
+    assert_eq!(space_domain.cell_flags().len(), 20);
+    assert_eq!(space_domain.neighbor_flags(0, 0, [-1, 0]), CellFlags::EMPTY);
+}
+
+#[test]
+fn flags_follow_geometry_changes() {
+    let mut space_domain = boxed();
+    assert!(space_domain.set_obstacle(2, 1, true));
+    assert!(space_domain
+        .flags(2, 1)
+        .contains(CellFlags::OBSTACLE | CellFlags::SURFACE));
+    assert!(!space_domain.flags(1, 1).contains(CellFlags::FLUID_EAST));
+    assert!(!space_domain.flags(2, 0).intersects(CellFlags::SURFACE));
+
+    space_domain.set_cell_type(
+        0,
+        2,
+        CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell),
+    );
+    assert_eq!(
+        space_domain.flags(0, 2),
+        CellFlags::INFLOW | CellFlags::SURFACE | CellFlags::FLUID_EAST
+    );
+
+    // Incremental updates agree with recomputing everything
+    let flags = space_domain.cell_flags().to_vec();
+    space_domain.update_flags();
+    assert_eq!(space_domain.cell_flags(), &flags[..]);
+
+    let cells = boxed().cells();
+    space_domain.set_cells(&cells);
+    assert_eq!(space_domain.cell_flags(), boxed().cell_flags());
+}
+
+#[test]
+fn cell_lists_hold_the_fluid_and_boundary_cells_in_order() {
+    let mut space_domain = boxed();
+    let scan = |space_domain: &SpaceDomain, flag: CellFlags| -> Vec<usize> {
+        (0..20)
+            .filter(|&index| space_domain.cell_flags()[index].intersects(flag))
+            .collect()
+    };
+    assert_eq!(space_domain.fluid_cells(), &[5, 6, 9, 10, 13, 14]);
+    assert_eq!(space_domain.boundary_cells().len(), 14);
+    assert_eq!(space_domain.fluid_cell_range(2..4), 2..6);
+    assert_eq!(space_domain.position(13), (3, 1));
+
+    space_domain.set_obstacle(2, 1, true);
+    space_domain.set_cell_type(0, 0, CellType::VoidCell);
+    space_domain.set_cell_type(3, 2, CellType::VoidCell);
+    assert_eq!(space_domain.fluid_cells(), &[5, 6, 10, 13]);
+    assert_eq!(
+        space_domain.fluid_cells(),
+        &scan(&space_domain, CellFlags::FLUID)[..]
+    );
+    assert_eq!(
+        space_domain.boundary_cells(),
+        &scan(&space_domain, CellFlags::BOUNDARY)[..]
+    );
+    assert_eq!(space_domain.fluid_cell_range(2..3), 2..3);
+}
@@ -0,0 +1,64 @@
+use flow2d_rs::field::Field;
+use flow2d_rs::observer::StepEvent;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[test]
+fn step_n_matches_single_steps() {
+    let preset = || presets::cylinder_cross_flow_sized([55, 21]);
+    let mut single = Simulation::from_preset(preset());
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = events.clone();
+    single.add_observer(move |event: &StepEvent| {
+        recorded
+            .lock()
+            .unwrap()
+            .push((event.pressure_iterations, event.pressure_residual));
+    });
+    for _ in 0..12 {
+        single.iterate_one_timestep();
+    }
+
+    let mut chunked = Simulation::from_preset(preset());
+    let stats = chunked.step_n(12);
+    assert_eq!(stats.steps, 12);
+    assert_eq!(chunked.time(), single.time());
+    assert!((stats.simulated_time - 12.0 * chunked.delta_time()).abs() < 1e-5);
+    for field in Field::ALL {
+        assert_eq!(
+            chunked.field(field).to_vec(),
+            single.field(field).to_vec(),
+            "{field:?}"
+        );
+    }
+    assert_eq!(chunked.pressure_range(), single.pressure_range());
+    assert_eq!(chunked.speed_range(), single.speed_range());
+
+    let events = events.lock().unwrap();
+    let iterations: Vec<usize> = events.iter().map(|event| event.0).collect();
+    assert_eq!(stats.pressure_iterations, iterations.iter().sum::<usize>());
+    assert_eq!(
+        stats.max_pressure_iterations,
+        *iterations.iter().max().unwrap()
+    );
+    let residual = events.iter().map(|event| event.1).fold(0.0, f32::max);
+    assert_eq!(stats.max_pressure_residual, residual);
+    assert_eq!(chunked.step_n(0).steps, 0);
+}
+
+#[test]
+fn step_for_keeps_to_the_budget() {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([32, 32]));
+    // Always at least one step
+    assert_eq!(simulation.step_for(Duration::ZERO).steps, 1);
+
+    let budget = Duration::from_millis(100);
+    let stats = simulation.step_for(budget);
+    assert!(stats.steps > 1);
+    let per_step = stats.wall_time / stats.steps as u32;
+    assert!(stats.wall_time <= budget + 2 * per_step + Duration::from_millis(20));
+    assert!((simulation.time() - (stats.steps + 1) as f32 * simulation.delta_time()).abs() < 1e-4);
+}
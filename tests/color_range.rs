@@ -0,0 +1,77 @@
+use flow2d_rs::cell::CellType;
+use flow2d_rs::colormap::{ColorRange, RangeMode};
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+
+// Cavity at rest whose fluid pressure spans [low, high], the walls hold 100
+fn with_pressure(simulation: &mut Simulation, low: f32, high: f32) {
+    let mut checkpoint = simulation.checkpoint();
+    let fluid_cells = checkpoint
+        .cells
+        .iter()
+        .filter(|cell| matches!(cell.cell_type, CellType::FluidCell))
+        .count();
+    let mut fluid = 0;
+    for cell in checkpoint.cells.iter_mut() {
+        cell.velocity = [0.0, 0.0];
+        cell.pressure = if matches!(cell.cell_type, CellType::FluidCell) {
+            fluid += 1;
+            low + (high - low) * (fluid - 1) as f32 / (fluid_cells - 1) as f32
+        } else {
+            100.0
+        };
+    }
+    simulation.restore(&checkpoint);
+}
+
+fn pressure_ranges(mode: RangeMode) -> [[f32; 2]; 2] {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([8, 8]));
+    simulation.set_range_modes(mode, RangeMode::Autoscale);
+    with_pressure(&mut simulation, -2.0, 3.0);
+    let first = simulation.pressure_range();
+    with_pressure(&mut simulation, -1.0, 1.0);
+    [first, simulation.pressure_range()]
+}
+
+#[test]
+fn ranges_of_a_known_field_follow_the_mode() {
+    // Walls are left out
+    assert_eq!(
+        pressure_ranges(RangeMode::Autoscale),
+        [[-2.0, 3.0], [-1.0, 1.0]]
+    );
+    assert_eq!(
+        pressure_ranges(RangeMode::RunningMinMax),
+        [[-2.0, 3.0], [-2.0, 3.0]]
+    );
+    assert_eq!(
+        pressure_ranges(RangeMode::Fixed([0.0, 10.0])),
+        [[0.0, 10.0], [0.0, 10.0]]
+    );
+    // Halfway from the first range to the second
+    assert_eq!(
+        pressure_ranges(RangeMode::Smoothed(0.5)),
+        [[-2.0, 3.0], [-1.5, 2.0]]
+    );
+}
+
+#[test]
+fn ranges_without_fluid_keep_the_previous_one() {
+    let mut running = ColorRange::new(RangeMode::RunningMinMax);
+    assert_eq!(running.update([1.0, 2.0]), [1.0, 2.0]);
+    assert_eq!(running.update([0.0, 1.5]), [0.0, 2.0]);
+    assert_eq!(
+        running.update([f32::INFINITY, f32::NEG_INFINITY]),
+        [0.0, 2.0]
+    );
+    assert_eq!(running.update([0.5, 3.0]), [0.0, 3.0]);
+
+    // A new mode forgets the history
+    running.set_mode(RangeMode::Autoscale);
+    assert_eq!(running.update([0.5, 1.0]), [0.5, 1.0]);
+    running.set_mode(RangeMode::RunningMinMax);
+    running.update([0.5, 1.0]);
+    running.reset();
+    assert_eq!(running.update([0.7, 0.8]), [0.7, 0.8]);
+}
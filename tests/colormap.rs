@@ -0,0 +1,94 @@
+use flow2d_rs::colormap::Colormap;
+
+fn luminance([r, g, b]: [f32; 3]) -> f32 {
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+fn assert_close(color: [f32; 3], expected: [f32; 3]) {
+    assert!(
+        color
+            .iter()
+            .zip(expected)
+            .all(|(c, e)| (c - e).abs() < 1e-6),
+        "{color:?} != {expected:?}"
+    );
+}
+
+fn samples(colormap: &Colormap) -> Vec<[f32; 3]> {
+    (0..=256)
+        .map(|i| colormap.color(i as f32 / 256.0))
+        .collect()
+}
+
+#[test]
+fn ends_of_the_range_give_the_end_stops() {
+    let palettes = [
+        (
+            Colormap::viridis(),
+            [0.267, 0.005, 0.329],
+            [0.993, 0.906, 0.144],
+        ),
+        (
+            Colormap::plasma(),
+            [0.050, 0.030, 0.528],
+            [0.940, 0.975, 0.131],
+        ),
+        (
+            Colormap::coolwarm(),
+            [0.230, 0.299, 0.754],
+            [0.706, 0.016, 0.150],
+        ),
+        (Colormap::rainbow(), [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]),
+        (Colormap::grayscale(), [0.0, 0.0, 0.0], [1.0, 1.0, 1.0]),
+    ];
+    for (colormap, low, high) in palettes {
+        assert_close(colormap.color(0.0), low);
+        assert_close(colormap.color(1.0), high);
+        // Clamped outside [0, 1], NaN is the lowest color
+        assert_close(colormap.color(-3.0), low);
+        assert_close(colormap.color(7.0), high);
+        assert_close(colormap.color(f32::NAN), low);
+        assert_close(colormap.map(5.0, [1.0, 5.0]), high);
+    }
+}
+
+#[test]
+fn sequential_maps_grow_lighter() {
+    for colormap in [
+        Colormap::viridis(),
+        Colormap::plasma(),
+        Colormap::grayscale(),
+    ] {
+        let lightness: Vec<f32> = samples(&colormap).into_iter().map(luminance).collect();
+        assert!(
+            lightness.windows(2).all(|pair| pair[0] <= pair[1]),
+            "{colormap:?}"
+        );
+    }
+
+    // Linear between the stops
+    let gray = Colormap::grayscale();
+    assert_close(gray.color(0.25), [0.25; 3]);
+    assert_close(gray.map(3.0, [2.0, 6.0]), [0.25; 3]);
+}
+
+#[test]
+fn diverging_maps_are_symmetric_around_zero() {
+    let coolwarm = Colormap::coolwarm();
+    assert!(coolwarm.is_diverging());
+    // Zero is in the middle whatever the range
+    assert_eq!(coolwarm.map(0.0, [-1.0, 3.0]), coolwarm.color(0.5));
+    assert_eq!(coolwarm.map(-3.0, [-1.0, 3.0]), coolwarm.color(0.0));
+
+    // The blue side darkens towards -1 and the red side towards +1
+    let lightness: Vec<f32> = samples(&coolwarm).into_iter().map(luminance).collect();
+    assert!(lightness[..=128].windows(2).all(|pair| pair[0] <= pair[1]));
+    assert!(lightness[128..].windows(2).all(|pair| pair[0] >= pair[1]));
+}
+
+#[test]
+fn rgba_buffers_are_refilled_with_transparent_gaps() {
+    let mut buffer = vec![7; 3];
+    Colormap::grayscale().fill_rgba([Some(0.0), None, Some(2.0)], [0.0, 2.0], &mut buffer);
+    assert_eq!(buffer, [0, 0, 0, 255, 0, 0, 0, 0, 255, 255, 255, 255]);
+}
@@ -0,0 +1,13 @@
+// Fixtures shared by the integration tests, each test uses only some of them
+#![allow(dead_code)]
+
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+
+// Half the default resolution of the cylinder in cross flow, quick to step while the
+// wake still sheds vortices
+pub const CYLINDER_SIZE: [usize; 2] = [55, 21];
+
+pub fn cylinder() -> Simulation {
+    Simulation::from_preset(presets::cylinder_cross_flow_sized(CYLINDER_SIZE))
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use common::cylinder;
+use flow2d_rs::cell::{BoundaryConditionCell, CellType};
+use flow2d_rs::control::{Actuator, ControlLoop, Controller, Pid, Probe, RegionForce};
+use flow2d_rs::presets;
+
+#[test]
+fn pid_terms_add_up_and_the_integral_does_not_wind_up() {
+    let mut proportional = Pid::new(2.0, 0.0, 0.0, 1.0).with_bias(0.5);
+    assert_eq!(proportional.update(0.25, 0.1), 0.5 + 2.0 * 0.75);
+
+    let mut integral = Pid::new(0.0, 1.0, 0.0, 1.0);
+    assert!((integral.update(0.0, 0.5) - 0.5).abs() < 1e-6);
+    assert!((integral.update(0.0, 0.5) - 1.0).abs() < 1e-6);
+    integral.reset();
+    assert!((integral.update(0.0, 0.5) - 0.5).abs() < 1e-6);
+
+    // The first update has no rate of change
+    let mut derivative = Pid::new(0.0, 0.0, 1.0, 0.0);
+    assert_eq!(derivative.update(1.0, 0.5), 0.0);
+    assert_eq!(derivative.update(2.0, 0.5), -2.0);
+
+    // Clamped outputs leave the integral as it was, so it recovers at once
+    let mut clamped = Pid::new(0.0, 1.0, 0.0, 1.0).with_output_range(Some([-0.5, 0.5]));
+    for _ in 0..10 {
+        assert_eq!(clamped.update(0.0, 1.0), 0.5);
+    }
+    assert!((clamped.update(2.0, 0.25) + 0.25).abs() < 1e-6);
+}
+
+#[test]
+fn the_loop_holds_a_probe_at_its_setpoint_through_the_inflow() {
+    let mut simulation = cylinder();
+    let mut control_loop = ControlLoop::new(
+        Probe::Velocity {
+            x: 5,
+            y: 5,
+            component: 0,
+        },
+        Pid::new(0.5, 5.0, 0.0, 1.0)
+            .with_bias(presets::CYLINDER_INFLOW_VELOCITY)
+            .with_output_range(Some([0.0, 3.0])),
+        Actuator::Inflow {
+            direction: [1.0, 0.0],
+        },
+    );
+    for _ in 0..300 {
+        control_loop.step(&mut simulation);
+    }
+    assert_eq!(control_loop.history.len(), 300);
+    let &(time, measurement, actuation) = control_loop.history.last().unwrap();
+    assert!(time > 0.0);
+    assert!((measurement - 1.0).abs() < 0.05, "{measurement}");
+    assert!(actuation < presets::CYLINDER_INFLOW_VELOCITY);
+}
+
+#[test]
+fn wall_jets_and_region_forces_act_on_the_flow() {
+    let mut simulation = cylinder();
+    simulation.add_region_force(RegionForce {
+        name: "push".to_string(),
+        x: 40..50,
+        y: 1..20,
+        force: [0.0, 0.0],
+    });
+    let mut reference = simulation.clone();
+    let probe = Probe::Velocity {
+        x: 45,
+        y: 10,
+        component: 1,
+    };
+    let mut loops = [
+        ControlLoop::new(
+            probe.clone(),
+            |_: f32, _: f32| 2.0,
+            Actuator::BodyForce {
+                name: "push".to_string(),
+                direction: [0.0, 1.0],
+            },
+        ),
+        ControlLoop::new(
+            probe,
+            |_: f32, _: f32| 0.5,
+            Actuator::WallJet {
+                x: 20..25,
+                y: 0..1,
+                direction: [0.0, 1.0],
+            },
+        ),
+    ];
+    for _ in 0..20 {
+        for control_loop in loops.iter_mut() {
+            control_loop.actuate(&mut simulation);
+        }
+        simulation.iterate_one_timestep();
+        reference.iterate_one_timestep();
+    }
+
+    assert_eq!(simulation.region_forces()[0].force, [0.0, 2.0]);
+    assert_eq!(
+        simulation.get_cell(22, 0).cell_type,
+        CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+            boundary_condition_velocity: [0.0, 0.5]
+        })
+    );
+    assert!(
+        simulation.get_centered_velocity(45, 10)[1]
+            > reference.get_centered_velocity(45, 10)[1] + 0.01
+    );
+    assert!(
+        simulation.get_centered_velocity(22, 1)[1]
+            > reference.get_centered_velocity(22, 1)[1] + 0.1
+    );
+    assert!(!simulation.set_wall_velocity(50, 5, [1.0, 0.0]));
+}
@@ -0,0 +1,75 @@
+use flow2d_rs::cell::Cell;
+use flow2d_rs::dirty_regions::{DirtyRegion, DirtyTracker};
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::space_domain::Fields;
+
+const SIZE: [usize; 2] = [10, 6];
+
+fn fluid() -> Fields {
+    Fields::from_cells(&vec![Cell::default(); SIZE[0] * SIZE[1]])
+}
+
+fn region(x: std::ops::Range<usize>, y: std::ops::Range<usize>) -> DirtyRegion {
+    DirtyRegion { x, y }
+}
+
+#[test]
+fn only_changed_tiles_are_reported() {
+    let mut fields = fluid();
+    let mut tracker = DirtyTracker::new(4, 0.1);
+    // Everything is new at the first update
+    assert_eq!(tracker.update(&fields, SIZE), [region(0..10, 0..6)]);
+
+    // Cell (5, 1) in the tile of x 4..8 and y 0..4, cell (9, 5) in the clipped corner tile
+    fields.u[5 * 6 + 1] = 1.0;
+    fields.dye[9 * 6 + 5] = 1.0;
+    // Below the threshold
+    fields.pressure[0] = 0.05;
+    assert_eq!(
+        tracker.update(&fields, SIZE),
+        [region(4..8, 0..4), region(8..10, 4..6)]
+    );
+    assert_eq!(tracker.dirty_regions().len(), 2);
+}
+
+#[test]
+fn reported_tiles_are_clean_until_they_change_again() {
+    let mut fields = fluid();
+    let mut tracker = DirtyTracker::new(4, 0.1);
+    tracker.update(&fields, SIZE);
+
+    fields.v[0] = 1.0;
+    assert_eq!(tracker.update(&fields, SIZE), [region(0..4, 0..4)]);
+    assert!(tracker.update(&fields, SIZE).is_empty());
+    assert!(tracker.dirty_regions().is_empty());
+
+    // Drift below the threshold adds up against the last report
+    for _ in 0..3 {
+        fields.v[0] += 0.04;
+        tracker.update(&fields, SIZE);
+    }
+    assert_eq!(tracker.dirty_regions(), [region(0..4, 0..4)]);
+
+    tracker.mark_all_dirty();
+    assert_eq!(tracker.update(&fields, SIZE), [region(0..10, 0..6)]);
+}
+
+#[test]
+fn simulations_report_the_tiles_of_each_timestep() {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([16, 16]));
+    assert!(simulation.dirty_regions().is_empty());
+
+    // Pressure answers the lid everywhere at once
+    simulation.enable_dirty_tracking(4, 0.0);
+    simulation.iterate_one_timestep();
+    assert_eq!(simulation.dirty_regions().len(), 16);
+
+    simulation.enable_dirty_tracking(4, 1e3);
+    simulation.iterate_one_timestep();
+    assert!(simulation.dirty_regions().is_empty());
+
+    simulation.disable_dirty_tracking();
+    simulation.iterate_one_timestep();
+    assert!(simulation.dirty_regions().is_empty());
+}
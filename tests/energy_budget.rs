@@ -0,0 +1,44 @@
+use flow2d_rs::energy_budget::{EnergyBudgetTracker, EnergyState};
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+
+#[test]
+fn the_lid_drives_the_cavity_budget() {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity());
+    let at_rest = EnergyState::from_solver(&simulation, simulation.reynolds(), [0.0, 0.0]);
+    assert_eq!(at_rest.kinetic_energy, 0.0);
+
+    let mut tracker = EnergyBudgetTracker::new();
+    assert!(tracker
+        .record(
+            &simulation,
+            simulation.reynolds(),
+            simulation.acceleration()
+        )
+        .is_none());
+    for _ in 0..100 {
+        simulation.iterate_one_timestep();
+        tracker.record(
+            &simulation,
+            simulation.reynolds(),
+            simulation.acceleration(),
+        );
+    }
+    assert_eq!(tracker.budgets().len(), 100);
+
+    let budget = tracker.latest().unwrap();
+    assert!((budget.time - simulation.time()).abs() < 1e-6);
+    assert_eq!(budget.body_force_work, 0.0);
+    // The wall shear of the lid is the only source, the viscosity takes most of it
+    assert!(budget.boundary_flux > budget.dissipation);
+    assert!(budget.dissipation > 0.0);
+    assert!(budget.kinetic_energy_rate > 0.0);
+    assert!(budget.numerical_dissipation.abs() < 0.15 * budget.dissipation);
+
+    let mut csv = Vec::new();
+    tracker.write_csv(&mut csv).unwrap();
+    assert_eq!(String::from_utf8(csv).unwrap().lines().count(), 101);
+
+    tracker.clear();
+    assert!(tracker.latest().is_none());
+}
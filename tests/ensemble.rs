@@ -0,0 +1,72 @@
+mod common;
+
+use flow2d_rs::ensemble::Ensemble;
+use flow2d_rs::perturbation::Perturbation;
+use flow2d_rs::presets;
+use flow2d_rs::solver::FluidSolver;
+
+#[test]
+fn identical_members_have_no_spread() {
+    let ensemble = Ensemble::new(
+        presets::cylinder_cross_flow_sized,
+        common::CYLINDER_SIZE,
+        3,
+        10,
+    );
+    let result = ensemble.run();
+    assert_eq!(result.members.len(), 3);
+    for member in &result.members {
+        assert_eq!(member.member.reynolds, 100.0);
+        assert_eq!(member.forces, result.members[0].forces);
+    }
+    // Up to the rounding of the mean
+    for statistics in [&result.u, &result.pressure] {
+        assert!(statistics.variance.iter().all(|&variance| variance < 1e-10));
+    }
+    assert_eq!(result.drag.min, result.drag.max);
+    assert_eq!(result.lift.time.len(), 10);
+    assert!(result.drag.mean.iter().all(|&drag| drag > 0.0));
+}
+
+#[test]
+fn perturbed_members_spread_around_their_mean() {
+    let ensemble = || {
+        Ensemble::new(
+            presets::cylinder_cross_flow_sized,
+            common::CYLINDER_SIZE,
+            4,
+            10,
+        )
+        .seed(3)
+        .perturbation(Some(Perturbation {
+            amplitude: 0.05,
+            wavelength: 0.5,
+            seed: 0,
+        }))
+        .reynolds_jitter(0.1)
+        .inflow_jitter(0.05)
+    };
+    let members = ensemble().members();
+    assert_eq!(members, ensemble().members());
+    assert_ne!(members[0].seed, members[1].seed);
+    assert_ne!(members[0].reynolds, members[1].reynolds);
+
+    let result = ensemble().run();
+    assert_eq!(ensemble().parallel(true).run(), result);
+    assert!(result.u.variance.iter().any(|&variance| variance > 0.0));
+    assert!(result.v.variance.iter().all(|&variance| variance >= 0.0));
+    for step in 0..result.lift.mean.len() {
+        assert!(result.lift.min[step] <= result.lift.mean[step] + 1e-6);
+        assert!(result.lift.mean[step] <= result.lift.max[step] + 1e-6);
+    }
+    assert!(result.drag.min.last() < result.drag.max.last());
+
+    // The mean field is that of the member runs
+    let index = 20 * 21 + 5;
+    let sum: f32 = members
+        .iter()
+        .map(|member| ensemble().run_member(member).1.fields().u[index])
+        .sum();
+    assert!((sum / 4.0 - result.u.mean[index]).abs() < 1e-5);
+    assert!((0.0..=1.0).contains(&result.shedding_fraction()));
+}
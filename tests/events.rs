@@ -0,0 +1,57 @@
+use flow2d_rs::events::{Event, EventSchedule};
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+
+fn acceleration(x: f32) -> Event {
+    Event::SetAcceleration([x, 0.0])
+}
+
+#[test]
+fn schedules_hand_out_due_events_in_time_order_once() {
+    let mut schedule = EventSchedule::new()
+        .with_event(2.0, acceleration(2.0))
+        .with_event(1.0, acceleration(1.0))
+        .with_event(3.0, acceleration(3.0));
+    // Same time as an earlier one, fires after it
+    schedule.add(1.0, acceleration(1.5));
+
+    assert!(schedule.take_due(0.5).is_empty());
+    assert_eq!(
+        schedule.take_due(2.0),
+        [acceleration(1.0), acceleration(1.5), acceleration(2.0)]
+    );
+    assert!(schedule.take_due(2.5).is_empty());
+    assert_eq!(schedule.pending_events(), &[(3.0, acceleration(3.0))]);
+    assert_eq!(schedule.take_due(10.0), [acceleration(3.0)]);
+    assert!(schedule.take_due(10.0).is_empty());
+    assert_eq!(schedule.events().len(), 4);
+
+    // Late additions for a time already passed fire on the next call
+    schedule.add(0.0, acceleration(0.0));
+    assert_eq!(schedule.take_due(10.0), [acceleration(0.0)]);
+}
+
+#[test]
+fn events_fire_at_the_timestep_closest_to_their_time() {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([8, 8]));
+    let delta_time = simulation.delta_time();
+    // Closer to the start of the fourth timestep than of the third
+    simulation.schedule_event(2.6 * delta_time, acceleration(1.0));
+    simulation.schedule_event(2.6 * delta_time, acceleration(2.0));
+
+    for _ in 0..3 {
+        simulation.iterate_one_timestep();
+        assert_eq!(simulation.acceleration(), [0.0, 0.0]);
+    }
+    simulation.iterate_one_timestep();
+    // Both fired, in the order they were scheduled
+    assert_eq!(simulation.acceleration(), [2.0, 0.0]);
+    assert!(simulation.events().pending_events().is_empty());
+
+    // and not again
+    simulation.set_acceleration([0.0, 0.0]);
+    for _ in 0..3 {
+        simulation.iterate_one_timestep();
+    }
+    assert_eq!(simulation.acceleration(), [0.0, 0.0]);
+}
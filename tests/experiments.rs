@@ -0,0 +1,26 @@
+use flow2d_rs::experiments::{self, Experiment};
+use flow2d_rs::presets;
+use flow2d_rs::run_controller::StopReason;
+
+#[test]
+fn sweep_runs_every_combination() {
+    let experiment = Experiment::new(presets::lid_driven_cavity_sized, [128, 128], 5)
+        .reynolds(&[100.0, 400.0])
+        .space_sizes(&[[16, 16], [24, 24]])
+        .parallel(true);
+    assert_eq!(experiment.cases().len(), 4);
+
+    let results = experiment.run();
+    assert_eq!(results.len(), 4);
+    for (result, case) in results.iter().zip(experiment.cases()) {
+        assert_eq!(result.case, case);
+        assert_eq!(result.reason, StopReason::MaxSteps);
+        assert_eq!(result.steps, 5);
+        // No obstacle and no inflow in the cavity
+        assert_eq!(result.strouhal, None);
+    }
+
+    let mut table = Vec::new();
+    experiments::write_csv(&mut table, &results).unwrap();
+    assert_eq!(String::from_utf8(table).unwrap().lines().count(), 5);
+}
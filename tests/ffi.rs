@@ -0,0 +1,143 @@
+use flow2d_rs::ffi::*;
+use flow2d_rs::field::Field;
+
+use std::ffi::CString;
+use std::ptr;
+
+#[test]
+fn the_c_interface_steps_and_copies_fields() {
+    let name = CString::new("lid_driven_cavity").unwrap();
+    unsafe {
+        let simulation = flow2d_simulation_create(name.as_ptr());
+        assert!(!simulation.is_null());
+
+        let (mut width, mut height) = (0, 0);
+        assert_eq!(
+            flow2d_simulation_size(simulation, &mut width, &mut height),
+            Flow2dStatus::Ok
+        );
+        flow2d_simulation_step(simulation, 3);
+        assert!(flow2d_simulation_time(simulation) > 0.0);
+
+        let mut u = vec![0.0; width * height];
+        assert_eq!(
+            flow2d_simulation_copy_field(simulation, FLOW2D_FIELD_U, u.as_mut_ptr(), u.len() - 1),
+            Flow2dStatus::BufferTooSmall
+        );
+        assert_eq!(
+            flow2d_simulation_copy_field(simulation, 7, u.as_mut_ptr(), u.len()),
+            Flow2dStatus::InvalidArgument
+        );
+        assert_eq!(
+            flow2d_simulation_copy_field(simulation, FLOW2D_FIELD_U, u.as_mut_ptr(), u.len()),
+            Flow2dStatus::Ok
+        );
+        // The lid drags the fluid below it
+        assert!(u[width / 2 * height + height - 2] > 0.0);
+
+        let mut parameters = Flow2dParameters {
+            reynolds: 0.0,
+            acceleration: [0.0, 0.0],
+            omega: 0.0,
+            delta_time: 0.0,
+        };
+        flow2d_simulation_get_parameters(simulation, &mut parameters);
+        let reynolds = parameters.reynolds;
+        parameters.omega = 2.5;
+        assert_eq!(
+            flow2d_simulation_set_parameters(simulation, &parameters),
+            Flow2dStatus::InvalidArgument
+        );
+        parameters.omega = 1.5;
+        parameters.reynolds = reynolds / 2.0;
+        assert_eq!(
+            flow2d_simulation_set_parameters(simulation, &parameters),
+            Flow2dStatus::Ok
+        );
+        flow2d_simulation_get_parameters(simulation, &mut parameters);
+        assert_eq!(
+            [parameters.reynolds, parameters.omega],
+            [reynolds / 2.0, 1.5]
+        );
+
+        // Loading a preset restarts the handle
+        assert_eq!(
+            flow2d_simulation_load_preset(simulation, name.as_ptr()),
+            Flow2dStatus::Ok
+        );
+        assert_eq!(flow2d_simulation_time(simulation), 0.0);
+
+        let unknown = CString::new("no_such_preset").unwrap();
+        assert!(flow2d_simulation_create(unknown.as_ptr()).is_null());
+        assert!(flow2d_simulation_create(ptr::null()).is_null());
+        flow2d_simulation_destroy(simulation);
+        flow2d_simulation_destroy(ptr::null_mut());
+    }
+}
+
+// Parameters between the parenthesis after name
+fn parameter_count(declarations: &str, name: &str) -> Option<usize> {
+    let start = declarations.find(&format!("{name}("))? + name.len() + 1;
+    let parameters = &declarations[start..start + declarations[start..].find(')')?];
+    Some(
+        parameters
+            .split(',')
+            .filter(|parameter| !parameter.trim().is_empty())
+            .count(),
+    )
+}
+
+#[test]
+fn the_header_matches_the_rust_interface() {
+    let header = include_str!("../include/flow2d.h");
+    let source = include_str!("../src/ffi.rs");
+
+    let constants = [
+        FLOW2D_FIELD_PRESSURE,
+        FLOW2D_FIELD_U,
+        FLOW2D_FIELD_V,
+        FLOW2D_FIELD_PSI,
+    ];
+    assert_eq!(constants.len(), Field::ALL.len());
+    assert_eq!(
+        header.matches("#define FLOW2D_FIELD_").count(),
+        Field::ALL.len()
+    );
+    for (index, (field, constant)) in Field::ALL.iter().zip(constants).enumerate() {
+        assert_eq!(constant as usize, index, "{field:?}");
+        let define = format!(
+            "#define FLOW2D_FIELD_{} {index}\n",
+            field.name().to_uppercase()
+        );
+        assert!(header.contains(&define), "{define}");
+    }
+
+    let statuses = [
+        Flow2dStatus::Ok,
+        Flow2dStatus::InvalidArgument,
+        Flow2dStatus::BufferTooSmall,
+    ];
+    assert_eq!(header.matches("Flow2dStatus_").count(), statuses.len());
+    for status in statuses {
+        let variant = format!("Flow2dStatus_{status:?} = {},", status as u32);
+        assert!(header.contains(&variant), "{variant}");
+    }
+
+    let functions: Vec<&str> = source
+        .split("extern \"C\" fn ")
+        .skip(1)
+        .map(|rest| &rest[..rest.find('(').unwrap()])
+        .collect();
+    let declarations = header
+        .lines()
+        .filter(|line| !line.starts_with("//") && line.contains("flow2d_"))
+        .count();
+    assert_eq!(declarations, functions.len());
+    for function in functions {
+        assert_eq!(
+            parameter_count(header, function),
+            parameter_count(source, function),
+            "{function}"
+        );
+    }
+}
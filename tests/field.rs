@@ -0,0 +1,112 @@
+use flow2d_rs::cell::{Cell, CellType};
+use flow2d_rs::field::{Field, FieldView, Location};
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+use flow2d_rs::space_domain::SpaceDomain;
+
+#[test]
+fn a_field_view_knows_its_geometry_and_ignores_walls() {
+    let mut cells = vec![vec![Cell::default(); 3]; 4];
+    cells[0][0].cell_type = CellType::VoidCell;
+    cells[0][0].pressure = 100.0;
+    cells[1][2].pressure = -2.0;
+    cells[3][1].pressure = 5.0;
+    let space_domain = SpaceDomain::new(cells, [0.5, 0.25], 0.9);
+
+    let pressure = space_domain.field(Field::Pressure);
+    assert_eq!(pressure.field(), Some(Field::Pressure));
+    assert_eq!(pressure.shape(), [4, 3]);
+    assert_eq!(pressure.extent(), [2.0, 0.75]);
+    assert_eq!(pressure.position(1, 2), [0.75, 0.625]);
+    assert_eq!(space_domain.field(Field::U).position(1, 2), [1.0, 0.625]);
+    assert_eq!(pressure.range(), Some([-2.0, 5.0]));
+    assert_eq!(pressure.iter().count(), 12);
+    assert_eq!(pressure.fluid_iter().count(), 11);
+
+    assert_eq!(pressure.column(1), &[0.0, 0.0, -2.0]);
+    assert_eq!(pressure.row(2).collect::<Vec<_>>(), [0.0, -2.0, 0.0, 0.0]);
+
+    let window = pressure.slice(1..4, 1..3);
+    assert_eq!(window.shape(), [3, 2]);
+    assert_eq!(window.get(0, 1), -2.0);
+    assert_eq!(window.position(0, 1), pressure.position(1, 2));
+    assert_eq!(window.to_vec(), [0.0, -2.0, 0.0, 0.0, 5.0, 0.0]);
+    assert_eq!(window.slice(2..3, 0..1).max(), Some(5.0));
+    assert_eq!(pressure.slice(0..1, 0..1).range(), None);
+}
+
+#[test]
+fn every_field_of_a_simulation_matches_its_cells() {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity());
+    for _ in 0..5 {
+        simulation.iterate_one_timestep();
+    }
+
+    let cells = simulation.cells();
+    let value = |field: Field, cell: &Cell| match field {
+        Field::Pressure => cell.pressure,
+        Field::U => cell.velocity[0],
+        Field::V => cell.velocity[1],
+        Field::Psi => cell.psi,
+    };
+    for field in Field::ALL {
+        let view: FieldView = simulation.field(field);
+        assert_eq!(view.shape(), simulation.space_size());
+        for ((_, actual), cell) in view.iter().zip(cells.iter()) {
+            assert_eq!(actual, value(field, cell), "{}", field.name());
+        }
+    }
+}
+
+#[test]
+fn downsampling_averages_the_fluid_of_each_block() {
+    let mut cells = vec![vec![Cell::default(); 3]; 5];
+    cells[0][0].cell_type = CellType::VoidCell;
+    cells[0][0].pressure = 100.0;
+    cells[0][1].pressure = 3.0;
+    cells[1][1].pressure = 1.0;
+    cells[4][2].pressure = 8.0;
+    let space_domain = SpaceDomain::new(cells, [0.5, 0.25], 0.9);
+
+    // Blocks of 2 x 2 cells, the last column and row of blocks only partly filled
+    let coarse = space_domain.field(Field::Pressure).downsample(2);
+    assert_eq!(coarse.shape, [3, 2]);
+    assert_eq!(coarse.delta_space, [1.0, 0.5]);
+    // The void cell doesn't count
+    assert_eq!(coarse.values[0], 4.0 / 3.0);
+    assert_eq!(coarse.fluid_fraction[0], 0.75);
+    assert_eq!(coarse.view().get(2, 1), 8.0);
+    assert_eq!(coarse.fluid_fraction[2 * 2 + 1], 1.0);
+    assert_eq!(coarse.view().range(), Some([0.0, 8.0]));
+
+    // A block of only walls takes their type
+    let wall = space_domain
+        .field(Field::Pressure)
+        .slice(0..1, 0..1)
+        .downsample(4);
+    assert_eq!(wall.values, [0.0]);
+    assert_eq!(wall.cell_types, [CellType::VoidCell]);
+    assert_eq!(wall.view().max(), None);
+}
+
+#[test]
+fn simulations_downsample_for_thumbnails() {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow_sized([55, 21]));
+    simulation.iterate_one_timestep();
+    let thumbnail = simulation.downsample_field(Field::U, 4);
+    assert_eq!(thumbnail.field, Some(Field::U));
+    assert_eq!(thumbnail.location, Location::RightFace);
+    assert_eq!(thumbnail.view().field(), Some(Field::U));
+    assert_eq!(thumbnail.shape, [14, 6]);
+    let u = simulation.field(Field::U);
+    let block: Vec<f32> = (20..24)
+        .flat_map(|x| (8..12).map(move |y| (x, y)))
+        .filter(|&(x, y)| u.is_fluid(x, y))
+        .map(|(x, y)| u.get(x, y))
+        .collect();
+    let mean = block.iter().sum::<f32>() / block.len() as f32;
+    assert!((thumbnail.view().get(5, 2) - mean).abs() < 1e-5);
+    // Blocks on the channel walls are partly fluid
+    assert!(thumbnail.fluid_fraction[0] < 1.0);
+}
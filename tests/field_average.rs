@@ -0,0 +1,86 @@
+mod common;
+
+use common::cylinder;
+use flow2d_rs::field_average::{AveragingWindow, TimeAverage};
+use flow2d_rs::field_snapshot::FieldSnapshot;
+use flow2d_rs::run_controller::RunController;
+use flow2d_rs::solver::FluidSolver;
+
+#[test]
+fn averages_sample_inside_their_window_while_running() {
+    let mut simulation = cylinder();
+    let mut average = TimeAverage::new().with_window(AveragingWindow {
+        start: Some(0.0475),
+        stop: Some(0.1475),
+    });
+    assert_eq!(average.mean(), None);
+
+    // Ten steps before the window, twenty inside it and ten after
+    let index = 30 * 21 + 4;
+    let mut expected = [0.0; 2];
+    for _ in 0..40 {
+        simulation.iterate_one_timestep();
+        if average.record(&simulation) {
+            expected[0] += simulation.fields().u[index];
+            expected[1] += simulation.fields().pressure[index];
+        }
+    }
+    assert_eq!(average.samples(), 20);
+    let mean = average.mean().unwrap();
+    assert!((mean.time_range[0] - 0.05).abs() < 1e-5);
+    assert!((mean.time_range[1] - 0.145).abs() < 1e-5);
+    assert!((mean.u[index] - expected[0] / 20.0).abs() < 1e-5);
+    assert!((mean.pressure[index] - expected[1] / 20.0).abs() < 1e-5);
+
+    // Paused averages skip the steps of a run
+    let mut average = TimeAverage::new();
+    RunController::new()
+        .max_steps(5)
+        .sink(&mut average)
+        .run(&mut simulation);
+    average.pause();
+    RunController::new()
+        .max_steps(5)
+        .sink(&mut average)
+        .run(&mut simulation);
+    assert_eq!(average.samples(), 5);
+    average.resume();
+    assert!(average.record(&simulation));
+    average.clear();
+    assert_eq!(average.samples(), 0);
+    assert!(!average.is_paused());
+}
+
+#[test]
+fn mean_fields_export_cell_centred_values() {
+    let mut simulation = cylinder();
+    let mut average = TimeAverage::new();
+    for _ in 0..10 {
+        simulation.iterate_one_timestep();
+        average.record(&simulation);
+    }
+    let mean = average.mean().unwrap();
+
+    let mut csv = Vec::new();
+    mean.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "x,y,is_fluid,u,v,pressure");
+    assert_eq!(lines.len(), 55 * 21 + 1);
+
+    let snapshot = mean.to_snapshot();
+    let names: Vec<&str> = snapshot
+        .fields
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+    assert_eq!(names, ["u", "v", "pressure"]);
+    let u = mean.checkpoint().centered_velocity(30, 4)[0];
+    assert_eq!(snapshot.field("u").unwrap()[30 * 21 + 4], u);
+    assert!(u > 0.5);
+
+    let path = std::env::temp_dir().join(format!("flow2d_rs_mean_{}.f2ds", std::process::id()));
+    snapshot.save(&path).unwrap();
+    assert_eq!(FieldSnapshot::load(&path).unwrap(), snapshot);
+    std::fs::remove_file(&path).unwrap();
+}
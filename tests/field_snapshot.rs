@@ -0,0 +1,46 @@
+use flow2d_rs::field_snapshot::FieldSnapshot;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+
+fn lid_driven_cavity_snapshot(steps: usize) -> FieldSnapshot {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity());
+    for _ in 0..steps {
+        simulation.iterate_one_timestep();
+    }
+    FieldSnapshot::from_solver(&simulation)
+}
+
+#[test]
+fn save_and_load_round_trip() {
+    let snapshot = lid_driven_cavity_snapshot(5);
+    let path = std::env::temp_dir().join(format!("flow2d_rs_snapshot_{}.f2ds", std::process::id()));
+
+    snapshot.save(&path).unwrap();
+    let loaded = FieldSnapshot::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(snapshot, loaded);
+    let diff = snapshot.compare(&loaded, 0.0);
+    assert!(diff.is_within_tolerance(), "{diff}");
+}
+
+#[test]
+fn compare_reports_the_changed_cell() {
+    let snapshot = lid_driven_cavity_snapshot(5);
+    let mut changed = snapshot.clone();
+    let ny = changed.space_size[1];
+    let (_, pressure) = changed
+        .fields
+        .iter_mut()
+        .find(|(name, _)| name == "pressure")
+        .unwrap();
+    pressure[40 * ny + 60] += 1.0;
+
+    let diff = snapshot.compare(&changed, 0.5);
+    assert!(!diff.is_within_tolerance());
+
+    let pressure = diff.field("pressure").unwrap();
+    assert_eq!(pressure.worst_cells[0].0, [40, 60]);
+    assert_eq!(pressure.max, 1.0);
+    assert_eq!(diff.field("u").unwrap().max, 0.0);
+}
@@ -0,0 +1,29 @@
+use flow2d_rs::cell::{BoundaryConditionCell, Cell, CellType};
+use flow2d_rs::space_domain::SpaceDomain;
+
+#[test]
+fn field_arrays_hold_the_same_values_as_the_cells() {
+    let mut cells = vec![vec![Cell::default(); 3]; 4];
+    cells[0][1].cell_type = CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell);
+    cells[2][1].velocity = [1.0, 2.0];
+    cells[2][1].pressure = 3.0;
+    cells[3][2].dye = 0.5;
+    let mut space_domain = SpaceDomain::new(cells, [0.1, 0.1], 0.9);
+
+    let fields = space_domain.fields();
+    assert_eq!(fields.len(), 12);
+    let index = space_domain.index(2, 1);
+    assert_eq!([fields.u[index], fields.v[index]], [1.0, 2.0]);
+    assert_eq!(fields.pressure[index], 3.0);
+    assert_eq!(fields.dye[space_domain.index(3, 2)], 0.5);
+    assert!(matches!(
+        fields.cell_type[space_domain.index(0, 1)],
+        CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell)
+    ));
+
+    *space_domain.psi_mut(1, 2) = 4.0;
+    let cells = space_domain.cells();
+    assert_eq!(cells[space_domain.index(1, 2)].psi, 4.0);
+    assert_eq!(cells[index].velocity, space_domain.velocity(2, 1));
+    assert_eq!(space_domain.get_cell(2, 1).pressure, 3.0);
+}
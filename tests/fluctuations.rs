@@ -0,0 +1,75 @@
+mod common;
+
+use common::cylinder;
+use flow2d_rs::field::Location;
+use flow2d_rs::field_average::TimeAverage;
+
+#[test]
+fn repeated_states_do_not_fluctuate() {
+    let mut simulation = cylinder();
+    for _ in 0..5 {
+        simulation.iterate_one_timestep();
+    }
+    let mut average = TimeAverage::new();
+    average.record(&simulation);
+    assert_eq!(average.fluctuations(), None);
+
+    let mut average = TimeAverage::new().with_fluctuations(true);
+    for _ in 0..3 {
+        average.record(&simulation);
+    }
+    let fluctuations = average.fluctuations().unwrap();
+    assert_eq!(fluctuations.samples, 3);
+    assert!(fluctuations.uu.iter().all(|&uu| uu == 0.0));
+    assert!(fluctuations.vv().max().unwrap() < 1e-10);
+    assert!(fluctuations.uv().range().unwrap()[1].abs() < 1e-10);
+    assert_eq!(fluctuations.dye_variance().max(), Some(0.0));
+
+    average.clear();
+    assert!(average.is_tracking_fluctuations());
+    assert_eq!(average.fluctuations(), None);
+}
+
+#[test]
+fn reynolds_stresses_are_those_of_the_samples() {
+    let mut simulation = cylinder();
+    let mut average = TimeAverage::new().with_fluctuations(true);
+    let [x, y] = [30, 7];
+    let mut samples = Vec::new();
+    for _ in 0..20 {
+        simulation.iterate_one_timestep();
+        average.record(&simulation);
+        samples.push(simulation.get_centered_velocity(x, y));
+    }
+    let mean = samples
+        .iter()
+        .fold([0.0; 2], |sum, velocity| {
+            [sum[0] + velocity[0], sum[1] + velocity[1]]
+        })
+        .map(|sum| sum / 20.0);
+    let covariance = |a: usize, b: usize| {
+        samples
+            .iter()
+            .map(|velocity| (velocity[a] - mean[a]) * (velocity[b] - mean[b]))
+            .sum::<f32>()
+            / 20.0
+    };
+
+    let fluctuations = average.fluctuations().unwrap();
+    let uu = fluctuations.uu();
+    assert_eq!(uu.field(), None);
+    assert_eq!(uu.location(), Location::CellCentre);
+    assert_eq!(uu.shape(), common::CYLINDER_SIZE);
+    assert!((uu.get(x, y) - covariance(0, 0)).abs() < 1e-4);
+    assert!((fluctuations.vv().get(x, y) - covariance(1, 1)).abs() < 1e-4);
+    assert!((fluctuations.uv().get(x, y) - covariance(0, 1)).abs() < 1e-4);
+    assert!(uu.max().unwrap() > 0.0);
+    assert!(fluctuations.vv().min().unwrap() >= 0.0);
+
+    let index = x * 21 + y;
+    let energy = fluctuations.turbulent_kinetic_energy()[index];
+    assert_eq!(
+        energy,
+        (fluctuations.uu[index] + fluctuations.vv[index]) / 2.0
+    );
+}
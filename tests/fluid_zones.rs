@@ -0,0 +1,96 @@
+use flow2d_rs::cell::{BoundaryConditionCell, Cell, CellType};
+use flow2d_rs::presets::{self, SimulationPreset};
+use flow2d_rs::region_statistics::zone_statistics;
+use flow2d_rs::simulation::{PressureGauge, Simulation};
+use flow2d_rs::solver::FluidSolver;
+
+fn wall() -> Cell {
+    Cell {
+        cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+            boundary_condition_velocity: [0.0, 0.0],
+        }),
+        ..Default::default()
+    }
+}
+
+// The cylinder channel split lengthwise into two channels by a wall through the
+// cylinder
+fn two_channels() -> SimulationPreset {
+    let mut preset = presets::cylinder_cross_flow_sized([55, 21]);
+    for x in 1..54 {
+        preset.space_domain.set_cell(x, 10, wall());
+    }
+    preset
+}
+
+#[test]
+fn unconnected_channels_are_separate_open_zones() {
+    let preset = two_channels();
+    assert!(preset.validate().is_empty(), "{:?}", preset.validate());
+    let simulation = Simulation::from_preset(preset);
+    let zones = simulation.fluid_zones();
+    assert_eq!(zones.len(), 2);
+    assert!(zones.is_open(0) && zones.is_open(1));
+    assert_eq!(zones.zone_of(5 * 21 + 10), None);
+    assert_ne!(zones.zone_of(5 * 21 + 5), zones.zone_of(5 * 21 + 15));
+    let fluid_cells: usize = (0..2).map(|zone| zones.cells(zone).len()).sum();
+    let fields = simulation.fields();
+    assert_eq!(
+        fluid_cells,
+        fields
+            .cell_type
+            .iter()
+            .filter(|cell_type| matches!(cell_type, CellType::FluidCell))
+            .count()
+    );
+
+    // The cavity is one closed zone, periodic cells join the sides of the shear layer
+    let cavity = Simulation::from_preset(presets::lid_driven_cavity_sized([16, 16]));
+    assert_eq!(cavity.fluid_zones().len(), 1);
+    assert!(!cavity.fluid_zones().is_open(0));
+    let shear_layer = Simulation::from_preset(presets::kelvin_helmholtz_sized([16, 16]));
+    assert_eq!(shear_layer.fluid_zones().len(), 1);
+}
+
+#[test]
+fn every_zone_gets_its_own_pressure_level() {
+    let run = |pressure_gauge: PressureGauge| {
+        let mut simulation = Simulation::from_preset(two_channels());
+        simulation.set_pressure_gauge(pressure_gauge);
+        for _ in 0..20 {
+            simulation.iterate_one_timestep();
+        }
+        simulation
+    };
+    let floating = run(PressureGauge::Floating);
+    let zero_mean = run(PressureGauge::ZeroMean);
+    let reference = run(PressureGauge::ReferenceCell(5, 5));
+
+    let zones = zero_mean.fluid_zones();
+    let statistics = zone_statistics(&zero_mean, &zones);
+    assert_eq!(statistics.len(), 2);
+    for zone in &statistics {
+        assert!(zone.mean_pressure.abs() < 1e-4, "{zone:?}");
+        assert!(zone.pressure_range[0] < 0.0 && zone.pressure_range[1] > 0.0);
+        assert!(zone.mean_velocity[0] > 0.5);
+    }
+
+    // The reference cell anchors its zone, the other keeps a zero mean
+    assert_eq!(reference.get_cell(5, 5).pressure, 0.0);
+    let statistics = zone_statistics(&reference, &zones);
+    let other = zones.zone_of(5 * 21 + 15).unwrap();
+    assert!(statistics[other].mean_pressure.abs() < 1e-4);
+
+    // Differences within a zone and velocities don't depend on the levels
+    for simulation in [&zero_mean, &reference] {
+        for (a, b) in [((20, 5), (40, 3)), ((20, 15), (40, 17))] {
+            let difference = |simulation: &Simulation| {
+                simulation.get_cell(a.0, a.1).pressure - simulation.get_cell(b.0, b.1).pressure
+            };
+            assert!((difference(simulation) - difference(&floating)).abs() < 1e-3);
+        }
+        for (u, expected) in simulation.fields().u.iter().zip(floating.fields().u.iter()) {
+            assert!((u - expected).abs() < 1e-4);
+        }
+    }
+}
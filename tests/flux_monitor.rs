@@ -0,0 +1,29 @@
+use flow2d_rs::flux_monitor::ControlSurface;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+
+#[test]
+fn inflow_and_outflow_balance() {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow());
+    let [nx, ny] = simulation.space_size();
+    let monitors = simulation.flux_monitors_mut();
+    monitors.add("inflow", ControlSurface::Vertical { x: 0, y: 1..ny - 1 });
+    monitors.add(
+        "outflow",
+        ControlSurface::Vertical {
+            x: nx - 2,
+            y: 1..ny - 1,
+        },
+    );
+
+    for _ in 0..50 {
+        simulation.iterate_one_timestep();
+    }
+
+    let monitors = simulation.flux_monitors();
+    assert_eq!(monitors.get("inflow").unwrap().samples.len(), 50);
+    let inflow = monitors.get("inflow").unwrap().latest().unwrap();
+    let imbalance = monitors.imbalance("inflow", "outflow").unwrap();
+    assert!(inflow > 0.0);
+    assert!(imbalance.abs() < 1e-3 * inflow, "{imbalance}");
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use common::cylinder;
+use flow2d_rs::control::{Actuator, RegionForce};
+use flow2d_rs::forcing::{self, Oscillation, PhaseAverage};
+use flow2d_rs::run_controller::RunController;
+
+use std::f32::consts::PI;
+
+#[test]
+fn an_oscillating_inflow_follows_its_sine() {
+    let mut simulation = cylinder();
+    let oscillation = Oscillation {
+        actuator: Actuator::Inflow {
+            direction: [1.0, 0.0],
+        },
+        mean: 1.5,
+        amplitude: 0.5,
+        frequency: 2.0,
+        phase: PI / 2.0,
+    };
+    assert_eq!(oscillation.period(), 0.5);
+    assert_eq!(oscillation.value(0.0), 2.0);
+    simulation.add_oscillation(oscillation.clone());
+
+    for _ in 0..30 {
+        let middle = simulation.time() + simulation.delta_time() / 2.0;
+        simulation.iterate_one_timestep();
+        let inflow = simulation.get_cell(0, 10).velocity[0];
+        assert!((inflow - oscillation.value(middle)).abs() < 1e-5);
+    }
+    assert_eq!(simulation.clone().oscillations(), &[oscillation]);
+
+    // Cells and region forces don't survive resampling, the inflow does
+    simulation.add_oscillation(Oscillation {
+        actuator: Actuator::WallJet {
+            x: 10..20,
+            y: 0..1,
+            direction: [0.0, 1.0],
+        },
+        mean: 0.0,
+        amplitude: 0.1,
+        frequency: 1.0,
+        phase: 0.0,
+    });
+    simulation.resample([44, 17]);
+    assert_eq!(simulation.oscillations().len(), 1);
+}
+
+#[test]
+fn locked_samples_average_per_phase() {
+    let (delta_time, steps) = forcing::locked_delta_time(0.1, 0.005);
+    assert_eq!(steps, 20);
+    assert!((delta_time - 0.005).abs() < 1e-7);
+    assert_eq!(forcing::locked_delta_time(0.5, 0.03).1, 17);
+
+    let mut simulation = cylinder();
+    simulation.set_delta_time(delta_time);
+    simulation.add_region_force(RegionForce {
+        name: "shaker".to_string(),
+        x: 30..40,
+        y: 5..16,
+        force: [0.0, 0.0],
+    });
+    simulation.add_oscillation(Oscillation {
+        actuator: Actuator::BodyForce {
+            name: "shaker".to_string(),
+            direction: [0.0, 1.0],
+        },
+        mean: 0.0,
+        amplitude: 20.0,
+        frequency: 10.0,
+        phase: 0.0,
+    });
+
+    let mut average = PhaseAverage::new(0.1, 4);
+    RunController::new()
+        .max_steps(3 * steps)
+        .sink(&mut average)
+        .run(&mut simulation);
+    // Five steps per bin and period, three periods
+    for bin in 0..4 {
+        assert_eq!(average.count(bin), 15);
+    }
+    let quarter = average.mean(1).unwrap();
+    let three_quarters = average.mean(3).unwrap();
+    assert!((quarter.phase - PI / 2.0).abs() < 1e-6);
+
+    // The force pushes up in the first half of the period and down in the second,
+    // so the upward velocity peaks at half the period
+    let index = 35 * 21 + 10;
+    let v: Vec<f32> = (0..4)
+        .map(|bin| average.mean(bin).unwrap().field.v[index])
+        .collect();
+    assert!(v[2] > v[0] + 0.1);
+    assert_eq!(quarter.field.v[index], v[1]);
+    assert!((three_quarters.field.v[index] - (v[0] + v[2]) / 2.0).abs() < 0.02);
+
+    average.clear();
+    assert_eq!(average.mean(0), None);
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use flow2d_rs::inflow_turbulence::InflowTurbulence;
+use flow2d_rs::metadata::SimulationMetadata;
+use flow2d_rs::observer::StepEvent;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+
+use std::sync::{Arc, Mutex};
+
+fn cylinder() -> Simulation {
+    let mut simulation = common::cylinder();
+    simulation.set_inflow_turbulence(Some(InflowTurbulence {
+        intensity: 0.05,
+        length_scale: 0.5,
+        seed: 7,
+    }));
+    simulation.set_warm_start(true);
+    simulation
+}
+
+fn run(simulation: &mut Simulation, steps: usize) {
+    for _ in 0..steps {
+        simulation.iterate_one_timestep();
+    }
+}
+
+#[test]
+fn a_clone_steps_like_the_original() {
+    let mut original = cylinder();
+    run(&mut original, 10);
+    let mut clone = original.clone();
+    run(&mut original, 10);
+    run(&mut clone, 10);
+
+    assert_eq!(clone.time(), original.time());
+    assert_eq!(clone.fields().u, original.fields().u);
+    assert_eq!(clone.fields().v, original.fields().v);
+    assert_eq!(clone.fields().pressure, original.fields().pressure);
+}
+
+#[test]
+fn forks_branch_without_touching_each_other() {
+    let mut original = cylinder();
+    let mut reference = cylinder();
+    run(&mut original, 10);
+    run(&mut reference, 10);
+
+    let steps = Arc::new(Mutex::new(0));
+    let count = steps.clone();
+    original.add_observer(move |_: &StepEvent| *count.lock().unwrap() += 1);
+    let shared = original.share_parameters();
+    original.set_metadata(Some(SimulationMetadata::new("cylinder", &original)));
+
+    // Continue one copy with a faster inflow
+    let mut fork = original.fork();
+    fork.set_inflow_velocity([3.0, 0.0]);
+    let metadata = fork.metadata().unwrap();
+    assert!(metadata.scene.starts_with("cylinder fork at "));
+    assert_eq!(original.metadata().unwrap().scene, "cylinder");
+
+    // Neither the observer nor the shared parameters follow the fork
+    run(&mut fork, 10);
+    assert_eq!(*steps.lock().unwrap(), 0);
+    shared.update(|parameters| parameters.reynolds = 50.0);
+    run(&mut fork, 1);
+    assert_eq!(fork.reynolds(), 100.0);
+
+    // The fork went faster while the original is where it was
+    let mean_u = |simulation: &Simulation| {
+        simulation.fields().u.iter().sum::<f32>() / simulation.fields().u.len() as f32
+    };
+    assert!(mean_u(&fork) > 1.5 * mean_u(&reference));
+    assert_eq!(original.fields().u, reference.fields().u);
+
+    run(&mut original, 1);
+    assert_eq!(original.reynolds(), 50.0);
+    assert_eq!(*steps.lock().unwrap(), 1);
+}
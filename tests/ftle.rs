@@ -0,0 +1,70 @@
+mod common;
+
+use flow2d_rs::ftle::{Direction, FlowRecording};
+use flow2d_rs::presets;
+use flow2d_rs::run_controller::RunController;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+
+#[test]
+fn saddle_flow_stretches_at_its_strain_rate() {
+    // Steady saddle u = x, v = -y around the cavity center, recorded over one second
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([40, 40]));
+    let [dx, dy] = simulation.delta_space();
+    let center = [20.0 * dx, 20.0 * dy];
+    let mut checkpoint = simulation.checkpoint();
+    for x in 0..40 {
+        for y in 0..40 {
+            checkpoint.cells[x * 40 + y].velocity = [
+                (x + 1) as f32 * dx - center[0],
+                -((y + 1) as f32 * dy - center[1]),
+            ];
+        }
+    }
+    let mut recording = FlowRecording::new();
+    for time in [0.0, 0.5, 1.0] {
+        checkpoint.time = time;
+        simulation.restore(&checkpoint);
+        recording.record(&simulation);
+    }
+    assert_eq!(recording.frames(), 3);
+    assert_eq!(recording.time_range(), Some([0.0, 1.0]));
+
+    // Tracers move apart along x forward in time and along y backward
+    let ends = recording.flow_map(&[[center[0] + 0.1 * dx, center[1]]], Direction::Forward);
+    assert!((ends[0][0] - center[0] - 0.1 * dx * 1f32.exp()).abs() < 1e-3 * dx);
+    for direction in [Direction::Forward, Direction::Backward] {
+        let ftle = recording.ftle([40, 40], direction);
+        assert_eq!(ftle.integration_time, 1.0);
+        // Near the center, tracers further out hit the walls
+        let value = ftle.view().get(20, 20);
+        assert!((value - 1.0).abs() < 0.02, "{direction:?} {value}");
+    }
+}
+
+#[test]
+fn cylinder_wake_has_coherent_structures() {
+    let mut simulation = common::cylinder();
+    for _ in 0..20 {
+        simulation.iterate_one_timestep();
+    }
+    let mut recording = FlowRecording::new();
+    RunController::new()
+        .max_steps(40)
+        .sink(&mut recording)
+        .run(&mut simulation);
+    assert_eq!(recording.frames(), 40);
+
+    let forward = recording.ftle([110, 42], Direction::Forward);
+    let backward = recording.ftle([110, 42], Direction::Backward);
+    for ftle in [&forward, &backward] {
+        assert_eq!(ftle.view().shape(), [110, 42]);
+        assert!(ftle.values.iter().all(|value| value.is_finite()));
+        assert!(ftle.view().max().unwrap() > 0.0);
+        // Seeds in walls hold zero
+        assert_eq!(ftle.values[0], 0.0);
+    }
+    assert_ne!(forward.values, backward.values);
+    // Between the first and the last of the 40 frames
+    assert!((forward.integration_time - 39.0 * simulation.delta_time()).abs() < 1e-4);
+}
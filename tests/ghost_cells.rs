@@ -0,0 +1,64 @@
+use flow2d_rs::cell::{Cell, CellType};
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+use flow2d_rs::space_domain::{SpaceDomain, FACE_NEIGHBORS};
+
+#[test]
+fn neighbors_outside_the_domain_are_void_ghost_cells() {
+    let cells = vec![vec![Cell::default(); 3]; 4];
+    let mut space_domain = SpaceDomain::new(cells, [0.1, 0.1], 0.9);
+    space_domain.set_velocity(0, 0, [1.0, 2.0]);
+
+    assert_eq!(space_domain.neighbor_position(0, 0, [-1, 0]), None);
+    assert_eq!(space_domain.neighbor_position(3, 2, [0, 1]), None);
+    assert_eq!(space_domain.neighbor_position(1, 1, [1, -1]), Some((2, 0)));
+    assert!(matches!(
+        space_domain.neighbor(0, 0, [0, -1]).cell_type,
+        CellType::VoidCell
+    ));
+    assert_eq!(space_domain.face_neighbors(0, 0).count(), 2);
+    assert_eq!(
+        space_domain.face_neighbors(1, 1).count(),
+        FACE_NEIGHBORS.len()
+    );
+
+    // Fluid on the edge reads the ghost cells at rest instead of panicking
+    let [dx, dy] = space_domain.delta_space();
+    assert_eq!(
+        space_domain.d2udx2(0, 0),
+        (0.0 - 2.0 * 1.0 + 0.0) / dx.powi(2)
+    );
+    assert_eq!(
+        space_domain.d2vdy2(0, 0),
+        (0.0 - 2.0 * 2.0 + 0.0) / dy.powi(2)
+    );
+    assert_eq!(space_domain.neighbor_pressure(0, 0, [-1, 0]), 0.0);
+}
+
+#[test]
+fn fluid_on_the_left_and_bottom_edge_steps_and_renders() {
+    // The cavity without its left and bottom walls, which from_preset doesn't reject
+    let mut preset = presets::lid_driven_cavity_sized([8, 8]);
+    for i in 0..7 {
+        preset.space_domain.set_cell(0, i, Cell::default());
+        preset.space_domain.set_cell(i, 0, Cell::default());
+    }
+    let mut simulation = Simulation::from_preset(preset);
+    for _ in 0..5 {
+        simulation.iterate_one_timestep();
+    }
+
+    let mut buffer = Vec::new();
+    simulation.pack_render_buffer(&mut buffer);
+    // Bottom left cell is the first of the last image row, its outer faces are at rest
+    let corner = buffer[7 * 8];
+    let expected = [
+        simulation.get_cell(0, 0).velocity[0] / 2.0,
+        simulation.get_cell(0, 0).velocity[1] / 2.0,
+    ];
+    assert_eq!(corner.velocity, expected);
+    assert!(buffer
+        .iter()
+        .all(|cell| cell.velocity.iter().all(|v| v.is_finite())));
+}
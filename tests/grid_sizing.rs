@@ -0,0 +1,93 @@
+use flow2d_rs::grid_sizing::{
+    bytes_per_cell, recommend_grid, Budget, GridRequirements, SizingIssue,
+};
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::units::DeltaTimeLimits;
+
+const UNLIMITED: Budget = Budget {
+    memory: usize::MAX,
+    cell_steps: f64::INFINITY,
+};
+
+// The cylinder of diameter 1 in its 11 x 4.1 channel
+fn cylinder() -> GridRequirements {
+    GridRequirements::new([11.0, 4.1], 100.0, 1.0)
+        .with_max_velocity(1.5)
+        .with_duration(2.0)
+}
+
+#[test]
+fn recommends_the_resolution_asked_for_within_budget() {
+    let grid = recommend_grid(&cylinder(), &UNLIMITED);
+    assert!(grid.is_resolved(), "{}", grid);
+    assert_eq!(grid.space_size, [110, 41]);
+    assert!(grid.cells_per_feature >= 10.0 - 1e-3);
+    let limits = DeltaTimeLimits::new(100.0, grid.delta_space, [1.5, 1.5]);
+    assert!(grid.delta_time > 0.0 && grid.delta_time < limits.stable());
+    assert_eq!(grid.steps, (2.0 / grid.delta_time).ceil() as usize);
+    assert_eq!(grid.memory, 110 * 41 * bytes_per_cell());
+
+    // A cell reynolds number limit refines the grid further
+    let grid = recommend_grid(&cylinder().with_max_cell_reynolds(Some(5.0)), &UNLIMITED);
+    assert!(grid.is_resolved(), "{}", grid);
+    assert!(grid.cell_reynolds <= 5.0 + 1e-3);
+    assert!(grid.space_size[0] >= 330);
+
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow_sized([55, 21]));
+    let grid = recommend_grid(&cylinder(), &UNLIMITED);
+    grid.apply(&mut simulation);
+    assert_eq!(simulation.space_size(), grid.space_size);
+    assert_eq!(simulation.delta_time(), grid.delta_time);
+    let delta_space = simulation.delta_space();
+    assert!((delta_space[0] - grid.delta_space[0]).abs() < 1e-5);
+    assert!((delta_space[1] - grid.delta_space[1]).abs() < 1e-5);
+}
+
+#[test]
+fn reports_what_the_budget_gives_up() {
+    // Room for about a quarter of the cells asked for
+    let memory = 110 * 41 * bytes_per_cell() / 4;
+    let budget = Budget {
+        memory,
+        cell_steps: f64::INFINITY,
+    };
+    let grid = recommend_grid(&cylinder(), &budget);
+    assert!(grid.memory <= memory);
+    assert!(grid.space_size[0] * grid.space_size[1] > 110 * 41 / 5);
+    assert!(matches!(
+        grid.issues[..],
+        [SizingIssue::UnderResolved { cells_per_feature }] if cells_per_feature < 6.0
+    ));
+    assert!(grid.to_string().contains("under resolved"));
+
+    // The time budget limits the grid too
+    let cell_steps = recommend_grid(&cylinder(), &UNLIMITED).cell_steps / 10.0;
+    let grid = recommend_grid(
+        &cylinder(),
+        &Budget {
+            memory: usize::MAX,
+            cell_steps,
+        },
+    );
+    assert!(grid.cell_steps <= cell_steps);
+    assert!(!grid.is_resolved());
+
+    // Nothing fits
+    let grid = recommend_grid(
+        &cylinder(),
+        &Budget {
+            memory: 1,
+            cell_steps: 1.0,
+        },
+    );
+    assert_eq!(grid.space_size, [4, 4]);
+    assert!(grid
+        .issues
+        .iter()
+        .any(|issue| matches!(issue, SizingIssue::OverMemory { .. })));
+    assert!(grid
+        .issues
+        .iter()
+        .any(|issue| matches!(issue, SizingIssue::OverCellSteps { .. })));
+}
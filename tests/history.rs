@@ -0,0 +1,34 @@
+use flow2d_rs::field_snapshot::FieldSnapshot;
+use flow2d_rs::history::History;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+
+#[test]
+fn rewind_re_simulates_from_the_keyframe() {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow());
+    let mut history = History::new(3, 5);
+    let mut snapshots = Vec::new();
+
+    history.record(&simulation);
+    snapshots.push(FieldSnapshot::from_solver(&simulation));
+    for _ in 0..20 {
+        simulation.iterate_one_timestep();
+        history.record(&simulation);
+        snapshots.push(FieldSnapshot::from_solver(&simulation));
+    }
+
+    // Keyframes 10, 15 and 20 remain
+    assert_eq!(history.steps(), Some(10..=20));
+    assert!(!history.rewind(&mut simulation, 9));
+
+    assert!(history.rewind(&mut simulation, 13));
+    let diff = FieldSnapshot::from_solver(&simulation).compare(&snapshots[13], 1e-3);
+    assert!(diff.is_within_tolerance(), "{diff}");
+
+    // Scrubbing forward again is possible until the next record
+    assert!(history.rewind(&mut simulation, 18));
+    assert!(history.rewind(&mut simulation, 12));
+    simulation.iterate_one_timestep();
+    history.record(&simulation);
+    assert_eq!(history.steps(), Some(10..=13));
+}
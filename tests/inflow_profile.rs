@@ -0,0 +1,91 @@
+use flow2d_rs::cell::{BoundaryConditionCell, Cell, CellType};
+use flow2d_rs::inflow_profile::{inlets, InflowProfile};
+use flow2d_rs::presets;
+use flow2d_rs::space_domain::SpaceDomain;
+
+#[test]
+fn profiles_have_their_shape_across_the_inlet() {
+    let parabolic = InflowProfile::Parabolic { max_speed: 3.0 };
+    let power_law = InflowProfile::PowerLaw { max_speed: 3.0 };
+    assert_eq!(InflowProfile::Uniform { speed: 2.0 }.speed(0.1), 2.0);
+    assert_eq!(parabolic.speed(0.5), 3.0);
+    assert_eq!(parabolic.speed(0.0), 0.0);
+    assert_eq!(parabolic.speed(0.25), parabolic.speed(0.75));
+    assert_eq!(power_law.speed(0.5), 3.0);
+    assert_eq!(power_law.speed(1.0), 0.0);
+
+    // Two thirds of the peak on average for Poiseuille flow, seven eighths for the
+    // flatter power law
+    let mean = |profile: InflowProfile| {
+        let samples = 10000;
+        (0..samples)
+            .map(|sample| profile.speed((sample as f32 + 0.5) / samples as f32))
+            .sum::<f32>()
+            / samples as f32
+    };
+    assert!((mean(parabolic) - 2.0).abs() < 1e-3);
+    assert!((mean(power_law) - 3.0 * 7.0 / 8.0).abs() < 1e-2);
+}
+
+// Inflow through the left wall and down through the top wall of a box of fluid
+#[test]
+fn every_inlet_takes_the_whole_profile_into_the_fluid() {
+    let [nx, ny] = [6, 5];
+    let inflow = CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell);
+    let wall = CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+        boundary_condition_velocity: [0.0, 0.0],
+    });
+    let cells = (0..nx)
+        .map(|x| {
+            (0..ny)
+                .map(|y| {
+                    let is_corner = (x == 0 || x == nx - 1) && (y == 0 || y == ny - 1);
+                    let cell_type = if is_corner {
+                        CellType::VoidCell
+                    } else if x == 0 || y == ny - 1 {
+                        inflow
+                    } else if x == nx - 1 || y == 0 {
+                        wall
+                    } else {
+                        CellType::FluidCell
+                    };
+                    Cell {
+                        cell_type,
+                        ..Default::default()
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let mut space_domain = SpaceDomain::new(cells, [0.1, 0.1], 0.9);
+    let profile = InflowProfile::Parabolic { max_speed: 1.0 };
+    profile.apply(&mut space_domain);
+
+    let inlets = inlets(&space_domain);
+    assert_eq!(inlets.len(), 2);
+    assert_eq!(inlets[0].normal, [0, -1]);
+    assert_eq!(inlets[0].cells.len(), nx - 2);
+    assert_eq!(inlets[1].normal, [1, 0]);
+    assert_eq!(inlets[1].cells, vec![(0, 1), (0, 2), (0, 3)]);
+
+    for (position, y) in (1..ny - 1).enumerate() {
+        let speed = profile.speed((position as f32 + 0.5) / 3.0);
+        assert_eq!(space_domain.velocity(0, y), [speed, 0.0]);
+    }
+    for (position, x) in (1..nx - 1).enumerate() {
+        let speed = profile.speed((position as f32 + 0.5) / 4.0);
+        assert_eq!(space_domain.velocity(x, ny - 1), [0.0, -speed]);
+    }
+}
+
+#[test]
+fn channel_presets_keep_their_uniform_inflow() {
+    let preset = presets::cylinder_cross_flow();
+    let [_, ny] = preset.space_domain.space_size();
+    for y in 1..ny - 1 {
+        assert_eq!(
+            preset.space_domain.velocity(0, y),
+            [presets::CYLINDER_INFLOW_VELOCITY, 0.0]
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use flow2d_rs::cell::{BoundaryConditionCell, CellType};
+use flow2d_rs::inflow_turbulence::{InflowTurbulence, TurbulentInflow};
+use flow2d_rs::presets::{self, CYLINDER_INFLOW_VELOCITY};
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+
+const TURBULENCE: InflowTurbulence = InflowTurbulence {
+    intensity: 0.1,
+    length_scale: 0.3,
+    seed: 7,
+};
+
+fn correlation(a: &[f32], b: &[f32]) -> f32 {
+    let mean = |values: &[f32]| values.iter().sum::<f32>() / values.len() as f32;
+    let [mean_a, mean_b] = [mean(a), mean(b)];
+    let covariance = |a: &[f32], mean_a: f32, b: &[f32], mean_b: f32| {
+        a.iter()
+            .zip(b.iter())
+            .map(|(a, b)| (a - mean_a) * (b - mean_b))
+            .sum::<f32>()
+    };
+    covariance(a, mean_a, b, mean_b)
+        / (covariance(a, mean_a, a, mean_a) * covariance(b, mean_b, b, mean_b)).sqrt()
+}
+
+#[test]
+fn fluctuations_have_the_requested_intensity_and_correlations() {
+    // Inflow cells along x = 0, 0.1 meters apart
+    let mut space_domain = presets::cylinder_cross_flow().space_domain;
+    let mut inflow = TurbulentInflow::new(TURBULENCE, &space_domain);
+    assert_eq!(inflow.len(), 39);
+
+    let ny = space_domain.space_size()[1];
+    let rows = [5, 20, 21, 35];
+    let mut samples = vec![Vec::new(); rows.len()];
+    for _ in 0..20000 {
+        inflow.advance(0.005, &mut space_domain);
+        let u = &space_domain.fields().u;
+        for (samples, row) in samples.iter_mut().zip(rows) {
+            samples.push(u[row]);
+        }
+        // The inflow rate does not fluctuate
+        let inflow_rate: f32 = u[1..ny - 1].iter().sum();
+        let expected = CYLINDER_INFLOW_VELOCITY * (ny - 2) as f32;
+        assert!((inflow_rate - expected).abs() < 1e-3, "{inflow_rate}");
+    }
+
+    let u = &samples[1];
+    let mean = u.iter().sum::<f32>() / u.len() as f32;
+    let rms = (u.iter().map(|u| (u - mean).powi(2)).sum::<f32>() / u.len() as f32).sqrt();
+    assert!((mean - CYLINDER_INFLOW_VELOCITY).abs() < 0.02, "{mean}");
+    let intensity = rms / CYLINDER_INFLOW_VELOCITY;
+    assert!((intensity - 0.1).abs() < 0.015, "{intensity}");
+
+    // Correlated over one length scale in time and along the inlet. Cells further
+    // apart are slightly anticorrelated, the fluctuations sum to zero over the inlet.
+    let lagged = correlation(&u[..u.len() - 1], &u[1..]);
+    assert!(lagged > 0.95, "{lagged}");
+    let neighbors = correlation(&samples[1], &samples[2]);
+    assert!(neighbors > 0.8, "{neighbors}");
+    let distant = correlation(&samples[0], &samples[3]);
+    assert!(distant < 0.0 && distant > -0.3, "{distant}");
+}
+
+fn inflow_velocities(simulation: &Simulation) -> Vec<[f32; 2]> {
+    let fields = simulation.fields();
+    (0..fields.len())
+        .filter(|&index| {
+            matches!(
+                fields.cell_type[index],
+                CellType::BoundaryConditionCell(BoundaryConditionCell::InflowCell)
+            )
+        })
+        .map(|index| [fields.u[index], fields.v[index]])
+        .collect()
+}
+
+fn run(turbulence: Option<InflowTurbulence>) -> Simulation {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow_sized([44, 16]));
+    simulation.set_inflow_turbulence(turbulence);
+    for _ in 0..20 {
+        simulation.iterate_one_timestep();
+    }
+    simulation
+}
+
+#[test]
+fn simulations_with_the_same_seed_see_the_same_inflow() {
+    let mut simulation = run(Some(TURBULENCE));
+    assert_eq!(simulation.inflow_turbulence(), Some(TURBULENCE));
+    assert_eq!(simulation.fields().u, run(Some(TURBULENCE)).fields().u);
+    let other_seed = run(Some(InflowTurbulence {
+        seed: 8,
+        ..TURBULENCE
+    }));
+    assert_ne!(simulation.fields().u, other_seed.fields().u);
+    assert!(simulation.fields().u.iter().all(|u| u.is_finite()));
+
+    let velocities = inflow_velocities(&simulation);
+    assert!(velocities
+        .iter()
+        .any(|velocity| (velocity[0] - CYLINDER_INFLOW_VELOCITY).abs() > 1e-3));
+
+    // A new mean is followed by the fluctuations, turning them off leaves the mean
+    simulation.set_inflow_velocity([1.0, 0.0]);
+    simulation.iterate_one_timestep();
+    let mean: f32 = inflow_velocities(&simulation)
+        .iter()
+        .map(|velocity| velocity[0])
+        .sum::<f32>()
+        / velocities.len() as f32;
+    assert!((mean - 1.0).abs() < 1e-4, "{mean}");
+    simulation.set_inflow_turbulence(None);
+    assert!(inflow_velocities(&simulation)
+        .iter()
+        .all(|&velocity| velocity == [1.0, 0.0]));
+
+    // Without intensity the inflow is the mean
+    let calm = run(Some(InflowTurbulence {
+        intensity: 0.0,
+        ..TURBULENCE
+    }));
+    assert_eq!(calm.fields().u, run(None).fields().u);
+}
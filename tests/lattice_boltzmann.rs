@@ -0,0 +1,206 @@
+use flow2d_rs::cell::{BoundaryConditionCell, Cell, CellType};
+use flow2d_rs::events::EventSchedule;
+use flow2d_rs::lattice_boltzmann::LatticeBoltzmann;
+use flow2d_rs::presets::{self, SimulationPreset};
+use flow2d_rs::solver::FluidSolver;
+use flow2d_rs::space_domain::SpaceDomain;
+
+use std::f32::consts::PI;
+
+const SIZE: [usize; 2] = [8, 18];
+const DELTA_SPACE: f32 = 1.0 / 16.0;
+
+fn wall(velocity: [f32; 2]) -> Cell {
+    Cell {
+        cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+            boundary_condition_velocity: velocity,
+        }),
+        ..Default::default()
+    }
+}
+
+// Channel of unit height, periodic in x, between a wall at rest and a wall moving
+// with top_velocity. Time step and Reynolds number give a relaxation time of 1.
+fn channel(top_velocity: f32, acceleration: f32) -> SimulationPreset {
+    let [nx, ny] = SIZE;
+    let cells = (0..nx)
+        .map(|x| {
+            (0..ny)
+                .map(|y| {
+                    if y == 0 {
+                        wall([0.0, 0.0])
+                    } else if y == ny - 1 {
+                        wall([top_velocity, 0.0])
+                    } else if x == 0 || x == nx - 1 {
+                        Cell {
+                            cell_type: CellType::BoundaryConditionCell(
+                                BoundaryConditionCell::PeriodicCell,
+                            ),
+                            ..Default::default()
+                        }
+                    } else {
+                        Cell::default()
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    SimulationPreset {
+        space_domain: SpaceDomain::new(cells, [DELTA_SPACE; 2], 0.9),
+        delta_time: DELTA_SPACE.powi(2) / 6.0,
+        reynolds: 1.0,
+        acceleration: [acceleration, 0.0],
+        events: EventSchedule::new(),
+        obstacles: Vec::new(),
+        dye_buoyancy: None,
+    }
+}
+
+// Distance of the center of row y from the bottom wall, which lies halfway between
+// the wall cells and the first fluid cells
+fn wall_distance(y: usize) -> f32 {
+    (y as f32 - 0.5) * DELTA_SPACE
+}
+
+fn run_to_steady_state(solver: &mut LatticeBoltzmann) {
+    // About 7 viscous times across the channel
+    for _ in 0..1200 {
+        solver.iterate_one_timestep();
+    }
+}
+
+#[test]
+fn couette_flow_has_a_linear_profile() {
+    let lid_velocity = 0.1;
+    let mut solver = LatticeBoltzmann::from_preset(channel(lid_velocity, 0.0));
+    assert!((solver.tau() - 1.0).abs() < 1e-4, "{}", solver.tau());
+    run_to_steady_state(&mut solver);
+
+    let [nx, ny] = SIZE;
+    for x in 1..nx - 1 {
+        for y in 1..ny - 1 {
+            let expected = lid_velocity * wall_distance(y);
+            let velocity = solver.get_centered_velocity(x, y);
+            assert!(
+                (velocity[0] - expected).abs() < 0.01 * lid_velocity,
+                "({x}, {y}): {} != {expected}",
+                velocity[0]
+            );
+            assert!(velocity[1].abs() < 1e-4 * lid_velocity, "{velocity:?}");
+        }
+    }
+}
+
+#[test]
+fn poiseuille_flow_has_a_parabolic_profile() {
+    // u = a y (h - y) / (2 nu) with nu = 1 / Re = 1
+    let acceleration = 0.8;
+    let mut solver = LatticeBoltzmann::from_preset(channel(0.0, acceleration));
+    run_to_steady_state(&mut solver);
+
+    let [nx, ny] = SIZE;
+    let height = (ny - 2) as f32 * DELTA_SPACE;
+    let max_velocity = acceleration * height.powi(2) / 8.0;
+    for x in 1..nx - 1 {
+        for y in 1..ny - 1 {
+            let distance = wall_distance(y);
+            let expected = acceleration * distance * (height - distance) / 2.0;
+            let velocity = solver.get_centered_velocity(x, y);
+            assert!(
+                (velocity[0] - expected).abs() < 0.02 * max_velocity,
+                "({x}, {y}): {} != {expected}",
+                velocity[0]
+            );
+        }
+    }
+}
+
+#[test]
+fn mass_is_conserved_in_a_closed_cavity() {
+    // Walls at rest around a swirl, so bounce-back neither adds nor removes mass
+    let mut preset = presets::lid_driven_cavity_sized([24, 24]).initial_velocity(|x, y| {
+        [
+            (PI * x).sin() * (PI * y).cos(),
+            -(PI * x).cos() * (PI * y).sin(),
+        ]
+    });
+    for x in 1..23 {
+        preset.space_domain.set_cell(x, 23, wall([0.0, 0.0]));
+    }
+    preset.reynolds = 100.0;
+    let mut solver = LatticeBoltzmann::from_preset(preset);
+    let fluid_cells: Vec<(usize, usize)> =
+        (1..23).flat_map(|x| (1..23).map(move |y| (x, y))).collect();
+    let mass = |solver: &LatticeBoltzmann| -> f64 {
+        fluid_cells
+            .iter()
+            .map(|&(x, y)| solver.density(x, y) as f64)
+            .sum()
+    };
+
+    let initial_mass = mass(&solver);
+    for _ in 0..50 {
+        solver.iterate_one_timestep();
+    }
+    let final_mass = mass(&solver);
+    assert!(
+        ((final_mass - initial_mass) / initial_mass).abs() < 1e-5,
+        "{initial_mass} -> {final_mass}"
+    );
+    // The swirl is still there
+    assert!(solver.get_centered_velocity(12, 4)[0].abs() > 0.1);
+}
+
+#[test]
+fn a_moving_wall_drags_the_fluid_next_to_it() {
+    // From rest, one lattice step of bounce-back on the lid gives the fluid below it
+    // a third of the lid velocity: 6 w rho c.u_w summed over the two diagonals
+    let mut preset = presets::lid_driven_cavity_sized([16, 16]);
+    let lid_velocity = 1.0;
+    preset.delta_time = 0.1 * preset.space_domain.delta_space()[0] / lid_velocity;
+    let mut solver = LatticeBoltzmann::from_preset(preset);
+    assert_eq!(solver.substeps(), 1);
+    solver.iterate_one_timestep();
+
+    // Away from the corners, where the lid ends
+    for x in 3..13 {
+        let velocity = solver.get_centered_velocity(x, 14);
+        assert!(
+            (velocity[0] - lid_velocity / 3.0).abs() < 1e-4,
+            "({x}, 14): {velocity:?}"
+        );
+        assert!(velocity[1].abs() < 1e-4, "({x}, 14): {velocity:?}");
+        // Nothing has reached the cells further down yet
+        let below = solver.get_centered_velocity(x, 12);
+        assert!(below[0].abs() < 1e-6, "({x}, 12): {below:?}");
+    }
+}
+
+#[test]
+fn fluid_on_the_edge_of_the_array_streams_without_panicking() {
+    // No ring of boundary cells, the edge of the array closes the fluid like a void
+    let cells = (0..6)
+        .map(|_| (0..6).map(|_| Cell::default()).collect())
+        .collect();
+    let mut solver = LatticeBoltzmann::from_preset(SimulationPreset {
+        space_domain: SpaceDomain::new(cells, [DELTA_SPACE; 2], 0.9),
+        delta_time: DELTA_SPACE.powi(2) / 6.0,
+        reynolds: 1.0,
+        acceleration: [1.0, 0.0],
+        events: EventSchedule::new(),
+        obstacles: Vec::new(),
+        dye_buoyancy: None,
+    });
+    for _ in 0..10 {
+        solver.iterate_one_timestep();
+    }
+
+    for x in 0..6 {
+        for y in 0..6 {
+            assert!((solver.density(x, y) - 1.0).abs() < 1e-3);
+        }
+        // The face on the top edge of the array is closed
+        assert_eq!(solver.get_cell(x, 5).velocity[1], 0.0);
+    }
+}
@@ -0,0 +1,59 @@
+use flow2d_rs::field::Field;
+use flow2d_rs::observer::StepEvent;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+
+fn cavity() -> Simulation {
+    Simulation::from_preset(presets::lid_driven_cavity_sized([24, 24]))
+}
+
+#[test]
+fn lazy_analytics_are_computed_when_read() {
+    let mut eager = cavity();
+    let mut lazy = cavity();
+    lazy.set_lazy_analytics(true);
+    assert!(lazy.lazy_analytics());
+    let initial_psi = lazy.field(Field::Psi).to_vec();
+    for _ in 0..10 {
+        eager.iterate_one_timestep();
+        lazy.iterate_one_timestep();
+    }
+    // Skipped by the steps
+    assert_eq!(lazy.field(Field::Psi).to_vec(), initial_psi);
+    assert_ne!(eager.field(Field::Psi).to_vec(), initial_psi);
+
+    // The ranges through the trait too
+    assert_eq!(lazy.pressure_range(), eager.pressure_range());
+    assert_eq!(
+        FluidSolver::speed_range(&lazy),
+        FluidSolver::speed_range(&eager)
+    );
+    let psi = lazy.psi().to_vec();
+    assert_eq!(psi, eager.field(Field::Psi).to_vec());
+    assert_eq!(lazy.field(Field::Psi).to_vec(), psi);
+
+    // Chunks stay lazy until switched back
+    lazy.step_n(5);
+    eager.step_n(5);
+    assert_eq!(lazy.field(Field::Psi).to_vec(), psi);
+    lazy.set_lazy_analytics(false);
+    assert_eq!(
+        lazy.field(Field::Psi).to_vec(),
+        eager.field(Field::Psi).to_vec()
+    );
+}
+
+#[test]
+fn observers_still_see_analytics_every_step() {
+    let mut simulation = cavity();
+    simulation.set_lazy_analytics(true);
+    simulation.add_observer(|event: &StepEvent| {
+        assert!(event.speed_range[1] > 0.0);
+    });
+    let initial_psi = simulation.field(Field::Psi).to_vec();
+    simulation.iterate_one_timestep();
+    let psi = simulation.field(Field::Psi).to_vec();
+    assert_ne!(psi, initial_psi);
+    assert_eq!(simulation.psi().to_vec(), psi);
+}
@@ -0,0 +1,55 @@
+use flow2d_rs::lic::line_integral_convolution;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+
+const SIZE: usize = 64;
+
+fn uniform(velocity: [f32; 2]) -> Vec<u8> {
+    line_integral_convolution([1.0, 1.0], SIZE, SIZE, |_| Some(velocity))
+}
+
+// Mean absolute difference of neighbouring pixels [along x, along y]
+fn roughness(image: &[u8]) -> [f32; 2] {
+    let pixel = |x: usize, row: usize| image[row * SIZE + x] as f32;
+    let mut sums = [0.0, 0.0];
+    for row in 0..SIZE - 1 {
+        for x in 0..SIZE - 1 {
+            sums[0] += (pixel(x + 1, row) - pixel(x, row)).abs();
+            sums[1] += (pixel(x, row + 1) - pixel(x, row)).abs();
+        }
+    }
+    sums.map(|sum| sum / ((SIZE - 1) * (SIZE - 1)) as f32)
+}
+
+#[test]
+fn textures_are_deterministic_and_sized() {
+    let image = uniform([1.0, 0.0]);
+    assert_eq!(image.len(), SIZE * SIZE);
+    assert_eq!(image, uniform([1.0, 0.0]));
+
+    let simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([16, 16]));
+    let texture = simulation.render_lic(40, 30);
+    assert_eq!(texture.len(), 40 * 30);
+    assert_eq!(texture, simulation.render_lic(40, 30));
+}
+
+#[test]
+fn noise_is_smeared_along_the_flow() {
+    let [along, across] = roughness(&uniform([1.0, 0.0]));
+    assert!(along < 0.3 * across, "{along} vs {across}");
+
+    let [across, along] = roughness(&uniform([0.0, -2.0]));
+    assert!(along < 0.3 * across, "{along} vs {across}");
+}
+
+#[test]
+fn pixels_outside_the_fluid_are_black() {
+    // Fluid in the left half only
+    let image = line_integral_convolution([1.0, 1.0], SIZE, SIZE, |position| {
+        (position[0] < 0.5).then_some([0.0, 1.0])
+    });
+    for row in image.chunks(SIZE) {
+        assert!(row[SIZE / 2..].iter().all(|&pixel| pixel == 0));
+        assert!(row[..SIZE / 2].iter().any(|&pixel| pixel != 0));
+    }
+}
@@ -0,0 +1,120 @@
+use flow2d_rs::linear_solver::{bicgstab, conjugate_gradient, LinearOperator, PoissonOperator};
+use flow2d_rs::observer::StepEvent;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::{PressureSolver, Simulation};
+use flow2d_rs::solver::FluidSolver;
+
+use std::sync::{Arc, Mutex};
+
+// Tridiagonal with more weight below the diagonal than above, not symmetric
+struct Convection {
+    len: usize,
+}
+
+impl LinearOperator for Convection {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn apply(&self, x: &[f32], y: &mut [f32]) {
+        for i in 0..self.len {
+            let below = if i > 0 { x[i - 1] } else { 0.0 };
+            let above = if i + 1 < self.len { x[i + 1] } else { 0.0 };
+            y[i] = 4.0 * x[i] - 2.5 * below - 0.5 * above;
+        }
+    }
+
+    fn diagonal(&self, _unknown: usize) -> f32 {
+        4.0
+    }
+}
+
+#[test]
+fn bicgstab_solves_a_non_symmetric_system() {
+    let operator = Convection { len: 50 };
+    let expected: Vec<f32> = (0..50).map(|i| (i as f32 * 0.3).sin()).collect();
+    let mut b = vec![0.0; 50];
+    operator.apply(&expected, &mut b);
+
+    let mut x = vec![0.0; 50];
+    let report = bicgstab(&operator, &b, &mut x, 1e-5, 100);
+    assert!(report.converged, "{report:?}");
+    for (value, expected) in x.iter().zip(expected.iter()) {
+        assert!((value - expected).abs() < 1e-3, "{value} != {expected}");
+    }
+
+    // Conjugate gradients relies on symmetry
+    let mut x = vec![0.0; 50];
+    let report = conjugate_gradient(&operator, &b, &mut x, 1e-5, 10);
+    assert!(!report.converged);
+}
+
+#[test]
+fn the_poisson_operator_is_symmetric_with_constants_in_its_null_space() {
+    let preset = presets::cylinder_cross_flow_sized([22, 12]);
+    let operator = PoissonOperator::new(&preset.space_domain);
+    let n = operator.len();
+    let unit = |i: usize| {
+        let mut x = vec![0.0; n];
+        x[i] = 1.0;
+        x
+    };
+    let columns: Vec<Vec<f32>> = (0..n)
+        .map(|i| {
+            let mut column = vec![0.0; n];
+            operator.apply(&unit(i), &mut column);
+            column
+        })
+        .collect();
+    for (i, column) in columns.iter().enumerate() {
+        assert_eq!(column[i], operator.diagonal(i));
+        for (j, &value) in column.iter().enumerate() {
+            assert_eq!(value, columns[j][i]);
+        }
+    }
+
+    let mut y = vec![1.0; n];
+    operator.apply(&vec![3.0; n], &mut y);
+    assert!(y.iter().all(|&y| y == 0.0));
+}
+
+fn run(preset: presets::SimulationPreset, pressure_solver: PressureSolver) -> (Simulation, usize) {
+    let mut simulation = Simulation::from_preset(preset);
+    simulation.set_pressure_solver(pressure_solver);
+    assert_eq!(simulation.pressure_solver(), pressure_solver);
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let log = events.clone();
+    simulation.add_observer(move |event: &StepEvent| log.lock().unwrap().push(*event));
+    for _ in 0..30 {
+        simulation.iterate_one_timestep();
+    }
+    let iterations = events
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|event| event.pressure_iterations)
+        .sum();
+    (simulation, iterations)
+}
+
+#[test]
+fn krylov_solvers_agree_with_sor() {
+    let presets: [fn() -> presets::SimulationPreset; 2] = [
+        || presets::lid_driven_cavity_sized([48, 40]),
+        || presets::cylinder_cross_flow_sized([66, 25]),
+    ];
+    for preset in presets {
+        let (sor, sweeps) = run(preset(), PressureSolver::Sor);
+        for pressure_solver in [PressureSolver::ConjugateGradient, PressureSolver::BiCgStab] {
+            let (krylov, iterations) = run(preset(), pressure_solver);
+            assert!(iterations < sweeps, "{pressure_solver:?}");
+
+            let [a, b] = [sor.fields(), krylov.fields()];
+            for (velocities, expected) in [(&b.u, &a.u), (&b.v, &a.v)] {
+                for (value, expected) in velocities.iter().zip(expected.iter()) {
+                    assert!((value - expected).abs() < 1e-3, "{value} != {expected}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use flow2d_rs::membrane::{Elasticity, Membrane};
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+
+const ELASTICITY: Elasticity = Elasticity {
+    stretching: 50.0,
+    bending: 0.01,
+};
+
+#[test]
+fn elastic_forces_vanish_at_rest_and_balance_when_deformed() {
+    let mut membrane = Membrane::straight([1.0, 1.0], [2.0, 1.0], 11, ELASTICITY);
+    assert!((membrane.rest_length() - 1.0).abs() < 1e-6);
+    for force in membrane.point_forces() {
+        assert!(force[0].abs() < 1e-3 && force[1].abs() < 1e-3, "{force:?}");
+    }
+
+    // Stretched by ten percent, the ends are pulled in by the tension
+    let stretched: Vec<[f32; 2]> = membrane
+        .points()
+        .iter()
+        .map(|point| [1.0 + 1.1 * (point[0] - 1.0), point[1]])
+        .collect();
+    membrane.set_points(stretched);
+    assert!((membrane.length() - 1.1).abs() < 1e-5);
+    let forces = membrane.point_forces();
+    let tension = ELASTICITY.stretching * 0.1;
+    assert!((forces[0][0] - tension).abs() < 1e-3, "{:?}", forces[0]);
+    assert!((forces[10][0] + tension).abs() < 1e-3, "{:?}", forces[10]);
+    assert!(forces[5][0].abs() < 1e-3, "{:?}", forces[5]);
+
+    // Bent into an arc, the internal forces add up to nothing
+    let bent: Vec<[f32; 2]> = (0..11)
+        .map(|i| {
+            let angle = i as f32 * 0.1;
+            [1.0 + angle.sin(), 1.0 + 1.0 - angle.cos()]
+        })
+        .collect();
+    membrane.set_points(bent);
+    let forces = membrane.point_forces();
+    let total = forces.iter().fold([0.0, 0.0], |sum, force| {
+        [sum[0] + force[0], sum[1] + force[1]]
+    });
+    assert!(total[0].abs() < 1e-3 && total[1].abs() < 1e-3, "{total:?}");
+    assert!(
+        forces[5][1] < 0.0,
+        "bending straightens the arc {:?}",
+        forces[5]
+    );
+}
+
+#[test]
+fn spreading_hands_the_whole_force_to_the_fluid() {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow_sized([55, 21]));
+    let mut membrane = Membrane::straight([6.0, 1.0], [6.0, 3.0], 21, ELASTICITY)
+        .with_tether(0, 100.0)
+        .with_tether(20, 100.0);
+    let displaced = membrane
+        .points()
+        .iter()
+        .map(|point| [point[0] + 0.1, point[1]])
+        .collect();
+    membrane.set_points(displaced);
+    simulation.add_membrane(membrane);
+    simulation.iterate_one_timestep();
+
+    let membrane = &simulation.membranes()[0];
+    let expected = membrane
+        .point_forces()
+        .iter()
+        .fold([0.0, 0.0], |sum, force| {
+            [sum[0] + force[0], sum[1] + force[1]]
+        });
+    let area = 11.0 / 55.0 * 4.1 / 21.0;
+    let spread = membrane.force().iter().fold([0.0, 0.0], |sum, force| {
+        [sum[0] + force[0] * area, sum[1] + force[1] * area]
+    });
+    assert!(expected[0] < -10.0, "{expected:?}");
+    for axis in 0..2 {
+        assert!(
+            (spread[axis] - expected[axis]).abs() < 1e-3 * expected[0].abs(),
+            "{spread:?} != {expected:?}"
+        );
+    }
+}
+
+#[test]
+fn the_flow_bulges_a_membrane_tethered_across_the_channel() {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow_sized([55, 21]));
+    let membrane = Membrane::straight([7.0, 1.0], [7.0, 3.1], 22, ELASTICITY)
+        .with_tether(0, 1000.0)
+        .with_tether(21, 1000.0);
+    let rest_length = membrane.rest_length();
+    simulation.add_membrane(membrane);
+    for _ in 0..400 {
+        simulation.iterate_one_timestep();
+    }
+
+    let membrane = &simulation.membranes()[0];
+    let points = membrane.points();
+    for (point, anchor) in [(points[0], [7.0, 1.0]), (points[21], [7.0, 3.1])] {
+        assert!(
+            (point[0] - anchor[0]).abs() < 0.05 && (point[1] - anchor[1]).abs() < 0.05,
+            "{point:?} != {anchor:?}"
+        );
+    }
+    let middle = [
+        (points[10][0] + points[11][0]) / 2.0,
+        (points[10][1] + points[11][1]) / 2.0,
+    ];
+    assert!(middle[0] > 7.3, "{middle:?}");
+    assert!((middle[1] - 2.05).abs() < 0.05, "{middle:?}");
+    assert!(membrane.length() > 1.05 * rest_length);
+    assert!(points.iter().all(|point| point[0] >= 6.95));
+}
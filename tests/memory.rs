@@ -0,0 +1,66 @@
+use flow2d_rs::cell::{Cell, CellType};
+use flow2d_rs::memory;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::space_domain::SpaceDomain;
+
+use std::mem::size_of;
+
+#[test]
+fn footprint_counts_the_buffers_of_the_grid() {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([32, 16]));
+    let footprint = simulation.memory_footprint();
+    for field in ["u", "v", "pressure", "rhs", "f", "g", "psi", "dye"] {
+        assert_eq!(footprint.bytes(field), Some(32 * 16 * 4), "{field}");
+    }
+    assert_eq!(footprint.bytes("flags"), Some(32 * 16 * 2));
+    assert_eq!(footprint.bytes("previous pressure"), None);
+    let sum: usize = footprint.buffers.iter().map(|(_, bytes)| bytes).sum();
+    assert_eq!(footprint.total(), sum);
+    assert!(footprint.to_string().ends_with("MiB"));
+
+    simulation.set_warm_start(true);
+    simulation.iterate_one_timestep();
+    let footprint = simulation.memory_footprint();
+    assert_eq!(footprint.bytes("previous pressure"), Some(32 * 16 * 4));
+}
+
+#[test]
+fn oversized_grids_fail_instead_of_aborting() {
+    assert!(presets::by_name_sized("backward_facing_step", [64, 64]).is_none());
+    let preset = presets::by_name_sized("lid_driven_cavity", [64, 48])
+        .unwrap()
+        .unwrap();
+    assert_eq!(preset.space_domain.space_size(), [64, 48]);
+
+    // A petabyte, refused by the first buffer the domain reserves
+    let Some(Err(error)) = presets::by_name_sized("cylinder_cross_flow", [1 << 24, 1 << 20]) else {
+        panic!("a petabyte grid must fail");
+    };
+    assert_eq!(error.space_size, [1 << 24, 1 << 20]);
+    assert_eq!(error.buffer, "cell types");
+    assert_eq!(error.bytes, Some((1 << 44) * size_of::<CellType>()));
+    assert!(error.to_string().contains("16777216 x 1048576 grid"));
+
+    let error = SpaceDomain::try_new([usize::MAX, 2], [1.0, 1.0], 0.9, |_, _| Cell::default())
+        .err()
+        .unwrap();
+    assert_eq!(error.bytes, None);
+    assert!(error.to_string().contains("address space"));
+}
+
+#[test]
+fn try_new_lays_out_the_cells_in_cell_order() {
+    let space_domain = SpaceDomain::try_new([3, 2], [1.0, 1.0], 0.9, |x, y| Cell {
+        pressure: (10 * x + y) as f32,
+        ..Default::default()
+    })
+    .unwrap();
+    let pressure: Vec<f32> = space_domain.pressure_iter().collect();
+    assert_eq!(pressure, [0.0, 1.0, 10.0, 11.0, 20.0, 21.0]);
+    assert_eq!(space_domain.fluid_cells().len(), 6);
+
+    let mut footprint = memory::MemoryFootprint::new();
+    space_domain.memory_footprint(&mut footprint);
+    assert_eq!(footprint.bytes("pressure"), Some(6 * 4));
+}
@@ -0,0 +1,142 @@
+use flow2d_rs::cell::CellType;
+use flow2d_rs::mesh_import::{MeshError, QuadMesh};
+use flow2d_rs::simulation::Simulation;
+
+// 4 x 4 nodes one unit apart, the 3 x 3 quadrangles between them without the middle
+// one, which leaves a hole
+fn ring_nodes() -> Vec<(usize, [f32; 2])> {
+    (0..16)
+        .map(|node| (node + 1, [(node % 4) as f32, (node / 4) as f32]))
+        .collect()
+}
+
+fn ring_quads() -> Vec<[usize; 4]> {
+    let node = |i: usize, j: usize| j * 4 + i + 1;
+    (0..3)
+        .flat_map(|j| (0..3).map(move |i| (i, j)))
+        .filter(|&(i, j)| (i, j) != (1, 1))
+        .map(|(i, j)| {
+            [
+                node(i, j),
+                node(i + 1, j),
+                node(i + 1, j + 1),
+                node(i, j + 1),
+            ]
+        })
+        .collect()
+}
+
+fn msh2() -> String {
+    let mut text = String::from("$MeshFormat\n2.2 0 8\n$EndMeshFormat\n$Nodes\n16\n");
+    for (tag, [x, y]) in ring_nodes() {
+        text += &format!("{tag} {x} {y} 0\n");
+    }
+    let quads = ring_quads();
+    // A boundary line as Gmsh writes them next to the surface elements
+    text += &format!("$EndNodes\n$Elements\n{}\n1 1 2 0 1 1 2\n", quads.len() + 1);
+    for (element, [a, b, c, d]) in quads.iter().enumerate() {
+        text += &format!("{} 3 2 0 1 {a} {b} {c} {d}\n", element + 2);
+    }
+    text + "$EndElements\n"
+}
+
+fn msh4() -> String {
+    let mut text = String::from("$MeshFormat\n4.1 0 8\n$EndMeshFormat\n$Nodes\n1 16 1 16\n");
+    text += "2 1 0 16\n";
+    for (tag, _) in ring_nodes() {
+        text += &format!("{tag}\n");
+    }
+    for (_, [x, y]) in ring_nodes() {
+        text += &format!("{x} {y} 0\n");
+    }
+    let quads = ring_quads();
+    text += &format!(
+        "$EndNodes\n$Elements\n1 {0} 1 {0}\n2 1 3 {0}\n",
+        quads.len()
+    );
+    for (element, [a, b, c, d]) in quads.iter().enumerate() {
+        text += &format!("{} {a} {b} {c} {d}\n", element + 1);
+    }
+    text + "$EndElements\n"
+}
+
+#[test]
+fn structured_meshes_map_onto_cells_with_checked_spacing() {
+    // 5 x 4 nodes a quarter apart, the x of every node and then the y
+    let mut text = String::from("5 4\n");
+    for _ in 0..4 {
+        for i in 0..5 {
+            text += &format!("{} ", i as f32 * 0.25);
+        }
+    }
+    for j in 0..4 {
+        for _ in 0..5 {
+            text += &format!("{} ", 1.0 + j as f32 * 0.25);
+        }
+    }
+    let mesh = QuadMesh::parse_structured(&text).unwrap();
+    assert_eq!(mesh.quads.len(), 4 * 3);
+    assert_eq!(mesh.element_size(), [0.25, 0.25]);
+
+    let domain = mesh.to_domain([0.25, 0.25]).unwrap();
+    assert_eq!([domain.cells.len(), domain.cells[0].len()], [6, 5]);
+    assert_eq!(domain.origin, [-0.25, 0.75]);
+    let fluid = domain
+        .cells
+        .iter()
+        .flatten()
+        .filter(|cell| matches!(cell.cell_type, CellType::FluidCell))
+        .count();
+    assert_eq!(fluid, 4 * 3);
+    assert!(matches!(domain.cells[0][0].cell_type, CellType::VoidCell));
+    assert_eq!(domain.segments.len(), 1);
+    assert_eq!(domain.segments[0].cells.len(), 2 * 4 + 2 * 3);
+
+    // Half cells would pad the mesh, cells larger than an element lose it
+    assert_eq!(
+        mesh.to_domain([0.3, 0.25]).unwrap_err(),
+        MeshError::SpacingMismatch {
+            axis: 0,
+            extent: 1.0,
+            delta_space: 0.3
+        }
+    );
+    assert!(matches!(
+        mesh.to_domain([0.25, 0.75]),
+        Err(MeshError::TooCoarse { axis: 1, .. })
+    ));
+    assert!(QuadMesh::parse_structured("5 4\n0 0.25").is_err());
+}
+
+#[test]
+fn gmsh_holes_become_obstacles() {
+    let mesh = QuadMesh::parse_msh(&msh2()).unwrap();
+    assert_eq!(mesh, QuadMesh::parse_msh(&msh4()).unwrap());
+    assert_eq!(mesh.quads.len(), 8);
+
+    // Two cells per element
+    let domain = mesh.to_domain([0.5, 0.5]).unwrap();
+    let names: Vec<&str> = domain
+        .segments
+        .iter()
+        .map(|segment| segment.name.as_str())
+        .collect();
+    assert_eq!(names, ["outer_wall", "hole_0"]);
+    assert_eq!(domain.segments[1].cells, [(3, 3), (3, 4), (4, 3), (4, 4)]);
+
+    let mut simulation = Simulation::from_preset(domain.into_preset(0.01, 100.0));
+    assert!(matches!(
+        simulation.get_cell(3, 3).cell_type,
+        CellType::BoundaryConditionCell(_)
+    ));
+    simulation.step_n(2);
+    assert_eq!(
+        simulation.segment_statistics("hole_0").unwrap().face_count,
+        8
+    );
+
+    let triangles = msh2().replace("1 1 2 0 1 1 2\n", "1 2 2 0 1 1 2 5\n");
+    assert!(QuadMesh::parse_msh(&triangles).is_err());
+    let binary = msh2().replace("2.2 0 8", "2.2 1 8");
+    assert!(QuadMesh::parse_msh(&binary).is_err());
+}
@@ -0,0 +1,144 @@
+use flow2d_rs::metadata::{SimulationMetadata, CRATE_VERSION};
+use flow2d_rs::netcdf::NetcdfWriter;
+use flow2d_rs::output_sink::{CheckpointSink, CsvStatsSink, SnapshotSink};
+use flow2d_rs::presets;
+use flow2d_rs::run_controller::RunController;
+use flow2d_rs::simulation::{PressureSolver, Simulation};
+use flow2d_rs::snapshot_writer::SnapshotFormat;
+use flow2d_rs::solver::{Checkpoint, FluidSolver};
+
+fn cavity() -> Simulation {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([24, 24]));
+    simulation.set_pressure_solver(PressureSolver::BiCgStab);
+    let metadata = SimulationMetadata::new("lid_driven_cavity", &simulation);
+    simulation.set_metadata(Some(metadata));
+    simulation
+}
+
+#[test]
+fn metadata_round_trips_through_its_text() {
+    let simulation = cavity();
+    let mut metadata = simulation.metadata().unwrap().clone();
+    assert_eq!(metadata.scene, "lid_driven_cavity");
+    assert_eq!(metadata.parameters, simulation.parameters());
+    assert_eq!(metadata.solver.pressure_solver, "BiCgStab");
+    assert_eq!(metadata.crate_version, CRATE_VERSION);
+    assert!(metadata.start_time > 0);
+    assert_eq!(
+        SimulationMetadata::parse(&metadata.to_string()),
+        Some(metadata.clone())
+    );
+
+    metadata.parameters.inflow_velocity = Some([0.1, -2.5]);
+    metadata.git_hash = Some("0123abcd".to_string());
+    metadata.scene = "a = b".to_string();
+    assert_eq!(
+        SimulationMetadata::parse(&metadata.to_string()),
+        Some(metadata.clone())
+    );
+
+    // Line breaks stay on the scene's line, literal backslashes survive
+    let multiline = SimulationMetadata::new("two\nlines\r, not \\n", &simulation);
+    let text = multiline.to_string();
+    assert!(text.starts_with("scene=two\\nlines\\r, not \\\\n\n"));
+    assert_eq!(SimulationMetadata::parse(&text), Some(multiline));
+
+    let without_reynolds: String = metadata
+        .to_string()
+        .lines()
+        .filter(|line| !line.starts_with("reynolds="))
+        .map(|line| format!("{line}\n"))
+        .collect();
+    assert_eq!(SimulationMetadata::parse(&without_reynolds), None);
+}
+
+#[test]
+fn checkpoints_carry_the_metadata() {
+    let directory = std::env::temp_dir().join(format!("flow2d_rs_metadata_{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("cavity.f2dc");
+
+    let mut simulation = cavity();
+    simulation.iterate_one_timestep();
+    simulation.checkpoint().save(&path).unwrap();
+    let checkpoint = Checkpoint::load(&path).unwrap();
+    assert_eq!(checkpoint.metadata.as_ref(), simulation.metadata());
+
+    // A restarted run keeps the provenance of the saved one
+    let mut restored = Simulation::from_preset(presets::lid_driven_cavity_sized([24, 24]));
+    restored.restore(&checkpoint);
+    assert_eq!(restored.metadata(), simulation.metadata());
+
+    // Version 1 files have no metadata block
+    let mut plain = simulation.checkpoint();
+    plain.metadata = None;
+    plain.save(&path).unwrap();
+    let mut bytes = std::fs::read(&path).unwrap();
+    assert_eq!(&bytes[20..24], &[0; 4]);
+    bytes.drain(20..24);
+    bytes[4..8].copy_from_slice(&1u32.to_le_bytes());
+    std::fs::write(&path, bytes).unwrap();
+    let checkpoint = Checkpoint::load(&path).unwrap();
+    assert_eq!(checkpoint.metadata, None);
+    assert_eq!(checkpoint.cells.len(), plain.cells.len());
+    assert_eq!(checkpoint.time, plain.time);
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn exports_record_the_metadata() {
+    let directory =
+        std::env::temp_dir().join(format!("flow2d_rs_metadata_exports_{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let mut simulation = cavity();
+
+    let mut netcdf = NetcdfWriter::create(directory.join("run.nc"), &simulation, &[]).unwrap();
+    RunController::new()
+        .max_steps(4)
+        .snapshot_interval(4)
+        .sink(CsvStatsSink::create(directory.join("stats.csv"), 2).unwrap())
+        .sink(SnapshotSink::new(&directory, SnapshotFormat::Vtk).unwrap())
+        .sink(CheckpointSink::new(directory.join("latest.f2dc")))
+        .run(&mut simulation);
+    netcdf.append(&simulation).unwrap();
+    netcdf.finish().unwrap();
+    let metadata = simulation.metadata().unwrap();
+
+    // Comment lines, then the usual header and rows
+    let stats = std::fs::read_to_string(directory.join("stats.csv")).unwrap();
+    let comments: String = stats
+        .lines()
+        .take_while(|line| line.starts_with('#'))
+        .map(|line| format!("{}\n", line.strip_prefix("# ").unwrap()))
+        .collect();
+    assert_eq!(
+        SimulationMetadata::parse(&comments).as_ref(),
+        Some(metadata)
+    );
+    let mut lines = stats.lines().skip_while(|line| line.starts_with('#'));
+    assert!(lines.next().unwrap().starts_with("step,time,"));
+    assert_eq!(lines.count(), 2);
+
+    let vtk = std::fs::read_to_string(directory.join("snapshot_000000.vtk")).unwrap();
+    let title = vtk.lines().nth(1).unwrap();
+    assert!(title.contains(CRATE_VERSION) && title.contains("lid_driven_cavity"));
+    assert!(vtk.contains("FIELD FieldData 5\nTIME 1 1 double\n"));
+    assert!(vtk.contains(&format!(
+        "reynolds 1 1 double\n{}\n",
+        metadata.parameters.reynolds
+    )));
+
+    let checkpoint = Checkpoint::load(directory.join("latest.f2dc")).unwrap();
+    assert_eq!(checkpoint.metadata.as_ref(), Some(metadata));
+
+    let netcdf = std::fs::read(directory.join("run.nc")).unwrap();
+    let contains = |text: &str| {
+        netcdf
+            .windows(text.len())
+            .any(|window| window == text.as_bytes())
+    };
+    assert!(contains("scene") && contains("lid_driven_cavity") && contains("BiCgStab"));
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,152 @@
+use flow2d_rs::field::Field;
+use flow2d_rs::netcdf::{Attribute, NetcdfWriter};
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+
+use std::collections::HashMap;
+
+struct Header {
+    records: u32,
+    dimensions: Vec<(String, u32)>,
+    attributes: HashMap<String, Vec<u8>>,
+    // name -> (dimension ids, size per record, begin)
+    variables: HashMap<String, (Vec<u32>, u32, u64)>,
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, count: usize) -> &[u8] {
+        let start = self.position;
+        self.position += count;
+        &self.bytes[start..start + count]
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_be_bytes(self.take(4).try_into().unwrap())
+    }
+
+    // Padded to four bytes
+    fn bytes(&mut self, count: usize) -> Vec<u8> {
+        let bytes = self.take(count).to_vec();
+        self.take((4 - count % 4) % 4);
+        bytes
+    }
+
+    fn name(&mut self) -> String {
+        let length = self.u32() as usize;
+        String::from_utf8(self.bytes(length)).unwrap()
+    }
+
+    fn attributes(&mut self) -> HashMap<String, Vec<u8>> {
+        self.u32();
+        (0..self.u32())
+            .map(|_| {
+                let name = self.name();
+                let size = match self.u32() {
+                    2 => 1,
+                    6 => 8,
+                    nc_type => panic!("unexpected attribute type {nc_type}"),
+                };
+                let count = self.u32() as usize;
+                (name, self.bytes(count * size))
+            })
+            .collect()
+    }
+}
+
+fn read_header(bytes: &[u8]) -> Header {
+    let mut reader = Reader { bytes, position: 0 };
+    assert_eq!(reader.take(4), b"CDF\x02");
+    let records = reader.u32();
+
+    assert_eq!(reader.u32(), 0x0A);
+    let dimensions = (0..reader.u32())
+        .map(|_| (reader.name(), reader.u32()))
+        .collect();
+    let attributes = reader.attributes();
+
+    assert_eq!(reader.u32(), 0x0B);
+    let variables = (0..reader.u32())
+        .map(|_| {
+            let name = reader.name();
+            let dimension_ids = (0..reader.u32()).map(|_| reader.u32()).collect();
+            reader.attributes();
+            reader.u32();
+            let size = reader.u32();
+            let begin = u64::from_be_bytes(reader.take(8).try_into().unwrap());
+            (name, (dimension_ids, size, begin))
+        })
+        .collect();
+
+    Header {
+        records,
+        dimensions,
+        attributes,
+        variables,
+    }
+}
+
+fn float(bytes: &[u8], offset: u64) -> f32 {
+    let offset = offset as usize;
+    f32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[test]
+fn records_append_to_one_netcdf_file() {
+    let path = std::env::temp_dir().join(format!("flow2d_rs_{}.nc", std::process::id()));
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity());
+    let [nx, ny] = simulation.space_size();
+
+    let mut writer = NetcdfWriter::create(
+        &path,
+        &simulation,
+        &[("title", Attribute::Text("cavity".to_string()))],
+    )
+    .unwrap();
+    for _ in 0..3 {
+        simulation.iterate_one_timestep();
+        writer.append(&simulation).unwrap();
+    }
+    assert_eq!(writer.records(), 3);
+    writer.finish().unwrap();
+
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let header = read_header(&bytes);
+
+    assert_eq!(header.records, 3);
+    assert_eq!(header.dimensions[0], ("time".to_string(), 0));
+    assert_eq!(header.dimensions[1], ("x".to_string(), nx as u32));
+    assert_eq!(header.attributes["title"], b"cavity");
+
+    let record_size: u64 = header
+        .variables
+        .values()
+        .filter(|(dimensions, _, _)| dimensions[0] == 0)
+        .map(|&(_, size, _)| size as u64)
+        .sum();
+    let (_, _, first_record) = header.variables["time"];
+    assert_eq!(bytes.len() as u64, first_record + 3 * record_size);
+
+    let (_, _, x_face) = header.variables["x_face"];
+    assert_eq!(float(&bytes, x_face), simulation.delta_space()[0]);
+
+    let last = 2 * record_size;
+    assert_eq!(
+        float(&bytes, header.variables["time"].2 + last),
+        simulation.time()
+    );
+    let (dimensions, _, u) = &header.variables["u"];
+    assert_eq!(dimensions, &[0, 3, 2]);
+    let view = simulation.field(Field::U);
+    let index = (nx / 2 * ny + ny - 2) as u64;
+    assert_ne!(view.get(nx / 2, ny - 2), 0.0);
+    assert_eq!(
+        float(&bytes, u + last + 4 * index),
+        view.get(nx / 2, ny - 2)
+    );
+}
@@ -0,0 +1,26 @@
+use flow2d_rs::observer::StepEvent;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn observers_see_every_step() {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity());
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let log = events.clone();
+    simulation.add_observer(move |event: &StepEvent| log.lock().unwrap().push(*event));
+
+    for _ in 0..5 {
+        simulation.iterate_one_timestep();
+    }
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 5);
+    for (index, event) in events.iter().enumerate() {
+        assert_eq!(event.step, index);
+        assert!(event.pressure_iterations <= 100);
+        assert!(event.pressure_residual.is_finite());
+    }
+    assert_eq!(events[4].time, simulation.time());
+}
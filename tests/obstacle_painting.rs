@@ -0,0 +1,92 @@
+use flow2d_rs::cell::{BoundaryConditionCell, CellFlags, CellType};
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+use flow2d_rs::space_domain::SpaceDomain;
+
+const N: usize = 10;
+
+fn cavity() -> SpaceDomain {
+    presets::lid_driven_cavity_sized([N, N]).space_domain
+}
+
+#[test]
+fn painting_a_wall_updates_the_flags_and_cell_lists() {
+    let mut space_domain = cavity();
+    let index = 4 * N + 5;
+    assert!(space_domain.fluid_cells().contains(&index));
+
+    assert!(space_domain.set_obstacle(4, 5, true));
+    assert!(matches!(
+        space_domain.cell_type(4, 5),
+        CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+            boundary_condition_velocity: [0.0, 0.0]
+        })
+    ));
+    assert_eq!(
+        space_domain.flags(4, 5),
+        CellFlags::OBSTACLE | CellFlags::SURFACE | CellFlags::FLUID_NEIGHBORS
+    );
+    assert!(!space_domain.flags(3, 5).contains(CellFlags::FLUID_EAST));
+    assert!(!space_domain.flags(5, 5).contains(CellFlags::FLUID_WEST));
+    assert!(!space_domain.flags(4, 4).contains(CellFlags::FLUID_NORTH));
+    assert!(!space_domain.flags(4, 6).contains(CellFlags::FLUID_SOUTH));
+    assert!(!space_domain.fluid_cells().contains(&index));
+    assert!(space_domain.boundary_cells().contains(&index));
+    assert_eq!(space_domain.fluid_cells().len(), (N - 2) * (N - 2) - 1);
+
+    // Already a wall
+    assert!(!space_domain.set_obstacle(4, 5, true));
+}
+
+#[test]
+fn erasing_a_wall_restores_the_fluid() {
+    let original = cavity();
+    let mut space_domain = cavity();
+    for (x, y) in [(4, 5), (5, 5), (5, 6)] {
+        space_domain.set_obstacle(x, y, true);
+    }
+    for (x, y) in [(4, 5), (5, 5), (5, 6)] {
+        assert!(space_domain.set_obstacle(x, y, false));
+        assert!(matches!(space_domain.cell_type(x, y), CellType::FluidCell));
+    }
+
+    assert_eq!(space_domain.cell_flags(), original.cell_flags());
+    assert_eq!(space_domain.fluid_cells(), original.fluid_cells());
+    assert_eq!(space_domain.boundary_cells(), original.boundary_cells());
+    // Fluid cells and the walls and corners of the domain can't be erased or painted
+    assert!(!space_domain.set_obstacle(4, 5, false));
+    assert!(!space_domain.set_obstacle(0, 5, false));
+    assert!(!space_domain.set_obstacle(4, N - 1, true));
+    assert!(!space_domain.set_obstacle(0, 0, true));
+}
+
+#[test]
+fn painted_walls_hold_no_flow_until_erased() {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([N, N]));
+    for _ in 0..10 {
+        simulation.iterate_one_timestep();
+    }
+    assert!(simulation.set_obstacle(4, 5, true));
+    for _ in 0..10 {
+        simulation.iterate_one_timestep();
+    }
+    // The faces of the wall on its left and bottom and its own faces
+    assert_eq!(simulation.get_cell(3, 5).velocity[0], 0.0);
+    assert_eq!(simulation.get_cell(4, 4).velocity[1], 0.0);
+    assert_eq!(simulation.get_cell(4, 5).velocity, [0.0, 0.0]);
+
+    assert!(simulation.set_obstacle(4, 5, false));
+    for _ in 0..10 {
+        simulation.iterate_one_timestep();
+    }
+    assert!(matches!(
+        simulation.get_cell(4, 5).cell_type,
+        CellType::FluidCell
+    ));
+    assert_ne!(simulation.get_cell(4, 5).velocity, [0.0, 0.0]);
+    assert!(simulation
+        .cells()
+        .iter()
+        .all(|cell| cell.velocity.iter().all(|v| v.is_finite())));
+}
@@ -0,0 +1,83 @@
+use flow2d_rs::cell::BoundaryConditionCell;
+use flow2d_rs::obstacles::{Obstacle, Shape};
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+
+#[test]
+fn forces_are_monitored_per_obstacle() {
+    let mut plate = Obstacle::new(
+        "plate",
+        Shape::Rectangle {
+            min: [5.0, 1.0],
+            max: [5.3, 3.0],
+        },
+    );
+    plate.monitor_force = true;
+    let mut wedge = Obstacle::new(
+        "wedge",
+        Shape::Polygon(vec![[7.0, 1.5], [8.5, 2.0], [7.0, 2.5]]),
+    );
+    wedge.wall = BoundaryConditionCell::FreeSlipCell;
+    wedge.monitor_force = true;
+    let preset = presets::cylinder_cross_flow()
+        .with_obstacle(plate)
+        .with_obstacle(wedge);
+
+    let mut simulation = Simulation::from_preset(preset);
+    assert_eq!(simulation.obstacles().len(), 3);
+    assert_eq!(simulation.obstacle("plate").unwrap().cells().len(), 3 * 20);
+    assert!(!simulation.obstacle("wedge").unwrap().cells().is_empty());
+
+    for _ in 0..20 {
+        simulation.iterate_one_timestep();
+    }
+    assert!(simulation
+        .obstacle("cylinder")
+        .unwrap()
+        .force_samples
+        .is_empty());
+    let samples = &simulation.obstacle("plate").unwrap().force_samples;
+    assert_eq!(samples.len(), 20);
+    assert_eq!(samples.last().unwrap().0, simulation.time());
+    assert!(
+        samples.last().unwrap().1[0] > 0.0,
+        "the flow pushes the plate"
+    );
+
+    // Without overlaps the no-slip obstacles add up to the total no-slip force
+    let force = |name: &str| simulation.named_obstacle_force(name).unwrap();
+    let total = simulation.obstacle_force();
+    for component in 0..2 {
+        let sum = force("cylinder")[component] + force("plate")[component];
+        assert!((sum - total[component]).abs() < 1e-4 * total[0].abs());
+    }
+}
+
+#[test]
+fn rasterizer_reports_features_below_the_cell_size() {
+    let airfoil = Shape::naca_airfoil(0.12, 2.0, 40)
+        .rotated(-0.1, [0.0, 0.0])
+        .translated([4.0, 2.05]);
+    let thin_plate = Shape::Rectangle {
+        min: [8.0, 1.0],
+        max: [8.02, 3.0],
+    }
+    .rotated(0.5, [8.0, 2.0]);
+    let preset = presets::cylinder_cross_flow_sized([220, 82])
+        .with_obstacle(Obstacle::new("airfoil", airfoil))
+        .with_obstacle(Obstacle::new("plate", thin_plate));
+    let simulation = Simulation::from_preset(preset);
+
+    // NACA 00xx airfoils cover about 0.685 * thickness * chord^2
+    let report = simulation.obstacle("airfoil").unwrap().raster_report();
+    let area = 0.685 * 0.12 * 4.0;
+    assert!((report.shape_area - area).abs() < 0.05 * area, "{report:?}");
+    assert!((report.wall_area - area).abs() < 0.2 * area, "{report:?}");
+    // The sharp trailing edge is thinner than a cell
+    assert!(!report.is_complete());
+
+    let plate = simulation.obstacle("plate").unwrap();
+    assert!(plate.cells().is_empty());
+    assert!(!plate.raster_report().is_complete());
+    assert_eq!(plate.raster_report().wall_area, 0.0);
+}
@@ -0,0 +1,113 @@
+use flow2d_rs::output_sink::{CheckpointSink, CsvStatsSink, OutputSink, SnapshotSink};
+use flow2d_rs::presets;
+use flow2d_rs::run_controller::{Progress, RunController, RunReport, StopReason};
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::snapshot_writer::SnapshotFormat;
+use flow2d_rs::solver::{Checkpoint, FluidSolver};
+
+use std::io;
+
+#[derive(Default)]
+struct CountingSink {
+    steps: usize,
+    snapshots: Vec<usize>,
+    finished: Option<StopReason>,
+}
+
+impl OutputSink for CountingSink {
+    fn on_step(&mut self, _solver: &dyn FluidSolver, _progress: &Progress) -> io::Result<()> {
+        self.steps += 1;
+        Ok(())
+    }
+
+    fn on_snapshot(&mut self, _solver: &dyn FluidSolver, index: usize) -> io::Result<()> {
+        self.snapshots.push(index);
+        Ok(())
+    }
+
+    fn on_finish(&mut self, _solver: &dyn FluidSolver, report: &RunReport) -> io::Result<()> {
+        self.finished = Some(report.reason);
+        Ok(())
+    }
+}
+
+struct FailingSink;
+
+impl OutputSink for FailingSink {
+    fn on_snapshot(&mut self, _solver: &dyn FluidSolver, _index: usize) -> io::Result<()> {
+        Err(io::Error::other("disk full"))
+    }
+}
+
+#[test]
+fn built_in_sinks_write_their_outputs() {
+    let directory =
+        std::env::temp_dir().join(format!("flow2d_rs_output_sink_{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity());
+    let mut counting = CountingSink::default();
+
+    let report = RunController::new()
+        .max_steps(7)
+        .snapshot_interval(3)
+        .sink(&mut counting)
+        .sink(CsvStatsSink::create(directory.join("stats.csv"), 2).unwrap())
+        .sink(SnapshotSink::new(&directory, SnapshotFormat::Vtk).unwrap())
+        .sink(SnapshotSink::new(&directory, SnapshotFormat::Png).unwrap())
+        .sink(CheckpointSink::new(directory.join("latest.f2dc")))
+        .run(&mut simulation);
+
+    assert_eq!(report.reason, StopReason::MaxSteps);
+    assert_eq!(counting.steps, 7);
+    assert_eq!(counting.snapshots, vec![0, 1]);
+    assert_eq!(counting.finished, Some(StopReason::MaxSteps));
+
+    // Steps 2, 4, 6 and the final step 7
+    let stats = std::fs::read_to_string(directory.join("stats.csv")).unwrap();
+    let steps: Vec<&str> = stats
+        .lines()
+        .skip(1)
+        .map(|line| line.split(',').next().unwrap())
+        .collect();
+    assert_eq!(steps, vec!["2", "4", "6", "7"]);
+
+    for name in [
+        "snapshot_000000.vtk",
+        "snapshot_000001.vtk",
+        "snapshot_000001.png",
+    ] {
+        assert!(directory.join(name).exists(), "{name} missing");
+    }
+
+    let checkpoint = Checkpoint::load(directory.join("latest.f2dc")).unwrap();
+    assert_eq!(checkpoint.time, simulation.time());
+    assert_eq!(checkpoint.space_size, simulation.space_size());
+    let mut restored = Simulation::from_preset(presets::lid_driven_cavity());
+    restored.restore(&checkpoint);
+    restored.iterate_one_timestep();
+    simulation.iterate_one_timestep();
+    assert_eq!(restored.fields().u, simulation.fields().u);
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn a_failing_sink_stops_the_run() {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity());
+    let mut counting = CountingSink::default();
+    let mut controller = RunController::new()
+        .max_steps(10)
+        .snapshot_interval(4)
+        .sink(FailingSink)
+        .sink(&mut counting);
+
+    let report = controller.run(&mut simulation);
+    assert_eq!(report.reason, StopReason::OutputFailed);
+    assert_eq!(report.progress.steps, 4);
+    assert_eq!(
+        controller.take_output_error().unwrap().to_string(),
+        "disk full"
+    );
+    drop(controller);
+    assert_eq!(counting.finished, Some(StopReason::OutputFailed));
+}
@@ -0,0 +1,64 @@
+use flow2d_rs::observer::StepEvent;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::{optimal_omega, Simulation};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[test]
+fn shared_parameters_apply_at_the_next_timestep() {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow());
+    let shared = simulation.share_parameters();
+    let delta_time = simulation.delta_time();
+
+    let writer = shared.clone();
+    std::thread::spawn(move || {
+        writer.update(|parameters| {
+            parameters.reynolds = 50.0;
+            parameters.acceleration = [0.0, -9.81];
+            parameters.omega = 1.5;
+            parameters.delta_time = delta_time / 2.0;
+        })
+    })
+    .join()
+    .unwrap();
+    assert_ne!(simulation.reynolds(), 50.0);
+
+    simulation.iterate_one_timestep();
+    assert_eq!(simulation.reynolds(), 50.0);
+    assert_eq!(simulation.acceleration(), [0.0, -9.81]);
+    assert_eq!(simulation.omega(), 1.5);
+    assert_eq!(simulation.time(), delta_time / 2.0);
+
+    // A setter sticks until the shared parameters change again
+    simulation.set_reynolds(80.0);
+    simulation.iterate_one_timestep();
+    assert_eq!(simulation.reynolds(), 80.0);
+}
+
+#[test]
+fn optimal_omega_grows_with_the_grid_and_saves_sweeps() {
+    let square = |cells: usize| optimal_omega([cells, cells], [1.0 / cells as f32; 2]);
+    assert!(1.0 < square(10) && square(10) < square(100) && square(100) < 2.0);
+    // 2 / (1 + sin(pi / n)) on a square grid of n interior cells
+    let expected = 2.0 / (1.0 + (std::f32::consts::PI / 126.0).sin());
+    assert!((square(128) - expected).abs() < 1e-3);
+
+    let sweeps = |optimal: bool| {
+        let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow());
+        if optimal {
+            let omega = simulation.set_optimal_omega();
+            assert_eq!(simulation.omega(), omega);
+        }
+        let sweeps = Arc::new(AtomicUsize::new(0));
+        let counter = sweeps.clone();
+        simulation.add_observer(move |event: &StepEvent| {
+            counter.fetch_add(event.pressure_iterations, Ordering::Relaxed);
+        });
+        for _ in 0..100 {
+            simulation.iterate_one_timestep();
+        }
+        sweeps.load(Ordering::Relaxed)
+    };
+    assert!(sweeps(true) < sweeps(false));
+}
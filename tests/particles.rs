@@ -0,0 +1,189 @@
+use flow2d_rs::cell::{BoundaryConditionCell, Cell, CellType};
+use flow2d_rs::events::EventSchedule;
+use flow2d_rs::particles::{Particle, Particles};
+use flow2d_rs::presets::{self, SimulationPreset};
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+use flow2d_rs::space_domain::SpaceDomain;
+
+fn closed_box(size: usize) -> SimulationPreset {
+    let wall = Cell {
+        cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+            boundary_condition_velocity: [0.0, 0.0],
+        }),
+        ..Default::default()
+    };
+    let cells = (0..size)
+        .map(|x| {
+            (0..size)
+                .map(|y| {
+                    if x == 0 || y == 0 || x == size - 1 || y == size - 1 {
+                        wall.clone()
+                    } else {
+                        Cell::default()
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let delta = 1.0 / size as f32;
+    SimulationPreset {
+        space_domain: SpaceDomain::new(cells, [delta, delta], 0.9),
+        delta_time: 0.01,
+        reynolds: 100.0,
+        acceleration: [0.0, 0.0],
+        events: EventSchedule::new(),
+        obstacles: Vec::new(),
+        dye_buoyancy: None,
+    }
+}
+
+fn particle(position: [f32; 2], velocity: [f32; 2], response_time: f32, mass: f32) -> Particle {
+    Particle {
+        position,
+        velocity,
+        response_time,
+        mass,
+        restitution: 0.0,
+    }
+}
+
+#[test]
+fn a_heavy_particle_sinks_at_its_terminal_velocity_and_rests_on_the_floor() {
+    let mut simulation = Simulation::from_preset(closed_box(20));
+    let response_time = 0.05;
+    let particles = Particles::new([0.0, -1.0]).with_particle(particle(
+        [0.5, 0.5],
+        [0.0, 0.0],
+        response_time,
+        1e-6,
+    ));
+    simulation.set_particles(Some(particles));
+
+    for _ in 0..30 {
+        simulation.iterate_one_timestep();
+    }
+    let [u, v] = simulation.particles().unwrap().particles()[0].velocity;
+    assert!(u.abs() < 1e-4, "{u}");
+    assert!(
+        (v + response_time).abs() < 1e-3,
+        "{v} != {}",
+        -response_time
+    );
+
+    for _ in 0..1000 {
+        simulation.iterate_one_timestep();
+    }
+    let particle = simulation.particles().unwrap().particles()[0];
+    let floor = 1.0 / 20.0;
+    assert!(
+        particle.position[1] >= floor && particle.position[1] < floor + 1e-3,
+        "{:?}",
+        particle.position
+    );
+    assert!(
+        (particle.position[0] - 0.5).abs() < 1e-3,
+        "{:?}",
+        particle.position
+    );
+}
+
+#[test]
+fn particles_bounce_off_walls_with_their_restitution() {
+    let mut simulation = Simulation::from_preset(closed_box(20));
+    let bouncing = Particle {
+        restitution: 1.0,
+        ..particle([0.5, 0.5], [1.0, 0.0], 1e6, 0.0)
+    };
+    let damped = Particle {
+        restitution: 0.5,
+        ..particle([0.5, 0.3], [1.0, 0.0], 1e6, 0.0)
+    };
+    simulation.set_particles(Some(
+        Particles::new([0.0, 0.0])
+            .with_particle(bouncing)
+            .with_particle(damped),
+    ));
+    for _ in 0..60 {
+        simulation.iterate_one_timestep();
+    }
+
+    // The right wall is 0.45 meters away, 0.15 meters less than the distance travelled
+    let particles = simulation.particles().unwrap().particles();
+    let [bouncing, damped] = [particles[0], particles[1]];
+    assert!(
+        (bouncing.velocity[0] + 1.0).abs() < 1e-4,
+        "{:?}",
+        bouncing.velocity
+    );
+    assert!(
+        (bouncing.position[0] - 0.8).abs() < 1e-3,
+        "{:?}",
+        bouncing.position
+    );
+    assert!(
+        (damped.velocity[0] + 0.5).abs() < 1e-4,
+        "{:?}",
+        damped.velocity
+    );
+    assert!(
+        (damped.position[0] - 0.875).abs() < 1e-3,
+        "{:?}",
+        damped.position
+    );
+}
+
+#[test]
+fn the_drag_of_a_particle_sets_the_fluid_in_motion_conserving_momentum() {
+    let size = 18;
+    let preset = presets::kelvin_helmholtz_sized([size, size]).initial_velocity(|_, _| [0.0, 0.0]);
+    let mut simulation = Simulation::from_preset(preset);
+    let mass = 0.01;
+    simulation.set_particles(Some(Particles::new([0.0, 0.0]).with_particle(particle(
+        [0.5, 0.5],
+        [1.0, 0.0],
+        0.02,
+        mass,
+    ))));
+    let fluid_momentum = |simulation: &Simulation| {
+        let fields = simulation.fields();
+        let delta = 1.0 / (size - 2) as f32;
+        (0..size * size)
+            .filter(|&index| matches!(fields.cell_type[index], CellType::FluidCell))
+            .map(|index| fields.u[index])
+            .sum::<f32>()
+            * delta
+            * delta
+    };
+
+    for _ in 0..100 {
+        simulation.iterate_one_timestep();
+    }
+    let particles = simulation.particles().unwrap();
+    let particle_momentum = particles.momentum()[0];
+    let fluid = fluid_momentum(&simulation);
+    // The particle handed most of its momentum to the fluid around it
+    assert!(particle_momentum < 0.5 * mass, "{particle_momentum}");
+    assert!(fluid > 0.5 * mass, "{fluid}");
+    let total = particle_momentum + fluid;
+    assert!((total - mass).abs() < 0.02 * mass, "{total} != {mass}");
+}
+
+#[test]
+fn particles_leave_through_the_outflow() {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow_sized([44, 16]));
+    simulation.set_particles(Some(Particles::new([0.0, 0.0]).with_particle(particle(
+        [9.0, 1.0],
+        [1.5, 0.0],
+        0.01,
+        0.0,
+    ))));
+    for _ in 0..100 {
+        simulation.iterate_one_timestep();
+    }
+    assert_eq!(simulation.particles().unwrap().particles().len(), 1);
+    for _ in 0..300 {
+        simulation.iterate_one_timestep();
+    }
+    assert!(simulation.particles().unwrap().particles().is_empty());
+}
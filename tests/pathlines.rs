@@ -0,0 +1,66 @@
+mod common;
+
+use common::cylinder;
+use flow2d_rs::pathlines::{Pathlines, Streaklines};
+use flow2d_rs::run_controller::RunController;
+
+#[test]
+fn streaklines_grow_from_their_seeds() {
+    let mut simulation = cylinder();
+    let dy = simulation.delta_space()[1];
+    let seeds = [[0.5, 5.5 * dy], [0.5, 15.5 * dy]];
+    let mut streaklines = Streaklines::new(&seeds)
+        .with_release_every(2)
+        .with_max_markers(Some(8));
+    // Releases on the first record and every other one after it
+    RunController::new()
+        .max_steps(31)
+        .sink(&mut streaklines)
+        .run(&mut simulation);
+
+    let lines = streaklines.lines();
+    assert_eq!(lines.len(), 2);
+    for (line, seed) in lines.iter().zip(seeds) {
+        assert_eq!(line.len(), 8);
+        // The newest marker sits on the seed, older ones further downstream
+        assert_eq!(line[0], (simulation.time(), seed));
+        assert!(line.windows(2).all(|pair| pair[0].0 > pair[1].0));
+        assert!(line.windows(2).all(|pair| pair[0].1[0] < pair[1].1[0]));
+        let age = simulation.time() - line[7].0;
+        let distance = line[7].1[0] - seed[0];
+        assert!(distance > 0.5 * age && distance < 3.0 * age);
+    }
+
+    let mut csv = Vec::new();
+    streaklines.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert_eq!(csv.lines().next(), Some("line,point,time,x,y"));
+    assert_eq!(csv.lines().count(), 1 + 16);
+    streaklines.clear();
+    assert!(streaklines.lines().iter().all(Vec::is_empty));
+}
+
+#[test]
+fn pathlines_follow_their_tracers_until_they_leave() {
+    let mut simulation = cylinder();
+    let [dx, dy] = simulation.delta_space();
+    // In the flow, inside the cylinder and just upstream of the outflow
+    let mut pathlines = Pathlines::new(&[[2.5 * dx, 4.5 * dy], [0.0, 0.0], [53.5 * dx, 10.5 * dy]]);
+    for _ in 0..20 {
+        pathlines.record(&simulation);
+        simulation.iterate_one_timestep();
+    }
+    pathlines.record(&simulation);
+
+    let lines = pathlines.lines();
+    assert_eq!(lines[0].len(), 21);
+    assert_eq!(lines[0][0], (0.0, [2.5 * dx, 4.5 * dy]));
+    assert_eq!(lines[0][20].0, simulation.time());
+    assert!(lines[0].windows(2).all(|pair| pair[1].1[0] > pair[0].1[0]));
+    assert!(pathlines.is_active(0));
+    // Never in the fluid
+    assert!(lines[1].is_empty() && !pathlines.is_active(1));
+    // Carried out through the outflow
+    assert!(!pathlines.is_active(2));
+    assert!(lines[2].len() < 21);
+}
@@ -0,0 +1,95 @@
+use flow2d_rs::colormap::Colormap;
+use flow2d_rs::frame_renderer::{FrameOutput, FrameRecorder, FrameRenderer, RenderField};
+use flow2d_rs::png::{write_png, PngColor};
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+
+// (kind, data, crc) of every chunk after the signature
+fn chunks(bytes: &[u8]) -> Vec<([u8; 4], Vec<u8>, u32)> {
+    assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+    let mut chunks = Vec::new();
+    let mut rest = &bytes[8..];
+    while !rest.is_empty() {
+        let length = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+        let kind = rest[4..8].try_into().unwrap();
+        let data = rest[8..8 + length].to_vec();
+        let crc = u32::from_be_bytes(rest[8 + length..12 + length].try_into().unwrap());
+        chunks.push((kind, data, crc));
+        rest = &rest[12 + length..];
+    }
+    chunks
+}
+
+fn decode(bytes: &[u8]) -> (png::OutputInfo, Vec<u8>) {
+    let mut reader = png::Decoder::new(bytes).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    pixels.truncate(info.buffer_size());
+    (info, pixels)
+}
+
+#[test]
+fn chunks_carry_the_reference_crc_and_adler_values() {
+    let mut bytes = Vec::new();
+    write_png(&mut bytes, [9, 1], PngColor::Grayscale, b"Wikipedia").unwrap();
+
+    let chunks = chunks(&bytes);
+    let kinds: Vec<&[u8; 4]> = chunks.iter().map(|(kind, _, _)| kind).collect();
+    assert_eq!(kinds, [b"IHDR", b"IDAT", b"IEND"]);
+    // Reference values from zlib.crc32 and zlib.adler32
+    assert_eq!(chunks[0].2, 0x29a9_dba1);
+    assert_eq!(chunks[2].2, 0xae42_6082);
+    // Adler-32 of the filter byte and the row, at the end of the zlib stream
+    let idat = &chunks[1].1;
+    assert_eq!(idat[idat.len() - 4..], 0x11e7_0398u32.to_be_bytes());
+}
+
+#[test]
+fn a_real_decoder_reads_the_pixels_back() {
+    let pixels: Vec<u8> = (0..5 * 3).map(|i| (i * 17) as u8).collect();
+    let mut bytes = Vec::new();
+    write_png(&mut bytes, [5, 3], PngColor::Grayscale, &pixels).unwrap();
+    let (info, decoded) = decode(&bytes);
+    assert_eq!([info.width, info.height], [5, 3]);
+    assert_eq!(info.color_type, png::ColorType::Grayscale);
+    assert_eq!(decoded, pixels);
+
+    // Larger than one stored deflate block
+    let size = [200, 100];
+    let pixels: Vec<u8> = (0..size[0] * size[1] * 4)
+        .map(|i| (i % 251) as u8)
+        .collect();
+    let mut bytes = Vec::new();
+    write_png(&mut bytes, size, PngColor::Rgba, &pixels).unwrap();
+    let (info, decoded) = decode(&bytes);
+    assert_eq!([info.width, info.height], [200, 100]);
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    assert_eq!(decoded, pixels);
+}
+
+#[test]
+fn recorded_frames_decode_to_the_rendered_image() {
+    let directory =
+        std::env::temp_dir().join(format!("flow2d_rs_png_frames_{}", std::process::id()));
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([12, 10]));
+    let renderer = FrameRenderer::new(RenderField::Speed, Colormap::viridis(), 2);
+    let mut recorder = FrameRecorder::new(
+        FrameRenderer::new(RenderField::Speed, Colormap::viridis(), 2),
+        FrameOutput::PngFiles(directory.clone()),
+        1,
+    )
+    .unwrap();
+    for _ in 0..3 {
+        simulation.iterate_one_timestep();
+    }
+    assert!(recorder.record(&simulation).unwrap());
+    recorder.finish().unwrap();
+
+    let image = renderer.render(&simulation);
+    let bytes = std::fs::read(directory.join("frame_000000.png")).unwrap();
+    let (info, decoded) = decode(&bytes);
+    assert_eq!([info.width as usize, info.height as usize], image.size);
+    assert_eq!(image.size, [24, 20]);
+    assert_eq!(decoded, image.pixels);
+    std::fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,63 @@
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+
+const N: usize = 20;
+
+fn linear(x: f32, y: f32) -> [f32; 3] {
+    [1.0 + 2.0 * x + 3.0 * y, 4.0 - x + 0.5 * y, 2.0 * x - y]
+}
+
+// Cavity whose u, v and pressure are the linear functions above, every cell holds
+// them on its own staggered location
+fn linear_fields() -> Simulation {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([N, N]));
+    let [dx, dy] = simulation.delta_space();
+    let mut checkpoint = simulation.checkpoint();
+    for x in 0..N {
+        for y in 0..N {
+            let (x_center, y_center) = ((x as f32 + 0.5) * dx, (y as f32 + 0.5) * dy);
+            let cell = &mut checkpoint.cells[x * N + y];
+            cell.velocity = [
+                linear((x + 1) as f32 * dx, y_center)[0],
+                linear(x_center, (y + 1) as f32 * dy)[1],
+            ];
+            cell.pressure = linear(x_center, y_center)[2];
+        }
+    }
+    simulation.restore(&checkpoint);
+    simulation
+}
+
+#[test]
+fn linear_fields_are_sampled_exactly() {
+    let simulation = linear_fields();
+    for position in [[0.3, 0.4], [0.52, 0.71], [0.15, 0.85], [0.5, 0.5]] {
+        let sample = simulation
+            .sample_at_point(position[0], position[1])
+            .unwrap();
+        let [u, v, pressure] = linear(position[0], position[1]);
+        assert!((sample.velocity[0] - u).abs() < 1e-4, "{position:?}");
+        assert!((sample.velocity[1] - v).abs() < 1e-4, "{position:?}");
+        assert!((sample.pressure - pressure).abs() < 1e-4, "{position:?}");
+        // dv/dx - du/dy
+        assert!((sample.vorticity + 4.0).abs() < 1e-2, "{position:?}");
+    }
+}
+
+#[test]
+fn points_outside_the_domain_or_in_walls_have_no_sample() {
+    let simulation = linear_fields();
+    let [dx, dy] = simulation.delta_space();
+    for position in [[-0.01, 0.5], [0.5, 1.01], [f32::NAN, 0.5]] {
+        assert!(simulation
+            .sample_at_point(position[0], position[1])
+            .is_none());
+    }
+    // The bottom wall, the lid and a void corner
+    assert!(simulation.sample_at_point(0.5, 0.5 * dy).is_none());
+    assert!(simulation.sample_at_point(0.5, 1.0 - 0.5 * dy).is_none());
+    assert!(simulation.sample_at_point(0.5 * dx, 0.5 * dy).is_none());
+    // Just inside the fluid
+    assert!(simulation.sample_at_point(1.5 * dx, 1.5 * dy).is_some());
+}
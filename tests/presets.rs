@@ -0,0 +1,150 @@
+use flow2d_rs::perturbation::Perturbation;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+
+#[test]
+fn initial_conditions_are_sampled_on_the_staggered_grid() {
+    let preset = presets::lid_driven_cavity_sized([32, 32])
+        .initial_velocity(|x, y| [y - 0.5, 0.5 - x])
+        .initial_pressure(|x, y| x + 2.0 * y);
+    let simulation = Simulation::from_preset(preset);
+    let d = 1.0 / 32.0;
+
+    let cell = simulation.get_cell(10, 20);
+    assert!((cell.velocity[0] - (20.5 * d - 0.5)).abs() < 1e-6);
+    assert!((cell.velocity[1] - (0.5 - 10.5 * d)).abs() < 1e-6);
+    assert!((cell.pressure - (10.5 * d + 2.0 * 20.5 * d)).abs() < 1e-6);
+
+    // Boundary cells and the faces towards them are left alone
+    assert_eq!(simulation.get_cell(0, 20).pressure, 0.0);
+    assert_eq!(simulation.get_cell(30, 20).velocity[0], 0.0);
+    assert_eq!(simulation.get_cell(10, 30).velocity[1], 0.0);
+}
+
+#[test]
+fn perturbation_is_reproducible_and_divergence_free() {
+    let perturbation = Perturbation {
+        amplitude: 0.1,
+        wavelength: 0.25,
+        seed: 7,
+    };
+    let perturbed = |perturbation: &Perturbation| {
+        Simulation::from_preset(presets::lid_driven_cavity_sized([64, 64]).perturbed(perturbation))
+    };
+    let simulation = perturbed(&perturbation);
+    let d = 1.0 / 64.0;
+
+    let mut squared_speed = 0.0;
+    let mut fluid_cells = 0;
+    for x in 1..63 {
+        for y in 1..63 {
+            let velocity = |x: usize, y: usize| simulation.get_cell(x, y).velocity;
+            let divergence = (velocity(x, y)[0] - velocity(x - 1, y)[0]) / d
+                + (velocity(x, y)[1] - velocity(x, y - 1)[1]) / d;
+            assert!(divergence.abs() < 1e-3, "({x}, {y}): {divergence}");
+            squared_speed += velocity(x, y)[0].powi(2) + velocity(x, y)[1].powi(2);
+            fluid_cells += 1;
+        }
+    }
+    let rms_speed = (squared_speed / fluid_cells as f32).sqrt();
+    assert!(rms_speed > 0.05 && rms_speed < 0.2, "{rms_speed}");
+
+    let same = perturbed(&perturbation);
+    let other = perturbed(&Perturbation {
+        seed: 8,
+        ..perturbation
+    });
+    assert_eq!(
+        same.get_cell(20, 30).velocity,
+        simulation.get_cell(20, 30).velocity
+    );
+    assert_ne!(
+        other.get_cell(20, 30).velocity,
+        simulation.get_cell(20, 30).velocity
+    );
+}
+
+#[test]
+fn kelvin_helmholtz_is_periodic_and_conserves_momentum() {
+    let mut simulation = Simulation::from_preset(presets::kelvin_helmholtz_sized([34, 34]));
+    let [nx, ny] = simulation.space_size();
+    let momentum = |simulation: &Simulation| {
+        (1..nx - 1)
+            .flat_map(|x| (1..ny - 1).map(move |y| (x, y)))
+            .map(|(x, y)| simulation.get_cell(x, y).velocity[0])
+            .sum::<f32>()
+    };
+    let initial_momentum = momentum(&simulation);
+
+    for _ in 0..50 {
+        simulation.iterate_one_timestep();
+    }
+    assert!((momentum(&simulation) - initial_momentum).abs() < 1e-2);
+
+    // The periodic cells mirror the cells one in from the opposite edge
+    for y in 1..ny - 1 {
+        assert_eq!(
+            simulation.get_cell(0, y).velocity,
+            simulation.get_cell(nx - 2, y).velocity
+        );
+        assert_eq!(
+            simulation.get_cell(nx - 1, y).pressure,
+            simulation.get_cell(1, y).pressure
+        );
+    }
+}
+
+#[test]
+fn every_preset_can_be_loaded_by_name() {
+    for name in presets::NAMES {
+        let preset = presets::by_name(name).unwrap();
+        assert!(preset.space_domain.space_size()[0] > 0, "{name}");
+    }
+    assert!(presets::by_name("no_such_preset").is_none());
+}
+
+// Horizontal position and height of the dye centroid in cells
+fn dye_centroid(simulation: &Simulation) -> [f32; 2] {
+    let [nx, ny] = simulation.space_size();
+    let dye = &simulation.fields().dye;
+    let mut sums = [0.0; 3];
+    for x in 1..nx - 1 {
+        for y in 1..ny - 1 {
+            let amount = dye[x * ny + y];
+            sums[0] += amount;
+            sums[1] += amount * x as f32;
+            sums[2] += amount * y as f32;
+        }
+    }
+    [sums[1] / sums[0], sums[2] / sums[0]]
+}
+
+#[test]
+fn the_buoyant_plume_rises_above_passive_dye() {
+    let run = |buoyant: bool| {
+        let mut preset = presets::buoyant_plume_sized([24, 48]);
+        if !buoyant {
+            preset.dye_buoyancy = None;
+        }
+        let mut simulation = Simulation::from_preset(preset);
+        for _ in 0..600 {
+            simulation.iterate_one_timestep();
+        }
+        simulation
+    };
+    let buoyant = run(true);
+    let passive = run(false);
+    assert_eq!(buoyant.dye_buoyancy(), Some([0.0, 0.1 * 9.81]));
+
+    let [x, height] = dye_centroid(&buoyant);
+    let [_, passive_height] = dye_centroid(&passive);
+    assert!(height > 2.0 * passive_height, "{height} {passive_height}");
+    assert!((x - 11.5).abs() < 0.5, "{x}");
+    // Faster than the inflow
+    assert!(
+        buoyant.speed_range()[1] > 0.3,
+        "{:?}",
+        buoyant.speed_range()
+    );
+}
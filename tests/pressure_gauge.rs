@@ -0,0 +1,47 @@
+use flow2d_rs::cell::CellType;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::{PressureGauge, Simulation};
+use flow2d_rs::solver::FluidSolver;
+
+fn run(pressure_gauge: PressureGauge) -> Simulation {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([32, 32]));
+    simulation.set_pressure_gauge(pressure_gauge);
+    for _ in 0..50 {
+        simulation.iterate_one_timestep();
+    }
+    simulation
+}
+
+#[test]
+fn a_gauge_fixes_the_pressure_level_only() {
+    let floating = run(PressureGauge::Floating);
+    let zero_mean = run(PressureGauge::ZeroMean);
+    let reference = run(PressureGauge::ReferenceCell(16, 16));
+    assert_eq!(
+        reference.pressure_gauge(),
+        PressureGauge::ReferenceCell(16, 16)
+    );
+
+    let fields = zero_mean.fields();
+    let fluid_pressures: Vec<f32> = fields
+        .pressure
+        .iter()
+        .zip(fields.cell_type.iter())
+        .filter(|(_, cell_type)| matches!(cell_type, CellType::FluidCell))
+        .map(|(&pressure, _)| pressure)
+        .collect();
+    let mean = fluid_pressures.iter().sum::<f32>() / fluid_pressures.len() as f32;
+    assert!(mean.abs() < 1e-4, "mean pressure {mean}");
+    assert_eq!(reference.get_cell(16, 16).pressure, 0.0);
+
+    // Differences and velocities agree with the floating level
+    for simulation in [&zero_mean, &reference] {
+        let difference = |simulation: &Simulation| {
+            simulation.get_cell(20, 10).pressure - simulation.get_cell(8, 24).pressure
+        };
+        assert!((difference(simulation) - difference(&floating)).abs() < 1e-3);
+        for (u, expected) in simulation.fields().u.iter().zip(floating.fields().u.iter()) {
+            assert!((u - expected).abs() < 1e-4);
+        }
+    }
+}
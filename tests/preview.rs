@@ -0,0 +1,29 @@
+use flow2d_rs::presets;
+use flow2d_rs::preview::Preview;
+
+#[test]
+fn refine_continues_the_coarse_run() {
+    let mut preview = Preview::new(presets::lid_driven_cavity_sized, [64, 64], 4);
+    assert_eq!(preview.simulation().space_size(), [16, 16]);
+    assert_eq!(preview.simulation().delta_time(), 0.02);
+    for _ in 0..50 {
+        preview.simulation_mut().iterate_one_timestep();
+    }
+    let coarse_time = preview.simulation().time();
+    let coarse = preview.simulation().sample_at_point(0.5, 0.9).unwrap();
+
+    let mut simulation = preview.refine();
+    assert_eq!(simulation.space_size(), [64, 64]);
+    assert_eq!(simulation.delta_time(), 0.005);
+    assert_eq!(simulation.time(), coarse_time);
+
+    let fine = simulation.sample_at_point(0.5, 0.9).unwrap();
+    assert!((fine.velocity[0] - coarse.velocity[0]).abs() < 0.05);
+    assert!(
+        fine.velocity[0] > 0.05,
+        "the lid has set the flow in motion"
+    );
+
+    simulation.iterate_one_timestep();
+    assert!(simulation.speed_range()[1].is_finite());
+}
@@ -0,0 +1,55 @@
+use flow2d_rs::colormap::Colormap;
+use flow2d_rs::observer::StepEvent;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn residual_map_matches_the_pressure_solve() {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow_sized([55, 21]));
+    let residuals = Arc::new(Mutex::new(Vec::new()));
+    let log = residuals.clone();
+    simulation
+        .add_observer(move |event: &StepEvent| log.lock().unwrap().push(event.pressure_residual));
+    for _ in 0..5 {
+        simulation.iterate_one_timestep();
+    }
+    let error = simulation.projection_error();
+    let reported = *residuals.lock().unwrap().last().unwrap();
+    // The solve measures its norm before the pressure gauge shifts the pressure
+    assert!((error.rms_residual() - reported).abs() <= 0.02 * reported);
+
+    let ([x, y], worst) = error.worst_residual().unwrap();
+    assert_eq!(error.residual().get(x, y), worst);
+    assert!(error
+        .residual()
+        .fluid_iter()
+        .all(|(_, residual)| residual.abs() <= worst.abs()));
+    assert_eq!(error.residual[0], 0.0);
+}
+
+#[test]
+fn divergence_is_that_of_the_projected_velocity() {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow_sized([55, 21]));
+    for _ in 0..5 {
+        simulation.iterate_one_timestep();
+    }
+    let error = simulation.projection_error();
+    let [dx, dy] = simulation.delta_space();
+    let fields = simulation.fields();
+    let [x, y] = [30, 7];
+    let index = x * 21 + y;
+    let expected = (fields.u[index] - fields.u[index - 21]) / dx
+        + (fields.v[index] - fields.v[index - 1]) / dy;
+    assert_eq!(error.divergence().get(x, y), expected);
+    assert!(error.worst_divergence().unwrap().1.abs() >= expected.abs());
+    assert!(error.rms_divergence() > 0.0);
+
+    let snapshot = error.to_snapshot();
+    assert_eq!(snapshot.field("divergence").unwrap()[index], expected);
+    assert_eq!(snapshot.field("residual"), Some(error.residual.as_slice()));
+    let image = error.residual_heatmap(&Colormap::coolwarm(), 1);
+    assert_eq!(image.size, [55, 21]);
+}
@@ -0,0 +1,42 @@
+use flow2d_rs::presets;
+use flow2d_rs::quiver::quiver;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+
+const SIZE: [usize; 2] = [20, 12];
+
+// Cavity whose faces, walls included, all move with [1, 0.5]
+fn uniform_flow() -> Simulation {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized(SIZE));
+    let mut checkpoint = simulation.checkpoint();
+    for cell in checkpoint.cells.iter_mut() {
+        cell.velocity = [1.0, 0.5];
+    }
+    simulation.restore(&checkpoint);
+    simulation
+}
+
+#[test]
+fn one_arrow_per_fluid_block() {
+    let simulation = uniform_flow();
+    // Columns 2, 6, .., 18 and rows 2, 6, 10
+    assert_eq!(quiver(&simulation, 4, 1.0).len(), 5 * 3);
+    // Columns 1, 4, .., 16 and rows 1, 4, 7, 10, column 19 is the wall
+    assert_eq!(quiver(&simulation, 3, 1.0).len(), 6 * 4);
+    // Every fluid cell
+    assert_eq!(quiver(&simulation, 1, 1.0).len(), 18 * 10);
+}
+
+#[test]
+fn arrows_point_along_a_uniform_flow() {
+    let simulation = uniform_flow();
+    let arrows = quiver(&simulation, 4, 2.0);
+    for arrow in &arrows {
+        assert_eq!(arrow.vector, [2.0, 1.0]);
+    }
+
+    let [dx, dy] = simulation.delta_space();
+    assert_eq!(arrows[0].position, [2.5 * dx, 2.5 * dy]);
+    assert_eq!(arrows[1].position, [2.5 * dx, 6.5 * dy]);
+    assert_eq!(arrows.last().unwrap().position, [18.5 * dx, 10.5 * dy]);
+}
@@ -0,0 +1,85 @@
+use flow2d_rs::presets;
+use flow2d_rs::reduction::{self, Reduction};
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+
+fn run_with_threads(threads: usize, reduction: Reduction) -> Vec<[u32; 3]> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap();
+
+    pool.install(|| {
+        let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow());
+        simulation.set_reduction(reduction);
+        for _ in 0..20 {
+            simulation.iterate_one_timestep();
+        }
+        simulation
+            .checkpoint()
+            .cells
+            .iter()
+            .map(|cell| {
+                [
+                    cell.velocity[0].to_bits(),
+                    cell.velocity[1].to_bits(),
+                    cell.pressure.to_bits(),
+                ]
+            })
+            .collect()
+    })
+}
+
+#[test]
+fn deterministic_reduction_is_bit_identical_across_thread_counts() {
+    assert_eq!(
+        run_with_threads(1, Reduction::Deterministic),
+        run_with_threads(4, Reduction::Deterministic)
+    );
+}
+
+#[test]
+fn sequential_reduction_is_bit_identical_across_thread_counts() {
+    assert_eq!(
+        run_with_threads(1, Reduction::Sequential),
+        run_with_threads(4, Reduction::Sequential)
+    );
+}
+
+// Halves down to runs of at most 1024 values summed left to right, the split points
+// of Reduction::Deterministic
+fn reference_pairwise_sum(values: &[f32]) -> f32 {
+    if values.len() <= 1024 {
+        return values.iter().sum();
+    }
+    let (left, right) = values.split_at(values.len() / 2);
+    reference_pairwise_sum(left) + reference_pairwise_sum(right)
+}
+
+#[test]
+fn deterministic_sum_follows_fixed_pairwise_splits() {
+    // Magnitudes far apart so the summation order shows in the result
+    let values: Vec<f32> = (0..10_000)
+        .map(|i| {
+            if i % 7 == 0 {
+                1e4
+            } else {
+                0.1 + i as f32 * 1e-3
+            }
+        })
+        .collect();
+    let expected = reference_pairwise_sum(&values);
+    assert_ne!(
+        expected.to_bits(),
+        reduction::sum(&values, Reduction::Sequential).to_bits()
+    );
+
+    for threads in [1, 2, 3, 8] {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        let sum = pool.install(|| reduction::sum(&values, Reduction::Deterministic));
+        assert_eq!(sum.to_bits(), expected.to_bits(), "{threads} threads");
+    }
+}
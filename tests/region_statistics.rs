@@ -0,0 +1,23 @@
+use flow2d_rs::presets;
+use flow2d_rs::region_statistics::section_statistics;
+use flow2d_rs::simulation::Simulation;
+
+#[test]
+fn flow_rate_is_conserved_along_the_channel() {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow());
+    for _ in 0..100 {
+        simulation.iterate_one_timestep();
+    }
+    let [nx, ny] = simulation.space_size();
+    let [dx, dy] = simulation.delta_space();
+    let height = ny as f32 * dy;
+
+    // Bottom to top, so flow in +x counts as positive
+    let flow_rate = |x: f32| section_statistics(&simulation, [x, 0.0], [x, height]).flow_rate;
+    let inflow = flow_rate(dx);
+    assert!(inflow > 0.0);
+    for x in [10, nx / 2, nx - 1] {
+        let difference = (flow_rate(x as f32 * dx) - inflow).abs();
+        assert!(difference < 1e-3 * inflow, "x = {x}: {difference}");
+    }
+}
@@ -0,0 +1,78 @@
+use flow2d_rs::cell::CellType;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::{FluidSolver, RenderCell};
+
+const SIZE: [usize; 2] = [6, 4];
+
+// Cavity whose faces hold u = 10 x + y and v = 100 + 10 x + y, pressure is the index
+fn known_cells() -> Simulation {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized(SIZE));
+    let mut checkpoint = simulation.checkpoint();
+    let [nx, ny] = SIZE;
+    for x in 0..nx {
+        for y in 0..ny {
+            let cell = &mut checkpoint.cells[x * ny + y];
+            cell.velocity = [(10 * x + y) as f32, (100 + 10 * x + y) as f32];
+            cell.pressure = (x * ny + y) as f32;
+            cell.dye = 0.5;
+        }
+    }
+    simulation.restore(&checkpoint);
+    simulation
+}
+
+#[test]
+fn cells_are_packed_in_image_order() {
+    let simulation = known_cells();
+    let mut buffer = Vec::new();
+    simulation.pack_render_buffer(&mut buffer);
+
+    let [nx, ny] = SIZE;
+    assert_eq!(buffer.len(), nx * ny);
+    assert_eq!(std::mem::size_of::<RenderCell>(), 6 * 4);
+    for row in 0..ny {
+        for x in 0..nx {
+            // Rows top to bottom
+            let y = ny - 1 - row;
+            let packed = buffer[row * nx + x];
+            let cell = simulation.get_cell(x, y);
+            assert_eq!(packed.pressure, (x * ny + y) as f32);
+            assert_eq!(packed.psi, cell.psi);
+            assert_eq!(packed.dye, 0.5);
+            let is_fluid = matches!(cell.cell_type, CellType::FluidCell);
+            assert_eq!(packed.is_fluid, is_fluid as u32);
+            if !is_fluid {
+                assert_eq!(packed.velocity, [0.0, 0.0]);
+            }
+        }
+    }
+}
+
+#[test]
+fn velocities_are_centred_between_the_faces() {
+    let simulation = known_cells();
+    let mut buffer = Vec::new();
+    simulation.pack_render_buffer(&mut buffer);
+
+    // Fluid cell (2, 1) is in the third row from the top, between u faces 11 and 21
+    // and v faces 120 and 121
+    let [nx, _] = SIZE;
+    assert_eq!(buffer[2 * nx + 2].velocity, [16.0, 120.5]);
+    assert_eq!(
+        buffer[2 * nx + 2].velocity,
+        simulation.get_centered_velocity(2, 1)
+    );
+}
+
+#[test]
+fn the_buffer_is_refilled_in_place() {
+    let simulation = known_cells();
+    let mut buffer = vec![RenderCell::default(); 100];
+    let capacity = buffer.capacity();
+    simulation.pack_render_buffer(&mut buffer);
+    simulation.pack_render_buffer(&mut buffer);
+
+    assert_eq!(buffer.len(), SIZE[0] * SIZE[1]);
+    assert_eq!(buffer.capacity(), capacity);
+}
@@ -0,0 +1,41 @@
+use flow2d_rs::cell::CellType;
+use flow2d_rs::presets;
+use flow2d_rs::region_statistics::section_statistics;
+use flow2d_rs::simulation::Simulation;
+
+#[test]
+fn resampling_keeps_the_flow_rate_and_closed_edges() {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow());
+    for _ in 0..100 {
+        simulation.iterate_one_timestep();
+    }
+    let height = 4.1;
+    let flow_rate = |simulation: &Simulation| {
+        section_statistics(simulation, [5.0, 0.0], [5.0, height]).flow_rate
+    };
+    let before = flow_rate(&simulation);
+
+    for new_size in [[220, 82], [55, 20]] {
+        simulation.resample(new_size);
+        let [nx, ny] = simulation.space_size();
+        assert_eq!([nx, ny], new_size);
+        for x in 0..nx {
+            for y in [0, ny - 1] {
+                assert!(!matches!(
+                    simulation.get_cell(x, y).cell_type,
+                    CellType::FluidCell
+                ));
+            }
+        }
+
+        let difference = (flow_rate(&simulation) - before).abs();
+        assert!(difference < 0.05 * before, "{new_size:?}: {difference}");
+
+        simulation.iterate_one_timestep();
+        let difference = (flow_rate(&simulation) - before).abs();
+        assert!(
+            difference < 0.05 * before,
+            "{new_size:?} after a step: {difference}"
+        );
+    }
+}
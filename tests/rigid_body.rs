@@ -0,0 +1,75 @@
+use flow2d_rs::obstacles::{RigidBody, SpringMount};
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+
+use std::f32::consts::PI;
+
+fn channel_with_body(body: RigidBody) -> Simulation {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow_sized([55, 21]));
+    simulation.obstacle_mut("cylinder").unwrap().rigid_body = Some(body);
+    simulation
+}
+
+#[test]
+fn a_sprung_cylinder_settles_where_the_spring_balances_the_drag() {
+    let spring = SpringMount {
+        stiffness: 8.0,
+        damping: 8.0,
+    };
+    let body = RigidBody::new(2.0)
+        .with_spring(spring)
+        .with_free_axes([true, false]);
+    assert_eq!(body.natural_frequency(), Some(1.0 / PI));
+    let mut simulation = channel_with_body(body);
+    simulation.obstacle_mut("cylinder").unwrap().monitor_force = true;
+    for _ in 0..1000 {
+        simulation.iterate_one_timestep();
+    }
+
+    let body = simulation.obstacle("cylinder").unwrap().rigid_body.unwrap();
+    // Cells changing hands as the body moves make the force jump
+    let samples = &simulation.obstacle("cylinder").unwrap().force_samples;
+    let drag = samples[samples.len() - 200..]
+        .iter()
+        .map(|(_, force)| force[0])
+        .sum::<f32>()
+        / 200.0;
+    let expected = drag / spring.stiffness;
+    assert!(body.displacement[0] > 0.0);
+    assert!(
+        (body.displacement[0] - expected).abs() < 0.1 * expected,
+        "{body:?} {expected}"
+    );
+    assert_eq!(body.displacement[1], 0.0);
+    assert_eq!(body.velocity[1], 0.0);
+}
+
+#[test]
+fn a_free_cylinder_is_carried_downstream_with_its_cells() {
+    let mut simulation = channel_with_body(RigidBody::new(2.0));
+    let leftmost = |simulation: &Simulation| {
+        let cells = simulation.obstacle("cylinder").unwrap().cells();
+        cells.iter().map(|&(x, _)| x).min().unwrap()
+    };
+    let start = leftmost(&simulation);
+    let mut speed = 0.0;
+    for step in 0..400 {
+        simulation.iterate_one_timestep();
+        if step % 100 == 99 {
+            let body = simulation.obstacle("cylinder").unwrap().rigid_body.unwrap();
+            assert!(body.velocity[0] > speed, "{body:?}");
+            speed = body.velocity[0];
+        }
+    }
+
+    let body = simulation.obstacle("cylinder").unwrap().rigid_body.unwrap();
+    assert!(speed < presets::CYLINDER_INFLOW_VELOCITY, "{speed}");
+    // The symmetric flow leaves it on the center line
+    assert!(body.displacement[1].abs() < 1e-3, "{body:?}");
+    let shift = leftmost(&simulation) - start;
+    let expected = body.displacement[0] / 0.2;
+    assert!(
+        (shift as f32 - expected).abs() <= 1.0,
+        "{shift} != {expected}"
+    );
+}
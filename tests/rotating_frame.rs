@@ -0,0 +1,97 @@
+use flow2d_rs::cell::{BoundaryConditionCell, Cell, CellType};
+use flow2d_rs::events::EventSchedule;
+use flow2d_rs::presets::{self, SimulationPreset};
+use flow2d_rs::simulation::{RotatingFrame, Simulation};
+use flow2d_rs::solver::FluidSolver;
+use flow2d_rs::space_domain::SpaceDomain;
+
+use std::f32::consts::PI;
+
+#[test]
+fn a_uniform_flow_turns_in_an_inertial_oscillation() {
+    let preset = presets::kelvin_helmholtz_sized([18, 18]).initial_velocity(|_, _| [1.0, 0.0]);
+    let mut simulation = Simulation::from_preset(preset);
+    let rate = PI / 2.0;
+    let frame = RotatingFrame {
+        rate,
+        centrifugal_center: None,
+    };
+    simulation.set_rotating_frame(Some(frame));
+    assert_eq!(simulation.rotating_frame(), Some(frame));
+
+    // A quarter period of the oscillation, the velocity turns clockwise at 2 rate
+    let steps = (PI / 4.0 / rate / simulation.delta_time()).round() as usize;
+    for _ in 0..steps {
+        simulation.iterate_one_timestep();
+    }
+    let angle = 2.0 * rate * simulation.time();
+    let expected = [angle.cos(), -angle.sin()];
+    let [u, v] = simulation.get_centered_velocity(9, 9);
+    assert!((u - expected[0]).abs() < 1e-2, "{u} != {}", expected[0]);
+    assert!((v - expected[1]).abs() < 1e-2, "{v} != {}", expected[1]);
+}
+
+fn closed_box(size: usize) -> SimulationPreset {
+    let wall = Cell {
+        cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+            boundary_condition_velocity: [0.0, 0.0],
+        }),
+        ..Default::default()
+    };
+    let cells = (0..size)
+        .map(|x| {
+            (0..size)
+                .map(|y| {
+                    if x == 0 || y == 0 || x == size - 1 || y == size - 1 {
+                        wall.clone()
+                    } else {
+                        Cell::default()
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let delta = 1.0 / size as f32;
+    SimulationPreset {
+        space_domain: SpaceDomain::new(cells, [delta, delta], 0.9),
+        delta_time: 0.005,
+        reynolds: 100.0,
+        acceleration: [0.0, 0.0],
+        events: EventSchedule::new(),
+        obstacles: Vec::new(),
+        dye_buoyancy: None,
+    }
+}
+
+#[test]
+fn fluid_at_rest_in_a_rotating_box_holds_a_centrifugal_pressure() {
+    let mut simulation = Simulation::from_preset(closed_box(22));
+    let rate = 2.0;
+    simulation.set_rotating_frame(Some(RotatingFrame {
+        rate,
+        centrifugal_center: Some([0.5, 0.5]),
+    }));
+    for _ in 0..10 {
+        simulation.iterate_one_timestep();
+    }
+
+    let fields = simulation.fields();
+    let speed = fields
+        .u
+        .iter()
+        .chain(fields.v.iter())
+        .fold(0.0f32, |max, value| max.max(value.abs()));
+    assert!(speed < 1e-3, "{speed}");
+
+    // p = rate^2 r^2 / 2 up to a constant
+    let delta = 1.0 / 22.0;
+    let squared_radius = |x: usize, y: usize| {
+        ((x as f32 + 0.5) * delta - 0.5).powi(2) + ((y as f32 + 0.5) * delta - 0.5).powi(2)
+    };
+    let expected = rate.powi(2) / 2.0 * (squared_radius(1, 1) - squared_radius(10, 10));
+    let difference = simulation.get_cell(1, 1).pressure - simulation.get_cell(10, 10).pressure;
+    assert!(
+        (difference - expected).abs() < 0.02 * expected,
+        "{difference} != {expected}"
+    );
+}
@@ -0,0 +1,22 @@
+use flow2d_rs::presets;
+use flow2d_rs::run_controller::{RunController, StopReason};
+use flow2d_rs::simulation::Simulation;
+
+#[test]
+fn first_criterion_met_stops_the_run() {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow());
+    let mut reported_steps = Vec::new();
+    let report = RunController::new()
+        .max_steps(10)
+        .max_time(1.0)
+        .on_progress(4, |progress| reported_steps.push(progress.steps))
+        .run(&mut simulation);
+
+    assert_eq!(report.reason, StopReason::MaxSteps);
+    assert_eq!(report.progress.steps, 10);
+    assert_eq!(reported_steps, vec![4, 8, 10]);
+
+    let report = RunController::new().max_time(0.1).run(&mut simulation);
+    assert_eq!(report.reason, StopReason::MaxTime);
+    assert!((simulation.time() - 0.1).abs() < 1e-4);
+}
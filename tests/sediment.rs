@@ -0,0 +1,146 @@
+use flow2d_rs::cell::{BoundaryConditionCell, Cell, CellType};
+use flow2d_rs::events::EventSchedule;
+use flow2d_rs::presets::{self, SimulationPreset};
+use flow2d_rs::sediment::SedimentSettings;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::space_domain::SpaceDomain;
+
+fn closed_box(size: usize) -> SimulationPreset {
+    let wall = Cell {
+        cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+            boundary_condition_velocity: [0.0, 0.0],
+        }),
+        ..Default::default()
+    };
+    let cells = (0..size)
+        .map(|x| {
+            (0..size)
+                .map(|y| {
+                    if x == 0 || y == 0 || x == size - 1 || y == size - 1 {
+                        wall.clone()
+                    } else {
+                        Cell::default()
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let delta = 1.0 / size as f32;
+    SimulationPreset {
+        space_domain: SpaceDomain::new(cells, [delta, delta], 0.9),
+        delta_time: 0.01,
+        reynolds: 100.0,
+        acceleration: [0.0, 0.0],
+        events: EventSchedule::new(),
+        obstacles: Vec::new(),
+        dye_buoyancy: None,
+    }
+}
+
+#[test]
+fn sediment_settles_out_of_still_water_onto_the_bottom() {
+    let size = 20;
+    let mut simulation = Simulation::from_preset(closed_box(size));
+    let settings = SedimentSettings {
+        settling_velocity: 0.1,
+        inflow_concentration: 0.0,
+    };
+    simulation.set_sediment(Some(settings));
+    simulation.set_sediment_concentration(|_, _| 1.0);
+    let initial = simulation.sediment().unwrap().suspended();
+    assert!((initial - 0.81).abs() < 1e-4, "{initial}");
+
+    for _ in 0..100 {
+        simulation.iterate_one_timestep();
+    }
+    let sediment = simulation.sediment().unwrap();
+    assert_eq!(sediment.settings(), settings);
+
+    // The bottom cells stay saturated until the clear water above reaches them
+    let expected = settings.settling_velocity * simulation.time();
+    for x in 1..size - 1 {
+        let deposit = sediment.deposits()[x * size];
+        assert!((deposit - expected).abs() < 1e-4, "{deposit} != {expected}");
+        assert_eq!(sediment.deposits()[x * size + size - 1], 0.0);
+    }
+    assert_eq!(sediment.deposits()[0], 0.0);
+    assert_eq!(sediment.deposits()[(size - 1) * size], 0.0);
+
+    let top = sediment.concentrations()[10 * size + size - 2];
+    let bottom = sediment.concentrations()[10 * size + 1];
+    assert!(top < 0.2, "{top}");
+    assert!((bottom - 1.0).abs() < 1e-4, "{bottom}");
+
+    let total = sediment.suspended() + sediment.deposited();
+    assert!(
+        (total - initial).abs() < 1e-4 * initial,
+        "{total} != {initial}"
+    );
+}
+
+#[test]
+fn sediment_from_the_inflow_settles_on_the_bed_and_the_cylinder() {
+    let run = |settling_velocity| {
+        let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow_sized([44, 16]));
+        simulation.set_sediment(Some(SedimentSettings {
+            settling_velocity,
+            inflow_concentration: 0.5,
+        }));
+        for _ in 0..400 {
+            simulation.iterate_one_timestep();
+        }
+        simulation
+    };
+
+    let simulation = run(0.3);
+    let sediment = simulation.sediment().unwrap();
+    let ny = 16;
+    let bed: f32 = (1..43).map(|x| sediment.deposits()[x * ny]).sum();
+    let roof: f32 = (1..43).map(|x| sediment.deposits()[x * ny + ny - 1]).sum();
+    assert!(bed > 0.0, "{bed}");
+    assert_eq!(roof, 0.0);
+
+    // Walls inside the channel collect sediment on the top of the cylinder only
+    let cylinder: f32 = (1..43)
+        .flat_map(|x| (1..ny - 1).map(move |y| (x, y)))
+        .filter(|&(x, y)| {
+            matches!(
+                simulation.get_cell(x, y).cell_type,
+                CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell { .. })
+            )
+        })
+        .map(|(x, y)| sediment.deposits()[x * ny + y])
+        .sum();
+    assert!(cylinder > 0.0, "{cylinder}");
+    assert!(sediment.suspended() > 0.0);
+
+    // Sediment that does not sink passes the channel
+    let simulation = run(0.0);
+    let sediment = simulation.sediment().unwrap();
+    assert_eq!(sediment.deposited(), 0.0);
+    assert!(sediment.suspended() > 0.0);
+    assert!(sediment
+        .concentrations()
+        .iter()
+        .all(|&concentration| concentration <= 0.5 + 1e-4));
+}
+
+#[test]
+fn resampling_keeps_the_sediment_settings() {
+    let mut simulation = Simulation::from_preset(closed_box(12));
+    let settings = SedimentSettings {
+        settling_velocity: 0.05,
+        inflow_concentration: 0.0,
+    };
+    simulation.set_sediment(Some(settings));
+    simulation.set_sediment_concentration(|_, y| y);
+    simulation.iterate_one_timestep();
+    simulation.resample([24, 24]);
+    let sediment = simulation.sediment().unwrap();
+    assert_eq!(sediment.settings(), settings);
+    assert_eq!(sediment.concentrations().len(), 24 * 24);
+    assert_eq!(sediment.suspended(), 0.0);
+
+    simulation.set_sediment(None);
+    assert!(simulation.sediment().is_none());
+}
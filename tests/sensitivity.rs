@@ -0,0 +1,51 @@
+mod common;
+
+use common::cylinder;
+use flow2d_rs::presets;
+use flow2d_rs::sensitivity::{self, Control, Sensitivity};
+
+#[test]
+fn controls_read_and_write_the_simulation() {
+    let mut simulation = cylinder();
+    let inflow = Control::InflowVelocity(0);
+    assert_eq!(inflow.value(&simulation), presets::CYLINDER_INFLOW_VELOCITY);
+    assert_eq!(Control::InflowVelocity(1).value(&simulation), 0.0);
+    inflow.set(&mut simulation, 2.0);
+    assert_eq!(inflow.value(&simulation), 2.0);
+
+    Control::Acceleration(1).set(&mut simulation, -0.5);
+    assert_eq!(simulation.acceleration()[1], -0.5);
+    Control::Reynolds.set(&mut simulation, 80.0);
+    assert_eq!(Control::Reynolds.value(&simulation), 80.0);
+}
+
+#[test]
+fn drag_grows_with_the_inflow_velocity() {
+    let simulation = cylinder();
+    let sensitivity = Sensitivity::new(
+        &simulation,
+        &[Control::InflowVelocity(0), Control::Reynolds],
+        0.2,
+        sensitivity::drag,
+    );
+    let values = sensitivity.values();
+    assert_eq!(values, vec![presets::CYLINDER_INFLOW_VELOCITY, 100.0]);
+
+    let gradient = sensitivity.gradient(&values);
+    assert_eq!(gradient.objective, sensitivity.evaluate(&values));
+    assert!(gradient.gradient[0] > 0.0, "{gradient:?}");
+
+    // Central differences of the evaluations, on the thread pool alike
+    let step = 0.01 * presets::CYLINDER_INFLOW_VELOCITY;
+    let stepped = |sign: f32| sensitivity.evaluate(&[values[0] + sign * step, values[1]]);
+    let expected = (stepped(1.0) - stepped(-1.0)) / (2.0 * step);
+    assert!((gradient.gradient[0] - expected).abs() <= 1e-3 * expected.abs());
+    let parallel = Sensitivity::new(
+        &simulation,
+        &[Control::InflowVelocity(0), Control::Reynolds],
+        0.2,
+        sensitivity::drag,
+    )
+    .parallel(true);
+    assert_eq!(parallel.gradient(&values), gradient);
+}
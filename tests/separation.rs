@@ -0,0 +1,89 @@
+use flow2d_rs::presets;
+use flow2d_rs::separation::{self, SeparationMonitor, Wall, WallPointKind};
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::wall_shear::WallFace;
+
+#[test]
+fn sign_changes_along_a_wall_are_interpolated() {
+    // Bottom wall walked along +x: attached, reversed, attached again
+    let faces: Vec<WallFace> = [1.0, 0.5, -0.5, -1.0, 3.0]
+        .iter()
+        .enumerate()
+        .map(|(x, &shear_stress)| WallFace {
+            wall: [x, 0],
+            fluid: [x, 1],
+            position: [x as f32 + 0.5, 1.0],
+            normal: [0.0, 1.0],
+            shear_stress,
+            friction_velocity: f32::abs(shear_stress).sqrt(),
+            y_plus: 0.0,
+        })
+        .collect();
+    let points = separation::wall_points(&faces, false);
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[0].kind, WallPointKind::Separation);
+    assert_eq!(points[0].s, 1.5);
+    assert_eq!(points[0].position, [2.0, 1.0]);
+    assert_eq!(points[1].kind, WallPointKind::Reattachment);
+    assert_eq!(points[1].s, 3.25);
+
+    // Walked the other way round the kinds stay, the flow still converges at the first
+    let reversed: Vec<WallFace> = faces.iter().rev().copied().collect();
+    let points = separation::wall_points(&reversed, false);
+    assert_eq!(points[0].kind, WallPointKind::Reattachment);
+    assert_eq!(points[1].kind, WallPointKind::Separation);
+    assert_eq!(points[1].position, [2.0, 1.0]);
+}
+
+#[test]
+fn flow_behind_a_step_reattaches_further_downstream_over_time() {
+    let mut simulation = Simulation::from_preset(presets::backward_facing_step());
+    // Bottom wall from the foot of the step to the outflow
+    let mut monitor = SeparationMonitor::new(Wall::Faces {
+        x: 76..149,
+        y: 0..1,
+        normal: [0.0, 1.0],
+    });
+    for step in 0..150 {
+        simulation.iterate_one_timestep();
+        if step % 50 == 49 {
+            monitor.record(&simulation);
+        }
+    }
+    assert_eq!(monitor.history.len(), 3);
+    let track = monitor.track(WallPointKind::Reattachment);
+    assert!(track.len() >= 2);
+    assert!(track.windows(2).all(|pair| pair[0].1 < pair[1].1));
+    let reattachment = monitor
+        .latest()
+        .unwrap()
+        .iter()
+        .find(|point| point.kind == WallPointKind::Reattachment)
+        .unwrap();
+    // Downstream of the step at x = 7.6
+    assert!(reattachment.position[0] > 7.6);
+    assert_eq!(reattachment.position[1], 0.02);
+}
+
+#[test]
+fn flow_reattaches_at_the_front_of_a_cylinder() {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow_sized([55, 21]));
+    for _ in 0..20 {
+        simulation.iterate_one_timestep();
+    }
+    let mut monitor = SeparationMonitor::new(Wall::Obstacle("cylinder".to_string()));
+    let points = monitor.record(&simulation).to_vec();
+    let faces = Wall::Obstacle("cylinder".to_string())
+        .faces(&simulation)
+        .unwrap()
+        .0;
+    let center_x = faces.iter().map(|face| face.position[0]).sum::<f32>() / faces.len() as f32;
+    // The front stagnation point
+    assert!(points
+        .iter()
+        .any(|point| point.kind == WallPointKind::Reattachment && point.position[0] < center_x));
+    assert_eq!(
+        Wall::Obstacle("missing".to_string()).faces(&simulation),
+        None
+    );
+}
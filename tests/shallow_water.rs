@@ -0,0 +1,114 @@
+use flow2d_rs::presets;
+use flow2d_rs::shallow_water::{self, ShallowWater};
+use flow2d_rs::solver::FluidSolver;
+
+#[test]
+fn a_lake_at_rest_over_an_island_stays_at_rest() {
+    // The top of the mound sticks out of the water
+    let mound = |x: f32, y: f32| 0.8 * (-((x - 0.5).powi(2) + (y - 0.5).powi(2)) / 0.05).exp();
+    let mut lake =
+        ShallowWater::from_preset(presets::lid_driven_cavity_sized([32, 32]), mound, |_, _| {
+            0.5
+        });
+    assert_eq!(lake.depth(16, 16), 0.0);
+    let volume = lake.volume();
+    for _ in 0..50 {
+        lake.iterate_one_timestep();
+    }
+
+    for x in 1..31 {
+        for y in 1..31 {
+            let [u, v] = lake.velocity(x, y);
+            assert!(u.abs() < 1e-5 && v.abs() < 1e-5, "{x} {y} {u} {v}");
+            if lake.depth(x, y) > 0.0 {
+                let surface = lake.surface_elevation(x, y);
+                assert!((surface - 0.5).abs() < 1e-5, "{x} {y} {surface}");
+            }
+        }
+    }
+    assert!((lake.volume() - volume).abs() < 1e-5 * volume);
+    assert_eq!(
+        lake.get_cell(16, 16).pressure,
+        lake.gravity() * lake.terrain(16, 16)
+    );
+}
+
+#[test]
+fn a_dam_break_on_a_dry_bed_follows_ritter() {
+    // One row of cells along a meter, 10 centimeters of water behind a dam at the middle
+    let initial_depth = 0.1;
+    let mut channel = ShallowWater::from_preset(
+        presets::lid_driven_cavity_sized([402, 3]),
+        |_, _| 0.0,
+        |x, _| if x < 0.5 { initial_depth } else { 0.0 },
+    );
+    let volume = channel.volume();
+    while channel.time() < 0.2 {
+        channel.iterate_one_timestep();
+    }
+    assert!(channel.substeps() > 1);
+    assert!((channel.volume() - volume).abs() < 1e-5 * volume);
+
+    // The depth at the dam stays 4/9 of the initial depth
+    let at_dam = (channel.depth(200, 1) + channel.depth(201, 1)) / 2.0;
+    assert!(
+        (at_dam - 4.0 / 9.0 * initial_depth).abs() < 0.02 * initial_depth,
+        "{at_dam}"
+    );
+    // Parabolic profile between the rarefaction running back at the celerity and the
+    // front running ahead at twice the celerity
+    let celerity = (channel.gravity() * initial_depth).sqrt();
+    let ritter = |x: f32| {
+        let similarity = (x - 0.5) / (celerity * channel.time());
+        initial_depth * ((2.0 - similarity.clamp(-1.0, 2.0)) / 3.0).powi(2)
+    };
+    let dx = channel.delta_space()[0];
+    let error = (1..401)
+        .map(|x| (channel.depth(x, 1) - ritter((x as f32 + 0.5) * dx)).abs())
+        .sum::<f32>()
+        / 400.0;
+    assert!(error < 0.01 * initial_depth, "{error}");
+}
+
+#[test]
+fn presets_of_the_navier_stokes_solver_run_as_shallow_water() {
+    let mut channel = ShallowWater::from_preset(
+        presets::cylinder_cross_flow_sized([44, 16]),
+        |_, _| 0.0,
+        |_, _| 1.0,
+    );
+    for _ in 0..100 {
+        channel.iterate_one_timestep();
+    }
+    assert!(channel.fields().u.iter().all(|u| u.is_finite()));
+    // Water piles up in front of the cylinder, centered at cell (8, 8)
+    let [front, back] = [
+        channel.surface_elevation(5, 8),
+        channel.surface_elevation(11, 8),
+    ];
+    assert!(front > back + 0.1, "{front} {back}");
+
+    // Restoring recovers the depth from the pressure
+    let checkpoint = channel.checkpoint();
+    let mut restored = ShallowWater::from_preset(
+        presets::cylinder_cross_flow_sized([44, 16]),
+        |_, _| 0.0,
+        |_, _| 1.0,
+    );
+    restored.restore(&checkpoint);
+    assert_eq!(restored.time(), channel.time());
+    for x in 1..43 {
+        for y in 1..15 {
+            assert!((restored.depth(x, y) - channel.depth(x, y)).abs() < 1e-5);
+        }
+    }
+
+    let mut dam_break = shallow_water::dam_break_sized([64, 32]);
+    let volume = dam_break.volume();
+    for _ in 0..100 {
+        dam_break.iterate_one_timestep();
+    }
+    assert!((dam_break.volume() - volume).abs() < 1e-4 * volume);
+    // The flood reached the far wall
+    assert!(dam_break.depth(62, 4) > 0.2, "{}", dam_break.depth(62, 4));
+}
@@ -0,0 +1,55 @@
+use flow2d_rs::presets;
+use flow2d_rs::shedding::{analyze_lift, cylinder_shedding};
+use flow2d_rs::simulation::Simulation;
+
+use std::f32::consts::TAU;
+
+// Lift sampled every 5 ms, oscillating with the given amplitude at time t
+fn lift_series(frequency: f32, duration: f32, amplitude: impl Fn(f32) -> f32) -> Vec<(f32, f32)> {
+    (0..(duration / 0.005) as usize)
+        .map(|step| {
+            let time = step as f32 * 0.005;
+            (time, 0.3 + amplitude(time) * (TAU * frequency * time).sin())
+        })
+        .collect()
+}
+
+#[test]
+fn steady_oscillation_is_a_limit_cycle() {
+    let samples = lift_series(0.25, 60.0, |_| 0.5);
+    let analysis = analyze_lift(&samples, 1.0, 1.5).unwrap();
+
+    assert!((analysis.frequency - 0.25).abs() < 1e-3);
+    assert!((analysis.strouhal - 0.25 / 1.5).abs() < 1e-3);
+    assert!((analysis.amplitude - 0.5).abs() < 0.01);
+    assert!((analysis.mean_lift - 0.3).abs() < 0.01);
+    assert!(analysis.periods >= 13);
+    assert!(analysis.is_limit_cycle);
+}
+
+#[test]
+fn growing_oscillation_is_not_yet_a_limit_cycle() {
+    // Noise first, then exponential growth
+    let samples = lift_series(0.25, 40.0, |time| 1e-4 * (time / 4.0).exp());
+    let analysis = analyze_lift(&samples, 1.0, 1.5).unwrap();
+
+    assert!((analysis.frequency - 0.25).abs() < 0.01);
+    assert!(!analysis.is_limit_cycle);
+
+    assert!(analyze_lift(&lift_series(0.25, 6.0, |_| 0.5), 1.0, 1.5).is_none());
+    assert!(analyze_lift(&[], 1.0, 1.5).is_none());
+}
+
+#[test]
+fn a_starting_cylinder_does_not_shed() {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow());
+    simulation.obstacle_mut("cylinder").unwrap().monitor_force = true;
+    for _ in 0..200 {
+        simulation.iterate_one_timestep();
+    }
+    assert_eq!(
+        simulation.obstacle("cylinder").unwrap().force_samples.len(),
+        200
+    );
+    assert!(cylinder_shedding(&simulation).is_none());
+}
@@ -0,0 +1,42 @@
+use flow2d_rs::events::Event;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::simulation_runner::{Command, SimulationRunner};
+
+use std::time::Duration;
+
+#[test]
+fn commands_are_handled_in_order() {
+    let mut reference = Simulation::from_preset(presets::lid_driven_cavity());
+    let runner = SimulationRunner::new(Simulation::from_preset(presets::lid_driven_cavity()));
+    assert!(runner.take_latest().is_some());
+
+    runner.step();
+    runner.send(Command::Apply(Event::SetAcceleration([0.0, -1.0])));
+    runner.step();
+    let snapshot = runner.request_snapshot().recv().unwrap();
+
+    reference.iterate_one_timestep();
+    reference.set_acceleration([0.0, -1.0]);
+    reference.iterate_one_timestep();
+    assert_eq!(snapshot.time, reference.time());
+    assert!(runner.take_latest().is_some());
+    assert!(runner.take_latest().is_none());
+
+    let simulation = runner.stop();
+    assert_eq!(simulation.acceleration(), [0.0, -1.0]);
+}
+
+#[test]
+fn play_steps_until_paused() {
+    let runner = SimulationRunner::new(Simulation::from_preset(presets::lid_driven_cavity()));
+    runner.send(Command::SetStepInterval(Some(Duration::from_millis(1))));
+    runner.play();
+    std::thread::sleep(Duration::from_millis(50));
+    runner.pause();
+    let paused_time = runner.request_snapshot().recv().unwrap().time;
+    assert!(paused_time > 0.0);
+
+    std::thread::sleep(Duration::from_millis(20));
+    assert_eq!(runner.stop().time(), paused_time);
+}
@@ -0,0 +1,185 @@
+use flow2d_rs::cell::{BoundaryConditionCell, Cell, CellType};
+use flow2d_rs::metadata::SimulationMetadata;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::snapshot_writer::{Backpressure, SnapshotFormat, SnapshotWriter};
+use flow2d_rs::solver::Checkpoint;
+
+use std::path::PathBuf;
+
+const ALL_FORMATS: [SnapshotFormat; 3] = [
+    SnapshotFormat::Vtk,
+    SnapshotFormat::Csv,
+    SnapshotFormat::Png,
+];
+
+fn temp_directory(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "flow2d_rs_snapshot_writer_{name}_{}",
+        std::process::id()
+    ))
+}
+
+// 3 x 2 cells moving in +x with pressure 10 x + y, the bottom left cell is a wall
+fn known_checkpoint() -> Checkpoint {
+    let mut cells = Vec::new();
+    for x in 0..3 {
+        for y in 0..2 {
+            cells.push(Cell {
+                velocity: [1.0, 0.0],
+                pressure: (10 * x + y) as f32,
+                dye: 0.5,
+                ..Cell::default()
+            });
+        }
+    }
+    cells[0].cell_type = CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+        boundary_condition_velocity: [0.0, 0.0],
+    });
+    Checkpoint {
+        time: 1.5,
+        space_size: [3, 2],
+        cells,
+        metadata: None,
+    }
+}
+
+fn write_known_checkpoint(name: &str) -> PathBuf {
+    let directory = temp_directory(name);
+    let mut writer = SnapshotWriter::new(&directory, &ALL_FORMATS, 1, Backpressure::Block).unwrap();
+    assert!(writer.submit([0.5, 0.25], known_checkpoint()));
+    assert_eq!(writer.finish().unwrap(), 1);
+    directory
+}
+
+#[test]
+fn blocking_writes_every_snapshot() {
+    let directory = temp_directory("block");
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([16, 16]));
+    let mut writer = SnapshotWriter::new(&directory, &ALL_FORMATS, 1, Backpressure::Block).unwrap();
+    for _ in 0..5 {
+        simulation.iterate_one_timestep();
+        assert!(writer.submit_from(&simulation));
+    }
+    assert_eq!((writer.submitted(), writer.dropped()), (5, 0));
+    assert_eq!(writer.finish().unwrap(), 5);
+
+    for index in 0..5 {
+        for format in ALL_FORMATS {
+            let path = directory.join(format!("snapshot_{index:06}.{}", format.extension()));
+            assert!(path.exists(), "{} is missing", path.display());
+        }
+    }
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn dropping_counts_every_snapshot_it_skips() {
+    let directory = temp_directory("drop");
+    let simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([64, 64]));
+    let mut writer = SnapshotWriter::new(&directory, &ALL_FORMATS, 1, Backpressure::Drop).unwrap();
+    // Queueing is much faster than writing three files of 4096 cells
+    for _ in 0..100 {
+        writer.submit_from(&simulation);
+    }
+    let (submitted, dropped) = (writer.submitted(), writer.dropped());
+    assert_eq!(submitted + dropped, 100);
+    assert!(dropped > 0);
+    assert_eq!(writer.finish().unwrap(), submitted);
+
+    // Indices count the dropped snapshots too, so the written files leave gaps
+    let files = std::fs::read_dir(&directory).unwrap().count();
+    assert_eq!(files, submitted * ALL_FORMATS.len());
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn dropping_the_writer_drains_the_queue() {
+    let directory = temp_directory("drain");
+    let mut writer = SnapshotWriter::new(&directory, &ALL_FORMATS, 4, Backpressure::Block).unwrap();
+    for _ in 0..3 {
+        writer.submit([0.5, 0.25], known_checkpoint());
+    }
+    drop(writer);
+
+    let files = std::fs::read_dir(&directory).unwrap().count();
+    assert_eq!(files, 3 * ALL_FORMATS.len());
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn vtk_lists_cells_with_x_varying_fastest() {
+    let directory = write_known_checkpoint("vtk");
+    let vtk = std::fs::read_to_string(directory.join("snapshot_000000.vtk")).unwrap();
+    let lines: Vec<&str> = vtk.lines().collect();
+
+    assert_eq!(lines[0], "# vtk DataFile Version 3.0");
+    assert_eq!(lines[1], "flow2d_rs snapshot at t = 1.5");
+    assert!(lines.contains(&"DATASET STRUCTURED_POINTS"));
+    assert!(lines.contains(&"DIMENSIONS 4 3 1"));
+    assert!(lines.contains(&"SPACING 0.5 0.25 1"));
+    assert!(lines.contains(&"CELL_DATA 6"));
+
+    let values = |header: &str, skip: usize| {
+        let start = lines.iter().position(|line| *line == header).unwrap() + 1 + skip;
+        lines[start..start + 6].to_vec()
+    };
+    assert_eq!(
+        values("SCALARS cell_type int 1", 1),
+        ["1", "0", "0", "0", "0", "0"]
+    );
+    assert_eq!(
+        values("SCALARS pressure float 1", 1),
+        ["0", "10", "20", "1", "11", "21"]
+    );
+    // The wall's left and the bottom faces are those of the cell itself
+    assert_eq!(values("VECTORS velocity float", 0), ["1 0 0"; 6]);
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn vtk_titles_keep_multiline_scenes_on_one_line() {
+    let directory = temp_directory("vtk_title");
+    let simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([16, 16]));
+    let mut checkpoint = known_checkpoint();
+    checkpoint.metadata = Some(SimulationMetadata::new("two\nlines", &simulation));
+    let mut writer =
+        SnapshotWriter::new(&directory, &[SnapshotFormat::Vtk], 1, Backpressure::Block).unwrap();
+    assert!(writer.submit([0.5, 0.25], checkpoint));
+    assert_eq!(writer.finish().unwrap(), 1);
+
+    let vtk = std::fs::read_to_string(directory.join("snapshot_000000.vtk")).unwrap();
+    let lines: Vec<&str> = vtk.lines().collect();
+    assert!(lines[1].contains("two lines snapshot at t = 1.5"));
+    assert_eq!(lines[2], "ASCII");
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn csv_has_one_row_per_cell_at_its_center() {
+    let directory = write_known_checkpoint("csv");
+    let csv = std::fs::read_to_string(directory.join("snapshot_000000.csv")).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+
+    assert_eq!(lines[0], "x,y,cell_type,u,v,pressure,psi,dye");
+    assert_eq!(lines.len(), 1 + 6);
+    assert_eq!(lines[1], "0.25,0.125,1,1,0,0,0,0.5");
+    // Cell (1, 1)
+    assert_eq!(lines[4], "0.75,0.375,0,1,0,11,0,0.5");
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn png_shows_the_speed_of_fluid_cells() {
+    let directory = write_known_checkpoint("png");
+    let file = std::fs::File::open(directory.join("snapshot_000000.png")).unwrap();
+    let mut reader = png::Decoder::new(file).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+
+    assert_eq!([info.width, info.height], [3, 2]);
+    assert_eq!(info.color_type, png::ColorType::Grayscale);
+    // Rows top to bottom, the wall is black and the uniform flow at full speed
+    assert_eq!(pixels[..info.buffer_size()], [255, 255, 255, 0, 255, 255]);
+    std::fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,141 @@
+use flow2d_rs::cell::{Cell, CellType};
+use flow2d_rs::lattice_boltzmann::LatticeBoltzmann;
+use flow2d_rs::presets::{self, SimulationPreset};
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::{Checkpoint, FluidSolver};
+use flow2d_rs::streamfunction_vorticity::StreamfunctionVorticity;
+
+fn cavity() -> SimulationPreset {
+    presets::lid_driven_cavity_sized([20, 20])
+}
+
+fn backends() -> Vec<(&'static str, Box<dyn FluidSolver>)> {
+    vec![
+        ("projection", Box::new(Simulation::from_preset(cavity()))),
+        (
+            "lattice Boltzmann",
+            Box::new(LatticeBoltzmann::from_preset(cavity())),
+        ),
+        (
+            "streamfunction vorticity",
+            Box::new(StreamfunctionVorticity::from_preset(cavity())),
+        ),
+    ]
+}
+
+fn run(solver: &mut dyn FluidSolver, steps: usize) {
+    for _ in 0..steps {
+        solver.iterate_one_timestep();
+    }
+}
+
+// Every value of every cell, Cell itself has no PartialEq
+fn state(cells: &[Cell]) -> Vec<(CellType, [f32; 8])> {
+    cells
+        .iter()
+        .map(|cell| {
+            let values = [
+                cell.velocity[0],
+                cell.velocity[1],
+                cell.pressure,
+                cell.rhs,
+                cell.f,
+                cell.g,
+                cell.psi,
+                cell.dye,
+            ];
+            (cell.cell_type, values)
+        })
+        .collect()
+}
+
+// Largest difference of the cell centred velocities of the fluid cells
+fn max_velocity_difference(a: &dyn FluidSolver, b: &dyn FluidSolver) -> f32 {
+    let [nx, ny] = a.space_size();
+    (0..nx)
+        .flat_map(|x| (0..ny).map(move |y| (x, y)))
+        .filter(|&(x, y)| matches!(a.get_cell(x, y).cell_type, CellType::FluidCell))
+        .flat_map(|(x, y)| {
+            let [a, b] = [a.get_centered_velocity(x, y), b.get_centered_velocity(x, y)];
+            [a[0] - b[0], a[1] - b[1]]
+        })
+        .fold(0.0, |max, difference| max.max(difference.abs()))
+}
+
+#[test]
+fn restoring_a_checkpoint_continues_the_run_exactly() {
+    let mut simulation = Simulation::from_preset(cavity());
+    run(&mut simulation, 10);
+    let checkpoint = simulation.checkpoint();
+    run(&mut simulation, 10);
+    let expected = state(&simulation.cells());
+
+    // In memory and through a file, into the same solver and a new one
+    let path = std::env::temp_dir().join(format!(
+        "flow2d_rs_solver_checkpoint_{}",
+        std::process::id()
+    ));
+    checkpoint.save(&path).unwrap();
+    let loaded = Checkpoint::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(state(&loaded.cells), state(&checkpoint.cells));
+    assert_eq!(loaded.time, checkpoint.time);
+
+    let mut restored = Simulation::from_preset(cavity());
+    for (solver, checkpoint) in [(&mut simulation, &checkpoint), (&mut restored, &loaded)] {
+        solver.restore(checkpoint);
+        assert_eq!(solver.time(), checkpoint.time);
+        assert_eq!(state(&solver.cells()), state(&checkpoint.cells));
+        run(solver, 10);
+        assert_eq!(state(&solver.cells()), expected);
+    }
+}
+
+#[test]
+fn every_backend_round_trips_its_checkpoint() {
+    for ((name, mut solver), (_, mut fresh)) in backends().into_iter().zip(backends()) {
+        run(solver.as_mut(), 10);
+        let checkpoint = solver.checkpoint();
+        run(solver.as_mut(), 10);
+
+        // The checkpoint alone decides the state, not what the solver held before
+        solver.restore(&checkpoint);
+        fresh.restore(&checkpoint);
+        assert_eq!(solver.time(), checkpoint.time, "{name}");
+        assert_eq!(state(&solver.cells()), state(&fresh.cells()), "{name}");
+        run(solver.as_mut(), 10);
+        run(fresh.as_mut(), 10);
+        assert_eq!(state(&solver.cells()), state(&fresh.cells()), "{name}");
+    }
+}
+
+#[test]
+fn backends_agree_through_the_trait() {
+    let mut solvers = backends();
+    for (_, solver) in solvers.iter_mut() {
+        run(solver.as_mut(), 1);
+    }
+    let (_, reference) = &solvers[0];
+    for (name, solver) in &solvers[1..] {
+        assert_eq!(solver.space_size(), reference.space_size(), "{name}");
+        assert_eq!(solver.delta_space(), reference.delta_space(), "{name}");
+        assert_eq!(solver.time(), reference.time(), "{name}");
+    }
+
+    // A state handed from one backend to another through checkpoints. The lattice
+    // Boltzmann backend keeps cell centred velocities, which smooths the staggered ones.
+    run(solvers[0].1.as_mut(), 20);
+    let checkpoint = solvers[0].1.checkpoint();
+    let (reference, others) = solvers.split_first_mut().unwrap();
+    for ((name, solver), tolerance) in others.iter_mut().zip([1e-2, 1e-4]) {
+        solver.restore(&checkpoint);
+        let difference = max_velocity_difference(reference.1.as_ref(), solver.as_ref());
+        assert!(difference < tolerance, "{name}: {difference}");
+    }
+
+    // And both staggered backends continue it alike
+    run(solvers[0].1.as_mut(), 20);
+    run(solvers[2].1.as_mut(), 20);
+    let difference = max_velocity_difference(solvers[0].1.as_ref(), solvers[2].1.as_ref());
+    assert!(difference < 0.05, "{difference}");
+}
@@ -0,0 +1,32 @@
+use flow2d_rs::presets;
+use flow2d_rs::simulation::{Simulation, SorOrdering};
+use flow2d_rs::solver::FluidSolver;
+
+fn run(preset: presets::SimulationPreset, sor_ordering: SorOrdering) -> Simulation {
+    let mut simulation = Simulation::from_preset(preset);
+    simulation.set_sor_ordering(sor_ordering);
+    assert_eq!(simulation.sor_ordering(), sor_ordering);
+    for _ in 0..30 {
+        simulation.iterate_one_timestep();
+    }
+    simulation
+}
+
+#[test]
+fn red_black_converges_to_the_lexicographic_result() {
+    let presets: [fn() -> presets::SimulationPreset; 2] = [
+        || presets::lid_driven_cavity_sized([48, 40]),
+        || presets::cylinder_cross_flow_sized([66, 25]),
+    ];
+    for preset in presets {
+        let lexicographic = run(preset(), SorOrdering::Lexicographic);
+        let red_black = run(preset(), SorOrdering::RedBlack);
+
+        let [a, b] = [lexicographic.fields(), red_black.fields()];
+        for (velocities, expected) in [(&b.u, &a.u), (&b.v, &a.v)] {
+            for (value, expected) in velocities.iter().zip(expected.iter()) {
+                assert!((value - expected).abs() < 1e-3, "{value} != {expected}");
+            }
+        }
+    }
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use flow2d_rs::colormap::Colormap;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+use flow2d_rs::units::DeltaTimeLimits;
+
+fn cylinder() -> Simulation {
+    let mut simulation = common::cylinder();
+    for _ in 0..10 {
+        simulation.iterate_one_timestep();
+    }
+    simulation
+}
+
+#[test]
+fn courant_numbers_follow_the_local_velocity() {
+    let simulation = cylinder();
+    let stability = simulation.stability();
+    let [dx, dy] = simulation.delta_space();
+    let dt = simulation.delta_time();
+
+    let ([x, y], courant) = stability.max_courant().unwrap();
+    let ny = 21;
+    let fields = simulation.fields();
+    let index = x * ny + y;
+    let expected = [fields.u[index], fields.u[index - ny]]
+        .map(|u| u.abs() * dt / dx)
+        .into_iter()
+        .chain([fields.v[index], fields.v[index - 1]].map(|v| v.abs() * dt / dy))
+        .fold(0.0f32, f32::max);
+    assert_eq!(courant, expected);
+    assert_eq!(stability.courant().max(), Some(courant));
+    // The flow speeds up around the cylinder
+    assert!(courant > 1.5 * dt / dx);
+
+    // Uniform diffusion number, zero off the fluid
+    let diffusion = dt / simulation.reynolds() * (1.0 / dx.powi(2) + 1.0 / dy.powi(2));
+    assert_eq!(stability.diffusion().range(), Some([diffusion; 2]));
+    assert_eq!(stability.diffusion[0], 0.0);
+    assert!(stability.is_stable());
+}
+
+#[test]
+fn marginal_timesteps_are_located() {
+    let mut simulation = cylinder();
+    let stable = simulation.stability();
+    let limiting = stable.limiting_delta_time();
+    let limits = DeltaTimeLimits::new(
+        simulation.reynolds(),
+        simulation.delta_space(),
+        [f32::INFINITY; 2],
+    );
+    assert!(limiting > simulation.delta_time());
+    assert!(limiting <= limits.diffusive * 1.0001);
+
+    simulation.set_delta_time(1.01 * limiting);
+    let unstable = simulation.stability();
+    assert!(!unstable.is_stable());
+    let (cell, stability) = unstable.most_marginal().unwrap();
+    assert!(stability >= 1.0);
+    assert_eq!(cell, stable.most_marginal().unwrap().0);
+
+    let image = unstable.heatmap(&Colormap::viridis(), 2);
+    assert_eq!(image.size, [110, 42]);
+    assert_eq!(image.pixels.len(), 110 * 42 * 4);
+}
@@ -0,0 +1,222 @@
+use flow2d_rs::cell::{BoundaryConditionCell, Cell, CellType};
+use flow2d_rs::space_domain::SpaceDomain;
+
+type Stencil = fn(&SpaceDomain, usize, usize) -> f32;
+type Exact = fn(f32, f32) -> f32;
+
+// Where u and v of a cell sit, in cells from the bottom left corner
+const U_FACE: [f32; 2] = [1.0, 0.5];
+const V_FACE: [f32; 2] = [0.5, 1.0];
+
+// Unit square of n x n cells, fluid inside a ring of resting no-slip walls, with the
+// velocity sampled from analytic fields at the staggered faces
+fn domain(
+    n: usize,
+    gamma: f32,
+    u: impl Fn(f32, f32) -> f32,
+    v: impl Fn(f32, f32) -> f32,
+) -> SpaceDomain {
+    let h = 1.0 / n as f32;
+    let cells = (0..n)
+        .map(|x| {
+            (0..n)
+                .map(|y| {
+                    let is_wall = x == 0 || y == 0 || x == n - 1 || y == n - 1;
+                    let at = |face: [f32; 2]| [(x as f32 + face[0]) * h, (y as f32 + face[1]) * h];
+                    let [ux, uy] = at(U_FACE);
+                    let [vx, vy] = at(V_FACE);
+                    Cell {
+                        cell_type: if is_wall {
+                            CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                                boundary_condition_velocity: [0.0, 0.0],
+                            })
+                        } else {
+                            CellType::FluidCell
+                        },
+                        velocity: [u(ux, uy), v(vx, vy)],
+                        ..Default::default()
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    SpaceDomain::new(cells, [h, h], gamma)
+}
+
+// Largest difference between a stencil and the exact derivative at the face it
+// approximates, over the cells at least two cells from the walls
+fn max_error(
+    space_domain: &SpaceDomain,
+    stencil: Stencil,
+    face: [f32; 2],
+    exact: impl Fn(f32, f32) -> f32,
+) -> f32 {
+    let [n, _] = space_domain.space_size();
+    let [h, _] = space_domain.delta_space();
+    let mut error: f32 = 0.0;
+    for x in 2..n - 2 {
+        for y in 2..n - 2 {
+            let position = [(x as f32 + face[0]) * h, (y as f32 + face[1]) * h];
+            let value = stencil(space_domain, x, y);
+            error = error.max((value - exact(position[0], position[1])).abs());
+        }
+    }
+    error
+}
+
+fn wave_u(x: f32, y: f32) -> f32 {
+    (2.0 * x).sin() * (3.0 * y).cos()
+}
+
+fn wave_v(x: f32, y: f32) -> f32 {
+    (2.0 * x).cos() * (3.0 * y).sin()
+}
+
+// Kept positive for the convective terms, upwinding switches sides where the
+// velocity changes sign
+fn u(x: f32, y: f32) -> f32 {
+    1.5 + wave_u(x, y)
+}
+
+fn v(x: f32, y: f32) -> f32 {
+    1.5 + wave_v(x, y)
+}
+
+#[test]
+fn second_derivatives_converge_at_second_order() {
+    // Stencil, face, exact value and the largest fourth derivative along its axis,
+    // which bounds the truncation error h^2 / 12 * f''''
+    let cases: [(Stencil, [f32; 2], Exact, f32); 4] = [
+        (
+            SpaceDomain::d2udx2,
+            U_FACE,
+            |x, y| -4.0 * wave_u(x, y),
+            16.0,
+        ),
+        (
+            SpaceDomain::d2udy2,
+            U_FACE,
+            |x, y| -9.0 * wave_u(x, y),
+            81.0,
+        ),
+        (
+            SpaceDomain::d2vdx2,
+            V_FACE,
+            |x, y| -4.0 * wave_v(x, y),
+            16.0,
+        ),
+        (
+            SpaceDomain::d2vdy2,
+            V_FACE,
+            |x, y| -9.0 * wave_v(x, y),
+            81.0,
+        ),
+    ];
+    for (index, (stencil, face, exact, fourth)) in cases.into_iter().enumerate() {
+        let errors =
+            [16, 32].map(|n| max_error(&domain(n, 0.0, wave_u, wave_v), stencil, face, exact));
+        for (n, error) in [16.0f32, 32.0].into_iter().zip(errors) {
+            // Plus the rounding of f32 values of up to one over h^2
+            let bound = fourth / 12.0 / (n * n) + 4.0 * f32::EPSILON * n * n;
+            assert!(
+                error <= 1.05 * bound,
+                "case {index}: {error} above {bound} at {n}"
+            );
+        }
+        assert!(errors[0] / errors[1] > 3.5, "case {index}: {errors:?}");
+    }
+}
+
+#[test]
+fn convective_terms_are_central_without_upwinding() {
+    let cases: [(Stencil, [f32; 2], Exact); 4] = [
+        // d(u^2)/dx = 2 u du/dx
+        (SpaceDomain::du2dx, U_FACE, |x, y| {
+            2.0 * u(x, y) * 2.0 * (2.0 * x).cos() * (3.0 * y).cos()
+        }),
+        // d(uv)/dy = du/dy v + u dv/dy
+        (SpaceDomain::duvdy, U_FACE, |x, y| {
+            -3.0 * (2.0 * x).sin() * (3.0 * y).sin() * v(x, y)
+                + u(x, y) * 3.0 * (2.0 * x).cos() * (3.0 * y).cos()
+        }),
+        // d(uv)/dx = du/dx v + u dv/dx
+        (SpaceDomain::duvdx, V_FACE, |x, y| {
+            2.0 * (2.0 * x).cos() * (3.0 * y).cos() * v(x, y)
+                - u(x, y) * 2.0 * (2.0 * x).sin() * (3.0 * y).sin()
+        }),
+        // d(v^2)/dy = 2 v dv/dy
+        (SpaceDomain::dv2dy, V_FACE, |x, y| {
+            2.0 * v(x, y) * 3.0 * (2.0 * x).cos() * (3.0 * y).cos()
+        }),
+    ];
+    for (index, (stencil, face, exact)) in cases.into_iter().enumerate() {
+        // Second order central differences
+        let central = [16, 32].map(|n| max_error(&domain(n, 0.0, u, v), stencil, face, exact));
+        assert!(central[0] / central[1] > 3.5, "case {index}: {central:?}");
+        assert!(central[1] < 0.05, "case {index}: {central:?}");
+
+        // Full donor cell upwinding is first order
+        let donor = [16, 32].map(|n| max_error(&domain(n, 1.0, u, v), stencil, face, exact));
+        let ratio = donor[0] / donor[1];
+        assert!((1.7..2.5).contains(&ratio), "case {index}: {donor:?}");
+        assert!(donor[1] > central[1], "case {index}");
+    }
+}
+
+#[test]
+fn low_order_polynomials_are_exact() {
+    // Linear velocities make every convective product quadratic, which the central
+    // stencils reproduce, and quadratic ones have constant second derivatives
+    let linear = domain(12, 0.0, |x, y| 1.0 + 2.0 * x - y, |x, y| 0.5 - x + 3.0 * y);
+    let exact_linear: [(Stencil, [f32; 2], Exact); 4] = [
+        (SpaceDomain::du2dx, U_FACE, |x, y| {
+            2.0 * (1.0 + 2.0 * x - y) * 2.0
+        }),
+        (SpaceDomain::duvdy, U_FACE, |x, y| {
+            -(0.5 - x + 3.0 * y) + (1.0 + 2.0 * x - y) * 3.0
+        }),
+        (SpaceDomain::duvdx, V_FACE, |x, y| {
+            2.0 * (0.5 - x + 3.0 * y) - (1.0 + 2.0 * x - y)
+        }),
+        (SpaceDomain::dv2dy, V_FACE, |x, y| {
+            2.0 * (0.5 - x + 3.0 * y) * 3.0
+        }),
+    ];
+    for (index, (stencil, face, exact)) in exact_linear.into_iter().enumerate() {
+        let error = max_error(&linear, stencil, face, exact);
+        assert!(error < 1e-4, "case {index}: {error}");
+    }
+
+    let quadratic = domain(
+        12,
+        0.0,
+        |x, y| x * x - 3.0 * x * y,
+        |x, y| 2.0 * y * y + x * y,
+    );
+    let exact_quadratic: [(Stencil, [f32; 2], f32); 4] = [
+        (SpaceDomain::d2udx2, U_FACE, 2.0),
+        (SpaceDomain::d2udy2, U_FACE, 0.0),
+        (SpaceDomain::d2vdx2, V_FACE, 0.0),
+        (SpaceDomain::d2vdy2, V_FACE, 4.0),
+    ];
+    for (index, (stencil, face, exact)) in exact_quadratic.into_iter().enumerate() {
+        let error = max_error(&quadratic, stencil, face, |_, _| exact);
+        assert!(error < 2e-3, "case {index}: {error}");
+    }
+}
+
+#[test]
+fn wall_ghost_values_continue_a_linear_profile() {
+    // Shear flow vanishing on the bottom wall face at y = h, with garbage in the wall
+    // cells that the ghost values must not read
+    let n = 10;
+    let h = 1.0 / n as f32;
+    let mut space_domain = domain(n, 0.0, |_, y| 2.0 * (y - h), |_, _| 0.0);
+    for x in 0..n {
+        space_domain.set_velocity(x, 0, [100.0, 0.0]);
+    }
+    for x in 1..n - 2 {
+        assert!(space_domain.d2udy2(x, 1).abs() < 1e-3, "{x}");
+        assert!(space_domain.duvdy(x, 1).abs() < 1e-6, "{x}");
+    }
+}
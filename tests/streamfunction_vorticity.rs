@@ -0,0 +1,102 @@
+use flow2d_rs::presets::{self, SimulationPreset};
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+use flow2d_rs::streamfunction_vorticity::{StreamfunctionError, StreamfunctionVorticity};
+
+const SIZE: [usize; 2] = [34, 34];
+
+fn cavity() -> SimulationPreset {
+    let mut preset = presets::lid_driven_cavity_sized(SIZE);
+    preset.reynolds = 100.0;
+    preset.delta_time = 0.002;
+    preset
+}
+
+#[test]
+fn lid_driven_cavity_agrees_with_the_projection_solver() {
+    let mut projection = Simulation::from_preset(cavity());
+    let mut streamfunction = StreamfunctionVorticity::from_preset(cavity());
+    // Three time units, close to steady at Re = 100
+    for _ in 0..1500 {
+        projection.iterate_one_timestep();
+        streamfunction.iterate_one_timestep();
+    }
+
+    // u along the vertical and v along the horizontal centerline, lid velocity is 1
+    let [nx, ny] = SIZE;
+    for y in 1..ny - 1 {
+        let expected = projection.get_centered_velocity(nx / 2, y)[0];
+        let u = streamfunction.get_centered_velocity(nx / 2, y)[0];
+        assert!((u - expected).abs() < 0.05, "u at {y}: {u} != {expected}");
+    }
+    for x in 1..nx - 1 {
+        let expected = projection.get_centered_velocity(x, ny / 2)[1];
+        let v = streamfunction.get_centered_velocity(x, ny / 2)[1];
+        assert!((v - expected).abs() < 0.05, "v at {x}: {v} != {expected}");
+    }
+}
+
+#[test]
+fn wall_vorticity_follows_thoms_formula() {
+    let mut solver = StreamfunctionVorticity::from_preset(cavity());
+    for _ in 0..20 {
+        solver.iterate_one_timestep();
+    }
+    // The wall vorticity of a step comes from psi at the end of the previous one
+    let [nx, ny] = SIZE;
+    let h = 1.0 / ny as f32;
+    let psi: Vec<[f32; 4]> = (1..nx - 2)
+        .map(|x| {
+            [
+                solver.corner_psi(x, 0),
+                solver.corner_psi(x, 1),
+                solver.corner_psi(x, ny - 2),
+                solver.corner_psi(x, ny - 3),
+            ]
+        })
+        .collect();
+    solver.iterate_one_timestep();
+
+    let lid_velocity = 1.0;
+    for (x, [bottom, above_bottom, lid, below_lid]) in (1..nx - 2).zip(psi) {
+        // omega = -2 (psi_1 - psi_0 - dpsi/dn h) / h^2, inward normal
+        let expected = -2.0 * (above_bottom - bottom) / h.powi(2);
+        let vorticity = solver.corner_vorticity(x, 0);
+        assert!(
+            (vorticity - expected).abs() <= 1e-3 * expected.abs().max(1.0),
+            "bottom at {x}: {vorticity} != {expected}"
+        );
+
+        let expected = -2.0 * (below_lid - lid + lid_velocity * h) / h.powi(2);
+        let vorticity = solver.corner_vorticity(x, ny - 2);
+        assert!(
+            (vorticity - expected).abs() <= 1e-3 * expected.abs().max(1.0),
+            "lid at {x}: {vorticity} != {expected}"
+        );
+    }
+}
+
+#[test]
+fn a_lid_starting_from_rest_gives_minus_two_u_over_h() {
+    let mut solver = StreamfunctionVorticity::from_preset(cavity());
+    solver.iterate_one_timestep();
+
+    let [nx, ny] = SIZE;
+    let h = 1.0 / ny as f32;
+    for x in 1..nx - 2 {
+        let vorticity = solver.corner_vorticity(x, ny - 2);
+        assert!(
+            (vorticity + 2.0 / h).abs() < 1e-3 * 2.0 / h,
+            "lid at {x}: {vorticity}"
+        );
+        assert_eq!(solver.corner_vorticity(x, 0), 0.0);
+    }
+}
+
+#[test]
+fn periodic_presets_are_rejected() {
+    let error =
+        StreamfunctionVorticity::try_from_preset(presets::kelvin_helmholtz_sized([16, 16])).err();
+    assert_eq!(error, Some(StreamfunctionError::PeriodicCell((0, 0))));
+    assert!(StreamfunctionVorticity::try_from_preset(cavity()).is_ok());
+}
@@ -0,0 +1,107 @@
+use flow2d_rs::cell::{BoundaryConditionCell, Cell, CellType};
+use flow2d_rs::events::{Event, EventSchedule};
+use flow2d_rs::presets::SimulationPreset;
+use flow2d_rs::simulation::{Simulation, SurfaceWind};
+use flow2d_rs::space_domain::SpaceDomain;
+
+use std::f32::consts::PI;
+
+// 8 x 1 meter basin with no slip walls and a free surface on top
+fn basin(events: EventSchedule) -> SimulationPreset {
+    let [nx, ny] = [82, 12];
+    let cells = (0..nx)
+        .map(|x| {
+            (0..ny)
+                .map(|y| {
+                    let cell_type = if y == ny - 1 {
+                        CellType::BoundaryConditionCell(BoundaryConditionCell::FreeSlipCell)
+                    } else if x == 0 || y == 0 || x == nx - 1 {
+                        CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                            boundary_condition_velocity: [0.0, 0.0],
+                        })
+                    } else {
+                        CellType::FluidCell
+                    };
+                    Cell {
+                        cell_type,
+                        ..Default::default()
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    SimulationPreset {
+        space_domain: SpaceDomain::new(cells, [0.1, 0.1], 0.9),
+        delta_time: 0.01,
+        reynolds: 10.0,
+        acceleration: [0.0, 0.0],
+        events,
+        obstacles: Vec::new(),
+        dye_buoyancy: None,
+    }
+}
+
+const WIND: SurfaceWind = SurfaceWind {
+    speed: 10.0,
+    direction: 0.0,
+    drag: 1e-3,
+};
+
+#[test]
+fn wind_drives_the_laminar_return_flow_of_a_closed_basin() {
+    let mut simulation = Simulation::from_preset(basin(EventSchedule::new()));
+    simulation.set_surface_wind(Some(WIND));
+    assert_eq!(simulation.surface_wind(), Some(WIND));
+    for _ in 0..1000 {
+        simulation.iterate_one_timestep();
+    }
+
+    // Away from the ends u = stress Re / (4 h) (3 y^2 - 2 h y) with the bottom at y = 0,
+    // forward at the surface and back below two thirds of the depth
+    let stress = WIND.stress();
+    let expected = |y: f32| stress * 10.0 / 4.0 * (3.0 * y.powi(2) - 2.0 * y);
+    for y in 1..11 {
+        let height = (y as f32 - 0.5) * 0.1;
+        let u = simulation.get_centered_velocity(41, y)[0];
+        assert!(
+            (u - expected(height)).abs() < 0.005,
+            "{y} {u} {}",
+            expected(height)
+        );
+    }
+    let flux: f32 = (1..11)
+        .map(|y| simulation.get_cell(40, y).velocity[0])
+        .sum();
+    assert!(flux.abs() < 1e-3, "{flux}");
+}
+
+#[test]
+fn wind_follows_its_schedule() {
+    let turned = SurfaceWind {
+        direction: PI,
+        ..WIND
+    };
+    let events = EventSchedule::new()
+        .with_event(0.5, Event::SetSurfaceWind(Some(WIND)))
+        .with_event(1.0, Event::SetSurfaceWind(Some(turned)))
+        .with_event(1.5, Event::SetSurfaceWind(None));
+    let mut simulation = Simulation::from_preset(basin(events));
+    let surface_velocity = |simulation: &Simulation| simulation.get_centered_velocity(41, 10)[0];
+
+    for _ in 0..50 {
+        simulation.iterate_one_timestep();
+    }
+    assert_eq!(simulation.surface_wind(), None);
+    assert_eq!(surface_velocity(&simulation), 0.0);
+    for _ in 0..50 {
+        simulation.iterate_one_timestep();
+    }
+    assert_eq!(simulation.surface_wind(), Some(WIND));
+    assert!(surface_velocity(&simulation) > 0.05);
+    for _ in 0..50 {
+        simulation.iterate_one_timestep();
+    }
+    assert!(surface_velocity(&simulation) < -0.05);
+    simulation.iterate_one_timestep();
+    assert_eq!(simulation.surface_wind(), None);
+}
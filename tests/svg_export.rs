@@ -0,0 +1,83 @@
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::svg_export::{contour, streamlines, write_svg, SvgOptions};
+
+use xml::reader::{EventReader, XmlEvent};
+
+// (name, attributes) of every element, fails on malformed XML
+fn elements(svg: &[u8]) -> Vec<(String, Vec<(String, String)>)> {
+    EventReader::new(svg)
+        .into_iter()
+        .filter_map(|event| match event.expect("malformed SVG") {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => Some((
+                name.local_name,
+                attributes
+                    .into_iter()
+                    .map(|attribute| (attribute.name.local_name, attribute.value))
+                    .collect(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+fn attribute<'a>(attributes: &'a [(String, String)], name: &str) -> &'a str {
+    &attributes.iter().find(|(key, _)| key == name).unwrap().1
+}
+
+#[test]
+fn svg_is_well_formed_with_outline_walls_and_streamlines() {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([20, 20]));
+    for _ in 0..50 {
+        simulation.iterate_one_timestep();
+    }
+    let options = SvgOptions {
+        pixels_per_meter: 200.0,
+        streamline_count: 5,
+        stroke_width: 1.5,
+    };
+    let mut svg = Vec::new();
+    write_svg(&mut svg, &simulation, &options).unwrap();
+    let elements = elements(&svg);
+
+    let (name, attributes) = &elements[0];
+    assert_eq!(name, "svg");
+    assert_eq!(attribute(attributes, "width"), "200");
+    assert_eq!(attribute(attributes, "height"), "200");
+
+    let rects: Vec<_> = elements.iter().filter(|(name, _)| name == "rect").collect();
+    // The outline, then runs of cells with the same fill per column: void, wall, void
+    // on the left and right and the bottom wall and the lid in between
+    assert_eq!(attribute(&rects[0].1, "fill"), "white");
+    let fills: Vec<&str> = rects[1..]
+        .iter()
+        .map(|(_, attributes)| attribute(attributes, "fill"))
+        .collect();
+    assert_eq!(fills.len(), 3 + 2 * 18 + 3);
+    assert!(fills
+        .iter()
+        .all(|fill| ["#808080", "#000000"].contains(fill)));
+    assert_eq!(fills.iter().filter(|fill| **fill == "#000000").count(), 4);
+
+    let paths: Vec<_> = elements.iter().filter(|(name, _)| name == "path").collect();
+    assert_eq!(paths.len(), 5);
+    assert!(paths
+        .iter()
+        .all(|(_, attributes)| attribute(attributes, "d").starts_with('M')));
+    assert_eq!(streamlines(&simulation, 5).len(), 5);
+}
+
+#[test]
+fn contours_cross_cells_where_the_level_lies_between_corners() {
+    // value = x on a 3 x 2 grid of points, the level 0.5 crosses the first column
+    let values = [0.0, 0.0, 1.0, 1.0, 2.0, 2.0];
+    let segments = contour(&values, [3, 2], [0.0, 0.0], [1.0, 1.0], 0.5);
+    assert_eq!(segments.len(), 1);
+    let mut points = segments[0];
+    points.sort_by(|a, b| a[1].total_cmp(&b[1]));
+    assert_eq!(points, [[0.5, 0.0], [0.5, 1.0]]);
+
+    assert!(contour(&values, [3, 2], [0.0, 0.0], [1.0, 1.0], 5.0).is_empty());
+}
@@ -0,0 +1,111 @@
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::telemetry::TelemetryServer;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// (opcode, payload) of one unmasked frame
+fn read_frame(stream: &mut impl Read) -> (u8, Vec<u8>) {
+    let mut header = [0; 2];
+    stream.read_exact(&mut header).unwrap();
+    let length = match header[1] {
+        126 => {
+            let mut length = [0; 2];
+            stream.read_exact(&mut length).unwrap();
+            u16::from_be_bytes(length) as usize
+        }
+        127 => {
+            let mut length = [0; 8];
+            stream.read_exact(&mut length).unwrap();
+            u64::from_be_bytes(length) as usize
+        }
+        length => length as usize,
+    };
+    let mut payload = vec![0; length];
+    stream.read_exact(&mut payload).unwrap();
+    (header[0] & 0x0f, payload)
+}
+
+// Upgraded connection to the server, after checking its answer
+fn connect(address: SocketAddr) -> BufReader<TcpStream> {
+    let stream = TcpStream::connect(address).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    let mut reader = BufReader::new(stream);
+    // The example key of RFC 6455
+    write!(
+        reader.get_mut(),
+        "GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n"
+    )
+    .unwrap();
+    let mut response = String::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line == "\r\n" {
+            break;
+        }
+        response.push_str(&line);
+    }
+    assert!(response.starts_with("HTTP/1.1 101"));
+    assert!(response.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+    reader
+}
+
+#[test]
+fn websocket_clients_receive_downsampled_frames() {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity());
+    let [nx, ny] = simulation.space_size();
+    let mut server = TelemetryServer::bind("127.0.0.1:0", 2, 4).unwrap();
+
+    let mut reader = connect(server.address());
+
+    while server.clients() == 0 {
+        thread::sleep(Duration::from_millis(10));
+    }
+    for _ in 0..3 {
+        simulation.iterate_one_timestep();
+        server.record(&simulation);
+    }
+
+    // Steps 0 and 2 are published, the last one is the current state
+    let shape = [nx.div_ceil(4), ny.div_ceil(4)];
+    let mut statistics = String::new();
+    for _ in 0..2 {
+        let (opcode, text) = read_frame(&mut reader);
+        assert_eq!(opcode, 1);
+        statistics = String::from_utf8(text).unwrap();
+        assert!(statistics.contains(&format!("\"shape\":[{},{}]", shape[0], shape[1])));
+        assert!(statistics.contains("\"psi\":["));
+
+        let (opcode, fields) = read_frame(&mut reader);
+        assert_eq!(opcode, 2);
+        assert_eq!(fields.len(), 4 * shape[0] * shape[1] * 4);
+    }
+    assert!(statistics.starts_with(&format!("{{\"time\":{},", simulation.time())));
+}
+
+#[test]
+fn a_silent_client_stalls_neither_others_nor_shutdown() {
+    let server = TelemetryServer::bind("127.0.0.1:0", 1, 1).unwrap();
+    let _silent = TcpStream::connect(server.address()).unwrap();
+    thread::sleep(Duration::from_millis(100));
+
+    // Well below the handshake timeout of the silent client
+    let start = Instant::now();
+    let _reader = connect(server.address());
+    while server.clients() == 0 {
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(server.clients(), 1);
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    let start = Instant::now();
+    drop(server);
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
@@ -0,0 +1,98 @@
+use flow2d_rs::colormap::Colormap;
+use flow2d_rs::frame_renderer::{FrameRenderer, RenderField};
+use flow2d_rs::presets;
+use flow2d_rs::shallow_water::ShallowWater;
+use flow2d_rs::solver::FluidSolver;
+use flow2d_rs::terrain::Heightmap;
+
+use std::io::ErrorKind;
+
+#[test]
+fn heightmaps_interpolate_between_their_samples() {
+    // Samples of x + 2 y at the centers of 0.5 meter pixels
+    let elevations = (0..4)
+        .flat_map(|x| (0..3).map(move |y| (x as f32 + 0.5) * 0.5 + (y as f32 + 0.5)))
+        .collect();
+    let heightmap = Heightmap::new([4, 3], [2.0, 1.5], elevations);
+    assert_eq!(heightmap.delta_space(), [0.5, 0.5]);
+    assert_eq!(heightmap.get(1, 2), 0.75 + 2.0 * 1.25);
+
+    let elevation = heightmap.elevation([1.1, 0.6]);
+    assert!((elevation - 2.3).abs() < 1e-6, "{elevation}");
+    // Clamped to the outermost samples
+    assert_eq!(heightmap.elevation([-1.0, 0.0]), heightmap.get(0, 0));
+    assert_eq!(heightmap.elevation([5.0, 5.0]), heightmap.get(3, 2));
+
+    let cells = heightmap.cell_elevations([8, 6], [0.25, 0.25]);
+    assert_eq!(cells.len(), 48);
+    assert!((cells[3 * 6 + 2] - heightmap.elevation([0.875, 0.625])).abs() < 1e-6);
+}
+
+#[test]
+fn heightmaps_load_from_pgm_images() {
+    let directory = std::env::temp_dir().join(format!("flow2d_rs_terrain_{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("heightmap.pgm");
+
+    // 3 x 2 pixels, the bright column on the right of the top row
+    let mut binary = b"P5\n# heightmap\n3 2\n255\n".to_vec();
+    binary.extend_from_slice(&[0, 0, 255, 0, 51, 102]);
+    std::fs::write(&path, &binary).unwrap();
+    let heightmap = Heightmap::load_pgm(&path, [3.0, 2.0], [-1.0, 4.0]).unwrap();
+    assert_eq!(heightmap.size(), [3, 2]);
+    assert_eq!(heightmap.get(2, 1), 4.0);
+    assert_eq!(heightmap.get(0, 1), -1.0);
+    assert_eq!(heightmap.get(1, 0), 0.0);
+    assert_eq!(heightmap.get(2, 0), 1.0);
+
+    // The same image as text and with 16 bits per pixel
+    std::fs::write(&path, "P2 3 2 255\n0 0 255\n0 51 102\n").unwrap();
+    assert_eq!(
+        Heightmap::load_pgm(&path, [3.0, 2.0], [-1.0, 4.0]).unwrap(),
+        heightmap
+    );
+    let mut wide = b"P5 3 2 65535\n".to_vec();
+    for pixel in [0u16, 0, 65535, 0, 13107, 26214] {
+        wide.extend_from_slice(&pixel.to_be_bytes());
+    }
+    std::fs::write(&path, &wide).unwrap();
+    assert_eq!(
+        Heightmap::load_pgm(&path, [3.0, 2.0], [-1.0, 4.0]).unwrap(),
+        heightmap
+    );
+
+    std::fs::write(&path, "P6 3 2 255\n").unwrap();
+    let error = Heightmap::load_pgm(&path, [3.0, 2.0], [0.0, 1.0]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    std::fs::write(&path, &binary[..binary.len() - 1]).unwrap();
+    let error = Heightmap::load_pgm(&path, [3.0, 2.0], [0.0, 1.0]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn shallow_water_runs_over_and_renders_a_heightmap() {
+    // A ramp rising to the right under a meter of water
+    let heightmap =
+        Heightmap::from_grayscale([2, 2], [1.0, 1.0], &[0, 255, 0, 255], 255, [0.0, 0.5]);
+    let mut lake = ShallowWater::from_preset(
+        presets::lid_driven_cavity_sized([16, 16]),
+        |x, y| heightmap.elevation([x, y]),
+        |_, _| 1.0,
+    );
+    let terrain = lake.terrain_elevations().unwrap().to_vec();
+    assert_eq!(
+        terrain,
+        heightmap.cell_elevations([16, 16], lake.delta_space())
+    );
+    assert!((lake.depth(12, 5) - (1.0 - lake.terrain(12, 5))).abs() < 1e-6);
+    lake.iterate_one_timestep();
+    assert!(lake.speed_range()[1] < 1e-5);
+
+    let image = FrameRenderer::new(RenderField::Terrain, Colormap::grayscale(), 1).render(&lake);
+    let pixel = |x: usize, y: usize| image.pixels[((15 - y) * 16 + x) * 4];
+    assert_eq!(pixel(1, 8), 0);
+    assert_eq!(pixel(14, 8), 255);
+    assert!(pixel(7, 8) > 0 && pixel(7, 8) < pixel(8, 8));
+}
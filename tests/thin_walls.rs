@@ -0,0 +1,215 @@
+use flow2d_rs::cell::{BoundaryConditionCell, Cell, CellType};
+use flow2d_rs::presets::{self, SimulationPreset};
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::space_domain::{SpaceDomain, FACE_NEIGHBORS};
+
+const SIZE: [usize; 2] = [33, 16];
+const WALL_X: usize = 16;
+
+fn rest_wall() -> Cell {
+    Cell {
+        cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+            boundary_condition_velocity: [0.0, 0.0],
+        }),
+        ..Default::default()
+    }
+}
+
+// Lid driven cavity whose right part is split off by a wall at WALL_X, the lid only
+// moves above the left part
+fn split_cavity(is_right_part_solid: bool) -> SimulationPreset {
+    let mut preset = presets::lid_driven_cavity_sized(SIZE);
+    let [nx, ny] = SIZE;
+    for x in WALL_X..nx - 1 {
+        preset.space_domain.set_cell(x, ny - 1, rest_wall());
+        for y in 1..ny - 1 {
+            if x == WALL_X || is_right_part_solid {
+                preset.space_domain.set_cell(x, y, rest_wall());
+            }
+        }
+    }
+    preset
+}
+
+#[test]
+fn a_wall_one_cell_thick_separates_the_flow_on_both_sides() {
+    let mut simulation = Simulation::from_preset(split_cavity(false));
+    let mut reference = Simulation::from_preset(split_cavity(true));
+    for _ in 0..100 {
+        simulation.iterate_one_timestep();
+        reference.iterate_one_timestep();
+    }
+
+    let [nx, ny] = SIZE;
+    for y in 1..ny - 1 {
+        // Nothing drives the right part
+        for x in WALL_X + 1..nx - 1 {
+            let speed = simulation.get_centered_velocity(x, y);
+            assert!(
+                speed[0].abs() < 1e-6 && speed[1].abs() < 1e-6,
+                "({x}, {y}): {speed:?}"
+            );
+        }
+        // The left part doesn't notice what is behind the wall
+        for x in 1..WALL_X {
+            let velocity = simulation.get_cell(x, y).velocity;
+            let expected = reference.get_cell(x, y).velocity;
+            assert!(
+                (velocity[0] - expected[0]).abs() < 1e-5
+                    && (velocity[1] - expected[1]).abs() < 1e-5,
+                "({x}, {y}): {velocity:?} != {expected:?}"
+            );
+        }
+    }
+}
+
+// Channel without the cylinder
+fn channel(nx: usize) -> SimulationPreset {
+    let mut preset = presets::cylinder_cross_flow_sized([nx, 21]);
+    let cylinder = preset.obstacles.remove(0);
+    for &(x, y) in cylinder.cells() {
+        preset.space_domain.set_obstacle(x, y, false);
+    }
+    preset
+}
+
+#[test]
+fn an_l_shaped_wall_one_cell_thick_blocks_flow_and_keeps_it_divergence_free() {
+    let mut preset = channel(60);
+    // Vertical leg up from the bottom wall, foot pointing downstream
+    for y in 1..12 {
+        preset.space_domain.set_obstacle(20, y, true);
+    }
+    for x in 21..30 {
+        preset.space_domain.set_obstacle(x, 11, true);
+    }
+    let mut simulation = Simulation::from_preset(preset);
+    for _ in 0..100 {
+        simulation.iterate_one_timestep();
+    }
+
+    let [nx, ny] = simulation.space_size();
+    let [dx, dy] = simulation.delta_space();
+    let is_fluid =
+        |x: usize, y: usize| matches!(simulation.get_cell(x, y).cell_type, CellType::FluidCell);
+    let inflow: f32 = (1..ny - 1)
+        .map(|y| simulation.get_cell(0, y).velocity[0] * dy)
+        .sum();
+    for x in 1..nx - 2 {
+        for y in 1..ny - 1 {
+            if !is_fluid(x, y) {
+                continue;
+            }
+            let velocity = simulation.get_cell(x, y).velocity;
+            let left = simulation.get_cell(x - 1, y).velocity;
+            let bottom = simulation.get_cell(x, y - 1).velocity;
+            let divergence = (velocity[0] - left[0]) / dx + (velocity[1] - bottom[1]) / dy;
+            assert!(divergence.abs() < 1e-2, "({x}, {y}): {divergence}");
+            // Nothing flows into the walls
+            if !is_fluid(x + 1, y) {
+                assert_eq!(velocity[0], 0.0, "({x}, {y})");
+            }
+            if !is_fluid(x, y + 1) {
+                assert_eq!(velocity[1], 0.0, "({x}, {y})");
+            }
+        }
+        // The trapped fluid under the foot doesn't leak the flow rate
+        // Faces inside walls hold ghost values
+        let flow_rate: f32 = (1..ny - 1)
+            .filter(|&y| is_fluid(x, y) || is_fluid(x + 1, y))
+            .map(|y| simulation.get_cell(x, y).velocity[0] * dy)
+            .sum();
+        assert!(
+            (flow_rate - inflow).abs() < 1e-2 * inflow,
+            "column {x}: {flow_rate} != {inflow}"
+        );
+    }
+}
+
+const BOX_SIZE: usize = 9;
+const STUB_HEIGHT: usize = 6;
+
+// Quarter turn counterclockwise in a square domain
+fn turned([x, y]: [usize; 2]) -> [usize; 2] {
+    [BOX_SIZE - 1 - y, x]
+}
+
+// Resting box with a wall one cell thick standing on its bottom and arbitrary
+// velocities everywhere, turned by quarter turns
+fn stub_box(quarter_turns: usize) -> SpaceDomain {
+    let n = BOX_SIZE;
+    let mut cells = vec![vec![Cell::default(); n]; n];
+    for (x, column) in cells.iter_mut().enumerate() {
+        for (y, cell) in column.iter_mut().enumerate() {
+            let is_wall = x == 0 || y == 0 || x == n - 1 || y == n - 1;
+            if is_wall || (x == n / 2 && y < STUB_HEIGHT) {
+                *cell = rest_wall();
+            }
+            let [x, y] = [x as f32, y as f32];
+            cell.velocity = [(1.3 * x + 0.7 * y).sin(), (0.4 * x - 1.1 * y).cos()];
+        }
+    }
+    for _ in 0..quarter_turns {
+        let mut turned_cells = cells.clone();
+        for (x, column) in cells.iter().enumerate() {
+            for (y, cell) in column.iter().enumerate() {
+                let [tx, ty] = turned([x, y]);
+                turned_cells[tx][ty].cell_type = cell.cell_type;
+                // The right face turns into the top face and the top face into the
+                // left one, the outermost top face has no counterpart
+                turned_cells[tx][ty].velocity[1] = cell.velocity[0];
+                if tx > 0 {
+                    turned_cells[tx - 1][ty].velocity[0] = -cell.velocity[1];
+                }
+            }
+        }
+        cells = turned_cells;
+    }
+    SpaceDomain::new(cells, [0.1, 0.1], 0.9)
+}
+
+// Velocity on the face of cell towards offset, positive along the axis of the face
+fn face_velocity(space_domain: &SpaceDomain, [x, y]: [usize; 2], offset: [isize; 2]) -> f32 {
+    match offset {
+        [-1, 0] => space_domain.velocity(x - 1, y)[0],
+        [1, 0] => space_domain.velocity(x, y)[0],
+        [0, -1] => space_domain.velocity(x, y - 1)[1],
+        _ => space_domain.velocity(x, y)[1],
+    }
+}
+
+#[test]
+fn ghost_velocities_of_a_wall_one_cell_thick_turn_with_the_wall() {
+    let mut reference = stub_box(0);
+    reference.update_boundary_velocities();
+    for quarter_turns in 1..4 {
+        let mut space_domain = stub_box(quarter_turns);
+        space_domain.update_boundary_velocities();
+        for y in 1..STUB_HEIGHT {
+            let mut cell = [BOX_SIZE / 2, y];
+            for _ in 0..quarter_turns {
+                cell = turned(cell);
+            }
+            // The face on the box wall is between two walls
+            for offset in FACE_NEIGHBORS
+                .into_iter()
+                .filter(|&offset| y > 1 || offset != [0, -1])
+            {
+                // A quarter turn carries u onto v and v onto -u
+                let mut turned_offset = offset;
+                let mut expected = face_velocity(&reference, [BOX_SIZE / 2, y], offset);
+                for _ in 0..quarter_turns {
+                    if turned_offset[1] != 0 {
+                        expected = -expected;
+                    }
+                    turned_offset = [-turned_offset[1], turned_offset[0]];
+                }
+                let velocity = face_velocity(&space_domain, cell, turned_offset);
+                assert!(
+                    (velocity - expected).abs() < 1e-6,
+                    "{quarter_turns} quarter turns, face {offset:?} at height {y}: {velocity} != {expected}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use flow2d_rs::observer::StepEvent;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::{AdaptiveTolerance, Simulation, ToleranceSchedule};
+use flow2d_rs::solver::FluidSolver;
+
+use std::sync::{Arc, Mutex};
+
+// Sweeps over the run and the final residual
+fn run(tolerance_schedule: ToleranceSchedule) -> (Simulation, usize, f32) {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow());
+    simulation.set_tolerance_schedule(tolerance_schedule);
+    assert_eq!(simulation.tolerance_schedule(), tolerance_schedule);
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let log = events.clone();
+    simulation.add_observer(move |event: &StepEvent| log.lock().unwrap().push(*event));
+    for _ in 0..100 {
+        simulation.iterate_one_timestep();
+    }
+    let events = events.lock().unwrap();
+    let sweeps = events.iter().map(|event| event.pressure_iterations).sum();
+    (simulation, sweeps, events.last().unwrap().pressure_residual)
+}
+
+#[test]
+fn adaptive_tolerance_trades_sweeps_for_accuracy() {
+    let (fixed, fixed_sweeps, fixed_residual) = run(ToleranceSchedule::Fixed);
+
+    // Loose in the start up transient
+    let (adaptive, adaptive_sweeps, _) =
+        run(ToleranceSchedule::Adaptive(AdaptiveTolerance::default()));
+    assert!(
+        adaptive_sweeps < fixed_sweeps * 3 / 4,
+        "{adaptive_sweeps} >= {fixed_sweeps}"
+    );
+    let [a, b] = [fixed.fields(), adaptive.fields()];
+    for (velocities, expected) in [(&b.u, &a.u), (&b.v, &a.v)] {
+        for (value, expected) in velocities.iter().zip(expected.iter()) {
+            assert!((value - expected).abs() < 1e-3, "{value} != {expected}");
+        }
+    }
+
+    // Tighter than the fixed tolerance once the flow settles
+    let tight = AdaptiveTolerance {
+        reduction: 0.01,
+        tight: 1e-5,
+        ..AdaptiveTolerance::default()
+    };
+    let (_, _, tight_residual) = run(ToleranceSchedule::Adaptive(tight));
+    assert!(
+        tight_residual < fixed_residual / 2.0,
+        "{tight_residual} >= {fixed_residual}"
+    );
+}
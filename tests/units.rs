@@ -0,0 +1,46 @@
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::units::{DeltaTimeLimits, PhysicalFlow};
+
+fn close(value: f32, expected: f32) -> bool {
+    (value - expected).abs() <= 1e-4 * expected.abs()
+}
+
+#[test]
+fn physical_flow_configures_the_nondimensional_simulation() {
+    // A 10 cm cavity of water with a 1 cm/s lid under gravity
+    let flow = PhysicalFlow::water(0.1, 0.01).with_gravity([0.0, -9.81]);
+    assert!(close(flow.reynolds(), 0.01 * 0.1 * 998.2 / 1.002e-3));
+    assert!(close(
+        PhysicalFlow::air(1.0, 1.0).reynolds(),
+        1.204 / 1.825e-5
+    ));
+
+    let scales = flow.scales();
+    assert!(close(scales.time, 10.0));
+    assert!(close(scales.pressure, 998.2 * 1e-4));
+    assert!(close(scales.to_seconds(2.5), 25.0));
+    assert!(close(scales.from_pascals(scales.to_pascals(3.0)), 3.0));
+    assert!(close(scales.from_meters_per_second(0.02), 2.0));
+
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([34, 34]));
+    let report = flow.configure(&mut simulation);
+    assert_eq!(simulation.reynolds(), flow.reynolds());
+    assert_eq!(simulation.acceleration(), [0.0, -9.81 * 0.1 / 1e-4]);
+    assert_eq!(report.acceleration, simulation.acceleration());
+
+    // The lid moves at the characteristic velocity
+    let [dx, dy] = simulation.delta_space();
+    let limits = report.delta_time_limits;
+    assert!(close(limits.convective, dx.min(dy)));
+    assert!(close(
+        limits.diffusive,
+        flow.reynolds() / 2.0 / (1.0 / dx.powi(2) + 1.0 / dy.powi(2))
+    ));
+    assert_eq!(limits.stable(), limits.convective);
+    assert!(report.to_string().starts_with("reynolds number: "));
+
+    // Viscous flows are limited by diffusion
+    let slow = DeltaTimeLimits::new(1.0, [0.1, 0.1], [1.0, 1.0]);
+    assert!(close(slow.stable(), 0.0025));
+}
@@ -0,0 +1,63 @@
+use flow2d_rs::cell::{BoundaryConditionCell, Cell, CellType};
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::validation::DomainIssue;
+
+fn wall() -> Cell {
+    Cell {
+        cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+            boundary_condition_velocity: [0.0, 0.0],
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn presets_are_valid() {
+    assert!(presets::lid_driven_cavity().validate().is_empty());
+    assert!(presets::backward_facing_step().validate().is_empty());
+    assert!(presets::cylinder_cross_flow().validate().is_empty());
+    assert!(presets::kelvin_helmholtz().validate().is_empty());
+}
+
+#[test]
+fn issues_are_found_and_repaired() {
+    let mut preset = presets::lid_driven_cavity_sized([16, 16]);
+    let space_domain = &mut preset.space_domain;
+    // Fluid on the edge
+    space_domain.set_cell(0, 5, Cell::default());
+    // A lone wall cell is fine
+    space_domain.set_cell(4, 4, wall());
+    // A wall from (10, 1) up to (10, 3) and on to (14, 3) cuts off the bottom right
+    // corner
+    for y in 1..=3 {
+        space_domain.set_cell(10, y, wall());
+    }
+    for x in 11..=14 {
+        space_domain.set_cell(x, 3, wall());
+    }
+
+    let issues = preset.validate();
+    assert!(issues.contains(&DomainIssue::FluidOnEdge { cell: (0, 5) }));
+    assert!(!issues
+        .iter()
+        .any(|issue| format!("{issue}").contains("(4, 4)")));
+    assert!(issues.iter().any(|issue| matches!(
+        issue,
+        DomainIssue::IsolatedPocket { cells } if cells.len() == 4 * 2
+    )));
+    assert!(Simulation::try_from_preset(presets::lid_driven_cavity_sized([16, 16])).is_ok());
+
+    let (preset, fixed) = preset.repaired();
+    assert!(!fixed.is_empty());
+    assert!(preset.validate().is_empty());
+    let simulation = Simulation::try_from_preset(preset).ok().unwrap();
+    assert!(!matches!(
+        simulation.get_cell(0, 5).cell_type,
+        CellType::FluidCell
+    ));
+    assert!(!matches!(
+        simulation.get_cell(12, 1).cell_type,
+        CellType::FluidCell
+    ));
+}
@@ -0,0 +1,65 @@
+use flow2d_rs::cell::{BoundaryConditionCell, CellType};
+use flow2d_rs::control::Probe;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+use flow2d_rs::space_domain::VelocityError;
+
+// Cavity of 1 x 1 meters with 20 x 20 cells and a linear velocity field
+fn linear_flow() -> Simulation {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([20, 20]));
+    let mut checkpoint = simulation.checkpoint();
+    let h = 1.0 / 20.0;
+    for x in 0..20 {
+        for y in 0..20 {
+            let cell = &mut checkpoint.cells[x * 20 + y];
+            // u on the right face, v on the top face
+            let [ux, uy] = [(x as f32 + 1.0) * h, (y as f32 + 0.5) * h];
+            let [vx, vy] = [(x as f32 + 0.5) * h, (y as f32 + 1.0) * h];
+            cell.velocity = [2.0 * ux + 3.0 * uy, vx - 2.0 * vy];
+        }
+    }
+    simulation.restore(&checkpoint);
+    simulation
+}
+
+#[test]
+fn interpolation_follows_the_staggered_faces() {
+    let simulation = linear_flow();
+    for position in [[0.37, 0.52], [0.5, 0.5], [0.061, 0.93], [0.9249, 0.1]] {
+        let [u, v] = simulation.velocity_at(position).unwrap();
+        let [x, y] = position;
+        assert!((u - (2.0 * x + 3.0 * y)).abs() < 1e-5, "{position:?}");
+        assert!((v - (x - 2.0 * y)).abs() < 1e-5, "{position:?}");
+    }
+
+    let probe = Probe::VelocityAt {
+        position: [0.37, 0.52],
+        component: 1,
+    };
+    assert!((probe.read(&simulation) - (0.37 - 1.04)).abs() < 1e-5);
+}
+
+#[test]
+fn points_outside_the_fluid_are_errors() {
+    let simulation = linear_flow();
+    assert_eq!(
+        simulation.velocity_at([0.5, 0.99]),
+        Err(VelocityError::NotFluid {
+            cell: (10, 19),
+            cell_type: CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                boundary_condition_velocity: [1.0, 0.0],
+            }),
+        })
+    );
+    // The top edge belongs to the last row
+    assert!(matches!(
+        simulation.velocity_at([0.5, 1.0]),
+        Err(VelocityError::NotFluid { cell: (10, 19), .. })
+    ));
+    for position in [[-0.01, 0.5], [0.5, 1.01], [f32::NAN, 0.5]] {
+        let error = simulation.velocity_at(position).unwrap_err();
+        assert!(matches!(error, VelocityError::OutsideDomain(_)));
+        assert!(error.to_string().contains("outside the domain"));
+    }
+}
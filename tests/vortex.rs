@@ -0,0 +1,73 @@
+use flow2d_rs::colormap::Colormap;
+use flow2d_rs::frame_renderer::{FrameRenderer, RenderField};
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+use flow2d_rs::vortex::VortexField;
+
+// Cavity with a linear velocity field, velocity(x, y) in meters from the center
+fn linear_flow(velocity: impl Fn(f32, f32) -> [f32; 2]) -> Simulation {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([20, 20]));
+    let mut checkpoint = simulation.checkpoint();
+    let [dx, dy] = simulation.delta_space();
+    let center = [10.0 * dx, 10.0 * dy];
+    for x in 0..20 {
+        for y in 0..20 {
+            // Right and top faces of the cell
+            let u = velocity(
+                (x + 1) as f32 * dx - center[0],
+                (y as f32 + 0.5) * dy - center[1],
+            );
+            let v = velocity(
+                (x as f32 + 0.5) * dx - center[0],
+                (y + 1) as f32 * dy - center[1],
+            );
+            checkpoint.cells[x * 20 + y].velocity = [u[0], v[1]];
+        }
+    }
+    simulation.restore(&checkpoint);
+    simulation
+}
+
+#[test]
+fn rotation_and_strain_have_opposite_signs() {
+    let rotation = VortexField::new(&linear_flow(|x, y| [-2.0 * y, 2.0 * x]));
+    let strain = VortexField::new(&linear_flow(|x, y| [3.0 * x, -3.0 * y]));
+    let shear = VortexField::new(&linear_flow(|_, y| [4.0 * y, 0.0]));
+    for (field, q) in [(&rotation, 4.0), (&strain, -9.0), (&shear, 0.0)] {
+        let [min, max] = field.q().range().unwrap();
+        assert!(
+            (min - q).abs() < 1e-3 && (max - q).abs() < 1e-3,
+            "{min} {max} {q}"
+        );
+        // Divergence free, so lambda2 is -Q
+        let [min, max] = field.lambda2().range().unwrap();
+        assert!((min + q).abs() < 1e-3 && (max + q).abs() < 1e-3);
+    }
+    assert_eq!(rotation.q[0], 0.0);
+}
+
+#[test]
+fn cavity_vortex_core_is_outlined() {
+    let mut simulation = Simulation::from_preset(presets::lid_driven_cavity_sized([20, 20]));
+    for _ in 0..100 {
+        simulation.iterate_one_timestep();
+    }
+    let vortex = VortexField::new(&simulation);
+    let ([x, y], q) = vortex.q().max_cell().unwrap();
+    assert!(q > 0.0);
+    assert!(vortex.lambda2().get(x, y) < 0.0);
+
+    let core = vortex.q_contour(0.25 * q);
+    assert!(!core.is_empty());
+    // Between cell centers, walls hold zero
+    let [dx, dy] = simulation.delta_space();
+    assert!(core.iter().flatten().all(|point| {
+        (0.5 * dx..=19.5 * dx).contains(&point[0]) && (0.5 * dy..=19.5 * dy).contains(&point[1])
+    }));
+    assert!(!vortex.lambda2_contour(-0.25 * q).is_empty());
+
+    let image =
+        FrameRenderer::new(RenderField::QCriterion, Colormap::coolwarm(), 1).render(&simulation);
+    assert_eq!(image.size, [20, 20]);
+}
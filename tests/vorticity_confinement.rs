@@ -0,0 +1,89 @@
+use flow2d_rs::cell::{BoundaryConditionCell, CellType};
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+
+const N: usize = 32;
+
+// Closed box at rest holding a Gaussian vortex, the face velocities come from a
+// stream function on the corners so the start is divergence free
+fn decaying_vortex() -> Simulation {
+    let mut preset = presets::lid_driven_cavity_sized([N, N]);
+    for x in 1..N - 1 {
+        preset.space_domain.set_cell_type(
+            x,
+            N - 1,
+            CellType::BoundaryConditionCell(BoundaryConditionCell::NoSlipCell {
+                boundary_condition_velocity: [0.0, 0.0],
+            }),
+        );
+    }
+    preset.reynolds = 200.0;
+    let mut simulation = Simulation::from_preset(preset);
+
+    let [dx, dy] = simulation.delta_space();
+    let psi = |x: f32, y: f32| 0.05 * (-((x - 0.5).powi(2) + (y - 0.5).powi(2)) / 0.01).exp();
+    let mut checkpoint = simulation.checkpoint();
+    for x in 1..N - 1 {
+        for y in 1..N - 1 {
+            // Corners at the top right and the ones below and to the left of it
+            let corner = |x: usize, y: usize| psi((x + 1) as f32 * dx, (y + 1) as f32 * dy);
+            let u = (corner(x, y) - corner(x, y - 1)) / dy;
+            let v = -(corner(x, y) - corner(x - 1, y)) / dx;
+            let cell = &mut checkpoint.cells[x * N + y];
+            cell.velocity = [
+                if x < N - 2 { u } else { 0.0 },
+                if y < N - 2 { v } else { 0.0 },
+            ];
+        }
+    }
+    simulation.restore(&checkpoint);
+    simulation
+}
+
+// Largest corner vorticity of the fluid
+fn peak_vorticity(simulation: &Simulation) -> f32 {
+    let [dx, dy] = simulation.delta_space();
+    let velocity = |x: usize, y: usize| simulation.get_cell(x, y).velocity;
+    (1..N - 2)
+        .flat_map(|x| (1..N - 2).map(move |y| (x, y)))
+        .map(|(x, y)| {
+            ((velocity(x + 1, y)[1] - velocity(x, y)[1]) / dx
+                - (velocity(x, y + 1)[0] - velocity(x, y)[0]) / dy)
+                .abs()
+        })
+        .fold(0.0, f32::max)
+}
+
+fn run(epsilon: Option<f32>) -> Simulation {
+    let mut simulation = decaying_vortex();
+    simulation.set_vorticity_confinement(epsilon);
+    for _ in 0..100 {
+        simulation.iterate_one_timestep();
+    }
+    simulation
+}
+
+#[test]
+fn confinement_slows_the_decay_of_a_vortex() {
+    let start = peak_vorticity(&decaying_vortex());
+    let free = peak_vorticity(&run(None));
+    let confined = peak_vorticity(&run(Some(2.0)));
+    assert!(free < 0.95 * start, "{free} vs {start}");
+    assert!(confined > free, "{confined} vs {free}");
+}
+
+#[test]
+fn zero_strength_changes_nothing() {
+    let free = run(None);
+    let zero = run(Some(0.0));
+    let velocities = |simulation: &Simulation| {
+        simulation
+            .cells()
+            .iter()
+            .map(|cell| cell.velocity)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(velocities(&zero), velocities(&free));
+    assert_eq!(peak_vorticity(&zero), peak_vorticity(&free));
+}
@@ -0,0 +1,64 @@
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::wall_shear;
+
+fn cylinder() -> Simulation {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow_sized([55, 21]));
+    for _ in 0..20 {
+        simulation.iterate_one_timestep();
+    }
+    simulation
+}
+
+#[test]
+fn channel_walls_resist_the_flow() {
+    let simulation = cylinder();
+    let faces = simulation.wall_shear();
+    let reynolds = simulation.reynolds();
+    let dy = simulation.delta_space()[1];
+
+    // Bottom wall in x order, stress along +x, one sided through two cell centers
+    let bottom: Vec<_> = faces
+        .iter()
+        .filter(|face| face.wall[1] == 0 && face.normal == [0.0, 1.0])
+        .collect();
+    assert_eq!(bottom.len(), 53);
+    let face = bottom[5];
+    assert_eq!(face.tangent(), [1.0, 0.0]);
+    assert_eq!(face.fluid, [face.wall[0], 1]);
+    let u = |y: usize| simulation.get_centered_velocity(face.wall[0], y)[0];
+    let expected = (9.0 * u(1) - u(2)) / (3.0 * dy) / reynolds;
+    assert!((face.shear_stress - expected).abs() < 1e-5 * expected.abs());
+    assert!(face.shear_stress > 0.0);
+    let y_plus = face.shear_stress.sqrt() * dy / 2.0 * reynolds;
+    assert!((face.y_plus - y_plus).abs() < 1e-4 * y_plus);
+
+    // The top wall sees the same flow with the tangent along -x
+    let top = faces
+        .iter()
+        .find(|face| face.wall == [bottom[5].wall[0], 20])
+        .unwrap();
+    assert_eq!(top.tangent(), [-1.0, 0.0]);
+    assert!(top.shear_stress < 0.0);
+}
+
+#[test]
+fn obstacle_profiles_go_around_the_body() {
+    let simulation = cylinder();
+    assert_eq!(simulation.obstacle_wall_shear("missing"), None);
+    let faces = simulation.obstacle_wall_shear("cylinder").unwrap();
+    assert!(faces.len() > 8);
+    assert!(faces
+        .iter()
+        .all(|face| face.y_plus >= 0.0 && face.friction_velocity >= 0.0));
+    // Counterclockwise from upstream, so the first face looks upstream
+    assert!(faces[0].position[0] < faces[faces.len() / 2].position[0]);
+
+    let mut csv = Vec::new();
+    wall_shear::write_csv(&faces, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "s,x,y,normal_x,normal_y,shear_stress,y_plus");
+    assert_eq!(lines.len(), faces.len() + 1);
+    assert!(lines[1].starts_with("0,"));
+}
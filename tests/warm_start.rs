@@ -0,0 +1,40 @@
+use flow2d_rs::observer::StepEvent;
+use flow2d_rs::presets;
+use flow2d_rs::simulation::Simulation;
+use flow2d_rs::solver::FluidSolver;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+fn run(warm_start: bool) -> (Simulation, usize) {
+    let mut simulation = Simulation::from_preset(presets::cylinder_cross_flow());
+    simulation.set_warm_start(warm_start);
+    assert_eq!(simulation.warm_start(), warm_start);
+    let sweeps = Arc::new(AtomicUsize::new(0));
+    let counter = sweeps.clone();
+    simulation.add_observer(move |event: &StepEvent| {
+        counter.fetch_add(event.pressure_iterations, Ordering::Relaxed);
+    });
+    for _ in 0..200 {
+        simulation.iterate_one_timestep();
+    }
+    let sweeps = sweeps.load(Ordering::Relaxed);
+    (simulation, sweeps)
+}
+
+#[test]
+fn warm_start_saves_sweeps_without_changing_the_flow() {
+    let (cold, cold_sweeps) = run(false);
+    let (warm, warm_sweeps) = run(true);
+    assert!(
+        warm_sweeps < cold_sweeps * 9 / 10,
+        "{warm_sweeps} >= {cold_sweeps}"
+    );
+
+    let [a, b] = [cold.fields(), warm.fields()];
+    for (velocities, expected) in [(&b.u, &a.u), (&b.v, &a.v)] {
+        for (value, expected) in velocities.iter().zip(expected.iter()) {
+            assert!((value - expected).abs() < 1e-3, "{value} != {expected}");
+        }
+    }
+}